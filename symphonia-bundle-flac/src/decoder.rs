@@ -11,17 +11,18 @@ use std::num::Wrapping;
 
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef};
 use symphonia_core::audio::{Signal, SignalSpec};
+use symphonia_core::checksum::Crc16Ansi;
 use symphonia_core::codecs::{
     CodecDescriptor, CodecParameters, VerificationCheck, CODEC_TYPE_FLAC,
 };
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::formats::Packet;
-use symphonia_core::io::{BitReaderLtr, BufReader, ReadBitsLtr};
+use symphonia_core::io::{BitReaderLtr, BufReader, Monitor, ReadBitsLtr, ReadBytes};
 use symphonia_core::support_codec;
 use symphonia_core::units::TimeBase;
 use symphonia_core::util::bits::sign_extend_leq32_to_i32;
-use symphonia_utils_xiph::flac::metadata::StreamInfo;
+use symphonia_utils_xiph::flac::metadata::{flac_channels_to_channels, StreamInfo};
 
 use log::{debug, log_enabled, warn};
 
@@ -86,6 +87,8 @@ pub struct FlacDecoder {
     is_validating: bool,
     validator: Validator,
     buf: AudioBuffer<i32>,
+    /// The number of frames that failed the frame header CRC8 or frame CRC16 check.
+    n_corrupt_frames: u64,
 }
 
 impl FlacDecoder {
@@ -95,7 +98,22 @@ impl FlacDecoder {
         // Synchronize to a frame and get the synchronization code.
         let sync = sync_frame(&mut reader)?;
 
-        let header = read_frame_header(&mut reader, sync)?;
+        // The frame starts at the synchronization code.
+        let frame = &packet.buf()[reader.pos() as usize - 2..];
+
+        let (header, is_crc_ok) = read_frame_header_unchecked(&mut reader, sync)?;
+
+        if !is_crc_ok {
+            self.n_corrupt_frames += 1;
+            return decode_error("flac: computed frame header CRC does not match expected CRC");
+        }
+
+        // If the demuxer does not guarantee the integrity of the packet, verify the CRC16 of the
+        // entire frame.
+        if !self.params.packet_data_integrity && cfg!(not(fuzzing)) && !is_frame_crc_ok(frame) {
+            self.n_corrupt_frames += 1;
+            return decode_error("flac: computed frame CRC does not match expected CRC");
+        }
 
         // Use the bits per sample and sample rate as stated in the frame header, falling back to
         // the stream information if provided. If neither are available, return an error.
@@ -109,6 +127,36 @@ impl FlacDecoder {
             return decode_error("flac: bits per sample not provided");
         };
 
+        // Use the sample rate as stated in the frame header, falling back to the stream
+        // information if provided.
+        let sample_rate = match header.sample_rate.or(self.params.sample_rate) {
+            Some(sample_rate) => sample_rate,
+            None => return decode_error("flac: sample rate not provided"),
+        };
+
+        let n_channels = match header.channel_assignment {
+            ChannelAssignment::Independant(channels) => channels,
+            _ => 2,
+        };
+
+        // Use the channel layout from the stream information if it has the same number of
+        // channels as the frame. Otherwise, use the default layout for the number of channels.
+        let channels = match self.params.channels {
+            Some(channels) if channels.count() == n_channels as usize => channels,
+            _ => flac_channels_to_channels(n_channels),
+        };
+
+        // The audio buffer is sized using the maximum block length from the stream information.
+        // If the stream information was not provided, or the frame does not fit, grow the audio
+        // buffer to fit the frame.
+        let spec = SignalSpec::new(sample_rate, channels);
+        let n_frames = usize::from(header.block_num_samples);
+
+        if self.buf.capacity() < n_frames || *self.buf.spec() != spec {
+            let duration = cmp::max(self.buf.capacity(), n_frames) as u64;
+            self.buf = AudioBuffer::new(duration, spec);
+        }
+
         // trace!("frame: [{:?}] strategy={:?}, n_samples={}, bps={}, channels={:?}",
         //     header.block_sequence,
         //     header.blocking_strategy,
@@ -176,6 +224,19 @@ impl FlacDecoder {
 
         Ok(())
     }
+
+    /// Decodes a packet, and returns the decoded audio buffer instead of a reference to it.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn decode_owned(&mut self, packet: &Packet) -> Result<AudioBuffer<i32>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            // The audio buffer is re-allocated, if required, when the next packet is decoded.
+            Ok(std::mem::replace(&mut self.buf, AudioBuffer::unused()))
+        }
+    }
 }
 
 impl Decoder for FlacDecoder {
@@ -185,18 +246,28 @@ impl Decoder for FlacDecoder {
             return unsupported_error("flac: invalid codec type");
         }
 
-        // Obtain the extra data.
+        // Clone the codec parameters so that the parameters can be supplemented and/or amended.
+        let mut params = params.clone();
+
+        // The extra data contains the stream information block. If it is not provided, such as
+        // when joining a stream part-way through, the stream parameters are obtained from the
+        // codec parameters, if provided, or each frame header.
         let extra_data = match params.extra_data.as_ref() {
             Some(buf) => buf,
-            _ => return unsupported_error("flac: missing extra data"),
+            _ => {
+                return Ok(FlacDecoder {
+                    params,
+                    is_validating: options.verify,
+                    validator: Default::default(),
+                    buf: AudioBuffer::unused(),
+                    n_corrupt_frames: 0,
+                })
+            }
         };
 
         // Read the stream information block.
         let info = StreamInfo::read(&mut BufReader::new(extra_data))?;
 
-        // Clone the codec parameters so that the parameters can be supplemented and/or amended.
-        let mut params = params.clone();
-
         // Amend the provided codec parameters with information from the stream information block.
         params
             .with_sample_rate(info.sample_rate)
@@ -216,16 +287,12 @@ impl Decoder for FlacDecoder {
         let spec = SignalSpec::new(info.sample_rate, info.channels);
         let buf = AudioBuffer::new(u64::from(info.block_len_max), spec);
 
-        // TODO: Verify packet integrity if the demuxer is not.
-        // if !params.packet_data_integrity {
-        //     return unsupported_error("flac: packet integrity is required");
-        // }
-
         Ok(FlacDecoder {
             params,
             is_validating: options.verify,
             validator: Default::default(),
             buf,
+            n_corrupt_frames: 0,
         })
     }
 
@@ -241,6 +308,26 @@ impl Decoder for FlacDecoder {
         &self.params
     }
 
+    fn output_spec(&self) -> Option<SignalSpec> {
+        // If the stream information block was not provided, the audio buffer is only allocated
+        // once the first frame is decoded.
+        if self.buf.capacity() > 0 {
+            Some(*self.buf.spec())
+        }
+        else {
+            None
+        }
+    }
+
+    fn max_frames_per_packet(&self) -> Option<u64> {
+        if self.buf.capacity() > 0 {
+            Some(self.buf.capacity() as u64)
+        }
+        else {
+            None
+        }
+    }
+
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
         if let Err(e) = self.decode_inner(packet) {
             self.buf.clear();
@@ -253,6 +340,7 @@ impl Decoder for FlacDecoder {
 
     fn finalize(&mut self) -> FinalizeResult {
         let mut result: FinalizeResult = Default::default();
+        result.with_n_corrupt_packets(self.n_corrupt_frames);
 
         // If verifying...
         if self.is_validating {
@@ -290,6 +378,20 @@ impl Decoder for FlacDecoder {
     }
 }
 
+/// Verifies the CRC16 footer of a complete frame, starting from the synchronization code.
+fn is_frame_crc_ok(frame: &[u8]) -> bool {
+    if frame.len() < 2 {
+        return false;
+    }
+
+    let (data, footer) = frame.split_at(frame.len() - 2);
+
+    let mut crc16 = Crc16Ansi::new(0);
+    crc16.process_buf_bytes(data);
+
+    crc16.crc() == u16::from_be_bytes([footer[0], footer[1]])
+}
+
 // Subframe business
 
 #[derive(Debug)]
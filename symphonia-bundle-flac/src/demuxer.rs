@@ -10,23 +10,39 @@ use std::io::{Seek, SeekFrom};
 use symphonia_core::support_format;
 
 use symphonia_core::codecs::{CodecParameters, VerificationCheck, CODEC_TYPE_FLAC};
-use symphonia_core::errors::{decode_error, seek_error, unsupported_error, Result, SeekErrorKind};
+use symphonia_core::errors::{decode_error, reset_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::formats::util::{SeekIndex, SeekSearchResult};
 use symphonia_core::io::*;
 use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_metadata::replaygain::ReplayGain;
 
 use symphonia_utils_xiph::flac::metadata::*;
 
-use log::{debug, info};
+use log::{debug, info, warn};
 
-use super::parser::PacketParser;
+use super::parser::{PacketParser, FLAC_STREAM_MARKER};
 
-/// The FLAC start of stream marker: "fLaC" in ASCII.
-const FLAC_STREAM_MARKER: [u8; 4] = *b"fLaC";
+/// The maximum total length of the application blocks of a stream that are stored as vendor data.
+const MAX_APPLICATION_DATA_LEN: usize = 16 * 1024 * 1024;
 
 /// Free Lossless Audio Codec (FLAC) native frame reader.
+///
+/// If lenient parsing is enabled, additional stream information and seek table metadata blocks are
+/// ignored instead of returning an error.
+///
+/// All application blocks are stored as vendor data, identified by their application ID, in the
+/// metadata. Application blocks exceeding a limit of 16 MiB in total are ignored.
+///
+/// If another FLAC stream is concatenated to the current stream, then `next_packet` will read the
+/// metadata blocks of the new stream and return `ResetRequired`. The track, cues, and seek index
+/// are replaced with those of the new stream, and a new revision of metadata is pushed.
+///
+/// The tracks of a cuesheet block, excluding the lead-out track, are exposed as cues starting at
+/// the sample offset of the track. ReplayGain information read from the `REPLAYGAIN_*` Vorbis
+/// comments is available from [`FlacReader::replay_gain`].
 pub struct FlacReader {
     reader: MediaSourceStream,
     metadata: MetadataLog,
@@ -35,30 +51,75 @@ pub struct FlacReader {
     index: Option<SeekIndex>,
     first_frame_offset: u64,
     parser: PacketParser,
+    enable_lenient: bool,
+    replay_gain: Option<ReplayGain>,
 }
 
 impl FlacReader {
+    /// Gets the ReplayGain information of the current stream, if any.
+    pub fn replay_gain(&self) -> Option<ReplayGain> {
+        self.replay_gain
+    }
+
     /// Reads all the metadata blocks, returning a fully populated `FlacReader`.
-    fn init_with_metadata(source: MediaSourceStream) -> Result<Self> {
+    fn init_with_metadata(source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        let mut flac = FlacReader {
+            reader: source,
+            metadata: Default::default(),
+            tracks: Vec::new(),
+            cues: Vec::new(),
+            index: None,
+            first_frame_offset: 0,
+            parser: Default::default(),
+            enable_lenient: options.enable_lenient,
+            replay_gain: None,
+        };
+
+        flac.read_metadata_blocks()?;
+
+        Ok(flac)
+    }
+
+    /// Reads all the metadata blocks of a stream. The stream marker must have already been read.
+    /// The tracks, cues, seek index, and ReplayGain information of the previous stream, if any, are
+    /// replaced.
+    fn read_metadata_blocks(&mut self) -> Result<()> {
         let mut metadata_builder = MetadataBuilder::new();
 
-        let mut reader = source;
+        let reader = &mut self.reader;
         let mut tracks = Vec::new();
         let mut cues = Vec::new();
         let mut index = None;
-        let mut parser = Default::default();
+        let mut application_data_len = 0;
+        let parser = &mut self.parser;
 
         loop {
-            let header = MetadataBlockHeader::read(&mut reader)?;
+            let header = MetadataBlockHeader::read(reader)?;
 
             // Create a scoped bytestream to error if the metadata block read functions exceed the
             // stated length of the block.
-            let mut block_stream = ScopedStream::new(&mut reader, u64::from(header.block_len));
+            let mut block_stream = ScopedStream::new(&mut *reader, u64::from(header.block_len));
 
             match header.block_type {
+                // Application blocks are stored as vendor data, up-to a limit on their total
+                // size. The block will be skipped below if the limit is exceeded.
                 MetadataBlockType::Application => {
-                    // TODO: Store vendor data.
-                    read_application_block(&mut block_stream, header.block_len)?;
+                    let len = header.block_len as usize;
+
+                    if application_data_len + len > MAX_APPLICATION_DATA_LEN {
+                        warn!("ignoring application block, application data limit exceeded");
+                    }
+                    else {
+                        let vendor_data =
+                            read_application_block(&mut block_stream, header.block_len)?;
+
+                        // Application blocks containing foreign RIFF or AIFF chunks may also
+                        // contain tags.
+                        read_foreign_metadata(&vendor_data, &mut metadata_builder);
+                        metadata_builder.add_vendor_data(vendor_data);
+
+                        application_data_len += len;
+                    }
                 }
                 // SeekTable blocks are parsed into a SeekIndex.
                 MetadataBlockType::SeekTable => {
@@ -70,6 +131,10 @@ impl FlacReader {
                         read_seek_table_block(&mut block_stream, header.block_len, &mut new_index)?;
                         index = Some(new_index);
                     }
+                    else if self.enable_lenient {
+                        // Keep the first SeekTable. The block will be skipped below.
+                        warn!("ignoring additional seek table block");
+                    }
                     else {
                         return decode_error("flac: found more than one seek table block");
                     }
@@ -88,7 +153,13 @@ impl FlacReader {
                 }
                 // StreamInfo blocks are parsed into Streams.
                 MetadataBlockType::StreamInfo => {
-                    read_stream_info_block(&mut block_stream, &mut tracks, &mut parser)?;
+                    if !tracks.is_empty() && self.enable_lenient {
+                        // Keep the first StreamInfo. The block will be skipped below.
+                        warn!("ignoring additional stream info block");
+                    }
+                    else {
+                        read_stream_info_block(&mut block_stream, &mut tracks, parser)?;
+                    }
                 }
                 // Padding blocks are skipped.
                 MetadataBlockType::Padding => {
@@ -117,18 +188,29 @@ impl FlacReader {
             }
         }
 
+        // Make sure that there is atleast one StreamInfo block.
+        if tracks.is_empty() {
+            return decode_error("flac: no stream info block");
+        }
+
         // Commit any read metadata to the metadata log.
-        let mut metadata = MetadataLog::default();
-        metadata.push(metadata_builder.metadata());
+        let metadata = metadata_builder.metadata();
+
+        self.replay_gain = ReplayGain::from_tags(metadata.tags());
+        self.metadata.push(metadata);
 
         // Synchronize the packet parser to the first audio frame.
-        let _ = parser.resync(&mut reader)?;
+        let _ = parser.resync(reader)?;
 
         // The first frame offset is the byte offset from the beginning of the stream after all the
         // metadata blocks have been read.
-        let first_frame_offset = reader.pos();
+        self.first_frame_offset = reader.pos();
 
-        Ok(FlacReader { reader, metadata, tracks, cues, index, first_frame_offset, parser })
+        self.tracks = tracks;
+        self.cues = cues;
+        self.index = index;
+
+        Ok(())
     }
 }
 
@@ -149,7 +231,7 @@ impl QueryDescriptor for FlacReader {
 }
 
 impl FormatReader for FlacReader {
-    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
         // Read the first 4 bytes of the stream. Ideally this will be the FLAC stream marker.
         let marker = source.read_quad_bytes()?;
 
@@ -161,18 +243,27 @@ impl FormatReader for FlacReader {
         // no technical need for this from the reader's point of view. Additionally, if the
         // reader is fed a stream mid-way there is no StreamInfo block. Therefore, just read
         // all metadata blocks and handle the StreamInfo block as it comes.
-        let flac = Self::init_with_metadata(source)?;
-
-        // Make sure that there is atleast one StreamInfo block.
-        if flac.tracks.is_empty() {
-            return decode_error("flac: no stream info block");
-        }
-
-        Ok(flac)
+        Self::init_with_metadata(source, options)
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
-        self.parser.parse(&mut self.reader)
+        match self.parser.parse(&mut self.reader)? {
+            Some(packet) => Ok(packet),
+            None => {
+                // The current stream ended, and a new stream was concatenated to it. Read the
+                // metadata blocks of the new stream. The parser guarantees the reader is
+                // positioned on the stream marker.
+                let marker = self.reader.read_quad_bytes()?;
+                debug_assert!(marker == FLAC_STREAM_MARKER);
+
+                info!("starting new stream at byte offset {}", self.reader.pos() - 4);
+
+                self.read_metadata_blocks()?;
+
+                // The track must be re-examined, and the decoder recreated.
+                reset_error()
+            }
+        }
     }
 
     fn metadata(&mut self) -> Metadata<'_> {
@@ -373,3 +464,417 @@ fn read_stream_info_block<B: ReadBytes + FiniteStream>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Signal};
+    use symphonia_core::checksum::{Crc16Ansi, Crc8Ccitt};
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_FLAC};
+    use symphonia_core::errors::Error;
+    use symphonia_core::formats::{FormatOptions, FormatReader, Packet};
+    use symphonia_core::io::{MediaSourceStream, Monitor};
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::FlacReader;
+    use crate::FlacDecoder;
+
+    /// Builds a mono 16-bit stream with the given number of stream information blocks, and the
+    /// given number of constant frames of 192 samples.
+    fn build_flac(sample_rate: u32, n_stream_info: usize, n_frames: u8, value: u16) -> Vec<u8> {
+        let mut buf = b"fLaC".to_vec();
+
+        let n_samples = 192 * u32::from(n_frames);
+
+        // Block length 192, unknown frame sizes, the sample rate, 1 channel, 16-bit, and the
+        // total number of samples.
+        let mut stream_info = vec![0x00, 0xc0, 0x00, 0xc0, 0, 0, 0, 0, 0, 0];
+        stream_info.extend_from_slice(&(sample_rate >> 4).to_be_bytes()[2..]);
+        stream_info.push((sample_rate << 4) as u8);
+        stream_info.push(0xf0);
+        stream_info.extend_from_slice(&n_samples.to_be_bytes());
+        stream_info.extend_from_slice(&[0; 16]);
+
+        for i in 0..n_stream_info {
+            buf.push(if i == n_stream_info - 1 { 0x80 } else { 0x00 });
+            buf.extend_from_slice(&(stream_info.len() as u32).to_be_bytes()[1..]);
+            buf.extend_from_slice(&stream_info);
+        }
+
+        for frame_num in 0..n_frames {
+            // 192 samples, and the sample rate from the stream information.
+            buf.extend_from_slice(&build_frame(0x10, frame_num, value));
+        }
+
+        buf
+    }
+
+    /// Builds a mono 16-bit frame with a constant subframe. The block length and sample rate are
+    /// given by the third byte of the frame header.
+    fn build_frame(block_len_and_rate: u8, frame_num: u8, value: u16) -> Vec<u8> {
+        // Fixed block size, the block length and sample rate, mono, 16-bit, and the frame number.
+        let mut frame = vec![0xff, 0xf8, block_len_and_rate, 0x08, frame_num];
+
+        let mut crc8 = Crc8Ccitt::new(0);
+        crc8.process_buf_bytes(&frame);
+        frame.push(crc8.crc());
+
+        // A constant subframe.
+        frame.push(0x00);
+        frame.extend_from_slice(&value.to_be_bytes());
+
+        let mut crc16 = Crc16Ansi::new(0);
+        crc16.process_buf_bytes(&frame);
+        frame.extend_from_slice(&crc16.crc().to_be_bytes());
+
+        frame
+    }
+
+    /// Inserts metadata blocks, given by their block type and contents, after the first stream
+    /// information block of a stream built by `build_flac` with one stream information block.
+    fn insert_blocks(mut buf: Vec<u8>, blocks: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        // Clear the last block flag of the stream information block.
+        buf[4] = 0x00;
+
+        let mut pos = 4 + 4 + 34;
+
+        for (i, (block_type, block)) in blocks.iter().enumerate() {
+            let is_last = if i == blocks.len() - 1 { 0x80 } else { 0x00 };
+
+            let mut header = vec![is_last | block_type];
+            header.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+
+            buf.splice(pos..pos, header.into_iter().chain(block.iter().copied()));
+            pos += 4 + block.len();
+        }
+
+        buf
+    }
+
+    fn open_flac(buf: Vec<u8>, enable_lenient: bool) -> symphonia_core::errors::Result<FlacReader> {
+        let mss = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        FlacReader::try_new(mss, FormatOptions::default().with_lenient(enable_lenient))
+    }
+
+    #[test]
+    fn verify_lenient_extra_stream_info_block() {
+        // Strict mode rejects the additional stream information block.
+        assert!(open_flac(build_flac(44100, 2, 1, 0), false).is_err());
+
+        // Lenient mode ignores it.
+        let mut reader = open_flac(build_flac(44100, 2, 1, 0), true).unwrap();
+
+        assert_eq!(reader.tracks().len(), 1);
+        assert_eq!(reader.tracks()[0].codec_params.sample_rate, Some(44100));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.dur()), (0, 192));
+    }
+
+    #[test]
+    fn verify_high_sample_rate() {
+        // Sample rates above 655350 Hz are valid in the stream information block.
+        let mut reader = open_flac(build_flac(768_000, 1, 2, 0), false).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.sample_rate, Some(768_000));
+        assert_eq!(params.n_frames, Some(384));
+
+        let mut decoder = FlacDecoder::try_new(params, &DecoderOptions::default()).unwrap();
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(decoder.decode(&packet).unwrap().spec().rate, 768_000);
+    }
+
+    #[test]
+    fn verify_output_spec() {
+        let mut reader = open_flac(build_flac(44100, 1, 2, 0), false).unwrap();
+
+        // With the stream information block, the signal specification and capacity are known
+        // before decoding.
+        let mut params = reader.tracks()[0].codec_params.clone();
+        let mut decoder = FlacDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        let spec = decoder.output_spec().unwrap();
+        let max_frames = decoder.max_frames_per_packet().unwrap();
+
+        let packet = reader.next_packet().unwrap();
+
+        let decoded = decoder.decode(&packet).unwrap();
+        assert_eq!(*decoded.spec(), spec);
+        assert_eq!(decoded.capacity() as u64, max_frames);
+
+        // Without the stream information block, they are only known after decoding.
+        params.extra_data = None;
+        let mut decoder = FlacDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        assert_eq!(decoder.output_spec(), None);
+        assert_eq!(decoder.max_frames_per_packet(), None);
+
+        decoder.decode(&packet).unwrap();
+        assert_eq!(decoder.output_spec(), Some(spec));
+    }
+
+    #[test]
+    fn verify_concatenated_streams() {
+        let mut buf = build_flac(44100, 1, 3, 1);
+        buf.extend_from_slice(&build_flac(48000, 1, 2, 2));
+
+        let mut reader = open_flac(buf, false).unwrap();
+
+        // Decodes all packets of the current stream, returning their timestamps, and the value of
+        // the first sample of each packet. Returns the error that ended the stream.
+        fn decode_stream(reader: &mut FlacReader) -> (Vec<u64>, Vec<i32>, Error) {
+            let params = &reader.tracks()[0].codec_params;
+            let mut decoder = FlacDecoder::try_new(params, &DecoderOptions::default()).unwrap();
+
+            let mut timestamps = Vec::new();
+            let mut values = Vec::new();
+
+            loop {
+                let packet = match reader.next_packet() {
+                    Ok(packet) => packet,
+                    Err(err) => return (timestamps, values, err),
+                };
+
+                timestamps.push(packet.ts());
+
+                match decoder.decode(&packet).unwrap() {
+                    AudioBufferRef::S32(buf) => values.push(buf.chan(0)[0]),
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        assert_eq!(reader.tracks()[0].codec_params.sample_rate, Some(44100));
+
+        let (timestamps, values, err) = decode_stream(&mut reader);
+
+        assert_eq!(timestamps, &[0, 192, 384]);
+        assert_eq!(values, &[1 << 16; 3]);
+        assert!(matches!(err, Error::ResetRequired));
+
+        // The track is replaced by the track of the new stream.
+        assert_eq!(reader.tracks().len(), 1);
+        assert_eq!(reader.tracks()[0].codec_params.sample_rate, Some(48000));
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(384));
+
+        let (timestamps, values, err) = decode_stream(&mut reader);
+
+        assert_eq!(timestamps, &[0, 192]);
+        assert_eq!(values, &[2 << 16; 2]);
+        assert!(matches!(err, Error::IoError(_)));
+    }
+
+    #[test]
+    fn verify_corrupt_frames() {
+        let reader = open_flac(build_flac(44100, 1, 1, 0), false).unwrap();
+
+        // Decode the frames as if they were provided by a demuxer that does not guarantee packet
+        // integrity.
+        let mut params = reader.tracks()[0].codec_params.clone();
+        params.with_packet_data_integrity(false);
+
+        let mut decoder = FlacDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        // Corrupt the frame number of the second frame, and the subframe of the third frame.
+        let mut frames = (0..4).map(|i| build_frame(0x10, i, 1)).collect::<Vec<_>>();
+        frames[1][4] ^= 0x01;
+        frames[2][7] ^= 0x01;
+
+        let results = frames
+            .iter()
+            .map(|frame| decoder.decode(&Packet::new_from_slice(0, 0, 192, frame)).is_ok())
+            .collect::<Vec<_>>();
+
+        assert_eq!(results, &[true, false, false, true]);
+        assert_eq!(decoder.finalize().n_corrupt_packets, Some(2));
+
+        // A demuxer that guarantees packet integrity has already verified the frame CRC16.
+        let params = &reader.tracks()[0].codec_params;
+        let mut decoder = FlacDecoder::try_new(params, &DecoderOptions::default()).unwrap();
+
+        assert!(decoder.decode(&Packet::new_from_slice(0, 0, 192, &frames[1])).is_err());
+        assert!(decoder.decode(&Packet::new_from_slice(0, 0, 192, &frames[2])).is_ok());
+        assert_eq!(decoder.finalize().n_corrupt_packets, Some(1));
+    }
+
+    #[test]
+    fn verify_foreign_riff_metadata() {
+        fn info(id: &[u8; 4], value: &[u8]) -> Vec<u8> {
+            let mut buf = id.to_vec();
+            buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            buf.extend_from_slice(value);
+            if value.len() & 1 == 1 {
+                buf.push(0);
+            }
+            buf
+        }
+
+        // The first application block contains the RIFF chunk header and the INFO list chunk, and
+        // the last contains the header of the data chunk.
+        let list = [&b"INFO"[..], &info(b"INAM", b"Title\0"), &info(b"IART", b"Artist\0")].concat();
+
+        let mut riff = b"riffRIFF\0\0\0\0WAVELIST".to_vec();
+        riff.extend_from_slice(&(list.len() as u32).to_le_bytes());
+        riff.extend_from_slice(&list);
+
+        let data = [&b"riffdata"[..], &384u32.to_le_bytes()].concat();
+
+        let buf = insert_blocks(build_flac(44100, 1, 1, 0), &[(2, riff.clone()), (2, data)]);
+
+        let mut reader = open_flac(buf, false).unwrap();
+
+        let metadata = reader.metadata();
+        let rev = metadata.current().unwrap();
+
+        let tags: Vec<_> = rev
+            .tags()
+            .iter()
+            .map(|tag| (tag.std_key, tag.key.as_str(), tag.value.to_string()))
+            .collect();
+
+        assert_eq!(
+            tags,
+            &[
+                (Some(StandardTagKey::TrackTitle), "INAM", "Title".to_string()),
+                (Some(StandardTagKey::Artist), "IART", "Artist".to_string()),
+            ]
+        );
+
+        // The foreign chunks are available as vendor data.
+        let vendor_data = rev.vendor_data();
+        assert_eq!(vendor_data.len(), 2);
+        assert_eq!(vendor_data[0].ident, "riff");
+        assert_eq!(&vendor_data[0].data[..], &riff[4..]);
+        assert_eq!(&vendor_data[1].data[..4], b"data");
+
+        assert!(reader.next_packet().is_ok());
+    }
+
+    #[test]
+    fn verify_application_blocks() {
+        let cue_tools = [&b"CTDB"[..], &[1, 2, 3, 4, 5]].concat();
+        let replay_gain = [&b"\0\x01rg"[..], &[0xaa; 3]].concat();
+
+        let buf = insert_blocks(build_flac(44100, 1, 1, 0), &[(2, cue_tools), (2, replay_gain)]);
+
+        let mut reader = open_flac(buf, false).unwrap();
+
+        let metadata = reader.metadata();
+        let rev = metadata.current().unwrap();
+
+        // All application blocks are available as vendor data, identified by their application ID.
+        // Non-printable characters in the ID are escaped.
+        let vendor_data: Vec<_> =
+            rev.vendor_data().iter().map(|data| (data.ident.as_str(), &data.data[..])).collect();
+
+        assert_eq!(vendor_data, &[("CTDB", &[1, 2, 3, 4, 5][..]), ("\\x00\\x01rg", &[0xaa; 3])]);
+
+        assert!(rev.tags().is_empty());
+        assert!(reader.next_packet().is_ok());
+    }
+
+    #[test]
+    fn verify_cuesheet_and_replay_gain() {
+        let mut comments = [&3u32.to_le_bytes()[..], b"foo", &2u32.to_le_bytes()].concat();
+
+        for comment in [&b"REPLAYGAIN_TRACK_GAIN=-6.50 dB"[..], b"REPLAYGAIN_ALBUM_PEAK=0.988312"] {
+            comments.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            comments.extend_from_slice(comment);
+        }
+
+        // A CD-DA cuesheet with 88200 lead-in samples.
+        let mut cuesheet = vec![0; 128];
+        cuesheet.extend_from_slice(&88200u64.to_be_bytes());
+        cuesheet.push(0x80);
+        cuesheet.extend_from_slice(&[0; 258]);
+        cuesheet.push(3);
+
+        let mut push_track = |offset: u64, number: u8, indicies: &[(u64, u8)]| {
+            cuesheet.extend_from_slice(&offset.to_be_bytes());
+            cuesheet.push(number);
+            cuesheet.extend_from_slice(b"ABCDE1234567");
+            cuesheet.extend_from_slice(&[0; 14]);
+            cuesheet.push(indicies.len() as u8);
+
+            for (offset, number) in indicies.iter() {
+                cuesheet.extend_from_slice(&offset.to_be_bytes());
+                cuesheet.extend_from_slice(&[*number, 0, 0, 0]);
+            }
+        };
+
+        // Track 1 with one index point, track 2 with two index points, and the lead-out track.
+        push_track(0, 1, &[(0, 1)]);
+        push_track(5880, 2, &[(0, 0), (588, 1)]);
+        push_track(11760, 170, &[]);
+
+        let buf = insert_blocks(build_flac(44100, 1, 1, 0), &[(4, comments), (5, cuesheet)]);
+
+        let reader = open_flac(buf, false).unwrap();
+
+        // The lead-out track is not a cue.
+        let cues: Vec<_> = reader
+            .cues()
+            .iter()
+            .map(|cue| {
+                let points: Vec<_> = cue.points.iter().map(|point| point.start_offset_ts).collect();
+                (cue.index, cue.start_ts, points)
+            })
+            .collect();
+
+        assert_eq!(cues, &[(1, 0, vec![0]), (2, 5880, vec![0, 588])]);
+        assert_eq!(reader.cues()[1].tags[0].value.to_string(), "ABCDE1234567");
+
+        let replay_gain = reader.replay_gain().unwrap();
+
+        assert_eq!(replay_gain.track_gain, Some(-6.5));
+        assert_eq!(replay_gain.track_peak, None);
+        assert_eq!(replay_gain.album_gain, None);
+        assert_eq!(replay_gain.album_peak, Some(0.988312));
+
+        // Streams without ReplayGain tags have no ReplayGain information.
+        assert!(open_flac(build_flac(44100, 1, 1, 0), false).unwrap().replay_gain().is_none());
+    }
+
+    #[test]
+    fn verify_decode_without_stream_info() {
+        fn decode(decoder: &mut FlacDecoder, frame: Vec<u8>) -> (usize, u32, usize, i32) {
+            let packet = Packet::new_from_boxed_slice(0, 0, 0, frame.into_boxed_slice());
+
+            match decoder.decode(&packet).unwrap() {
+                AudioBufferRef::S32(buf) => {
+                    (buf.frames(), buf.spec().rate, buf.spec().channels.count(), buf.chan(0)[0])
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // Frames without a preceding stream information block, such as when joining a stream
+        // part-way through. The first frame has 192 samples and states a sample rate of 44.1 kHz,
+        // the second has 4096 samples and states a sample rate of 48 kHz.
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_FLAC);
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        assert_eq!(decode(&mut decoder, build_frame(0x19, 0, 1)), (192, 44100, 1, 1 << 16));
+        assert_eq!(decode(&mut decoder, build_frame(0xca, 1, 2)), (4096, 48000, 1, 2 << 16));
+
+        // A frame that uses the sample rate from the stream information requires the sample rate
+        // in the codec parameters.
+        let packet = Packet::new_from_slice(0, 0, 0, &build_frame(0x10, 2, 3));
+        assert!(decoder.decode(&packet).is_err());
+
+        params.with_sample_rate(8000);
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        assert_eq!(decode(&mut decoder, build_frame(0x10, 2, 3)), (192, 8000, 1, 3 << 16));
+
+        // A frame larger than the maximum block length stated by the stream information.
+        let reader = open_flac(build_flac(44100, 1, 1, 0), false).unwrap();
+        let params = &reader.tracks()[0].codec_params;
+        let mut decoder = FlacDecoder::try_new(params, &Default::default()).unwrap();
+
+        assert_eq!(decode(&mut decoder, build_frame(0xc0, 1, 4)), (4096, 44100, 1, 4 << 16));
+    }
+}
@@ -75,6 +75,21 @@ pub fn sync_frame<B: ReadBytes>(reader: &mut B) -> Result<u16> {
 }
 
 pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<FrameHeader> {
+    let (header, is_crc_ok) = read_frame_header_unchecked(reader, sync)?;
+
+    if !is_crc_ok {
+        return decode_error("flac: computed frame header CRC does not match expected CRC");
+    }
+
+    Ok(header)
+}
+
+/// Reads a frame header, but does not return an error if the computed frame header CRC does not
+/// match the expected CRC. Instead, the result of the comparison is returned alongside the header.
+pub fn read_frame_header_unchecked<B: ReadBytes>(
+    reader: &mut B,
+    sync: u16,
+) -> Result<(FrameHeader, bool)> {
     // The header is checksummed with a CRC8 hash. Include the sync code in this CRC.
     let mut crc8 = Crc8Ccitt::new(0);
     crc8.process_buf_bytes(&sync.to_be_bytes());
@@ -207,17 +222,17 @@ pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<Fram
     // Get expected CRC8 checksum from the header.
     let crc8_expected = reader_crc8.into_inner().read_u8()?;
 
-    if crc8_expected != crc8_computed && cfg!(not(fuzzing)) {
-        return decode_error("flac: computed frame header CRC does not match expected CRC");
-    }
+    let is_crc_ok = crc8_expected == crc8_computed || cfg!(fuzzing);
 
-    Ok(FrameHeader {
+    let header = FrameHeader {
         block_sequence,
         block_num_samples,
         channel_assignment,
         bits_per_sample,
         sample_rate,
-    })
+    };
+
+    Ok((header, is_crc_ok))
 }
 
 /// A very quick check if the provided buffer is likely be a FLAC frame header.
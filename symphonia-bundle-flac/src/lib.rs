@@ -17,8 +17,12 @@
 mod decoder;
 mod demuxer;
 mod frame;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod parser;
 mod validate;
 
 pub use decoder::FlacDecoder;
 pub use demuxer::FlacReader;
+#[cfg(feature = "parallel")]
+pub use parallel::ParallelFlacDecoder;
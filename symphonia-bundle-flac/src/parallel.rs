@@ -0,0 +1,172 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use rayon::prelude::*;
+
+use symphonia_core::audio::AudioBuffer;
+use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions};
+use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::formats::Packet;
+
+use crate::decoder::FlacDecoder;
+
+/// Free Lossless Audio Codec (FLAC) parallel decoder.
+///
+/// Given the stream information, every FLAC frame may be decoded independently of all other
+/// frames. `ParallelFlacDecoder` decodes many FLAC packets at once by dispatching each packet to
+/// the current rayon thread pool, and returns the decoded audio in the same order as the packets.
+/// This is useful when an entire stream must be decoded as quickly as possible, such as when
+/// generating a waveform.
+///
+/// FLAC frames are not byte-aligned to any index, therefore the frame boundaries must be found by
+/// scanning the stream before decoding. [`FlacReader`](crate::FlacReader) performs this scan, and
+/// returns exactly one frame per packet. To decode a stream in parallel, first read the packets of
+/// the stream, or a large section of it, from the reader, and then decode them all at once.
+///
+/// Unlike [`FlacDecoder`], the decoded audio is not verified against the MD5 checksum of the
+/// stream.
+pub struct ParallelFlacDecoder {
+    params: CodecParameters,
+}
+
+impl ParallelFlacDecoder {
+    /// Instantiate a new `ParallelFlacDecoder` given the codec parameters of a FLAC track.
+    pub fn try_new(params: &CodecParameters) -> Result<Self> {
+        // Instantiate a decoder to validate the codec parameters.
+        let decoder = FlacDecoder::try_new(params, &DecoderOptions::default())?;
+
+        Ok(ParallelFlacDecoder { params: decoder.codec_params().clone() })
+    }
+
+    /// Gets a reference to the codec parameters, amended with the stream information, if it was
+    /// provided.
+    pub fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    /// Decodes all packets in parallel, and returns the result of decoding each packet in the same
+    /// order as the packets.
+    ///
+    /// The packets are decoded using the current rayon thread pool. To use a different thread
+    /// pool, call this function from within `ThreadPool::install`.
+    pub fn decode(&self, packets: &[Packet]) -> Vec<Result<AudioBuffer<i32>>> {
+        packets
+            .par_iter()
+            .map_init(
+                || FlacDecoder::try_new(&self.params, &DecoderOptions::default()).ok(),
+                |decoder, packet| match decoder {
+                    Some(decoder) => decoder.decode_owned(packet),
+                    // The codec parameters were validated when the parallel decoder was
+                    // instantiated, so this should not occur.
+                    None => unsupported_error("flac: failed to instantiate decoder"),
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::{AudioBufferRef, Signal};
+    use symphonia_core::checksum::{Crc16Ansi, Crc8Ccitt};
+    use symphonia_core::codecs::{Decoder, DecoderOptions};
+    use symphonia_core::formats::{FormatOptions, FormatReader, Packet};
+    use symphonia_core::io::{MediaSourceStream, Monitor};
+
+    use super::ParallelFlacDecoder;
+    use crate::{FlacDecoder, FlacReader};
+
+    const N_FRAMES: u8 = 100;
+
+    /// Builds a mono 16-bit frame of 192 samples with a verbatim subframe.
+    fn build_frame(frame_num: u8) -> Vec<u8> {
+        // Fixed block size, 192 samples, the sample rate from the stream information, mono,
+        // 16-bit, and the frame number.
+        let mut frame = vec![0xff, 0xf8, 0x10, 0x08, frame_num];
+
+        let mut crc8 = Crc8Ccitt::new(0);
+        crc8.process_buf_bytes(&frame);
+        frame.push(crc8.crc());
+
+        // A verbatim subframe with a unique sawtooth for each frame.
+        frame.push(0x02);
+
+        for i in 0..192u16 {
+            let sample = (i * 97).wrapping_add(u16::from(frame_num) * 600);
+            frame.extend_from_slice(&sample.to_be_bytes());
+        }
+
+        let mut crc16 = Crc16Ansi::new(0);
+        crc16.process_buf_bytes(&frame);
+        frame.extend_from_slice(&crc16.crc().to_be_bytes());
+
+        frame
+    }
+
+    /// Builds a mono 16-bit 44.1kHz stream of `N_FRAMES` frames.
+    fn build_flac() -> Vec<u8> {
+        let mut buf = b"fLaC".to_vec();
+
+        // Block length 192, unknown frame sizes, 44.1kHz, 1 channel, 16-bit, and the total number
+        // of samples.
+        let mut stream_info =
+            vec![0x00, 0xc0, 0x00, 0xc0, 0, 0, 0, 0, 0, 0, 0x0a, 0xc4, 0x40, 0xf0];
+        stream_info.extend_from_slice(&(192 * u32::from(N_FRAMES)).to_be_bytes());
+        stream_info.extend_from_slice(&[0; 16]);
+
+        buf.push(0x80);
+        buf.extend_from_slice(&(stream_info.len() as u32).to_be_bytes()[1..]);
+        buf.extend_from_slice(&stream_info);
+
+        for frame_num in 0..N_FRAMES {
+            buf.extend_from_slice(&build_frame(frame_num));
+        }
+
+        buf
+    }
+
+    #[test]
+    fn verify_parallel_decode() {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(build_flac())), Default::default());
+        let mut reader = FlacReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let params = reader.tracks()[0].codec_params.clone();
+
+        let mut packets = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            packets.push(packet);
+        }
+
+        assert_eq!(packets.len(), usize::from(N_FRAMES));
+
+        // A packet that is not a FLAC frame.
+        packets.insert(50, Packet::new_from_slice(0, 0, 192, &[0; 16]));
+
+        let parallel = ParallelFlacDecoder::try_new(&params).unwrap().decode(&packets);
+
+        let mut decoder = FlacDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        assert_eq!(parallel.len(), packets.len());
+
+        // The parallel decode must be bit-exact, and in the same order, as the serial decode.
+        for (packet, parallel) in packets.iter().zip(parallel) {
+            match decoder.decode(packet) {
+                Ok(AudioBufferRef::S32(serial)) => {
+                    let parallel = parallel.unwrap();
+                    assert_eq!(parallel.spec(), serial.spec());
+                    assert_eq!(parallel.frames(), 192);
+                    assert_eq!(parallel.chan(0), serial.chan(0));
+                }
+                Ok(_) => unreachable!(),
+                Err(_) => assert!(parallel.is_err()),
+            }
+        }
+    }
+}
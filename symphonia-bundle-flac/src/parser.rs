@@ -16,6 +16,9 @@ use log::warn;
 
 use crate::frame::*;
 
+/// The FLAC start of stream marker: "fLaC" in ASCII.
+pub const FLAC_STREAM_MARKER: [u8; 4] = *b"fLaC";
+
 struct MovingAverage<const N: usize> {
     samples: [usize; N],
     count: usize,
@@ -87,6 +90,16 @@ impl FragmentFooter {
     }
 }
 
+/// The outcome of reading a fragment.
+enum ReadFragment {
+    /// A fragment was read.
+    Fragment(Fragment),
+    /// A new stream starts at the current position of the reader.
+    NewStream,
+    /// Synchronization was lost.
+    LostSync,
+}
+
 /// A FLAC packet fragment state tracker.
 struct FragmentState {
     /// A running total CRC16 of a packet if it started at the first byte of this fragment.
@@ -304,12 +317,8 @@ impl PacketParser {
 
     /// Tries to read a fragment upto the maximum size of a FLAC frame using the reader and returns
     /// it. If a fragment cannot be read, then the reader has lost synchronization and must be
-    /// resynchronized.
-    fn try_read_fragment<B>(
-        &self,
-        reader: &mut B,
-        avg_frame_size: usize,
-    ) -> Result<Option<Fragment>>
+    /// resynchronized. A fragment never extends past the start of a new, concatenated, stream.
+    fn try_read_fragment<B>(&self, reader: &mut B, avg_frame_size: usize) -> Result<ReadFragment>
     where
         B: ReadBytes + SeekBuffered,
     {
@@ -339,10 +348,21 @@ impl PacketParser {
         // first byte.
         let mut pos = 1;
 
-        // Read until the next frame header is found, or an IO error such as EOF.
+        // The position from which to search for the start of a new stream. Unlike a frame header,
+        // a new stream may start on the first byte.
+        let mut stream_pos = 0;
+
+        // Read until the next frame header is found, the start of a new stream is found, or an IO
+        // error such as EOF.
         let size = 'found: loop {
+            // If another stream was concatenated to the current stream, then frame headers may
+            // only be searched for up-to the start of the new stream.
+            let new_stream = scan_for_stream_header(&buf[stream_pos..end]).map(|i| stream_pos + i);
+
+            let scan_end = new_stream.unwrap_or(end).max(pos);
+
             // Find the next frame header. Start by searching for the sync preamble.
-            while let Some((offset, sync)) = scan_for_sync_preamble(&buf[pos..end]) {
+            while let Some((offset, sync)) = scan_for_sync_preamble(&buf[pos..scan_end]) {
                 let size = pos + offset;
 
                 let frame = &buf[size..];
@@ -370,11 +390,19 @@ impl PacketParser {
                 pos += offset + 1;
             }
 
+            // No frame header precedes the start of the new stream. Rewind the reader such that
+            // the next read will start on the stream marker.
+            if let Some(size) = new_stream {
+                reader.seek_buffered_rev(end - size);
+
+                break 'found size;
+            }
+
             // If enough data has been read such even a FLAC frame of the maximum size should've
             // been fully read, and the header for the next frame found, then synchronization has
             // been lost.
             if end >= FLAC_MAX_FRAME_SIZE + FLAC_MAX_FRAME_HEADER_SIZE {
-                return Ok(None);
+                return Ok(ReadFragment::LostSync);
             }
 
             // Calculate the required buffer size after reading a new 1kB chunk of data, and grow
@@ -389,6 +417,7 @@ impl PacketParser {
             // header size) of old data such that if a frame header was partially read in the last
             // iteration it will be considered again in the next iteration.
             pos = end.saturating_sub(FLAC_MAX_FRAME_HEADER_SIZE);
+            stream_pos = pos;
 
             // Read the new chunk.
             end += match reader.read_buf(&mut buf[end..next_read_end]) {
@@ -405,20 +434,32 @@ impl PacketParser {
         //     end - size
         // );
 
+        // A new stream starts on the first byte, there is no fragment.
+        if size == 0 {
+            return Ok(ReadFragment::NewStream);
+        }
+
         // Truncate the buffer at the start of the new frame header.
         buf.truncate(size);
 
-        Ok(Some(Fragment::new(buf.into_boxed_slice())))
+        Ok(ReadFragment::Fragment(Fragment::new(buf.into_boxed_slice())))
     }
 
-    /// Reads a fragment using the reader and performs resynchronization when necessary.
-    fn read_fragment<B>(&mut self, reader: &mut B, avg_frame_size: usize) -> Result<Fragment>
+    /// Reads a fragment using the reader and performs resynchronization when necessary. Returns
+    /// `None` if a new stream starts at the current position of the reader.
+    fn read_fragment<B>(
+        &mut self,
+        reader: &mut B,
+        avg_frame_size: usize,
+    ) -> Result<Option<Fragment>>
     where
         B: ReadBytes + SeekBuffered,
     {
         loop {
-            if let Some(fragment) = self.try_read_fragment(reader, avg_frame_size)? {
-                return Ok(fragment);
+            match self.try_read_fragment(reader, avg_frame_size)? {
+                ReadFragment::Fragment(fragment) => return Ok(Some(fragment)),
+                ReadFragment::NewStream => return Ok(None),
+                ReadFragment::LostSync => (),
             }
 
             // If a fragment could not be read, synchronization was lost. Try to resync.
@@ -427,8 +468,10 @@ impl PacketParser {
         }
     }
 
-    /// Parse the next packet from the stream.
-    pub fn parse<B>(&mut self, reader: &mut B) -> Result<Packet>
+    /// Parse the next packet from the stream. Returns `None` if the current stream ended, and a
+    /// new stream, starting with the FLAC stream marker, was concatenated to it. The reader will
+    /// be positioned on the stream marker.
+    pub fn parse<B>(&mut self, reader: &mut B) -> Result<Option<Packet>>
     where
         B: ReadBytes + SeekBuffered,
     {
@@ -439,7 +482,12 @@ impl PacketParser {
 
         // Build a packet.
         let parsed = loop {
-            let fragment = self.read_fragment(reader, avg_frame_size)?;
+            let fragment = match self.read_fragment(reader, avg_frame_size)? {
+                Some(fragment) => fragment,
+                // Any fragments that did not form a packet before the end of the stream are
+                // discarded.
+                None => return Ok(None),
+            };
 
             if let Some(packet) = self.builder.try_build(&self.info, fragment) {
                 break packet;
@@ -449,7 +497,7 @@ impl PacketParser {
         // Update the frame size moving average.
         self.fsma.push(parsed.buf.len());
 
-        Ok(Packet::new_from_boxed_slice(0, parsed.sync.ts, parsed.sync.dur, parsed.buf))
+        Ok(Some(Packet::new_from_boxed_slice(0, parsed.sync.ts, parsed.sync.dur, parsed.buf)))
     }
 
     /// Resync the reader to the start of the next frame.
@@ -625,3 +673,15 @@ fn scan_for_sync_preamble(buf: &[u8]) -> Option<(usize, u16)> {
     // No preamble found.
     None
 }
+
+/// Scan for the start of a new stream, and if one is found, return the position of it in buf.
+///
+/// A new stream starts with the FLAC stream marker followed by the header of a stream information
+/// block. Requiring both makes it unlikely that audio data is mistaken for the start of a stream.
+fn scan_for_stream_header(buf: &[u8]) -> Option<usize> {
+    buf.windows(8).position(|win| {
+        // The stream information block type is 0, and its length is always 34 bytes. The block
+        // may, or may not, be the last metadata block.
+        win[..4] == FLAC_STREAM_MARKER && win[4] & 0x7f == 0 && win[5..8] == [0x00, 0x00, 0x22]
+    })
+}
@@ -11,6 +11,10 @@ use symphonia_core::errors::Result;
 
 use symphonia_core::io::BufReader;
 
+/// The inherent delay, in audio frames, of the layer 3 decoder caused by the hybrid and polyphase
+/// synthesis filterbanks.
+pub const LAYER3_DECODER_DELAY: u32 = 528 + 1;
+
 /// The MPEG audio version.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum MpegVersion {
@@ -103,9 +107,9 @@ pub struct FrameHeader {
     pub is_copyrighted: bool,
     #[allow(dead_code)]
     pub is_original: bool,
-    #[allow(dead_code)]
     pub has_padding: bool,
     pub has_crc: bool,
+    pub is_free_format: bool,
     pub frame_size: usize,
 }
 
@@ -5,9 +5,12 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::time::Instant;
+
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal};
 use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CodecType};
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::diagnostics::{DiagnosticEvent, Diagnostics};
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::formats::Packet;
 use symphonia_core::io::FiniteStream;
@@ -57,13 +60,23 @@ pub struct MpaDecoder {
     params: CodecParameters,
     state: State,
     buf: AudioBuffer<f32>,
+    diagnostics: Diagnostics,
 }
 
 impl MpaDecoder {
     fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
         let mut reader = packet.as_buf_reader();
 
-        let header = header::read_frame_header(&mut reader)?;
+        let mut header = header::read_frame_header(&mut reader)?;
+
+        // The size of a free bit-rate frame is not stated in the header. Assume the packet
+        // contains exactly one frame.
+        if header.is_free_format {
+            let frame_len = header::MPEG_HEADER_LEN + reader.bytes_available() as usize;
+            let unpadded_len = frame_len.saturating_sub(header::padding_len(&header));
+
+            header::set_free_format_frame_len(&mut header, unpadded_len);
+        }
 
         // The packet should be the size stated in the header.
         if header.frame_size != reader.bytes_available() as usize {
@@ -100,6 +113,13 @@ impl MpaDecoder {
             #[cfg(feature = "mp3")]
             State::Layer3(layer) if header.layer == MpegLayer::Layer3 => {
                 layer.decode(&mut reader, &header, &mut self.buf)?;
+
+                if layer.underflow > 0 {
+                    self.diagnostics.emit(DiagnosticEvent::ErrorConcealed {
+                        ts: packet.ts(),
+                        reason: "mpa: main data missing from bit resevoir",
+                    });
+                }
             }
             _ => return decode_error("mpa: invalid mpeg audio layer"),
         }
@@ -126,7 +146,12 @@ impl Decoder for MpaDecoder {
         // Create decoder state.
         let state = State::new(params.codec);
 
-        Ok(MpaDecoder { params: params.clone(), state, buf: AudioBuffer::unused() })
+        Ok(MpaDecoder {
+            params: params.clone(),
+            state,
+            buf: AudioBuffer::unused(),
+            diagnostics: Default::default(),
+        })
     }
 
     fn supported_codecs() -> &'static [CodecDescriptor] {
@@ -144,17 +169,44 @@ impl Decoder for MpaDecoder {
         &self.params
     }
 
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    fn delay(&self) -> u64 {
+        // Only the delay of the layer 3 decoder is reported. Measuring the delay of the polyphase
+        // synthesis filterbank used by the layer 1 and 2 decoders is out of scope, and since no
+        // reader compensates for it, 0 is reported for those layers.
+        match &self.state {
+            #[cfg(feature = "mp3")]
+            State::Layer3(_) => u64::from(LAYER3_DECODER_DELAY),
+            #[allow(unreachable_patterns)]
+            _ => 0,
+        }
+    }
+
     fn reset(&mut self) {
         // Fully reset the decoder state.
         self.state = State::new(self.params.codec);
     }
 
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        let start = if self.diagnostics.is_enabled() { Some(Instant::now()) } else { None };
+
         if let Err(e) = self.decode_inner(packet) {
             self.buf.clear();
             Err(e)
         }
         else {
+            if let Some(start) = start {
+                self.diagnostics.emit(DiagnosticEvent::PacketDecoded {
+                    ts: packet.ts(),
+                    len: packet.buf().len(),
+                    frames: self.buf.frames(),
+                    elapsed: start.elapsed(),
+                });
+            }
+
             Ok(self.buf.as_audio_buffer_ref())
         }
     }
@@ -167,3 +219,69 @@ impl Decoder for MpaDecoder {
         self.buf.as_audio_buffer_ref()
     }
 }
+
+#[cfg(all(test, feature = "mp3"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_MP3};
+    use symphonia_core::diagnostics::{DiagnosticEvent, Diagnostics};
+    use symphonia_core::formats::Packet;
+
+    use super::MpaDecoder;
+
+    #[test]
+    fn verify_layer3_delay() {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_MP3);
+
+        let decoder = MpaDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+        assert_eq!(decoder.delay(), 529);
+    }
+
+    /// Builds a silent MPEG1 layer 3, 128 kbps, 44.1 kHz, stereo frame with the given value of
+    /// main_data_begin.
+    fn silent_frame(main_data_begin: u16) -> Vec<u8> {
+        let mut frame = vec![0; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+        frame[4] = (main_data_begin >> 1) as u8;
+        frame[5] = (main_data_begin << 7) as u8;
+        frame
+    }
+
+    #[test]
+    fn verify_diagnostics() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let handler = {
+            let events = Arc::clone(&events);
+            move |event: &DiagnosticEvent| events.lock().unwrap().push(event.clone())
+        };
+
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_MP3);
+
+        let mut decoder = MpaDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+        decoder.set_diagnostics(Diagnostics::new(Arc::new(handler)));
+
+        // The first frame references main data of a previous frame that was never decoded.
+        for (ts, main_data_begin) in [(0, 1), (1152, 0)].iter() {
+            let buf = silent_frame(*main_data_begin).into_boxed_slice();
+            let packet = Packet::new_from_boxed_slice(0, *ts, 1152, buf);
+            assert_eq!(decoder.decode(&packet).unwrap().frames(), 1152);
+        }
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+
+        assert!(matches!(events[0], DiagnosticEvent::ErrorConcealed { ts: 0, .. }));
+        assert!(matches!(
+            events[1],
+            DiagnosticEvent::PacketDecoded { ts: 0, len: 417, frames: 1152, .. }
+        ));
+        assert!(matches!(
+            events[2],
+            DiagnosticEvent::PacketDecoded { ts: 1152, len: 417, frames: 1152, .. }
+        ));
+    }
+}
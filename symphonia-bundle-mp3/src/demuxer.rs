@@ -9,15 +9,18 @@ use symphonia_core::support_format;
 
 use symphonia_core::checksum::Crc16AnsiLe;
 use symphonia_core::codecs::CodecParameters;
-use symphonia_core::errors::{seek_error, Result, SeekErrorKind};
+use symphonia_core::errors::{decode_error, seek_error, Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::io::*;
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 
-use crate::common::{FrameHeader, MpegLayer};
-use crate::header::{self, MAX_MPEG_FRAME_SIZE, MPEG_HEADER_LEN};
+use symphonia_metadata::ape;
 
+use crate::common::{FrameHeader, MpegLayer, LAYER3_DECODER_DELAY};
+use crate::header::{self, MAX_FREE_FORMAT_FRAME_SIZE, MAX_MPEG_FRAME_SIZE, MPEG_HEADER_LEN};
+
+use std::convert::TryFrom;
 use std::io::{Seek, SeekFrom};
 
 use log::{debug, info, warn};
@@ -25,6 +28,9 @@ use log::{debug, info, warn};
 /// MPEG1 and MPEG2 audio elementary stream reader.
 ///
 /// `MpaReader` implements a demuxer for the MPEG1 and MPEG2 audio elementary stream.
+///
+/// Whether the stream is variable bitrate (VBR), and its nominal bitrate, are available from
+/// [`MpaReader::is_vbr`] and [`MpaReader::nominal_bitrate`].
 pub struct MpaReader {
     reader: MediaSourceStream,
     tracks: Vec<Track>,
@@ -33,6 +39,81 @@ pub struct MpaReader {
     options: FormatOptions,
     first_packet_pos: u64,
     next_packet_ts: u64,
+    bitrate: BitrateInfo,
+    /// The length in bytes of a free bit-rate frame, including the header, but excluding padding.
+    free_format_len: Option<usize>,
+}
+
+impl MpaReader {
+    /// Returns `true` if the stream is variable bitrate (VBR).
+    ///
+    /// A stream is VBR if it starts with a Xing or VBRI tag, or if frames with different bitrates
+    /// have been read. Therefore, a stream without a tag is only known to be VBR once a frame with
+    /// a different bitrate than the first frame is read.
+    pub fn is_vbr(&self) -> bool {
+        self.bitrate.is_vbr
+    }
+
+    /// Gets the nominal bitrate of the stream in bits per second.
+    ///
+    /// For a constant bitrate (CBR) stream this is the bitrate of the frames. For a VBR stream
+    /// this is the average bitrate given by the Xing or VBRI tag, or, if the tag does not provide
+    /// it, the average bitrate of the frames read so far.
+    pub fn nominal_bitrate(&self) -> Option<u32> {
+        let sample_rate = self.tracks[0].codec_params.sample_rate?;
+        self.bitrate.nominal_bitrate(sample_rate)
+    }
+}
+
+/// The bitrate information of a stream, from the Xing/Info or VBRI tag, and the frames read.
+#[derive(Default)]
+struct BitrateInfo {
+    /// The stream is known to be VBR.
+    is_vbr: bool,
+    /// The average bitrate of a VBR stream given by a tag.
+    tag_bitrate: Option<u32>,
+    /// The bitrate of the first frame.
+    first_bitrate: u32,
+    /// The total length in bytes of the frames read.
+    num_bytes: u64,
+    /// The total number of audio frames in the frames read.
+    num_frames: u64,
+}
+
+impl BitrateInfo {
+    /// Accumulate the bitrate of a frame.
+    fn observe(&mut self, header: &FrameHeader) {
+        if header.bitrate != self.first_bitrate {
+            self.is_vbr = true;
+        }
+
+        self.num_bytes += (MPEG_HEADER_LEN + header.frame_size) as u64;
+        self.num_frames += header.duration();
+    }
+
+    fn nominal_bitrate(&self, sample_rate: u32) -> Option<u32> {
+        if !self.is_vbr {
+            Some(self.first_bitrate)
+        }
+        else if self.tag_bitrate.is_some() {
+            self.tag_bitrate
+        }
+        else {
+            average_bitrate(self.num_bytes, self.num_frames, sample_rate)
+        }
+    }
+}
+
+/// Calculates the average bitrate, in bits per second, of the given number of bytes containing
+/// the given number of audio frames.
+fn average_bitrate(num_bytes: u64, num_frames: u64, sample_rate: u32) -> Option<u32> {
+    if num_frames == 0 {
+        return None;
+    }
+
+    let bitrate = 8 * num_bytes * u64::from(sample_rate) / num_frames;
+
+    u32::try_from(bitrate).ok()
 }
 
 impl QueryDescriptor for MpaReader {
@@ -93,8 +174,10 @@ impl QueryDescriptor for MpaReader {
 
 impl FormatReader for MpaReader {
     fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        let mut free_format_len = None;
+
         // Try to read the first MPEG frame.
-        let (header, packet) = read_mpeg_frame_strict(&mut source)?;
+        let (header, packet) = read_mpeg_frame_strict(&mut source, &mut free_format_len)?;
 
         // Use the header to populate the codec parameters.
         let mut params = CodecParameters::new();
@@ -105,8 +188,24 @@ impl FormatReader for MpaReader {
             .with_time_base(TimeBase::new(1, header.sample_rate))
             .with_channels(header.channel_mode.channels());
 
+        let mut bitrate = BitrateInfo { first_bitrate: header.bitrate, ..Default::default() };
+
         // Check if there is a Xing/Info tag contained in the first frame.
         if let Some(info_tag) = try_read_info_tag(&packet, &header) {
+            // The "Xing" id is used for VBR files. The tag frame itself may have a different
+            // bitrate than the audio frames.
+            if !info_tag.is_cbr {
+                bitrate.is_vbr = true;
+
+                if let (Some(num_bytes), Some(num_mpeg_frames)) =
+                    (info_tag.num_bytes, info_tag.num_frames)
+                {
+                    let num_frames = u64::from(num_mpeg_frames) * header.duration();
+                    bitrate.tag_bitrate =
+                        average_bitrate(u64::from(num_bytes), num_frames, header.sample_rate);
+                }
+            }
+
             // The LAME tag contains ReplayGain and padding information.
             let (delay, padding) = if let Some(lame_tag) = info_tag.lame {
                 params.with_delay(lame_tag.enc_delay).with_padding(lame_tag.enc_padding);
@@ -117,15 +216,32 @@ impl FormatReader for MpaReader {
                 (0, 0)
             };
 
-            // The base Xing/Info tag may contain the number of frames.
-            if let Some(num_mpeg_frames) = info_tag.num_frames {
+            // The base Xing/Info tag may contain the number of frames. If it does not, estimate
+            // the number of frames from the frames following the tag if seekable.
+            let num_mpeg_frames = if let Some(num_mpeg_frames) = info_tag.num_frames {
                 debug!("using xing header for duration");
 
-                let num_frames = u64::from(num_mpeg_frames) * header.duration();
+                Some((u64::from(num_mpeg_frames), false))
+            }
+            else if source.is_seekable() {
+                info!("estimating duration from bitrate, tag has no frame count");
+
+                estimate_num_mpeg_frames(&mut source, &mut free_format_len).map(|n| (n, true))
+            }
+            else {
+                None
+            };
+
+            if let Some((num_mpeg_frames, is_estimate)) = num_mpeg_frames {
+                let mut num_frames = num_mpeg_frames * header.duration();
 
                 // Adjust for gapless playback.
                 if options.enable_gapless {
-                    params.with_n_frames(num_frames - u64::from(delay) - u64::from(padding));
+                    num_frames = num_frames.saturating_sub(u64::from(delay) + u64::from(padding));
+                }
+
+                if is_estimate {
+                    params.with_estimated_n_frames(num_frames);
                 }
                 else {
                     params.with_n_frames(num_frames);
@@ -139,6 +255,11 @@ impl FormatReader for MpaReader {
 
             // Check if there is a VBRI tag.
             params.with_n_frames(num_frames);
+
+            // VBRI tags are only used for VBR files.
+            bitrate.is_vbr = true;
+            bitrate.tag_bitrate =
+                average_bitrate(u64::from(vbri_tag.num_bytes), num_frames, header.sample_rate);
         }
         else {
             // The first frame was not a Xing/Info header, rewind back to the start of the frame so
@@ -149,29 +270,44 @@ impl FormatReader for MpaReader {
             if source.is_seekable() {
                 info!("estimating duration from bitrate, may be inaccurate for vbr files");
 
-                if let Some(n_mpeg_frames) = estimate_num_mpeg_frames(&mut source) {
-                    params.with_n_frames(n_mpeg_frames * header.duration());
+                if let Some(n_mpeg_frames) =
+                    estimate_num_mpeg_frames(&mut source, &mut free_format_len)
+                {
+                    params.with_estimated_n_frames(n_mpeg_frames * header.duration());
                 }
             }
         }
 
+        // An APE tag may be appended to the end of the stream.
+        let mut metadata: MetadataLog = Default::default();
+
+        let mut builder = MetadataBuilder::new();
+
+        match ape::read_ape_tag_at_end(&mut source, &mut builder) {
+            Ok(true) => metadata.push(builder.metadata()),
+            Ok(false) => (),
+            Err(err) => warn!("ignoring invalid ape tag: {}", err),
+        }
+
         let first_packet_pos = source.pos();
 
         Ok(MpaReader {
             reader: source,
             tracks: vec![Track::new(0, params)],
             cues: Vec::new(),
-            metadata: Default::default(),
+            metadata,
             options: *options,
             first_packet_pos,
             next_packet_ts: 0,
+            bitrate,
+            free_format_len,
         })
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
         let (header, packet) = loop {
             // Read the next MPEG frame.
-            let (header, packet) = read_mpeg_frame(&mut self.reader)?;
+            let (header, packet) = read_mpeg_frame(&mut self.reader, &mut self.free_format_len)?;
 
             // Check if the packet contains a Xing, Info, or VBRI tag.
             if is_maybe_info_tag(&packet, &header) {
@@ -192,6 +328,8 @@ impl FormatReader for MpaReader {
             break (header, packet);
         };
 
+        self.bitrate.observe(&header);
+
         // Each frame contains 1 or 2 granules with each granule being exactly 576 samples long.
         let ts = self.next_packet_ts;
         let duration = header.duration();
@@ -290,7 +428,9 @@ impl FormatReader for MpaReader {
 
         loop {
             // Parse the next frame header.
-            let header = header::parse_frame_header(header::sync_frame(&mut self.reader)?)?;
+            let mut header = header::parse_frame_header(header::sync_frame(&mut self.reader)?)?;
+
+            resolve_free_format_frame(&mut self.reader, &mut header, &mut self.free_format_len)?;
 
             // Position of the frame header.
             let pos = self.reader.pos() - std::mem::size_of::<u32>() as u64;
@@ -422,7 +562,7 @@ impl MpaReader {
         self.reader.seek(SeekFrom::Start(seek_pos))?;
 
         // Resync to the start of the next packet.
-        let (header, _) = read_mpeg_frame_strict(&mut self.reader)?;
+        let (header, _) = read_mpeg_frame_strict(&mut self.reader, &mut self.free_format_len)?;
 
         // Calculate, roughly, the timestamp of the packet based on the byte position after resync.
         let seeked_pos = self.reader.pos();
@@ -460,14 +600,19 @@ impl MpaReader {
 }
 
 /// Reads a MPEG frame and returns the header and buffer.
-fn read_mpeg_frame(reader: &mut MediaSourceStream) -> Result<(FrameHeader, Vec<u8>)> {
+fn read_mpeg_frame(
+    reader: &mut MediaSourceStream,
+    free_format_len: &mut Option<usize>,
+) -> Result<(FrameHeader, Vec<u8>)> {
     let (header, header_word) = loop {
         // Sync to the next frame header.
         let sync = header::sync_frame(reader)?;
 
         // Parse the frame header fully.
-        if let Ok(header) = header::parse_frame_header(sync) {
-            break (header, sync);
+        if let Ok(mut header) = header::parse_frame_header(sync) {
+            if resolve_free_format_frame(reader, &mut header, free_format_len).is_ok() {
+                break (header, sync);
+            }
         }
 
         warn!("invalid mpeg audio header");
@@ -485,10 +630,13 @@ fn read_mpeg_frame(reader: &mut MediaSourceStream) -> Result<(FrameHeader, Vec<u
 }
 
 /// Reads a MPEG frame and checks if the next frame begins after the packet.
-fn read_mpeg_frame_strict(reader: &mut MediaSourceStream) -> Result<(FrameHeader, Vec<u8>)> {
+fn read_mpeg_frame_strict(
+    reader: &mut MediaSourceStream,
+    free_format_len: &mut Option<usize>,
+) -> Result<(FrameHeader, Vec<u8>)> {
     loop {
         // Read the next MPEG frame.
-        let (header, packet) = read_mpeg_frame(reader)?;
+        let (header, packet) = read_mpeg_frame(reader, free_format_len)?;
 
         // Get the position before trying to read the next header.
         let pos = reader.pos();
@@ -532,6 +680,86 @@ fn is_frame_header_similar(header: &FrameHeader, sync: u32) -> bool {
     false
 }
 
+/// Sets the size of a free bit-rate frame. If the length of the free bit-rate frames in the stream
+/// is not yet known, then it is found by locating the header of the next frame. The stream must be
+/// positioned immediately after the frame header.
+fn resolve_free_format_frame(
+    reader: &mut MediaSourceStream,
+    header: &mut FrameHeader,
+    free_format_len: &mut Option<usize>,
+) -> Result<()> {
+    if !header.is_free_format {
+        return Ok(());
+    }
+
+    let unpadded_len = match *free_format_len {
+        Some(len) => len,
+        None => match find_free_format_frame_len(reader, header) {
+            Some((len, true)) => {
+                // The length of all free bit-rate frames in the stream is the same, excluding
+                // padding. Cache it so that the next frame does not need to be located again.
+                let unpadded_len = len - header::padding_len(header);
+                *free_format_len = Some(unpadded_len);
+                unpadded_len
+            }
+            // The stream ended before the next frame was found. The frame is the last, and
+            // therefore its length cannot be assumed to be that of any other frame.
+            Some((len, false)) => len.saturating_sub(header::padding_len(header)),
+            None => return decode_error("mpa: free bit-rate frame length not found"),
+        },
+    };
+
+    header::set_free_format_frame_len(header, unpadded_len);
+
+    Ok(())
+}
+
+/// Finds the length in bytes of a free bit-rate frame, including the header, by scanning for the
+/// header of the next frame. The stream must be positioned immediately after the frame header, and
+/// is returned to that position afterwards.
+///
+/// Returns the length, and `true` if the next frame header was found, or `false` if the stream
+/// ended. Returns `None` if the length exceeds the maximum length of a free bit-rate frame.
+fn find_free_format_frame_len(
+    reader: &mut MediaSourceStream,
+    header: &FrameHeader,
+) -> Option<(usize, bool)> {
+    reader.ensure_seekback_buffer(MAX_FREE_FORMAT_FRAME_SIZE);
+
+    let start_pos = reader.pos();
+
+    let mut sync = 0u32;
+    let mut len = MPEG_HEADER_LEN;
+
+    let result = loop {
+        let byte = match reader.read_u8() {
+            Ok(byte) => byte,
+            _ => break Some((len, false)),
+        };
+
+        sync = (sync << 8) | u32::from(byte);
+        len += 1;
+
+        // The next frame header must be a free bit-rate frame header similar to this one.
+        if len >= 2 * MPEG_HEADER_LEN
+            && header::is_frame_header_word_synced(sync)
+            && header::check_header(sync)
+            && (sync & 0xf000) == 0
+            && is_frame_header_similar(header, sync)
+        {
+            break Some((len - MPEG_HEADER_LEN, true));
+        }
+
+        if len > MAX_FREE_FORMAT_FRAME_SIZE {
+            break None;
+        }
+    };
+
+    reader.seek_buffered(start_pos);
+
+    result
+}
+
 #[derive(Default)]
 struct FramePos {
     ts: u64,
@@ -558,7 +786,10 @@ fn read_main_data_begin<B: ReadBytes>(reader: &mut B, header: &FrameHeader) -> R
 }
 
 /// Estimates the total number of MPEG frames in the media source stream.
-fn estimate_num_mpeg_frames(reader: &mut MediaSourceStream) -> Option<u64> {
+fn estimate_num_mpeg_frames(
+    reader: &mut MediaSourceStream,
+    free_format_len: &mut Option<usize>,
+) -> Option<u64> {
     const MAX_FRAMES: u32 = 16;
     const MAX_LEN: usize = 16 * 1024;
 
@@ -587,7 +818,9 @@ fn estimate_num_mpeg_frames(reader: &mut MediaSourceStream) -> Option<u64> {
         let header_val = break_on_err!(reader.read_be_u32());
 
         // Parse the frame header.
-        let header = break_on_err!(header::parse_frame_header(header_val));
+        let mut header = break_on_err!(header::parse_frame_header(header_val));
+
+        break_on_err!(resolve_free_format_frame(reader, &mut header, free_format_len));
 
         // Tabulate the size.
         total_frame_len += MPEG_HEADER_LEN + header.frame_size;
@@ -727,10 +960,10 @@ fn try_read_info_tag_inner(buf: &[u8], header: &FrameHeader) -> Result<Option<Xi
             let trim = reader.read_be_u24()?;
 
             if encoder[..4] == *b"LAME" || encoder[..4] == *b"Lavf" || encoder[..4] == *b"Lavc" {
-                let delay = 528 + 1 + (trim >> 12);
+                let delay = LAYER3_DECODER_DELAY + (trim >> 12);
                 let padding = trim & ((1 << 12) - 1);
 
-                (delay, padding.saturating_sub(528 + 1))
+                (delay, padding.saturating_sub(LAYER3_DECODER_DELAY))
             }
             else {
                 (0, 0)
@@ -847,7 +1080,6 @@ fn is_maybe_info_tag(buf: &[u8], header: &FrameHeader) -> bool {
 const VBRI_TAG_ID: [u8; 4] = *b"VBRI";
 
 /// The contents of a VBRI tag.
-#[allow(dead_code)]
 struct VbriTag {
     num_bytes: u32,
     num_mpeg_frames: u32,
@@ -921,3 +1153,231 @@ fn is_maybe_vbri_tag(buf: &[u8], header: &FrameHeader) -> bool {
     // The bytes preceeding the VBRI tag (mostly the side information) should be all 0.
     !buf[MPEG_HEADER_LEN..VBRI_TAG_OFFSET].iter().any(|&b| b != 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::formats::{FormatOptions, FormatReader};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::*;
+
+    /// Builds a silent MPEG1 Layer 3 mono 44.1 kHz frame with the given bitrate index.
+    fn build_frame(bitrate_idx: u8) -> Vec<u8> {
+        let word = u32::from_be_bytes([0xff, 0xfb, bitrate_idx << 4, 0xc0]);
+        let header = header::parse_frame_header(word).unwrap();
+
+        let mut frame = word.to_be_bytes().to_vec();
+        frame.resize(MPEG_HEADER_LEN + header.frame_size, 0);
+        frame
+    }
+
+    /// Builds a stream of 10 frames, alternating between 128 and 160 kbps if `is_vbr` is true,
+    /// optionally preceded by a frame containing a Xing tag.
+    fn build_stream(is_vbr: bool, with_xing: bool) -> (Vec<u8>, u64) {
+        let frames: Vec<Vec<u8>> =
+            (0..10).map(|i| build_frame(if is_vbr && i % 2 == 1 { 10 } else { 9 })).collect();
+
+        let num_bytes = frames.iter().map(|frame| frame.len() as u64).sum();
+
+        let mut buf = Vec::new();
+
+        if with_xing {
+            let mut tag = build_frame(9);
+            tag[21..25].copy_from_slice(&XING_TAG_ID);
+            tag[25..29].copy_from_slice(&3u32.to_be_bytes());
+            tag[29..33].copy_from_slice(&10u32.to_be_bytes());
+            tag[33..37].copy_from_slice(&(num_bytes as u32).to_be_bytes());
+            buf.extend(tag);
+        }
+
+        buf.extend(frames.concat());
+        (buf, num_bytes)
+    }
+
+    /// Builds a stream of 10 silent free bit-rate MPEG1 Layer 3 mono 44.1 kHz frames of 500 bytes,
+    /// where every third frame is padded by 1 byte.
+    fn build_free_format_stream() -> Vec<u8> {
+        (0..10)
+            .flat_map(|i| {
+                let has_padding = i % 3 == 2;
+
+                let mut frame = vec![0xff, 0xfb, if has_padding { 0x02 } else { 0x00 }, 0xc0];
+                frame.resize(500 + usize::from(has_padding), 0);
+                frame
+            })
+            .collect()
+    }
+
+    fn open(buf: Vec<u8>) -> MpaReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        MpaReader::try_new(mss, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_cbr_bitrate() {
+        let mut reader = open(build_stream(false, false).0);
+
+        assert!(!reader.is_vbr());
+        assert_eq!(reader.nominal_bitrate(), Some(128_000));
+
+        while reader.next_packet().is_ok() {}
+
+        assert!(!reader.is_vbr());
+        assert_eq!(reader.nominal_bitrate(), Some(128_000));
+    }
+
+    #[test]
+    fn verify_vbr_bitrate_from_xing_tag() {
+        let (buf, num_bytes) = build_stream(true, true);
+        let mut reader = open(buf);
+
+        let expected = (8 * num_bytes * 44_100 / (10 * 1152)) as u32;
+
+        assert!(reader.is_vbr());
+        assert_eq!(reader.nominal_bitrate(), Some(expected));
+
+        // The tag frame is not a packet.
+        let mut count = 0;
+        while reader.next_packet().is_ok() {
+            count += 1;
+        }
+
+        assert_eq!(count, 10);
+        assert_eq!(reader.nominal_bitrate(), Some(expected));
+    }
+
+    #[test]
+    fn verify_vbr_bitrate_from_frames() {
+        let (buf, num_bytes) = build_stream(true, false);
+        let mut reader = open(buf);
+
+        // VBR is only detected once a frame with a different bitrate is read.
+        assert!(!reader.is_vbr());
+        assert_eq!(reader.nominal_bitrate(), Some(128_000));
+
+        reader.next_packet().unwrap();
+        assert!(!reader.is_vbr());
+
+        reader.next_packet().unwrap();
+        assert!(reader.is_vbr());
+
+        while reader.next_packet().is_ok() {}
+
+        // The average of the 128 and 160 kbps frames.
+        let expected = (8 * num_bytes * 44_100 / (10 * 1152)) as u32;
+        assert_eq!(reader.nominal_bitrate(), Some(expected));
+        assert!(expected > 128_000 && expected < 160_000);
+    }
+
+    #[test]
+    fn verify_estimated_duration() {
+        // 40 frames alternating between 128 and 160 kbps.
+        let frames: Vec<u8> = (0..40).flat_map(|i| build_frame(9 + i % 2)).collect();
+
+        // A Xing tag with only the number of bytes.
+        let mut tag = build_frame(9);
+        tag[21..25].copy_from_slice(&XING_TAG_ID);
+        tag[25..29].copy_from_slice(&2u32.to_be_bytes());
+        tag[29..33].copy_from_slice(&(frames.len() as u32).to_be_bytes());
+
+        let reader = open([tag, frames].concat());
+
+        let params = &reader.tracks()[0].codec_params;
+        assert!(params.n_frames_is_estimate);
+
+        // The estimate is within 2% of the actual duration.
+        let n_frames = params.n_frames.unwrap() as f64;
+        assert!((n_frames / (40.0 * 1152.0) - 1.0).abs() < 0.02);
+
+        // The estimate of a CBR stream is exact.
+        let reader = open((0..40).flat_map(|_| build_frame(9)).collect());
+
+        let params = &reader.tracks()[0].codec_params;
+        assert!(params.n_frames_is_estimate);
+        assert_eq!(params.n_frames, Some(40 * 1152));
+
+        // The number of frames given by a Xing tag is exact.
+        let reader = open(build_stream(true, true).0);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert!(!params.n_frames_is_estimate);
+        assert_eq!(params.n_frames, Some(10 * 1152));
+    }
+
+    #[cfg(feature = "mp3")]
+    #[test]
+    fn verify_free_format() {
+        use symphonia_core::codecs::Decoder;
+
+        use crate::MpaDecoder;
+
+        let mut reader = open(build_free_format_stream());
+
+        // The bit-rate is derived from the length of an unpadded frame.
+        assert!(!reader.is_vbr());
+        assert_eq!(reader.nominal_bitrate(), Some(500 * 44_100 / 144));
+
+        let params = &reader.tracks()[0].codec_params;
+        let mut decoder = MpaDecoder::try_new(params, &Default::default()).unwrap();
+
+        let mut lens = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            lens.push(packet.buf().len());
+            assert_eq!(decoder.decode(&packet).unwrap().frames(), 1152);
+        }
+
+        // The last frame is not followed by another frame.
+        assert_eq!(lens, &[500, 500, 501, 500, 500, 501, 500, 500, 501, 500]);
+
+        // Seek back to the sixth frame.
+        let seeked_to =
+            reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 5 * 1152, track_id: 0 });
+        assert_eq!(seeked_to.unwrap().actual_ts, 5 * 1152);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts(), packet.buf().len()), (5 * 1152, 501));
+    }
+
+    #[test]
+    fn verify_ape_tag_at_end() {
+        let (mut buf, _) = build_stream(false, false);
+
+        // An APEv2 tag, without a header, with one item.
+        let mut tag = 5u32.to_le_bytes().to_vec();
+        tag.extend_from_slice(&0u32.to_le_bytes());
+        tag.extend_from_slice(b"Title\0Hello");
+
+        let mut footer = b"APETAGEX".to_vec();
+        footer.extend_from_slice(&2000u32.to_le_bytes());
+        footer.extend_from_slice(&(tag.len() as u32 + 32).to_le_bytes());
+        footer.extend_from_slice(&1u32.to_le_bytes());
+        footer.extend_from_slice(&[0; 12]);
+
+        // The APE tag is followed by an ID3v1 tag.
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+
+        buf.extend([tag, footer, id3v1].concat());
+
+        let mut reader = open(buf);
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(tags[0].value.to_string(), "Hello");
+
+        // The tag is read without disturbing the position of the first packet.
+        let mut count = 0;
+        while reader.next_packet().is_ok() {
+            count += 1;
+        }
+
+        assert_eq!(count, 10);
+    }
+}
@@ -5,7 +5,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 
 use crate::common::*;
@@ -16,6 +16,9 @@ pub const MPEG_HEADER_LEN: usize = 4;
 /// The maximum length in bytes of a MPEG audio frame including the header.
 pub const MAX_MPEG_FRAME_SIZE: u64 = 2881;
 
+/// The maximum length in bytes of a free bit-rate MPEG audio frame including the header.
+pub const MAX_FREE_FORMAT_FRAME_SIZE: usize = 8 * 1024;
+
 /// Bit-rate lookup table for MPEG version 1 layer 1.
 const BIT_RATES_MPEG1_L1: [u32; 15] = [
     0, 32_000, 64_000, 96_000, 128_000, 160_000, 192_000, 224_000, 256_000, 288_000, 320_000,
@@ -124,8 +127,9 @@ pub fn parse_frame_header(header: u32) -> Result<FrameHeader> {
 
     let bitrate = match ((header & 0xf000) >> 12, version, layer) {
         // "Free" bit-rate. Note, this is NOT variable bit-rate and is not a mandatory feature of
-        // MP3 decoders.
-        (0b0000, _, _) => return unsupported_error("mpa: free bit-rate is not supported"),
+        // MP3 decoders. The bit-rate, and therefore the frame size, is constant but not stated in
+        // the header. It must be set using `set_free_format_frame_len` once known.
+        (0b0000, _, _) => 0,
         // Invalid bit-rate.
         (0b1111, _, _) => return decode_error("mpa: invalid bit-rate"),
         // MPEG 1 bit-rates.
@@ -197,26 +201,20 @@ pub fn parse_frame_header(header: u32) -> Result<FrameHeader> {
 
     let has_crc = header & 0x1_0000 == 0;
 
-    // Constants provided for size calculation in section ISO-11172 section 2.4.3.1.
-    let factor = match layer {
-        MpegLayer::Layer1 => 12,
-        MpegLayer::Layer2 => 144,
-        MpegLayer::Layer3 if version == MpegVersion::Mpeg1 => 144,
-        MpegLayer::Layer3 => 72,
-    };
-
-    // The header specifies the total frame size in "slots". For layers 2 & 3 a slot is 1 byte,
-    // however for layer 1 a slot is 4 bytes.
-    let slot_size = match layer {
-        MpegLayer::Layer1 => 4,
-        _ => 1,
-    };
+    let is_free_format = bitrate == 0;
 
-    // Calculate the total frame size in number of slots.
-    let frame_size_slots = (factor * bitrate / sample_rate) as usize + usize::from(has_padding);
+    // The size of a free bit-rate frame is unknown until set.
+    let frame_size = if is_free_format {
+        0
+    }
+    else {
+        // Calculate the total frame size in number of slots.
+        let frame_size_slots = (size_factor(version, layer) * bitrate / sample_rate) as usize
+            + usize::from(has_padding);
 
-    // Calculate the frame size in bytes, excluding the header.
-    let frame_size = (frame_size_slots * slot_size) - 4;
+        // Calculate the frame size in bytes, excluding the header.
+        (frame_size_slots * slot_size(layer)) - 4
+    };
 
     Ok(FrameHeader {
         version,
@@ -230,10 +228,54 @@ pub fn parse_frame_header(header: u32) -> Result<FrameHeader> {
         is_original,
         has_padding,
         has_crc,
+        is_free_format,
         frame_size,
     })
 }
 
+/// Gets the constant provided for frame size calculation in ISO-11172 section 2.4.3.1.
+fn size_factor(version: MpegVersion, layer: MpegLayer) -> u32 {
+    match layer {
+        MpegLayer::Layer1 => 12,
+        MpegLayer::Layer2 => 144,
+        MpegLayer::Layer3 if version == MpegVersion::Mpeg1 => 144,
+        MpegLayer::Layer3 => 72,
+    }
+}
+
+/// Gets the size of a slot in bytes.
+fn slot_size(layer: MpegLayer) -> usize {
+    // The header specifies the total frame size in "slots". For layers 2 & 3 a slot is 1 byte,
+    // however for layer 1 a slot is 4 bytes.
+    match layer {
+        MpegLayer::Layer1 => 4,
+        _ => 1,
+    }
+}
+
+/// Gets the length in bytes of the padding of the frame.
+pub fn padding_len(header: &FrameHeader) -> usize {
+    if header.has_padding {
+        slot_size(header.layer)
+    }
+    else {
+        0
+    }
+}
+
+/// Sets the frame size and bit-rate of a free bit-rate frame header. The length of a free bit-rate
+/// frame is constant throughout the stream, excluding padding, and must be provided in bytes
+/// including the header, but excluding the padding.
+pub fn set_free_format_frame_len(header: &mut FrameHeader, unpadded_len: usize) {
+    debug_assert!(header.is_free_format);
+
+    let n_slots = (unpadded_len / slot_size(header.layer)) as u64;
+    let factor = u64::from(size_factor(header.version, header.layer));
+
+    header.bitrate = (n_slots * u64::from(header.sample_rate) / factor) as u32;
+    header.frame_size = (unpadded_len + padding_len(header)).saturating_sub(MPEG_HEADER_LEN);
+}
+
 /// Synchronize the stream to the start of the next MPEG audio frame header, then read and return
 /// the frame header or an error.
 #[inline]
@@ -256,6 +256,9 @@ pub struct Layer3 {
     pub overlap: [[[f32; 18]; 32]; 2],
     pub synthesis: [synthesis::SynthesisState; 2],
     pub resevoir: BitResevoir,
+    /// The number of bytes of main data that were missing from the bit resevoir when the last
+    /// frame was decoded. The granules the missing bytes belong to are decoded as silence.
+    pub underflow: u32,
 }
 
 impl Layer3 {
@@ -265,6 +268,7 @@ impl Layer3 {
             overlap: [[[0f32; 18]; 32]; 2],
             synthesis: Default::default(),
             resevoir: BitResevoir::new(),
+            underflow: 0,
         }
     }
 
@@ -401,11 +405,11 @@ impl Layer for Layer3 {
         };
 
         // Buffer main data into the bit resevoir.
-        let underflow =
+        self.underflow =
             self.resevoir.fill(&buf[side_info_len..], frame_data.main_data_begin as usize)?;
 
         // Read the main data (scale factors and spectral samples).
-        match self.read_main_data(header, 8 * underflow, &mut frame_data) {
+        match self.read_main_data(header, 8 * self.underflow, &mut frame_data) {
             Ok(len) => {
                 // Consume the bytes of main data read from the resevoir.
                 self.resevoir.consume(len);
@@ -0,0 +1,199 @@
+// Symphonia Check Tool
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Deterministic hashing of decoded audio for regression testing.
+//!
+//! Instead of comparing against a reference decoder, the decoded audio of a track is reduced to
+//! an MD5 hash of its interleaved signed 32-bit little-endian samples. The hash of a known-good
+//! decoding can then be committed as a golden value, and compared against in future.
+
+use std::fmt;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::checksum::Md5;
+use symphonia::core::codecs::Decoder;
+use symphonia::core::errors::{Error, Result};
+use symphonia::core::formats::FormatReader;
+use symphonia::core::io::Monitor;
+
+/// The hash of the decoded audio of a track.
+pub struct PcmHash {
+    /// The number of decoded audio frames.
+    pub n_frames: u64,
+    /// The MD5 hash of the interleaved signed 32-bit little-endian samples.
+    pub md5: [u8; 16],
+}
+
+impl fmt::Display for PcmHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.md5 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode all packets of the track with the given track ID until the end of the stream, and hash
+/// the decoded audio.
+///
+/// Any error, including a decode error, stops hashing and is returned since a hash of a partial
+/// decoding would not be useful for regression testing.
+pub fn hash_track(
+    format: &mut dyn FormatReader,
+    decoder: &mut dyn Decoder,
+    track_id: u32,
+) -> Result<PcmHash> {
+    let mut md5 = Md5::default();
+    let mut n_frames = 0;
+
+    let mut sample_buf: Option<SampleBuffer<i32>> = None;
+    let mut bytes = Vec::new();
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = decoder.decode(&packet)?;
+        let frames = decoded.frames();
+
+        if frames == 0 {
+            continue;
+        }
+
+        // Reallocate the sample buffer if the decoded buffer would not fit.
+        let capacity = sample_buf.as_ref().map_or(0, |buf| buf.capacity());
+        let required = frames * decoded.spec().channels.count();
+
+        if capacity < required {
+            sample_buf = Some(SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+        }
+
+        let sample_buf = sample_buf.as_mut().unwrap();
+        sample_buf.copy_interleaved_ref(decoded);
+
+        bytes.clear();
+        bytes.extend(sample_buf.samples().iter().flat_map(|sample| sample.to_le_bytes()));
+        md5.process_buf_bytes(&bytes);
+
+        n_frames += frames as u64;
+    }
+
+    Ok(PcmHash { n_frames, md5: md5.md5() })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, Channels, Signal, SignalSpec};
+    use symphonia::core::checksum::{Crc16Ansi, Crc8Ccitt};
+    use symphonia::core::io::{MediaSourceStream, Monitor};
+    use symphonia::default::formats::{WavSampleFormat, WavWriter};
+
+    use super::hash_track;
+    use crate::DecoderInstance;
+
+    /// The number of frames of the test signal.
+    const N_FRAMES: u32 = 1000;
+
+    /// The golden hash of the test signal.
+    const TEST_SIGNAL_MD5: &str = "7f2b246856116f668a4f0683df9dcd65";
+
+    /// Get the frame of the 16-bit stereo test signal at the given index.
+    fn test_signal(i: u32) -> [i16; 2] {
+        [(i * 7919) as u16 as i16, 16384u32.wrapping_sub(3 * i) as u16 as i16]
+    }
+
+    fn build_wav() -> Vec<u8> {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i16>::new(u64::from(N_FRAMES), spec);
+        buf.render_reserved(Some(N_FRAMES as usize));
+
+        for ch in 0..2 {
+            for (i, sample) in buf.chan_mut(ch).iter_mut().enumerate() {
+                *sample = test_signal(i as u32)[ch];
+            }
+        }
+
+        let mut writer =
+            WavWriter::new(Cursor::new(Vec::new()), spec, WavSampleFormat::S16).unwrap();
+        writer.write(buf.as_audio_buffer_ref()).unwrap();
+        writer.finalize().unwrap().into_inner()
+    }
+
+    fn build_flac() -> Vec<u8> {
+        const BLOCK_LEN: u32 = 256;
+
+        let mut buf = b"fLaC".to_vec();
+
+        // The last metadata block flag, and the stream information block type and length.
+        buf.extend_from_slice(&[0x80, 0, 0, 34]);
+        // Block length 256, and unknown frame sizes.
+        buf.extend_from_slice(&[0x01, 0x00, 0x01, 0x00, 0, 0, 0, 0, 0, 0]);
+        // 44100 Hz, 2 channels, 16 bits per sample, and the total number of frames.
+        let info = (44100u64 << 44) | (1 << 41) | (15 << 36) | u64::from(N_FRAMES);
+        buf.extend_from_slice(&info.to_be_bytes());
+        buf.extend_from_slice(&[0; 16]);
+
+        for (frame_num, start) in (0..N_FRAMES).step_by(BLOCK_LEN as usize).enumerate() {
+            let len = BLOCK_LEN.min(N_FRAMES - start);
+
+            // Fixed block size, 8-bit block length at the end of the header, sample rate from the
+            // stream information, 2 independent channels, 16-bit, the frame number, and the block
+            // length.
+            let mut frame = vec![0xff, 0xf8, 0x60, 0x18, frame_num as u8, (len - 1) as u8];
+
+            let mut crc8 = Crc8Ccitt::new(0);
+            crc8.process_buf_bytes(&frame);
+            frame.push(crc8.crc());
+
+            // A verbatim subframe per channel.
+            for ch in 0..2 {
+                frame.push(0x02);
+
+                for i in start..start + len {
+                    frame.extend_from_slice(&test_signal(i)[ch].to_be_bytes());
+                }
+            }
+
+            let mut crc16 = Crc16Ansi::new(0);
+            crc16.process_buf_bytes(&frame);
+            frame.extend_from_slice(&crc16.crc().to_be_bytes());
+
+            buf.extend_from_slice(&frame);
+        }
+
+        buf
+    }
+
+    fn hash(buf: Vec<u8>) -> (u64, String) {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        let mut inst = DecoderInstance::try_open(mss, &Default::default()).unwrap();
+
+        let hash = hash_track(inst.format.as_mut(), inst.decoder.as_mut(), inst.track_id).unwrap();
+
+        (hash.n_frames, hash.to_string())
+    }
+
+    #[test]
+    fn verify_pcm_hash() {
+        assert_eq!(hash(build_wav()), (u64::from(N_FRAMES), TEST_SIGNAL_MD5.to_string()));
+    }
+
+    #[test]
+    fn verify_flac_hash() {
+        assert_eq!(hash(build_flac()), (u64::from(N_FRAMES), TEST_SIGNAL_MD5.to_string()));
+    }
+}
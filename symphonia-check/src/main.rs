@@ -27,6 +27,8 @@ use symphonia::core::probe::Hint;
 use clap::Arg;
 use log::{info, warn};
 
+mod hash;
+
 /// The absolute maximum allowable sample delta. Around 2^-17 (-102.4dB).
 const ABS_MAX_ALLOWABLE_SAMPLE_DELTA: f32 = 0.00001;
 
@@ -326,7 +328,8 @@ fn run_test(path: &str, opts: &TestOptions, result: &mut TestResult) -> Result<(
     let tgt_ms = Box::new(File::open(Path::new(path))?);
     let tgt_mss = MediaSourceStream::new(tgt_ms, Default::default());
 
-    let tgt_fmt_opts = FormatOptions { enable_gapless: opts.gapless, ..Default::default() };
+    let mut tgt_fmt_opts = FormatOptions::default();
+    tgt_fmt_opts.with_gapless(opts.gapless);
 
     let mut tgt_inst = DecoderInstance::try_open(tgt_mss, &tgt_fmt_opts)?;
 
@@ -334,6 +337,18 @@ fn run_test(path: &str, opts: &TestOptions, result: &mut TestResult) -> Result<(
     run_check(&mut ref_inst, &mut tgt_inst, opts, result)
 }
 
+fn run_hash(path: &str, gapless: bool) -> Result<hash::PcmHash> {
+    let ms = Box::new(File::open(Path::new(path))?);
+    let mss = MediaSourceStream::new(ms, Default::default());
+
+    let mut fmt_opts = FormatOptions::default();
+    fmt_opts.with_gapless(gapless);
+
+    let mut inst = DecoderInstance::try_open(mss, &fmt_opts)?;
+
+    hash::hash_track(inst.format.as_mut(), inst.decoder.as_mut(), inst.track_id)
+}
+
 fn main() {
     pretty_env_logger::init();
 
@@ -363,11 +378,27 @@ fn main() {
                 .help("Specify a particular decoder to be used as the reference"),
         )
         .arg(Arg::new("no-gapless").long("no-gapless").help("Disable gapless decoding"))
+        .arg(
+            Arg::new("hash")
+                .long("hash")
+                .help("Print a hash of the decoded audio instead of checking it with a reference"),
+        )
         .arg(Arg::new("INPUT").help("The input file path").required(true).index(1))
         .get_matches();
 
     let path = matches.value_of("INPUT").unwrap();
 
+    if matches.is_present("hash") {
+        match run_hash(path, !matches.is_present("no-gapless")) {
+            Ok(hash) => println!("{}  {} frames  {}", hash, hash.n_frames, path),
+            Err(err) => {
+                eprintln!("Hashing interrupted by error: {}", err);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
     let ref_decoder = match matches.value_of("decoder").unwrap() {
         "ffmpeg" => RefDecoder::Ffmpeg,
         "flac" => RefDecoder::Flac,
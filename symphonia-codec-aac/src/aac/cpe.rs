@@ -100,6 +100,9 @@ impl ChannelPair {
         if common_window {
             let bands = self.ics0.get_bands();
 
+            // The length of a window. For short windows, this is the offset between windows.
+            let win_len = bands[bands.len() - 1];
+
             let mut g = 0;
 
             for w in 0..self.ics0.info.num_windows {
@@ -108,8 +111,8 @@ impl ChannelPair {
                 }
 
                 for sfb in 0..self.ics0.info.max_sfb {
-                    let start = w * 128 + bands[sfb];
-                    let end = w * 128 + bands[sfb + 1];
+                    let start = w * win_len + bands[sfb];
+                    let end = w * win_len + bands[sfb + 1];
 
                     if self.ics1.is_intensity(g, sfb) {
                         // Intensity stereo
@@ -154,11 +157,12 @@ impl ChannelPair {
         dsp: &mut dsp::Dsp,
         abuf: &mut AudioBuffer<f32>,
         rate_idx: usize,
+        channel_map: &[usize],
     ) {
-        self.ics0.synth_channel(dsp, rate_idx, abuf.chan_mut(self.channel));
+        self.ics0.synth_channel(dsp, rate_idx, abuf.chan_mut(channel_map[self.channel]));
 
         if self.is_pair {
-            self.ics1.synth_channel(dsp, rate_idx, abuf.chan_mut(self.channel + 1));
+            self.ics1.synth_channel(dsp, rate_idx, abuf.chan_mut(channel_map[self.channel + 1]));
         }
     }
 }
@@ -357,6 +357,9 @@ impl Ics {
 
         let bands = self.get_bands();
 
+        // The length of a window. For short windows, this is the offset between windows.
+        let win_len = bands[bands.len() - 1];
+
         for g in 0..self.info.window_groups {
             let cur_w = self.info.get_group_start(g);
             let next_w = self.info.get_group_start(g + 1);
@@ -368,7 +371,7 @@ impl Ics {
                 let scale = self.scales[g][sfb];
 
                 for w in cur_w..next_w {
-                    let dst = &mut self.coeffs[start + w * 128..end + w * 128];
+                    let dst = &mut self.coeffs[start + w * win_len..end + w * win_len];
 
                     // Derived from ISO/IEC-14496-3 Table 4.151.
                     match cb_idx {
@@ -410,6 +413,9 @@ impl Ics {
             self.info.decode(bs)?;
         }
 
+        // The maximum scale factor band must not exceed the number of bands.
+        validate!(self.info.max_sfb < self.get_bands().len());
+
         self.decode_section_data(bs)?;
 
         self.decode_scale_factor_data(bs)?;
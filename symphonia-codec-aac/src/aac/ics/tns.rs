@@ -161,6 +161,9 @@ impl Tns {
         })
         .min(info.max_sfb);
 
+        // The length of a window. For short windows, this is the offset between windows.
+        let win_len = bands[bands.len() - 1];
+
         for w in 0..info.num_windows {
             let mut bottom = bands.len() - 1;
 
@@ -176,8 +179,8 @@ impl Tns {
                     continue;
                 }
 
-                let start = w * 128 + bands[bottom.min(tns_max_bands)];
-                let end = w * 128 + bands[top.min(tns_max_bands)];
+                let start = w * win_len + bands[bottom.min(tns_max_bands)];
+                let end = w * win_len + bands[top.min(tns_max_bands)];
 
                 let lpc = &self.coeffs[w][f].coef;
 
@@ -30,18 +30,19 @@ mod window;
 use crate::common::*;
 use common::*;
 
-struct M4AInfo {
-    otype: M4AType,
-    srate: u32,
-    channels: usize,
-    samples: usize,
+/// The audio specific config of a MPEG-4 audio stream.
+pub(crate) struct M4AInfo {
+    pub(crate) otype: M4AType,
+    pub(crate) srate: u32,
+    pub(crate) channels: usize,
+    pub(crate) samples: usize,
     sbr_ps_info: Option<(u32, usize)>,
     sbr_present: bool,
     ps_present: bool,
 }
 
 impl M4AInfo {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             otype: M4AType::None,
             srate: 0,
@@ -71,10 +72,7 @@ impl M4AInfo {
     fn read_sampling_frequency<B: ReadBitsLtr>(bs: &mut B) -> Result<u32> {
         match bs.read_bits_leq32(4)? {
             idx if idx < 15 => Ok(AAC_SAMPLE_RATES[idx as usize]),
-            _ => {
-                let srate = (0xf << 20) & bs.read_bits_leq32(20)?;
-                Ok(srate)
-            }
+            _ => Ok(bs.read_bits_leq32(24)?),
         }
     }
 
@@ -91,23 +89,54 @@ impl M4AInfo {
     fn read(&mut self, buf: &[u8]) -> Result<()> {
         let mut bs = BitReaderLtr::new(buf);
 
-        self.otype = Self::read_object_type(&mut bs)?;
-        self.srate = Self::read_sampling_frequency(&mut bs)?;
+        self.read_config(&mut bs)?;
+
+        if self.sbr_ps_info.is_some() && (bs.bits_left() >= 16) {
+            let sync = bs.read_bits_leq32(11)?;
+
+            if sync == 0x2B7 {
+                let ext_otype = Self::read_object_type(&mut bs)?;
+                if ext_otype == M4AType::Sbr {
+                    self.sbr_present = bs.read_bool()?;
+                    if self.sbr_present {
+                        let _ext_srate = Self::read_sampling_frequency(&mut bs)?;
+                        if bs.bits_left() >= 12 {
+                            let sync = bs.read_bits_leq32(11)?;
+                            if sync == 0x548 {
+                                self.ps_present = bs.read_bool()?;
+                            }
+                        }
+                    }
+                }
+                if ext_otype == M4AType::PS {
+                    self.sbr_present = bs.read_bool()?;
+                    if self.sbr_present {
+                        let _ext_srate = Self::read_sampling_frequency(&mut bs)?;
+                    }
+                    let _ext_channels = bs.read_bits_leq32(4)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads an audio specific config from a bitstream, excluding any trailing backwards-compatible
+    /// extension signalling, since the length of the config may not be known.
+    pub(crate) fn read_config<B: ReadBitsLtr>(&mut self, bs: &mut B) -> Result<()> {
+        self.otype = Self::read_object_type(bs)?;
+        self.srate = Self::read_sampling_frequency(bs)?;
 
         validate!(self.srate > 0);
 
-        self.channels = Self::read_channel_config(&mut bs)?;
+        self.channels = Self::read_channel_config(bs)?;
 
         if (self.otype == M4AType::Sbr) || (self.otype == M4AType::PS) {
-            let ext_srate = Self::read_sampling_frequency(&mut bs)?;
-            self.otype = Self::read_object_type(&mut bs)?;
+            let ext_srate = Self::read_sampling_frequency(bs)?;
+            self.otype = Self::read_object_type(bs)?;
 
-            let ext_chans = if self.otype == M4AType::ER_BSAC {
-                Self::read_channel_config(&mut bs)?
-            }
-            else {
-                0
-            };
+            let ext_chans =
+                if self.otype == M4AType::ER_BSAC { Self::read_channel_config(bs)? } else { 0 };
 
             self.sbr_ps_info = Some((ext_srate, ext_chans));
         }
@@ -246,33 +275,6 @@ impl M4AInfo {
             _ => {}
         };
 
-        if self.sbr_ps_info.is_some() && (bs.bits_left() >= 16) {
-            let sync = bs.read_bits_leq32(11)?;
-
-            if sync == 0x2B7 {
-                let ext_otype = Self::read_object_type(&mut bs)?;
-                if ext_otype == M4AType::Sbr {
-                    self.sbr_present = bs.read_bool()?;
-                    if self.sbr_present {
-                        let _ext_srate = Self::read_sampling_frequency(&mut bs)?;
-                        if bs.bits_left() >= 12 {
-                            let sync = bs.read_bits_leq32(11)?;
-                            if sync == 0x548 {
-                                self.ps_present = bs.read_bool()?;
-                            }
-                        }
-                    }
-                }
-                if ext_otype == M4AType::PS {
-                    self.sbr_present = bs.read_bool()?;
-                    if self.sbr_present {
-                        let _ext_srate = Self::read_sampling_frequency(&mut bs)?;
-                    }
-                    let _ext_channels = bs.read_bits_leq32(4)?;
-                }
-            }
-        }
-
         Ok(())
     }
 }
@@ -295,6 +297,8 @@ pub struct AacDecoder {
     // info: NACodecInfoRef,
     m4ainfo: M4AInfo,
     pairs: Vec<cpe::ChannelPair>,
+    /// Maps the channel number of a syntactic element to an audio buffer channel index.
+    channel_map: &'static [usize],
     dsp: dsp::Dsp,
     sbinfo: GASubbandInfo,
     params: CodecParameters,
@@ -387,7 +391,7 @@ impl AacDecoder {
         }
         let rate_idx = GASubbandInfo::find_idx(self.m4ainfo.srate);
         for pair in 0..cur_pair {
-            self.pairs[pair].synth_audio(&mut self.dsp, &mut self.buf, rate_idx);
+            self.pairs[pair].synth_audio(&mut self.dsp, &mut self.buf, rate_idx, self.channel_map);
         }
         Ok(())
     }
@@ -453,20 +457,33 @@ impl Decoder for AacDecoder {
 
         //print!("edata:"); for s in edata.iter() { print!(" {:02X}", *s);}println!("");
 
-        if (m4ainfo.otype != M4AType::Lc) || (m4ainfo.channels > 2) || (m4ainfo.samples != 1024) {
+        if m4ainfo.otype != M4AType::Lc {
             return unsupported_error("aac: aac too complex");
         }
 
+        // Only the default channel configurations with up-to 6 channels are supported.
+        let channel_map = match map_channel_indices(m4ainfo.channels as u32) {
+            Some(channel_map) => channel_map,
+            None => return unsupported_error("aac: unsupported channel configuration"),
+        };
+
+        // Both the 1024 and 960 sample frame lengths are supported.
+        if (m4ainfo.samples != 1024) && (m4ainfo.samples != 960) {
+            return unsupported_error("aac: unsupported frame length");
+        }
+
         let spec = SignalSpec::new(m4ainfo.srate, map_channels(m4ainfo.channels as u32).unwrap());
 
         let duration = m4ainfo.samples as Duration;
         let srate = m4ainfo.srate;
+        let frame_len = m4ainfo.samples;
 
         Ok(AacDecoder {
             m4ainfo,
             pairs: Vec::new(),
-            dsp: dsp::Dsp::new(),
-            sbinfo: GASubbandInfo::find(srate),
+            channel_map,
+            dsp: dsp::Dsp::new(frame_len),
+            sbinfo: GASubbandInfo::find(srate, frame_len),
             params: params.clone(),
             buf: AudioBuffer::new(duration, spec),
         })
@@ -486,6 +503,11 @@ impl Decoder for AacDecoder {
         &self.params
     }
 
+    fn delay(&self) -> u64 {
+        // The overlap-add of the IMDCT delays the decoded audio by one frame.
+        self.m4ainfo.samples as u64
+    }
+
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
         if let Err(e) = self.decode_inner(packet) {
             self.buf.clear();
@@ -504,3 +526,29 @@ impl Decoder for AacDecoder {
         self.buf.as_audio_buffer_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::Channels;
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_AAC};
+
+    use super::AacDecoder;
+
+    #[test]
+    fn verify_delay() {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_AAC)
+            .with_sample_rate(44100)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let decoder = AacDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+        assert_eq!(decoder.delay(), 1024);
+
+        // AAC-LC, 44.1kHz, stereo, with the 960 sample frame length flag set.
+        params.with_extra_data(Box::new([0x12, 0x14]));
+
+        let decoder = AacDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+        assert_eq!(decoder.delay(), 960);
+    }
+}
@@ -163,6 +163,24 @@ pub const AAC_SAMPLE_RATES: [u32; 16] = [
 
 pub const AAC_CHANNELS: [usize; 8] = [0, 1, 2, 3, 4, 5, 6, 8];
 
+/// Gets the mapping of AAC channels, in the order of the syntactic elements of the default channel
+/// configuration, to the audio buffer channel index for the channels returned by `map_channels`.
+pub fn map_channel_indices(channels: u32) -> Option<&'static [usize]> {
+    match channels {
+        1 => Some(&[0]),
+        2 => Some(&[0, 1]),
+        // C, L, R
+        3 => Some(&[2, 0, 1]),
+        // C, L, R, Cs
+        4 => Some(&[2, 0, 1, 3]),
+        // C, L, R, Ls, Rs
+        5 => Some(&[2, 0, 1, 3, 4]),
+        // C, L, R, Ls, Rs, LFE
+        6 => Some(&[2, 0, 1, 4, 5, 3]),
+        _ => None,
+    }
+}
+
 pub fn map_channels(channels: u32) -> Option<Channels> {
     match channels {
         0 => None,
@@ -19,6 +19,8 @@
 mod aac;
 mod adts;
 mod common;
+mod loas;
 
 pub use aac::AacDecoder;
 pub use adts::AdtsReader;
+pub use loas::LoasReader;
@@ -0,0 +1,442 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::support_format;
+
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_AAC};
+use symphonia_core::errors::{decode_error, seek_error, unsupported_error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+
+use std::collections::VecDeque;
+use std::io::{Seek, SeekFrom};
+
+use crate::aac::M4AInfo;
+use crate::common::map_channels;
+
+use log::debug;
+
+/// The LOAS synchronization word, followed by the 5 most-significant bits of the frame length.
+/// Since markers must be whole bytes, there is one marker for each value of the length bits.
+#[rustfmt::skip]
+const LOAS_MARKERS: &[&[u8]] = &[
+    &[0x56, 0xe0], &[0x56, 0xe1], &[0x56, 0xe2], &[0x56, 0xe3],
+    &[0x56, 0xe4], &[0x56, 0xe5], &[0x56, 0xe6], &[0x56, 0xe7],
+    &[0x56, 0xe8], &[0x56, 0xe9], &[0x56, 0xea], &[0x56, 0xeb],
+    &[0x56, 0xec], &[0x56, 0xed], &[0x56, 0xee], &[0x56, 0xef],
+    &[0x56, 0xf0], &[0x56, 0xf1], &[0x56, 0xf2], &[0x56, 0xf3],
+    &[0x56, 0xf4], &[0x56, 0xf5], &[0x56, 0xf6], &[0x56, 0xf7],
+    &[0x56, 0xf8], &[0x56, 0xf9], &[0x56, 0xfa], &[0x56, 0xfb],
+    &[0x56, 0xfc], &[0x56, 0xfd], &[0x56, 0xfe], &[0x56, 0xff],
+];
+
+/// The length of the LOAS sync word and frame length.
+const LOAS_HEADER_LEN: usize = 3;
+
+/// Low Overhead Audio Stream (LOAS) format reader.
+///
+/// `LoasReader` implements a demuxer for LOAS, the synchronization layer of the Low-overhead MPEG-4
+/// Audio Transport Multiplex (LATM), as defined in ISO/IEC 14496-3. Only streams with a single
+/// program and layer are supported.
+pub struct LoasReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    config: Option<StreamMuxConfig>,
+    access_units: VecDeque<Box<[u8]>>,
+    frame_len: u64,
+    first_frame_pos: u64,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for LoasReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "loas",
+            "Low Overhead Audio Stream (LATM)",
+            &["loas", "latm"],
+            &["audio/mp4a-latm"],
+            LOAS_MARKERS
+        )]
+    }
+
+    fn score(context: &[u8]) -> u8 {
+        // Since the markers are short, only accept the stream if the first audio mux element
+        // contains a supported stream mux config.
+        let buf = match context.get(LOAS_HEADER_LEN..) {
+            Some(buf) => buf,
+            None => return 0,
+        };
+
+        let mut bs = BitReaderLtr::new(buf);
+
+        match bs.read_bool() {
+            Ok(false) if StreamMuxConfig::read(&mut bs, buf).is_ok() => 255,
+            _ => 0,
+        }
+    }
+}
+
+/// A stream mux config, as defined in ISO/IEC 14496-3 section 1.7.3.
+#[derive(Debug)]
+struct StreamMuxConfig {
+    /// The audio specific config of the stream, padded to a whole number of bytes.
+    asc: Box<[u8]>,
+    /// The sample rate of the stream.
+    sample_rate: u32,
+    /// The number of channels in the stream.
+    channels: usize,
+    /// The number of samples per access unit.
+    frame_len: usize,
+    /// The number of access units in each audio mux element.
+    num_sub_frames: u32,
+}
+
+impl StreamMuxConfig {
+    /// Read a stream mux config. The bitstream must have been created from `buf`.
+    fn read(bs: &mut BitReaderLtr<'_>, buf: &[u8]) -> Result<Self> {
+        let audio_mux_version = bs.read_bool()?;
+
+        let audio_mux_version_a = if audio_mux_version { bs.read_bool()? } else { false };
+
+        if audio_mux_version_a {
+            return unsupported_error("loas: unsupported audio mux version");
+        }
+
+        if audio_mux_version {
+            let _tara_buffer_fullness = read_latm_value(bs)?;
+        }
+
+        let _all_streams_same_time_framing = bs.read_bool()?;
+
+        let num_sub_frames = bs.read_bits_leq32(6)? + 1;
+        let num_program = bs.read_bits_leq32(4)? + 1;
+
+        if num_program > 1 {
+            return unsupported_error("loas: multiple programs are not supported");
+        }
+
+        let num_layer = bs.read_bits_leq32(3)? + 1;
+
+        if num_layer > 1 {
+            return unsupported_error("loas: multiple layers are not supported");
+        }
+
+        // The first layer of the first program always has an audio specific config. If the audio
+        // mux version is 0, the length of the audio specific config is only known once read.
+        let mut info = M4AInfo::new();
+
+        let asc = if audio_mux_version {
+            let asc_len = read_latm_value(bs)?;
+
+            let start = bit_pos(bs, buf);
+            info.read_config(bs)?;
+            let used = bit_pos(bs, buf) - start;
+
+            if used > u64::from(asc_len) {
+                return decode_error("loas: invalid audio specific config length");
+            }
+
+            // The remainder of the audio specific config may contain extension signalling.
+            bs.ignore_bits(asc_len - used as u32)?;
+
+            copy_bits(buf, start, u64::from(asc_len))?
+        }
+        else {
+            let start = bit_pos(bs, buf);
+            info.read_config(bs)?;
+
+            copy_bits(buf, start, bit_pos(bs, buf) - start)?
+        };
+
+        if info.samples == 0 {
+            return unsupported_error("loas: unsupported audio object type");
+        }
+
+        // Only variable length payloads are used by AAC. The remaining frame length types are
+        // used by CELP and HVXC.
+        let frame_length_type = bs.read_bits_leq32(3)?;
+
+        if frame_length_type != 0 {
+            return unsupported_error("loas: unsupported frame length type");
+        }
+
+        let _latm_buffer_fullness = bs.read_bits_leq32(8)?;
+
+        let other_data_present = bs.read_bool()?;
+
+        if other_data_present {
+            if audio_mux_version {
+                let _other_data_len_bits = read_latm_value(bs)?;
+            }
+            else {
+                loop {
+                    let other_data_len_esc = bs.read_bool()?;
+                    let _other_data_len_tmp = bs.read_bits_leq32(8)?;
+
+                    if !other_data_len_esc {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let crc_check_present = bs.read_bool()?;
+
+        if crc_check_present {
+            let _crc_check_sum = bs.read_bits_leq32(8)?;
+        }
+
+        Ok(StreamMuxConfig {
+            asc,
+            sample_rate: info.srate,
+            channels: info.channels,
+            frame_len: info.samples,
+            num_sub_frames,
+        })
+    }
+}
+
+/// Read a value encoded with `LatmGetValue()`.
+fn read_latm_value<B: ReadBitsLtr>(bs: &mut B) -> Result<u32> {
+    let bytes_for_value = bs.read_bits_leq32(2)? + 1;
+    Ok(bs.read_bits_leq32(8 * bytes_for_value)?)
+}
+
+/// Gets the position of the bitstream in bits relative to the start of `buf`.
+fn bit_pos(bs: &BitReaderLtr<'_>, buf: &[u8]) -> u64 {
+    8 * buf.len() as u64 - bs.bits_left()
+}
+
+/// Copy `len` bits from `buf`, starting at the bit position `start`, into a new buffer. The last
+/// byte is padded with 0s.
+fn copy_bits(buf: &[u8], start: u64, len: u64) -> Result<Box<[u8]>> {
+    let mut bs = BitReaderLtr::new(buf);
+    bs.ignore_bits(start as u32)?;
+
+    let mut out = Vec::with_capacity(((len + 7) / 8) as usize);
+    let mut left = len;
+
+    while left > 0 {
+        let n_bits = left.min(8) as u32;
+        out.push((bs.read_bits_leq32(n_bits)? << (8 - n_bits)) as u8);
+        left -= u64::from(n_bits);
+    }
+
+    Ok(out.into_boxed_slice())
+}
+
+/// Synchronize the reader to the next LOAS frame, and read the audio mux element it contains.
+///
+/// If the audio mux element contains a stream mux config, `config` is updated. The access units
+/// of the audio mux element are appended to `access_units`. Returns the position of the frame.
+fn read_audio_mux_element(
+    reader: &mut MediaSourceStream,
+    config: &mut Option<StreamMuxConfig>,
+    access_units: &mut VecDeque<Box<[u8]>>,
+) -> Result<u64> {
+    let mut sync = 0u16;
+
+    while sync & 0xffe0 != 0x56e0 {
+        sync = (sync << 8) | u16::from(reader.read_u8()?);
+    }
+
+    let pos = reader.pos() - 2;
+
+    let len = (usize::from(sync & 0x1f) << 8) | usize::from(reader.read_u8()?);
+
+    let buf = reader.read_boxed_slice_exact(len)?;
+
+    let mut bs = BitReaderLtr::new(&buf);
+
+    let use_same_stream_mux = bs.read_bool()?;
+
+    if !use_same_stream_mux {
+        *config = Some(StreamMuxConfig::read(&mut bs, &buf)?);
+    }
+
+    let config = match config {
+        Some(config) => config,
+        None => {
+            // Access units preceding the first stream mux config cannot be decoded.
+            debug!("loas: skipping audio mux element without a stream mux config");
+            return Ok(pos);
+        }
+    };
+
+    for _ in 0..config.num_sub_frames {
+        // PayloadLengthInfo
+        let mut au_len = 0;
+
+        loop {
+            let tmp = bs.read_bits_leq32(8)?;
+            au_len += tmp as usize;
+
+            if tmp != 255 {
+                break;
+            }
+        }
+
+        // PayloadMux. The access unit is not byte-aligned.
+        let mut au = vec![0; au_len];
+
+        for byte in au.iter_mut() {
+            *byte = bs.read_bits_leq32(8)? as u8;
+        }
+
+        access_units.push_back(au.into_boxed_slice());
+    }
+
+    Ok(pos)
+}
+
+impl FormatReader for LoasReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let mut config = None;
+        let mut access_units = VecDeque::new();
+
+        // Find the first frame with a stream mux config.
+        let first_frame_pos = loop {
+            let pos = read_audio_mux_element(&mut source, &mut config, &mut access_units)?;
+
+            if config.is_some() {
+                break pos;
+            }
+        };
+
+        let config = config.unwrap();
+
+        // Use the stream mux config to populate the codec parameters.
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_AAC)
+            .with_sample_rate(config.sample_rate)
+            .with_time_base(TimeBase::new(1, config.sample_rate))
+            .with_extra_data(config.asc.clone());
+
+        if let Some(channels) = map_channels(config.channels as u32) {
+            params.with_channels(channels);
+        }
+
+        let frame_len = config.frame_len as u64;
+
+        Ok(LoasReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            config: Some(config),
+            access_units,
+            frame_len,
+            first_frame_pos,
+            next_packet_ts: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        // Read audio mux elements until there is an access unit to return.
+        let au = loop {
+            match self.access_units.pop_front() {
+                Some(au) => break au,
+                None => {
+                    read_audio_mux_element(
+                        &mut self.reader,
+                        &mut self.config,
+                        &mut self.access_units,
+                    )?;
+                }
+            }
+        };
+
+        let ts = self.next_packet_ts;
+
+        self.next_packet_ts += self.frame_len;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, self.frame_len, au))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Get the timestamp of the desired audio frame.
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // Use the sample rate to calculate the frame timestamp. If sample rate is not
+                // known, the seek cannot be completed.
+                if let Some(sample_rate) = self.tracks[0].codec_params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        debug!("seeking to ts={}", required_ts);
+
+        // If the desired timestamp is less-than the next packet timestamp, attempt to seek
+        // to the start of the stream.
+        if required_ts < self.next_packet_ts {
+            // If the reader is not seekable then only forward seeks are possible.
+            if self.reader.is_seekable() {
+                let seeked_pos = self.reader.seek(SeekFrom::Start(self.first_frame_pos))?;
+
+                // Since the stream has no timestamp information, the position seeked to must be
+                // exactly as requested.
+                if seeked_pos != self.first_frame_pos {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+
+            // Successfuly seeked to the start of the stream, reset the next packet timestamp.
+            self.next_packet_ts = 0;
+            self.access_units.clear();
+        }
+
+        // Discard access units until the access unit containing the desired timestamp is reached.
+        while self.next_packet_ts + self.frame_len <= required_ts {
+            if self.access_units.pop_front().is_some() {
+                self.next_packet_ts += self.frame_len;
+            }
+            else {
+                read_audio_mux_element(&mut self.reader, &mut self.config, &mut self.access_units)?;
+            }
+        }
+
+        debug!(
+            "seeked to ts={} (delta={})",
+            self.next_packet_ts,
+            required_ts as i64 - self.next_packet_ts as i64
+        );
+
+        Ok(SeekedTo { track_id: 0, required_ts, actual_ts: self.next_packet_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
@@ -1,7 +1,8 @@
-use symphonia_codec_aac::{AacDecoder, AdtsReader};
+use symphonia_codec_aac::{AacDecoder, AdtsReader, LoasReader};
+use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
 use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_AAC};
 use symphonia_core::errors;
-use symphonia_core::formats::{FormatOptions, FormatReader};
+use symphonia_core::formats::{FormatOptions, FormatReader, Packet, SeekMode, SeekTo};
 use symphonia_core::io::MediaSourceStream;
 
 fn test_decode(data: Vec<u8>) -> symphonia_core::errors::Result<()> {
@@ -33,3 +34,355 @@ fn invalid_channels_aac() {
 
     assert!(matches!(err, errors::Error::Unsupported(_)));
 }
+
+/// Decode a single AAC-LC frame, with the provided audio specific config, where the only non-zero
+/// spectral coefficient of the single channel element is at index 40. Returns the decoded samples.
+fn test_decode_tone_frame(extra_data: &[u8]) -> Vec<f32> {
+    let mut decoder = AacDecoder::try_new(
+        CodecParameters::new()
+            .for_codec(CODEC_TYPE_AAC)
+            .with_extra_data(extra_data.to_vec().into_boxed_slice()),
+        &DecoderOptions::default(),
+    )
+    .unwrap();
+
+    let mut bw = BitWriter::default();
+
+    // Single channel element, global gain, and a long sine window with 11 scale factor bands.
+    bw.write_bits(0, 3);
+    bw.write_bits(0, 4);
+    bw.write_bits(200, 8);
+    bw.write_bits(0, 4);
+    bw.write_bits(11, 6);
+    bw.write_bits(0, 1);
+    // The first 10 bands, spanning coefficients 0 to 39 at 48kHz, use the zero codebook, and band
+    // 10, spanning coefficients 40 to 47, uses spectrum codebook 1.
+    bw.write_bits(0, 4);
+    bw.write_bits(10, 5);
+    bw.write_bits(1, 4);
+    bw.write_bits(1, 5);
+    // The scale factor of band 10 is the global gain.
+    bw.write_bits(0, 1);
+    // No pulse, TNS, or gain control data.
+    bw.write_bits(0, 3);
+    // The quads (1, 0, 0, 0), and (0, 0, 0, 0).
+    bw.write_bits(0x10, 5);
+    bw.write_bits(0, 1);
+    // End.
+    bw.write_bits(7, 3);
+
+    let packet = Packet::new_from_slice(0, 0, 0, &bw.buf);
+
+    match decoder.decode(&packet).unwrap() {
+        AudioBufferRef::F32(buf) => buf.chan(0).to_vec(),
+        _ => unreachable!(),
+    }
+}
+
+/// Verifies the samples of the first frame decoded from a single spectral coefficient, `k`, are
+/// the first half of the sine windowed inverse MDCT of that coefficient. The overlap-add of the
+/// first frame is with silence.
+fn verify_tone_frame(samples: &[f32], k: usize) {
+    let n = samples.len();
+    let n0 = (n as f64 + 1.0) / 2.0;
+
+    let expected: Vec<f64> = (0..n)
+        .map(|i| {
+            let i = i as f64;
+            let window = (std::f64::consts::PI / (2 * n) as f64 * (i + 0.5)).sin();
+            let imdct = (std::f64::consts::PI / n as f64 * (i + n0) * (k as f64 + 0.5)).cos();
+            window * imdct
+        })
+        .collect();
+
+    // The gain of the decoded samples relative to the expected samples.
+    let dot: f64 = samples.iter().zip(&expected).map(|(&s, &e)| f64::from(s) * e).sum();
+    let energy: f64 = expected.iter().map(|&e| e * e).sum();
+    let gain = dot / energy;
+
+    assert!(gain.abs() > 0.1);
+
+    for (&s, &e) in samples.iter().zip(&expected) {
+        assert!((f64::from(s) - gain * e).abs() < 1e-4 * gain.abs());
+    }
+}
+
+#[test]
+fn decode_tone_frame_aac() {
+    // AAC-LC, 48kHz, mono, with the 1024 and 960 sample frame lengths. The spectral coefficient
+    // must be transformed with the inverse MDCT of the frame length.
+    let samples_1024 = test_decode_tone_frame(&[0x11, 0x88]);
+    assert_eq!(samples_1024.len(), 1024);
+    verify_tone_frame(&samples_1024, 40);
+
+    let samples_960 = test_decode_tone_frame(&[0x11, 0x8c]);
+    assert_eq!(samples_960.len(), 960);
+    verify_tone_frame(&samples_960, 40);
+}
+
+/// Decode a single silent AAC-LC frame with the provided audio specific config, and return the
+/// number of decoded frames.
+fn test_decode_silent_frame(extra_data: &[u8]) -> symphonia_core::errors::Result<usize> {
+    let mut decoder = AacDecoder::try_new(
+        CodecParameters::new()
+            .for_codec(CODEC_TYPE_AAC)
+            .with_extra_data(extra_data.to_vec().into_boxed_slice()),
+        &DecoderOptions::default(),
+    )?;
+
+    // A single channel element with a long window and no scale factor bands, followed by the end
+    // element.
+    let packet = Packet::new_from_slice(0, 0, 0, &[0x00, 0x00, 0x00, 0x07]);
+
+    match decoder.decode(&packet)? {
+        AudioBufferRef::F32(buf) => {
+            assert!(buf.chan(0).iter().all(|&s| s == 0.0));
+            Ok(buf.frames())
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn decode_1024_frame_aac() {
+    // AAC-LC, 48kHz, mono.
+    assert_eq!(test_decode_silent_frame(&[0x11, 0x88]).unwrap(), 1024);
+}
+
+#[test]
+fn decode_960_frame_aac() {
+    // AAC-LC, 48kHz, mono, with the frame length flag set.
+    assert_eq!(test_decode_silent_frame(&[0x11, 0x8c]).unwrap(), 960);
+}
+
+#[test]
+fn verify_adts_packet_frames() {
+    // An ADTS header for a 11 byte AAC-LC, 48kHz, mono frame, followed by a silent frame.
+    let frame = [0xff, 0xf1, 0x4c, 0x40, 0x01, 0x7f, 0xfc, 0x00, 0x00, 0x00, 0x07];
+
+    let data = std::io::Cursor::new(frame.repeat(3));
+
+    let source = MediaSourceStream::new(Box::new(data), Default::default());
+
+    let mut reader = AdtsReader::try_new(source, &FormatOptions::default()).unwrap();
+
+    let params = &reader.tracks()[0].codec_params;
+    let mut decoder = AacDecoder::try_new(params, &DecoderOptions::default()).unwrap();
+
+    let mut n_packets = 0;
+
+    while let Ok(packet) = reader.next_packet() {
+        assert_eq!(packet.frames(), 1024);
+        assert_eq!(decoder.decode(&packet).unwrap().frames() as u64, packet.frames());
+        n_packets += 1;
+    }
+
+    assert_eq!(n_packets, 3);
+}
+
+/// Writes bits, most-significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    buf: Vec<u8>,
+    n_bits: u32,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, bit_width: u32) {
+        for i in (0..bit_width).rev() {
+            if self.n_bits % 8 == 0 {
+                self.buf.push(0);
+            }
+            if (value >> i) & 1 != 0 {
+                *self.buf.last_mut().unwrap() |= 0x80 >> (self.n_bits % 8);
+            }
+            self.n_bits += 1;
+        }
+    }
+
+    /// Writes an individual channel stream with a long window. If `noise` is true, the first
+    /// scale factor band is filled with noise using perceptual noise substitution, otherwise the
+    /// channel is silent.
+    fn write_ics(&mut self, noise: bool) {
+        // Global gain.
+        self.write_bits(100, 8);
+        // Reserved bit, window sequence, window shape.
+        self.write_bits(0, 4);
+        // Maximum scale factor band, and the predictor data present flag.
+        self.write_bits(if noise { 1 } else { 0 }, 6);
+        self.write_bits(0, 1);
+        if noise {
+            // A section of one band using the noise codebook, and the noise energy.
+            self.write_bits(13, 4);
+            self.write_bits(1, 5);
+            self.write_bits(256, 9);
+        }
+        // No pulse, TNS, or gain control data.
+        self.write_bits(0, 3);
+    }
+}
+
+/// Decodes a single AAC-LC frame in the 5.1 channel configuration where only the channel with
+/// the given AAC channel number (C, L, R, Ls, Rs, LFE) is not silent. Returns the channels of
+/// the audio buffer, and the indicies of the non-silent audio buffer channels.
+fn decode_5p1_frame(noisy: usize) -> (Channels, Vec<usize>) {
+    // AAC-LC, 48kHz, 5.1 channels.
+    let mut decoder = AacDecoder::try_new(
+        CodecParameters::new()
+            .for_codec(CODEC_TYPE_AAC)
+            .with_extra_data(vec![0x11, 0xb0].into_boxed_slice()),
+        &DecoderOptions::default(),
+    )
+    .unwrap();
+
+    let mut bw = BitWriter::default();
+
+    // C (SCE), L and R (CPE), Ls and Rs (CPE), and LFE.
+    for (id, channels) in [(0, 0..1), (1, 1..3), (1, 3..5), (3, 5..6)].iter().cloned() {
+        bw.write_bits(id, 3);
+        bw.write_bits(0, 4);
+        if id == 1 {
+            // No common window.
+            bw.write_bits(0, 1);
+        }
+        for ch in channels {
+            bw.write_ics(ch == noisy);
+        }
+    }
+
+    // End.
+    bw.write_bits(7, 3);
+
+    let packet = Packet::new_from_slice(0, 0, 0, &bw.buf);
+
+    match decoder.decode(&packet).unwrap() {
+        AudioBufferRef::F32(buf) => {
+            let channels = buf.spec().channels;
+            let non_silent = (0..channels.count())
+                .filter(|&ch| buf.chan(ch).iter().any(|&s| s != 0.0))
+                .collect();
+
+            (channels, non_silent)
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn decode_5p1_channel_placement() {
+    // The channels of the 5.1 channel configuration, in the order of the syntactic elements.
+    let order = [
+        Channels::FRONT_CENTRE,
+        Channels::FRONT_LEFT,
+        Channels::FRONT_RIGHT,
+        Channels::SIDE_LEFT,
+        Channels::SIDE_RIGHT,
+        Channels::LFE1,
+    ];
+
+    for (ch, &channel) in order.iter().enumerate() {
+        let (channels, non_silent) = decode_5p1_frame(ch);
+
+        // Each channel must be placed in the audio buffer channel for its channel position.
+        let expected = channels.iter().position(|c| c == channel).unwrap();
+        assert_eq!(non_silent, &[expected]);
+    }
+
+    // The LFE channel is the 4th channel.
+    assert_eq!(decode_5p1_frame(5).1, &[3]);
+}
+
+/// Builds a LOAS frame containing an audio mux element with the given access units. If
+/// `mux_version` is not `None`, the audio mux element contains a stream mux config of that version
+/// for AAC-LC, 48kHz, mono.
+fn build_loas_frame(mux_version: Option<u32>, access_units: &[&[u8]]) -> Vec<u8> {
+    let mut bw = BitWriter::default();
+
+    // Use same stream mux flag.
+    bw.write_bits(if mux_version.is_some() { 0 } else { 1 }, 1);
+
+    if let Some(version) = mux_version {
+        bw.write_bits(version, 1);
+        if version == 1 {
+            // Audio mux version A, and the 1 byte tara buffer fullness.
+            bw.write_bits(0, 1);
+            bw.write_bits(0, 2);
+            bw.write_bits(0xff, 8);
+        }
+        // All streams same time framing, number of sub-frames, programs, and layers.
+        bw.write_bits(1, 1);
+        bw.write_bits(access_units.len() as u32 - 1, 6);
+        bw.write_bits(0, 4);
+        bw.write_bits(0, 3);
+        if version == 1 {
+            // The 1 byte length of the audio specific config in bits.
+            bw.write_bits(0, 2);
+            bw.write_bits(16, 8);
+        }
+        // Audio specific config.
+        bw.write_bits(0x1188, 16);
+        // Frame length type, and the buffer fullness.
+        bw.write_bits(0, 3);
+        bw.write_bits(0xff, 8);
+        // No other data, or CRC.
+        bw.write_bits(0, 2);
+    }
+
+    for au in access_units {
+        bw.write_bits(au.len() as u32, 8);
+        for &byte in au.iter() {
+            bw.write_bits(u32::from(byte), 8);
+        }
+    }
+
+    let len = bw.buf.len();
+
+    let mut frame = vec![0x56, 0xe0 | (len >> 8) as u8, len as u8];
+    frame.extend(bw.buf);
+    frame
+}
+
+#[test]
+fn verify_loas_packets() {
+    let silent: &[u8] = &[0x00, 0x00, 0x00, 0x07];
+
+    for &version in [0, 1].iter() {
+        // An audio mux element without a stream mux config preceding the first stream mux config
+        // cannot be decoded.
+        let frames = [
+            build_loas_frame(None, &[silent]),
+            build_loas_frame(Some(version), &[silent, silent]),
+            build_loas_frame(None, &[silent, silent]),
+        ];
+
+        let data = std::io::Cursor::new(frames.concat());
+
+        let source = MediaSourceStream::new(Box::new(data), Default::default());
+
+        let mut reader = LoasReader::try_new(source, &FormatOptions::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+
+        assert_eq!(params.sample_rate, Some(48000));
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT));
+        assert_eq!(params.extra_data.as_deref(), Some(&[0x11, 0x88][..]));
+
+        let mut decoder = AacDecoder::try_new(params, &DecoderOptions::default()).unwrap();
+
+        let mut timestamps = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            assert_eq!(packet.buf(), silent);
+            assert_eq!(decoder.decode(&packet).unwrap().frames(), 1024);
+            timestamps.push(packet.ts());
+        }
+
+        assert_eq!(timestamps, &[0, 1024, 2048, 3072]);
+
+        // Seek back to the third access unit.
+        let seeked_to =
+            reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 2500, track_id: 0 });
+        assert_eq!(seeked_to.unwrap().actual_ts, 2048);
+        assert_eq!(reader.next_packet().unwrap().ts(), 2048);
+    }
+}
@@ -6,17 +6,32 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use symphonia_core::errors::{decode_error, Result};
-use symphonia_core::io::ReadBytes;
+use symphonia_core::io::{BitReaderRtl, ReadBitsRtl, ReadBytes};
 use symphonia_core::util::clamp::clamp_i16;
 
 use crate::common::{from_i16_shift, u16_to_i32, Nibble};
 
+#[rustfmt::skip]
+const IMA_INDEX_TABLE_3: [i32; 8] = [
+    -1, -1, 1, 2,
+    -1, -1, 1, 2,
+];
+
 #[rustfmt::skip]
 const IMA_INDEX_TABLE: [i32; 16] = [
     -1, -1, -1, -1, 2, 4, 6, 8,
     -1, -1, -1, -1, 2, 4, 6, 8,
 ];
 
+#[rustfmt::skip]
+const IMA_INDEX_TABLE_5: [i32; 32] = [
+    -1, -1, -1, -1, -1, -1, -1, -1, 1, 2, 4, 6, 8, 10, 13, 16,
+    -1, -1, -1, -1, -1, -1, -1, -1, 1, 2, 4, 6, 8, 10, 13, 16,
+];
+
+/// The number of samples per channel in a group of packed samples.
+const SAMPLES_PER_GROUP: usize = 32;
+
 #[rustfmt::skip]
 const IMA_STEP_TABLE: [i32; 89] = [
     7, 8, 9, 10, 11, 12, 13, 14, 16, 17,
@@ -50,14 +65,26 @@ impl AdpcmImaBlockStatus {
     }
 
     fn expand_nibble(&mut self, byte: u8, nibble: Nibble) -> i32 {
-        let nibble = nibble.get_nibble(byte);
+        self.expand_code(u32::from(nibble.get_nibble(byte)), 4)
+    }
+
+    /// Expand a code with a width of 3, 4, or 5 bits. The most-significant bit of the code is the
+    /// sign.
+    fn expand_code(&mut self, code: u32, bits: u32) -> i32 {
+        let index_table: &[i32] = match bits {
+            3 => &IMA_INDEX_TABLE_3,
+            4 => &IMA_INDEX_TABLE,
+            5 => &IMA_INDEX_TABLE_5,
+            _ => unreachable!(),
+        };
+        let shift = bits - 1;
         let step = IMA_STEP_TABLE[self.step_index as usize];
-        let sign = (nibble & 0x08) != 0;
-        let delta = (nibble & 0x07) as i32;
-        let diff = ((2 * delta + 1) * step) >> 3;
+        let sign = (code >> shift) & 1 != 0;
+        let delta = (code & ((1 << shift) - 1)) as i32;
+        let diff = ((2 * delta + 1) * step) >> shift;
         let predictor = if sign { self.predictor - diff } else { self.predictor + diff };
         self.predictor = clamp_i16(predictor) as i32;
-        self.step_index = (self.step_index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
+        self.step_index = (self.step_index + index_table[code as usize]).clamp(0, 88);
         from_i16_shift!(self.predictor)
     }
 }
@@ -100,3 +127,188 @@ pub(crate) fn decode_stereo<B: ReadBytes>(
     }
     Ok(())
 }
+
+/// Decode a block of IMA ADPCM with codes that are `BITS` wide, where `BITS` is 3 or 5.
+///
+/// After the preamble, the codes are packed, least-significant bit first, into groups of 32 codes
+/// per channel. Each group is stored as `BITS` 32-bit words, and the words of each channel are
+/// interleaved.
+fn decode_packed<B: ReadBytes, const BITS: u32>(
+    stream: &mut B,
+    buffers: &mut [&mut [i32]],
+    frames_per_block: usize,
+) -> Result<()> {
+    let group_len = 4 * BITS as usize;
+
+    let mut status = Vec::with_capacity(buffers.len());
+
+    for buffer in buffers.iter_mut() {
+        let channel_status = AdpcmImaBlockStatus::read_preamble(stream)?;
+        buffer[0] = from_i16_shift!(channel_status.predictor);
+        status.push(channel_status);
+    }
+
+    let mut group = vec![0; group_len * buffers.len()];
+    let mut codes = vec![0; group_len];
+
+    for offset in (1..frames_per_block).step_by(SAMPLES_PER_GROUP) {
+        stream.read_buf_exact(&mut group)?;
+
+        for (ch, buffer) in buffers.iter_mut().enumerate() {
+            // De-interleave the words of the channel.
+            for (word, codes) in codes.chunks_exact_mut(4).enumerate() {
+                let pos = 4 * (word * status.len() + ch);
+                codes.copy_from_slice(&group[pos..pos + 4]);
+            }
+
+            let mut bs = BitReaderRtl::new(&codes);
+
+            for sample in &mut buffer[offset..offset + SAMPLES_PER_GROUP] {
+                *sample = status[ch].expand_code(bs.read_bits_leq32(BITS)?, BITS);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn decode_mono_packed<B: ReadBytes, const BITS: u32>(
+    stream: &mut B,
+    buffer: &mut [i32],
+    frames_per_block: usize,
+) -> Result<()> {
+    decode_packed::<B, BITS>(stream, &mut [buffer], frames_per_block)
+}
+
+pub(crate) fn decode_stereo_packed<B: ReadBytes, const BITS: u32>(
+    stream: &mut B,
+    buffers: [&mut [i32]; 2],
+    frames_per_block: usize,
+) -> Result<()> {
+    let [left, right] = buffers;
+    decode_packed::<B, BITS>(stream, &mut [left, right], frames_per_block)
+}
+
+/// Returns `true` if a block with the given number of frames can be decoded when the codes are
+/// the given number of bits wide.
+pub(crate) fn is_valid_block(frames_per_block: u64, bits: u32) -> bool {
+    match bits {
+        4 => true,
+        3 | 5 => (frames_per_block - 1) % SAMPLES_PER_GROUP as u64 == 0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_ADPCM_IMA_WAV};
+    use symphonia_core::formats::Packet;
+
+    use super::*;
+    use crate::AdpcmDecoder;
+
+    fn make_decoder(channels: Channels, bits: u32, frames_per_block: u64) -> Result<AdpcmDecoder> {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_ADPCM_IMA_WAV)
+            .with_sample_rate(22050)
+            .with_channels(channels)
+            .with_bits_per_coded_sample(bits)
+            .with_frames_per_block(frames_per_block)
+            .with_max_frames_per_packet(frames_per_block);
+
+        AdpcmDecoder::try_new(&params, &Default::default())
+    }
+
+    fn decode(decoder: &mut AdpcmDecoder, buf: &[u8], frames: u64) -> Vec<Vec<i32>> {
+        let packet = Packet::new_from_slice(0, 0, frames, buf);
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => (0..buf.spec().channels.count())
+                .map(|ch| buf.chan(ch).iter().map(|&s| s >> 16).collect())
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn verify_expand_code() {
+        let mut status = AdpcmImaBlockStatus { predictor: 0, step_index: 0 };
+
+        // 3-bit: (7 * 7) >> 2 = 12, then the step index is 2, and 12 - (7 * 9) >> 2 = -3.
+        assert_eq!(status.expand_code(0b011, 3) >> 16, 12);
+        assert_eq!(status.expand_code(0b111, 3) >> 16, -3);
+        assert_eq!(status.step_index, 4);
+
+        // 4-bit: (15 * 7) >> 3 = 13, then the step index is 8, and 13 - (15 * 16) >> 3 = -17.
+        let mut status = AdpcmImaBlockStatus { predictor: 0, step_index: 0 };
+        assert_eq!(status.expand_nibble(0xf7, Nibble::Lower) >> 16, 13);
+        assert_eq!(status.expand_nibble(0xf7, Nibble::Upper) >> 16, -17);
+        assert_eq!(status.step_index, 16);
+
+        // 5-bit: (31 * 7) >> 4 = 13, then the step index is 16, and 13 - (31 * 34) >> 4 = -52.
+        let mut status = AdpcmImaBlockStatus { predictor: 0, step_index: 0 };
+        assert_eq!(status.expand_code(0x0f, 5) >> 16, 13);
+        assert_eq!(status.expand_code(0x1f, 5) >> 16, -52);
+        assert_eq!(status.step_index, 32);
+    }
+
+    #[test]
+    fn verify_decode_3bit_mono() {
+        let mut decoder = make_decoder(Channels::FRONT_LEFT, 3, 33).unwrap();
+
+        // The preamble, followed by one group of 32 codes packed into 3 words. The first codes are
+        // 0b011 and 0b111, and the rest are 0.
+        let mut buf = vec![0, 0, 0, 0, 0x3b];
+        buf.resize(16, 0);
+
+        let samples = decode(&mut decoder, &buf, 33);
+
+        assert_eq!(samples[0].len(), 33);
+        // The third sample is -3 + (11 >> 2) = -1.
+        assert_eq!(&samples[0][..4], &[0, 12, -3, -1]);
+    }
+
+    #[test]
+    fn verify_decode_4bit_stereo() {
+        let mut decoder = make_decoder(Channels::FRONT_LEFT | Channels::FRONT_RIGHT, 4, 9).unwrap();
+
+        // Two preambles, followed by one word of 8 codes per channel.
+        let mut buf = vec![0x64, 0x00, 0x00, 0x00, 0x9c, 0xff, 0x00, 0x00];
+        buf.extend_from_slice(&[0xf7, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00]);
+
+        let samples = decode(&mut decoder, &buf, 9);
+
+        // 100 + 13 = 113, then 113 - 30 = 83, and -100 + 13 = -87, then -87 + (16 >> 3) = -85.
+        assert_eq!(&samples[0][..3], &[100, 113, 83]);
+        assert_eq!(&samples[1][..3], &[-100, -87, -85]);
+    }
+
+    #[test]
+    fn verify_decode_5bit_stereo() {
+        let mut decoder =
+            make_decoder(Channels::FRONT_LEFT | Channels::FRONT_RIGHT, 5, 33).unwrap();
+
+        // Two preambles, followed by one group of 32 codes per channel packed into 5 words, with
+        // the words of the channels interleaved. The first codes of the left channel are 0x0f and
+        // 0x1f, and the first codes of the right channel are 0x08 and 0x08.
+        let mut buf = vec![0; 8];
+        buf.extend_from_slice(&[0xef, 0x03, 0x00, 0x00, 0x08, 0x01, 0x00, 0x00]);
+        buf.resize(8 + 40, 0);
+
+        let samples = decode(&mut decoder, &buf, 33);
+
+        // The third left sample is -52 + (157 >> 4) = -43.
+        assert_eq!(&samples[0][..4], &[0, 13, -52, -43]);
+        // (17 * 7) >> 4 = 7, then the step index is 1, and 7 + (17 * 8) >> 4 = 15.
+        assert_eq!(&samples[1][..4], &[0, 7, 15, 15]);
+    }
+
+    #[test]
+    fn verify_invalid_code_width() {
+        assert!(make_decoder(Channels::FRONT_LEFT, 6, 33).is_err());
+        // Blocks of 3 and 5-bit codes must contain whole groups of 32 codes.
+        assert!(make_decoder(Channels::FRONT_LEFT, 3, 32).is_err());
+        assert!(make_decoder(Channels::FRONT_LEFT, 5, 65).is_ok());
+    }
+}
@@ -0,0 +1,200 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::ReadBytes;
+use symphonia_core::util::clamp::clamp_i16;
+
+use crate::common::{from_i16_shift, Nibble};
+
+/// The number of frames (samples per channel) in a PSX ADPCM block.
+pub(crate) const FRAMES_PER_BLOCK: usize = 28;
+
+/// The number of bytes per channel in a PSX ADPCM block.
+pub(crate) const BYTES_PER_BLOCK: usize = 16;
+
+/// The prediction filter coefficients, in units of 1/64.
+const PSX_FILTER_TABLE: [[i32; 2]; 5] = [[0, 0], [60, 0], [115, -52], [98, -55], [122, -60]];
+
+/// The loop end flag. Playback stops, or jumps to the loop start, after a block with this flag.
+const FLAG_LOOP_END: u8 = 0x1;
+
+/// A block with all flags set (loop end, loop repeat, and loop start) marks the end of the stream
+/// and contains no audio.
+const FLAG_END_MARKER: u8 = 0x7;
+
+/// The decoding state of a single channel.
+#[derive(Default)]
+pub(crate) struct AdpcmPsxChannelStatus {
+    /// The two previously decoded samples, most recent first.
+    history: [i32; 2],
+}
+
+impl AdpcmPsxChannelStatus {
+    fn expand_nibble(&mut self, byte: u8, nibble: Nibble, shift: u8, filter: &[i32; 2]) -> i32 {
+        // Sign-extend the 4-bit nibble, and scale it by the shift.
+        let nibble = ((nibble.get_nibble(byte) as i32) << 28) >> 28;
+        let delta = (nibble << 12) >> shift;
+
+        let prediction = (self.history[0] * filter[0] + self.history[1] * filter[1] + 32) >> 6;
+        let sample = clamp_i16(delta + prediction) as i32;
+
+        self.history = [sample, self.history[0]];
+        from_i16_shift!(sample)
+    }
+}
+
+/// The result of decoding a PSX ADPCM block.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AdpcmPsxBlock {
+    /// The block was decoded.
+    Decoded,
+    /// The block was decoded and is the last block of the stream.
+    DecodedLast,
+    /// The block is an end-of-stream marker and was not decoded.
+    EndMarker,
+}
+
+/// Decode one PSX ADPCM block of a single channel into `buffer`.
+pub(crate) fn decode_block<B: ReadBytes>(
+    stream: &mut B,
+    status: &mut AdpcmPsxChannelStatus,
+    buffer: &mut [i32],
+) -> Result<AdpcmPsxBlock> {
+    let header = stream.read_u8()?;
+    let flags = stream.read_u8()?;
+
+    let mut data = [0; BYTES_PER_BLOCK - 2];
+    stream.read_buf_exact(&mut data)?;
+
+    if flags == FLAG_END_MARKER {
+        return Ok(AdpcmPsxBlock::EndMarker);
+    }
+
+    let filter = match PSX_FILTER_TABLE.get(usize::from(header >> 4)) {
+        Some(filter) => filter,
+        None => return decode_error("adpcm (psx): invalid filter"),
+    };
+
+    // Shifts 13 to 15 are reserved, and behave like a shift of 9 on the PlayStation.
+    let shift = match header & 0xf {
+        shift @ 0..=12 => shift,
+        _ => 9,
+    };
+
+    for (i, &byte) in data.iter().enumerate() {
+        buffer[2 * i] = status.expand_nibble(byte, Nibble::Lower, shift, filter);
+        buffer[2 * i + 1] = status.expand_nibble(byte, Nibble::Upper, shift, filter);
+    }
+
+    if flags & FLAG_LOOP_END != 0 {
+        Ok(AdpcmPsxBlock::DecodedLast)
+    }
+    else {
+        Ok(AdpcmPsxBlock::Decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_ADPCM_PSX};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::BufReader;
+
+    use super::*;
+    use crate::AdpcmDecoder;
+
+    fn block(header: u8, flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut block = vec![header, flags];
+        block.extend_from_slice(data);
+        block.resize(BYTES_PER_BLOCK, 0);
+        block
+    }
+
+    #[test]
+    fn verify_decode_block() {
+        // Filter 0, shift 8. The last sample is 7 << 4.
+        let mut first = [0; 14];
+        first[0] = 0x7f;
+        first[1] = 0x08;
+        first[13] = 0x70;
+
+        // Filter 1, shift 12, and the loop end flag.
+        let mut buf = block(0x08, 0x00, &first);
+        buf.extend(block(0x1c, FLAG_LOOP_END, &[0x01]));
+        buf.extend(block(0x00, FLAG_END_MARKER, &[]));
+
+        let mut stream = BufReader::new(&buf);
+        let mut status = AdpcmPsxChannelStatus::default();
+        let mut out = [0; FRAMES_PER_BLOCK];
+
+        let result = decode_block(&mut stream, &mut status, &mut out).unwrap();
+        assert_eq!(result, AdpcmPsxBlock::Decoded);
+
+        let samples: Vec<i32> = out.iter().map(|&s| s >> 16).collect();
+        assert_eq!(&samples[..4], &[-16, 112, -128, 0]);
+        assert_eq!(samples[27], 112);
+
+        let result = decode_block(&mut stream, &mut status, &mut out).unwrap();
+        assert_eq!(result, AdpcmPsxBlock::DecodedLast);
+
+        // 1 + (112 * 60 + 32) / 64 = 106, then (106 * 60 + 32) / 64 = 99.
+        assert_eq!(out[0] >> 16, 106);
+        assert_eq!(out[1] >> 16, 99);
+
+        let result = decode_block(&mut stream, &mut status, &mut out).unwrap();
+        assert_eq!(result, AdpcmPsxBlock::EndMarker);
+    }
+
+    #[test]
+    fn verify_decoder_stops_at_loop_end() {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_ADPCM_PSX)
+            .with_sample_rate(44100)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT)
+            .with_max_frames_per_packet(4 * FRAMES_PER_BLOCK as u64);
+
+        let mut decoder = AdpcmDecoder::try_new(&params, &Default::default()).unwrap();
+
+        // Two stereo blocks, where the second ends the stream, followed by padding.
+        let mut buf = block(0x0c, 0x00, &[0x11]);
+        buf.extend(block(0x0c, 0x00, &[0x22]));
+        buf.extend(block(0x0c, FLAG_LOOP_END, &[]));
+        buf.extend(block(0x0c, FLAG_LOOP_END, &[]));
+        buf.extend(block(0x0c, 0x00, &[0x33]));
+        buf.extend(block(0x0c, 0x00, &[0x33]));
+
+        let packet = Packet::new_from_slice(0, 0, 3 * FRAMES_PER_BLOCK as u64, &buf);
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                assert_eq!(buf.frames(), 2 * FRAMES_PER_BLOCK);
+                assert_eq!(buf.chan(0)[0] >> 16, 1);
+                assert_eq!(buf.chan(1)[0] >> 16, 2);
+            }
+            _ => unreachable!(),
+        }
+
+        // Nothing is decoded after the end of the stream until the decoder is reset.
+        assert_eq!(decoder.decode(&packet).unwrap().frames(), 0);
+
+        decoder.reset();
+        assert_eq!(decoder.decode(&packet).unwrap().frames(), 2 * FRAMES_PER_BLOCK);
+    }
+
+    #[test]
+    fn verify_invalid_filter() {
+        let buf = block(0x50, 0x00, &[]);
+
+        let mut status = AdpcmPsxChannelStatus::default();
+        let mut out = [0; FRAMES_PER_BLOCK];
+
+        assert!(decode_block(&mut BufReader::new(&buf), &mut status, &mut out).is_err());
+    }
+}
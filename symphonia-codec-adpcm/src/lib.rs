@@ -17,6 +17,8 @@
 use symphonia_core::support_codec;
 
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+#[cfg(feature = "psx")]
+use symphonia_core::codecs::CODEC_TYPE_ADPCM_PSX;
 use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CodecType};
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
 use symphonia_core::codecs::{CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS};
@@ -26,22 +28,43 @@ use symphonia_core::io::ReadBytes;
 
 mod codec_ima;
 mod codec_ms;
+#[cfg(feature = "psx")]
+mod codec_psx;
 mod common;
 
 fn is_supported_adpcm_codec(codec_type: CodecType) -> bool {
-    matches!(codec_type, CODEC_TYPE_ADPCM_MS | CODEC_TYPE_ADPCM_IMA_WAV)
+    match codec_type {
+        CODEC_TYPE_ADPCM_MS | CODEC_TYPE_ADPCM_IMA_WAV => true,
+        #[cfg(feature = "psx")]
+        CODEC_TYPE_ADPCM_PSX => true,
+        _ => false,
+    }
 }
 
+#[allow(clippy::enum_variant_names)]
 enum InnerDecoder {
     AdpcmMs,
-    AdpcmIma,
+    /// IMA ADPCM with codes that are 3, 4, or 5 bits wide.
+    AdpcmIma {
+        bits: u32,
+    },
+    /// PSX ADPCM is decoded continuously across blocks and packets, so it is stateful.
+    #[cfg(feature = "psx")]
+    AdpcmPsx {
+        status: Vec<codec_psx::AdpcmPsxChannelStatus>,
+        ended: bool,
+    },
 }
 
 impl InnerDecoder {
     fn decode_mono_fn<B: ReadBytes>(&self) -> impl Fn(&mut B, &mut [i32], usize) -> Result<()> {
         match *self {
             InnerDecoder::AdpcmMs => codec_ms::decode_mono,
-            InnerDecoder::AdpcmIma => codec_ima::decode_mono,
+            InnerDecoder::AdpcmIma { bits: 3 } => codec_ima::decode_mono_packed::<B, 3>,
+            InnerDecoder::AdpcmIma { bits: 5 } => codec_ima::decode_mono_packed::<B, 5>,
+            InnerDecoder::AdpcmIma { .. } => codec_ima::decode_mono,
+            #[cfg(feature = "psx")]
+            InnerDecoder::AdpcmPsx { .. } => unreachable!(),
         }
     }
 
@@ -50,7 +73,11 @@ impl InnerDecoder {
     ) -> impl Fn(&mut B, [&mut [i32]; 2], usize) -> Result<()> {
         match *self {
             InnerDecoder::AdpcmMs => codec_ms::decode_stereo,
-            InnerDecoder::AdpcmIma => codec_ima::decode_stereo,
+            InnerDecoder::AdpcmIma { bits: 3 } => codec_ima::decode_stereo_packed::<B, 3>,
+            InnerDecoder::AdpcmIma { bits: 5 } => codec_ima::decode_stereo_packed::<B, 5>,
+            InnerDecoder::AdpcmIma { .. } => codec_ima::decode_stereo,
+            #[cfg(feature = "psx")]
+            InnerDecoder::AdpcmPsx { .. } => unreachable!(),
         }
     }
 }
@@ -63,7 +90,69 @@ pub struct AdpcmDecoder {
 }
 
 impl AdpcmDecoder {
+    /// Decode a packet of PSX ADPCM blocks. Each block contains 16 bytes per channel, with the
+    /// channels interleaved.
+    #[cfg(feature = "psx")]
+    fn decode_psx(&mut self, packet: &Packet) -> Result<()> {
+        use codec_psx::{AdpcmPsxBlock, BYTES_PER_BLOCK, FRAMES_PER_BLOCK};
+
+        let (status, ended) = match &mut self.inner_decoder {
+            InnerDecoder::AdpcmPsx { status, ended } => (status, ended),
+            _ => unreachable!(),
+        };
+
+        self.buf.clear();
+
+        // Any blocks following the last block of the stream are padding.
+        if *ended {
+            return Ok(());
+        }
+
+        let mut stream = packet.as_buf_reader();
+
+        let block_count = packet.buf().len() / (BYTES_PER_BLOCK * status.len());
+
+        self.buf.render_reserved(Some(block_count * FRAMES_PER_BLOCK));
+
+        let mut n_frames = 0;
+
+        for _ in 0..block_count {
+            let range = n_frames..n_frames + FRAMES_PER_BLOCK;
+
+            let mut is_last = false;
+            let mut is_end_marker = false;
+
+            for (ch, status) in status.iter_mut().enumerate() {
+                let buffer = &mut self.buf.chan_mut(ch)[range.clone()];
+
+                match codec_psx::decode_block(&mut stream, status, buffer)? {
+                    AdpcmPsxBlock::Decoded => (),
+                    AdpcmPsxBlock::DecodedLast => is_last = true,
+                    AdpcmPsxBlock::EndMarker => is_end_marker = true,
+                }
+            }
+
+            if !is_end_marker {
+                n_frames += FRAMES_PER_BLOCK;
+            }
+
+            if is_last || is_end_marker {
+                *ended = true;
+                break;
+            }
+        }
+
+        self.buf.truncate(n_frames);
+
+        Ok(())
+    }
+
     fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        #[cfg(feature = "psx")]
+        if let InnerDecoder::AdpcmPsx { .. } = self.inner_decoder {
+            return self.decode_psx(packet);
+        }
+
         let mut stream = packet.as_buf_reader();
 
         let frames_per_block = self.params.frames_per_block.unwrap() as usize;
@@ -115,7 +204,15 @@ impl Decoder for AdpcmDecoder {
             _ => return unsupported_error("adpcm: maximum frames per packet is required"),
         };
 
-        if params.frames_per_block.is_none() || params.frames_per_block.unwrap() == 0 {
+        // PSX ADPCM always has a fixed number of frames per block.
+        #[cfg(feature = "psx")]
+        let is_fixed_block = params.codec == CODEC_TYPE_ADPCM_PSX;
+        #[cfg(not(feature = "psx"))]
+        let is_fixed_block = false;
+
+        if !is_fixed_block
+            && (params.frames_per_block.is_none() || params.frames_per_block.unwrap() == 0)
+        {
             return unsupported_error("adpcm: valid frames per block is required");
         }
 
@@ -136,7 +233,21 @@ impl Decoder for AdpcmDecoder {
 
         let inner_decoder = match params.codec {
             CODEC_TYPE_ADPCM_MS => InnerDecoder::AdpcmMs,
-            CODEC_TYPE_ADPCM_IMA_WAV => InnerDecoder::AdpcmIma,
+            CODEC_TYPE_ADPCM_IMA_WAV => {
+                let bits = params.bits_per_coded_sample.unwrap_or(4);
+
+                // Non-standard code widths pack the codes into groups of 32 samples per channel.
+                if !codec_ima::is_valid_block(params.frames_per_block.unwrap(), bits) {
+                    return unsupported_error("adpcm: invalid ima adpcm code width or block size");
+                }
+
+                InnerDecoder::AdpcmIma { bits }
+            }
+            #[cfg(feature = "psx")]
+            CODEC_TYPE_ADPCM_PSX => {
+                let status = (0..spec.channels.count()).map(|_| Default::default()).collect();
+                InnerDecoder::AdpcmPsx { status, ended: false }
+            }
             _ => return unsupported_error("adpcm: codec is unsupported"),
         };
 
@@ -151,11 +262,18 @@ impl Decoder for AdpcmDecoder {
         &[
             support_codec!(CODEC_TYPE_ADPCM_MS, "adpcm_ms", "Microsoft ADPCM"),
             support_codec!(CODEC_TYPE_ADPCM_IMA_WAV, "adpcm_ima_wav", "ADPCM IMA WAV"),
+            #[cfg(feature = "psx")]
+            support_codec!(CODEC_TYPE_ADPCM_PSX, "adpcm_psx", "Sony PlayStation ADPCM"),
         ]
     }
 
     fn reset(&mut self) {
-        // No state is stored between packets, therefore do nothing.
+        // Only PSX ADPCM stores state between packets.
+        #[cfg(feature = "psx")]
+        if let InnerDecoder::AdpcmPsx { status, ended } = &mut self.inner_decoder {
+            status.iter_mut().for_each(|status| *status = Default::default());
+            *ended = false;
+        }
     }
 
     fn codec_params(&self) -> &CodecParameters {
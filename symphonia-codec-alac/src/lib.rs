@@ -17,6 +17,7 @@
 #![allow(clippy::collapsible_else_if)]
 
 use std::cmp::min;
+use std::convert::TryInto;
 
 use symphonia_core::audio::{
     AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec,
@@ -69,21 +70,34 @@ enum ChannelLayout {
     /// Centre, Front Left of Centre, Front Right of Centre, Front Left, Front Right, Side Left,
     /// Side Right, LFE
     Mpeg7p1B,
+    /// The given number of discrete channels without positions.
+    Discrete(u8),
 }
 
+/// The maximum number of discrete channels.
+const MAX_DISCRETE_CHANNELS: u8 = 26;
+
 impl ChannelLayout {
     /// Given the current ALAC channel layout, this function will return a mappings of an ALAC
     /// channel number (the index into the array) to a Symphonia `AudioBuffer` channel index.
-    fn channel_map(&self) -> [u8; 8] {
+    ///
+    /// Discrete channels are mapped in the order they are decoded.
+    fn channel_map(&self) -> &'static [u8] {
+        const IDENTITY: [u8; MAX_DISCRETE_CHANNELS as usize] = [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+            24, 25,
+        ];
+
         match self {
-            ChannelLayout::Mono => [0, 0, 0, 0, 0, 0, 0, 0],
-            ChannelLayout::Stereo => [0, 1, 0, 0, 0, 0, 0, 0],
-            ChannelLayout::Mpeg3p0B => [2, 0, 1, 0, 0, 0, 0, 0],
-            ChannelLayout::Mpeg4p0B => [2, 0, 1, 3, 0, 0, 0, 0],
-            ChannelLayout::Mpeg5p0D => [2, 0, 1, 3, 4, 0, 0, 0],
-            ChannelLayout::Mpeg5p1D => [2, 0, 1, 4, 5, 3, 0, 0],
-            ChannelLayout::Aac6p1 => [2, 0, 1, 5, 6, 4, 3, 0],
-            ChannelLayout::Mpeg7p1B => [2, 4, 5, 0, 1, 6, 7, 3],
+            ChannelLayout::Mono => &[0],
+            ChannelLayout::Stereo => &[0, 1],
+            ChannelLayout::Mpeg3p0B => &[2, 0, 1],
+            ChannelLayout::Mpeg4p0B => &[2, 0, 1, 3],
+            ChannelLayout::Mpeg5p0D => &[2, 0, 1, 3, 4],
+            ChannelLayout::Mpeg5p1D => &[2, 0, 1, 4, 5, 3],
+            ChannelLayout::Aac6p1 => &[2, 0, 1, 5, 6, 4, 3],
+            ChannelLayout::Mpeg7p1B => &[2, 4, 5, 0, 1, 6, 7, 3],
+            ChannelLayout::Discrete(count) => &IDENTITY[..usize::from(*count)],
         }
     }
 
@@ -135,6 +149,7 @@ impl ChannelLayout {
                     | Channels::SIDE_RIGHT
                     | Channels::LFE1
             }
+            ChannelLayout::Discrete(count) => Channels::discrete(usize::from(*count)).unwrap(),
         }
     }
 }
@@ -189,10 +204,9 @@ impl MagicCookie {
             return decode_error("alac: invalid bit depth");
         }
 
-        // Only 8 channel layouts exist.
-        // TODO: Support discrete/auxiliary channels.
-        if config.num_channels < 1 || config.num_channels > 8 {
-            return unsupported_error("alac: more than 8 channels");
+        // Only 8 channel layouts exist. Additional channels are discrete.
+        if config.num_channels < 1 || config.num_channels > MAX_DISCRETE_CHANNELS {
+            return unsupported_error("alac: unsupported number of channels");
         }
 
         // If the magic cookie is 48 bytes, the channel layout is explictly set, otherwise select a
@@ -252,11 +266,12 @@ impl MagicCookie {
         }
         else {
             // If extra channel information is not provided, use the number of channels to assign
-            // a channel layout.
+            // a channel layout. If there are more channels than any channel layout has, then the
+            // channels are discrete.
             //
             // TODO: If the number of channels is > 2, then the additional channels are considered
-            // discrete and not part of a channel layout. However, Symphonia does not support
-            // discrete/auxiliary channels so the standard ALAC channel layouts are used for now.
+            // discrete and not part of a channel layout. However, the standard ALAC channel
+            // layouts are used for up-to 8 channels for now.
             match config.num_channels {
                 1 => ChannelLayout::Mono,
                 2 => ChannelLayout::Stereo,
@@ -266,7 +281,7 @@ impl MagicCookie {
                 6 => ChannelLayout::Mpeg5p1D,
                 7 => ChannelLayout::Aac6p1,
                 8 => ChannelLayout::Mpeg7p1B,
-                _ => return decode_error("alac: unknown channel layout for number of channels"),
+                count => ChannelLayout::Discrete(count),
             }
         };
 
@@ -398,67 +413,23 @@ impl ElementChannel {
             out[i] = clip_msbs(out[i].wrapping_add(out[i - 1]), num_clip_bits);
         }
 
-        // Do the prediction.
-        //
-        // TODO: Orders for 4 and 8 are special-cased in the reference decoder. Consider using
-        // optimized versions for those cases like the FLAC decoder does.
-        for i in 1 + order..out.len() {
-            // Value of the output sample before prediction (the residual or difference).
-            let mut res = out[i];
-
-            // Value of the sample preceeding the first past sample.
-            let past0 = out[i - order - 1];
-
-            // Run the FIR filter.
-            let sum = self.lpc_coeffs[..order]
-                .iter()
-                .rev()
-                .zip(&out[i - order..i])
-                .map(|(&coeff, &s)| coeff.wrapping_mul(s - past0))
-                .fold(0i32, |sum, s| sum.wrapping_add(s));
-
-            // Rewrite `1 << (self.shift - 1)` as `(1 << self.shift) >> 1` to prevent overflowing
-            // when shift is 0.
-            let val = (sum + ((1 << self.shift) >> 1)) >> self.shift;
-            out[i] = clip_msbs(out[i].wrapping_add(past0).wrapping_add(val), num_clip_bits);
-
-            // Adjust the coefficients if the initial value of the residual was not 0.
-            if res != 0 {
-                let iter =
-                    self.lpc_coeffs[..order].iter_mut().rev().zip(&out[i - order..i]).enumerate();
-
-                // Note the subtle change in operations and signs for the following two cases.
-                if res > 0 {
-                    // Positive residual case.
-                    for (j, (coeff, &sample)) in iter {
-                        let val = past0 - sample;
-                        let sign = val.signum();
-
-                        *coeff -= sign;
-
-                        res -= (1 + j as i32) * ((sign * val) >> self.shift);
-
-                        if res <= 0 {
-                            break;
-                        }
-                    }
-                }
-                else {
-                    // Negative residual case.
-                    for (j, (coeff, &sample)) in iter {
-                        let val = past0 - sample;
-                        let sign = val.signum();
-
-                        *coeff += sign;
-
-                        res -= (1 + j as i32) * ((-sign * val) >> self.shift);
-
-                        if res >= 0 {
-                            break;
-                        }
-                    }
-                }
-            }
+        // Do the prediction. Orders 4 and 8 are the most common, and are special-cased in the
+        // reference decoder. Specialize the predictor for these orders so that its inner loops may
+        // be unrolled. All other orders use the generic predictor.
+        match order {
+            4 => adaptive_predict_fixed::<4>(
+                (&mut self.lpc_coeffs[..4]).try_into().unwrap(),
+                self.shift,
+                num_clip_bits,
+                out,
+            ),
+            8 => adaptive_predict_fixed::<8>(
+                (&mut self.lpc_coeffs[..8]).try_into().unwrap(),
+                self.shift,
+                num_clip_bits,
+                out,
+            ),
+            _ => adaptive_predict(&mut self.lpc_coeffs[..order], self.shift, num_clip_bits, out),
         }
 
         Ok(())
@@ -474,6 +445,8 @@ pub struct AlacDecoder {
     tail_bits: Vec<u16>,
     /// ALAC codec-specific configuration.
     config: MagicCookie,
+    /// If true, the samples are not shifted up to the full range of the output buffer.
+    native_bit_depth: bool,
     /// Output buffer.
     buf: AudioBuffer<i32>,
 }
@@ -568,10 +541,11 @@ impl AlacDecoder {
         self.buf.truncate(num_frames);
 
         // The audio buffer is always signed 32-bit, but the actual bit-depth may be smaller. If
-        // the bit-depth is less-than 32, shift the final samples up.
+        // the bit-depth is less-than 32, shift the final samples up unless the native bit-depth
+        // was requested.
         let shift = 32 - self.config.bit_depth;
 
-        if shift > 0 {
+        if shift > 0 && !self.native_bit_depth {
             self.buf.transform(|sample| sample << shift);
         }
 
@@ -580,7 +554,7 @@ impl AlacDecoder {
 }
 
 impl Decoder for AlacDecoder {
-    fn try_new(params: &CodecParameters, _: &DecoderOptions) -> Result<Self> {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
         // Verify codec type.
         if params.codec != CODEC_TYPE_ALAC {
             return unsupported_error("alac: invalid codec type");
@@ -599,7 +573,17 @@ impl Decoder for AlacDecoder {
 
         let max_tail_values = min(2, config.num_channels) as usize * config.frame_length as usize;
 
-        Ok(AlacDecoder { params: params.clone(), tail_bits: vec![0; max_tail_values], buf, config })
+        // The bit-depth of the decoded audio is given by the magic cookie.
+        let mut params = params.clone();
+        params.with_bits_per_sample(u32::from(config.bit_depth));
+
+        Ok(AlacDecoder {
+            params,
+            tail_bits: vec![0; max_tail_values],
+            config,
+            native_bit_depth: options.enable_native_bit_depth,
+            buf,
+        })
     }
 
     fn reset(&mut self) {
@@ -614,6 +598,14 @@ impl Decoder for AlacDecoder {
         &self.params
     }
 
+    fn output_spec(&self) -> Option<SignalSpec> {
+        Some(*self.buf.spec())
+    }
+
+    fn max_frames_per_packet(&self) -> Option<u64> {
+        Some(self.buf.capacity() as u64)
+    }
+
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
         if let Err(e) = self.decode_inner(packet) {
             self.buf.clear();
@@ -771,18 +763,15 @@ fn read_rice_code<B: ReadBitsLtr>(bs: &mut B, k: u32, kb: u32) -> Result<u32> {
         // `m` is always `(1<<k)-1` which is `2^k - 1`. This can be rewritten using a bit-shift.
         let value = (prefix << k) - prefix;
 
-        // Ideally, we need to read, but not consume, `k` bits here. This is because if the value is
-        // less-than 2 we must only consume `k-1` bits. The bit reader does not support peeking,
-        // therefore, we read the `k-1` top-most bits. If the value is > 0, then the `k`-bit value
-        // would be > 2. In that case, we'll then read the least-significant bit in a second read
-        // operation.
-        let suffix = bs.read_bits_leq32(k - 1)?;
+        // Peek the `k`-bit suffix since if it is less-than 2 we must only consume `k-1` bits.
+        let suffix = bs.peek_bits_leq32(k)?;
 
-        if suffix > 0 {
-            // Shift suffix left by 1 because it is missing its LSb, and then read the missing bit.
-            value + (suffix << 1) + bs.read_bit()? - 1
+        if suffix > 1 {
+            bs.ignore_bits(k)?;
+            value + suffix - 1
         }
         else {
+            bs.ignore_bits(k - 1)?;
             value
         }
     }
@@ -810,6 +799,82 @@ fn clip_msbs(val: i32, num: u32) -> i32 {
     (val << num) >> num
 }
 
+/// Runs the adaptive FIR predictor of order `coeffs.len()` over `out`. The coefficients are adapted
+/// as each sample is predicted. The first `order + 1` samples of `out` must be warm-up samples.
+#[inline(always)]
+fn adaptive_predict(coeffs: &mut [i32], shift: u32, num_clip_bits: u32, out: &mut [i32]) {
+    let order = coeffs.len();
+
+    for i in 1 + order..out.len() {
+        // Value of the output sample before prediction (the residual or difference).
+        let mut res = out[i];
+
+        // Value of the sample preceeding the first past sample.
+        let past0 = out[i - order - 1];
+
+        // Run the FIR filter.
+        let sum = coeffs
+            .iter()
+            .rev()
+            .zip(&out[i - order..i])
+            .map(|(&coeff, &s)| coeff.wrapping_mul(s - past0))
+            .fold(0i32, |sum, s| sum.wrapping_add(s));
+
+        // Rewrite `1 << (shift - 1)` as `(1 << shift) >> 1` to prevent overflowing when shift is
+        // 0.
+        let val = (sum + ((1 << shift) >> 1)) >> shift;
+        out[i] = clip_msbs(out[i].wrapping_add(past0).wrapping_add(val), num_clip_bits);
+
+        // Adjust the coefficients if the initial value of the residual was not 0.
+        if res != 0 {
+            let iter = coeffs.iter_mut().rev().zip(&out[i - order..i]).enumerate();
+
+            // Note the subtle change in operations and signs for the following two cases.
+            if res > 0 {
+                // Positive residual case.
+                for (j, (coeff, &sample)) in iter {
+                    let val = past0 - sample;
+                    let sign = val.signum();
+
+                    *coeff -= sign;
+
+                    res -= (1 + j as i32) * ((sign * val) >> shift);
+
+                    if res <= 0 {
+                        break;
+                    }
+                }
+            }
+            else {
+                // Negative residual case.
+                for (j, (coeff, &sample)) in iter {
+                    let val = past0 - sample;
+                    let sign = val.signum();
+
+                    *coeff += sign;
+
+                    res -= (1 + j as i32) * ((-sign * val) >> shift);
+
+                    if res >= 0 {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs the adaptive FIR predictor with a fixed order of `N`. Since the order is known at
+/// compile-time, the inner loops of the predictor may be unrolled.
+fn adaptive_predict_fixed<const N: usize>(
+    coeffs: &mut [i32; N],
+    shift: u32,
+    num_clip_bits: u32,
+    out: &mut [i32],
+) {
+    adaptive_predict(coeffs, shift, num_clip_bits, out)
+}
+
 /// Decorrelates a mid-side channel pair.
 fn decorrelate_mid_side(out0: &mut [i32], out1: &mut [i32], weight: i32, shift: u8) {
     assert!(out0.len() == out1.len());
@@ -819,3 +884,226 @@ fn decorrelate_mid_side(out0: &mut [i32], out1: &mut [i32], weight: i32, shift:
         *s1 = *s0 - *s1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_ALAC};
+    use symphonia_core::formats::Packet;
+
+    use super::{adaptive_predict, adaptive_predict_fixed, AlacDecoder};
+
+    /// Writes bits, most-significant bit first.
+    #[derive(Default)]
+    struct BitWriter {
+        buf: Vec<u8>,
+        n_bits: u32,
+    }
+
+    impl BitWriter {
+        fn write_bits(&mut self, value: u32, bit_width: u32) {
+            for i in (0..bit_width).rev() {
+                if self.n_bits % 8 == 0 {
+                    self.buf.push(0);
+                }
+                if (value >> i) & 1 != 0 {
+                    *self.buf.last_mut().unwrap() |= 0x80 >> (self.n_bits % 8);
+                }
+                self.n_bits += 1;
+            }
+        }
+    }
+
+    const FRAME_LEN: u32 = 4;
+
+    /// Instantiates a decoder for 44.1kHz audio with the given bit-depth and number of channels.
+    /// The magic cookie does not have an explicit channel layout.
+    fn make_decoder(bit_depth: u8, num_channels: u8, options: &DecoderOptions) -> AlacDecoder {
+        let mut cookie = FRAME_LEN.to_be_bytes().to_vec();
+        cookie.extend_from_slice(&[0, bit_depth, 40, 10, 14, num_channels]);
+        cookie.extend_from_slice(&255u16.to_be_bytes());
+        cookie.extend_from_slice(&[0; 8]);
+        cookie.extend_from_slice(&44100u32.to_be_bytes());
+
+        AlacDecoder::try_new(
+            CodecParameters::new()
+                .for_codec(CODEC_TYPE_ALAC)
+                .with_extra_data(cookie.into_boxed_slice()),
+            options,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn verify_5p1_channel_placement() {
+        // A 16-bit, 6 channel stream.
+        let mut decoder = make_decoder(16, 6, &DecoderOptions::default());
+
+        // Encode uncompressed elements where every sample of a channel is the 1-based index of
+        // the channel in the 5.1 channel layout: C (SCE), L and R (CPE), Ls and Rs (CPE), and LFE.
+        let mut bw = BitWriter::default();
+
+        for &(tag, values) in [(0, &[3][..]), (1, &[1, 2]), (1, &[5, 6]), (3, &[4])].iter() {
+            // Element tag, instance tag, and unused header bits.
+            bw.write_bits(tag, 3);
+            bw.write_bits(0, 4);
+            bw.write_bits(0, 12);
+            // Not a partial frame, no shift, and uncompressed.
+            bw.write_bits(0b0001, 4);
+
+            for _ in 0..FRAME_LEN {
+                for &value in values {
+                    bw.write_bits(value, 16);
+                }
+            }
+        }
+
+        // End element.
+        bw.write_bits(7, 3);
+
+        let packet = Packet::new_from_slice(0, 0, u64::from(FRAME_LEN), &bw.buf);
+
+        // The signal specification and capacity are known before decoding.
+        let spec = decoder.output_spec().unwrap();
+        assert_eq!(spec.rate, 44100);
+        assert_eq!(decoder.max_frames_per_packet(), Some(u64::from(FRAME_LEN)));
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                assert_eq!(*buf.spec(), spec);
+                assert_eq!(buf.capacity(), FRAME_LEN as usize);
+
+                let channels = buf.spec().channels;
+
+                // The audio buffer channels are ordered by their channel position.
+                let expected = [
+                    Channels::FRONT_LEFT,
+                    Channels::FRONT_RIGHT,
+                    Channels::FRONT_CENTRE,
+                    Channels::LFE1,
+                    Channels::SIDE_LEFT,
+                    Channels::SIDE_RIGHT,
+                ];
+
+                assert_eq!(channels.iter().collect::<Vec<_>>(), &expected);
+
+                for ch in 0..channels.count() {
+                    assert_eq!(buf.chan(ch), &[(ch as i32 + 1) << 16; FRAME_LEN as usize]);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn verify_native_bit_depth() {
+        // An uncompressed mono element with 24-bit samples.
+        let mut bw = BitWriter::default();
+        bw.write_bits(0, 3 + 4 + 12);
+        bw.write_bits(0b0001, 4);
+
+        for _ in 0..FRAME_LEN {
+            bw.write_bits(0x80_0001, 24);
+        }
+
+        bw.write_bits(7, 3);
+
+        let packet = Packet::new_from_slice(0, 0, u64::from(FRAME_LEN), &bw.buf);
+
+        // By default, the samples are scaled to the full range of the 32-bit output buffer.
+        let mut decoder = make_decoder(24, 1, &DecoderOptions::default());
+        assert_eq!(decoder.codec_params().bits_per_sample, Some(24));
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => assert_eq!(buf.chan(0), &[-0x7fff_ff00; 4]),
+            _ => unreachable!(),
+        }
+
+        // When requested, the samples are output at their native bit-depth.
+        let mut options = DecoderOptions::default();
+        options.with_native_bit_depth(true);
+
+        let mut decoder = make_decoder(24, 1, &options);
+        assert_eq!(decoder.codec_params().bits_per_sample, Some(24));
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => assert_eq!(buf.chan(0), &[-0x7f_ffff; 4]),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn verify_discrete_channels() {
+        // A 16-bit, 12 channel stream. Without an explicit channel layout, the channels are
+        // discrete.
+        let mut decoder = make_decoder(16, 12, &DecoderOptions::default());
+
+        // Encode 6 uncompressed CPEs where every sample is the 1-based index of the channel.
+        let mut bw = BitWriter::default();
+
+        for pair in 0..6 {
+            bw.write_bits(1, 3);
+            bw.write_bits(0, 4 + 12);
+            bw.write_bits(0b0001, 4);
+
+            for _ in 0..FRAME_LEN {
+                bw.write_bits(2 * pair + 1, 16);
+                bw.write_bits(2 * pair + 2, 16);
+            }
+        }
+
+        bw.write_bits(7, 3);
+
+        let packet = Packet::new_from_slice(0, 0, u64::from(FRAME_LEN), &bw.buf);
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                // The channels are output in the order they were decoded.
+                assert_eq!(buf.spec().channels, Channels::discrete(12).unwrap());
+
+                for ch in 0..12 {
+                    assert_eq!(buf.chan(ch), &[(ch as i32 + 1) << 16; FRAME_LEN as usize]);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn verify_adaptive_predict_fixed_order() {
+        /// Runs the generic and specialized predictors over the same residuals, and asserts that
+        /// both the samples and the adapted coefficients are identical.
+        fn verify<const N: usize>(residuals: &[i32], shift: u32, num_clip_bits: u32) {
+            // Alternating positive and negative initial coefficients.
+            let init: Vec<i32> =
+                (1..=N as i32).map(|i| if i % 2 == 0 { -100 * i } else { 100 * i }).collect();
+
+            let mut generic_coeffs = init.clone();
+            let mut generic = residuals.to_vec();
+            adaptive_predict(&mut generic_coeffs, shift, num_clip_bits, &mut generic);
+
+            let mut fixed_coeffs = [0; N];
+            fixed_coeffs.copy_from_slice(&init);
+            let mut fixed = residuals.to_vec();
+            adaptive_predict_fixed::<N>(&mut fixed_coeffs, shift, num_clip_bits, &mut fixed);
+
+            assert_eq!(generic, fixed);
+            assert_eq!(generic_coeffs, fixed_coeffs);
+        }
+
+        // Pseudo-random residuals with a mix of positive, negative, and zero values.
+        let mut state = 0x1234_5678u32;
+
+        let residuals: Vec<i32> = (0..4096)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 16) as i32 % 2048 - 1024
+            })
+            .collect();
+
+        for &(shift, num_clip_bits) in [(0, 16), (9, 16), (12, 8), (15, 0)].iter() {
+            verify::<4>(&residuals, shift, num_clip_bits);
+            verify::<8>(&residuals, shift, num_clip_bits);
+        }
+    }
+}
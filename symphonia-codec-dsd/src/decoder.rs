@@ -0,0 +1,198 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::support_codec;
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::codecs::{CODEC_TYPE_DSD_LSBF, CODEC_TYPE_DSD_LSBF_PLANAR};
+use symphonia_core::codecs::{CODEC_TYPE_DSD_MSBF, CODEC_TYPE_DSD_MSBF_PLANAR};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+
+/// The default number of frames in a packet if the maximum is not provided.
+const DEFAULT_MAX_FRAMES_PER_PACKET: u64 = 4096;
+
+/// Direct Stream Digital (DSD) decoder.
+///
+/// DSD is a 1-bit audio format. `DsdDecoder` does not convert DSD to PCM. Instead, each decoded
+/// `u8` sample contains 8 consecutive 1-bit DSD samples with the earliest sample in the
+/// most-significant bit. Therefore, the sample rate of a DSD track, and of the decoded audio, is
+/// 1/8th of the DSD sample rate.
+///
+/// Regardless of the bit order and interleaving of the codec, the decoded audio is always
+/// most-significant bit first and planar.
+///
+/// To convert the decoded audio to PCM, use [`DsdToPcm`](crate::DsdToPcm).
+pub struct DsdDecoder {
+    params: CodecParameters,
+    buf: AudioBuffer<u8>,
+    lsb_first: bool,
+    planar: bool,
+}
+
+impl DsdDecoder {
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let data = packet.buf();
+
+        let n_channels = self.buf.spec().channels.count();
+
+        if data.len() % n_channels != 0 {
+            return decode_error("dsd: packet is not a whole number of frames");
+        }
+
+        let n_frames = data.len() / n_channels;
+
+        // Packets are not required to have a fixed length, grow the buffer if required.
+        if n_frames > self.buf.capacity() {
+            self.buf = AudioBuffer::new(n_frames as u64, *self.buf.spec());
+        }
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(n_frames));
+
+        for ch in 0..n_channels {
+            let out = self.buf.chan_mut(ch);
+
+            // Planar packets contain a block of bytes for each channel, while interleaved packets
+            // alternate bytes between channels.
+            if self.planar {
+                out.copy_from_slice(&data[ch * n_frames..(ch + 1) * n_frames]);
+            }
+            else {
+                for (out, &byte) in out.iter_mut().zip(data[ch..].iter().step_by(n_channels)) {
+                    *out = byte;
+                }
+            }
+
+            if self.lsb_first {
+                for out in out.iter_mut() {
+                    *out = out.reverse_bits();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for DsdDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let (lsb_first, planar) = match params.codec {
+            CODEC_TYPE_DSD_LSBF => (true, false),
+            CODEC_TYPE_DSD_LSBF_PLANAR => (true, true),
+            CODEC_TYPE_DSD_MSBF => (false, false),
+            CODEC_TYPE_DSD_MSBF_PLANAR => (false, true),
+            _ => return unsupported_error("dsd: invalid codec type"),
+        };
+
+        let channels = match params.channels {
+            Some(channels) if channels.count() > 0 => channels,
+            _ => return unsupported_error("dsd: channels are missing or invalid"),
+        };
+
+        let sample_rate = match params.sample_rate {
+            Some(sample_rate) => sample_rate,
+            _ => return unsupported_error("dsd: sample rate is required"),
+        };
+
+        let max_frames = params.max_frames_per_packet.unwrap_or(DEFAULT_MAX_FRAMES_PER_PACKET);
+
+        let buf = AudioBuffer::new(max_frames, SignalSpec::new(sample_rate, channels));
+
+        Ok(DsdDecoder { params: params.clone(), buf, lsb_first, planar })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[
+            support_codec!(CODEC_TYPE_DSD_LSBF, "dsd_lsbf", "DSD (LSB first)"),
+            support_codec!(
+                CODEC_TYPE_DSD_LSBF_PLANAR,
+                "dsd_lsbf_planar",
+                "DSD (LSB first, planar)"
+            ),
+            support_codec!(CODEC_TYPE_DSD_MSBF, "dsd_msbf", "DSD (MSB first)"),
+            support_codec!(
+                CODEC_TYPE_DSD_MSBF_PLANAR,
+                "dsd_msbf_planar",
+                "DSD (MSB first, planar)"
+            ),
+        ]
+    }
+
+    fn reset(&mut self) {
+        // DSD packets are independent. Therefore, there is nothing to reset.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, CodecType, Decoder, DecoderOptions};
+    use symphonia_core::codecs::{CODEC_TYPE_DSD_LSBF, CODEC_TYPE_DSD_LSBF_PLANAR};
+    use symphonia_core::codecs::{CODEC_TYPE_DSD_MSBF, CODEC_TYPE_DSD_MSBF_PLANAR};
+    use symphonia_core::formats::Packet;
+
+    use super::DsdDecoder;
+
+    fn decode(codec: CodecType, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(codec)
+            .with_sample_rate(352_800)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT)
+            .with_max_frames_per_packet(2);
+
+        let mut decoder = DsdDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        match decoder.decode(&Packet::new_from_slice(0, 0, 0, data)).unwrap() {
+            AudioBufferRef::U8(buf) => vec![buf.chan(0).to_vec(), buf.chan(1).to_vec()],
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn verify_bit_order_and_interleaving() {
+        // Three frames of a left channel of 0x01, 0x02, 0x03, and a right channel of 0xf0, 0xe0,
+        // 0xd0, stored most-significant bit first.
+        let expected = vec![vec![0x01, 0x02, 0x03], vec![0xf0, 0xe0, 0xd0]];
+
+        // The packets are longer than the maximum frames per packet, so the buffer must grow.
+        let msbf = [0x01, 0xf0, 0x02, 0xe0, 0x03, 0xd0];
+        let msbf_planar = [0x01, 0x02, 0x03, 0xf0, 0xe0, 0xd0];
+        let lsbf = [0x80, 0x0f, 0x40, 0x07, 0xc0, 0x0b];
+        let lsbf_planar = [0x80, 0x40, 0xc0, 0x0f, 0x07, 0x0b];
+
+        assert_eq!(decode(CODEC_TYPE_DSD_MSBF, &msbf), expected);
+        assert_eq!(decode(CODEC_TYPE_DSD_MSBF_PLANAR, &msbf_planar), expected);
+        assert_eq!(decode(CODEC_TYPE_DSD_LSBF, &lsbf), expected);
+        assert_eq!(decode(CODEC_TYPE_DSD_LSBF_PLANAR, &lsbf_planar), expected);
+    }
+}
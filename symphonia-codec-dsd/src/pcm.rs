@@ -0,0 +1,360 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::f64::consts::PI;
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef};
+use symphonia_core::audio::{Channels, Signal, SignalSpec};
+use symphonia_core::errors::{unsupported_error, Result};
+
+/// The sample rate of DSD64 audio in hertz. This is 64 times the sample rate of CD audio.
+pub const DSD64_RATE: u32 = 2_822_400;
+
+/// The cutoff frequency of the low-pass filter in hertz. DSD audio contains a large amount of
+/// noise above the audible band, which must be removed before decimation.
+const CUTOFF: f64 = 24_000.0;
+
+/// The number of zero-crossings of the sinc function on either side of the centre of the
+/// low-pass filter.
+const N_ZERO_CROSSINGS: usize = 16;
+
+/// The DSD idle pattern. It is used as silence since a constant stream of zeros or ones is not
+/// silent.
+const DSD_SILENCE: u8 = 0x69;
+
+/// A `DsdToPcm` converter converts 1-bit DSD audio, as decoded by
+/// [`DsdDecoder`](crate::DsdDecoder), to PCM audio.
+///
+/// The DSD audio is filtered by a windowed-sinc low-pass filter, and then decimated to the PCM
+/// sample rate. Since every tap of the filter is applied to a single bit, a lookup table is
+/// computed for each byte of the filter window.
+///
+/// The converter is stateful, and audio buffers from the same stream must be processed in order.
+/// The output is delayed by half the length of the filter, and `n / r` samples are output per
+/// channel for `n` input samples per channel, where `r` is the ratio between the sample rates.
+/// When seeking, the converter must be reset.
+pub struct DsdToPcm {
+    from_rate: u32,
+    /// The output buffer.
+    buf: AudioBuffer<f32>,
+    /// The number of input frames per output frame.
+    ratio: usize,
+    /// A lookup table for each byte of the filter window, starting with the most recent byte.
+    /// Each table maps the 8 1-bit samples of a byte to the sum of the samples multiplied by their
+    /// taps.
+    tables: Vec<[f32; 256]>,
+    /// The most recent bytes of each channel.
+    history: Vec<Vec<u8>>,
+    /// The position of the most recent byte in the history of each channel.
+    pos: usize,
+    /// The number of input frames since the last output frame.
+    phase: usize,
+}
+
+impl DsdToPcm {
+    /// Instantiate a new `DsdToPcm` converter for DSD audio with the given channels.
+    ///
+    /// The sample rate of decoded DSD audio, `from_rate`, is 1/8th of the DSD sample rate.
+    ///
+    /// The PCM sample rate, `to_rate`, must evenly divide the sample rate of the DSD audio.
+    pub fn new(from_rate: u32, to_rate: u32, channels: Channels) -> Result<Self> {
+        if to_rate == 0 || from_rate < to_rate || from_rate % to_rate != 0 {
+            return unsupported_error("dsd: pcm sample rate must divide the dsd sample rate");
+        }
+
+        if channels.count() == 0 {
+            return unsupported_error("dsd: there must be at least one channel");
+        }
+
+        // The cutoff must also be below the Nyquist frequency of the output.
+        let cutoff = CUTOFF.min(0.45 * f64::from(to_rate)) / (4.0 * f64::from(from_rate));
+
+        // The length of the filter in bits, rounded up to a whole number of bytes.
+        let half_len = (N_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+        let n_bytes = (2 * half_len + 7) / 8;
+
+        let centre = (8 * n_bytes - 1) as f64 / 2.0;
+
+        let mut taps: Vec<f64> = (0..8 * n_bytes)
+            .map(|k| {
+                let t = k as f64 - centre;
+                cutoff * sinc(cutoff * t) * blackman(t / (centre + 1.0))
+            })
+            .collect();
+
+        // Normalize the filter to unity gain.
+        let sum: f64 = taps.iter().sum();
+        taps.iter_mut().for_each(|c| *c /= sum);
+
+        // Tap `8 * j + i` is applied to the bit that is `i` bits older than the most recent bit of
+        // byte `j`. The most recent bit of a byte is the least-significant bit.
+        let tables = taps
+            .chunks_exact(8)
+            .map(|taps| {
+                let mut table = [0.0; 256];
+
+                for (byte, entry) in table.iter_mut().enumerate() {
+                    let sum: f64 = taps
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &c)| if byte & (1 << i) != 0 { c } else { -c })
+                        .sum();
+
+                    *entry = sum as f32;
+                }
+
+                table
+            })
+            .collect();
+
+        let mut converter = DsdToPcm {
+            from_rate,
+            buf: AudioBuffer::new(0, SignalSpec::new(to_rate, channels)),
+            ratio: (from_rate / to_rate) as usize,
+            tables,
+            history: vec![vec![0; n_bytes]; channels.count()],
+            pos: 0,
+            phase: 0,
+        };
+
+        converter.reset();
+        Ok(converter)
+    }
+
+    /// Gets the signal specification of the output.
+    pub fn spec(&self) -> &SignalSpec {
+        self.buf.spec()
+    }
+
+    /// Convert an audio buffer of DSD audio, and return the PCM audio.
+    ///
+    /// The audio buffer must contain DSD audio, as decoded by `DsdDecoder`, with the sample rate
+    /// and channels the converter was instantiated with.
+    pub fn process(&mut self, input: &AudioBufferRef<'_>) -> Result<AudioBufferRef<'_>> {
+        let input = match input {
+            AudioBufferRef::U8(buf) => buf,
+            _ => return unsupported_error("dsd: audio buffer is not dsd audio"),
+        };
+
+        if input.spec().rate != self.from_rate || input.spec().channels != self.spec().channels {
+            return unsupported_error("dsd: signal specification does not match");
+        }
+
+        let n_frames = (self.phase + input.frames()) / self.ratio;
+
+        // The length of the input is not fixed, grow the output buffer if required.
+        if n_frames > self.buf.capacity() {
+            self.buf = AudioBuffer::new(n_frames as u64, *self.buf.spec());
+        }
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(n_frames));
+
+        // Filter each channel, one plane at a time.
+        for (ch, history) in self.history.iter_mut().enumerate() {
+            let mut pos = self.pos;
+            let mut phase = self.phase;
+
+            let mut plane = self.buf.chan_mut(ch).iter_mut();
+
+            for &byte in input.chan(ch) {
+                pos = if pos + 1 < history.len() { pos + 1 } else { 0 };
+                history[pos] = byte;
+
+                phase += 1;
+
+                if phase < self.ratio {
+                    continue;
+                }
+
+                phase = 0;
+
+                // From the most recent byte to the oldest byte.
+                let (newer, older) = history.split_at(pos + 1);
+                let window = newer.iter().rev().chain(older.iter().rev());
+
+                if let Some(out) = plane.next() {
+                    *out = self.tables.iter().zip(window).map(|(t, &b)| t[usize::from(b)]).sum();
+                }
+            }
+        }
+
+        // The position and phase advance identically for every channel.
+        self.pos = (self.pos + input.frames()) % self.tables.len();
+        self.phase = (self.phase + input.frames()) % self.ratio;
+
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    /// Discard the filter state and reset the converter.
+    pub fn reset(&mut self) {
+        for history in self.history.iter_mut() {
+            history.iter_mut().for_each(|b| *b = DSD_SILENCE);
+        }
+
+        self.pos = 0;
+        self.phase = 0;
+    }
+}
+
+/// The normalized sinc function.
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    }
+    else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// The Blackman window over the interval [-1, 1].
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        0.0
+    }
+    else {
+        0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef};
+    use symphonia_core::audio::{Channels, Signal, SignalSpec};
+    use symphonia_core::codecs::{CodecParameters, CodecType, Decoder, DecoderOptions};
+    use symphonia_core::codecs::{CODEC_TYPE_DSD_LSBF, CODEC_TYPE_DSD_MSBF};
+    use symphonia_core::formats::Packet;
+
+    use super::{DsdToPcm, DSD64_RATE};
+    use crate::DsdDecoder;
+
+    /// Modulates a sine with a second-order sigma-delta modulator, and returns mono DSD64 audio,
+    /// most-significant bit first.
+    fn modulate(freq: f64, amplitude: f64, n_frames: usize) -> Vec<u8> {
+        let (mut v1, mut v2, mut y) = (0.0, 0.0, 1.0);
+
+        (0..n_frames)
+            .map(|i| {
+                let mut byte = 0;
+
+                for j in 0..8 {
+                    let t = (8 * i + j) as f64 / f64::from(DSD64_RATE);
+
+                    v1 += amplitude * (2.0 * PI * freq * t).sin() - y;
+                    v2 += v1 - y;
+                    y = if v2 >= 0.0 { 1.0 } else { -1.0 };
+
+                    byte = (byte << 1) | u8::from(y > 0.0);
+                }
+
+                byte
+            })
+            .collect()
+    }
+
+    /// Decodes mono DSD64 audio, and converts it to PCM in packets of the given length.
+    fn convert(converter: &mut DsdToPcm, codec: CodecType, data: &[u8], len: usize) -> Vec<f32> {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(codec)
+            .with_sample_rate(DSD64_RATE / 8)
+            .with_channels(Channels::FRONT_LEFT);
+
+        let mut decoder = DsdDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        let mut pcm = Vec::new();
+
+        for chunk in data.chunks(len) {
+            let decoded = decoder.decode(&Packet::new_from_slice(0, 0, 0, chunk)).unwrap();
+            match converter.process(&decoded).unwrap() {
+                AudioBufferRef::F32(buf) => pcm.extend_from_slice(buf.chan(0)),
+                _ => unreachable!(),
+            }
+        }
+
+        pcm
+    }
+
+    #[test]
+    fn verify_tone() {
+        // 50 ms of a 1 kHz tone.
+        let dsd = modulate(1000.0, 0.5, 17_640);
+        let lsbf: Vec<u8> = dsd.iter().map(|byte| byte.reverse_bits()).collect();
+
+        let mut converter = DsdToPcm::new(DSD64_RATE / 8, 88_200, Channels::FRONT_LEFT).unwrap();
+        assert_eq!(converter.spec().rate, 88_200);
+
+        let pcm = convert(&mut converter, CODEC_TYPE_DSD_MSBF, &dsd, 4096);
+        assert_eq!(pcm.len(), 4410);
+
+        // The filter state is maintained between packets, so the packet length does not matter.
+        converter.reset();
+        assert_eq!(convert(&mut converter, CODEC_TYPE_DSD_MSBF, &dsd, 17_640), pcm);
+
+        // The bit order of the codec does not matter.
+        converter.reset();
+        assert_eq!(convert(&mut converter, CODEC_TYPE_DSD_LSBF, &lsbf, 1000), pcm);
+
+        // Skip the delay of the filter. Then, find the frequency from the first and last positive
+        // going zero-crossings.
+        let pcm = &pcm[441..];
+
+        let crossings: Vec<f64> = pcm
+            .windows(2)
+            .enumerate()
+            .filter(|(_, w)| w[0] < 0.0 && w[1] >= 0.0)
+            .map(|(i, w)| i as f64 + f64::from(w[0] / (w[0] - w[1])))
+            .collect();
+
+        let first = crossings.first().unwrap();
+        let last = crossings.last().unwrap();
+
+        let freq = (crossings.len() - 1) as f64 * 88_200.0 / (last - first);
+        assert!((freq - 1000.0).abs() < 1.0, "frequency is {}", freq);
+
+        let peak = pcm.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 0.5).abs() < 0.02, "peak is {}", peak);
+    }
+
+    #[test]
+    fn verify_silence() {
+        let rate = DSD64_RATE / 8;
+        let mut converter = DsdToPcm::new(rate, rate, Channels::FRONT_LEFT).unwrap();
+
+        let pcm = convert(&mut converter, CODEC_TYPE_DSD_MSBF, &[0x69; 4096], 4096);
+
+        assert_eq!(pcm.len(), 4096);
+        assert!(pcm.iter().all(|s| s.abs() < 1e-3));
+    }
+
+    #[test]
+    fn verify_invalid_input() {
+        let rate = DSD64_RATE / 8;
+
+        // The PCM sample rate must evenly divide the DSD sample rate.
+        assert!(DsdToPcm::new(rate, 48_000, Channels::FRONT_LEFT).is_err());
+        assert!(DsdToPcm::new(rate, 0, Channels::FRONT_LEFT).is_err());
+        assert!(DsdToPcm::new(rate, 88_200, Channels::empty()).is_err());
+
+        let mut converter = DsdToPcm::new(rate, 88_200, Channels::FRONT_LEFT).unwrap();
+
+        // The audio must be DSD audio.
+        let pcm = AudioBuffer::<f32>::new(16, SignalSpec::new(rate, Channels::FRONT_LEFT));
+        assert!(converter.process(&pcm.as_audio_buffer_ref()).is_err());
+
+        // The signal specification must match.
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+
+        let dsd = AudioBuffer::<u8>::new(16, SignalSpec::new(rate, stereo));
+        assert!(converter.process(&dsd.as_audio_buffer_ref()).is_err());
+
+        let dsd = AudioBuffer::<u8>::new(16, SignalSpec::new(rate / 2, Channels::FRONT_LEFT));
+        assert!(converter.process(&dsd.as_audio_buffer_ref()).is_err());
+    }
+}
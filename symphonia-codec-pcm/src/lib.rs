@@ -90,6 +90,29 @@ impl GenericAudioBuffer {
     fn clear(&mut self) {
         impl_generic_audio_buffer_func!(self, buf, buf.clear());
     }
+
+    fn sample_format(&self) -> SampleFormat {
+        match self {
+            GenericAudioBuffer::U8(_) => SampleFormat::U8,
+            GenericAudioBuffer::U16(_) => SampleFormat::U16,
+            GenericAudioBuffer::U24(_) => SampleFormat::U24,
+            GenericAudioBuffer::U32(_) => SampleFormat::U32,
+            GenericAudioBuffer::S8(_) => SampleFormat::S8,
+            GenericAudioBuffer::S16(_) => SampleFormat::S16,
+            GenericAudioBuffer::S24(_) => SampleFormat::S24,
+            GenericAudioBuffer::S32(_) => SampleFormat::S32,
+            GenericAudioBuffer::F32(_) => SampleFormat::F32,
+            GenericAudioBuffer::F64(_) => SampleFormat::F64,
+        }
+    }
+
+    fn spec(&self) -> &SignalSpec {
+        impl_generic_audio_buffer_func!(self, buf, buf.spec())
+    }
+
+    fn capacity(&self) -> usize {
+        impl_generic_audio_buffer_func!(self, buf, buf.capacity())
+    }
 }
 
 impl AsAudioBufferRef for GenericAudioBuffer {
@@ -366,8 +389,16 @@ impl PcmDecoder {
     }
 }
 
-impl Decoder for PcmDecoder {
-    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+/// The decoder configuration derived from a set of `CodecParameters`.
+struct PcmConfig {
+    sample_format: SampleFormat,
+    max_frames: Duration,
+    spec: SignalSpec,
+    coded_width: u32,
+}
+
+impl PcmConfig {
+    fn try_new(params: &CodecParameters) -> Result<Self> {
         // This decoder only supports certain PCM codecs.
         if !is_supported_pcm_codec(params.codec) {
             return unsupported_error("pcm: invalid codec type");
@@ -440,10 +471,41 @@ impl Decoder for PcmDecoder {
             return decode_error("pcm: coded bits per sample is greater than the sample format");
         }
 
+        Ok(PcmConfig { sample_format, max_frames: frames, spec, coded_width })
+    }
+}
+
+impl Decoder for PcmDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let config = PcmConfig::try_new(params)?;
+
         // Create an audio buffer of the correct format.
-        let buf = GenericAudioBuffer::new(sample_format, frames, spec);
+        let buf = GenericAudioBuffer::new(config.sample_format, config.max_frames, config.spec);
 
-        Ok(PcmDecoder { params: params.clone(), coded_width, buf })
+        Ok(PcmDecoder { params: params.clone(), coded_width: config.coded_width, buf })
+    }
+
+    fn reset_with_params(&mut self, params: &CodecParameters) -> Result<()> {
+        let config = PcmConfig::try_new(params)?;
+
+        // The audio buffer may only be reused if the sample format and signal specification are
+        // unchanged, and it is large enough to hold the largest packet of the new stream.
+        let is_reusable = self.buf.sample_format() == config.sample_format
+            && self.buf.spec() == &config.spec
+            && self.buf.capacity() as Duration >= config.max_frames;
+
+        if is_reusable {
+            self.buf.clear();
+        }
+        else {
+            self.buf =
+                GenericAudioBuffer::new(config.sample_format, config.max_frames, config.spec);
+        }
+
+        self.params = params.clone();
+        self.coded_width = config.coded_width;
+
+        Ok(())
     }
 
     fn supported_codecs() -> &'static [CodecDescriptor] {
@@ -633,6 +695,14 @@ impl Decoder for PcmDecoder {
         &self.params
     }
 
+    fn output_spec(&self) -> Option<SignalSpec> {
+        Some(*self.buf.spec())
+    }
+
+    fn max_frames_per_packet(&self) -> Option<u64> {
+        Some(self.buf.capacity() as u64)
+    }
+
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
         if let Err(e) = self.decode_inner(packet) {
             self.buf.clear();
@@ -651,3 +721,92 @@ impl Decoder for PcmDecoder {
         self.buf.as_audio_buffer_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal, SignalSpec};
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_PCM_S16LE};
+    use symphonia_core::formats::Packet;
+
+    use super::PcmDecoder;
+
+    fn make_params(sample_rate: u32, channels: Channels) -> CodecParameters {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_PCM_S16LE)
+            .with_sample_rate(sample_rate)
+            .with_channels(channels)
+            .with_bits_per_coded_sample(16)
+            .with_max_frames_per_packet(1152);
+        params
+    }
+
+    #[test]
+    fn verify_reset_with_params() {
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+
+        let streams = [
+            make_params(44_100, stereo),
+            make_params(44_100, stereo),
+            make_params(48_000, Channels::FRONT_LEFT),
+        ];
+
+        let mut decoder = PcmDecoder::try_new(&streams[0], &Default::default()).unwrap();
+
+        for params in streams.iter() {
+            decoder.reset_with_params(params).unwrap();
+
+            let n_channels = params.channels.unwrap().count();
+            let data = [0x01, 0x00].repeat(4 * n_channels);
+
+            let decoded = decoder.decode(&Packet::new_from_slice(0, 0, 4, &data)).unwrap();
+
+            assert_eq!(decoded.frames(), 4);
+            assert_eq!(decoded.spec().rate, params.sample_rate.unwrap());
+            assert_eq!(decoded.spec().channels.count(), n_channels);
+
+            match decoded {
+                AudioBufferRef::S16(buf) => assert!(buf.chan(0).iter().all(|&s| s == 1)),
+                _ => panic!("unexpected sample format"),
+            }
+        }
+    }
+
+    #[test]
+    fn verify_output_spec() {
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+
+        let mut decoder =
+            PcmDecoder::try_new(&make_params(44_100, stereo), &Default::default()).unwrap();
+
+        let spec = decoder.output_spec().unwrap();
+        assert_eq!(spec, SignalSpec::new(44_100, stereo));
+        assert_eq!(decoder.max_frames_per_packet(), Some(1152));
+
+        let decoded = decoder.decode(&Packet::new_from_slice(0, 0, 2, &[0; 8])).unwrap();
+        assert_eq!(*decoded.spec(), spec);
+        assert_eq!(decoded.capacity(), 1152);
+    }
+
+    #[test]
+    fn verify_discrete_channels() {
+        let channels = Channels::discrete(12).unwrap();
+
+        let mut decoder =
+            PcmDecoder::try_new(&make_params(48_000, channels), &Default::default()).unwrap();
+
+        // Each sample is the index of its channel.
+        let data: Vec<u8> = (0..2 * 12).flat_map(|i| ((i % 12) as i16).to_le_bytes()).collect();
+
+        match decoder.decode(&Packet::new_from_slice(0, 0, 2, &data)).unwrap() {
+            AudioBufferRef::S16(buf) => {
+                assert_eq!(buf.spec().channels.count(), 12);
+
+                for ch in 0..12 {
+                    assert_eq!(buf.chan(ch), &[ch as i16, ch as i16]);
+                }
+            }
+            _ => panic!("unexpected sample format"),
+        }
+    }
+}
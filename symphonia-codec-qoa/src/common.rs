@@ -0,0 +1,206 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::Channels;
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::ReadBytes;
+
+/// The QOA file header magic.
+pub const QOA_MAGIC: [u8; 4] = *b"qoaf";
+
+/// The length of the QOA file header.
+pub const QOA_FILE_HEADER_LEN: u64 = 8;
+
+/// The length of the QOA frame header.
+pub const QOA_FRAME_HEADER_LEN: usize = 8;
+
+/// The number of samples coded by a slice.
+pub const QOA_SLICE_LEN: usize = 20;
+
+/// The maximum number of slices per channel in a frame.
+pub const QOA_SLICES_PER_FRAME: usize = 256;
+
+/// The maximum number of audio frames in a frame.
+pub const QOA_FRAME_LEN: usize = QOA_SLICE_LEN * QOA_SLICES_PER_FRAME;
+
+/// The number of taps of the LMS predictor.
+pub const QOA_LMS_LEN: usize = 4;
+
+/// The maximum number of channels.
+pub const QOA_MAX_CHANNELS: u8 = 8;
+
+/// The dequantization table, indexed by the scale factor and the quantized residual.
+pub const QOA_DEQUANT_TABLE: [[i32; 8]; 16] = [
+    [1, -1, 3, -3, 5, -5, 7, -7],
+    [5, -5, 18, -18, 32, -32, 49, -49],
+    [16, -16, 53, -53, 95, -95, 147, -147],
+    [34, -34, 113, -113, 203, -203, 315, -315],
+    [63, -63, 210, -210, 378, -378, 588, -588],
+    [104, -104, 345, -345, 621, -621, 966, -966],
+    [158, -158, 528, -528, 950, -950, 1477, -1477],
+    [228, -228, 760, -760, 1368, -1368, 2128, -2128],
+    [316, -316, 1053, -1053, 1895, -1895, 2947, -2947],
+    [422, -422, 1405, -1405, 2529, -2529, 3934, -3934],
+    [548, -548, 1828, -1828, 3290, -3290, 5117, -5117],
+    [696, -696, 2320, -2320, 4176, -4176, 6496, -6496],
+    [868, -868, 2893, -2893, 5207, -5207, 8099, -8099],
+    [1064, -1064, 3548, -3548, 6386, -6386, 9933, -9933],
+    [1286, -1286, 4288, -4288, 7718, -7718, 12005, -12005],
+    [1536, -1536, 5120, -5120, 9216, -9216, 14336, -14336],
+];
+
+/// Gets the channels for a given number of QOA channels.
+///
+/// The QOA channel order for up-to 8 channels matches the order of the channel bits.
+pub fn channels(n_channels: u8) -> Option<Channels> {
+    let channels = match n_channels {
+        1 => Channels::FRONT_LEFT,
+        2 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        3 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE,
+        4 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        5 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        6 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::LFE1
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        7 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::LFE1
+                | Channels::REAR_CENTRE
+                | Channels::SIDE_LEFT
+                | Channels::SIDE_RIGHT
+        }
+        8 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::LFE1
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+                | Channels::SIDE_LEFT
+                | Channels::SIDE_RIGHT
+        }
+        _ => return None,
+    };
+
+    Some(channels)
+}
+
+/// Gets the size in bytes of a frame with the given number of channels and audio frames.
+pub fn calc_frame_size(n_channels: u8, n_frames: usize) -> usize {
+    let n_channels = usize::from(n_channels);
+    let n_slices = (n_frames + QOA_SLICE_LEN - 1) / QOA_SLICE_LEN;
+
+    // Each channel has a LMS state of 4 bytes per tap, and 8 bytes per slice.
+    QOA_FRAME_HEADER_LEN + 4 * QOA_LMS_LEN * n_channels + 8 * n_slices * n_channels
+}
+
+/// A QOA frame header.
+#[derive(Debug)]
+pub struct FrameHeader {
+    /// The number of channels.
+    pub n_channels: u8,
+    /// The sample rate.
+    pub sample_rate: u32,
+    /// The number of audio frames (samples per channel) in the frame.
+    pub n_frames: u16,
+    /// The size of the frame in bytes, including the frame header.
+    pub frame_size: u16,
+}
+
+impl FrameHeader {
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<FrameHeader> {
+        let header = reader.read_be_u64()?;
+
+        let n_channels = (header >> 56) as u8;
+        let sample_rate = ((header >> 32) & 0xff_ffff) as u32;
+        let n_frames = (header >> 16) as u16;
+        let frame_size = header as u16;
+
+        if n_channels == 0 || n_channels > QOA_MAX_CHANNELS {
+            return decode_error("qoa: invalid number of channels");
+        }
+
+        if sample_rate == 0 {
+            return decode_error("qoa: invalid sample rate");
+        }
+
+        if usize::from(n_frames) > QOA_FRAME_LEN {
+            return decode_error("qoa: too many audio frames in frame");
+        }
+
+        // The frame must be large enough to contain the LMS state of every channel and all the
+        // slices.
+        if usize::from(frame_size) < calc_frame_size(n_channels, usize::from(n_frames)) {
+            return decode_error("qoa: frame size is too small");
+        }
+
+        Ok(FrameHeader { n_channels, sample_rate, n_frames, frame_size })
+    }
+}
+
+/// The state of the LMS predictor of a channel.
+#[derive(Default)]
+pub struct Lms {
+    pub history: [i32; QOA_LMS_LEN],
+    pub weights: [i32; QOA_LMS_LEN],
+}
+
+impl Lms {
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<Lms> {
+        let mut lms: Lms = Default::default();
+
+        // The history and weights are each stored as 4 signed 16-bit values.
+        for history in lms.history.iter_mut() {
+            *history = i32::from(reader.read_be_u16()? as i16);
+        }
+
+        for weight in lms.weights.iter_mut() {
+            *weight = i32::from(reader.read_be_u16()? as i16);
+        }
+
+        Ok(lms)
+    }
+
+    pub fn predict(&self) -> i32 {
+        let mut prediction = 0i32;
+
+        for (&weight, &history) in self.weights.iter().zip(self.history.iter()) {
+            prediction = prediction.wrapping_add(weight.wrapping_mul(history));
+        }
+
+        prediction >> 13
+    }
+
+    pub fn update(&mut self, sample: i32, residual: i32) {
+        let delta = residual >> 4;
+
+        for (weight, &history) in self.weights.iter_mut().zip(self.history.iter()) {
+            *weight = weight.wrapping_add(if history < 0 { -delta } else { delta });
+        }
+
+        self.history.rotate_left(1);
+        self.history[QOA_LMS_LEN - 1] = sample;
+    }
+}
@@ -0,0 +1,132 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::support_codec;
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_QOA};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BufReader, ReadBytes};
+
+use crate::common::*;
+
+/// Quite OK Audio (QOA) decoder.
+///
+/// Each packet must contain exactly one QOA frame, including the frame header.
+pub struct QoaDecoder {
+    params: CodecParameters,
+    buf: AudioBuffer<i16>,
+}
+
+impl QoaDecoder {
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let mut reader = BufReader::new(packet.buf());
+
+        let header = FrameHeader::read(&mut reader)?;
+
+        let n_channels = self.buf.spec().channels.count();
+
+        if usize::from(header.n_channels) != n_channels {
+            return decode_error("qoa: number of channels changed");
+        }
+
+        if usize::from(header.frame_size) > packet.buf().len() {
+            return decode_error("qoa: frame is truncated");
+        }
+
+        // Read the LMS predictor state of each channel.
+        let mut lms = Vec::with_capacity(n_channels);
+
+        for _ in 0..n_channels {
+            lms.push(Lms::read(&mut reader)?);
+        }
+
+        let n_frames = usize::from(header.n_frames);
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(n_frames));
+
+        // Slices are interleaved by channel.
+        for start in (0..n_frames).step_by(QOA_SLICE_LEN) {
+            let end = (start + QOA_SLICE_LEN).min(n_frames);
+
+            for (ch, lms) in lms.iter_mut().enumerate() {
+                let mut slice = reader.read_be_u64()?;
+
+                // The top 4 bits are the scale factor, followed by up-to 20 3-bit quantized
+                // residuals.
+                let dequant = &QOA_DEQUANT_TABLE[(slice >> 60) as usize];
+                slice <<= 4;
+
+                for out in self.buf.chan_mut(ch)[start..end].iter_mut() {
+                    let residual = dequant[(slice >> 61) as usize];
+                    let sample = lms.predict().wrapping_add(residual).clamp(-32768, 32767);
+
+                    lms.update(sample, residual);
+
+                    *out = sample as i16;
+                    slice <<= 3;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for QoaDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        // This decoder only supports QOA.
+        if params.codec != CODEC_TYPE_QOA {
+            return unsupported_error("qoa: invalid codec type");
+        }
+
+        let channels = match params.channels {
+            Some(channels) if channels.count() <= usize::from(QOA_MAX_CHANNELS) => channels,
+            _ => return unsupported_error("qoa: channels are missing or invalid"),
+        };
+
+        let sample_rate = params.sample_rate.unwrap_or(0);
+
+        let buf = AudioBuffer::new(QOA_FRAME_LEN as u64, SignalSpec::new(sample_rate, channels));
+
+        Ok(QoaDecoder { params: params.clone(), buf })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_QOA, "qoa", "Quite OK Audio")]
+    }
+
+    fn reset(&mut self) {
+        // Each QOA frame contains the complete decoder state. Therefore, there is nothing to
+        // reset.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
@@ -0,0 +1,195 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::support_format;
+
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_QOA};
+use symphonia_core::errors::{
+    decode_error, end_of_stream_error, seek_error, unsupported_error, Result, SeekErrorKind,
+};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::sample::SampleFormat;
+
+use log::debug;
+
+use crate::common::*;
+
+/// Quite OK Audio (QOA) format reader.
+///
+/// `QoaReader` implements a demuxer for the QOA file format. Each packet contains one QOA frame.
+///
+/// Since every frame except the last contains the same number of audio frames, seeking is
+/// supported by calculating the position of the frame containing the desired timestamp. Seeking
+/// is not supported for streaming QOA files that do not state the total number of audio frames.
+pub struct QoaReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    n_channels: u8,
+    sample_rate: u32,
+    n_frames: Option<u64>,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for QoaReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!("qoa", "Quite OK Audio", &["qoa"], &["audio/qoa"], &[b"qoaf"])]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for QoaReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let magic = source.read_quad_bytes()?;
+
+        if magic != QOA_MAGIC {
+            return unsupported_error("qoa: missing qoaf stream marker");
+        }
+
+        // The total number of audio frames, or 0 for a streaming file.
+        let n_frames = match source.read_be_u32()? {
+            0 => None,
+            n_frames => Some(u64::from(n_frames)),
+        };
+
+        // The file header does not contain the number of channels or the sample rate. Read them
+        // from the header of the first frame, then rewind.
+        let header = FrameHeader::read(&mut source)?;
+        source.seek_buffered_rev(QOA_FRAME_HEADER_LEN);
+
+        let channels = match channels(header.n_channels) {
+            Some(channels) => channels,
+            _ => return unsupported_error("qoa: unsupported number of channels"),
+        };
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_QOA)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_channels(channels)
+            .with_sample_format(SampleFormat::S16)
+            .with_bits_per_sample(16)
+            .with_max_frames_per_packet(QOA_FRAME_LEN as u64);
+
+        if let Some(n_frames) = n_frames {
+            codec_params.with_n_frames(n_frames);
+        }
+
+        Ok(QoaReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            n_channels: header.n_channels,
+            sample_rate: header.sample_rate,
+            n_frames,
+            next_packet_ts: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        if let Some(n_frames) = self.n_frames {
+            if self.next_packet_ts >= n_frames {
+                return end_of_stream_error();
+            }
+        }
+
+        let mut header_buf = [0; QOA_FRAME_HEADER_LEN];
+        self.reader.read_buf_exact(&mut header_buf)?;
+
+        let header = FrameHeader::read(&mut BufReader::new(&header_buf))?;
+
+        if header.n_channels != self.n_channels || header.sample_rate != self.sample_rate {
+            return decode_error("qoa: stream parameters changed");
+        }
+
+        // The packet contains the entire frame, including the frame header.
+        let mut buf = vec![0; usize::from(header.frame_size)];
+
+        buf[..QOA_FRAME_HEADER_LEN].copy_from_slice(&header_buf);
+        self.reader.read_buf_exact(&mut buf[QOA_FRAME_HEADER_LEN..])?;
+
+        let ts = self.next_packet_ts;
+        let dur = u64::from(header.n_frames);
+
+        self.next_packet_ts += dur;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf.into_boxed_slice()))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Only files with a known number of audio frames are guaranteed to have fixed size frames.
+        let n_frames = match self.n_frames {
+            Some(n_frames) => n_frames,
+            _ => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => TimeBase::new(1, self.sample_rate).calc_timestamp(time),
+        };
+
+        if ts > n_frames {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // Seek to the start of the frame containing the desired timestamp.
+        let frame_idx = ts / QOA_FRAME_LEN as u64;
+        let max_frame_size = calc_frame_size(self.n_channels, QOA_FRAME_LEN) as u64;
+
+        let actual_ts = frame_idx * QOA_FRAME_LEN as u64;
+        let seek_pos = QOA_FILE_HEADER_LEN + frame_idx * max_frame_size;
+
+        debug!("seeking to frame_ts={}, frame={}", actual_ts, frame_idx);
+
+        // If the reader supports seeking we can seek directly to the frame's offset, otherwise,
+        // only forward seeks may be emulated by consuming bytes.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_packet_ts = actual_ts;
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts: ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
@@ -0,0 +1,166 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![warn(rust_2018_idioms)]
+#![forbid(unsafe_code)]
+// The following lints are allowed in all Symphonia crates. Please see clippy.toml for their
+// justification.
+#![allow(clippy::comparison_chain)]
+#![allow(clippy::excessive_precision)]
+#![allow(clippy::identity_op)]
+#![allow(clippy::manual_range_contains)]
+
+mod common;
+mod decoder;
+mod demuxer;
+
+pub use decoder::QoaDecoder;
+pub use demuxer::QoaReader;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::{AudioBufferRef, Signal};
+    use symphonia_core::codecs::{Decoder, DecoderOptions};
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::{QoaDecoder, QoaReader};
+
+    /// The LMS history and weights of a channel.
+    type LmsState = ([i16; 4], [i16; 4]);
+
+    /// Encodes a QOA frame. The slices must be interleaved by channel.
+    fn encode_frame(n_frames: u16, lms: &[LmsState], slices: &[u64]) -> Vec<u8> {
+        let frame_size = (8 + 16 * lms.len() + 8 * slices.len()) as u64;
+
+        let header = ((lms.len() as u64) << 56) | (44100 << 32) | (u64::from(n_frames) << 16);
+
+        let mut buf = (header | frame_size).to_be_bytes().to_vec();
+
+        for (history, weights) in lms {
+            for value in history.iter().chain(weights.iter()) {
+                buf.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+
+        for slice in slices {
+            buf.extend_from_slice(&slice.to_be_bytes());
+        }
+
+        buf
+    }
+
+    /// Encodes a slice from a scale factor and up-to 20 quantized residuals.
+    fn encode_slice(sf: u64, quantized: &[u64]) -> u64 {
+        let mut slice = sf << 60;
+        for (i, &q) in quantized.iter().enumerate() {
+            slice |= q << (57 - 3 * i);
+        }
+        slice
+    }
+
+    fn encode_file(n_frames: u32, frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = b"qoaf".to_vec();
+        buf.extend_from_slice(&n_frames.to_be_bytes());
+        buf.extend(frames.iter().flatten());
+        buf
+    }
+
+    fn decode_all(reader: &mut QoaReader, decoder: &mut QoaDecoder) -> (Vec<u64>, Vec<Vec<i16>>) {
+        let n_channels = reader.tracks()[0].codec_params.channels.unwrap().count();
+
+        let mut timestamps = Vec::new();
+        let mut samples = vec![Vec::new(); n_channels];
+
+        while let Ok(packet) = reader.next_packet() {
+            timestamps.push(packet.ts());
+
+            match decoder.decode(&packet).unwrap() {
+                AudioBufferRef::S16(buf) => {
+                    for (ch, samples) in samples.iter_mut().enumerate() {
+                        samples.extend_from_slice(buf.chan(ch));
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        (timestamps, samples)
+    }
+
+    #[test]
+    fn verify_decode_bit_exact() {
+        // The first channel starts with a zeroed LMS state, and uses the largest scale factor
+        // such that the weights are updated. The second channel uses a first-order predictor
+        // (the previous sample) with small residuals that do not update the weights.
+        let lms = [([0; 4], [0; 4]), ([0, 0, 0, 100], [0, 0, 0, 1 << 13])];
+        let slices = [encode_slice(15, &[6, 1, 0]), encode_slice(0, &[2, 2, 6])];
+
+        let file = encode_file(3, &[encode_frame(3, &lms, &slices)]);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(file)), Default::default());
+
+        let mut reader = QoaReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let params = reader.tracks()[0].codec_params.clone();
+
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.n_frames, Some(3));
+
+        let mut decoder = QoaDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        let (timestamps, samples) = decode_all(&mut reader, &mut decoder);
+
+        // The expected samples were calculated by hand following the reference decoder.
+        assert_eq!(timestamps, &[0]);
+        assert_eq!(samples[0], &[14336, 32, 2939]);
+        assert_eq!(samples[1], &[103, 106, 113]);
+    }
+
+    #[test]
+    fn verify_seek() {
+        // Two mono frames, the first is a full frame of 5120 audio frames, and the second is a
+        // partial frame of 100 audio frames. All residuals are 1, and all samples are 1.
+        let lms = [([0; 4], [0; 4])];
+
+        let full_frame = encode_frame(5120, &lms, &[encode_slice(0, &[0; 20]); 256]);
+        let partial_frame = encode_frame(100, &lms, &[encode_slice(0, &[0; 20]); 5]);
+
+        let file = encode_file(5220, &[full_frame, partial_frame]);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(file)), Default::default());
+
+        let mut reader = QoaReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let params = reader.tracks()[0].codec_params.clone();
+        let mut decoder = QoaDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 5200, track_id: 0 });
+        let seeked = seeked.unwrap();
+        assert_eq!((seeked.required_ts, seeked.actual_ts), (5200, 5120));
+
+        let (timestamps, samples) = decode_all(&mut reader, &mut decoder);
+
+        assert_eq!(timestamps, &[5120]);
+        assert_eq!(samples[0], vec![1; 100]);
+
+        // Seek backwards to the first frame.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 10, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 0);
+
+        let (timestamps, samples) = decode_all(&mut reader, &mut decoder);
+
+        assert_eq!(timestamps, &[0, 5120]);
+        assert_eq!(samples[0].len(), 5220);
+
+        // Seeking past the end of the stream is an error.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 5221, track_id: 0 });
+        assert!(seeked.is_err());
+    }
+}
@@ -0,0 +1,423 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, ReadBitsLtr, ReadBytes};
+
+/// The Shorten stream marker.
+pub const SHORTEN_STREAM_MARKER: [u8; 4] = *b"ajkg";
+
+/// The maximum supported Shorten version.
+pub const MAX_VERSION: u8 = 3;
+
+/// The maximum number of channels supported.
+pub const MAX_CHANNELS: u32 = 8;
+
+/// The maximum block size supported.
+pub const MAX_BLOCK_SIZE: u32 = 65535;
+
+/// The default block size for version 0 streams.
+const DEFAULT_BLOCK_SIZE: u32 = 256;
+
+/// The number of wrap-around samples kept from the previous block for prediction.
+pub const NWRAP: u32 = 3;
+
+/// The number of bits of precision in a quantized LPC coefficient.
+pub const LPCQUANT: u32 = 5;
+
+// Bit-widths for the fixed parameter of variable-length integers.
+const TYPESIZE: u32 = 4;
+const CHANSIZE: u32 = 0;
+const LPCQSIZE: u32 = 2;
+const NSKIPSIZE: u32 = 1;
+const ULONGSIZE: u32 = 2;
+const FNSIZE: u32 = 2;
+const ENERGYSIZE: u32 = 3;
+const BITSHIFTSIZE: u32 = 2;
+const VERBATIM_CKSIZE_SIZE: u32 = 5;
+const VERBATIM_BYTE_SIZE: u32 = 8;
+
+/// The maximum length of a verbatim chunk.
+const MAX_VERBATIM_LEN: u32 = 1 << 20;
+
+/// The sample types defined by Shorten.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SampleType {
+    S8,
+    U8,
+    S16,
+    U16,
+}
+
+impl SampleType {
+    fn from_ftype(ftype: u32) -> Result<Self> {
+        match ftype {
+            1 => Ok(SampleType::S8),
+            2 => Ok(SampleType::U8),
+            // Signed 16-bit, big- or little-endian.
+            3 | 5 => Ok(SampleType::S16),
+            // Unsigned 16-bit, big- or little-endian.
+            4 | 6 => Ok(SampleType::U16),
+            _ => unsupported_error("shorten: unsupported sample type"),
+        }
+    }
+
+    /// Gets the number of bits per sample.
+    pub fn bits_per_sample(&self) -> u32 {
+        match self {
+            SampleType::S8 | SampleType::U8 => 8,
+            SampleType::S16 | SampleType::U16 => 16,
+        }
+    }
+
+    /// Gets the bias that must be subtracted from a sample to make it signed.
+    pub fn bias(&self) -> i32 {
+        match self {
+            SampleType::S8 | SampleType::S16 => 0,
+            SampleType::U8 => 0x80,
+            SampleType::U16 => 0x8000,
+        }
+    }
+}
+
+/// A Shorten bitstream command.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Diff0,
+    Diff1,
+    Diff2,
+    Diff3,
+    Quit,
+    BlockSize,
+    BitShift,
+    Qlpc,
+    Zero,
+    Verbatim,
+}
+
+/// A bit reader for the Shorten bitstream.
+///
+/// Shorten blocks are not byte-aligned. This trait abstracts over the bit reader used by the
+/// decoder, and the bit reader used by the demuxer to split the bitstream into packets.
+pub trait ShortenBitRead {
+    /// Reads up-to 32 bits.
+    fn read_bits(&mut self, bit_width: u32) -> Result<u32>;
+
+    /// Reads a unary zeros encoded integer.
+    fn read_unary(&mut self) -> Result<u32>;
+
+    /// Reads an unsigned variable-length integer with `k` low-order bits.
+    fn read_uvar(&mut self, k: u32) -> Result<u32> {
+        let high = self.read_unary()?;
+        let low = self.read_bits(k)?;
+
+        // Validate the high part of the value will not overflow.
+        if k >= 32 || high > (u32::MAX >> k) {
+            return decode_error("shorten: variable-length integer overflow");
+        }
+
+        Ok((high << k) | low)
+    }
+
+    /// Reads a signed variable-length integer with `k` low-order bits.
+    fn read_svar(&mut self, k: u32) -> Result<i32> {
+        let value = self.read_uvar(k + 1)?;
+
+        // The sign is stored in the least-significant bit.
+        if value & 1 == 0 {
+            Ok((value >> 1) as i32)
+        }
+        else {
+            Ok(!(value >> 1) as i32)
+        }
+    }
+
+    /// Reads an unsigned long.
+    fn read_ulong(&mut self, version: u8, k: u32) -> Result<u32> {
+        // Version 0 streams store unsigned longs as plain variable-length integers. In later
+        // versions, the fixed parameter is stored before the value.
+        let k = if version > 0 { self.read_uvar(ULONGSIZE)? } else { k };
+
+        if k > 31 {
+            return decode_error("shorten: invalid unsigned long");
+        }
+
+        self.read_uvar(k)
+    }
+
+    /// Reads a command.
+    fn read_command(&mut self) -> Result<Command> {
+        let cmd = match self.read_uvar(FNSIZE)? {
+            0 => Command::Diff0,
+            1 => Command::Diff1,
+            2 => Command::Diff2,
+            3 => Command::Diff3,
+            4 => Command::Quit,
+            5 => Command::BlockSize,
+            6 => Command::BitShift,
+            7 => Command::Qlpc,
+            8 => Command::Zero,
+            9 => Command::Verbatim,
+            _ => return decode_error("shorten: invalid command"),
+        };
+        Ok(cmd)
+    }
+
+    /// Reads the energy (Rice parameter) of a residual.
+    fn read_energy(&mut self, version: u8) -> Result<u32> {
+        let energy = self.read_uvar(ENERGYSIZE)?;
+
+        // Version 0 streams store the energy with an extra bit.
+        let energy = if version == 0 { energy.wrapping_sub(1) } else { energy };
+
+        if energy > 30 {
+            return decode_error("shorten: invalid residual energy");
+        }
+
+        Ok(energy)
+    }
+
+    /// Reads the order of a QLPC block.
+    fn read_lpc_order(&mut self, max_order: u32) -> Result<u32> {
+        let order = self.read_uvar(LPCQSIZE)?;
+
+        if order > max_order {
+            return decode_error("shorten: lpc order exceeds the maximum order");
+        }
+
+        Ok(order)
+    }
+
+    /// Reads the block size given in a block size command.
+    fn read_block_size(&mut self, version: u8, block_size: u32) -> Result<u32> {
+        let k = 31 - block_size.leading_zeros();
+        let block_size = self.read_ulong(version, k)?;
+
+        if block_size == 0 || block_size > MAX_BLOCK_SIZE {
+            return decode_error("shorten: invalid block size");
+        }
+
+        Ok(block_size)
+    }
+
+    /// Reads the bit shift given in a bit shift command.
+    fn read_bit_shift(&mut self) -> Result<u32> {
+        let shift = self.read_uvar(BITSHIFTSIZE)?;
+
+        if shift > 32 {
+            return decode_error("shorten: invalid bit shift");
+        }
+
+        Ok(shift)
+    }
+
+    /// Reads the contents of a verbatim command.
+    fn read_verbatim(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_uvar(VERBATIM_CKSIZE_SIZE)?;
+
+        if len > MAX_VERBATIM_LEN {
+            return decode_error("shorten: verbatim chunk too large");
+        }
+
+        (0..len).map(|_| self.read_uvar(VERBATIM_BYTE_SIZE).map(|byte| byte as u8)).collect()
+    }
+}
+
+impl<'a> ShortenBitRead for BitReaderLtr<'a> {
+    fn read_bits(&mut self, bit_width: u32) -> Result<u32> {
+        Ok(self.read_bits_leq32(bit_width)?)
+    }
+
+    fn read_unary(&mut self) -> Result<u32> {
+        Ok(self.read_unary_zeros()?)
+    }
+}
+
+/// The Shorten stream header.
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub version: u8,
+    pub sample_type: SampleType,
+    pub n_channels: u32,
+    pub block_size: u32,
+    pub max_lpc_order: u32,
+    pub n_mean: u32,
+}
+
+impl Header {
+    /// Reads the header parameters that follow the marker and version.
+    pub fn read<B: ShortenBitRead>(bs: &mut B, version: u8) -> Result<Header> {
+        if version > MAX_VERSION {
+            return unsupported_error("shorten: unsupported version");
+        }
+
+        let sample_type = SampleType::from_ftype(bs.read_ulong(version, TYPESIZE)?)?;
+
+        let n_channels = bs.read_ulong(version, CHANSIZE)?;
+
+        if n_channels == 0 || n_channels > MAX_CHANNELS {
+            return unsupported_error("shorten: unsupported number of channels");
+        }
+
+        let mut header = Header {
+            version,
+            sample_type,
+            n_channels,
+            block_size: DEFAULT_BLOCK_SIZE,
+            max_lpc_order: 0,
+            n_mean: if version < 2 { 0 } else { 4 },
+        };
+
+        if version > 0 {
+            header.block_size = bs.read_block_size(version, DEFAULT_BLOCK_SIZE)?;
+            header.max_lpc_order = bs.read_ulong(version, LPCQSIZE)?;
+            header.n_mean = bs.read_ulong(version, 0)?;
+
+            if header.max_lpc_order > 1024 || header.n_mean > 32768 {
+                return decode_error("shorten: invalid header");
+            }
+
+            let n_skip = bs.read_ulong(version, NSKIPSIZE)?;
+
+            for _ in 0..n_skip {
+                bs.read_bits(8)?;
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// Reads the header from the codec extra data. The extra data is the stream marker, version,
+    /// and the bitstream coded header parameters.
+    pub fn read_extra_data(buf: &[u8]) -> Result<Header> {
+        if buf.len() < 5 || buf[..4] != SHORTEN_STREAM_MARKER {
+            return decode_error("shorten: invalid extra data");
+        }
+
+        Header::read(&mut BitReaderLtr::new(&buf[5..]), buf[4])
+    }
+
+    /// Gets the number of samples from the previous block required for prediction.
+    pub fn n_wrap(&self) -> u32 {
+        self.max_lpc_order.max(NWRAP)
+    }
+}
+
+/// Skips a block of audio for one channel without decoding it.
+pub fn skip_audio_block<B: ShortenBitRead>(
+    bs: &mut B,
+    cmd: Command,
+    header: &Header,
+    block_size: u32,
+) -> Result<()> {
+    if cmd == Command::Zero {
+        return Ok(());
+    }
+
+    let energy = bs.read_energy(header.version)?;
+
+    if cmd == Command::Qlpc {
+        let order = bs.read_lpc_order(header.n_wrap())?;
+
+        for _ in 0..order {
+            bs.read_svar(LPCQUANT)?;
+        }
+    }
+
+    for _ in 0..block_size {
+        bs.read_svar(energy)?;
+    }
+
+    Ok(())
+}
+
+/// Audio parameters parsed from the verbatim header of the source file.
+#[derive(Default)]
+pub struct SourceInfo {
+    pub sample_rate: Option<u32>,
+    pub n_frames: Option<u64>,
+}
+
+impl SourceInfo {
+    /// Parses the WAVE or AIFF header that Shorten stores verbatim at the start of the stream.
+    pub fn parse<B: ReadBytes>(reader: &mut B) -> Result<SourceInfo> {
+        let mut info: SourceInfo = Default::default();
+
+        match &reader.read_quad_bytes()? {
+            b"RIFF" => {
+                reader.ignore_bytes(4)?;
+
+                if &reader.read_quad_bytes()? != b"WAVE" {
+                    return Ok(info);
+                }
+
+                let mut frame_len = 0;
+
+                // An end-of-stream error simply means the chunk was not found.
+                while let Ok(tag) = reader.read_quad_bytes() {
+                    let len = reader.read_u32()?;
+
+                    match &tag {
+                        b"fmt " => {
+                            reader.ignore_bytes(2)?;
+                            let n_channels = reader.read_u16()?;
+                            info.sample_rate = Some(reader.read_u32()?);
+                            reader.ignore_bytes(4)?;
+                            let block_align = reader.read_u16()?;
+                            reader.ignore_bytes(u64::from(len.saturating_sub(14)))?;
+
+                            if n_channels > 0 {
+                                frame_len = block_align;
+                            }
+                        }
+                        b"data" => {
+                            if frame_len > 0 {
+                                info.n_frames = Some(u64::from(len / u32::from(frame_len)));
+                            }
+                            break;
+                        }
+                        _ => reader.ignore_bytes(u64::from(len) + u64::from(len & 1))?,
+                    }
+                }
+            }
+            b"FORM" => {
+                reader.ignore_bytes(4)?;
+
+                match &reader.read_quad_bytes()? {
+                    b"AIFF" | b"AIFC" => (),
+                    _ => return Ok(info),
+                }
+
+                while let Ok(tag) = reader.read_quad_bytes() {
+                    let len = reader.read_be_u32()?;
+
+                    if &tag == b"COMM" {
+                        reader.ignore_bytes(2)?;
+                        info.n_frames = Some(u64::from(reader.read_be_u32()?));
+                        reader.ignore_bytes(2)?;
+
+                        // The sample rate is an 80-bit extended precision floating point value.
+                        let exp = reader.read_be_u16()? & 0x7fff;
+                        let mantissa = reader.read_be_u64()?;
+
+                        let shift = 16383 + 63 - i32::from(exp);
+
+                        if (0..64).contains(&shift) {
+                            info.sample_rate = Some((mantissa >> shift) as u32);
+                        }
+                        break;
+                    }
+
+                    reader.ignore_bytes(u64::from(len) + u64::from(len & 1))?;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(info)
+    }
+}
@@ -0,0 +1,348 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::support_codec;
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_SHORTEN};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::BitReaderLtr;
+
+use crate::common::*;
+
+/// The coefficients of the fixed polynomial predictors used by the DIFF commands.
+const FIXED_COEFFS: [[i32; 3]; 4] = [[0, 0, 0], [1, 0, 0], [2, -1, 0], [3, -3, 1]];
+
+/// The per-channel decoder state.
+struct ChannelState {
+    /// The decoded samples of the current block, preceeded by `n_wrap` samples of the previous
+    /// block.
+    samples: Vec<i32>,
+    /// The running means of the last `n_mean` blocks.
+    means: Vec<i32>,
+}
+
+/// Shorten decoder.
+pub struct ShortenDecoder {
+    params: CodecParameters,
+    header: Header,
+    channels: Vec<ChannelState>,
+    block_size: u32,
+    bit_shift: u32,
+    buf: AudioBuffer<i32>,
+}
+
+impl ShortenDecoder {
+    fn n_wrap(&self) -> usize {
+        self.header.n_wrap() as usize
+    }
+
+    /// Calculates the DC offset of a channel from the running means.
+    fn offset(&self, ch: usize) -> i32 {
+        let means = &self.channels[ch].means;
+
+        if self.header.n_mean == 0 {
+            return means[0];
+        }
+
+        let n_mean = self.header.n_mean as i32;
+
+        let mut sum = if self.header.version < 2 { 0 } else { n_mean / 2 };
+
+        for &mean in means.iter() {
+            sum = sum.wrapping_add(mean);
+        }
+
+        let offset = sum / n_mean;
+
+        if self.header.version >= 2 && self.bit_shift > 0 {
+            (offset >> (self.bit_shift - 1)) >> 1
+        }
+        else {
+            offset
+        }
+    }
+
+    /// Updates the running means of a channel after a block was decoded.
+    fn update_means(&mut self, ch: usize) {
+        if self.header.n_mean == 0 {
+            return;
+        }
+
+        let n_wrap = self.n_wrap();
+        let block_size = self.block_size as i32;
+
+        let state = &mut self.channels[ch];
+
+        let mut sum = if self.header.version < 2 { 0 } else { block_size / 2 };
+
+        for &sample in &state.samples[n_wrap..n_wrap + self.block_size as usize] {
+            sum = sum.wrapping_add(sample);
+        }
+
+        let mean = if self.header.version < 2 {
+            sum / block_size
+        }
+        else if self.bit_shift == 32 {
+            0
+        }
+        else {
+            (i64::from(sum / block_size) << self.bit_shift) as i32
+        };
+
+        state.means.rotate_left(1);
+        *state.means.last_mut().unwrap() = mean;
+    }
+
+    fn decode_audio_block(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        cmd: Command,
+        ch: usize,
+    ) -> Result<()> {
+        let n_wrap = self.n_wrap();
+        let block_size = self.block_size as usize;
+        let version = self.header.version;
+        let offset = self.offset(ch);
+
+        let samples = &mut self.channels[ch].samples[..n_wrap + block_size];
+
+        if cmd == Command::Zero {
+            samples[n_wrap..].iter_mut().for_each(|s| *s = 0);
+        }
+        else {
+            let energy = bs.read_energy(version)?;
+
+            let mut coeffs = [0i32; 1024];
+
+            let (order, qshift, init_sum) = match cmd {
+                Command::Qlpc => {
+                    let order = bs.read_lpc_order(n_wrap as u32)? as usize;
+
+                    for coeff in coeffs[..order].iter_mut() {
+                        *coeff = bs.read_svar(LPCQUANT)?;
+                    }
+
+                    // Without coefficients, the prediction is the offset, as for DIFF0. Otherwise,
+                    // version 2 and later round the prediction.
+                    let init_sum = if order == 0 {
+                        offset
+                    }
+                    else if version > 1 {
+                        1 << LPCQUANT
+                    }
+                    else {
+                        0
+                    };
+
+                    (order, LPCQUANT, init_sum)
+                }
+                Command::Diff0 => (0, 0, offset),
+                _ => {
+                    let order = match cmd {
+                        Command::Diff1 => 1,
+                        Command::Diff2 => 2,
+                        _ => 3,
+                    };
+
+                    coeffs[..3].copy_from_slice(&FIXED_COEFFS[order]);
+
+                    (order, 0, 0)
+                }
+            };
+
+            // QLPC predicts the signal with the DC offset removed.
+            let remove_offset = cmd == Command::Qlpc && offset != 0;
+
+            if remove_offset {
+                for sample in samples[n_wrap - order..n_wrap].iter_mut() {
+                    *sample = sample.wrapping_sub(offset);
+                }
+            }
+
+            for i in n_wrap..n_wrap + block_size {
+                let mut sum = init_sum;
+
+                for (j, &coeff) in coeffs[..order].iter().enumerate() {
+                    sum = sum.wrapping_add(coeff.wrapping_mul(samples[i - j - 1]));
+                }
+
+                samples[i] = bs.read_svar(energy)?.wrapping_add(sum >> qshift);
+            }
+
+            if remove_offset {
+                for sample in samples[n_wrap..].iter_mut() {
+                    *sample = sample.wrapping_add(offset);
+                }
+            }
+        }
+
+        self.update_means(ch);
+
+        Ok(())
+    }
+
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let mut bs = BitReaderLtr::new(packet.buf());
+
+        let n_channels = self.header.n_channels as usize;
+        let mut ch = 0;
+
+        while ch < n_channels {
+            let cmd = bs.read_command()?;
+
+            match cmd {
+                Command::Quit => return decode_error("shorten: unexpected end of stream"),
+                Command::BlockSize => {
+                    self.block_size = bs.read_block_size(self.header.version, self.block_size)?;
+
+                    // Grow the buffers if the new block size is larger than the current capacity.
+                    if self.block_size as usize > self.buf.capacity() {
+                        self.alloc_buffers();
+                    }
+                }
+                Command::BitShift => self.bit_shift = bs.read_bit_shift()?,
+                Command::Verbatim => {
+                    bs.read_verbatim()?;
+                }
+                _ => {
+                    self.decode_audio_block(&mut bs, cmd, ch)?;
+                    self.write_output(ch);
+                    self.wrap(ch);
+                    ch += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the decoded block of a channel to the output buffer.
+    fn write_output(&mut self, ch: usize) {
+        let n_wrap = self.n_wrap();
+        let block_size = self.block_size as usize;
+
+        if ch == 0 {
+            self.buf.clear();
+            self.buf.render_reserved(Some(block_size));
+        }
+
+        let bias = self.header.sample_type.bias();
+        let shift = 32 - self.header.sample_type.bits_per_sample();
+        let bit_shift = self.bit_shift;
+
+        let samples = &self.channels[ch].samples[n_wrap..n_wrap + block_size];
+
+        for (out, &sample) in self.buf.chan_mut(ch).iter_mut().zip(samples) {
+            // Restore the bits dropped by the encoder, then scale to the full 32-bit range.
+            let sample = if bit_shift == 32 { 0 } else { sample.wrapping_shl(bit_shift) };
+
+            *out = sample.wrapping_sub(bias).wrapping_shl(shift);
+        }
+    }
+
+    /// Saves the end of the decoded block of a channel for prediction in the next block.
+    fn wrap(&mut self, ch: usize) {
+        let n_wrap = self.n_wrap();
+        let block_size = self.block_size as usize;
+
+        self.channels[ch].samples.copy_within(block_size..block_size + n_wrap, 0);
+    }
+
+    fn alloc_buffers(&mut self) {
+        let n_wrap = self.n_wrap();
+        let block_size = self.block_size.max(self.header.block_size) as usize;
+
+        for state in self.channels.iter_mut() {
+            state.samples.resize(n_wrap + block_size, 0);
+        }
+
+        let spec = *self.buf.spec();
+        self.buf = AudioBuffer::new(block_size as u64, spec);
+    }
+}
+
+impl Decoder for ShortenDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        // This decoder only supports Shorten.
+        if params.codec != CODEC_TYPE_SHORTEN {
+            return unsupported_error("shorten: invalid codec type");
+        }
+
+        // The extra data contains the stream header (mandatory).
+        let header = match params.extra_data.as_ref() {
+            Some(buf) => Header::read_extra_data(buf)?,
+            _ => return unsupported_error("shorten: missing extra data"),
+        };
+
+        let channels = match params.channels {
+            Some(channels) if channels.count() == header.n_channels as usize => channels,
+            _ => return unsupported_error("shorten: channels are missing or invalid"),
+        };
+
+        let sample_rate = params.sample_rate.unwrap_or(0);
+
+        let n_wrap = header.n_wrap() as usize;
+        let block_size = header.block_size as usize;
+
+        let channel_states = (0..header.n_channels)
+            .map(|_| ChannelState {
+                samples: vec![0; n_wrap + block_size],
+                means: vec![0; header.n_mean.max(1) as usize],
+            })
+            .collect();
+
+        let buf = AudioBuffer::new(block_size as u64, SignalSpec::new(sample_rate, channels));
+
+        Ok(ShortenDecoder {
+            params: params.clone(),
+            block_size: header.block_size,
+            header,
+            channels: channel_states,
+            bit_shift: 0,
+            buf,
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_SHORTEN, "shorten", "Shorten")]
+    }
+
+    fn reset(&mut self) {
+        for state in self.channels.iter_mut() {
+            state.samples.iter_mut().for_each(|s| *s = 0);
+            state.means.iter_mut().for_each(|m| *m = 0);
+        }
+
+        self.block_size = self.header.block_size;
+        self.bit_shift = 0;
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
@@ -0,0 +1,326 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::support_format;
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_SHORTEN};
+use symphonia_core::errors::{
+    decode_error, end_of_stream_error, seek_error, unsupported_error, Result, SeekErrorKind,
+};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+
+use log::warn;
+
+use crate::common::*;
+
+/// A bit reader that reads the Shorten bitstream from a `ReadBytes`, and records every bit read
+/// so that it may be repackaged into a byte-aligned packet.
+struct RecordingBitReader<'a, B: ReadBytes> {
+    reader: &'a mut B,
+    state: &'a mut BitState,
+    recorder: BitRecorder,
+}
+
+/// The unread bits of a partially consumed byte.
+#[derive(Default)]
+struct BitState {
+    /// Unread bits, left-aligned.
+    bits: u64,
+    /// The number of unread bits.
+    n_bits: u32,
+}
+
+/// Accumulates bits, most-significant bit first, into a byte buffer.
+#[derive(Default)]
+struct BitRecorder {
+    buf: Vec<u8>,
+    bits: u64,
+    n_bits: u32,
+}
+
+impl BitRecorder {
+    fn write_bits(&mut self, value: u32, bit_width: u32) {
+        if bit_width == 0 {
+            return;
+        }
+
+        self.bits |= (u64::from(value) << (64 - bit_width)) >> self.n_bits;
+        self.n_bits += bit_width;
+
+        while self.n_bits >= 8 {
+            self.buf.push((self.bits >> 56) as u8);
+            self.bits <<= 8;
+            self.n_bits -= 8;
+        }
+    }
+
+    fn write_zeros(&mut self, mut count: u32) {
+        while count > 0 {
+            let n = count.min(32);
+            self.write_bits(0, n);
+            count -= n;
+        }
+    }
+
+    fn take(&mut self) -> Box<[u8]> {
+        // Flush the final partial byte.
+        if self.n_bits > 0 {
+            self.buf.push((self.bits >> 56) as u8);
+            self.bits = 0;
+            self.n_bits = 0;
+        }
+
+        std::mem::take(&mut self.buf).into_boxed_slice()
+    }
+}
+
+impl<'a, B: ReadBytes> RecordingBitReader<'a, B> {
+    fn new(reader: &'a mut B, state: &'a mut BitState) -> Self {
+        RecordingBitReader { reader, state, recorder: Default::default() }
+    }
+
+    fn fetch_byte(&mut self) -> Result<()> {
+        let byte = self.reader.read_u8()?;
+        self.state.bits |= u64::from(byte) << (56 - self.state.n_bits);
+        self.state.n_bits += 8;
+        Ok(())
+    }
+}
+
+impl<'a, B: ReadBytes> ShortenBitRead for RecordingBitReader<'a, B> {
+    fn read_bits(&mut self, bit_width: u32) -> Result<u32> {
+        if bit_width == 0 {
+            return Ok(0);
+        }
+
+        while self.state.n_bits < bit_width {
+            self.fetch_byte()?;
+        }
+
+        let value = (self.state.bits >> (64 - bit_width)) as u32;
+
+        self.state.bits <<= bit_width;
+        self.state.n_bits -= bit_width;
+
+        self.recorder.write_bits(value, bit_width);
+
+        Ok(value)
+    }
+
+    fn read_unary(&mut self) -> Result<u32> {
+        let mut count = 0;
+
+        loop {
+            if self.state.n_bits == 0 {
+                self.fetch_byte()?;
+            }
+
+            let n_zeros = self.state.bits.leading_zeros().min(self.state.n_bits);
+
+            if n_zeros < self.state.n_bits {
+                // A 1 bit terminates the zeros.
+                self.state.bits <<= n_zeros + 1;
+                self.state.n_bits -= n_zeros + 1;
+
+                count += n_zeros;
+                break;
+            }
+
+            // All the remaining bits were zeros.
+            count += n_zeros;
+            self.state.bits = 0;
+            self.state.n_bits = 0;
+        }
+
+        self.recorder.write_zeros(count);
+        self.recorder.write_bits(1, 1);
+
+        Ok(count)
+    }
+}
+
+/// Shorten (SHN) format reader.
+///
+/// `ShortenReader` implements a demuxer for the native Shorten stream format. Since Shorten blocks
+/// are not byte-aligned, each block of audio is repackaged into a byte-aligned packet.
+///
+/// Seeking is not supported since Shorten streams do not have a seek table.
+pub struct ShortenReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    header: Header,
+    state: BitState,
+    block_size: u32,
+    next_packet_ts: u64,
+    is_eos: bool,
+}
+
+impl QueryDescriptor for ShortenReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "shorten",
+            "Shorten",
+            &["shn"],
+            &["audio/x-shorten"],
+            &[&SHORTEN_STREAM_MARKER]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for ShortenReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let marker = source.read_quad_bytes()?;
+
+        if marker != SHORTEN_STREAM_MARKER {
+            return unsupported_error("shorten: missing stream marker");
+        }
+
+        let version = source.read_u8()?;
+
+        let mut state: BitState = Default::default();
+        let mut bs = RecordingBitReader::new(&mut source, &mut state);
+
+        let header = Header::read(&mut bs, version)?;
+
+        // The recorded header parameters are passed to the decoder as extra data.
+        let mut extra_data = SHORTEN_STREAM_MARKER.to_vec();
+        extra_data.push(version);
+        extra_data.extend_from_slice(&bs.recorder.take());
+
+        // The first command must be a verbatim copy of the header of the source file. This is
+        // the only source of the sample rate.
+        if bs.read_command()? != Command::Verbatim {
+            return decode_error("shorten: missing verbatim source file header");
+        }
+
+        let verbatim = bs.read_verbatim()?;
+
+        let info = SourceInfo::parse(&mut BufReader::new(&verbatim)).unwrap_or_else(|_| {
+            warn!("malformed source file header");
+            Default::default()
+        });
+
+        let bits_per_sample = header.sample_type.bits_per_sample();
+
+        // Channels are assumed to be in the standard order.
+        let channels = match Channels::from_bits(((1u64 << header.n_channels) - 1) as u32) {
+            Some(channels) => channels,
+            _ => return unsupported_error("shorten: unsupported number of channels"),
+        };
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_SHORTEN)
+            .with_channels(channels)
+            .with_bits_per_sample(bits_per_sample)
+            .with_bits_per_coded_sample(bits_per_sample)
+            .with_max_frames_per_packet(u64::from(MAX_BLOCK_SIZE))
+            .with_extra_data(extra_data.into_boxed_slice());
+
+        if let Some(sample_rate) = info.sample_rate {
+            codec_params
+                .with_sample_rate(sample_rate)
+                .with_time_base(TimeBase::new(1, sample_rate));
+        }
+
+        if let Some(n_frames) = info.n_frames {
+            codec_params.with_n_frames(n_frames);
+        }
+
+        let block_size = header.block_size;
+
+        Ok(ShortenReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            header,
+            state,
+            block_size,
+            next_packet_ts: 0,
+            is_eos: false,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        if self.is_eos {
+            return end_of_stream_error();
+        }
+
+        let mut bs = RecordingBitReader::new(&mut self.reader, &mut self.state);
+
+        let mut n_audio_blocks = 0;
+
+        // A packet contains all commands up-to, and including, the audio block of the last
+        // channel.
+        while n_audio_blocks < self.header.n_channels {
+            let cmd = bs.read_command()?;
+
+            match cmd {
+                Command::Quit => {
+                    self.is_eos = true;
+                    return end_of_stream_error();
+                }
+                Command::BlockSize => {
+                    self.block_size = bs.read_block_size(self.header.version, self.block_size)?;
+                }
+                Command::BitShift => {
+                    bs.read_bit_shift()?;
+                }
+                Command::Verbatim => {
+                    bs.read_verbatim()?;
+                }
+                _ => {
+                    skip_audio_block(&mut bs, cmd, &self.header, self.block_size)?;
+                    n_audio_blocks += 1;
+                }
+            }
+        }
+
+        let buf = bs.recorder.take();
+
+        let ts = self.next_packet_ts;
+        let dur = u64::from(self.block_size);
+
+        self.next_packet_ts += dur;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, _to: SeekTo) -> Result<SeekedTo> {
+        // Shorten predictors depend on all previously decoded samples. Without a seek table
+        // containing the decoder state, seeking is not possible.
+        seek_error(SeekErrorKind::Unseekable)
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
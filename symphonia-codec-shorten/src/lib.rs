@@ -0,0 +1,290 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![warn(rust_2018_idioms)]
+#![forbid(unsafe_code)]
+// The following lints are allowed in all Symphonia crates. Please see clippy.toml for their
+// justification.
+#![allow(clippy::comparison_chain)]
+#![allow(clippy::excessive_precision)]
+#![allow(clippy::identity_op)]
+#![allow(clippy::manual_range_contains)]
+
+mod common;
+mod decoder;
+mod demuxer;
+
+pub use decoder::ShortenDecoder;
+pub use demuxer::ShortenReader;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, Channels, Signal, SignalSpec};
+    use symphonia_core::codecs::{Decoder, DecoderOptions};
+    use symphonia_core::formats::{FormatOptions, FormatReader};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_format_riff::{WavSampleFormat, WavWriter};
+
+    use super::{ShortenDecoder, ShortenReader};
+
+    /// Writes bits, most-significant bit first.
+    #[derive(Default)]
+    struct BitWriter {
+        buf: Vec<u8>,
+        n_bits: u32,
+    }
+
+    impl BitWriter {
+        fn write_bit(&mut self, bit: u32) {
+            if self.n_bits % 8 == 0 {
+                self.buf.push(0);
+            }
+            if bit != 0 {
+                *self.buf.last_mut().unwrap() |= 0x80 >> (self.n_bits % 8);
+            }
+            self.n_bits += 1;
+        }
+
+        fn write_bits(&mut self, value: u32, bit_width: u32) {
+            for i in (0..bit_width).rev() {
+                self.write_bit((value >> i) & 1);
+            }
+        }
+
+        fn write_uvar(&mut self, value: u32, k: u32) {
+            for _ in 0..(value >> k) {
+                self.write_bit(0);
+            }
+            self.write_bit(1);
+            self.write_bits(value, k);
+        }
+
+        fn write_svar(&mut self, value: i32, k: u32) {
+            let value = if value < 0 { ((!value as u32) << 1) | 1 } else { (value as u32) << 1 };
+            self.write_uvar(value, k + 1);
+        }
+
+        fn write_ulong(&mut self, value: u32) {
+            let k = 32 - value.leading_zeros();
+            self.write_uvar(k, 2);
+            self.write_uvar(value, k);
+        }
+    }
+
+    /// Gets the WAVE header of a mono 16-bit 8kHz source file with the given number of frames.
+    fn wave_header(n_frames: u32) -> Vec<u8> {
+        let spec = SignalSpec::new(8000, Channels::FRONT_LEFT);
+
+        let mut buf = AudioBuffer::<i16>::new(u64::from(n_frames), spec);
+        buf.render_reserved(Some(n_frames as usize));
+
+        let mut writer =
+            WavWriter::new(Cursor::new(Vec::new()), spec, WavSampleFormat::S16).unwrap();
+        writer.write(buf.as_audio_buffer_ref()).unwrap();
+
+        let mut wav = writer.finalize().unwrap().into_inner();
+        wav.truncate(44);
+        wav
+    }
+
+    /// Encodes a mono, signed 16-bit, version 2 stream with a block size of 4.
+    fn encode_stream() -> Vec<u8> {
+        let mut bw = BitWriter::default();
+
+        // Header: file type, channels, block size, maximum LPC order, means, and skip bytes.
+        for &value in &[5, 1, 4, 1, 0, 0] {
+            bw.write_ulong(value);
+        }
+
+        // Verbatim source file header.
+        let header = wave_header(14);
+        bw.write_uvar(9, 2);
+        bw.write_uvar(header.len() as u32, 5);
+        for &byte in &header {
+            bw.write_uvar(u32::from(byte), 8);
+        }
+
+        // DIFF1: 1, 2, 3, 4.
+        bw.write_uvar(1, 2);
+        bw.write_uvar(1, 3);
+        for &res in &[1, 1, 1, 1] {
+            bw.write_svar(res, 1);
+        }
+
+        // QLPC with order 1 and a coefficient of 1.0: 5, 6, 8, 11.
+        bw.write_uvar(7, 2);
+        bw.write_uvar(2, 3);
+        bw.write_uvar(1, 2);
+        bw.write_svar(32, 5);
+        for &res in &[0, 0, 1, 2] {
+            bw.write_svar(res, 2);
+        }
+
+        // ZERO: 0, 0, 0, 0.
+        bw.write_uvar(8, 2);
+
+        // BLOCKSIZE 2, then DIFF2: -1, -3.
+        bw.write_uvar(5, 2);
+        bw.write_ulong(2);
+        bw.write_uvar(2, 2);
+        bw.write_uvar(2, 3);
+        for &res in &[-1, -1] {
+            bw.write_svar(res, 2);
+        }
+
+        // QUIT.
+        bw.write_uvar(4, 2);
+
+        let mut stream = b"ajkg\x02".to_vec();
+        stream.extend_from_slice(&bw.buf);
+        stream
+    }
+
+    /// Encodes a mono, signed 16-bit, version 2 stream with a block size of 4 and a running mean
+    /// over 4 blocks. The residuals are derived from the prediction of the reference decoder.
+    fn encode_stream_with_means() -> Vec<u8> {
+        let mut bw = BitWriter::default();
+
+        // Header: file type, channels, block size, maximum LPC order, means, and skip bytes.
+        for &value in &[5, 1, 4, 2, 4, 0] {
+            bw.write_ulong(value);
+        }
+
+        let header = wave_header(20);
+        bw.write_uvar(9, 2);
+        bw.write_uvar(header.len() as u32, 5);
+        for &byte in &header {
+            bw.write_uvar(u32::from(byte), 8);
+        }
+
+        // DIFF0 with an offset of (2 + 0) / 4 = 0: 100, 102, 98, 100. The mean is
+        // (2 + 400) / 4 = 100.
+        bw.write_uvar(0, 2);
+        bw.write_uvar(6, 3);
+        for &res in &[100, 102, 98, 100] {
+            bw.write_svar(res, 6);
+        }
+
+        // QLPC with order 0 and an offset of (2 + 100) / 4 = 25. The prediction is the offset
+        // shifted by the quantization of the coefficients, 25 >> 5 = 0, and the offset is then
+        // added: 120, 130, 125, 125. The mean is (2 + 500) / 4 = 125.
+        bw.write_uvar(7, 2);
+        bw.write_uvar(6, 3);
+        bw.write_uvar(0, 2);
+        for &res in &[95, 105, 100, 100] {
+            bw.write_svar(res, 6);
+        }
+
+        // QLPC with order 2, coefficients of 2.0 and -1.0, and an offset of (2 + 225) / 4 = 56.
+        // The offset is removed from the previous 2 samples, 125, 125, before prediction:
+        // 130, 140, 145, 150. The mean is (2 + 565) / 4 = 141.
+        bw.write_uvar(7, 2);
+        bw.write_uvar(2, 3);
+        bw.write_uvar(2, 2);
+        bw.write_svar(64, 5);
+        bw.write_svar(-32, 5);
+        for &res in &[4, 4, -6, -1] {
+            bw.write_svar(res, 2);
+        }
+
+        // BITSHIFT 1, then DIFF1: 152, 154, 156, 160. The previous sample, 150, is not shifted.
+        // The mean is ((2 + 311) / 4) << 1 = 156.
+        bw.write_uvar(6, 2);
+        bw.write_uvar(1, 2);
+        bw.write_uvar(1, 2);
+        bw.write_uvar(6, 3);
+        for &res in &[-74, 1, 1, 2] {
+            bw.write_svar(res, 6);
+        }
+
+        // DIFF0 with an offset of ((2 + 522) / 4) >> 1 = 65: 130, 132, 128, 130.
+        bw.write_uvar(0, 2);
+        bw.write_uvar(1, 3);
+        for &res in &[0, 1, -1, 0] {
+            bw.write_svar(res, 1);
+        }
+
+        // QUIT.
+        bw.write_uvar(4, 2);
+
+        let mut stream = b"ajkg\x02".to_vec();
+        stream.extend_from_slice(&bw.buf);
+        stream
+    }
+
+    /// Decodes a mono stream, and returns the 16-bit samples.
+    fn decode_stream(stream: Vec<u8>) -> Vec<i32> {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = ShortenReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let params = reader.tracks()[0].codec_params.clone();
+
+        let mut decoder = ShortenDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        let mut samples = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            match decoder.decode(&packet).unwrap() {
+                symphonia_core::audio::AudioBufferRef::S32(buf) => {
+                    samples.extend(buf.chan(0).iter().map(|&s| s >> 16))
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        samples
+    }
+
+    #[test]
+    fn verify_decode_with_means() {
+        #[rustfmt::skip]
+        let expected = [
+            100, 102, 98, 100,
+            120, 130, 125, 125,
+            130, 140, 145, 150,
+            152, 154, 156, 160,
+            130, 132, 128, 130,
+        ];
+
+        assert_eq!(decode_stream(encode_stream_with_means()), expected);
+    }
+
+    #[test]
+    fn verify_decode_bit_exact() {
+        let mss =
+            MediaSourceStream::new(Box::new(Cursor::new(encode_stream())), Default::default());
+
+        let mut reader = ShortenReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let params = reader.tracks()[0].codec_params.clone();
+
+        assert_eq!(params.sample_rate, Some(8000));
+        assert_eq!(params.n_frames, Some(14));
+
+        let mut decoder = ShortenDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        let mut samples = Vec::new();
+        let mut timestamps = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            timestamps.push(packet.ts());
+
+            match decoder.decode(&packet).unwrap() {
+                symphonia_core::audio::AudioBufferRef::S32(buf) => {
+                    samples.extend(buf.chan(0).iter().map(|&s| s >> 16))
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        assert_eq!(timestamps, &[0, 4, 8, 12]);
+        assert_eq!(samples, &[1, 2, 3, 4, 5, 6, 8, 11, 0, 0, 0, 0, -1, -3]);
+    }
+}
@@ -171,6 +171,19 @@ impl Floor0 {
         let floor0_number_of_books = bs.read_bits_leq32(4)? as u8 + 1;
         let mut floor0_book_list = [0; 16];
 
+        // The LSP curve is undefined if any of these are 0.
+        if floor0_order == 0 {
+            return decode_error("vorbis: floor0, invalid order");
+        }
+
+        if floor0_rate == 0 {
+            return decode_error("vorbis: floor0, invalid rate");
+        }
+
+        if floor0_bark_map_size == 0 {
+            return decode_error("vorbis: floor0, invalid bark map size");
+        }
+
         let end = usize::from(floor0_number_of_books);
 
         for book in &mut floor0_book_list[..end] {
@@ -219,6 +232,12 @@ impl Floor for Floor0 {
             let floor_book_idx_bits = ilog(u32::from(self.setup.floor0_number_of_books));
             let floor_book_idx = io_try_or_ret!(bs.read_bits_leq32(floor_book_idx_bits)) as usize;
 
+            // The index may exceed the number of codebooks in the list. The packet is undecodable
+            // in this case, however, libvorbis only treats the floor as unused. Do the same.
+            if floor_book_idx >= usize::from(self.setup.floor0_number_of_books) {
+                return Ok(());
+            }
+
             // Get the actual codebook index from the floor's codebook list.
             let codebook_idx = self.setup.floor0_book_list[floor_book_idx] as usize;
 
@@ -239,6 +258,11 @@ impl Floor for Floor0 {
                 // Read and obtain the VQ vector from the codebook.
                 let vq = try_or_ret!(codebook.read_vq(bs));
 
+                // A codebook with 0 dimensions would never complete the coefficient vector.
+                if vq.is_empty() {
+                    return decode_error("vorbis: floor0, invalid codebook dimensions");
+                }
+
                 // The VQ vector may be much larger (up-to 65535 scalars) than the remaining number
                 // of coefficients (up-to 255 scalars). Cap the amount of coefficients to be
                 // processed.
@@ -820,3 +844,129 @@ fn render_line(x0: u32, y0: i32, x1: u32, y1: i32, n: usize, v: &mut [f32]) {
         *v = FLOOR1_INVERSE_DB_TABLE[y as usize];
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BitReaderRtl;
+
+    use super::{bark_map, Floor, Floor0, Floor0Setup};
+
+    const BS0_EXP: u8 = 8;
+    const BS1_EXP: u8 = 11;
+
+    fn make_floor0(lsp: &[f32], amplitude: u64) -> Floor0 {
+        let setup = Floor0Setup {
+            floor0_order: lsp.len() as u8,
+            floor0_bark_map_size: 256,
+            floor0_amplitude_bits: 6,
+            floor0_amplitude_offset: 100,
+            floor0_number_of_books: 1,
+            floor0_book_list: [0; 16],
+            floor0_map_short_bs_exp: BS0_EXP,
+            floor0_map_short: bark_map(1 << (BS0_EXP - 1), 44100, 256),
+            floor0_map_long: bark_map(1 << (BS1_EXP - 1), 44100, 256),
+        };
+
+        let mut coeffs = [0.0; 256];
+
+        for (coeff, &lsp) in coeffs.iter_mut().zip(lsp) {
+            *coeff = 2.0 * lsp.cos();
+        }
+
+        Floor0 { setup, is_unused: false, amplitude, coeffs }
+    }
+
+    /// Computes the floor curve exactly as described in section 6.2.3 of the Vorbis I
+    /// specification.
+    fn reference_curve(lsp: &[f32], amplitude: u64, map: &[i32]) -> Vec<f64> {
+        let order = lsp.len();
+
+        map.iter()
+            .map(|&map| {
+                let omega = std::f64::consts::PI * f64::from(map) / 256.0;
+                let cos_omega = omega.cos();
+
+                let term = |j: usize| 4.0 * (f64::from(lsp[j]).cos() - cos_omega).powi(2);
+
+                let (p, q) = if order % 2 == 1 {
+                    let p: f64 = (0..(order - 1) / 2).map(|j| term(2 * j + 1)).product();
+                    let q: f64 = (0..(order + 1) / 2).map(|j| term(2 * j)).product();
+                    ((1.0 - cos_omega * cos_omega) * p, 0.25 * q)
+                }
+                else {
+                    let p: f64 = (0..order / 2).map(|j| term(2 * j + 1)).product();
+                    let q: f64 = (0..order / 2).map(|j| term(2 * j)).product();
+                    ((1.0 - cos_omega) / 2.0 * p, (1.0 + cos_omega) / 2.0 * q)
+                };
+
+                let a = amplitude as f64 * 100.0 / (63.0 * (p + q).sqrt());
+
+                (0.11512925 * (a - 100.0)).exp()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify_floor0_synthesis() {
+        let tests: [&[f32]; 3] = [&[0.4, 1.1, 2.3], &[0.2, 0.7, 1.6, 2.8], &[1.3]];
+
+        for lsp in tests.iter() {
+            let mut floor0 = make_floor0(lsp, 40);
+
+            for &bs_exp in &[BS0_EXP, BS1_EXP] {
+                let n = (1 << bs_exp) >> 1;
+
+                let mut curve = vec![0.0; n];
+                floor0.synthesis(bs_exp, &mut curve).unwrap();
+
+                let map = if bs_exp == BS0_EXP {
+                    &floor0.setup.floor0_map_short
+                }
+                else {
+                    &floor0.setup.floor0_map_long
+                };
+
+                let expected = reference_curve(lsp, 40, map);
+
+                for (&actual, &expected) in curve.iter().zip(&expected) {
+                    let error = (f64::from(actual) - expected).abs() / expected;
+                    assert!(error < 1e-3, "actual={}, expected={}", actual, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn verify_bark_map() {
+        let map = bark_map(128, 44100, 256);
+
+        assert_eq!(map.len(), 128);
+        assert_eq!(map[0], 0);
+        assert_eq!(map[127], 255);
+        // The map is monotonically increasing.
+        assert!(map.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn verify_floor0_invalid_setup() {
+        // An order of 0.
+        let buf = [0x00, 0x44, 0xac, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        let mut bs = BitReaderRtl::new(&buf);
+        assert!(Floor0::try_read(&mut bs, BS0_EXP, BS1_EXP, 1).is_err());
+
+        // A rate of 0.
+        let buf = [0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        let mut bs = BitReaderRtl::new(&buf);
+        assert!(Floor0::try_read(&mut bs, BS0_EXP, BS1_EXP, 1).is_err());
+
+        // A bark map size of 0.
+        let buf = [0x02, 0x44, 0xac, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let mut bs = BitReaderRtl::new(&buf);
+        assert!(Floor0::try_read(&mut bs, BS0_EXP, BS1_EXP, 1).is_err());
+
+        // A valid setup.
+        let buf = [0x02, 0x44, 0xac, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        let mut bs = BitReaderRtl::new(&buf);
+        assert!(Floor0::try_read(&mut bs, BS0_EXP, BS1_EXP, 1).is_ok());
+    }
+}
@@ -16,10 +16,13 @@
 // Disable to better express the specification.
 #![allow(clippy::collapsible_else_if)]
 
+use std::time::Instant;
+
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef};
 use symphonia_core::audio::{Signal, SignalSpec};
 use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_VORBIS};
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::diagnostics::{DiagnosticEvent, Diagnostics};
 use symphonia_core::dsp::mdct::Imdct;
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::formats::Packet;
@@ -64,6 +67,8 @@ pub struct VorbisDecoder {
     dsp: Dsp,
     /// Output buffer.
     buf: AudioBuffer<f32>,
+    /// Diagnostics hook.
+    diagnostics: Diagnostics,
 }
 
 impl VorbisDecoder {
@@ -255,8 +260,13 @@ impl VorbisDecoder {
             );
         }
 
-        // Trim
-        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+        // Trim. The trim amounts are clamped to the rendered block since the demuxer may request
+        // more samples be trimmed than were rendered, for example, if the final packet is
+        // truncated by more than its duration.
+        let trim_end = (packet.trim_end() as usize).min(self.buf.frames());
+        let trim_start = (packet.trim_start() as usize).min(self.buf.frames() - trim_end);
+
+        self.buf.trim(trim_start, trim_end);
 
         // Save the new lapping state.
         self.dsp.lapping_state = Some(LappingState { prev_block_flag: mode.block_flag });
@@ -267,50 +277,10 @@ impl VorbisDecoder {
 
 impl Decoder for VorbisDecoder {
     fn try_new(params: &CodecParameters, _: &DecoderOptions) -> Result<Self> {
-        // This decoder only supports Vorbis.
-        if params.codec != CODEC_TYPE_VORBIS {
-            return unsupported_error("vorbis: invalid codec type");
-        }
-
-        // Get the extra data (mandatory).
-        let extra_data = match params.extra_data.as_ref() {
-            Some(buf) => buf,
-            _ => return unsupported_error("vorbis: missing extra data"),
-        };
-
-        // The extra data contains the identification and setup headers.
-        let mut reader = BufReader::new(extra_data);
-
-        // Read ident header.
-        let ident = read_ident_header(&mut reader)?;
-
-        // Read setup data.
-        let setup = read_setup(&mut reader, &ident)?;
-
-        // Initialize static DSP data.
-        let windows = Windows::new(1 << ident.bs0_exp, 1 << ident.bs1_exp);
-
-        // Initialize dynamic DSP for each channel.
-        let dsp_channels =
-            (0..ident.n_channels).map(|_| DspChannel::new(ident.bs0_exp, ident.bs1_exp)).collect();
-
-        // Map the channels
-        let channels = match vorbis_channels_to_channels(ident.n_channels) {
-            Some(channels) => channels,
-            _ => return unsupported_error("vorbis: unknown channel map (fix me)"),
-        };
-
-        // Initialize the output buffer.
-        let spec = SignalSpec::new(ident.sample_rate, channels);
-
-        let imdct_short = Imdct::new((1 << ident.bs0_exp) >> 1);
-        let imdct_long = Imdct::new((1 << ident.bs1_exp) >> 1);
-
-        // TODO: Should this be half the block size?
-        let duration = 1u64 << ident.bs1_exp;
+        let (ident, setup) = read_headers(params)?;
 
-        let dsp =
-            Dsp { windows, channels: dsp_channels, imdct_short, imdct_long, lapping_state: None };
+        let dsp = new_dsp(&ident);
+        let buf = new_output_buffer(&ident)?;
 
         Ok(VorbisDecoder {
             params: params.clone(),
@@ -321,10 +291,48 @@ impl Decoder for VorbisDecoder {
             modes: setup.modes,
             mappings: setup.mappings,
             dsp,
-            buf: AudioBuffer::new(duration, spec),
+            buf,
+            diagnostics: Default::default(),
         })
     }
 
+    fn reset_with_params(&mut self, params: &CodecParameters) -> Result<()> {
+        // The setup is specific to each stream and can never be reused.
+        let (ident, setup) = read_headers(params)?;
+
+        // The DSP state is only dependant on the block sizes and the number of channels.
+        if ident.bs0_exp == self.ident.bs0_exp
+            && ident.bs1_exp == self.ident.bs1_exp
+            && ident.n_channels == self.ident.n_channels
+        {
+            self.dsp.reset();
+        }
+        else {
+            self.dsp = new_dsp(&ident);
+        }
+
+        // The output buffer is additionally dependant on the sample rate.
+        if ident.bs1_exp == self.ident.bs1_exp
+            && ident.n_channels == self.ident.n_channels
+            && ident.sample_rate == self.ident.sample_rate
+        {
+            self.buf.clear();
+        }
+        else {
+            self.buf = new_output_buffer(&ident)?;
+        }
+
+        self.params = params.clone();
+        self.ident = ident;
+        self.codebooks = setup.codebooks;
+        self.floors = setup.floors;
+        self.residues = setup.residues;
+        self.modes = setup.modes;
+        self.mappings = setup.mappings;
+
+        Ok(())
+    }
+
     fn reset(&mut self) {
         self.dsp.reset();
     }
@@ -337,12 +345,41 @@ impl Decoder for VorbisDecoder {
         &self.params
     }
 
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    fn delay(&self) -> u64 {
+        // The first packet only primes the overlap-add and produces no audio, therefore the
+        // decoded audio is not delayed.
+        0
+    }
+
+    fn output_spec(&self) -> Option<SignalSpec> {
+        Some(*self.buf.spec())
+    }
+
+    fn max_frames_per_packet(&self) -> Option<u64> {
+        Some(self.buf.capacity() as u64)
+    }
+
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        let start = if self.diagnostics.is_enabled() { Some(Instant::now()) } else { None };
+
         if let Err(e) = self.decode_inner(packet) {
             self.buf.clear();
             Err(e)
         }
         else {
+            if let Some(start) = start {
+                self.diagnostics.emit(DiagnosticEvent::PacketDecoded {
+                    ts: packet.ts(),
+                    len: packet.buf().len(),
+                    frames: self.buf.frames(),
+                    elapsed: start.elapsed(),
+                });
+            }
+
             Ok(self.buf.as_audio_buffer_ref())
         }
     }
@@ -356,6 +393,63 @@ impl Decoder for VorbisDecoder {
     }
 }
 
+/// Read the identification and setup headers from the extra data of the codec parameters.
+fn read_headers(params: &CodecParameters) -> Result<(IdentHeader, Setup)> {
+    // This decoder only supports Vorbis.
+    if params.codec != CODEC_TYPE_VORBIS {
+        return unsupported_error("vorbis: invalid codec type");
+    }
+
+    // Get the extra data (mandatory).
+    let extra_data = match params.extra_data.as_ref() {
+        Some(buf) => buf,
+        _ => return unsupported_error("vorbis: missing extra data"),
+    };
+
+    // The extra data contains the identification and setup headers.
+    let mut reader = BufReader::new(extra_data);
+
+    // Read ident header.
+    let ident = read_ident_header(&mut reader)?;
+
+    // Read setup data.
+    let setup = read_setup(&mut reader, &ident)?;
+
+    Ok((ident, setup))
+}
+
+/// Instantiate the DSP state for a stream described by the identification header.
+fn new_dsp(ident: &IdentHeader) -> Dsp {
+    // Initialize static DSP data.
+    let windows = Windows::new(1 << ident.bs0_exp, 1 << ident.bs1_exp);
+
+    // Initialize dynamic DSP for each channel.
+    let dsp_channels =
+        (0..ident.n_channels).map(|_| DspChannel::new(ident.bs0_exp, ident.bs1_exp)).collect();
+
+    let imdct_short = Imdct::new((1 << ident.bs0_exp) >> 1);
+    let imdct_long = Imdct::new((1 << ident.bs1_exp) >> 1);
+
+    Dsp { windows, channels: dsp_channels, imdct_short, imdct_long, lapping_state: None }
+}
+
+/// Instantiate the output buffer for a stream described by the identification header.
+fn new_output_buffer(ident: &IdentHeader) -> Result<AudioBuffer<f32>> {
+    // Map the channels
+    let channels = match vorbis_channels_to_channels(ident.n_channels) {
+        Some(channels) => channels,
+        _ => return unsupported_error("vorbis: unsupported number of channels"),
+    };
+
+    // Initialize the output buffer.
+    let spec = SignalSpec::new(ident.sample_rate, channels);
+
+    // TODO: Should this be half the block size?
+    let duration = 1u64 << ident.bs1_exp;
+
+    Ok(AudioBuffer::new(duration, spec))
+}
+
 #[derive(Debug)]
 struct IdentHeader {
     n_channels: u8,
@@ -761,3 +855,261 @@ pub fn map_vorbis_channel(num_channels: u8, ch: usize) -> usize {
 
     usize::from(mapped_ch)
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_VORBIS};
+    use symphonia_core::formats::Packet;
+    use symphonia_utils_xiph::vorbis::vorbis_channels_to_channels;
+
+    use super::{map_vorbis_channel, VorbisDecoder};
+
+    /// Packs values into a buffer, least-significant bit first, as read by `BitReaderRtl`.
+    #[derive(Default)]
+    struct BitPacker {
+        buf: Vec<u8>,
+        n_bits: usize,
+    }
+
+    impl BitPacker {
+        fn write(&mut self, value: u32, bits: u32) {
+            for i in 0..bits {
+                if self.n_bits % 8 == 0 {
+                    self.buf.push(0);
+                }
+
+                let bit = ((value >> i) & 1) as u8;
+                *self.buf.last_mut().unwrap() |= bit << (self.n_bits % 8);
+                self.n_bits += 1;
+            }
+        }
+    }
+
+    /// Builds the identification and setup headers of a minimal Vorbis stream with the given
+    /// number of channels. All channels share a single unused floor 1, and residue 0.
+    fn build_headers(n_channels: u8) -> Vec<u8> {
+        let mut buf = vec![1];
+        buf.extend_from_slice(b"vorbis");
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.push(n_channels);
+        buf.extend_from_slice(&44_100u32.to_le_bytes());
+        buf.extend_from_slice(&[0; 12]);
+        // Both block sizes are 256 samples.
+        buf.push(0x88);
+        buf.push(1);
+
+        buf.push(5);
+        buf.extend_from_slice(b"vorbis");
+
+        let mut bs = BitPacker::default();
+
+        // One codebook with two entries of length 1, and no lookup table.
+        bs.write(0, 8);
+        bs.write(0x564342, 24);
+        bs.write(1, 16);
+        bs.write(2, 24);
+        bs.write(0, 1);
+        bs.write(0, 1);
+        bs.write(0, 5);
+        bs.write(0, 5);
+        bs.write(0, 4);
+        // One time domain transform placeholder.
+        bs.write(0, 6);
+        bs.write(0, 16);
+        // One floor 1 without partitions.
+        bs.write(0, 6);
+        bs.write(1, 16);
+        bs.write(0, 5);
+        bs.write(0, 2);
+        bs.write(7, 4);
+        // One residue 0 without any codebooks.
+        bs.write(0, 6);
+        bs.write(0, 16);
+        bs.write(0, 24);
+        bs.write(0, 24);
+        bs.write(0, 24);
+        bs.write(0, 6);
+        bs.write(0, 8);
+        bs.write(0, 3);
+        bs.write(0, 1);
+        // One mapping with one submap, and no channel coupling.
+        bs.write(0, 6);
+        bs.write(0, 16);
+        bs.write(0, 1);
+        bs.write(0, 1);
+        bs.write(0, 2);
+        bs.write(0, 8);
+        bs.write(0, 8);
+        bs.write(0, 8);
+        // One mode using a short block.
+        bs.write(0, 6);
+        bs.write(0, 1);
+        bs.write(0, 16);
+        bs.write(0, 16);
+        bs.write(0, 8);
+        // Framing flag.
+        bs.write(1, 1);
+
+        buf.extend(bs.buf);
+        buf
+    }
+
+    #[test]
+    fn verify_map_vorbis_channel() {
+        const FL: Channels = Channels::FRONT_LEFT;
+        const FR: Channels = Channels::FRONT_RIGHT;
+        const FC: Channels = Channels::FRONT_CENTRE;
+        const LFE: Channels = Channels::LFE1;
+        const RL: Channels = Channels::REAR_LEFT;
+        const RR: Channels = Channels::REAR_RIGHT;
+        const RC: Channels = Channels::REAR_CENTRE;
+        const SL: Channels = Channels::SIDE_LEFT;
+        const SR: Channels = Channels::SIDE_RIGHT;
+
+        // The channel order for each number of channels as defined in section 4.3.9 of the Vorbis
+        // I specification.
+        let orders: [&[Channels]; 8] = [
+            &[FL],
+            &[FL, FR],
+            &[FL, FC, FR],
+            &[FL, FR, RL, RR],
+            &[FL, FC, FR, RL, RR],
+            &[FL, FC, FR, RL, RR, LFE],
+            &[FL, FC, FR, SL, SR, RC, LFE],
+            &[FL, FC, FR, SL, SR, RL, RR, LFE],
+        ];
+
+        for order in orders.iter() {
+            let num_channels = order.len() as u8;
+            let channels = vorbis_channels_to_channels(num_channels).unwrap();
+
+            // Each Vorbis channel must be mapped to the audio buffer channel for its position.
+            for (ch, &channel) in order.iter().enumerate() {
+                let expected = channels.iter().position(|c| c == channel);
+                assert_eq!(Some(map_vorbis_channel(num_channels, ch)), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_discrete_channels() {
+        // The channel mapping of more than 8 channels is application defined. Each Vorbis channel
+        // is mapped to the audio buffer channel of the same index.
+        let channels = vorbis_channels_to_channels(12).unwrap();
+        assert_eq!(channels, Channels::discrete(12).unwrap());
+
+        for ch in 0..12 {
+            assert_eq!(map_vorbis_channel(12, ch), ch);
+        }
+    }
+
+    #[test]
+    fn verify_decode_discrete_channels() {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_VORBIS).with_extra_data(build_headers(10).into_boxed_slice());
+
+        let mut decoder = VorbisDecoder::try_new(&params, &Default::default()).unwrap();
+
+        // The signal specification and capacity are known before decoding.
+        let spec = decoder.output_spec().unwrap();
+        let max_frames = decoder.max_frames_per_packet().unwrap();
+
+        // An audio packet where the floor of every channel is unused.
+        let mut bs = BitPacker::default();
+        bs.write(0, 1 + 10);
+
+        for i in 0..3 {
+            let packet = Packet::new_from_slice(0, i * 128, 128, &bs.buf);
+
+            let frames = match decoder.decode(&packet).unwrap() {
+                AudioBufferRef::F32(buf) => {
+                    assert_eq!(*buf.spec(), spec);
+                    assert_eq!(buf.capacity() as u64, max_frames);
+
+                    // No channel may be dropped.
+                    assert_eq!(buf.spec().channels, Channels::discrete(10).unwrap());
+                    assert_eq!(buf.planes().planes().len(), 10);
+                    buf.frames()
+                }
+                _ => panic!("expected a f32 audio buffer"),
+            };
+
+            // The first packet only primes the overlap-add.
+            assert_eq!(frames, if i == 0 { 0 } else { 128 });
+        }
+    }
+
+    #[test]
+    fn verify_reset_with_params() {
+        let make_params = |n_channels: u8| {
+            let mut params = CodecParameters::new();
+            params
+                .for_codec(CODEC_TYPE_VORBIS)
+                .with_extra_data(build_headers(n_channels).into_boxed_slice());
+            params
+        };
+
+        let streams = [make_params(2), make_params(2), make_params(1), make_params(10)];
+
+        let mut decoder = VorbisDecoder::try_new(&streams[0], &Default::default()).unwrap();
+
+        for params in streams.iter() {
+            decoder.reset_with_params(params).unwrap();
+
+            let n_channels = decoder.output_spec().unwrap().channels.count();
+
+            // An audio packet where the floor of every channel is unused.
+            let mut bs = BitPacker::default();
+            bs.write(0, 1 + n_channels as u32);
+
+            // The first packet of each stream only primes the overlap-add.
+            for (i, &expected) in [0, 128].iter().enumerate() {
+                let packet = Packet::new_from_slice(0, i as u64 * 128, 128, &bs.buf);
+
+                let decoded = decoder.decode(&packet).unwrap();
+                assert_eq!(decoded.frames(), expected);
+                assert_eq!(decoded.spec().channels.count(), n_channels);
+            }
+        }
+
+        assert_eq!(decoder.codec_params().extra_data, streams[3].extra_data);
+
+        // A decoder cannot be reset for another codec.
+        let mut params = CodecParameters::new();
+        params.with_extra_data(build_headers(2).into_boxed_slice());
+
+        assert!(decoder.reset_with_params(&params).is_err());
+    }
+
+    #[test]
+    fn verify_decode_trim() {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_VORBIS).with_extra_data(build_headers(1).into_boxed_slice());
+
+        let mut decoder = VorbisDecoder::try_new(&params, &Default::default()).unwrap();
+
+        // An audio packet where the floor is unused.
+        let mut bs = BitPacker::default();
+        bs.write(0, 1 + 1);
+
+        // The trim of each packet, and the expected number of decoded frames. A trim exceeding the
+        // rendered block is clamped to the block.
+        let trims = [(0, 0, 0), (28, 0, 100), (0, 100, 28), (0, 1000, 0), (100, 100, 0)];
+
+        let mut n_frames = 0;
+
+        for (i, &(trim_start, trim_end, expected)) in trims.iter().enumerate() {
+            let mut packet = Packet::new_from_slice(0, i as u64 * 128, 128, &bs.buf);
+            packet.trim_start = trim_start;
+            packet.trim_end = trim_end;
+
+            let frames = decoder.decode(&packet).unwrap().frames();
+            assert_eq!(frames, expected);
+
+            n_frames += frames;
+        }
+
+        assert_eq!(n_frames, 128);
+    }
+}
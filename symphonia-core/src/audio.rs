@@ -18,7 +18,7 @@ use arrayvec::ArrayVec;
 use bitflags::bitflags;
 
 use crate::conv::{ConvertibleSample, FromSample, IntoSample};
-use crate::errors::Result;
+use crate::errors::{unsupported_error, Result};
 use crate::sample::{i24, u24, Sample};
 use crate::units::Duration;
 
@@ -89,6 +89,10 @@ bitflags! {
     }
 }
 
+/// The bits of a Microsoft WAVEFORMATEXTENSIBLE channel mask with a defined speaker position. These
+/// are identical to the first 18 channels of `Channels`.
+const WAVE_CHANNEL_MASK: u32 = 0x0003_ffff;
+
 /// An iterator over individual channels within a `Channels` bitmask.
 pub struct ChannelsIter {
     channels: Channels,
@@ -110,6 +114,41 @@ impl Iterator for ChannelsIter {
 }
 
 impl Channels {
+    /// Gets a set of `count` discrete channels, or `None` if `count` is 0 or exceeds the number of
+    /// channel positions.
+    ///
+    /// Discrete channels have no defined speaker positions. They may be used for streams without a
+    /// channel layout, or with an unknown channel layout, such as multichannel stems or ambisonics.
+    /// Since `Channels` is a bitmask of positions, discrete channels occupy the first `count`
+    /// positions, in order. The audio of channel `n` is stored in plane `n` of an audio buffer, and
+    /// its position should not be interpreted as a speaker position.
+    pub fn discrete(count: usize) -> Option<Channels> {
+        if count == 0 || count > 32 {
+            return None;
+        }
+
+        Channels::from_bits(((1u64 << count) - 1) as u32)
+    }
+
+    /// Gets the channels with a speaker position in a Microsoft WAVEFORMATEXTENSIBLE channel mask.
+    ///
+    /// Bits of the channel mask without a defined speaker position, such as `SPEAKER_ALL`, are
+    /// ignored.
+    pub fn from_wave_channel_mask(mask: u32) -> Channels {
+        Channels::from_bits_truncate(mask & WAVE_CHANNEL_MASK)
+    }
+
+    /// Gets the Microsoft WAVEFORMATEXTENSIBLE channel mask of the channels, or `None` if a
+    /// channel has no equivalent speaker position in the channel mask.
+    pub fn to_wave_channel_mask(self) -> Option<u32> {
+        if self.bits & !WAVE_CHANNEL_MASK == 0 {
+            Some(self.bits)
+        }
+        else {
+            None
+        }
+    }
+
     /// Gets the number of channels.
     pub fn count(self) -> usize {
         self.bits.count_ones() as usize
@@ -138,6 +177,9 @@ pub enum Layout {
     TwoPointOne,
     /// Front Left and Right, Rear Left and Right, and a single low-frequency channel.
     FivePointOne,
+    /// Front Left and Right, Rear Left and Right, Side Left and Right, and a single low-frequency
+    /// channel.
+    SevenPointOne,
 }
 
 impl Layout {
@@ -155,6 +197,9 @@ impl Layout {
                     | Channels::REAR_RIGHT
                     | Channels::LFE1
             }
+            Layout::SevenPointOne => {
+                Layout::FivePointOne.into_channels() | Channels::SIDE_LEFT | Channels::SIDE_RIGHT
+            }
         }
     }
 }
@@ -180,6 +225,73 @@ impl SignalSpec {
     }
 }
 
+/// `DownmixOptions` is a common set of options for downmixing an audio buffer.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DownmixOptions {
+    /// Mix the low-frequency effects (LFE) channel into the output at -3 dB instead of dropping
+    /// it. Default: `false`.
+    pub include_lfe: bool,
+}
+
+/// Gets the downmix coefficients for each channel of `src` when downmixing to `dest`. Each
+/// coefficient pair contains the gain applied to the source channel for the first and second
+/// destination channel.
+///
+/// The coefficients are those of ITU-R BS.775. The source must contain the front left and right
+/// channels, and optionally, a front centre channel, a LFE channel, and a pair of rear or side
+/// surround channels. The destination must be stereo or mono.
+fn downmix_coefficients(
+    src: Channels,
+    dest: Channels,
+    options: &DownmixOptions,
+) -> Result<Vec<[f64; 2]>> {
+    const MINUS_3DB: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+    let front = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+    let rear = Channels::REAR_LEFT | Channels::REAR_RIGHT;
+    let side = Channels::SIDE_LEFT | Channels::SIDE_RIGHT;
+    let supported = front | Channels::FRONT_CENTRE | Channels::LFE1 | rear | side;
+
+    // Surround channels must be paired, and only one pair of surround channels is supported.
+    let surround = src & (rear | side);
+
+    let is_paired = surround.is_empty() || surround == rear || surround == side;
+
+    if !src.contains(front) || !supported.contains(src) || !is_paired {
+        return unsupported_error("core (audio): unsupported downmix source channel layout");
+    }
+
+    let lfe = if options.include_lfe { MINUS_3DB } else { 0.0 };
+
+    let coefficients = if dest == front {
+        src.iter()
+            .map(|channel| match channel {
+                Channels::FRONT_LEFT => [1.0, 0.0],
+                Channels::FRONT_RIGHT => [0.0, 1.0],
+                Channels::FRONT_CENTRE => [MINUS_3DB, MINUS_3DB],
+                Channels::LFE1 => [lfe, lfe],
+                Channels::REAR_LEFT | Channels::SIDE_LEFT => [MINUS_3DB, 0.0],
+                _ => [0.0, MINUS_3DB],
+            })
+            .collect()
+    }
+    else if dest.count() == 1 {
+        src.iter()
+            .map(|channel| match channel {
+                Channels::FRONT_LEFT | Channels::FRONT_RIGHT => [MINUS_3DB, 0.0],
+                Channels::FRONT_CENTRE => [1.0, 0.0],
+                Channels::LFE1 => [lfe, 0.0],
+                _ => [0.5, 0.0],
+            })
+            .collect()
+    }
+    else {
+        return unsupported_error("core (audio): unsupported downmix destination channel layout");
+    };
+
+    Ok(coefficients)
+}
+
 /// Small-storage optimization capable storage of immutable slices of `AudioBuffer` audio planes.
 enum AudioPlaneStorage<'a, S, const N: usize> {
     Stack(ArrayVec<&'a [S], N>),
@@ -395,8 +507,128 @@ impl<S: Sample> AudioBuffer<S> {
     pub fn make_equivalent<E: Sample>(&self) -> AudioBuffer<E> {
         AudioBuffer::<E>::new(self.n_capacity as Duration, self.spec)
     }
+
+    /// Gets an iterator over the samples of all audio planes in interleaved channel order. Each
+    /// sample is converted into the sample format `T`.
+    ///
+    /// Unlike copying the buffer into a `SampleBuffer`, no intermediate buffer is required.
+    /// However, samples are converted one at a time, and consecutive samples are read from
+    /// different audio planes. Therefore, the cost per sample is higher than that of a bulk copy.
+    ///
+    /// Only the frames written to the buffer are iterated. A buffer without any channels yields
+    /// no samples.
+    pub fn iter_interleaved<T: Sample>(&self) -> InterleavedIter<'_, S, T>
+    where
+        S: IntoSample<T>,
+    {
+        InterleavedIter::new(self)
+    }
+
+    /// Downmixes the audio buffer into a new audio buffer with the channels specified.
+    ///
+    /// The source buffer may be stereo, quadraphonic, or 5.1 (with or without the LFE channel),
+    /// and the destination must be stereo or mono. The ITU-R BS.775 downmix coefficients are used.
+    /// The coefficients are not normalized, therefore the mix may exceed full-scale. Integer
+    /// sample formats are clipped, while floating-point sample formats are not.
+    ///
+    /// Returns an unsupported error if the source or destination channel layout is unsupported.
+    pub fn downmix(&self, channels: Channels, options: &DownmixOptions) -> Result<AudioBuffer<S>>
+    where
+        S: ConvertibleSample + IntoSample<f64>,
+    {
+        let coefficients = downmix_coefficients(self.spec.channels, channels, options)?;
+
+        let spec = SignalSpec::new(self.spec.rate, channels);
+
+        let mut dest = AudioBuffer::new(self.n_capacity as Duration, spec);
+        dest.render_reserved(Some(self.n_frames));
+
+        let mut mix = vec![0.0; self.n_frames];
+
+        for d in 0..channels.count() {
+            mix.iter_mut().for_each(|m| *m = 0.0);
+
+            for (s, gains) in coefficients.iter().enumerate() {
+                let gain = gains[d];
+
+                if gain != 0.0 {
+                    for (m, &sample) in mix.iter_mut().zip(self.chan(s)) {
+                        let sample: f64 = sample.into_sample();
+                        *m += gain * sample;
+                    }
+                }
+            }
+
+            for (sample, &m) in dest.chan_mut(d).iter_mut().zip(&mix) {
+                *sample = S::from_sample(m);
+            }
+        }
+
+        Ok(dest)
+    }
+}
+
+/// `InterleavedIter` is an iterator over the samples of an `AudioBuffer` in interleaved channel
+/// order.
+pub struct InterleavedIter<'a, S: Sample, T: Sample> {
+    buf: &'a [S],
+    n_capacity: usize,
+    n_channels: usize,
+    n_frames: usize,
+    frame: usize,
+    ch: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, S: Sample, T: Sample> InterleavedIter<'a, S, T> {
+    fn new(buf: &'a AudioBuffer<S>) -> Self {
+        let n_channels = buf.spec.channels.count();
+
+        // If there are no channels, then there are no samples to iterate.
+        let n_frames = if n_channels > 0 { buf.n_frames } else { 0 };
+
+        InterleavedIter {
+            buf: &buf.buf,
+            n_capacity: buf.n_capacity,
+            n_channels,
+            n_frames,
+            frame: 0,
+            ch: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<S: Sample + IntoSample<T>, T: Sample> Iterator for InterleavedIter<'_, S, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.frame >= self.n_frames {
+            return None;
+        }
+
+        let sample = self.buf[self.ch * self.n_capacity + self.frame];
+
+        // Advance to the next channel, or the first channel of the next frame.
+        self.ch += 1;
+
+        if self.ch == self.n_channels {
+            self.ch = 0;
+            self.frame += 1;
+        }
+
+        Some(sample.into_sample())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.n_frames - self.frame) * self.n_channels - self.ch;
+        (len, Some(len))
+    }
 }
 
+impl<S: Sample + IntoSample<T>, T: Sample> ExactSizeIterator for InterleavedIter<'_, S, T> {}
+
 macro_rules! impl_audio_buffer_ref_func {
     ($var:expr, $buf:ident,$expr:expr) => {
         match $var {
@@ -466,8 +698,161 @@ impl<'a> AudioBufferRef<'a> {
     pub fn make_equivalent<E: Sample>(&self) -> AudioBuffer<E> {
         impl_audio_buffer_ref_func!(self, buf, buf.make_equivalent::<E>())
     }
+
+    /// Converts the reference into an owned `AudioBufferRef` with the same sample format. If the
+    /// buffer is borrowed, it will be cloned.
+    ///
+    /// This allows the audio returned by a `Decoder` to be retained after the next packet is
+    /// decoded.
+    pub fn into_owned(self) -> AudioBufferRef<'static> {
+        match self {
+            AudioBufferRef::U8(buf) => AudioBufferRef::U8(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::U16(buf) => AudioBufferRef::U16(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::U24(buf) => AudioBufferRef::U24(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::U32(buf) => AudioBufferRef::U32(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::S8(buf) => AudioBufferRef::S8(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::S16(buf) => AudioBufferRef::S16(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::S24(buf) => AudioBufferRef::S24(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::S32(buf) => AudioBufferRef::S32(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::F32(buf) => AudioBufferRef::F32(Cow::Owned(buf.into_owned())),
+            AudioBufferRef::F64(buf) => AudioBufferRef::F64(Cow::Owned(buf.into_owned())),
+        }
+    }
+
+    /// Downmixes the audio buffer into a new owned `AudioBufferRef` with the same sample format
+    /// and the channels specified.
+    ///
+    /// See [`AudioBuffer::downmix`] for details.
+    pub fn downmix(
+        &self,
+        channels: Channels,
+        opts: &DownmixOptions,
+    ) -> Result<AudioBufferRef<'static>> {
+        let buf = match self {
+            AudioBufferRef::U8(buf) => AudioBufferRef::U8(Cow::Owned(buf.downmix(channels, opts)?)),
+            AudioBufferRef::U16(buf) => {
+                AudioBufferRef::U16(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+            AudioBufferRef::U24(buf) => {
+                AudioBufferRef::U24(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+            AudioBufferRef::U32(buf) => {
+                AudioBufferRef::U32(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+            AudioBufferRef::S8(buf) => AudioBufferRef::S8(Cow::Owned(buf.downmix(channels, opts)?)),
+            AudioBufferRef::S16(buf) => {
+                AudioBufferRef::S16(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+            AudioBufferRef::S24(buf) => {
+                AudioBufferRef::S24(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+            AudioBufferRef::S32(buf) => {
+                AudioBufferRef::S32(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+            AudioBufferRef::F32(buf) => {
+                AudioBufferRef::F32(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+            AudioBufferRef::F64(buf) => {
+                AudioBufferRef::F64(Cow::Owned(buf.downmix(channels, opts)?))
+            }
+        };
+
+        Ok(buf)
+    }
+
+    /// Gets an iterator over the samples of all audio planes in interleaved channel order. Each
+    /// sample is converted into the sample format `T`.
+    ///
+    /// See [`AudioBuffer::iter_interleaved`] for details.
+    pub fn iter_interleaved<T: ConvertibleSample>(&self) -> InterleavedRefIter<'_, T> {
+        let inner = match self {
+            AudioBufferRef::U8(buf) => InterleavedRefIterInner::U8(buf.iter_interleaved()),
+            AudioBufferRef::U16(buf) => InterleavedRefIterInner::U16(buf.iter_interleaved()),
+            AudioBufferRef::U24(buf) => InterleavedRefIterInner::U24(buf.iter_interleaved()),
+            AudioBufferRef::U32(buf) => InterleavedRefIterInner::U32(buf.iter_interleaved()),
+            AudioBufferRef::S8(buf) => InterleavedRefIterInner::S8(buf.iter_interleaved()),
+            AudioBufferRef::S16(buf) => InterleavedRefIterInner::S16(buf.iter_interleaved()),
+            AudioBufferRef::S24(buf) => InterleavedRefIterInner::S24(buf.iter_interleaved()),
+            AudioBufferRef::S32(buf) => InterleavedRefIterInner::S32(buf.iter_interleaved()),
+            AudioBufferRef::F32(buf) => InterleavedRefIterInner::F32(buf.iter_interleaved()),
+            AudioBufferRef::F64(buf) => InterleavedRefIterInner::F64(buf.iter_interleaved()),
+        };
+
+        InterleavedRefIter { inner }
+    }
+
+    /// Truncates the buffer to the number of frames specified. If the number of frames in the
+    /// buffer is less-than the number of frames specified, then this function does nothing.
+    ///
+    /// If the buffer is borrowed, and truncation is required, the buffer will first be cloned.
+    pub fn truncate(&mut self, n_frames: usize) {
+        if n_frames < self.frames() {
+            impl_audio_buffer_ref_func!(self, buf, buf.to_mut().truncate(n_frames))
+        }
+    }
+
+    /// Shifts the contents of the buffer back by the number of frames specified. The leading frames
+    /// are dropped from the buffer.
+    ///
+    /// If the buffer is borrowed, and shifting is required, the buffer will first be cloned.
+    pub fn shift(&mut self, shift: usize) {
+        if shift > 0 {
+            impl_audio_buffer_ref_func!(self, buf, buf.to_mut().shift(shift))
+        }
+    }
+}
+
+enum InterleavedRefIterInner<'a, T: Sample> {
+    U8(InterleavedIter<'a, u8, T>),
+    U16(InterleavedIter<'a, u16, T>),
+    U24(InterleavedIter<'a, u24, T>),
+    U32(InterleavedIter<'a, u32, T>),
+    S8(InterleavedIter<'a, i8, T>),
+    S16(InterleavedIter<'a, i16, T>),
+    S24(InterleavedIter<'a, i24, T>),
+    S32(InterleavedIter<'a, i32, T>),
+    F32(InterleavedIter<'a, f32, T>),
+    F64(InterleavedIter<'a, f64, T>),
 }
 
+macro_rules! impl_interleaved_ref_iter_func {
+    ($var:expr, $iter:ident, $expr:expr) => {
+        match $var {
+            InterleavedRefIterInner::U8($iter) => $expr,
+            InterleavedRefIterInner::U16($iter) => $expr,
+            InterleavedRefIterInner::U24($iter) => $expr,
+            InterleavedRefIterInner::U32($iter) => $expr,
+            InterleavedRefIterInner::S8($iter) => $expr,
+            InterleavedRefIterInner::S16($iter) => $expr,
+            InterleavedRefIterInner::S24($iter) => $expr,
+            InterleavedRefIterInner::S32($iter) => $expr,
+            InterleavedRefIterInner::F32($iter) => $expr,
+            InterleavedRefIterInner::F64($iter) => $expr,
+        }
+    };
+}
+
+/// `InterleavedRefIter` is an iterator over the samples of an `AudioBufferRef` in interleaved
+/// channel order.
+pub struct InterleavedRefIter<'a, T: Sample> {
+    inner: InterleavedRefIterInner<'a, T>,
+}
+
+impl<T: ConvertibleSample> Iterator for InterleavedRefIter<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        impl_interleaved_ref_iter_func!(&mut self.inner, iter, iter.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        impl_interleaved_ref_iter_func!(&self.inner, iter, iter.size_hint())
+    }
+}
+
+impl<T: ConvertibleSample> ExactSizeIterator for InterleavedRefIter<'_, T> {}
+
 /// `AsAudioBufferRef` is a trait implemented for `AudioBuffer`s that may be referenced in an
 /// `AudioBufferRef`.
 pub trait AsAudioBufferRef {
@@ -717,6 +1102,8 @@ impl<S: Sample> Signal<S> for AudioBuffer<S> {
 pub struct SampleBuffer<S: Sample> {
     buf: Box<[S]>,
     n_written: usize,
+    /// The number of frames per channel if the samples were copied in planar channel order.
+    n_planar_frames: Option<usize>,
 }
 
 impl<S: Sample> SampleBuffer<S> {
@@ -737,7 +1124,7 @@ impl<S: Sample> SampleBuffer<S> {
         // Allocate enough memory for all the samples and fill the buffer with silence.
         let buf = vec![S::MID; n_samples as usize].into_boxed_slice();
 
-        SampleBuffer { buf, n_written: 0 }
+        SampleBuffer { buf, n_written: 0, n_planar_frames: None }
     }
 
     /// Gets the number of written samples.
@@ -760,6 +1147,38 @@ impl<S: Sample> SampleBuffer<S> {
         &mut self.buf[..self.n_written]
     }
 
+    /// Gets an immutable slice of the written samples of a channel.
+    ///
+    /// Returns `None` if the channel does not exist, no samples were written, or the samples were
+    /// not copied in planar channel order.
+    pub fn chan(&self, channel: usize) -> Option<&[S]> {
+        let n_frames = self.n_planar_frames?;
+        let start = channel * n_frames;
+
+        if start < self.n_written {
+            Some(&self.buf[start..start + n_frames])
+        }
+        else {
+            None
+        }
+    }
+
+    /// Gets a mutable slice of the written samples of a channel.
+    ///
+    /// Returns `None` if the channel does not exist, no samples were written, or the samples were
+    /// not copied in planar channel order.
+    pub fn chan_mut(&mut self, channel: usize) -> Option<&mut [S]> {
+        let n_frames = self.n_planar_frames?;
+        let start = channel * n_frames;
+
+        if start < self.n_written {
+            Some(&mut self.buf[start..start + n_frames])
+        }
+        else {
+            None
+        }
+    }
+
     /// Gets the maximum number of samples the `SampleBuffer` may store.
     pub fn capacity(&self) -> usize {
         self.buf.len()
@@ -768,10 +1187,13 @@ impl<S: Sample> SampleBuffer<S> {
     /// Clears all written samples.
     pub fn clear(&mut self) {
         self.n_written = 0;
+        self.n_planar_frames = None;
     }
 
     /// Copies all audio data from the source `AudioBufferRef` in planar channel order into the
     /// `SampleBuffer`. The two buffers must be equivalent.
+    ///
+    /// After copying, the samples of each channel may be accessed with [`SampleBuffer::chan`].
     pub fn copy_planar_ref(&mut self, src: AudioBufferRef)
     where
         S: ConvertibleSample,
@@ -814,6 +1236,7 @@ impl<S: Sample> SampleBuffer<S> {
 
         // Commit the written samples.
         self.n_written = n_samples;
+        self.n_planar_frames = Some(n_frames);
     }
 
     /// Copies all audio data from the source `AudioBufferRef` in interleaved channel order into the
@@ -860,6 +1283,7 @@ impl<S: Sample> SampleBuffer<S> {
 
         // Commit the written samples.
         self.n_written = n_samples;
+        self.n_planar_frames = None;
     }
 }
 
@@ -997,6 +1421,8 @@ impl RawSample for f64 {
 pub struct RawSampleBuffer<S: Sample + RawSample> {
     buf: Box<[S::RawType]>,
     n_written: usize,
+    /// The number of frames per channel if the samples were copied in planar channel order.
+    n_planar_frames: Option<usize>,
     // Might take your heart.
     sample_format: PhantomData<S>,
 }
@@ -1022,7 +1448,7 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         // Allocate enough memory for all the samples and fill the buffer with silence.
         let buf = vec![S::MID.into_raw_sample(); n_samples as usize].into_boxed_slice();
 
-        RawSampleBuffer { buf, n_written: 0, sample_format: PhantomData }
+        RawSampleBuffer { buf, n_written: 0, n_planar_frames: None, sample_format: PhantomData }
     }
 
     /// Gets the number of written samples.
@@ -1043,6 +1469,7 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
     /// Clears all written samples.
     pub fn clear(&mut self) {
         self.n_written = 0;
+        self.n_planar_frames = None;
     }
 
     /// Gets an immutable slice to the bytes of the sample's written in the `RawSampleBuffer`.
@@ -1053,8 +1480,28 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         bytemuck::cast_slice(&self.buf[..self.n_written])
     }
 
+    /// Gets an immutable slice to the bytes of the samples of a channel written in the
+    /// `RawSampleBuffer`.
+    ///
+    /// Returns `None` if the channel does not exist, no samples were written, or the samples were
+    /// not copied in planar channel order.
+    pub fn chan_as_bytes(&self, channel: usize) -> Option<&[u8]> {
+        let n_frames = self.n_planar_frames?;
+        let start = channel * n_frames;
+
+        if start < self.n_written {
+            Some(bytemuck::cast_slice(&self.buf[start..start + n_frames]))
+        }
+        else {
+            None
+        }
+    }
+
     /// Copies all audio data from the source `AudioBufferRef` in planar channel order into the
     /// `RawSampleBuffer`. The two buffers must be equivalent.
+    ///
+    /// After copying, the samples of each channel may be accessed with
+    /// [`RawSampleBuffer::chan_as_bytes`].
     pub fn copy_planar_ref(&mut self, src: AudioBufferRef)
     where
         S: ConvertibleSample,
@@ -1087,17 +1534,16 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         // of samples that will be copied from the source buffer.
         assert!(self.capacity() >= n_samples);
 
-        let dst_buf = &mut self.buf[..n_samples];
-
-        for (ch, dst_ch) in dst_buf.chunks_exact_mut(src.n_frames).enumerate() {
-            let src_ch = src.chan(ch);
+        for ch in 0..n_channels {
+            let dst_ch = &mut self.buf[ch * src.n_frames..(ch + 1) * src.n_frames];
 
-            for (&s, d) in src_ch.iter().zip(dst_ch) {
+            for (&s, d) in src.chan(ch).iter().zip(dst_ch) {
                 *d = s.into_sample().into_raw_sample();
             }
         }
 
         self.n_written = n_samples;
+        self.n_planar_frames = Some(src.n_frames);
     }
 
     /// Copies all audio data from the source `AudioBuffer` to the `RawSampleBuffer` in planar order.
@@ -1110,17 +1556,16 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         // of samples that will be copied from the source buffer.
         assert!(self.capacity() >= n_samples);
 
-        let dst_buf = &mut self.buf[..n_samples];
-
-        for (ch, dst_ch) in dst_buf.chunks_exact_mut(src.n_frames).enumerate() {
-            let src_ch = src.chan(ch);
+        for ch in 0..n_channels {
+            let dst_ch = &mut self.buf[ch * src.n_frames..(ch + 1) * src.n_frames];
 
-            for (&s, d) in src_ch.iter().zip(dst_ch) {
+            for (&s, d) in src.chan(ch).iter().zip(dst_ch) {
                 *d = s.into_raw_sample();
             }
         }
 
         self.n_written = n_samples;
+        self.n_planar_frames = Some(src.n_frames);
     }
 
     /// Copies all audio data from the source `AudioBufferRef` in interleaved channel order into the
@@ -1195,6 +1640,7 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         }
 
         self.n_written = n_samples;
+        self.n_planar_frames = None;
     }
 
     /// Copies all audio data from the source `AudioBuffer` to the `RawSampleBuffer` in interleaved
@@ -1245,5 +1691,386 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         }
 
         self.n_written = n_samples;
+        self.n_planar_frames = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::mem;
+
+    use super::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec};
+    use super::{DownmixOptions, Layout, RawSampleBuffer, SampleBuffer};
+    use crate::conv::FromSample;
+    use crate::errors::Error;
+    use crate::sample::{i24, u24, Sample};
+
+    fn verify_into_owned_for<S: Sample + FromSample<f32>>()
+    where
+        AudioBuffer<S>: AsAudioBufferRef,
+    {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<S>::new(8, spec);
+        buf.render_reserved(Some(5));
+
+        for ch in 0..2 {
+            for (i, s) in buf.chan_mut(ch).iter_mut().enumerate() {
+                *s = S::from_sample(0.125 * i as f32 - 0.25 * ch as f32);
+            }
+        }
+
+        let borrowed = buf.as_audio_buffer_ref();
+        let owned = borrowed.clone().into_owned();
+
+        // The owned buffer has the same sample format.
+        assert_eq!(mem::discriminant(&borrowed), mem::discriminant(&owned));
+        assert!(impl_audio_buffer_ref_func!(&owned, buf, matches!(buf, Cow::Owned(_))));
+
+        assert_eq!(owned.spec(), borrowed.spec());
+        assert_eq!(owned.frames(), 5);
+
+        // The samples are identical.
+        let mut expected = buf.make_equivalent::<f64>();
+        let mut actual = buf.make_equivalent::<f64>();
+
+        borrowed.convert(&mut expected);
+        owned.convert(&mut actual);
+
+        for ch in 0..2 {
+            assert_eq!(actual.chan(ch), expected.chan(ch));
+        }
+
+        // The owned buffer outlives the original buffer.
+        drop(buf);
+        assert_eq!(owned.frames(), 5);
+    }
+
+    #[test]
+    fn verify_into_owned() {
+        verify_into_owned_for::<u8>();
+        verify_into_owned_for::<u16>();
+        verify_into_owned_for::<u24>();
+        verify_into_owned_for::<u32>();
+        verify_into_owned_for::<i8>();
+        verify_into_owned_for::<i16>();
+        verify_into_owned_for::<i24>();
+        verify_into_owned_for::<i32>();
+        verify_into_owned_for::<f32>();
+        verify_into_owned_for::<f64>();
+    }
+
+    #[test]
+    fn verify_discrete_channels() {
+        assert_eq!(Channels::discrete(0), None);
+        assert_eq!(Channels::discrete(1), Some(Channels::FRONT_LEFT));
+
+        let channels = Channels::discrete(12).unwrap();
+        assert_eq!(channels.count(), 12);
+        assert_eq!(channels.iter().last(), Some(Channels::TOP_CENTRE));
+
+        assert_eq!(Channels::discrete(26).map(|channels| channels.count()), Some(26));
+        assert_eq!(Channels::discrete(27), None);
+    }
+
+    #[test]
+    fn verify_wave_channel_mask() {
+        // Stereo, 5.1, and 7.1 masks as defined by KSAUDIO_SPEAKER_STEREO, KSAUDIO_SPEAKER_5POINT1,
+        // and KSAUDIO_SPEAKER_7POINT1_SURROUND.
+        let layouts = [
+            (Layout::Stereo, 0x0003),
+            (Layout::FivePointOne, 0x003f),
+            (Layout::SevenPointOne, 0x063f),
+        ];
+
+        for &(layout, mask) in &layouts {
+            let channels = Channels::from_wave_channel_mask(mask);
+
+            assert_eq!(channels, layout.into_channels());
+            assert_eq!(channels.to_wave_channel_mask(), Some(mask));
+        }
+
+        // The 5.1 side layout.
+        let channels = Channels::from_wave_channel_mask(0x060f);
+        assert_eq!(channels.iter().last(), Some(Channels::SIDE_RIGHT));
+        assert_eq!(channels.to_wave_channel_mask(), Some(0x060f));
+
+        // Bits without a speaker position are ignored.
+        assert_eq!(Channels::from_wave_channel_mask(0x8004_0003), layouts[0].0.into_channels());
+
+        // Channels defined by Symphonia have no equivalent speaker position.
+        let channels = Channels::FRONT_LEFT | Channels::FRONT_LEFT_WIDE;
+        assert_eq!(channels.to_wave_channel_mask(), None);
+        assert_eq!(Channels::TOP_REAR_RIGHT.to_wave_channel_mask(), Some(0x0002_0000));
+    }
+
+    #[test]
+    fn verify_s32_to_f64_sample_buffer() {
+        let spec = SignalSpec::new(48000, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        // The middle samples cannot be represented exactly by an f32.
+        let left = [i32::MAX, 0x1234_5679, 0, i32::MIN];
+        let right = [i32::MIN, -0x1234_5679, 1, i32::MAX];
+
+        let mut buf = AudioBuffer::<i32>::new(4, spec);
+        buf.render_reserved(Some(4));
+        buf.chan_mut(0).copy_from_slice(&left);
+        buf.chan_mut(1).copy_from_slice(&right);
+
+        let mut samples = SampleBuffer::<f64>::new(4, spec);
+        samples.copy_interleaved_ref(buf.as_audio_buffer_ref());
+
+        let expected: Vec<f64> = left
+            .iter()
+            .zip(&right)
+            .flat_map(|(&l, &r)| vec![l, r])
+            .map(|s| f64::from(s) / 2_147_483_648.0)
+            .collect();
+
+        assert_eq!(samples.samples(), &expected[..]);
+        assert_eq!(samples.samples()[0], 2_147_483_647.0 / 2_147_483_648.0);
+        assert_eq!(samples.samples()[1], -1.0);
+
+        // Converting back to i32 is lossless.
+        for (&s, &t) in samples.samples().iter().step_by(2).zip(&left) {
+            assert_eq!(i32::from_sample(s), t);
+        }
+    }
+
+    #[test]
+    fn verify_f64_sample_buffer_round_trip() {
+        let spec = SignalSpec::new(48000, Channels::FRONT_LEFT);
+
+        let src = [0.1, -1.0 / 3.0, 1.0, -1.0, 1e-300, 0.999_999_999_999];
+
+        let mut buf = AudioBuffer::<f64>::new(src.len() as u64, spec);
+        buf.render_reserved(Some(src.len()));
+        buf.chan_mut(0).copy_from_slice(&src);
+
+        let mut samples = SampleBuffer::<f64>::new(src.len() as u64, spec);
+        samples.copy_interleaved_ref(buf.as_audio_buffer_ref());
+
+        assert_eq!(samples.samples(), &src[..]);
+
+        let mut raw = RawSampleBuffer::<f64>::new(src.len() as u64, spec);
+        raw.copy_planar_ref(buf.as_audio_buffer_ref());
+
+        let expected: Vec<u8> = src.iter().flat_map(|s| s.to_ne_bytes().to_vec()).collect();
+
+        assert_eq!(raw.as_bytes(), &expected[..]);
+    }
+
+    #[test]
+    fn verify_iter_interleaved() {
+        let spec = SignalSpec::new(
+            48000,
+            Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE,
+        );
+
+        // Only 5 of the 8 frames of capacity are written.
+        let mut buf = AudioBuffer::<i16>::new(8, spec);
+        buf.render_reserved(Some(5));
+
+        for ch in 0..3 {
+            for (i, s) in buf.chan_mut(ch).iter_mut().enumerate() {
+                *s = (1000 * ch + i) as i16 - 1500;
+            }
+        }
+
+        let buf = buf.as_audio_buffer_ref();
+
+        let mut expected = SampleBuffer::<f32>::new(8, spec);
+        expected.copy_interleaved_ref(buf.clone());
+
+        let iter = buf.iter_interleaved::<f32>();
+        assert_eq!(iter.len(), 15);
+
+        let samples: Vec<f32> = iter.collect();
+        assert_eq!(&samples, expected.samples());
+
+        let mut expected = SampleBuffer::<u8>::new(8, spec);
+        expected.copy_interleaved_ref(buf.clone());
+
+        let samples: Vec<u8> = buf.iter_interleaved().collect();
+        assert_eq!(&samples, expected.samples());
+    }
+
+    #[test]
+    fn verify_iter_interleaved_no_channels() {
+        // An unused buffer has no channels.
+        let buf = AudioBuffer::<f32>::unused();
+
+        let mut iter = buf.iter_interleaved::<i32>();
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(buf.as_audio_buffer_ref().iter_interleaved::<i32>().count(), 0);
+    }
+
+    /// Builds an audio buffer with the channels specified, where `planes` contains the samples of
+    /// each channel.
+    fn build_buffer<S: Sample>(channels: Channels, planes: &[&[S]]) -> AudioBuffer<S> {
+        let n_frames = planes[0].len();
+
+        let mut buf = AudioBuffer::<S>::new(n_frames as u64, SignalSpec::new(48000, channels));
+        buf.render_reserved(None);
+
+        for (ch, plane) in planes.iter().enumerate() {
+            buf.chan_mut(ch).copy_from_slice(plane);
+        }
+
+        buf
+    }
+
+    fn assert_planes_eq(buf: &AudioBuffer<f32>, expected: &[&[f32]]) {
+        assert_eq!(buf.spec().channels.count(), expected.len());
+
+        for (ch, plane) in expected.iter().enumerate() {
+            assert_eq!(buf.chan(ch).len(), plane.len());
+
+            for (&actual, &expected) in buf.chan(ch).iter().zip(plane.iter()) {
+                assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_downmix_5_1_to_stereo() {
+        const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+        let stereo = Layout::Stereo.into_channels();
+
+        // Front left, front right, front centre, LFE, rear left, and rear right.
+        let (fl, fr, c, lfe, rl, rr) = (0.1, -0.2, 0.3, 0.4, 0.05, -0.06);
+
+        let buf = build_buffer(
+            Layout::FivePointOne.into_channels(),
+            &[&[fl, 0.0], &[fr, 0.0], &[c, 0.0], &[lfe, 0.0], &[rl, 0.0], &[rr, 0.25]],
+        );
+
+        // The LFE channel is dropped by default.
+        let left = fl + MINUS_3DB * c + MINUS_3DB * rl;
+        let right = fr + MINUS_3DB * c + MINUS_3DB * rr;
+
+        let mixed = buf.downmix(stereo, &Default::default()).unwrap();
+        assert_eq!(mixed.spec(), &SignalSpec::new(48000, stereo));
+        assert_planes_eq(&mixed, &[&[left, 0.0], &[right, MINUS_3DB * 0.25]]);
+
+        // If included, the LFE channel is mixed into both channels at -3 dB.
+        let options = DownmixOptions { include_lfe: true };
+
+        let mixed = buf.downmix(stereo, &options).unwrap();
+        assert_planes_eq(
+            &mixed,
+            &[&[left + MINUS_3DB * lfe, 0.0], &[right + MINUS_3DB * lfe, MINUS_3DB * 0.25]],
+        );
+    }
+
+    #[test]
+    fn verify_downmix() {
+        const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+        let mono = Layout::Mono.into_channels();
+        let stereo = Layout::Stereo.into_channels();
+        let quad = stereo | Channels::SIDE_LEFT | Channels::SIDE_RIGHT;
+
+        // 5.1 to mono.
+        let buf = build_buffer(
+            Layout::FivePointOne.into_channels(),
+            &[&[0.1], &[0.2], &[0.3], &[0.4], &[0.05], &[0.06]],
+        );
+
+        let mixed = buf.downmix(mono, &Default::default()).unwrap();
+        assert_planes_eq(&mixed, &[&[MINUS_3DB * (0.1 + 0.2) + 0.3 + 0.5 * (0.05 + 0.06)]]);
+
+        // Quadraphonic to stereo.
+        let buf = build_buffer(quad, &[&[0.1], &[0.2], &[0.3], &[0.4]]);
+
+        let mixed = buf.downmix(stereo, &Default::default()).unwrap();
+        assert_planes_eq(&mixed, &[&[0.1 + MINUS_3DB * 0.3], &[0.2 + MINUS_3DB * 0.4]]);
+
+        // Stereo to mono.
+        let buf = build_buffer(stereo, &[&[0.5, -0.5], &[0.25, 0.5]]);
+
+        let mixed = buf.downmix(mono, &Default::default()).unwrap();
+        assert_planes_eq(&mixed, &[&[MINUS_3DB * 0.75, 0.0]]);
+
+        // Integer sample formats are clipped, and the sample format is retained.
+        let buf = build_buffer::<i16>(stereo, &[&[i16::MAX], &[i16::MAX]]);
+
+        let mixed = buf.as_audio_buffer_ref().downmix(mono, &Default::default()).unwrap();
+        assert!(matches!(&mixed, AudioBufferRef::S16(buf) if buf.chan(0) == [i16::MAX]));
+
+        // Unsupported source and destination channel layouts.
+        let surround = stereo | Channels::REAR_LEFT | Channels::REAR_RIGHT;
+
+        for (src, dest) in [
+            (mono, mono),
+            (stereo | Channels::REAR_LEFT, stereo),
+            (quad | Channels::REAR_LEFT | Channels::REAR_RIGHT, stereo),
+            (surround | Channels::REAR_CENTRE, stereo),
+            (surround, surround),
+        ]
+        .iter()
+        {
+            let buf = AudioBuffer::<f32>::new(1, SignalSpec::new(48000, *src));
+
+            let result = buf.downmix(*dest, &Default::default());
+            assert!(matches!(result, Err(Error::Unsupported(_))));
+        }
+    }
+
+    #[test]
+    fn verify_planar_sample_buffer() {
+        let channels = Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE;
+        let spec = SignalSpec::new(48000, channels);
+
+        // Only 5 of the 8 frames of capacity are written.
+        let mut buf = AudioBuffer::<i16>::new(8, spec);
+        buf.render_reserved(Some(5));
+
+        for ch in 0..3 {
+            for (i, s) in buf.chan_mut(ch).iter_mut().enumerate() {
+                *s = (1000 * ch + i) as i16;
+            }
+        }
+
+        let mut interleaved = SampleBuffer::<f32>::new(8, spec);
+        interleaved.copy_interleaved_typed(&buf);
+
+        let mut planar = SampleBuffer::<f32>::new(8, spec);
+        planar.copy_planar_ref(buf.as_audio_buffer_ref());
+
+        let mut raw_planar = RawSampleBuffer::<f32>::new(8, spec);
+        raw_planar.copy_planar_ref(buf.as_audio_buffer_ref());
+
+        assert_eq!(planar.len(), 15);
+
+        // Each channel of the planar buffers must match the de-interleaved interleaved buffer.
+        for ch in 0..3 {
+            let expected: Vec<f32> =
+                interleaved.samples().iter().skip(ch).step_by(3).copied().collect();
+            assert_eq!(expected.len(), 5);
+
+            assert_eq!(planar.chan(ch), Some(&expected[..]));
+
+            let raw: &[u8] = bytemuck::cast_slice(&expected);
+            assert_eq!(raw_planar.chan_as_bytes(ch), Some(raw));
+        }
+
+        assert_eq!(planar.chan(3), None);
+        assert_eq!(raw_planar.chan_as_bytes(3), None);
+
+        // The channels of an interleaved buffer cannot be accessed.
+        assert_eq!(interleaved.chan(0), None);
+
+        planar.copy_interleaved_ref(buf.as_audio_buffer_ref());
+        assert_eq!(planar.chan(0), None);
+
+        // An empty audio buffer can be copied.
+        buf.clear();
+        raw_planar.copy_planar_ref(buf.as_audio_buffer_ref());
+        assert!(raw_planar.is_empty());
     }
 }
@@ -12,11 +12,12 @@ use std::collections::HashMap;
 use std::default::Default;
 use std::fmt;
 
-use crate::audio::{AudioBufferRef, Channels, Layout};
+use crate::audio::{AudioBufferRef, Channels, Layout, SignalSpec};
+use crate::diagnostics::Diagnostics;
 use crate::errors::{unsupported_error, Result};
-use crate::formats::Packet;
+use crate::formats::{FormatReader, Packet, SeekedTo};
 use crate::sample::SampleFormat;
-use crate::units::TimeBase;
+use crate::units::{Time, TimeBase};
 
 /// A `CodecType` is a unique identifier used to identify a specific codec.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -177,6 +178,20 @@ pub const CODEC_TYPE_ADPCM_MS: CodecType = CodecType(0x203);
 pub const CODEC_TYPE_ADPCM_IMA_WAV: CodecType = CodecType(0x204);
 /// ADPCM IMA QuickTime
 pub const CODEC_TYPE_ADPCM_IMA_QT: CodecType = CodecType(0x205);
+/// Sony PlayStation ADPCM (VAG/XA)
+pub const CODEC_TYPE_ADPCM_PSX: CodecType = CodecType(0x206);
+
+// DSD (1-bit) audio codecs
+//-------------------------
+
+/// DSD (Direct Stream Digital), least-significant bit first, interleaved
+pub const CODEC_TYPE_DSD_LSBF: CodecType = CodecType(0x300);
+/// DSD (Direct Stream Digital), least-significant bit first, planar
+pub const CODEC_TYPE_DSD_LSBF_PLANAR: CodecType = CodecType(0x301);
+/// DSD (Direct Stream Digital), most-significant bit first, interleaved
+pub const CODEC_TYPE_DSD_MSBF: CodecType = CodecType(0x302);
+/// DSD (Direct Stream Digital), most-significant bit first, planar
+pub const CODEC_TYPE_DSD_MSBF_PLANAR: CodecType = CodecType(0x303);
 
 // Compressed lossy audio codecs
 //------------------------------
@@ -213,6 +228,8 @@ pub const CODEC_TYPE_AC4: CodecType = CodecType(0x100d);
 pub const CODEC_TYPE_DCA: CodecType = CodecType(0x100e);
 /// Windows Media Audio
 pub const CODEC_TYPE_WMA: CodecType = CodecType(0x100f);
+/// Quite OK Audio (QOA)
+pub const CODEC_TYPE_QOA: CodecType = CodecType(0x1010);
 
 // Compressed lossless audio codecs
 //---------------------------------
@@ -227,6 +244,20 @@ pub const CODEC_TYPE_MONKEYS_AUDIO: CodecType = CodecType(0x2002);
 pub const CODEC_TYPE_ALAC: CodecType = CodecType(0x2003);
 /// True Audio (TTA)
 pub const CODEC_TYPE_TTA: CodecType = CodecType(0x2004);
+/// Shorten (SHN)
+pub const CODEC_TYPE_SHORTEN: CodecType = CodecType(0x2005);
+
+// Subtitle codecs
+//----------------
+
+/// UTF-8 plain text subtitles, such as SubRip (SRT) cues without timing information
+pub const CODEC_TYPE_TEXT_UTF8: CodecType = CodecType(0x3000);
+/// SubStation Alpha (SSA) and Advanced SubStation Alpha (ASS) subtitles
+pub const CODEC_TYPE_SSA: CodecType = CodecType(0x3001);
+/// Web Video Text Tracks (WebVTT) subtitles
+pub const CODEC_TYPE_WEBVTT: CodecType = CodecType(0x3002);
+/// Presentation Graphic Stream (PGS) bitmap subtitles, as used by Blu-ray
+pub const CODEC_TYPE_HDMV_PGS: CodecType = CodecType(0x3003);
 
 /// A method and expected value to perform verification on the decoded audio.
 #[derive(Copy, Clone, Debug)]
@@ -246,6 +277,7 @@ pub enum VerificationCheck {
 /// Codec parameters stored in a container format's headers and metadata may be passed to a codec
 /// using the `CodecParameters` structure.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct CodecParameters {
     /// The codec type.
     pub codec: CodecType,
@@ -266,6 +298,11 @@ pub struct CodecParameters {
     /// timestamp.
     pub n_frames: Option<u64>,
 
+    /// If `true`, `n_frames` is an estimate of the length of the stream rather than an exact value.
+    /// For example, the length may have been estimated from the average bitrate and the length in
+    /// bytes of the stream.
+    pub n_frames_is_estimate: bool,
+
     /// The timestamp of the first frame.
     pub start_ts: u64,
 
@@ -304,6 +341,16 @@ pub struct CodecParameters {
     pub frames_per_block: Option<u64>,
 
     /// Extra data (defined by the codec).
+    ///
+    /// The extra data is the codec-specific configuration required to decode the stream. It is
+    /// provided in the same form regardless of the container the stream was read from. For
+    /// example:
+    ///
+    /// * FLAC: the body of the stream information metadata block.
+    /// * Vorbis: the identification header packet followed by the setup header packet.
+    /// * Opus: the identification header packet (`OpusHead`).
+    /// * AAC: the audio specific configuration.
+    /// * ALAC: the magic cookie.
     pub extra_data: Option<Box<[u8]>>,
 }
 
@@ -314,6 +361,7 @@ impl CodecParameters {
             sample_rate: None,
             time_base: None,
             n_frames: None,
+            n_frames_is_estimate: false,
             start_ts: 0,
             sample_format: None,
             bits_per_sample: None,
@@ -351,6 +399,15 @@ impl CodecParameters {
     /// Provide the total number of frames.
     pub fn with_n_frames(&mut self, n_frames: u64) -> &mut Self {
         self.n_frames = Some(n_frames);
+        self.n_frames_is_estimate = false;
+        self
+    }
+
+    /// Provide an estimate of the total number of frames. Use only if the exact number of frames
+    /// is unknown.
+    pub fn with_estimated_n_frames(&mut self, n_frames: u64) -> &mut Self {
+        self.n_frames = Some(n_frames);
+        self.n_frames_is_estimate = true;
         self
     }
 
@@ -442,17 +499,57 @@ impl Default for CodecParameters {
 /// `FinalizeResult` contains optional information that can only be found, calculated, or
 /// determined after decoding is complete.
 #[derive(Copy, Clone, Debug, Default)]
+#[non_exhaustive]
 pub struct FinalizeResult {
     /// If verification is enabled and supported by the decoder, provides the verification result
     /// if available.
     pub verify_ok: Option<bool>,
+    /// If supported by the decoder, provides the number of packets that failed an integrity check,
+    /// such as a CRC mismatch, and were therefore not decoded.
+    pub n_corrupt_packets: Option<u64>,
+}
+
+impl FinalizeResult {
+    /// Provide the verification result.
+    pub fn with_verify_ok(&mut self, verify_ok: bool) -> &mut Self {
+        self.verify_ok = Some(verify_ok);
+        self
+    }
+
+    /// Provide the number of packets that failed an integrity check.
+    pub fn with_n_corrupt_packets(&mut self, n_corrupt_packets: u64) -> &mut Self {
+        self.n_corrupt_packets = Some(n_corrupt_packets);
+        self
+    }
 }
 
 /// `DecoderOptions` is a common set of options that all decoders use.
 #[derive(Copy, Clone, Debug, Default)]
+#[non_exhaustive]
 pub struct DecoderOptions {
     /// The decoded audio should be verified if possible during the decode process.
     pub verify: bool,
+    /// Output integer samples at the native bit-depth of the encoded audio. Default: `false`.
+    ///
+    /// By default, a decoder that outputs integer samples in a sample format wider than the
+    /// bit-depth of the encoded audio scales the samples up to the full range of the sample
+    /// format. When enabled, decoders that support this option will instead output the samples
+    /// unscaled, such that the bit-depth given by `CodecParameters::bits_per_sample` applies.
+    pub enable_native_bit_depth: bool,
+}
+
+impl DecoderOptions {
+    /// Verify the decoded audio if possible.
+    pub fn with_verify(&mut self, verify: bool) -> &mut Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Output integer samples at the native bit-depth of the encoded audio.
+    pub fn with_native_bit_depth(&mut self, enable: bool) -> &mut Self {
+        self.enable_native_bit_depth = enable;
+        self
+    }
 }
 
 /// A `Decoder` implements a codec's decode algorithm. It consumes `Packet`s and produces
@@ -477,10 +574,70 @@ pub trait Decoder: Send + Sync {
     /// amount of state.
     fn reset(&mut self);
 
+    /// Reset the `Decoder` and reconfigure it to decode a new stream using the provided
+    /// `CodecParameters`.
+    ///
+    /// This allows a single `Decoder` to be reused across many streams of the same codec. If the
+    /// new parameters are compatible with the current configuration, the decoder should reuse its
+    /// internal buffers. Otherwise, the decoder should be fully reinitialized. On error, the
+    /// decoder is left in an unspecified state and should be discarded.
+    ///
+    /// The default implementation returns an unsupported error. In that case, a new `Decoder`
+    /// must be instantiated instead.
+    fn reset_with_params(&mut self, params: &CodecParameters) -> Result<()> {
+        let _ = params;
+        unsupported_error("core (codec): decoder cannot be reset with new parameters")
+    }
+
     /// Gets a reference to an updated set of `CodecParameters` based on the parameters the
     /// `Decoder` was instantiated with.
     fn codec_params(&self) -> &CodecParameters;
 
+    /// Sets the diagnostics hook the decoder emits events to. Events are emitted for the packets
+    /// decoded after the hook is set.
+    ///
+    /// The default implementation ignores the hook for decoders that do not emit diagnostic
+    /// events.
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        let _ = diagnostics;
+    }
+
+    /// Gets the inherent delay, in audio frames, of the decoded audio.
+    ///
+    /// Some codecs have an algorithmic delay such that the decoded audio lags the encoded audio by
+    /// a fixed number of audio frames. For gapless playback, this delay is usually included in the
+    /// encoder delay signalled by the container (see `CodecParameters::delay`). However, it may be
+    /// used to compensate for the latency of the decoder when that information is not available,
+    /// or to synchronize the decoded audio with other media.
+    ///
+    /// The default implementation returns 0 for decoders without delay, or for which the delay is
+    /// not known.
+    fn delay(&self) -> u64 {
+        0
+    }
+
+    /// Gets the signal specification of the decoded audio, if it is known before decoding.
+    ///
+    /// Together with [`Decoder::max_frames_per_packet`], this allows an audio output to be opened,
+    /// or buffers to be allocated, before the first packet is decoded. The audio buffers returned
+    /// by `decode` will have this signal specification unless the parameters of the stream change
+    /// mid-stream.
+    ///
+    /// The default implementation returns `None` for decoders where the signal specification is
+    /// only known after decoding.
+    fn output_spec(&self) -> Option<SignalSpec> {
+        None
+    }
+
+    /// Gets the maximum number of audio frames a single packet will be decoded into, if it is
+    /// known before decoding. This is the capacity of the audio buffers returned by `decode`.
+    ///
+    /// The default implementation returns `None` for decoders where the capacity is only known
+    /// after decoding.
+    fn max_frames_per_packet(&self) -> Option<u64> {
+        None
+    }
+
     /// Decodes a `Packet` of audio data and returns a copy-on-write generic (untyped) audio buffer
     /// of the decoded audio.
     ///
@@ -503,6 +660,157 @@ pub trait Decoder: Send + Sync {
     fn last_decoded(&self) -> AudioBufferRef;
 }
 
+/// `DecodeLimit` is a guard that limits the total duration of decoded audio, starting from the
+/// current position, to a fixed number of frames.
+///
+/// Decoded audio buffers are passed through the guard after every call to `Decoder::decode`. The
+/// limit is accounted for using the number of frames actually output by the decoder. Therefore,
+/// packets that produce no audio (e.g., the first packet of a codec that requires warm-up), or
+/// packets that are trimmed for gapless playback, do not count against the limit. The buffer that
+/// crosses the limit is truncated such that exactly the requested number of frames are output.
+#[derive(Copy, Clone, Debug)]
+pub struct DecodeLimit {
+    remaining: u64,
+}
+
+impl DecodeLimit {
+    /// Instantiate a new `DecodeLimit` that stops after `n_frames` frames were decoded.
+    pub fn new(n_frames: u64) -> Self {
+        DecodeLimit { remaining: n_frames }
+    }
+
+    /// Instantiate a new `DecodeLimit` that stops after the given duration of audio, sampled at
+    /// `sample_rate`, was decoded.
+    pub fn from_time(time: Time, sample_rate: u32) -> Self {
+        Self::new(TimeBase::new(1, sample_rate).calc_timestamp(time))
+    }
+
+    /// Gets the number of frames that may still be decoded before the limit is reached.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Returns `true` if the limit was reached and decoding should stop.
+    pub fn is_reached(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Applies the limit to a decoded audio buffer.
+    ///
+    /// If the limit was already reached, `None` is returned and decoding should stop. Otherwise,
+    /// the buffer is returned, truncated if necessary, such that the total number of frames output
+    /// does not exceed the limit.
+    pub fn apply<'a>(&mut self, mut buf: AudioBufferRef<'a>) -> Option<AudioBufferRef<'a>> {
+        if self.is_reached() {
+            return None;
+        }
+
+        let n_frames = buf.frames() as u64;
+
+        if n_frames > self.remaining {
+            buf.truncate(self.remaining as usize);
+            self.remaining = 0;
+        }
+        else {
+            self.remaining -= n_frames;
+        }
+
+        Some(buf)
+    }
+}
+
+/// `SampleSkipper` discards a fixed number of frames from the start of the decoded audio.
+///
+/// After a seek, a `FormatReader` usually seeks to a packet that starts at, or before, the
+/// requested timestamp. Decoding must then resume from that packet, and the frames preceding the
+/// requested timestamp discarded to obtain a sample-accurate seek. `SampleSkipper` discards
+/// entire decoded audio buffers until the buffer containing the requested frame, and then shifts
+/// that buffer such that it starts at the requested frame.
+///
+/// Like `DecodeLimit`, the skipped frames are counted using the number of frames actually output
+/// by the decoder.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SampleSkipper {
+    remaining: u64,
+}
+
+impl SampleSkipper {
+    /// Instantiate a new `SampleSkipper` that discards the first `n_frames` decoded frames.
+    pub fn new(n_frames: u64) -> Self {
+        SampleSkipper { remaining: n_frames }
+    }
+
+    /// Instantiate a new `SampleSkipper` that discards the frames between the timestamp that was
+    /// seeked to, and the timestamp that was required by the seek.
+    pub fn from_seeked_to(seeked_to: &SeekedTo) -> Self {
+        Self::new(seeked_to.required_ts.saturating_sub(seeked_to.actual_ts))
+    }
+
+    /// Gets the number of frames that remain to be skipped.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Returns `true` if all frames were skipped.
+    pub fn is_done(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Applies the skipper to a decoded audio buffer.
+    ///
+    /// If the entire buffer was skipped, `None` is returned. Otherwise, the buffer is returned,
+    /// shifted if necessary, such that it starts with the first frame that is not skipped.
+    pub fn apply<'a>(&mut self, mut buf: AudioBufferRef<'a>) -> Option<AudioBufferRef<'a>> {
+        let n_frames = buf.frames() as u64;
+
+        if self.remaining > 0 && n_frames <= self.remaining {
+            self.remaining -= n_frames;
+            return None;
+        }
+
+        buf.shift(self.remaining as usize);
+        self.remaining = 0;
+
+        Some(buf)
+    }
+
+    /// Reads and decodes packets of the selected track until a decoded audio buffer that is not
+    /// entirely skipped is obtained, and returns it with any remaining frames skipped.
+    ///
+    /// Errors returned by the reader or decoder are returned as-is. Packets that failed to decode
+    /// do not count against the frames to skip.
+    pub fn decode_next<'d>(
+        &mut self,
+        reader: &mut dyn FormatReader,
+        decoder: &'d mut dyn Decoder,
+        track_id: u32,
+    ) -> Result<AudioBufferRef<'d>> {
+        loop {
+            let packet = reader.next_packet()?;
+
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let n_frames = decoder.decode(&packet)?.frames() as u64;
+
+            if self.remaining == 0 || n_frames > self.remaining {
+                break;
+            }
+
+            self.remaining -= n_frames;
+        }
+
+        let decoder: &'d dyn Decoder = decoder;
+
+        let mut buf = decoder.last_decoded();
+        buf.shift(self.remaining as usize);
+        self.remaining = 0;
+
+        Ok(buf)
+    }
+}
+
 /// A `CodecDescriptor` stores a description of a single logical codec. Common information such as
 /// the `CodecType`, a short name, and a long name are provided. The `CodecDescriptor` also provides
 /// an instantiation function. When the instantiation function is called, a `Decoder` for the codec
@@ -536,6 +844,21 @@ impl CodecRegistry {
         self.codecs.get(&codec)
     }
 
+    /// Gets the `CodecDescriptor` for a registered codec by its short name. The short name is
+    /// matched case-insensitively.
+    ///
+    /// Note: A `CodecType` declared with [`decl_codec_type`] may be looked up by its character code
+    /// using [`CodecRegistry::get_codec`].
+    pub fn get_codec_by_name(&self, name: &str) -> Option<&CodecDescriptor> {
+        self.codecs.values().find(|descriptor| descriptor.short_name.eq_ignore_ascii_case(name))
+    }
+
+    /// Gets an iterator over the `CodecDescriptor`s of all registered codecs, in no particular
+    /// order.
+    pub fn codecs(&self) -> impl Iterator<Item = &CodecDescriptor> {
+        self.codecs.values()
+    }
+
     /// Registers all codecs supported by `Decoder`. If a supported codec was previously registered
     /// by another `Decoder` it will be replaced within the registry.
     pub fn register_all<D: Decoder>(&mut self) {
@@ -586,3 +909,146 @@ macro_rules! support_codec {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::audio::{AsAudioBufferRef, AudioBuffer, Signal, SignalSpec};
+    use crate::formats::{SeekMode, SeekTo};
+    use crate::test_util::MockReader;
+
+    /// The number of frames per packet of the mock reader.
+    const PACKET_LEN: u64 = 100;
+
+    /// A decoder that outputs the timestamp of each frame as its sample value.
+    struct TestDecoder {
+        params: CodecParameters,
+        buf: AudioBuffer<i32>,
+    }
+
+    impl Decoder for TestDecoder {
+        fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+            let spec = SignalSpec::new(44_100, Channels::FRONT_LEFT);
+            Ok(TestDecoder { params: params.clone(), buf: AudioBuffer::new(PACKET_LEN, spec) })
+        }
+
+        fn supported_codecs() -> &'static [CodecDescriptor] {
+            &[]
+        }
+
+        fn reset(&mut self) {}
+
+        fn codec_params(&self) -> &CodecParameters {
+            &self.params
+        }
+
+        fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+            self.buf.clear();
+            self.buf.render_reserved(Some(packet.dur() as usize));
+
+            for (i, s) in self.buf.chan_mut(0).iter_mut().enumerate() {
+                *s = (packet.ts() + i as u64) as i32;
+            }
+
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+
+        fn finalize(&mut self) -> FinalizeResult {
+            Default::default()
+        }
+
+        fn last_decoded(&self) -> AudioBufferRef<'_> {
+            self.buf.as_audio_buffer_ref()
+        }
+    }
+
+    #[test]
+    fn verify_decode_limit() {
+        let spec = SignalSpec::new(44_100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut limit = DecodeLimit::from_time(Time::from(1u32), 44_100);
+        assert_eq!(limit.remaining(), 44_100);
+
+        let mut buf = AudioBuffer::<f32>::new(1152, spec);
+        let mut total = 0;
+
+        // The first packet produces no output (warm-up), subsequent packets produce 1152 frames.
+        for i in 0..100 {
+            buf.clear();
+
+            if i > 0 {
+                buf.render_reserved(None);
+            }
+
+            match limit.apply(AudioBufferRef::F32(Cow::Borrowed(&buf))) {
+                Some(decoded) => total += decoded.frames(),
+                None => break,
+            }
+        }
+
+        assert!(limit.is_reached());
+        assert_eq!(total, 44_100);
+    }
+
+    #[test]
+    fn verify_sample_skipper() {
+        let spec = SignalSpec::new(44_100, Channels::FRONT_LEFT);
+
+        let mut skipper = SampleSkipper::new(2000);
+
+        let mut buf = AudioBuffer::<f32>::new(1152, spec);
+        buf.render_reserved(None);
+
+        // The first buffer is skipped entirely, and the second buffer is shifted.
+        assert!(skipper.apply(AudioBufferRef::F32(Cow::Borrowed(&buf))).is_none());
+        assert_eq!(skipper.remaining(), 848);
+
+        let decoded = skipper.apply(AudioBufferRef::F32(Cow::Borrowed(&buf))).unwrap();
+        assert_eq!(decoded.frames(), 304);
+        assert!(skipper.is_done());
+
+        // Subsequent buffers are not modified.
+        let decoded = skipper.apply(AudioBufferRef::F32(Cow::Borrowed(&buf))).unwrap();
+        assert_eq!(decoded.frames(), 1152);
+    }
+
+    #[test]
+    fn verify_sample_skipper_decode_next() {
+        let mut reader = MockReader::interleaved(2, 20, PACKET_LEN);
+        reader.with_seek_preroll(PACKET_LEN);
+
+        let mut decoder =
+            TestDecoder::try_new(&CodecParameters::new(), &Default::default()).unwrap();
+
+        // Seek into the middle of a packet. The reader seeks to the preceding packet.
+        let seeked_to =
+            reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 456, track_id: 1 }).unwrap();
+
+        assert_eq!(seeked_to.actual_ts, 300);
+
+        let mut skipper = SampleSkipper::from_seeked_to(&seeked_to);
+        assert_eq!(skipper.remaining(), 156);
+
+        let decoded = skipper.decode_next(&mut reader, &mut decoder, 1).unwrap();
+
+        match decoded {
+            AudioBufferRef::S32(buf) => {
+                assert_eq!(buf.frames(), 44);
+                assert_eq!(buf.chan(0)[0], 456);
+            }
+            _ => unreachable!(),
+        }
+
+        assert!(skipper.is_done());
+
+        // Once done, the next decoded buffer is returned as-is.
+        match skipper.decode_next(&mut reader, &mut decoder, 1).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                assert_eq!(buf.chan(0), &(500..600).collect::<Vec<_>>()[..])
+            }
+            _ => unreachable!(),
+        }
+    }
+}
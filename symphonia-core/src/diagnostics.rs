@@ -0,0 +1,113 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `diagnostics` module provides an opt-in hook to receive structured diagnostic events from
+//! readers and decoders.
+//!
+//! Unlike logging, diagnostic events are delivered to a handler provided by the application, and
+//! only for the reader or decoder they were enabled for. This allows a tool to build a timeline of
+//! what happened while demuxing and decoding a specific file.
+//!
+//! Diagnostics are enabled by calling `set_diagnostics` on a `FormatReader` or `Decoder`. When
+//! unset, which is the default, the cost of diagnostics is a single branch per event.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A diagnostic event emitted by a reader or decoder.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticEvent {
+    /// A packet was decoded.
+    PacketDecoded {
+        /// The timestamp of the packet.
+        ts: u64,
+        /// The length of the packet in bytes.
+        len: usize,
+        /// The number of audio frames decoded.
+        frames: usize,
+        /// The time taken to decode the packet.
+        elapsed: Duration,
+    },
+    /// A packet was decoded, but some of its audio could not be and was replaced.
+    ErrorConcealed {
+        /// The timestamp of the packet.
+        ts: u64,
+        /// A description of the error that was concealed.
+        reason: &'static str,
+    },
+    /// A reader encountered a change in the stream that requires the tracks to be re-examined, and
+    /// the decoders to be recreated. The next packet will return a `ResetRequired` error.
+    ResetRequired {
+        /// The position of the reader in bytes.
+        pos: u64,
+    },
+    /// A checksum did not match the data it protects.
+    CrcMismatch {
+        /// The position of the reader in bytes where the protected data starts, if known.
+        pos: Option<u64>,
+        /// The timestamp of the packet containing the protected data, if known.
+        ts: Option<u64>,
+        /// The expected checksum.
+        expected: u32,
+        /// The computed checksum.
+        computed: u32,
+    },
+}
+
+/// A `DiagnosticsHandler` receives diagnostic events.
+///
+/// A handler may be called from any thread a reader or decoder is used on, and therefore must be
+/// `Send` and `Sync`. It is implemented for all closures taking a `&DiagnosticEvent`.
+pub trait DiagnosticsHandler: Send + Sync {
+    /// Handle a diagnostic event.
+    fn on_event(&self, event: &DiagnosticEvent);
+}
+
+impl<F> DiagnosticsHandler for F
+where
+    F: Fn(&DiagnosticEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &DiagnosticEvent) {
+        self(event)
+    }
+}
+
+/// `Diagnostics` is an optional hook that diagnostic events are emitted to.
+///
+/// The handler is shared, therefore the same handler may be used by many readers and decoders.
+#[derive(Clone, Default)]
+pub struct Diagnostics {
+    handler: Option<Arc<dyn DiagnosticsHandler>>,
+}
+
+impl Diagnostics {
+    /// Instantiate `Diagnostics` that emits events to the given handler.
+    pub fn new(handler: Arc<dyn DiagnosticsHandler>) -> Self {
+        Diagnostics { handler: Some(handler) }
+    }
+
+    /// Returns `true` if a handler is set.
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.handler.is_some()
+    }
+
+    /// Emit an event to the handler, if set.
+    #[inline(always)]
+    pub fn emit(&self, event: DiagnosticEvent) {
+        if let Some(handler) = &self.handler {
+            handler.on_event(&event);
+        }
+    }
+}
+
+impl fmt::Debug for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Diagnostics").field("enabled", &self.is_enabled()).finish()
+    }
+}
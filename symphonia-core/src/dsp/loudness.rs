@@ -0,0 +1,493 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `loudness` module implements loudness and true peak measurement as specified by ITU-R
+//! BS.1770 and EBU R128.
+
+use std::f64::consts::PI;
+
+use crate::audio::{AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec};
+use crate::conv::IntoSample;
+use crate::errors::{unsupported_error, Result};
+use crate::sample::Sample;
+
+/// The absolute gating threshold in LUFS.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// The relative gating threshold in LU below the absolute-gated loudness.
+const RELATIVE_GATE: f64 = -10.0;
+
+/// The number of 100 ms sub-blocks in a 400 ms gating block.
+const SUB_BLOCKS_PER_BLOCK: usize = 4;
+
+/// The number of taps per phase of the true peak interpolation filter.
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// The oversampling factor of the true peak interpolation filter.
+const TRUE_PEAK_PHASES: usize = 4;
+
+/// Converts a mean square energy into loudness in LUFS.
+fn energy_to_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Converts loudness in LUFS into a mean square energy.
+fn loudness_to_energy(loudness: f64) -> f64 {
+    10.0f64.powf((loudness + 0.691) / 10.0)
+}
+
+/// A second-order IIR filter in transposed direct form II.
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// Gets the K-weighting filter stages for the given sample rate.
+///
+/// The first stage is a high-shelf filter modelling the acoustic effects of the head, and the
+/// second stage is the revised low-frequency B-weighting (RLB) high-pass filter. The coefficients
+/// are derived from the analog prototypes such that they match those specified by ITU-R BS.1770
+/// at 48 kHz.
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(rate);
+
+    let shelf = {
+        let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+
+        let k = (PI * f0 / rate).tan();
+        let vh = 10.0f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+
+        Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        }
+    };
+
+    let high_pass = {
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+
+        let k = (PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+
+        Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        }
+    };
+
+    [shelf, high_pass]
+}
+
+/// Gets the weight of a channel when summing the energy of all channels.
+fn channel_weight(channel: Channels) -> f64 {
+    match channel {
+        // The low-frequency effects channels are excluded.
+        Channels::LFE1 | Channels::LFE2 => 0.0,
+        // The surround channels are weighted by approximately +1.5 dB.
+        Channels::REAR_LEFT | Channels::REAR_RIGHT | Channels::SIDE_LEFT | Channels::SIDE_RIGHT => {
+            1.41
+        }
+        _ => 1.0,
+    }
+}
+
+/// Gets the polyphase coefficients of the true peak interpolation filter.
+///
+/// Phase `p` interpolates the sample `p / TRUE_PEAK_PHASES` of a sample period after the centre of
+/// the filter using a Hann-windowed sinc. Each phase is normalized to unity gain.
+fn true_peak_filter() -> [[f64; TRUE_PEAK_TAPS]; TRUE_PEAK_PHASES] {
+    let half = (TRUE_PEAK_TAPS / 2) as f64;
+
+    let mut phases = [[0.0; TRUE_PEAK_TAPS]; TRUE_PEAK_PHASES];
+
+    for (p, taps) in phases.iter_mut().enumerate() {
+        for (k, tap) in taps.iter_mut().enumerate() {
+            let t = k as f64 - (half - 1.0) - p as f64 / TRUE_PEAK_PHASES as f64;
+
+            let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+            let window = 0.5 * (1.0 + (PI * t / half).cos());
+
+            *tap = sinc * window;
+        }
+
+        let sum: f64 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap /= sum);
+    }
+
+    phases
+}
+
+/// The per-channel state of a `LoudnessMeter`.
+struct ChannelState {
+    weight: f64,
+    filters: [Biquad; 2],
+    /// The sum of squares of the K-weighted samples in the current sub-block.
+    sum: f64,
+    /// The most recent samples, oldest first, for true peak interpolation.
+    history: [f64; TRUE_PEAK_TAPS],
+    peak: f64,
+}
+
+/// A `LoudnessMeter` measures the integrated loudness and true peak of an audio stream as
+/// specified by ITU-R BS.1770 and EBU R128.
+///
+/// Audio buffers of the stream are passed to the meter, in order, using [`LoudnessMeter::process`].
+/// The loudness is measured over 400 ms gating blocks overlapping by 75%. The integrated loudness
+/// is the loudness of all gating blocks above both the absolute gate of -70 LUFS, and the relative
+/// gate of 10 LU below the loudness of the blocks above the absolute gate.
+///
+/// The true peak is estimated by 4x oversampling using a windowed-sinc interpolation filter.
+pub struct LoudnessMeter {
+    spec: SignalSpec,
+    channels: Vec<ChannelState>,
+    true_peak_filter: [[f64; TRUE_PEAK_TAPS]; TRUE_PEAK_PHASES],
+    /// The number of frames per 100 ms sub-block.
+    sub_block_len: usize,
+    /// The number of frames in the current sub-block.
+    sub_block_pos: usize,
+    /// The channel-weighted mean square energies of the most recent sub-blocks.
+    sub_blocks: Vec<f64>,
+    /// The mean square energies of all gating blocks.
+    blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    /// Instantiate a new `LoudnessMeter` for a stream with the given signal specification.
+    ///
+    /// Returns an unsupported error if the sample rate is 0, or there are no channels.
+    pub fn new(spec: SignalSpec) -> Result<Self> {
+        if spec.rate == 0 {
+            return unsupported_error("core (loudness): sample rate must be non-zero");
+        }
+
+        if spec.channels.count() == 0 {
+            return unsupported_error("core (loudness): there must be at least one channel");
+        }
+
+        let channels = spec
+            .channels
+            .iter()
+            .map(|channel| ChannelState {
+                weight: channel_weight(channel),
+                filters: k_weighting(spec.rate),
+                sum: 0.0,
+                history: [0.0; TRUE_PEAK_TAPS],
+                peak: 0.0,
+            })
+            .collect();
+
+        Ok(LoudnessMeter {
+            spec,
+            channels,
+            true_peak_filter: true_peak_filter(),
+            sub_block_len: ((spec.rate + 5) / 10) as usize,
+            sub_block_pos: 0,
+            sub_blocks: Vec::with_capacity(SUB_BLOCKS_PER_BLOCK),
+            blocks: Vec::new(),
+        })
+    }
+
+    /// Gets the signal specification of the stream being measured.
+    pub fn spec(&self) -> &SignalSpec {
+        &self.spec
+    }
+
+    /// Process the next audio buffer of the stream.
+    ///
+    /// Returns an unsupported error, and ignores the audio buffer, if the signal specification of
+    /// the audio buffer does not match the signal specification the meter was instantiated with.
+    /// To measure a stream with a different signal specification, instantiate a new meter.
+    pub fn process(&mut self, buf: &AudioBufferRef<'_>) -> Result<()> {
+        if buf.spec() != &self.spec {
+            return unsupported_error("core (loudness): signal specification does not match");
+        }
+
+        match buf {
+            AudioBufferRef::U8(buf) => self.process_typed(buf),
+            AudioBufferRef::U16(buf) => self.process_typed(buf),
+            AudioBufferRef::U24(buf) => self.process_typed(buf),
+            AudioBufferRef::U32(buf) => self.process_typed(buf),
+            AudioBufferRef::S8(buf) => self.process_typed(buf),
+            AudioBufferRef::S16(buf) => self.process_typed(buf),
+            AudioBufferRef::S24(buf) => self.process_typed(buf),
+            AudioBufferRef::S32(buf) => self.process_typed(buf),
+            AudioBufferRef::F32(buf) => self.process_typed(buf),
+            AudioBufferRef::F64(buf) => self.process_typed(buf),
+        }
+
+        Ok(())
+    }
+
+    fn process_typed<S: Sample + IntoSample<f64>>(&mut self, buf: &AudioBuffer<S>) {
+        let n_frames = buf.frames();
+        let mut start = 0;
+
+        // Process the buffer in segments that end at a sub-block boundary, or the end of the
+        // buffer.
+        while start < n_frames {
+            let len = (self.sub_block_len - self.sub_block_pos).min(n_frames - start);
+
+            for (ch, state) in self.channels.iter_mut().enumerate() {
+                for &sample in &buf.chan(ch)[start..start + len] {
+                    let x: f64 = sample.into_sample();
+
+                    // Shift the sample into the history, and interpolate the true peak.
+                    state.history.copy_within(1.., 0);
+                    state.history[TRUE_PEAK_TAPS - 1] = x;
+
+                    for taps in self.true_peak_filter.iter() {
+                        let y: f64 = taps.iter().zip(&state.history).map(|(h, x)| h * x).sum();
+                        state.peak = state.peak.max(y.abs());
+                    }
+
+                    // Samples at the end of the stream are not interpolated, therefore the sample
+                    // peak must also be tracked.
+                    state.peak = state.peak.max(x.abs());
+
+                    let y = state.filters.iter_mut().fold(x, |x, filter| filter.process(x));
+                    state.sum += y * y;
+                }
+            }
+
+            start += len;
+            self.sub_block_pos += len;
+
+            if self.sub_block_pos == self.sub_block_len {
+                self.end_sub_block();
+            }
+        }
+    }
+
+    fn end_sub_block(&mut self) {
+        let n_frames = self.sub_block_len as f64;
+
+        // The channel-weighted sum of the mean square of each channel.
+        let energy = self
+            .channels
+            .iter_mut()
+            .map(|state| state.weight * std::mem::replace(&mut state.sum, 0.0) / n_frames)
+            .sum();
+
+        if self.sub_blocks.len() == SUB_BLOCKS_PER_BLOCK {
+            self.sub_blocks.remove(0);
+        }
+
+        self.sub_blocks.push(energy);
+        self.sub_block_pos = 0;
+
+        // A gating block is complete every sub-block once enough sub-blocks were processed.
+        if self.sub_blocks.len() == SUB_BLOCKS_PER_BLOCK {
+            let block = self.sub_blocks.iter().sum::<f64>() / SUB_BLOCKS_PER_BLOCK as f64;
+            self.blocks.push(block);
+        }
+    }
+
+    /// Gets the integrated loudness, in LUFS, of all audio processed so far.
+    ///
+    /// Returns negative infinity if no gating block exceeded the absolute gate.
+    pub fn integrated(&self) -> f64 {
+        let absolute_gate = loudness_to_energy(ABSOLUTE_GATE);
+
+        let mean = |threshold: f64| {
+            let (sum, count) = self
+                .blocks
+                .iter()
+                .filter(|&&block| block > threshold)
+                .fold((0.0, 0usize), |(sum, count), block| (sum + block, count + 1));
+
+            if count > 0 {
+                Some(sum / count as f64)
+            }
+            else {
+                None
+            }
+        };
+
+        let relative_gate = match mean(absolute_gate) {
+            Some(energy) => loudness_to_energy(energy_to_loudness(energy) + RELATIVE_GATE),
+            None => return f64::NEG_INFINITY,
+        };
+
+        match mean(absolute_gate.max(relative_gate)) {
+            Some(energy) => energy_to_loudness(energy),
+            None => f64::NEG_INFINITY,
+        }
+    }
+
+    /// Gets the true peak, as a linear amplitude, of all audio processed so far across all
+    /// channels.
+    ///
+    /// The true peak in dBTP is `20 * log10(true_peak)`.
+    pub fn true_peak(&self) -> f64 {
+        self.channels.iter().map(|state| state.peak).fold(0.0, f64::max)
+    }
+
+    /// Reset the meter so that it may be used for a new stream with the same signal specification.
+    pub fn reset(&mut self) {
+        for state in self.channels.iter_mut() {
+            state.filters.iter_mut().for_each(|filter| filter.z = [0.0; 2]);
+            state.sum = 0.0;
+            state.history = [0.0; TRUE_PEAK_TAPS];
+            state.peak = 0.0;
+        }
+
+        self.sub_block_pos = 0;
+        self.sub_blocks.clear();
+        self.blocks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AsAudioBufferRef, Layout};
+
+    /// Generates a buffer containing a sine wave with the given peak amplitude in dBFS on every
+    /// channel.
+    fn sine(spec: SignalSpec, freq: f64, level_db: f64, phase: f64, secs: f64) -> AudioBuffer<f32> {
+        let n_frames = (f64::from(spec.rate) * secs) as usize;
+        let amplitude = 10.0f64.powf(level_db / 20.0);
+
+        let mut buf = AudioBuffer::new(n_frames as u64, spec);
+        buf.render_reserved(Some(n_frames));
+
+        for ch in 0..spec.channels.count() {
+            for (n, sample) in buf.chan_mut(ch).iter_mut().enumerate() {
+                let t = n as f64 / f64::from(spec.rate);
+                *sample = (amplitude * (2.0 * PI * freq * t + phase).sin()) as f32;
+            }
+        }
+
+        buf
+    }
+
+    /// Processes the buffer in chunks of the given number of frames.
+    fn process_chunked(meter: &mut LoudnessMeter, buf: &AudioBuffer<f32>, chunk: usize) {
+        let mut part = AudioBuffer::new(chunk as u64, *buf.spec());
+
+        for start in (0..buf.frames()).step_by(chunk) {
+            let len = chunk.min(buf.frames() - start);
+
+            part.clear();
+            part.render_reserved(Some(len));
+
+            for ch in 0..buf.spec().channels.count() {
+                part.chan_mut(ch).copy_from_slice(&buf.chan(ch)[start..start + len]);
+            }
+
+            meter.process(&part.as_audio_buffer_ref()).unwrap();
+        }
+    }
+
+    #[test]
+    fn verify_integrated_loudness() {
+        let stereo = SignalSpec::new_with_layout(48000, Layout::Stereo);
+
+        // EBU Tech 3341 test cases 1 and 2: a 1 kHz stereo sine wave at -23 dBFS and -33 dBFS
+        // measures -23 LUFS and -33 LUFS, respectively.
+        for &level in [-23.0, -33.0].iter() {
+            let mut meter = LoudnessMeter::new(stereo).unwrap();
+            process_chunked(&mut meter, &sine(stereo, 1000.0, level, 0.0, 3.0), 1000);
+
+            let loudness = meter.integrated();
+            assert!((loudness - level).abs() < 0.1, "{} != {}", loudness, level);
+        }
+
+        // A mono 1 kHz sine wave at -20 dBFS measures approximately -23 LUFS. The 44.1 kHz sample
+        // rate does not change the measurement.
+        let mono = SignalSpec::new_with_layout(44100, Layout::Mono);
+
+        let mut meter = LoudnessMeter::new(mono).unwrap();
+        process_chunked(&mut meter, &sine(mono, 1000.0, -20.0, 0.0, 3.0), 4096);
+
+        let loudness = meter.integrated();
+        assert!((loudness + 23.01).abs() < 0.1, "{} != -23.01", loudness);
+    }
+
+    #[test]
+    fn verify_loudness_gating() {
+        let stereo = SignalSpec::new_with_layout(48000, Layout::Stereo);
+
+        let mut meter = LoudnessMeter::new(stereo).unwrap();
+
+        // A silent stream is below the absolute gate.
+        process_chunked(&mut meter, &sine(stereo, 1000.0, -120.0, 0.0, 1.0), 4800);
+        assert_eq!(meter.integrated(), f64::NEG_INFINITY);
+
+        // Silence is gated by the absolute gate, and a quiet passage 20 LU below the loud passage
+        // is gated by the relative gate. Without the relative gate, the loudness would be
+        // approximately -26 LUFS. The gating blocks overlapping both passages are not gated, and
+        // slightly lower the loudness.
+        process_chunked(&mut meter, &sine(stereo, 1000.0, -23.0, 0.0, 3.0), 4800);
+        process_chunked(&mut meter, &sine(stereo, 1000.0, -43.0, 0.0, 3.0), 4800);
+
+        let loudness = meter.integrated();
+        assert!((loudness + 23.0).abs() < 0.5, "{} != -23", loudness);
+
+        meter.reset();
+        assert_eq!(meter.integrated(), f64::NEG_INFINITY);
+        assert_eq!(meter.true_peak(), 0.0);
+    }
+
+    #[test]
+    fn verify_true_peak() {
+        let mono = SignalSpec::new_with_layout(48000, Layout::Mono);
+
+        // A sine wave at a quarter of the sample rate, offset by 45 degrees, is sampled at only
+        // 1/sqrt(2) of its peak amplitude.
+        let buf = sine(mono, 12000.0, 0.0, PI / 4.0, 1.0);
+
+        let sample_peak = buf.chan(0).iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((f64::from(sample_peak) - 0.5f64.sqrt()).abs() < 1e-3);
+
+        let mut meter = LoudnessMeter::new(mono).unwrap();
+        meter.process(&buf.as_audio_buffer_ref()).unwrap();
+
+        // The interpolation filter may slightly overestimate the true peak.
+        let true_peak = meter.true_peak();
+        assert!((true_peak - 1.0).abs() < 0.02, "{} != 1", true_peak);
+    }
+
+    #[test]
+    fn verify_invalid_spec() {
+        let stereo = SignalSpec::new_with_layout(48000, Layout::Stereo);
+        let mono = SignalSpec::new_with_layout(48000, Layout::Mono);
+
+        assert!(LoudnessMeter::new(SignalSpec::new_with_layout(0, Layout::Stereo)).is_err());
+        assert!(LoudnessMeter::new(SignalSpec::new(48000, Channels::empty())).is_err());
+
+        let mut meter = LoudnessMeter::new(stereo).unwrap();
+
+        // Audio buffers with a different number of channels, or sample rate, are rejected.
+        let buf = sine(mono, 1000.0, -23.0, 0.0, 1.0);
+        assert!(meter.process(&buf.as_audio_buffer_ref()).is_err());
+
+        let buf = sine(SignalSpec::new_with_layout(44100, Layout::Stereo), 1000.0, -23.0, 0.0, 1.0);
+        assert!(meter.process(&buf.as_audio_buffer_ref()).is_err());
+
+        assert_eq!(meter.true_peak(), 0.0);
+    }
+}
@@ -77,4 +77,25 @@ mod tests {
             assert!(delta.abs() < 0.00001);
         }
     }
+
+    #[test]
+    fn verify_imdct_mixed_radix() {
+        // N = 60, therefore the FFT size is 30 = 2 * 15.
+        let input: Vec<f32> = (0..60).map(|i| ((i * 7) % 13) as f32 - 6.0).collect();
+
+        let mut actual = [0f32; 120];
+        let mut expected = [0f32; 120];
+
+        let scale = (2.0f64 / 120.0).sqrt();
+
+        imdct_analytical(&input, &mut expected, scale);
+
+        let mut mdct = Imdct::new_scaled(60, scale);
+        mdct.imdct(&input, &mut actual);
+
+        for i in 0..120 {
+            let delta = f64::from(actual[i]) - f64::from(expected[i]);
+            assert!(delta.abs() < 0.0001);
+        }
+    }
 }
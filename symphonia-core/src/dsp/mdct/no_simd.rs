@@ -10,9 +10,108 @@
 use crate::dsp::complex::Complex;
 use crate::dsp::fft::*;
 
+/// A complex FFT of size N = P * M, where P is a power-of-two, and M is odd.
+///
+/// The transform is decomposed into M radix-2 FFTs of size P, followed by P naive DFTs of size M.
+/// Therefore, it is only efficient if M is small.
+struct MixedRadixFft {
+    fft: Fft,
+    m: usize,
+    /// Twiddle factors for a N-point FFT.
+    twiddle: Box<[Complex]>,
+    /// Twiddle factors for a M-point DFT.
+    dft_twiddle: Box<[Complex]>,
+    fft_in: Box<[Complex]>,
+    fft_out: Box<[Complex]>,
+}
+
+impl MixedRadixFft {
+    fn new(n: usize) -> Self {
+        // Split N into its power-of-two and odd factors.
+        let p = 1 << n.trailing_zeros();
+        let m = n / p;
+
+        let twiddle = (0..n).map(|k| Self::twiddle_factor(k, n)).collect();
+        let dft_twiddle = (0..m).map(|k| Self::twiddle_factor(k, m)).collect();
+
+        MixedRadixFft {
+            fft: Fft::new(p),
+            m,
+            twiddle,
+            dft_twiddle,
+            fft_in: vec![Default::default(); p].into_boxed_slice(),
+            fft_out: vec![Default::default(); n].into_boxed_slice(),
+        }
+    }
+
+    fn twiddle_factor(k: usize, n: usize) -> Complex {
+        let theta = 2.0 * std::f64::consts::PI * k as f64 / n as f64;
+        Complex::new(theta.cos() as f32, -theta.sin() as f32)
+    }
+
+    fn size(&self) -> usize {
+        self.fft.size() * self.m
+    }
+
+    fn fft(&mut self, x: &[Complex], y: &mut [Complex]) {
+        let p = self.fft.size();
+        let m = self.m;
+        let n = p * m;
+
+        // Do a P-point FFT of every M-th sample for each of the M offsets, then multiply by the
+        // twiddle factors.
+        for (n2, out) in self.fft_out.chunks_exact_mut(p).enumerate() {
+            for (n1, t) in self.fft_in.iter_mut().enumerate() {
+                *t = x[m * n1 + n2];
+            }
+
+            self.fft.fft(&self.fft_in, out);
+
+            for (k1, t) in out.iter_mut().enumerate() {
+                *t *= self.twiddle[(n2 * k1) % n];
+            }
+        }
+
+        // Do a M-point DFT across the P-point FFT outputs.
+        for k1 in 0..p {
+            for k2 in 0..m {
+                let mut acc = Complex::default();
+
+                for n2 in 0..m {
+                    acc += self.fft_out[n2 * p + k1] * self.dft_twiddle[(n2 * k2) % m];
+                }
+
+                y[k1 + p * k2] = acc;
+            }
+        }
+    }
+}
+
+/// The FFT used to compute the IMDCT.
+enum ImdctFft {
+    Radix2(Fft),
+    MixedRadix(MixedRadixFft),
+}
+
+impl ImdctFft {
+    fn size(&self) -> usize {
+        match self {
+            ImdctFft::Radix2(fft) => fft.size(),
+            ImdctFft::MixedRadix(fft) => fft.size(),
+        }
+    }
+
+    fn fft(&mut self, x: &[Complex], y: &mut [Complex]) {
+        match self {
+            ImdctFft::Radix2(fft) => fft.fft(x, y),
+            ImdctFft::MixedRadix(fft) => fft.fft(x, y),
+        }
+    }
+}
+
 /// The Inverse Modified Discrete Transform (IMDCT).
 pub struct Imdct {
-    fft: Fft,
+    fft: ImdctFft,
     fft_in: Box<[Complex]>,
     fft_out: Box<[Complex]>,
     twiddle: Box<[Complex]>,
@@ -21,22 +120,25 @@ pub struct Imdct {
 impl Imdct {
     /// Instantiate a N-point IMDCT with no scaling.
     ///
-    /// The value of `n` is the number of spectral samples and must be a power-of-2 and less-than or
-    /// equal to `2 * Fft::MAX_SIZE`.
+    /// The value of `n` is the number of spectral samples and must be a power-of-2, or a multiple
+    /// of 4. The power-of-2 factor of `n` must be less-than or equal to `2 * Fft::MAX_SIZE`.
     pub fn new(n: usize) -> Self {
         Imdct::new_scaled(n, 1.0)
     }
 
     /// Instantiate a N-point IMDCT with scaling.
     ///
-    /// The value of `n` is the number of spectral samples and must be a power-of-2 and less-than or
-    /// equal to `2 * Fft::MAX_SIZE`.
+    /// The value of `n` is the number of spectral samples and must be a power-of-2, or a multiple
+    /// of 4. The power-of-2 factor of `n` must be less-than or equal to `2 * Fft::MAX_SIZE`.
+    ///
+    /// Note: If `n` is not a power-of-2, then the IMDCT is only efficient if the odd factor of `n`
+    /// is small (e.g., 960 = 64 * 15).
     pub fn new_scaled(n: usize, scale: f64) -> Self {
-        // The FFT requires a power-of-two N.
-        assert!(n.is_power_of_two(), "n must be a power of two");
-        // A complex FFT of size N/2 is used to compute the IMDCT. Therefore, the maximum value of N
-        // is 2 * Fft::MAX_SIZE.
-        assert!(n <= 2 * Fft::MAX_SIZE, "maximum size exceeded");
+        // The algorithm requires N to be a power-of-two, or a multiple of 4.
+        assert!(n.is_power_of_two() || n % 4 == 0, "n must be a power of two or a multiple of 4");
+        // A complex FFT of size N/2 is used to compute the IMDCT. Therefore, the maximum power-of-2
+        // factor of N is 2 * Fft::MAX_SIZE.
+        assert!(1 << n.trailing_zeros() <= 2 * Fft::MAX_SIZE, "maximum size exceeded");
 
         let n2 = n / 2;
         let mut twiddle = Vec::with_capacity(n2);
@@ -55,7 +157,15 @@ impl Imdct {
         let fft_in = vec![Default::default(); n2].into_boxed_slice();
         let fft_out = vec![Default::default(); n2].into_boxed_slice();
 
-        Imdct { fft: Fft::new(n2), fft_in, fft_out, twiddle: twiddle.into_boxed_slice() }
+        // Use a radix-2 FFT if possible, otherwise fallback to a mixed-radix FFT.
+        let fft = if n2.is_power_of_two() {
+            ImdctFft::Radix2(Fft::new(n2))
+        }
+        else {
+            ImdctFft::MixedRadix(MixedRadixFft::new(n2))
+        };
+
+        Imdct { fft, fft_in, fft_out, twiddle: twiddle.into_boxed_slice() }
     }
 
     /// Performs the the N-point Inverse Modified Discrete Cosine Transform.
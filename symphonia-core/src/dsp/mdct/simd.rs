@@ -22,19 +22,19 @@ pub struct Imdct {
 impl Imdct {
     /// Instantiate a N-point IMDCT with no scaling.
     ///
-    /// The value of `n` is the number of spectral samples and must be a power-of-2 and less-than or
-    /// equal to `2 * Fft::MAX_SIZE`.
+    /// The value of `n` is the number of spectral samples and must be a power-of-2, or a multiple
+    /// of 4. The power-of-2 factor of `n` must be less-than or equal to `2 * Fft::MAX_SIZE`.
     pub fn new(n: usize) -> Self {
         Imdct::new_scaled(n, 1.0)
     }
 
     /// Instantiate a N-point IMDCT with scaling.
     ///
-    /// The value of `n` is the number of spectral samples and must be a power-of-2 and less-than or
-    /// equal to `2 * Fft::MAX_SIZE`.
+    /// The value of `n` is the number of spectral samples and must be a power-of-2, or a multiple
+    /// of 4. The power-of-2 factor of `n` must be less-than or equal to `2 * Fft::MAX_SIZE`.
     pub fn new_scaled(n: usize, scale: f64) -> Self {
-        // The algorithm requires a power-of-two N.
-        assert!(n.is_power_of_two(), "n must be a power of two");
+        // The algorithm requires N to be a power-of-two, or a multiple of 4.
+        assert!(n.is_power_of_two() || n % 4 == 0, "n must be a power of two or a multiple of 4");
 
         let n2 = n / 2;
 
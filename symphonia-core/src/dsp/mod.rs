@@ -9,4 +9,9 @@
 
 pub mod complex;
 pub mod fft;
+pub mod loudness;
 pub mod mdct;
+pub mod normalize;
+#[cfg(feature = "resample")]
+pub mod resample;
+pub mod silence;
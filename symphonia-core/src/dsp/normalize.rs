@@ -0,0 +1,129 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `normalize` module implements simple peak and RMS normalization of audio buffers.
+//!
+//! Each function operates on a single audio buffer. To normalize an entire stream without
+//! varying the gain between buffers, measure the stream first, for example, using a
+//! [`super::loudness::LoudnessMeter`], then apply a single gain to every buffer using
+//! [`apply_gain`].
+
+use crate::audio::{AudioBuffer, Signal};
+
+/// Converts a level in decibels relative to full scale (dBFS) into a linear amplitude.
+fn db_to_amplitude(level_db: f32) -> f32 {
+    10.0f32.powf(level_db / 20.0)
+}
+
+/// Gets the peak absolute sample value of all channels.
+pub fn peak(buf: &AudioBuffer<f32>) -> f32 {
+    (0..buf.spec().channels.count())
+        .flat_map(|ch| buf.chan(ch).iter())
+        .fold(0.0, |peak, sample| peak.max(sample.abs()))
+}
+
+/// Gets the root mean square (RMS) of all samples of all channels.
+pub fn rms(buf: &AudioBuffer<f32>) -> f32 {
+    let n_samples = buf.frames() * buf.spec().channels.count();
+
+    if n_samples == 0 {
+        return 0.0;
+    }
+
+    let sum: f64 = (0..buf.spec().channels.count())
+        .flat_map(|ch| buf.chan(ch).iter())
+        .map(|&sample| f64::from(sample) * f64::from(sample))
+        .sum();
+
+    (sum / n_samples as f64).sqrt() as f32
+}
+
+/// Multiplies every sample by the linear gain provided.
+pub fn apply_gain(buf: &mut AudioBuffer<f32>, gain: f32) {
+    buf.transform(|sample| sample * gain);
+}
+
+/// Scales the buffer such that its peak is at the level provided, in dBFS. Returns the linear gain
+/// that was applied.
+///
+/// A silent buffer is not scaled.
+pub fn normalize_peak(buf: &mut AudioBuffer<f32>, level_db: f32) -> f32 {
+    let peak = peak(buf);
+
+    if peak == 0.0 {
+        return 1.0;
+    }
+
+    let gain = db_to_amplitude(level_db) / peak;
+    apply_gain(buf, gain);
+    gain
+}
+
+/// Scales the buffer such that its RMS is at the level provided, in dBFS. Returns the linear gain
+/// that was applied.
+///
+/// The gain is limited such that the peak of the buffer does not exceed full scale. A silent buffer
+/// is not scaled.
+pub fn normalize_rms(buf: &mut AudioBuffer<f32>, level_db: f32) -> f32 {
+    let rms = rms(buf);
+
+    if rms == 0.0 {
+        return 1.0;
+    }
+
+    let gain = (db_to_amplitude(level_db) / rms).min(1.0 / peak(buf));
+    apply_gain(buf, gain);
+    gain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{Channels, SignalSpec};
+
+    fn make_buffer(left: &[f32], right: &[f32]) -> AudioBuffer<f32> {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::new(left.len() as u64, spec);
+        buf.render_reserved(Some(left.len()));
+        buf.chan_mut(0).copy_from_slice(left);
+        buf.chan_mut(1).copy_from_slice(right);
+        buf
+    }
+
+    #[test]
+    fn verify_normalize() {
+        let mut buf = make_buffer(&[0.1, -0.2, 0.1, 0.0], &[0.0, 0.1, -0.1, 0.25]);
+
+        assert_eq!(peak(&buf), 0.25);
+
+        // Normalize the peak to -6 dBFS.
+        let gain = normalize_peak(&mut buf, -6.0);
+        assert!((gain - 2.0).abs() < 0.01);
+        assert!((peak(&buf) - 0.5012).abs() < 1e-4);
+
+        // Normalize the RMS to -20 dBFS.
+        let mut buf = make_buffer(&[0.5, -0.5], &[0.5, -0.5]);
+        assert_eq!(rms(&buf), 0.5);
+
+        let gain = normalize_rms(&mut buf, -20.0);
+        assert!((gain - 0.2).abs() < 1e-4);
+        assert!((rms(&buf) - 0.1).abs() < 1e-4);
+
+        // The RMS gain is limited to prevent clipping.
+        let mut buf = make_buffer(&[0.5, 0.0, 0.0, 0.0], &[0.0; 4]);
+
+        let gain = normalize_rms(&mut buf, 0.0);
+        assert_eq!(gain, 2.0);
+        assert_eq!(peak(&buf), 1.0);
+
+        // Silence is not scaled.
+        let mut buf = make_buffer(&[0.0; 4], &[0.0; 4]);
+        assert_eq!(normalize_peak(&mut buf, 0.0), 1.0);
+        assert_eq!(normalize_rms(&mut buf, 0.0), 1.0);
+    }
+}
@@ -0,0 +1,346 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `resample` module implements sample rate conversion.
+
+use std::f64::consts::PI;
+
+use crate::audio::{AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec};
+use crate::conv::IntoSample;
+use crate::sample::Sample;
+
+/// The number of zero-crossings of the sinc function on either side of the centre of the
+/// interpolation filter.
+const N_ZERO_CROSSINGS: usize = 64;
+
+/// The cutoff frequency of the interpolation filter relative to the lower of the two Nyquist
+/// frequencies. The transition band of the filter is centred on the cutoff frequency, and ends
+/// just below the Nyquist frequency.
+const ROLLOFF: f64 = 0.95;
+
+/// A `Resampler` converts audio from one sample rate to another.
+///
+/// The resampler is a polyphase windowed-sinc interpolator. The ratio between the two sample
+/// rates is reduced to `L / M`, and a bank of `L` interpolation filters is computed, one for each
+/// possible fractional position of an output sample between two input samples. Therefore, the
+/// memory required grows with `L`, which is small for common sample rates (e.g., `L = 147` from
+/// 48 kHz to 44.1 kHz).
+///
+/// The resampler is stateful, and audio buffers from the same stream must be processed in order.
+/// The output is aligned to the input such that the first output sample corresponds to the first
+/// input sample. At the end of the stream, [`Resampler::flush`] must be called to get the
+/// remaining samples. In total, `ceil(n * to_rate / from_rate)` samples are output per channel for
+/// `n` input samples per channel.
+pub struct Resampler {
+    from_rate: u32,
+    spec: SignalSpec,
+    /// The upsampling factor.
+    up: usize,
+    /// The downsampling factor.
+    down: usize,
+    /// The number of taps on either side of the centre of each filter.
+    half_len: usize,
+    /// The filter bank. The filter for phase `p` is `filters[p * 2 * half_len..]`.
+    filters: Vec<f32>,
+    /// The input samples of each channel, starting with the first sample of the filter window.
+    input: Vec<Vec<f32>>,
+    /// The phase of the next output sample.
+    phase: usize,
+    /// The total number of input frames.
+    n_input: u64,
+    /// The total number of output frames.
+    n_output: u64,
+}
+
+impl Resampler {
+    /// Instantiate a new `Resampler` that converts audio with the given channels from one sample
+    /// rate to another.
+    ///
+    /// Panics if either sample rate is 0.
+    pub fn new(from_rate: u32, to_rate: u32, channels: Channels) -> Self {
+        assert!(from_rate > 0 && to_rate > 0, "sample rates must be greater than 0");
+
+        let gcd = gcd(from_rate, to_rate);
+
+        let up = (to_rate / gcd) as usize;
+        let down = (from_rate / gcd) as usize;
+
+        // When downsampling, the cutoff is lowered to the Nyquist frequency of the output, and the
+        // filter is widened accordingly.
+        let cutoff = ROLLOFF * (up as f64 / down as f64).min(1.0);
+        let half_len = (N_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+
+        let mut filters = Vec::with_capacity(up * 2 * half_len);
+
+        for phase in 0..up {
+            let start = filters.len();
+
+            // Tap j of the filter is applied to the input sample that is `half_len - 1 - j` input
+            // samples before the centre, offset by the phase.
+            for j in 0..2 * half_len {
+                let t = phase as f64 / up as f64 + (half_len - 1) as f64 - j as f64;
+                filters.push(cutoff * sinc(cutoff * t) * blackman(t / half_len as f64));
+            }
+
+            // Normalize each filter to unity gain.
+            let sum: f64 = filters[start..].iter().sum();
+            filters[start..].iter_mut().for_each(|c| *c /= sum);
+        }
+
+        let mut resampler = Resampler {
+            from_rate,
+            spec: SignalSpec::new(to_rate, channels),
+            up,
+            down,
+            half_len,
+            filters: filters.into_iter().map(|c| c as f32).collect(),
+            input: vec![Vec::new(); channels.count()],
+            phase: 0,
+            n_input: 0,
+            n_output: 0,
+        };
+
+        resampler.reset();
+        resampler
+    }
+
+    /// Gets the signal specification of the output.
+    pub fn spec(&self) -> &SignalSpec {
+        &self.spec
+    }
+
+    /// Resample an audio buffer, and return the resampled audio. Some of the audio is retained
+    /// until more audio is processed, or the resampler is flushed.
+    ///
+    /// Panics if the signal specification of the audio buffer does not match the sample rate and
+    /// channels the resampler was instantiated with.
+    pub fn process(&mut self, input: &AudioBufferRef<'_>) -> AudioBuffer<f32> {
+        match input {
+            AudioBufferRef::U8(buf) => self.append(buf),
+            AudioBufferRef::U16(buf) => self.append(buf),
+            AudioBufferRef::U24(buf) => self.append(buf),
+            AudioBufferRef::U32(buf) => self.append(buf),
+            AudioBufferRef::S8(buf) => self.append(buf),
+            AudioBufferRef::S16(buf) => self.append(buf),
+            AudioBufferRef::S24(buf) => self.append(buf),
+            AudioBufferRef::S32(buf) => self.append(buf),
+            AudioBufferRef::F32(buf) => self.append(buf),
+            AudioBufferRef::F64(buf) => self.append(buf),
+        }
+
+        self.resample(None)
+    }
+
+    /// Resample the audio retained by the resampler, and return it. The resampler is then reset,
+    /// and may be used for a new stream.
+    pub fn flush(&mut self) -> AudioBuffer<f32> {
+        // Pad the input with silence so that the filter window of the last output sample is full.
+        for input in self.input.iter_mut() {
+            input.resize(input.len() + self.half_len, 0.0);
+        }
+
+        let n_total = (self.n_input * self.up as u64 + self.down as u64 - 1) / self.down as u64;
+
+        let buf = self.resample(Some((n_total - self.n_output) as usize));
+
+        self.reset();
+
+        buf
+    }
+
+    /// Discard any retained audio and reset the resampler.
+    pub fn reset(&mut self) {
+        // The filter window of the first output sample begins before the first input sample.
+        for input in self.input.iter_mut() {
+            input.clear();
+            input.resize(self.half_len - 1, 0.0);
+        }
+
+        self.phase = 0;
+        self.n_input = 0;
+        self.n_output = 0;
+    }
+
+    fn append<S>(&mut self, buf: &AudioBuffer<S>)
+    where
+        S: Sample + IntoSample<f32>,
+    {
+        assert!(buf.spec().rate == self.from_rate, "sample rate does not match");
+        assert!(buf.spec().channels == self.spec.channels, "channels do not match");
+
+        for (ch, input) in self.input.iter_mut().enumerate() {
+            input.extend(buf.chan(ch).iter().map(|&s| s.into_sample()));
+        }
+
+        self.n_input += buf.frames() as u64;
+    }
+
+    /// Produce as many output samples as possible, or at most `limit` samples.
+    fn resample(&mut self, limit: Option<usize>) -> AudioBuffer<f32> {
+        let taps = 2 * self.half_len;
+        let n_input = self.input.first().map_or(0, |input| input.len());
+
+        // Find the position and phase of each output sample with a full filter window.
+        let mut windows = Vec::new();
+
+        let mut pos = 0;
+        let mut phase = self.phase;
+
+        while pos + taps <= n_input && limit.map_or(true, |limit| windows.len() < limit) {
+            windows.push((pos, phase));
+
+            phase += self.down;
+            pos += phase / self.up;
+            phase %= self.up;
+        }
+
+        let mut output = AudioBuffer::new(windows.len() as u64, self.spec);
+        output.render_reserved(Some(windows.len()));
+
+        // Filter each channel, one plane at a time.
+        for (ch, input) in self.input.iter_mut().enumerate() {
+            let plane = output.chan_mut(ch);
+
+            for (out, &(pos, phase)) in plane.iter_mut().zip(&windows) {
+                let filter = &self.filters[phase * taps..(phase + 1) * taps];
+                *out = filter.iter().zip(&input[pos..pos + taps]).map(|(c, s)| c * s).sum();
+            }
+
+            // Discard input samples that are no longer in the window of any future output sample.
+            input.drain(..pos.min(input.len()));
+        }
+
+        self.phase = phase;
+        self.n_output += windows.len() as u64;
+
+        output
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// The normalized sinc function.
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    }
+    else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// The Blackman window over the interval [-1, 1].
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        0.0
+    }
+    else {
+        0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::Resampler;
+    use crate::audio::{AsAudioBufferRef, AudioBuffer, Channels, Signal, SignalSpec};
+
+    /// Resample 1 second of a sine with the given frequency in blocks of 10 ms, and return the
+    /// resampled left and right channels.
+    fn resample_sine(freq: f64, from_rate: u32, to_rate: u32) -> (Vec<f32>, Vec<f32>) {
+        let channels = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+        let mut resampler = Resampler::new(from_rate, to_rate, channels);
+
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+
+        let mut append = |buf: AudioBuffer<f32>| {
+            assert_eq!(buf.spec(), &SignalSpec::new(to_rate, channels));
+            left.extend_from_slice(buf.chan(0));
+            right.extend_from_slice(buf.chan(1));
+        };
+
+        let block_len = from_rate / 100;
+
+        for block in 0..100 {
+            let mut buf =
+                AudioBuffer::new(u64::from(block_len), SignalSpec::new(from_rate, channels));
+            buf.render_reserved(None);
+
+            let (l, r) = buf.chan_pair_mut(0, 1);
+
+            for (i, (l, r)) in l.iter_mut().zip(r.iter_mut()).enumerate() {
+                let t = (block_len * block + i as u32) as f64 / f64::from(from_rate);
+                *l = (0.5 * (2.0 * PI * freq * t).sin()) as f32;
+                *r = -*l;
+            }
+
+            append(resampler.process(&buf.as_audio_buffer_ref()));
+        }
+
+        append(resampler.flush());
+
+        (left, right)
+    }
+
+    /// Returns the maximum error between the samples and a sine with the given frequency,
+    /// excluding the edges.
+    fn max_sine_error(samples: &[f32], freq: f64, rate: u32) -> f64 {
+        let edge = rate as usize / 100;
+
+        samples[edge..samples.len() - edge]
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| {
+                let t = (i + edge) as f64 / f64::from(rate);
+                (f64::from(s) - 0.5 * (2.0 * PI * freq * t).sin()).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn verify_resample_48k_to_44k1() {
+        let (left, right) = resample_sine(1000.0, 48_000, 44_100);
+
+        assert_eq!(left.len(), 44_100);
+        assert_eq!(right.len(), 44_100);
+
+        assert!(max_sine_error(&left, 1000.0, 44_100) < 1e-3);
+        assert!(left.iter().zip(&right).all(|(l, r)| *l == -*r));
+
+        // A 1 kHz sine has 2000 zero crossings per second.
+        let crossings = left.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count();
+        assert!((1998..=2000).contains(&crossings));
+    }
+
+    #[test]
+    fn verify_resample_44k1_to_48k() {
+        let (left, _) = resample_sine(440.0, 44_100, 48_000);
+
+        assert_eq!(left.len(), 48_000);
+        assert!(max_sine_error(&left, 440.0, 48_000) < 1e-3);
+    }
+
+    #[test]
+    fn verify_resample_removes_aliases() {
+        // A 23 kHz sine is above the Nyquist frequency of the output, and should be removed.
+        let (left, _) = resample_sine(23_000.0, 48_000, 44_100);
+
+        let edge = 441;
+        let peak = left[edge..left.len() - edge].iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+
+        assert!(peak < 1e-3);
+    }
+}
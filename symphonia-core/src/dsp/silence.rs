@@ -0,0 +1,144 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `silence` module implements detection of leading and trailing silence.
+
+use crate::audio::{AudioBuffer, Signal};
+
+/// A `SilenceDetector` tracks the timestamps of the first and last non-silent audio frames across
+/// a sequence of decoded audio buffers.
+///
+/// An audio frame is non-silent if the absolute value of a sample in any channel exceeds the
+/// threshold. Once all audio buffers were processed, the leading and trailing silence may be
+/// trimmed by only keeping the audio frames in the range `first..=last`.
+pub struct SilenceDetector {
+    threshold: f32,
+    first: Option<u64>,
+    last: Option<u64>,
+}
+
+impl SilenceDetector {
+    /// Instantiate a new `SilenceDetector` with the given linear amplitude threshold.
+    ///
+    /// The threshold must be non-negative.
+    pub fn new(threshold: f32) -> Self {
+        assert!(threshold >= 0.0, "threshold must be non-negative");
+
+        SilenceDetector { threshold, first: None, last: None }
+    }
+
+    /// Instantiate a new `SilenceDetector` with a threshold given in decibels relative to full
+    /// scale (dBFS).
+    pub fn with_threshold_db(threshold_db: f32) -> Self {
+        SilenceDetector::new(10.0f32.powf(threshold_db / 20.0))
+    }
+
+    /// Gets the linear amplitude threshold.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Process an audio buffer where the first audio frame has the timestamp `ts`.
+    pub fn process(&mut self, ts: u64, buf: &AudioBuffer<f32>) {
+        let n_channels = buf.spec().channels.count();
+        let threshold = self.threshold;
+
+        let mut first = None;
+        let mut last = None;
+
+        // Find the first and last non-silent audio frame amongst all channels.
+        for ch in 0..n_channels {
+            let samples = buf.chan(ch);
+
+            if let Some(pos) = samples.iter().position(|s| s.abs() > threshold) {
+                first = Some(first.map_or(pos, |first: usize| first.min(pos)));
+            }
+
+            if let Some(pos) = samples.iter().rposition(|s| s.abs() > threshold) {
+                last = Some(last.map_or(pos, |last: usize| last.max(pos)));
+            }
+        }
+
+        // The first non-silent audio frame is only updated once, while the last non-silent audio
+        // frame is updated for every buffer with a non-silent audio frame.
+        if let Some(first) = first {
+            if self.first.is_none() {
+                self.first = Some(ts + first as u64);
+            }
+        }
+
+        if let Some(last) = last {
+            self.last = Some(ts + last as u64);
+        }
+    }
+
+    /// Gets the timestamp of the first non-silent audio frame, if one was found.
+    pub fn first_non_silent(&self) -> Option<u64> {
+        self.first
+    }
+
+    /// Gets the timestamp of the last non-silent audio frame, if one was found.
+    pub fn last_non_silent(&self) -> Option<u64> {
+        self.last
+    }
+
+    /// Reset the detector so that it may be used for a new stream.
+    pub fn reset(&mut self) {
+        self.first = None;
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{Channels, SignalSpec};
+
+    fn make_buffer(left: &[f32], right: &[f32]) -> AudioBuffer<f32> {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::new(left.len() as u64, spec);
+        buf.render_reserved(Some(left.len()));
+        buf.chan_mut(0).copy_from_slice(left);
+        buf.chan_mut(1).copy_from_slice(right);
+        buf
+    }
+
+    #[test]
+    fn verify_silence_detector() {
+        let mut detector = SilenceDetector::new(0.01);
+
+        // Entirely silent buffer.
+        detector.process(0, &make_buffer(&[0.0, 0.005, -0.01, 0.0], &[0.0; 4]));
+
+        assert_eq!(detector.first_non_silent(), None);
+        assert_eq!(detector.last_non_silent(), None);
+
+        // The right channel becomes non-silent before the left channel.
+        detector.process(4, &make_buffer(&[0.0, 0.0, 0.5, 0.2], &[0.0, -0.3, 0.0, 0.0]));
+
+        assert_eq!(detector.first_non_silent(), Some(5));
+        assert_eq!(detector.last_non_silent(), Some(7));
+
+        // The left channel becomes silent before the right channel.
+        detector.process(8, &make_buffer(&[0.1, 0.0, 0.0, 0.0], &[0.2, 0.1, -0.4, 0.0]));
+
+        assert_eq!(detector.first_non_silent(), Some(5));
+        assert_eq!(detector.last_non_silent(), Some(10));
+
+        // A silent lead-out does not change the boundaries.
+        detector.process(12, &make_buffer(&[0.0; 4], &[0.001; 4]));
+
+        assert_eq!(detector.first_non_silent(), Some(5));
+        assert_eq!(detector.last_non_silent(), Some(10));
+
+        detector.reset();
+
+        assert_eq!(detector.first_non_silent(), None);
+        assert_eq!(detector.last_non_silent(), None);
+    }
+}
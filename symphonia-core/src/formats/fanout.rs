@@ -0,0 +1,189 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `fanout` module provides a helper to consume the packets of multiple tracks independently
+//! from a single pass over a `FormatReader`.
+
+use std::collections::VecDeque;
+
+use crate::errors::{limit_error, unsupported_error, Result};
+
+use super::{FormatReader, Packet, SeekMode, SeekTo, SeekedTo};
+
+/// A queue of packets for a selected track.
+struct TrackQueue {
+    track_id: u32,
+    packets: VecDeque<Packet>,
+}
+
+/// `TrackFanout` splits the packets of a `FormatReader` into a stream of packets per track.
+///
+/// A `FormatReader` returns the packets of all tracks interleaved in the order they are stored in
+/// the container. `TrackFanout` reads packets on-demand, returning packets of the requested track,
+/// and queueing packets of the other selected tracks until they are requested. Packets of tracks
+/// that were not selected are discarded.
+///
+/// The number of packets queued per track is bounded. If reading the next packet could exceed the
+/// bound for any track, a `LimitError` is returned instead, and no packets are lost. The packets
+/// of the track with the full queue must be consumed before the other tracks may continue.
+pub struct TrackFanout {
+    reader: Box<dyn FormatReader>,
+    queues: Vec<TrackQueue>,
+    max_packets: usize,
+}
+
+impl TrackFanout {
+    /// Instantiate a `TrackFanout` for the given tracks of `reader`, queueing up to `max_packets`
+    /// packets per track.
+    pub fn new(reader: Box<dyn FormatReader>, track_ids: &[u32], max_packets: usize) -> Self {
+        let queues = track_ids
+            .iter()
+            .map(|&track_id| TrackQueue { track_id, packets: Default::default() })
+            .collect();
+
+        TrackFanout { reader, queues, max_packets: max_packets.max(1) }
+    }
+
+    /// Get a reference to the wrapped reader.
+    pub fn reader(&self) -> &dyn FormatReader {
+        self.reader.as_ref()
+    }
+
+    /// Get the number of packets queued for the given track.
+    pub fn queued(&self, track_id: u32) -> usize {
+        self.queues.iter().find(|q| q.track_id == track_id).map_or(0, |q| q.packets.len())
+    }
+
+    /// Get the next packet of the given track.
+    ///
+    /// Errors returned by the wrapped reader, such as the end-of-stream, are returned as-is after
+    /// all queued packets of the track are consumed.
+    pub fn next_packet(&mut self, track_id: u32) -> Result<Packet> {
+        let idx = match self.queues.iter().position(|q| q.track_id == track_id) {
+            Some(idx) => idx,
+            None => return unsupported_error("core (formats): track was not selected for fanout"),
+        };
+
+        if let Some(packet) = self.queues[idx].packets.pop_front() {
+            return Ok(packet);
+        }
+
+        loop {
+            // Do not read another packet if it could not be queued.
+            if self.queues.iter().any(|q| q.packets.len() >= self.max_packets) {
+                return limit_error("core (formats): fanout queue of another track is full");
+            }
+
+            let packet = self.reader.next_packet()?;
+
+            if packet.track_id() == track_id {
+                return Ok(packet);
+            }
+
+            if let Some(queue) = self.queues.iter_mut().find(|q| q.track_id == packet.track_id()) {
+                queue.packets.push_back(packet);
+            }
+        }
+    }
+
+    /// Seek the wrapped reader, discarding all queued packets.
+    pub fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        for queue in self.queues.iter_mut() {
+            queue.packets.clear();
+        }
+
+        self.reader.seek(mode, to)
+    }
+
+    /// Get the wrapped reader, discarding all queued packets.
+    pub fn into_inner(self) -> Box<dyn FormatReader> {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use crate::test_util::MockReader;
+
+    /// Instantiate a reader of 30 packets interleaved between three tracks.
+    fn new_reader() -> Box<MockReader> {
+        Box::new(MockReader::interleaved(3, 30, 1))
+    }
+
+    /// Gets the index of a packet of the mock reader.
+    fn index(packet: Packet) -> u64 {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(packet.buf());
+        u64::from_le_bytes(buf)
+    }
+
+    fn read_track(fanout: &mut TrackFanout, track_id: u32) -> Vec<u64> {
+        let mut indices = Vec::new();
+
+        loop {
+            match fanout.next_packet(track_id) {
+                Ok(packet) => indices.push(index(packet)),
+                Err(Error::IoError(_)) => break,
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+
+        indices
+    }
+
+    #[test]
+    fn verify_fanout_two_tracks() {
+        let expected = |track_id: u64| -> Vec<u64> { (track_id..30).step_by(3).collect() };
+
+        // Reading the first track entirely queues all packets of the second track. The packets of
+        // the unselected third track are discarded.
+        let mut fanout = TrackFanout::new(new_reader(), &[0, 1], 16);
+
+        assert_eq!(read_track(&mut fanout, 0), expected(0));
+        assert_eq!(fanout.queued(1), 10);
+        assert_eq!(read_track(&mut fanout, 1), expected(1));
+
+        assert!(matches!(fanout.next_packet(2), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn verify_fanout_queue_limit() {
+        let mut fanout = TrackFanout::new(new_reader(), &[0, 1], 2);
+
+        let mut track0 = Vec::new();
+        let mut track1 = Vec::new();
+
+        // Read the first track until the queue of the second track is full.
+        loop {
+            match fanout.next_packet(0) {
+                Ok(packet) => track0.push(index(packet)),
+                Err(Error::LimitError(_)) => break,
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+
+        assert_eq!(track0, &[0, 3]);
+        assert_eq!(fanout.queued(1), 2);
+
+        // Alternate between the tracks. No packets may be dropped.
+        loop {
+            match fanout.next_packet(1) {
+                Ok(packet) => track1.push(index(packet)),
+                Err(Error::LimitError(_)) => track0.push(index(fanout.next_packet(0).unwrap())),
+                Err(Error::IoError(_)) => break,
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+
+        track0.extend(read_track(&mut fanout, 0));
+
+        assert_eq!(track0, (0..30).step_by(3).collect::<Vec<_>>());
+        assert_eq!(track1, (1..30).step_by(3).collect::<Vec<_>>());
+    }
+}
@@ -8,18 +8,27 @@
 //! The `format` module provides the traits and support structures necessary to implement media
 //! demuxers.
 
+use std::io;
+use std::iter::FusedIterator;
+
 use crate::codecs::CodecParameters;
-use crate::errors::Result;
+use crate::diagnostics::Diagnostics;
+use crate::errors::{seek_error, Error, Result, SeekErrorKind};
 use crate::io::{BufReader, MediaSourceStream};
 use crate::meta::{Metadata, Tag};
 use crate::units::{Time, TimeStamp};
 
+pub mod fanout;
+pub mod prefetch;
+
 pub mod prelude {
     //! The `formats` module prelude.
 
     pub use crate::units::{Duration, TimeBase, TimeStamp};
 
-    pub use super::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track};
+    pub use super::{
+        Cue, FormatOptions, FormatReader, Packet, Packets, SeekMode, SeekTo, SeekedTo, Track,
+    };
 }
 
 /// `SeekTo` specifies a position to seek to.
@@ -68,6 +77,7 @@ pub enum SeekMode {
 
 /// `FormatOptions` is a common set of options that all demuxers use.
 #[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub struct FormatOptions {
     /// If a `FormatReader` requires a seek index, but the container does not provide one, build the
     /// seek index during instantiation instead of building it progressively. Default: `false`.
@@ -88,6 +98,20 @@ pub struct FormatOptions {
     /// When enabled, this option will also alter the value and interpretation of timestamps and
     /// durations such that they are relative to the non-trimmed region.
     pub enable_gapless: bool,
+    /// Enable lenient parsing. Default: `false`.
+    ///
+    /// By default, readers are strict and will return an error upon encountering any violation of
+    /// the format's specification. When enabled, readers will instead tolerate minor violations
+    /// that are known to be safe to ignore, log a warning, and continue. Each reader decides which
+    /// violations it tolerates, and documents them.
+    pub enable_lenient: bool,
+    /// The maximum number of bytes to scan while searching for a format marker when probing.
+    /// Default: `1048576` (1 MiB).
+    ///
+    /// If a format marker is not found within this number of bytes, the probe returns an
+    /// unsupported error instead of continuing to scan. Since metadata, such as an ID3v2 tag, may
+    /// precede the format, the limit applies to each search for a marker.
+    pub probe_search_limit: u64,
 }
 
 impl Default for FormatOptions {
@@ -96,10 +120,44 @@ impl Default for FormatOptions {
             prebuild_seek_index: false,
             seek_index_fill_rate: 20,
             enable_gapless: false,
+            enable_lenient: false,
+            probe_search_limit: 1024 * 1024,
         }
     }
 }
 
+impl FormatOptions {
+    /// Build the seek index during instantiation if required.
+    pub fn with_prebuild_seek_index(&mut self, prebuild: bool) -> &mut Self {
+        self.prebuild_seek_index = prebuild;
+        self
+    }
+
+    /// Provide how often in seconds of decoded content an entry is added to a seek index.
+    pub fn with_seek_index_fill_rate(&mut self, fill_rate: u16) -> &mut Self {
+        self.seek_index_fill_rate = fill_rate;
+        self
+    }
+
+    /// Enable support for gapless playback.
+    pub fn with_gapless(&mut self, enable: bool) -> &mut Self {
+        self.enable_gapless = enable;
+        self
+    }
+
+    /// Enable lenient parsing.
+    pub fn with_lenient(&mut self, enable: bool) -> &mut Self {
+        self.enable_lenient = enable;
+        self
+    }
+
+    /// Provide the maximum number of bytes to scan while searching for a format marker.
+    pub fn with_probe_search_limit(&mut self, limit: u64) -> &mut Self {
+        self.probe_search_limit = limit;
+        self
+    }
+}
+
 /// A `Cue` is a designated point of time within a media stream.
 ///
 /// A `Cue` may be a mapping from either a source track, a chapter, cuesheet, or a timestamp
@@ -133,6 +191,11 @@ pub struct CuePoint {
 
 /// A `Track` is an independently coded media bitstream. A media format may contain multiple tracks
 /// in one container. Each of those tracks are represented by one `Track`.
+///
+/// The timestamps and durations of all `Packet`s belonging to a track are in the units of the
+/// track's `TimeBase` (see `CodecParameters::time_base`). To place packets from multiple tracks on
+/// a common clock, add the track's `offset` to the packet timestamp, and then convert the result
+/// into a `Time` using the track's `TimeBase`.
 #[derive(Clone, Debug)]
 pub struct Track {
     /// A unique identifier for the track.
@@ -141,11 +204,65 @@ pub struct Track {
     pub codec_params: CodecParameters,
     /// The language of the track. May be unknown.
     pub language: Option<String>,
+    /// The offset of the start of the track relative to the start of the presentation. See
+    /// [`Track::offset`].
+    offset: Option<i64>,
+    /// The stereo balance of the track. See [`Track::balance`].
+    balance: Option<f32>,
 }
 
 impl Track {
     pub fn new(id: u32, codec_params: CodecParameters) -> Self {
-        Track { id, codec_params, language: None }
+        Track { id, codec_params, language: None, offset: None, balance: None }
+    }
+
+    /// Gets the offset, in `TimeBase` units, of the start of the track relative to the start of the
+    /// presentation, as signalled by the container (e.g., an edit list in MP4, or a track offset
+    /// and codec delay in Matroska). May be unknown.
+    ///
+    /// A positive offset indicates that the first packet of the track should be presented after
+    /// the start of the presentation. A negative offset indicates that the leading part of the
+    /// track should not be presented.
+    ///
+    /// Note: When gapless support is enabled, the encoder delay is already removed from the packet
+    /// timestamps. Some containers signal the encoder delay using both gapless information and an
+    /// edit list. In this case, the edit list offset should not be applied a second time.
+    pub fn offset(&self) -> Option<i64> {
+        self.offset
+    }
+
+    /// Provide the offset, in `TimeBase` units, of the start of the track relative to the start of
+    /// the presentation.
+    pub fn with_offset(&mut self, offset: i64) -> &mut Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Gets the stereo balance of the track in the range [-1.0, 1.0], where -1.0 is fully left,
+    /// 0.0 is centered, and 1.0 is fully right. May be unknown.
+    ///
+    /// The balance describes the placement of a mono track within a stereo presentation. It should
+    /// be applied as a pan when a mono track is output to, or mixed into, stereo.
+    pub fn balance(&self) -> Option<f32> {
+        self.balance
+    }
+
+    /// Provide the stereo balance of the track.
+    pub fn with_balance(&mut self, balance: f32) -> &mut Self {
+        self.balance = Some(balance);
+        self
+    }
+
+    /// Converts a timestamp, in the units of the track's `TimeBase`, into a `Time`. Returns `None`
+    /// if the track does not have a `TimeBase`.
+    pub fn ts_to_time(&self, ts: TimeStamp) -> Option<Time> {
+        self.codec_params.time_base.map(|tb| tb.calc_time(ts))
+    }
+
+    /// Converts a `Time` into a timestamp in the units of the track's `TimeBase`. Returns `None`
+    /// if the track does not have a `TimeBase`.
+    pub fn time_to_ts(&self, time: Time) -> Option<TimeStamp> {
+        self.codec_params.time_base.map(|tb| tb.calc_timestamp(time))
     }
 }
 
@@ -165,6 +282,16 @@ impl Track {
 /// `FormatReader` provides an Iterator-like interface over packets for easy consumption and
 /// filtering. Seeking will invalidate the state of any `Decoder` processing packets from the
 /// `FormatReader` and should be reset after a successful seek operation.
+///
+/// # Remuxing
+///
+/// The data of a packet is the encoded bitstream of the track exactly as it was stored in the
+/// container, without any container framing. Together with the codec type and the extra data of
+/// the track (see [`CodecParameters::extra_data`]), this is sufficient to store the packets in
+/// another container without re-encoding them. To remux a container, write a header for each
+/// track using its codec parameters, then, for each packet returned by `next_packet`, get its track
+/// using [`FormatReader::track`] and write the packet data, timestamp, and duration to the new
+/// container.
 pub trait FormatReader: Send + Sync {
     /// Attempt to instantiate a `FormatReader` using the provided `FormatOptions` and
     /// `MediaSourceStream`. The reader will probe the container to verify format support, determine
@@ -193,9 +320,43 @@ pub trait FormatReader: Send + Sync {
     /// seek.
     fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo>;
 
+    /// Rewind the `FormatReader` such that `next_packet` will return packets from the start of
+    /// the media again. This is useful to make multiple passes over the media without re-opening
+    /// it.
+    ///
+    /// After a rewind, all `Decoder`s consuming packets from this reader should be reset.
+    ///
+    /// By default, this is an accurate seek to the first timestamp of the default track. If the
+    /// media cannot be rewound, for example, because the underlying media source is not seekable,
+    /// then an `Unseekable` seek error is returned.
+    fn rewind(&mut self) -> Result<()> {
+        let (ts, track_id) = match self.default_track() {
+            Some(track) => (track.codec_params.start_ts, track.id),
+            None => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        match self.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id }) {
+            Ok(_) => Ok(()),
+            // A forward-only seek to the start of the media is never possible.
+            Err(Error::SeekError(SeekErrorKind::ForwardOnly)) => {
+                seek_error(SeekErrorKind::Unseekable)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
     /// Gets a list of tracks in the container.
     fn tracks(&self) -> &[Track];
 
+    /// Sets the diagnostics hook the reader emits events to. Events are emitted for the data read
+    /// after the hook is set. Events that occurred while the reader was instantiated are not
+    /// emitted.
+    ///
+    /// The default implementation ignores the hook for readers that do not emit diagnostic events.
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        let _ = diagnostics;
+    }
+
     /// Gets the default track. If the `FormatReader` has a method of determining the default track,
     /// this function should return it. Otherwise, the first track is returned. If no tracks are
     /// present then `None` is returned.
@@ -203,8 +364,37 @@ pub trait FormatReader: Send + Sync {
         self.tracks().first()
     }
 
+    /// Gets the track with the given track ID. If there is no such track, then `None` is returned.
+    ///
+    /// Since a packet only identifies the track it belongs to by track ID, this function may be
+    /// used to get the codec parameters for a packet.
+    fn track(&self, track_id: u32) -> Option<&Track> {
+        self.tracks().iter().find(|track| track.id == track_id)
+    }
+
+    /// Gets the total duration of the media.
+    ///
+    /// Some containers state an overall duration for the media that is independent of, and may
+    /// differ from, the number of frames in any one track. If the `FormatReader` knows of such a
+    /// duration, this function should return it. Otherwise, the duration of the default track is
+    /// returned if its number of frames and timebase are known. If the duration cannot be
+    /// determined then `None` is returned.
+    fn duration(&self) -> Option<Time> {
+        let params = &self.default_track()?.codec_params;
+
+        match (params.time_base, params.n_frames) {
+            (Some(tb), Some(n_frames)) => Some(tb.calc_time(n_frames)),
+            _ => None,
+        }
+    }
+
     /// Get the next packet from the container.
     ///
+    /// Packets of all tracks are returned interleaved in the order they are stored in the
+    /// container. To decode multiple tracks from a single pass over the container, the packets
+    /// must be dispatched to each track's decoder by track ID. The `fanout::TrackFanout` helper
+    /// may be used to consume each track's packets independently.
+    ///
     /// If `ResetRequired` is returned, then the track list must be re-examined and all `Decoder`s
     /// re-created. All other errors are unrecoverable.
     fn next_packet(&mut self) -> Result<Packet>;
@@ -213,6 +403,59 @@ pub trait FormatReader: Send + Sync {
     fn into_inner(self: Box<Self>) -> MediaSourceStream;
 }
 
+impl dyn FormatReader {
+    /// Gets an iterator over the packets of the container. See [`Packets`] for details.
+    ///
+    /// To iterate over the packets of a concrete `FormatReader` type, use [`Packets::new`].
+    pub fn packets(&mut self) -> Packets<'_> {
+        Packets::new(self)
+    }
+}
+
+/// `Packets` is an iterator over the packets of a `FormatReader`.
+///
+/// Each item is the result of a call to [`FormatReader::next_packet`]. The iterator ends when the
+/// end of the stream is reached. A `ResetRequired` error is returned as an item, after which the
+/// iteration may continue once the track list has been re-examined. All other errors are
+/// unrecoverable, therefore the iterator ends after returning the error.
+pub struct Packets<'a> {
+    reader: &'a mut dyn FormatReader,
+    is_done: bool,
+}
+
+impl<'a> Packets<'a> {
+    /// Instantiate a new `Packets` iterator over the packets of `reader`.
+    pub fn new(reader: &'a mut dyn FormatReader) -> Self {
+        Packets { reader, is_done: false }
+    }
+}
+
+impl Iterator for Packets<'_> {
+    type Item = Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        match self.reader.next_packet() {
+            Ok(packet) => Some(Ok(packet)),
+            Err(Error::ResetRequired) => Some(Err(Error::ResetRequired)),
+            // The end of the stream was reached.
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                self.is_done = true;
+                None
+            }
+            Err(err) => {
+                self.is_done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl FusedIterator for Packets<'_> {}
+
 /// A `Packet` contains a discrete amount of encoded data for a single codec bitstream. The exact
 /// amount of data is bounded, but not defined, and is dependant on the container and/or the
 /// encapsulated codec.
@@ -236,6 +479,9 @@ pub struct Packet {
     /// When gapless support is enabled, this is the number of decoded frames that should be trimmed
     /// from the end of the packet to remove the encoder padding. Must be 0 in all other cases.
     pub trim_end: u32,
+    /// If `true`, one or more packets preceeding this packet were lost or dropped by the
+    /// `FormatReader`. A decoder should be reset before decoding this packet.
+    discontinuity: bool,
     /// The packet buffer.
     pub data: Box<[u8]>,
 }
@@ -243,12 +489,20 @@ pub struct Packet {
 impl Packet {
     /// Create a new `Packet` from a slice.
     pub fn new_from_slice(track_id: u32, ts: u64, dur: u64, buf: &[u8]) -> Self {
-        Packet { track_id, ts, dur, trim_start: 0, trim_end: 0, data: Box::from(buf) }
+        Packet {
+            track_id,
+            ts,
+            dur,
+            trim_start: 0,
+            trim_end: 0,
+            discontinuity: false,
+            data: Box::from(buf),
+        }
     }
 
     /// Create a new `Packet` from a boxed slice.
     pub fn new_from_boxed_slice(track_id: u32, ts: u64, dur: u64, data: Box<[u8]>) -> Self {
-        Packet { track_id, ts, dur, trim_start: 0, trim_end: 0, data }
+        Packet { track_id, ts, dur, trim_start: 0, trim_end: 0, discontinuity: false, data }
     }
 
     /// Create a new `Packet` with trimming information from a slice.
@@ -260,7 +514,15 @@ impl Packet {
         trim_end: u32,
         buf: &[u8],
     ) -> Self {
-        Packet { track_id, ts, dur, trim_start, trim_end, data: Box::from(buf) }
+        Packet {
+            track_id,
+            ts,
+            dur,
+            trim_start,
+            trim_end,
+            discontinuity: false,
+            data: Box::from(buf),
+        }
     }
 
     /// Create a new `Packet` with trimming information from a boxed slice.
@@ -272,7 +534,7 @@ impl Packet {
         trim_end: u32,
         data: Box<[u8]>,
     ) -> Self {
-        Packet { track_id, ts, dur, trim_start, trim_end, data }
+        Packet { track_id, ts, dur, trim_start, trim_end, discontinuity: false, data }
     }
 
     /// The track identifier of the track this packet belongs to.
@@ -303,6 +565,22 @@ impl Packet {
         self.dur + u64::from(self.trim_start) + u64::from(self.trim_end)
     }
 
+    /// Get the number of audio frames decoding the packet is expected to produce, before any
+    /// frames are trimmed. This may be used to size output buffers before decoding the packet.
+    ///
+    /// This returns the same value as [`Packet::block_dur`]. It exists to state the unit of the
+    /// value: `block_dur` is in `TimeBase` units, while this is in audio frames. The two are only
+    /// equal if the `TimeBase` of the track is the reciprocal of the sample rate, which is the case
+    /// for most audio tracks, but not all (e.g., Matroska tracks). Check the `TimeBase` of the
+    /// track before relying on this value.
+    ///
+    /// For codecs with a constant number of frames per packet, such as PCM and AAC, this value is
+    /// exact. For codecs with a variable number of frames per packet, this value may only be an
+    /// upper bound. Decoders that trim frames themselves will produce `dur` frames instead.
+    pub fn frames(&self) -> u64 {
+        self.block_dur()
+    }
+
     /// Get the number of frames to trim from the start of the decoded packet.
     pub fn trim_start(&self) -> u32 {
         self.trim_start
@@ -313,6 +591,16 @@ impl Packet {
         self.trim_end
     }
 
+    /// Returns `true` if one or more packets preceeding this packet were lost or dropped.
+    pub fn is_discontinuity(&self) -> bool {
+        self.discontinuity
+    }
+
+    /// Flag, or unflag, the packet as following one or more lost or dropped packets.
+    pub fn set_discontinuity(&mut self, discontinuity: bool) {
+        self.discontinuity = discontinuity;
+    }
+
     /// Get an immutable slice to the packet buffer.
     pub fn buf(&self) -> &[u8] {
         &self.data
@@ -513,3 +801,68 @@ pub mod util {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{decode_error, reset_error};
+    use crate::test_util::MockReader;
+    use crate::units::TimeBase;
+
+    fn packet(track_id: u32, ts: u64) -> Result<Packet> {
+        Ok(Packet::new_from_slice(track_id, ts, 1, &[]))
+    }
+
+    /// Summarizes the items of a packet iterator as the packet timestamp, or the error.
+    fn summarize(item: Result<Packet>) -> std::result::Result<u64, String> {
+        item.map(|packet| packet.ts()).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn verify_track_time_conversion() {
+        let mut params = CodecParameters::new();
+        params.with_time_base(TimeBase::new(1, 44_100));
+
+        let track = Track::new(0, params);
+
+        assert_eq!(track.ts_to_time(0), Some(Time::new(0, 0.0)));
+        assert_eq!(track.ts_to_time(66_150), Some(Time::new(1, 0.5)));
+        assert_eq!(track.time_to_ts(Time::new(1, 0.5)), Some(66_150));
+
+        for &ts in &[1, 44_099, 44_100, 123_456_789] {
+            assert_eq!(track.time_to_ts(track.ts_to_time(ts).unwrap()), Some(ts));
+        }
+
+        // A track without a time base cannot convert timestamps.
+        let track = Track::new(1, CodecParameters::new());
+
+        assert_eq!(track.ts_to_time(44_100), None);
+        assert_eq!(track.time_to_ts(Time::new(1, 0.0)), None);
+    }
+
+    #[test]
+    fn verify_packets_reset_required() {
+        let mut reader = MockReader::new(1, vec![packet(0, 0), reset_error(), packet(0, 1)]);
+
+        let items: Vec<_> = Packets::new(&mut reader).map(summarize).collect();
+
+        // A reset required error does not end the iteration.
+        let reset = Error::ResetRequired.to_string();
+        assert_eq!(items, vec![Ok(0), Err(reset), Ok(1)]);
+    }
+
+    #[test]
+    fn verify_packets_unrecoverable_error() {
+        let results = vec![packet(0, 0), decode_error("bad packet"), packet(0, 1)];
+
+        let mut reader: Box<dyn FormatReader> = Box::new(MockReader::new(1, results));
+
+        let mut packets = reader.packets();
+        assert_eq!(packets.next().map(summarize), Some(Ok(0)));
+        assert!(matches!(packets.next(), Some(Err(Error::DecodeError(_)))));
+
+        // The iteration ends after an unrecoverable error.
+        assert!(packets.next().is_none());
+        assert!(packets.next().is_none());
+    }
+}
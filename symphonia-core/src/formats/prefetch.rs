@@ -0,0 +1,268 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `prefetch` module provides a `FormatReader` wrapper that reads packets ahead of time on a
+//! background thread.
+
+use std::collections::VecDeque;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::diagnostics::Diagnostics;
+use crate::errors::{decode_error, unsupported_error, Result};
+use crate::io::MediaSourceStream;
+use crate::meta::Metadata;
+
+use super::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track};
+
+/// The default maximum number of packets to read ahead.
+const DEFAULT_MAX_PACKETS: usize = 16;
+
+/// A background thread that reads packets from a `FormatReader` into a bounded queue.
+struct Prefetcher {
+    /// The queue of prefetched packets. The `Mutex` is only required to make `PrefetchReader`
+    /// `Sync`, it is never contended.
+    packets: Mutex<Receiver<Result<Packet>>>,
+    /// Signals the thread to stop reading packets.
+    stop: Arc<AtomicBool>,
+    /// The thread. Returns the reader when it stops.
+    handle: JoinHandle<Box<dyn FormatReader>>,
+}
+
+impl Prefetcher {
+    fn start(mut reader: Box<dyn FormatReader>, max_packets: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(max_packets);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                let result = reader.next_packet();
+                let is_err = result.is_err();
+
+                // Stop after the first error since it is usually the end-of-stream, or after the
+                // receiver is dropped.
+                if sender.send(result).is_err() || is_err {
+                    break;
+                }
+            }
+            reader
+        });
+
+        Prefetcher { packets: Mutex::new(receiver), stop, handle }
+    }
+
+    fn recv(&mut self) -> Option<Result<Packet>> {
+        self.packets.get_mut().unwrap().recv().ok()
+    }
+
+    /// Stop the thread, move all prefetched packets into `pending`, and return the reader.
+    fn stop(self, pending: &mut VecDeque<Result<Packet>>) -> Box<dyn FormatReader> {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // Drain the queue until the thread exits and drops the sender. Draining also unblocks the
+        // thread if it is waiting for space in the queue.
+        pending.extend(self.packets.into_inner().unwrap().iter());
+
+        match self.handle.join() {
+            Ok(reader) => reader,
+            Err(err) => panic::resume_unwind(err),
+        }
+    }
+}
+
+/// A `FormatReader` wrapper that reads packets ahead of time on a background thread.
+///
+/// `PrefetchReader` reads up to a configurable number of packets from the wrapped reader into a
+/// bounded queue such that `next_packet` rarely blocks on I/O. Packets are returned in the same
+/// order, and with the same track attribution, as the wrapped reader would return them.
+///
+/// Prefetching starts on the first call to `next_packet`. Seeking flushes all prefetched packets.
+/// Calling `metadata` pauses prefetching, but keeps all prefetched packets.
+///
+/// The tracks and cues returned by a `PrefetchReader` are those of the wrapped reader when
+/// prefetching was last paused.
+pub struct PrefetchReader {
+    /// The wrapped reader when not prefetching.
+    reader: Option<Box<dyn FormatReader>>,
+    /// The prefetch thread when prefetching.
+    prefetcher: Option<Prefetcher>,
+    /// Packets that were prefetched before prefetching was paused.
+    pending: VecDeque<Result<Packet>>,
+    max_packets: usize,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+}
+
+impl PrefetchReader {
+    /// Wrap `reader` and prefetch up to `max_packets` packets ahead of time.
+    pub fn new(reader: Box<dyn FormatReader>, max_packets: usize) -> Self {
+        PrefetchReader {
+            tracks: reader.tracks().to_vec(),
+            cues: reader.cues().to_vec(),
+            reader: Some(reader),
+            prefetcher: None,
+            pending: Default::default(),
+            max_packets: max_packets.max(1),
+        }
+    }
+
+    /// Wrap `reader` and prefetch up to a default number of packets ahead of time.
+    pub fn with_default_max_packets(reader: Box<dyn FormatReader>) -> Self {
+        Self::new(reader, DEFAULT_MAX_PACKETS)
+    }
+
+    /// Get the maximum number of packets that will be read ahead of time.
+    pub fn max_packets(&self) -> usize {
+        self.max_packets
+    }
+
+    /// Stop prefetching, if prefetching, and get the wrapped reader.
+    fn pause(&mut self) -> &mut Box<dyn FormatReader> {
+        if let Some(prefetcher) = self.prefetcher.take() {
+            let reader = prefetcher.stop(&mut self.pending);
+
+            self.tracks = reader.tracks().to_vec();
+            self.cues = reader.cues().to_vec();
+            self.reader = Some(reader);
+        }
+
+        self.reader.as_mut().unwrap()
+    }
+}
+
+impl FormatReader for PrefetchReader {
+    fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        unsupported_error("core (formats): a prefetch reader must wrap an existing reader")
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.pause().metadata()
+    }
+
+    fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        self.pause();
+        self.pending.clear();
+        self.reader.as_mut().unwrap().seek(mode, to)
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.pause().set_diagnostics(diagnostics)
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        if let Some(result) = self.pending.pop_front() {
+            return result;
+        }
+
+        if let Some(reader) = self.reader.take() {
+            self.prefetcher = Some(Prefetcher::start(reader, self.max_packets));
+        }
+
+        match self.prefetcher.as_mut().unwrap().recv() {
+            Some(Ok(packet)) => Ok(packet),
+            Some(Err(err)) => {
+                // The prefetch thread stops after an error. Take back the reader so that the next
+                // call restarts prefetching.
+                self.pause();
+                Err(err)
+            }
+            None => {
+                // The prefetch thread exited without an error. This only happens if it panicked,
+                // in which case the panic is resumed here.
+                self.pause();
+                decode_error("core (formats): prefetch thread stopped unexpectedly")
+            }
+        }
+    }
+
+    fn into_inner(mut self: Box<Self>) -> MediaSourceStream {
+        self.pause();
+        self.reader.take().unwrap().into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use crate::test_util::MockReader;
+
+    /// Instantiate a reader of 100 packets interleaved between two tracks.
+    fn new_reader() -> Box<MockReader> {
+        Box::new(MockReader::interleaved(2, 100, 1))
+    }
+
+    fn read_all(reader: &mut dyn FormatReader) -> Vec<(u32, u64, Vec<u8>)> {
+        let mut packets = Vec::new();
+
+        loop {
+            match reader.next_packet() {
+                Ok(packet) => packets.push((packet.track_id(), packet.ts(), packet.buf().to_vec())),
+                Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    break
+                }
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+
+        packets
+    }
+
+    #[test]
+    fn verify_prefetch_matches_reader() {
+        let expected = read_all(new_reader().as_mut());
+
+        for &max_packets in &[1, 3, 16, 200] {
+            let mut reader = PrefetchReader::new(new_reader(), max_packets);
+
+            assert_eq!(reader.tracks().len(), 2);
+            assert_eq!(read_all(&mut reader), expected);
+
+            // Reading after the end-of-stream continues to return the end-of-stream.
+            assert!(reader.next_packet().is_err());
+        }
+    }
+
+    #[test]
+    fn verify_prefetch_seek_and_metadata() {
+        let expected = read_all(new_reader().as_mut());
+
+        let mut reader = PrefetchReader::new(new_reader(), 8);
+
+        // Pausing for metadata must not lose prefetched packets.
+        let mut packets = Vec::new();
+        for _ in 0..10 {
+            let packet = reader.next_packet().unwrap();
+            packets.push((packet.track_id(), packet.ts(), packet.buf().to_vec()));
+        }
+        let _ = reader.metadata();
+        packets.extend(read_all(&mut reader));
+        assert_eq!(packets, expected);
+
+        // Seeking must flush prefetched packets.
+        let _ = reader.next_packet();
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 20, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 20);
+        assert_eq!(read_all(&mut reader), expected[40..]);
+
+        // The source can still be retrieved.
+        let _ = Box::new(reader).into_inner();
+    }
+}
@@ -0,0 +1,219 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+use tokio::runtime::Handle;
+
+use super::MediaSource;
+
+/// `AsyncMediaSource` wraps any seekable asynchronous Tokio source implementing
+/// [`tokio::io::AsyncRead`] and [`tokio::io::AsyncSeek`] in a seekable [`MediaSource`].
+///
+/// Each read or seek of the `MediaSource` blocks the calling thread until the asynchronous source
+/// completes the operation on the Tokio runtime the `AsyncMediaSource` was instantiated on. Data
+/// is only read from the asynchronous source when requested, therefore a slow producer will block,
+/// rather than spin, the consumer, and a slow consumer will apply backpressure to the producer.
+/// Buffering is provided by the [`super::MediaSourceStream`] consuming the `MediaSource`.
+///
+/// Since the operations are blocking, the `MediaSource` must not be read from within an
+/// asynchronous execution context. Instead, decode from a dedicated thread, or a blocking task
+/// spawned with `tokio::task::spawn_blocking`.
+pub struct AsyncMediaSource<R: AsyncRead + AsyncSeek + Unpin> {
+    inner: R,
+    handle: Handle,
+    byte_len: Option<u64>,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncMediaSource<R> {
+    /// Instantiates a new `AsyncMediaSource<R>` by taking ownership and wrapping the provided
+    /// asynchronous source. If known, the length of the source in bytes may be provided.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not called from within the context of a Tokio runtime.
+    pub fn new(inner: R, byte_len: Option<u64>) -> Self {
+        AsyncMediaSource { inner, handle: Handle::current(), byte_len }
+    }
+
+    /// Gets a reference to the underlying asynchronous source.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying asynchronous source.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `AsyncMediaSource<R>`, returning the underlying asynchronous source.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin + Send + Sync> MediaSource for AsyncMediaSource<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.byte_len
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> io::Read for AsyncMediaSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.handle.block_on(self.inner.read(buf))
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> io::Seek for AsyncMediaSource<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.handle.block_on(self.inner.seek(pos))
+    }
+}
+
+/// `AsyncReadOnlySource` wraps any asynchronous Tokio source implementing
+/// [`tokio::io::AsyncRead`] in an unseekable [`MediaSource`].
+///
+/// This is the asynchronous counterpart of [`super::ReadOnlySource`], and is suitable for network
+/// streams, pipes, and in-process channels such as `tokio::io::DuplexStream`. See
+/// [`AsyncMediaSource`] for details on how reads are performed.
+pub struct AsyncReadOnlySource<R: AsyncRead + Unpin> {
+    inner: R,
+    handle: Handle,
+}
+
+impl<R: AsyncRead + Unpin> AsyncReadOnlySource<R> {
+    /// Instantiates a new `AsyncReadOnlySource<R>` by taking ownership and wrapping the provided
+    /// asynchronous source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not called from within the context of a Tokio runtime.
+    pub fn new(inner: R) -> Self {
+        AsyncReadOnlySource { inner, handle: Handle::current() }
+    }
+
+    /// Gets a reference to the underlying asynchronous source.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying asynchronous source.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `AsyncReadOnlySource<R>`, returning the underlying asynchronous source.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send + Sync> MediaSource for AsyncReadOnlySource<R> {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<R: AsyncRead + Unpin> io::Read for AsyncReadOnlySource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.handle.block_on(self.inner.read(buf))
+    }
+}
+
+impl<R: AsyncRead + Unpin> io::Seek for AsyncReadOnlySource<R> {
+    fn seek(&mut self, _: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(io::ErrorKind::Other, "source does not support seeking"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    use tokio::io::AsyncWriteExt;
+    use tokio::runtime::Builder;
+
+    use super::{AsyncMediaSource, AsyncReadOnlySource};
+    use crate::io::{MediaSource, MediaSourceStream, ReadBytes};
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn verify_async_read_only_source() {
+        let rt = Builder::new_current_thread().build().unwrap();
+
+        let data = pattern(10_000);
+        let expected = data.clone();
+
+        let read = rt.block_on(async move {
+            // The duplex buffer is much smaller than the data. Therefore, the writer must wait for
+            // the reader to consume data, and the reader must wait for the writer to produce data.
+            let (mut tx, rx) = tokio::io::duplex(64);
+
+            let source = AsyncReadOnlySource::new(rx);
+            assert!(!source.is_seekable());
+            assert_eq!(source.byte_len(), None);
+
+            let reader = tokio::task::spawn_blocking(move || {
+                let mut mss = MediaSourceStream::new(Box::new(source), Default::default());
+
+                let mut buf = vec![0; 10_000];
+                mss.read_buf_exact(&mut buf).unwrap();
+
+                // The writer was dropped, so the end of the stream is reached.
+                assert!(mss.read_byte().is_err());
+                buf
+            });
+
+            for chunk in data.chunks(100) {
+                tx.write_all(chunk).await.unwrap();
+            }
+
+            drop(tx);
+
+            reader.await.unwrap()
+        });
+
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn verify_async_media_source() {
+        let rt = Builder::new_current_thread().build().unwrap();
+        let _guard = rt.enter();
+
+        let data = pattern(1000);
+
+        let source = AsyncMediaSource::new(Cursor::new(data.clone()), Some(1000));
+        assert!(source.is_seekable());
+        assert_eq!(source.byte_len(), Some(1000));
+
+        // Reading and seeking is performed outside of the asynchronous execution context.
+        let mut mss = MediaSourceStream::new(Box::new(source), Default::default());
+
+        assert_eq!(mss.seek(SeekFrom::Start(600)).unwrap(), 600);
+        assert_eq!(mss.read_byte().unwrap(), data[600]);
+
+        assert_eq!(mss.seek(SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(mss.read_be_u16().unwrap(), u16::from_be_bytes([data[10], data[11]]));
+
+        assert_eq!(mss.seek(SeekFrom::End(-1)).unwrap(), 999);
+        assert_eq!(mss.read_byte().unwrap(), data[999]);
+        assert!(mss.read_byte().is_err());
+    }
+}
@@ -630,6 +630,28 @@ pub trait ReadBitsLtr: private::FetchBitsLtr {
         Ok(bit as u32)
     }
 
+    /// Returns up to 32-bits without consuming them or returns an error. The following read will
+    /// return the same bits.
+    #[inline(always)]
+    fn peek_bits_leq32(&mut self, bit_width: u32) -> io::Result<u32> {
+        debug_assert!(bit_width <= u32::BITS);
+
+        // Fetch bits until there are enough bits buffered. A fetch that does not increase the
+        // number of bits buffered indicates the end of the bitstream.
+        while self.num_bits_left() < bit_width {
+            let num_bits_left = self.num_bits_left();
+
+            self.fetch_bits_partial()?;
+
+            if self.num_bits_left() == num_bits_left {
+                return end_of_bitstream_error();
+            }
+        }
+
+        // Shift in two 32-bit operations to avoid panicing when bit_width == 0.
+        Ok(((self.get_bits() >> u32::BITS) >> (u32::BITS - bit_width)) as u32)
+    }
+
     /// Reads and returns up to 32-bits or returns an error.
     #[inline(always)]
     fn read_bits_leq32(&mut self, mut bit_width: u32) -> io::Result<u32> {
@@ -915,7 +937,14 @@ impl<'a, B: ReadBytes> private::FetchBitsLtr for BitStreamLtr<'a, B> {
 
     #[inline(always)]
     fn fetch_bits_partial(&mut self) -> io::Result<()> {
-        todo!()
+        // Only one byte is fetched at a time to avoid reading past the end of the bitstream in the
+        // source.
+        if self.n_bits_left <= u64::BITS - u8::BITS {
+            let byte = u64::from(self.reader.read_u8()?);
+            self.bits |= byte << (u64::BITS - u8::BITS - self.n_bits_left);
+            self.n_bits_left += u8::BITS;
+        }
+        Ok(())
     }
 
     #[inline(always)]
@@ -1080,6 +1109,30 @@ pub trait ReadBitsRtl: private::FetchBitsRtl {
         Ok(bit as u32)
     }
 
+    /// Returns up to 32-bits without consuming them or returns an error. The following read will
+    /// return the same bits.
+    #[inline(always)]
+    fn peek_bits_leq32(&mut self, bit_width: u32) -> io::Result<u32> {
+        debug_assert!(bit_width <= u32::BITS);
+
+        // Fetch bits until there are enough bits buffered. A fetch that does not increase the
+        // number of bits buffered indicates the end of the bitstream.
+        while self.num_bits_left() < bit_width {
+            let num_bits_left = self.num_bits_left();
+
+            self.fetch_bits_partial()?;
+
+            if self.num_bits_left() == num_bits_left {
+                return end_of_bitstream_error();
+            }
+        }
+
+        // Since bit_width is <= 32, this shift will never panic.
+        let mask = !(!0 << bit_width);
+
+        Ok((self.get_bits() & mask) as u32)
+    }
+
     /// Reads and returns up to 32-bits or returns an error.
     #[inline(always)]
     fn read_bits_leq32(&mut self, bit_width: u32) -> io::Result<u32> {
@@ -1364,7 +1417,14 @@ impl<'a, B: ReadBytes> private::FetchBitsRtl for BitStreamRtl<'a, B> {
 
     #[inline(always)]
     fn fetch_bits_partial(&mut self) -> io::Result<()> {
-        todo!()
+        // Only one byte is fetched at a time to avoid reading past the end of the bitstream in the
+        // source.
+        if self.n_bits_left <= u64::BITS - u8::BITS {
+            let byte = u64::from(self.reader.read_u8()?);
+            self.bits |= byte << self.n_bits_left;
+            self.n_bits_left += u8::BITS;
+        }
+        Ok(())
     }
 
     #[inline(always)]
@@ -1467,8 +1527,10 @@ impl<'a> FiniteBitStream for BitReaderRtl<'a> {
 #[cfg(test)]
 mod tests {
     use super::vlc::{BitOrder, Codebook, CodebookBuilder, Entry8x8};
-    use super::{BitReaderLtr, ReadBitsLtr};
-    use super::{BitReaderRtl, ReadBitsRtl};
+    use super::FiniteBitStream;
+    use super::{BitReaderLtr, BitStreamLtr, ReadBitsLtr};
+    use super::{BitReaderRtl, BitStreamRtl, ReadBitsRtl};
+    use crate::io::BufReader;
 
     #[test]
     #[allow(clippy::bool_assert_comparison)]
@@ -1638,6 +1700,45 @@ mod tests {
         assert!(bs.read_bits_leq64(9).is_err());
     }
 
+    #[test]
+    fn verify_bitstreamltr_peek_bits_leq32() {
+        // Peeking does not consume bits, and the following read returns the same bits.
+        let mut bs = BitReaderLtr::new(&[0b1010_0101, 0b0111_1110, 0b1101_0011]);
+
+        assert_eq!(bs.peek_bits_leq32(4).unwrap(), 0b1010);
+        assert_eq!(bs.peek_bits_leq32(12).unwrap(), 0b1010_0101_0111);
+        assert_eq!(bs.bits_left(), 24);
+        assert_eq!(bs.read_bits_leq32(4).unwrap(), 0b1010);
+        assert_eq!(bs.peek_bits_leq32(0).unwrap(), 0);
+        assert_eq!(bs.peek_bits_leq32(20).unwrap(), 0b0101_0111_1110_1101_0011);
+        assert_eq!(bs.bits_left(), 20);
+        assert_eq!(bs.read_bits_leq32(20).unwrap(), 0b0101_0111_1110_1101_0011);
+
+        // Peeking across the bit cache boundary.
+        let mut bs = BitReaderLtr::new(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x5a]);
+
+        assert_eq!(bs.read_bits_leq32(32).unwrap(), u32::MAX);
+        assert_eq!(bs.read_bits_leq32(24).unwrap(), 0xff_ffff);
+        assert_eq!(bs.peek_bits_leq32(16).unwrap(), 0x005a);
+        assert_eq!(bs.bits_left(), 16);
+        assert_eq!(bs.read_bits_leq32(16).unwrap(), 0x005a);
+
+        // Peeking from a bitstream.
+        let mut reader = BufReader::new(&[0b1010_0101, 0b0111_1110, 0b1101_0011, 0xff]);
+        let mut bs = BitStreamLtr::new(&mut reader);
+
+        assert_eq!(bs.read_bits_leq32(4).unwrap(), 0b1010);
+        assert_eq!(bs.peek_bits_leq32(16).unwrap(), 0b0101_0111_1110_1101);
+        assert_eq!(bs.read_bits_leq32(16).unwrap(), 0b0101_0111_1110_1101);
+        assert_eq!(bs.read_bits_leq32(12).unwrap(), 0b0011_1111_1111);
+
+        // Test error cases.
+        let mut bs = BitReaderLtr::new(&[0xff]);
+
+        assert!(bs.peek_bits_leq32(9).is_err());
+        assert_eq!(bs.read_bits_leq32(8).unwrap(), 0xff);
+    }
+
     #[test]
     fn verify_bitstreamltr_read_unary_zeros() {
         // General tests
@@ -2007,6 +2108,45 @@ mod tests {
         assert!(bs.read_bits_leq64(9).is_err());
     }
 
+    #[test]
+    fn verify_bitstreamrtl_peek_bits_leq32() {
+        // Peeking does not consume bits, and the following read returns the same bits.
+        let mut bs = BitReaderRtl::new(&[0b1010_0101, 0b0111_1110, 0b1101_0011]);
+
+        assert_eq!(bs.peek_bits_leq32(4).unwrap(), 0b0101);
+        assert_eq!(bs.peek_bits_leq32(12).unwrap(), 0b1110_1010_0101);
+        assert_eq!(bs.bits_left(), 24);
+        assert_eq!(bs.read_bits_leq32(4).unwrap(), 0b0101);
+        assert_eq!(bs.peek_bits_leq32(0).unwrap(), 0);
+        assert_eq!(bs.peek_bits_leq32(20).unwrap(), 0b1101_0011_0111_1110_1010);
+        assert_eq!(bs.bits_left(), 20);
+        assert_eq!(bs.read_bits_leq32(20).unwrap(), 0b1101_0011_0111_1110_1010);
+
+        // Peeking across the bit cache boundary.
+        let mut bs = BitReaderRtl::new(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x5a]);
+
+        assert_eq!(bs.read_bits_leq32(32).unwrap(), u32::MAX);
+        assert_eq!(bs.read_bits_leq32(24).unwrap(), 0xff_ffff);
+        assert_eq!(bs.peek_bits_leq32(16).unwrap(), 0x5a00);
+        assert_eq!(bs.bits_left(), 16);
+        assert_eq!(bs.read_bits_leq32(16).unwrap(), 0x5a00);
+
+        // Peeking from a bitstream.
+        let mut reader = BufReader::new(&[0b1010_0101, 0b0111_1110, 0b1101_0011, 0xff]);
+        let mut bs = BitStreamRtl::new(&mut reader);
+
+        assert_eq!(bs.read_bits_leq32(4).unwrap(), 0b0101);
+        assert_eq!(bs.peek_bits_leq32(16).unwrap(), 0b0011_0111_1110_1010);
+        assert_eq!(bs.read_bits_leq32(16).unwrap(), 0b0011_0111_1110_1010);
+        assert_eq!(bs.read_bits_leq32(12).unwrap(), 0b1111_1111_1101);
+
+        // Test error cases.
+        let mut bs = BitReaderRtl::new(&[0xff]);
+
+        assert!(bs.peek_bits_leq32(9).is_err());
+        assert_eq!(bs.read_bits_leq32(8).unwrap(), 0xff);
+    }
+
     #[test]
     fn verify_bitstreamrtl_read_unary_zeros() {
         // General tests
@@ -49,6 +49,11 @@ impl Default for MediaSourceStreamOptions {
 /// length buffer cache. By default, the buffer caches allows backtracking by up-to the minimum of
 /// either `buffer_len - 32kB` or the total number of bytes read since instantiation or the last
 /// buffer cache invalidation. Note that regular a `seek()` will invalidate the buffer cache.
+///
+/// Lastly, to allow a non-seekable source to be read more than once, a rewind window may be
+/// started with [`MediaSourceStream::start_rewind_window`]. All bytes read after the window is
+/// started are retained, and [`MediaSourceStream::rewind_to_window_start`] returns the stream to
+/// the start of the window.
 pub struct MediaSourceStream {
     /// The source reader.
     inner: Box<dyn MediaSource>,
@@ -67,6 +72,17 @@ pub struct MediaSourceStream {
     /// Relative position of the inner stream from the last seek or 0. This is a count of bytes
     /// read from the inner reader since instantiation or the last seek.
     rel_pos: u64,
+    /// The active rewind window, if any.
+    rewind_window: Option<RewindWindow>,
+}
+
+/// A range of the stream that may be rewound.
+#[derive(Copy, Clone)]
+struct RewindWindow {
+    /// The position of the start of the window.
+    start: u64,
+    /// The maximum length of the window in bytes.
+    max_len: usize,
 }
 
 impl MediaSourceStream {
@@ -87,9 +103,55 @@ impl MediaSourceStream {
             read_block_len: Self::MIN_BLOCK_LEN,
             abs_pos: 0,
             rel_pos: 0,
+            rewind_window: None,
         }
     }
 
+    /// Starts a rewind window at the current position of the stream. Up-to `max_len` bytes read
+    /// after this call are retained such that the stream may be rewound to the current position
+    /// with [`MediaSourceStream::rewind_to_window_start`], even if the source is not seekable.
+    /// This is useful to examine the start of a stream before handing it over to a reader.
+    ///
+    /// Starting a new window replaces the previous window. A regular `seek()` ends the window.
+    pub fn start_rewind_window(&mut self, max_len: usize) {
+        self.ensure_seekback_buffer(max_len);
+        self.rewind_window = Some(RewindWindow { start: self.pos(), max_len });
+    }
+
+    /// Ends the rewind window, if one was started.
+    pub fn end_rewind_window(&mut self) {
+        self.rewind_window = None;
+    }
+
+    /// Rewinds the stream to the start of the rewind window. The window remains active so that the
+    /// stream may be rewound again.
+    ///
+    /// Returns an error if no rewind window was started, or if the stream is positioned outside of
+    /// the window because more than the maximum length of the window was read.
+    pub fn rewind_to_window_start(&mut self) -> io::Result<()> {
+        let window = match self.rewind_window {
+            Some(window) => window,
+            None => {
+                return Err(io::Error::new(io::ErrorKind::Other, "no rewind window was started"))
+            }
+        };
+
+        let pos = self.pos();
+
+        if pos < window.start || pos - window.start > window.max_len as u64 {
+            return Err(io::Error::new(io::ErrorKind::Other, "rewind window exceeded"));
+        }
+
+        // Since the ring buffer retains at least the maximum length of the window, the start of
+        // the window is always buffered.
+        let len = (pos - window.start) as usize;
+        debug_assert!(len <= self.read_buffer_len());
+
+        self.seek_buffered_rev(len);
+
+        Ok(())
+    }
+
     /// Returns if the buffer has been exhausted This is a marginally more efficient way of checking
     /// if `unread_buffer_len() == 0`.
     #[inline(always)]
@@ -171,6 +233,7 @@ impl MediaSourceStream {
         self.read_block_len = Self::MIN_BLOCK_LEN;
         self.abs_pos = pos;
         self.rel_pos = 0;
+        self.rewind_window = None;
     }
 }
 
@@ -468,6 +531,7 @@ impl SeekBuffered for MediaSourceStream {
 #[cfg(test)]
 mod tests {
     use super::{MediaSourceStream, ReadBytes, SeekBuffered};
+    use crate::io::ReadOnlySource;
     use std::io::{Cursor, Read};
 
     /// Generate a random vector of bytes of the specified length using a PRNG.
@@ -608,4 +672,45 @@ mod tests {
         assert_eq!(mss.read_u32().unwrap(), 2378776723);
         assert_eq!(mss.read_u64().unwrap(), 5170196279331153683);
     }
+
+    #[test]
+    fn verify_mss_rewind_window() {
+        let data = generate_random_bytes(1024 * 1024);
+
+        // A non-seekable source.
+        let ms = ReadOnlySource::new(Cursor::new(data.clone()));
+        let mut mss = MediaSourceStream::new(Box::new(ms), Default::default());
+
+        // Rewinding requires a window.
+        assert!(mss.rewind_to_window_start().is_err());
+
+        mss.ignore_bytes(100).unwrap();
+
+        // The window is larger than the default buffer.
+        let max_len = 200 * 1024;
+        mss.start_rewind_window(max_len);
+
+        let mut buf = vec![0; max_len];
+
+        // The stream can be rewound more than once.
+        for _ in 0..2 {
+            ReadBytes::read_buf_exact(&mut mss, &mut buf).unwrap();
+            assert_eq!(&buf[..], &data[100..100 + max_len]);
+
+            mss.rewind_to_window_start().unwrap();
+            assert_eq!(mss.pos(), 100);
+        }
+
+        // Reading past the end of the window prevents rewinding.
+        mss.ignore_bytes(max_len as u64 + 1).unwrap();
+        assert!(mss.rewind_to_window_start().is_err());
+
+        // The stream continues to be readable after an error.
+        assert_eq!(mss.read_byte().unwrap(), data[100 + max_len + 1]);
+
+        // Once the window is ended, rewinding is not possible.
+        mss.start_rewind_window(16);
+        mss.end_rewind_window();
+        assert!(mss.rewind_to_window_start().is_err());
+    }
 }
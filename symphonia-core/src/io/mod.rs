@@ -22,12 +22,16 @@
 use std::io;
 use std::mem;
 
+#[cfg(feature = "async")]
+mod async_source;
 mod bit;
 mod buf_reader;
 mod media_source_stream;
 mod monitor_stream;
 mod scoped_stream;
 
+#[cfg(feature = "async")]
+pub use async_source::{AsyncMediaSource, AsyncReadOnlySource};
 pub use bit::*;
 pub use buf_reader::BufReader;
 pub use media_source_stream::{MediaSourceStream, MediaSourceStreamOptions};
@@ -140,8 +144,65 @@ impl<R: io::Read> io::Seek for ReadOnlySource<R> {
     }
 }
 
+/// `MemorySource` wraps an owned in-memory buffer of bytes in a seekable [`MediaSource`] of known
+/// length.
+///
+/// Any buffer that can be viewed as a byte slice may be wrapped, such as a `Vec<u8>`, `Box<[u8]>`,
+/// `Arc<[u8]>`, or `bytes::Bytes`. The buffer is not copied.
+pub struct MemorySource<T: AsRef<[u8]>> {
+    inner: io::Cursor<T>,
+}
+
+impl<T: AsRef<[u8]>> MemorySource<T> {
+    /// Instantiates a new `MemorySource<T>` by taking ownership of the provided buffer. Reading
+    /// starts at the beginning of the buffer.
+    pub fn new(buf: T) -> Self {
+        MemorySource { inner: io::Cursor::new(buf) }
+    }
+
+    /// Gets a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &T {
+        self.inner.get_ref()
+    }
+
+    /// Gets the current position in the underlying buffer.
+    pub fn position(&self) -> u64 {
+        self.inner.position()
+    }
+
+    /// Unwraps this `MemorySource<T>`, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: AsRef<[u8]> + Send + Sync> MediaSource for MemorySource<T> {
+    /// Always returns true since a buffer in memory is always seekable.
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    /// Returns the length in bytes of the underlying buffer.
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.inner.get_ref().as_ref().len() as u64)
+    }
+}
+
+impl<T: AsRef<[u8]>> io::Read for MemorySource<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<T: AsRef<[u8]>> io::Seek for MemorySource<T> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
 /// `ReadBytes` provides methods to read bytes and interpret them as little- or big-endian
-/// unsigned integers or floating-point values of standard widths.
+/// unsigned integers or floating-point values of standard widths, unsigned integers of any width up
+/// to 8 bytes, or variable-size integers.
 pub trait ReadBytes {
     /// Reads a single byte from the stream and returns it or an error.
     fn read_byte(&mut self) -> io::Result<u8>;
@@ -329,6 +390,76 @@ pub trait ReadBytes {
         Ok(f64::from_be_bytes(buf))
     }
 
+    /// Reads `n_bytes` bytes from the stream and interprets them as an unsigned big-endian integer
+    /// of that width or returns an error. The width must be at most 8 bytes. A width of 0 bytes
+    /// reads nothing, and returns 0.
+    fn read_be_uint(&mut self, n_bytes: usize) -> io::Result<u64> {
+        if n_bytes > mem::size_of::<u64>() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "integer width exceeds 8 bytes",
+            ));
+        }
+
+        let mut buf = [0u8; mem::size_of::<u64>()];
+        self.read_buf_exact(&mut buf[mem::size_of::<u64>() - n_bytes..])?;
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    /// Reads `n_bytes` bytes from the stream and interprets them as an unsigned little-endian
+    /// integer of that width or returns an error. The width must be at most 8 bytes. A width of 0
+    /// bytes reads nothing, and returns 0.
+    fn read_le_uint(&mut self, n_bytes: usize) -> io::Result<u64> {
+        if n_bytes > mem::size_of::<u64>() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "integer width exceeds 8 bytes",
+            ));
+        }
+
+        let mut buf = [0u8; mem::size_of::<u64>()];
+        self.read_buf_exact(&mut buf[..n_bytes])?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Reads an EBML variable-size integer (as in RFC8794) from the stream, and returns its value
+    /// and width in bytes, or an error.
+    ///
+    /// The width of a variable-size integer is one more than the number of leading zero bits of
+    /// its first byte, and is at most 8 bytes. The returned value excludes the length marker bit.
+    /// A value with all bits set is reserved, and is returned as-is. See
+    /// [`ReadBytes::read_vint_value`] to detect it.
+    fn read_vint(&mut self) -> io::Result<(u64, u32)> {
+        let byte = self.read_byte()?;
+
+        if byte == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid variable-size integer",
+            ));
+        }
+
+        let width = byte.leading_zeros() + 1;
+
+        // Clear the length marker bit, and then append the remaining bytes.
+        let first = u64::from(byte) & (0xff >> width);
+        let rest = self.read_be_uint((width - 1) as usize)?;
+
+        Ok(((first << (8 * (width - 1))) | rest, width))
+    }
+
+    /// Reads an EBML variable-size integer (as in RFC8794) from the stream, and returns its value
+    /// or an error. If all the bits of the value are set, the value is unknown, and `None` is
+    /// returned. For example, this indicates an element of unknown size.
+    fn read_vint_value(&mut self) -> io::Result<Option<u64>> {
+        let (value, width) = self.read_vint()?;
+
+        // The maximum value for the width, where all 7 bits per byte are set.
+        let unknown = u64::MAX >> (64 - 7 * width);
+
+        Ok(if value == unknown { None } else { Some(value) })
+    }
+
     /// Reads up-to the number of bytes requested, and returns a boxed slice of the data or an
     /// error.
     fn read_boxed_slice(&mut self, len: usize) -> io::Result<Box<[u8]>> {
@@ -505,3 +636,128 @@ pub trait FiniteStream {
     /// Returns the number of bytes available for reading.
     fn bytes_available(&self) -> u64;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, SeekFrom};
+    use std::sync::Arc;
+
+    use super::{BufReader, MediaSource, MediaSourceStream, MemorySource, ReadBytes};
+
+    #[test]
+    fn verify_memory_source() {
+        let buf: Vec<u8> = (0..100).collect();
+
+        let mut source = MemorySource::new(buf.clone());
+        assert!(source.is_seekable());
+        assert_eq!(source.byte_len(), Some(100));
+
+        let mut bytes = [0; 4];
+        source.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, [0, 1, 2, 3]);
+
+        // Seek relative to the end, current position, and start of the buffer.
+        assert_eq!(source.seek(SeekFrom::End(-10)).unwrap(), 90);
+        source.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, [90, 91, 92, 93]);
+
+        assert_eq!(source.seek(SeekFrom::Current(-44)).unwrap(), 50);
+        assert_eq!(source.seek(SeekFrom::Start(20)).unwrap(), 20);
+        assert_eq!(source.position(), 20);
+
+        // Seeking before the start of the buffer is an error, while seeking past the end reads
+        // nothing.
+        assert!(source.seek(SeekFrom::Current(-21)).is_err());
+        assert_eq!(source.seek(SeekFrom::Start(200)).unwrap(), 200);
+        assert_eq!(source.read(&mut bytes).unwrap(), 0);
+
+        // The length does not change while reading, and the buffer is returned unmodified.
+        assert_eq!(source.byte_len(), Some(100));
+        assert_eq!(source.into_inner(), buf);
+    }
+
+    #[test]
+    fn verify_memory_source_stream() {
+        let buf: Arc<[u8]> = (0..100).collect::<Vec<u8>>().into();
+
+        let mut mss = MediaSourceStream::new(Box::new(MemorySource::new(buf)), Default::default());
+        assert!(mss.is_seekable());
+        assert_eq!(mss.byte_len(), Some(100));
+
+        assert_eq!(mss.seek(SeekFrom::Start(98)).unwrap(), 98);
+        assert_eq!(mss.read_be_u16().unwrap(), 0x6263);
+        assert!(mss.read_byte().is_err());
+    }
+
+    #[test]
+    fn verify_read_uint() {
+        let buf = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+        for n_bytes in 1..=8 {
+            let be = buf[..n_bytes].iter().fold(0u64, |acc, &byte| (acc << 8) | u64::from(byte));
+            let le =
+                buf[..n_bytes].iter().rev().fold(0u64, |acc, &byte| (acc << 8) | u64::from(byte));
+
+            let mut reader = BufReader::new(&buf);
+            assert_eq!(reader.read_be_uint(n_bytes).unwrap(), be);
+            assert_eq!(reader.pos(), n_bytes as u64);
+
+            let mut reader = BufReader::new(&buf);
+            assert_eq!(reader.read_le_uint(n_bytes).unwrap(), le);
+            assert_eq!(reader.pos(), n_bytes as u64);
+        }
+
+        // All bits set at the maximum width.
+        let mut reader = BufReader::new(&[0xff; 8]);
+        assert_eq!(reader.read_be_uint(8).unwrap(), u64::MAX);
+
+        // A width of 0 bytes reads nothing.
+        let mut reader = BufReader::new(&buf);
+        assert_eq!(reader.read_le_uint(0).unwrap(), 0);
+        assert_eq!(reader.pos(), 0);
+
+        // A width greater than 8 bytes, or longer than the stream, is an error.
+        assert!(BufReader::new(&[0; 9]).read_be_uint(9).is_err());
+        assert!(BufReader::new(&buf[..2]).read_le_uint(3).is_err());
+    }
+
+    #[test]
+    fn verify_read_vint() {
+        // The value 2 encoded at every width.
+        for width in 1..=8 {
+            let mut buf = vec![0; width];
+            buf[0] = 0x80 >> (width - 1);
+            buf[width - 1] |= 0x02;
+
+            let mut reader = BufReader::new(&buf);
+            assert_eq!(reader.read_vint().unwrap(), (2, width as u32));
+            assert_eq!(reader.pos(), width as u64);
+        }
+
+        // Data bits in the first byte are part of the value.
+        assert_eq!(BufReader::new(&[0x5a, 0xbc]).read_vint().unwrap(), (0x1abc, 2));
+        assert_eq!(BufReader::new(&[0x5a, 0xbc]).read_vint_value().unwrap(), Some(0x1abc));
+
+        // A first byte without a length marker is an error.
+        assert!(BufReader::new(&[0x00, 0xff]).read_vint().is_err());
+    }
+
+    #[test]
+    fn verify_read_vint_unknown() {
+        // All data bits set is the unknown value at every width.
+        for width in 1..=8 {
+            let mut buf = vec![0xff; width];
+            buf[0] = 0xff >> (width - 1);
+
+            let mut reader = BufReader::new(&buf);
+            assert_eq!(reader.read_vint_value().unwrap(), None);
+
+            let mut reader = BufReader::new(&buf);
+            assert_eq!(reader.read_vint().unwrap(), ((1 << (7 * width)) - 1, width as u32));
+        }
+
+        // One less than the unknown value is known.
+        assert_eq!(BufReader::new(&[0xfe]).read_vint_value().unwrap(), Some(0x7e));
+        assert_eq!(BufReader::new(&[0x7f, 0xfe]).read_vint_value().unwrap(), Some(0x3ffe));
+    }
+}
@@ -16,6 +16,7 @@ pub mod audio;
 pub mod checksum;
 pub mod codecs;
 pub mod conv;
+pub mod diagnostics;
 pub mod dsp;
 pub mod errors;
 pub mod formats;
@@ -25,3 +26,6 @@ pub mod probe;
 pub mod sample;
 pub mod units;
 pub mod util;
+
+#[cfg(test)]
+mod test_util;
@@ -15,6 +15,7 @@ use std::num::NonZeroU32;
 
 use crate::errors::Result;
 use crate::io::MediaSourceStream;
+use crate::units::Time;
 
 /// `Limit` defines an upper-bound on how much of a resource should be allocated when the amount to
 /// be allocated is specified by the media stream, which is untrusted. A limit will place an
@@ -130,6 +131,7 @@ pub enum StandardTagKey {
     IdentPn,
     IdentPodcast,
     IdentUpc,
+    InitialKey,
     Label,
     Language,
     License,
@@ -388,12 +390,46 @@ pub struct VendorData {
     pub data: Box<[u8]>,
 }
 
+/// A `Chapter` is a titled section of the media, such as a chapter of an audiobook or podcast.
+#[derive(Clone, Debug)]
+pub struct Chapter {
+    /// The time the chapter starts at.
+    pub start_time: Time,
+    /// The time the chapter ends at, if known.
+    pub end_time: Option<Time>,
+    /// Any tags associated with the chapter (e.g., the title).
+    pub tags: Vec<Tag>,
+    /// Any visuals associated with the chapter.
+    pub visuals: Vec<Visual>,
+}
+
+/// An item of a `ChapterGroup`.
+#[derive(Clone, Debug)]
+pub enum ChapterGroupItem {
+    /// A nested group of chapters.
+    Group(ChapterGroup),
+    /// A chapter.
+    Chapter(Chapter),
+}
+
+/// A `ChapterGroup` is a group of chapters and nested chapter groups, such as a table of contents.
+#[derive(Clone, Debug, Default)]
+pub struct ChapterGroup {
+    /// The chapters and nested chapter groups of the group, in order.
+    pub items: Vec<ChapterGroupItem>,
+    /// Any tags associated with the group (e.g., the title).
+    pub tags: Vec<Tag>,
+    /// Any visuals associated with the group.
+    pub visuals: Vec<Visual>,
+}
+
 /// `Metadata` is a container for a single discrete revision of metadata information.
 #[derive(Clone, Debug, Default)]
 pub struct MetadataRevision {
     tags: Vec<Tag>,
     visuals: Vec<Visual>,
     vendor_data: Vec<VendorData>,
+    chapters: Option<ChapterGroup>,
 }
 
 impl MetadataRevision {
@@ -414,6 +450,11 @@ impl MetadataRevision {
     pub fn vendor_data(&self) -> &[VendorData] {
         &self.vendor_data
     }
+
+    /// Gets the top-level `ChapterGroup` of this revision, if any.
+    pub fn chapters(&self) -> Option<&ChapterGroup> {
+        self.chapters.as_ref()
+    }
 }
 
 /// `MetadataBuilder` is the builder for `Metadata` revisions.
@@ -446,6 +487,12 @@ impl MetadataBuilder {
         self
     }
 
+    /// Set the top-level `ChapterGroup` of the metadata, replacing any existing chapters.
+    pub fn set_chapters(&mut self, chapters: ChapterGroup) -> &mut Self {
+        self.metadata.chapters = Some(chapters);
+        self
+    }
+
     /// Yield the constructed `Metadata` revision.
     pub fn metadata(self) -> MetadataRevision {
         self.metadata
@@ -8,8 +8,12 @@
 //! The `probe` module provides methods and traits to support auto-detection of media formats from
 //! arbitrary media streams.
 
+use crate::codecs::CodecType;
 use crate::errors::{unsupported_error, Result};
 use crate::formats::{FormatOptions, FormatReader};
+use std::cmp::Reverse;
+use std::io::{Seek, SeekFrom};
+
 use crate::io::{MediaSourceStream, ReadBytes, SeekBuffered};
 use crate::meta::{Metadata, MetadataLog, MetadataOptions, MetadataReader};
 
@@ -121,7 +125,9 @@ pub trait QueryDescriptor {
     fn query() -> &'static [Descriptor];
 
     /// Using the provided context buffer, score calculate and returns a value between 0 and 255
-    /// indicating the confidence of the reader in decoding or parsing the source stream.
+    /// indicating the confidence of the reader in decoding or parsing the source stream. The
+    /// context buffer starts with the matched marker. A score of 0 indicates that the reader does
+    /// not support the source stream.
     fn score(context: &[u8]) -> u8;
 }
 
@@ -136,12 +142,13 @@ pub trait QueryDescriptor {
 pub struct Hint {
     extension: Option<String>,
     mime_type: Option<String>,
+    codec: Option<CodecType>,
 }
 
 impl Hint {
     /// Instantiate an empty `Hint`.
     pub fn new() -> Self {
-        Hint { extension: None, mime_type: None }
+        Hint { extension: None, mime_type: None, codec: None }
     }
 
     /// Add a file extension `Hint`.
@@ -150,11 +157,58 @@ impl Hint {
         self
     }
 
-    /// Add a MIME/Media-type `Hint`.
-    pub fn mime_type(&mut self, mime_type: &str) -> &mut Self {
+    /// Add a MIME/Media-type `Hint`. For example, the value of a HTTP `Content-Type` header.
+    pub fn with_mime(&mut self, mime_type: &str) -> &mut Self {
         self.mime_type = Some(mime_type.to_owned());
         self
     }
+
+    /// Add a MIME/Media-type `Hint`. Equivalent to [`Hint::with_mime`].
+    pub fn mime_type(&mut self, mime_type: &str) -> &mut Self {
+        self.with_mime(mime_type)
+    }
+
+    /// Add a codec `Hint`.
+    ///
+    /// Format descriptors do not list the codecs a format may contain, therefore the codec hint is
+    /// not used to select a format. However, it is retained so that it may be passed along with
+    /// the other hints.
+    pub fn with_codec(&mut self, codec: CodecType) -> &mut Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// Get the file extension `Hint`, if provided.
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
+    /// Get the MIME/Media-type `Hint`, if provided.
+    pub fn mime(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+
+    /// Get the codec `Hint`, if provided.
+    pub fn codec(&self) -> Option<CodecType> {
+        self.codec
+    }
+
+    /// Returns `true` if the MIME/Media-type or file extension `Hint` is supported by the format
+    /// or metadata described by `descriptor`. Comparisons are case-insensitive, and any
+    /// parameters following the MIME/Media-type essence (e.g., `; codecs=1`) are ignored.
+    fn is_match(&self, descriptor: &Descriptor) -> bool {
+        let mime_match = self.mime_type.as_ref().map_or(false, |mime_type| {
+            let essence = mime_type.split(';').next().unwrap_or_default().trim();
+            descriptor.mime_types.iter().any(|mime| mime.trim().eq_ignore_ascii_case(essence))
+        });
+
+        let ext_match = self.extension.as_ref().map_or(false, |extension| {
+            let extension = extension.trim_start_matches('.');
+            descriptor.extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension))
+        });
+
+        mime_match || ext_match
+    }
 }
 
 /// Metadata that came from the `metadata` field of [`ProbeResult`].
@@ -198,7 +252,7 @@ pub struct Probe {
 }
 
 impl Probe {
-    const PROBE_SEARCH_LIMIT: u64 = 1 * 1024 * 1024;
+    const PROBE_REWIND_LIMIT: usize = 2 * 1024 * 1024;
 
     /// Register all `Descriptor`s supported by the parameterized type.
     pub fn register_all<Q: QueryDescriptor>(&mut self) {
@@ -224,29 +278,56 @@ impl Probe {
         self.registered.push(*descriptor);
     }
 
-    /// Searches the provided `MediaSourceStream` for metadata or a container format.
+    /// Searches the provided `MediaSourceStream` for metadata or a container format. The search is
+    /// limited to the default `FormatOptions::probe_search_limit`.
     pub fn next(&self, mss: &mut MediaSourceStream) -> Result<Instantiate> {
+        // The candidates are sorted by score, so the first candidate is the best.
+        let search_limit = FormatOptions::default().probe_search_limit;
+
+        let candidates = self.scan(&Hint::new(), mss, search_limit)?;
+        Ok(candidates[0].0.inst)
+    }
+
+    /// Searches the provided `MediaSourceStream` for the first position where one or more
+    /// registered markers are found, and at least one format scores the stream. Returns the
+    /// descriptors and scores of all formats with a non-zero score. Formats matching the `Hint` are
+    /// ordered first, then formats are sorted from the highest to the lowest score. Formats with
+    /// the same score are ordered by registration order.
+    ///
+    /// At most `search_limit` bytes are scanned. On success, the stream is positioned at the start
+    /// of the marker.
+    fn scan(
+        &self,
+        hint: &Hint,
+        mss: &mut MediaSourceStream,
+        search_limit: u64,
+    ) -> Result<Vec<(Descriptor, u8)>> {
         let mut win = 0u16;
 
         let init_pos = mss.pos();
         let mut count = 0;
 
         // Scan the stream byte-by-byte. Shifting each byte through a 2-byte window.
-        while let Ok(byte) = mss.read_byte() {
+        loop {
+            if count >= search_limit {
+                // Could not find any marker within the probe limit.
+                error!("reached probe limit of {} bytes.", search_limit);
+                return unsupported_error("core (probe): no format marker found within the limit");
+            }
+
+            let byte = match mss.read_byte() {
+                Ok(byte) => byte,
+                Err(_) => break,
+            };
+
             win = (win << 8) | u16::from(byte);
 
             count += 1;
 
-            if count > Probe::PROBE_SEARCH_LIMIT {
-                break;
-            }
-
             if count % 4096 == 0 {
                 debug!(
                     "searching for format marker... {}+{} / {} bytes.",
-                    init_pos,
-                    count,
-                    Probe::PROBE_SEARCH_LIMIT
+                    init_pos, count, search_limit
                 );
             }
 
@@ -264,54 +345,146 @@ impl Probe {
                     context, init_pos, count,
                 );
 
-                // Search for registered markers in the 16-byte window.
+                // Search for registered markers in the 16-byte window. Multiple formats may share a
+                // marker, so score the context with every format that has a matching marker. A
+                // score of 0 indicates that the format does not support the stream.
+                let mut candidates = Vec::new();
+
                 for registered in &self.registered {
-                    for marker in registered.markers {
+                    let marker = registered.markers.iter().find(|marker| {
                         let len = marker.len();
+                        context[0..len] == ***marker
+                    });
+
+                    if let Some(marker) = marker {
+                        let score = (registered.score)(&context);
+
+                        debug!(
+                            "found the format marker {:x?} @ {}+{} bytes (score={}).",
+                            &context[0..marker.len()],
+                            init_pos,
+                            count,
+                            score,
+                        );
+
+                        if score > 0 {
+                            candidates.push((*registered, score));
+                        }
+                    }
+                }
 
-                        // If a match is found, return the instantiate.
-                        if context[0..len] == **marker {
-                            // Re-align the stream to the start of the marker.
-                            mss.seek_buffered_rev(16);
-
-                            // TODO: Implement scoring.
+                // If a match is found, return the candidates.
+                if !candidates.is_empty() {
+                    // Re-align the stream to the start of the marker.
+                    mss.seek_buffered_rev(16);
 
-                            debug!(
-                                "found the format marker {:x?} @ {}+{} bytes.",
-                                &context[0..len],
-                                init_pos,
-                                count,
-                            );
+                    // Prefer formats matching the hint, then sort from the highest to the lowest
+                    // score. The sort is stable, so formats with the same score remain in
+                    // registration order.
+                    candidates.sort_by_key(|(desc, score)| {
+                        (Reverse(hint.is_match(desc)), Reverse(*score))
+                    });
 
-                            return Ok(registered.inst);
-                        }
-                    }
+                    return Ok(candidates);
                 }
 
                 // If no registered markers were matched, then the bloom filter returned a false
-                // positive. Re-align the stream to the end of the 2-byte window and continue the
-                // search.
+                // positive, or no format supports the stream. Re-align the stream to the end of the
+                // 2-byte window and continue the search.
                 mss.seek_buffered_rev(16 - 2);
             }
         }
 
-        if count < Probe::PROBE_SEARCH_LIMIT {
-            error!("probe reach EOF at {} bytes.", count);
-        }
-        else {
-            // Could not find any marker within the probe limit.
-            error!("reached probe limit of {} bytes.", Probe::PROBE_SEARCH_LIMIT);
-        }
+        error!("probe reach EOF at {} bytes.", count);
 
         unsupported_error("core (probe): no suitable format reader found")
     }
 
+    /// Searches the provided `MediaSourceStream` for a container format, and returns the
+    /// descriptors of all container formats that support the stream along with their scores. The
+    /// candidates matching the `Hint` are ordered first, then candidates are sorted from the
+    /// highest to the lowest score. The first candidate is the format that [`Probe::format`] would
+    /// instantiate.
+    ///
+    /// No format reader is instantiated. Any metadata preceding the container format is read, but
+    /// discarded. Afterwards, the stream is returned to its original position, either by rewinding
+    /// a rewind window, or by seeking if more data was read than the window retains. To instantiate
+    /// a specific candidate, register its descriptor with a new `Probe` and call
+    /// [`Probe::format`]. The search for each format marker is limited to the default
+    /// `FormatOptions::probe_search_limit`.
+    pub fn probe_candidates(
+        &self,
+        hint: &Hint,
+        mss: &mut MediaSourceStream,
+        metadata_opts: &MetadataOptions,
+    ) -> Result<Vec<(Descriptor, u8)>> {
+        let init_pos = mss.pos();
+
+        // The format options are not available, so use the default search limit.
+        let search_limit = FormatOptions::default().probe_search_limit;
+
+        mss.start_rewind_window(Probe::PROBE_REWIND_LIMIT);
+
+        let result = self.find_format_candidates(hint, mss, metadata_opts, search_limit);
+
+        // Return the stream to its original position.
+        let rewound = match mss.rewind_to_window_start() {
+            Ok(_) => Ok(()),
+            Err(_) => mss.seek(SeekFrom::Start(init_pos)).map(|_| ()),
+        };
+
+        mss.end_rewind_window();
+
+        let candidates = result?;
+        rewound?;
+
+        Ok(candidates)
+    }
+
+    /// Searches for the candidate container formats, reading and discarding any metadata.
+    fn find_format_candidates(
+        &self,
+        hint: &Hint,
+        mss: &mut MediaSourceStream,
+        metadata_opts: &MetadataOptions,
+        search_limit: u64,
+    ) -> Result<Vec<(Descriptor, u8)>> {
+        loop {
+            let candidates = self.scan(hint, mss, search_limit)?;
+
+            match candidates[0].0.inst {
+                // If the best candidate is a container format, return all container format
+                // candidates.
+                Instantiate::Format(_) => {
+                    let formats = candidates
+                        .into_iter()
+                        .filter(|(desc, _)| matches!(desc.inst, Instantiate::Format(_)))
+                        .collect();
+
+                    return Ok(formats);
+                }
+                // If the best candidate is metadata, read and discard the metadata, then continue
+                // the search after it.
+                Instantiate::Metadata(meta) => {
+                    let mut reader = meta(metadata_opts);
+                    reader.read_all(mss)?;
+                }
+            }
+        }
+    }
+
     /// Searches the provided `MediaSourceStream` for a container format. Any metadata that is read
     /// during the search will be queued and attached to the `FormatReader` instance once a
     /// container format is found.
+    ///
+    /// If multiple container formats support the stream, the format matching the `Hint` is
+    /// preferred.
+    ///
+    /// The search for each format marker is limited to `FormatOptions::probe_search_limit` bytes.
+    /// If a marker is not found within the limit, an unsupported error is returned.
     pub fn format(
         &self,
-        _hint: &Hint,
+        hint: &Hint,
         mut mss: MediaSourceStream,
         format_opts: &FormatOptions,
         metadata_opts: &MetadataOptions,
@@ -320,7 +493,9 @@ impl Probe {
 
         // Loop over all elements in the stream until a container format is found.
         loop {
-            match self.next(&mut mss)? {
+            let candidates = self.scan(hint, &mut mss, format_opts.probe_search_limit)?;
+
+            match candidates[0].0.inst {
                 // If a container format is found, return an instance to it's reader.
                 Instantiate::Format(fmt) => {
                     let format = fmt(mss, format_opts)?;
@@ -377,3 +552,192 @@ macro_rules! support_metadata {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{Descriptor, Hint, Instantiate, Probe};
+    use crate::errors::{unsupported_error, Error, Result};
+    use crate::formats::{FormatOptions, FormatReader};
+    use crate::io::{MediaSourceStream, ReadBytes};
+    use crate::meta::{MetadataOptions, MetadataReader, MetadataRevision};
+
+    fn no_format(_: MediaSourceStream, _: &FormatOptions) -> Result<Box<dyn FormatReader>> {
+        unsupported_error("no format")
+    }
+
+    /// A metadata reader for a "META" marker followed by a 1 byte length and that many bytes of
+    /// metadata.
+    struct SkipMetadata;
+
+    impl MetadataReader for SkipMetadata {
+        fn new(_: &MetadataOptions) -> Self {
+            SkipMetadata
+        }
+
+        fn read_all(&mut self, reader: &mut MediaSourceStream) -> Result<MetadataRevision> {
+            reader.ignore_bytes(4)?;
+            let len = reader.read_byte()?;
+            reader.ignore_bytes(u64::from(len))?;
+            Ok(Default::default())
+        }
+    }
+
+    fn descriptor(
+        name: &'static str,
+        markers: &'static [&'static [u8]],
+        score: fn(&[u8]) -> u8,
+    ) -> Descriptor {
+        Descriptor {
+            short_name: name,
+            long_name: name,
+            extensions: &[],
+            mime_types: &[],
+            markers,
+            score,
+            inst: Instantiate::Format(no_format),
+        }
+    }
+
+    /// Builds a probe with three formats sharing a marker. Format "b" scores the stream highest,
+    /// format "a" and "c" score the stream equally, and format "d" does not support the stream.
+    fn build_probe() -> Probe {
+        let mut probe = Probe::default();
+
+        // Format "b" only supports the stream if the marker is followed by 0xff.
+        let score_b = |ctx: &[u8]| if ctx[4] == 0xff { 200 } else { 0 };
+
+        probe.register(&descriptor("a", &[b"MARK"], |_| 100));
+        probe.register(&descriptor("b", &[b"MA", b"MARK"], score_b));
+        probe.register(&Descriptor {
+            extensions: &["c"],
+            mime_types: &["audio/x-c"],
+            ..descriptor("c", &[b"MARK"], |_| 100)
+        });
+        probe.register(&descriptor("d", &[b"MARK"], |_| 0));
+        probe.register(&Descriptor {
+            short_name: "meta",
+            long_name: "meta",
+            extensions: &[],
+            mime_types: &[],
+            markers: &[b"META"],
+            score: |_| 255,
+            inst: Instantiate::Metadata(|opts| Box::new(SkipMetadata::new(opts))),
+        });
+
+        probe
+    }
+
+    fn probe_candidates(
+        probe: &Probe,
+        hint: &Hint,
+        buf: Vec<u8>,
+    ) -> (Vec<(&'static str, u8)>, Vec<u8>) {
+        let mut mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+
+        let candidates = probe
+            .probe_candidates(hint, &mut mss, &Default::default())
+            .unwrap()
+            .iter()
+            .map(|(desc, score)| (desc.short_name, *score))
+            .collect();
+
+        // The stream must be returned to the start.
+        let mut rest = vec![0; 8];
+        mss.read_buf_exact(&mut rest).unwrap();
+
+        (candidates, rest)
+    }
+
+    #[test]
+    fn verify_probe_candidates() {
+        let probe = build_probe();
+
+        let mut buf = b"junkMARK\xff".to_vec();
+        buf.resize(32, 0);
+
+        let (candidates, rest) = probe_candidates(&probe, &Hint::new(), buf);
+        assert_eq!(candidates, &[("b", 200), ("a", 100), ("c", 100)]);
+        assert_eq!(&rest, b"junkMARK");
+
+        // When probing, the stream is positioned at the marker of the best candidate.
+        let mut buf = b"junkMARK\xff".to_vec();
+        buf.resize(32, 0);
+
+        let mut mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        assert!(matches!(probe.next(&mut mss).unwrap(), Instantiate::Format(_)));
+        assert_eq!(mss.pos(), 4);
+    }
+
+    #[test]
+    fn verify_probe_candidates_after_metadata() {
+        let probe = build_probe();
+
+        // Metadata precedes the marker, and contains a marker that must not be matched.
+        let mut buf = b"META\x04MARK".to_vec();
+        buf.extend_from_slice(b"MARK\x00");
+        buf.resize(32, 0);
+
+        let (candidates, rest) = probe_candidates(&probe, &Hint::new(), buf);
+        assert_eq!(candidates, &[("a", 100), ("c", 100)]);
+        assert_eq!(&rest, b"META\x04MAR");
+    }
+
+    #[test]
+    fn verify_probe_search_limit() {
+        let probe = build_probe();
+
+        let mut buf = vec![0; 10_000];
+        buf.extend_from_slice(b"MARK\xff");
+        buf.resize(100_000, 0);
+
+        // Scans the buffer, and returns the result and the position of the stream afterwards.
+        let scan = |search_limit| {
+            let mut mss =
+                MediaSourceStream::new(Box::new(Cursor::new(buf.clone())), Default::default());
+
+            let result = probe.scan(&Hint::new(), &mut mss, search_limit);
+            (result, mss.pos())
+        };
+
+        // The marker is found once the first 2 bytes of the marker have been scanned.
+        let (result, pos) = scan(10_002);
+        assert!(result.is_ok());
+        assert_eq!(pos, 10_000);
+
+        // Otherwise, the search stops at the limit instead of continuing to the end of the stream.
+        let (result, pos) = scan(10_001);
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+        assert_eq!(pos, 10_001);
+    }
+
+    #[test]
+    fn verify_probe_hint() {
+        let probe = build_probe();
+
+        let mut buf = b"junkMARK\xff".to_vec();
+        buf.resize(32, 0);
+
+        // A MIME/Media-type hint is preferred over the score. Case and parameters are ignored.
+        let mut hint = Hint::new();
+        hint.with_mime("Audio/X-C; codecs=1");
+
+        let (candidates, _) = probe_candidates(&probe, &hint, buf.clone());
+        assert_eq!(candidates, &[("c", 100), ("b", 200), ("a", 100)]);
+
+        // A file extension hint is also preferred over the score.
+        let mut hint = Hint::new();
+        hint.with_extension(".C");
+
+        let (candidates, _) = probe_candidates(&probe, &hint, buf.clone());
+        assert_eq!(candidates, &[("c", 100), ("b", 200), ("a", 100)]);
+
+        // A hint that does not match any candidate does not change the order.
+        let mut hint = Hint::new();
+        hint.with_mime("audio/x-unknown");
+
+        let (candidates, _) = probe_candidates(&probe, &hint, buf);
+        assert_eq!(candidates, &[("b", 200), ("a", 100), ("c", 100)]);
+    }
+}
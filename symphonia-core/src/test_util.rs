@@ -0,0 +1,132 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Mock implementations of the core traits used by the tests.
+
+use std::io::Cursor;
+use std::mem;
+
+use crate::codecs::CodecParameters;
+use crate::errors::{end_of_stream_error, seek_error, Result, SeekErrorKind};
+use crate::formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track};
+use crate::io::MediaSourceStream;
+use crate::meta::{Metadata, MetadataLog};
+
+/// A reader that returns a scripted sequence of packets and errors, then the end of the stream.
+pub struct MockReader {
+    source: MediaSourceStream,
+    metadata: MetadataLog,
+    tracks: Vec<Track>,
+    script: Vec<Result<Packet>>,
+    next: usize,
+    seek_preroll: u64,
+}
+
+impl MockReader {
+    /// Instantiate a reader of `n_tracks` tracks that returns each item of `script` in order.
+    pub fn new(n_tracks: u32, script: Vec<Result<Packet>>) -> Self {
+        MockReader {
+            source: MediaSourceStream::new(Box::new(Cursor::new(Vec::new())), Default::default()),
+            metadata: Default::default(),
+            tracks: (0..n_tracks).map(|id| Track::new(id, CodecParameters::new())).collect(),
+            script,
+            next: 0,
+            seek_preroll: 0,
+        }
+    }
+
+    /// Instantiate a reader of `n_tracks` tracks, with `n_packets` packets interleaved between the
+    /// tracks in turn. Each track has a packet with a duration of `dur` at every multiple of
+    /// `dur`. The data of each packet is the index of the packet, as a little-endian `u64`.
+    pub fn interleaved(n_tracks: u32, n_packets: u64, dur: u64) -> Self {
+        let n_tracks_u64 = u64::from(n_tracks);
+
+        let script = (0..n_packets)
+            .map(|n| {
+                let track_id = (n % n_tracks_u64) as u32;
+                let ts = (n / n_tracks_u64) * dur;
+                Ok(Packet::new_from_slice(track_id, ts, dur, &n.to_le_bytes()))
+            })
+            .collect();
+
+        MockReader::new(n_tracks, script)
+    }
+
+    /// Seek to the packet at or before `preroll` timestamp units prior to the required timestamp,
+    /// instead of the packet containing the required timestamp.
+    pub fn with_seek_preroll(&mut self, preroll: u64) -> &mut Self {
+        self.seek_preroll = preroll;
+        self
+    }
+}
+
+impl FormatReader for MockReader {
+    fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        Ok(MockReader::new(0, Vec::new()))
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &[]
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    /// Seeks to the last packet of the track at or before the required timestamp, less the seek
+    /// preroll. Reading resumes from the first packet of any track at that timestamp.
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let (required_ts, track_id) = match to {
+            SeekTo::TimeStamp { ts, track_id } => (ts, track_id),
+            _ => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        let target_ts = required_ts.saturating_sub(self.seek_preroll);
+
+        let actual_ts = match self
+            .script
+            .iter()
+            .flat_map(|item| item.as_ref().ok())
+            .filter(|packet| packet.track_id() == track_id && packet.ts() <= target_ts)
+            .last()
+        {
+            Some(packet) => packet.ts(),
+            None => return seek_error(SeekErrorKind::OutOfRange),
+        };
+
+        self.next = self
+            .script
+            .iter()
+            .position(|item| matches!(item, Ok(packet) if packet.ts() >= actual_ts))
+            .unwrap();
+
+        Ok(SeekedTo { track_id, required_ts, actual_ts })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let item = match self.script.get_mut(self.next) {
+            Some(item) => item,
+            None => return end_of_stream_error(),
+        };
+
+        self.next += 1;
+
+        match item {
+            Ok(packet) => Ok(packet.clone()),
+            // Errors cannot be cloned, therefore a scripted error is only returned once.
+            Err(_) => mem::replace(item, end_of_stream_error()),
+        }
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.source
+    }
+}
@@ -196,35 +196,22 @@ impl TimeBase {
         assert!(self.numer > 0 && self.denom > 0, "TimeBase numerator or denominator are 0.");
         assert!(time.frac >= 0.0 && time.frac < 1.0, "Invalid range for Time fractional part.");
 
-        // The dividing factor.
-        let k = 1.0 / f64::from(self.numer);
-
         // Multiplying seconds by the denominator requires up-to 96-bits (32-bit timebase
-        // denominator * 64-bit timestamp).
+        // denominator * 64-bit seconds). Divide by the numerator using integer arithmetic to
+        // calculate the integer portion of the timestamp exactly.
         let product = u128::from(time.seconds) * u128::from(self.denom);
 
-        // Like calc_time, a 64-bit floating-point value only has 52-bits of integer precision.
-        // If the product requires more than 52-bits, split the product into upper and lower parts
-        // and multiply by k separately, before adding back together.
-        let a = if product > (1 << 52) {
-            // Split the 96-bit product into 48-bit halves.
-            let u = ((product & !0xffff_ffff_ffff) >> 48) as u64;
-            let l = ((product & 0xffff_ffff_ffff) >> 0) as u64;
+        let a = product / u128::from(self.numer);
 
-            let uk = (u as f64) * k;
-            let ul = (l as f64) * k;
+        // The remainder will never equal or exceed the numerator, so it must fit within a u32.
+        let rem = (product - (a * u128::from(self.numer))) as u32;
 
-            // Add the upper and lower halves.
-            ((uk as u64) << 48).wrapping_add(ul as u64)
-        }
-        else {
-            ((product as f64) * k) as u64
-        };
+        // The remainder, and the fractional part of the time multiplied by the denominator, are
+        // both less than 2^32. Therefore, 64-bit floating point arithmetic provides enough accuracy
+        // to calculate the remaining portion of the timestamp.
+        let b = (f64::from(rem) + f64::from(self.denom) * time.frac) / f64::from(self.numer);
 
-        // The fractional portion can be calculate directly using floating point arithemtic.
-        let b = (k * f64::from(self.denom) * time.frac) as u64;
-
-        a.wrapping_add(b)
+        (a as u64).wrapping_add(b as u64)
     }
 }
 
@@ -274,6 +261,33 @@ mod tests {
         assert_eq!(tb1.calc_timestamp(Time::new(57_646_075_230_342_348, 0.796875)), u64::MAX);
     }
 
+    #[test]
+    fn verify_timebase_high_sample_rates() {
+        // 10 hours at 768 kHz, 24 hours at 22.5792 MHz (DSD512), and 1000 hours at the maximum
+        // rate representable by a timebase.
+        for &(rate, seconds) in &[(768_000, 36_000), (22_579_200, 86_400), (u32::MAX, 3_600_000)] {
+            let tb = TimeBase::new(1, rate);
+            let ts = u64::from(rate) * seconds;
+
+            assert_eq!(tb.calc_time(ts), Time::new(seconds, 0.0));
+            assert_eq!(tb.calc_timestamp(Time::new(seconds, 0.0)), ts);
+
+            // Half a second later.
+            let half = u64::from(rate / 2);
+            let frac = f64::from(rate / 2) / f64::from(rate);
+
+            assert_eq!(tb.calc_time(ts + half), Time::new(seconds, frac));
+            assert_eq!(tb.calc_timestamp(Time::new(seconds, frac)), ts + half);
+        }
+
+        // A non-unit numerator where the product of the seconds and denominator exceeds 52 bits.
+        let tb = TimeBase::new(3, 4_000_000_000);
+
+        assert_eq!(tb.calc_timestamp(Time::new(10_000_000, 0.0)), 13_333_333_333_333_333);
+        assert_eq!(tb.calc_timestamp(Time::new(10_000_000, 0.5)), 13_333_334_000_000_000);
+        assert_eq!(tb.calc_time(13_333_333_333_333_333), Time::new(9_999_999, 0.99999999975));
+    }
+
     #[test]
     fn verify_duration_to_time() {
         // Verify accuracy of Duration -> Time
@@ -8,7 +8,7 @@
 use log::{debug, error, info, warn};
 use std::{convert::TryFrom, fmt, mem::size_of, str};
 use symphonia_core::{
-    audio::{Channels, Layout},
+    audio::Channels,
     codecs::*,
     errors::{decode_error, unsupported_error, Error, Result},
     io::{MediaSourceStream, ReadBytes},
@@ -297,71 +297,45 @@ impl ChannelLayout {
         Ok(Self { channel_layout, channel_bitmap, channel_descriptions })
     }
 
+    /// Translates the channel layout into a Symphonia channels bitmask.
+    ///
+    /// Returns `None` if the layout contains channels that have no Symphonia equivalent.
     pub fn channels(&self) -> Option<Channels> {
-        let channels = match self.channel_layout {
+        match self.channel_layout {
             // Use channel descriptions
-            0 => {
-                let mut channels: u32 = 0;
-                for channel in self.channel_descriptions.iter() {
-                    match channel.channel_label {
-                        1 => channels |= Channels::FRONT_LEFT.bits(),
-                        2 => channels |= Channels::FRONT_RIGHT.bits(),
-                        3 => channels |= Channels::FRONT_CENTRE.bits(),
-                        4 => channels |= Channels::LFE1.bits(),
-                        5 => channels |= Channels::REAR_LEFT.bits(),
-                        6 => channels |= Channels::REAR_RIGHT.bits(),
-                        7 => channels |= Channels::FRONT_LEFT_CENTRE.bits(),
-                        8 => channels |= Channels::FRONT_RIGHT_CENTRE.bits(),
-                        9 => channels |= Channels::REAR_CENTRE.bits(),
-                        10 => channels |= Channels::SIDE_LEFT.bits(),
-                        11 => channels |= Channels::SIDE_RIGHT.bits(),
-                        12 => channels |= Channels::TOP_CENTRE.bits(),
-                        13 => channels |= Channels::TOP_FRONT_LEFT.bits(),
-                        14 => channels |= Channels::TOP_FRONT_CENTRE.bits(),
-                        15 => channels |= Channels::TOP_FRONT_RIGHT.bits(),
-                        16 => channels |= Channels::TOP_REAR_LEFT.bits(),
-                        17 => channels |= Channels::TOP_REAR_CENTRE.bits(),
-                        18 => channels |= Channels::TOP_REAR_RIGHT.bits(),
-                        unsupported => {
-                            info!("unsupported channel label: {}", unsupported);
-                            return None;
-                        }
-                    }
-                }
-                return Channels::from_bits(channels);
-            }
-            // Use the channel bitmap
-            LAYOUT_TAG_USE_CHANNEL_BITMAP => return Channels::from_bits(self.channel_bitmap),
-            // Layout tags which have channel roles that match the standard channel layout
-            LAYOUT_TAG_MONO => Layout::Mono.into_channels(),
-            LAYOUT_TAG_STEREO | LAYOUT_TAG_STEREO_HEADPHONES => Layout::Stereo.into_channels(),
-            LAYOUT_TAG_MPEG_3_0_A => {
-                Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE
-            }
-            LAYOUT_TAG_MPEG_5_1_A => Layout::FivePointOne.into_channels(),
-            LAYOUT_TAG_MPEG_7_1_A => {
-                Channels::FRONT_LEFT
-                    | Channels::FRONT_RIGHT
-                    | Channels::FRONT_CENTRE
-                    | Channels::LFE1
-                    | Channels::REAR_LEFT
-                    | Channels::REAR_RIGHT
-                    | Channels::FRONT_LEFT_CENTRE
-                    | Channels::FRONT_RIGHT_CENTRE
-            }
-            LAYOUT_TAG_DVD_10 => {
-                Channels::FRONT_LEFT
-                    | Channels::FRONT_RIGHT
-                    | Channels::FRONT_CENTRE
-                    | Channels::LFE1
+            LAYOUT_TAG_USE_CHANNEL_DESCRIPTIONS => {
+                let labels: Vec<u32> =
+                    self.channel_descriptions.iter().map(|desc| desc.channel_label).collect();
+                channel_labels_to_channels(&labels)
             }
-            unsupported => {
-                debug!("unsupported channel layout: {}", unsupported);
-                return None;
+            // Use the channel bitmap. The CAF channel bits for the first 18 channels are
+            // identical to Symphonia's, but the remaining bits have no direct equivalent.
+            LAYOUT_TAG_USE_CHANNEL_BITMAP => {
+                if self.channel_bitmap & !CHANNEL_BITMAP_MASK != 0 {
+                    info!("unsupported channel bitmap: {:#x}", self.channel_bitmap);
+                    return None;
+                }
+                Channels::from_bits(self.channel_bitmap)
             }
-        };
+            // Use a predefined layout
+            tag => {
+                let labels = match layout_tag_channel_labels(tag) {
+                    Some(labels) => labels,
+                    None => {
+                        debug!("unsupported channel layout: {:#x}", tag);
+                        return None;
+                    }
+                };
 
-        Some(channels)
+                // The lower 16 bits of a layout tag is the number of channels in the layout.
+                if labels.len() != (tag & 0xffff) as usize {
+                    debug!("unexpected channel count for layout: {:#x}", tag);
+                    return None;
+                }
+
+                channel_labels_to_channels(labels)
+            }
+        }
     }
 }
 
@@ -384,15 +358,238 @@ impl ChannelDescription {
     }
 }
 
+const LAYOUT_TAG_USE_CHANNEL_DESCRIPTIONS: u32 = 0;
 const LAYOUT_TAG_USE_CHANNEL_BITMAP: u32 = 1 << 16;
-// Layout tags from the CAF spec that match the first N channels of a standard layout
-const LAYOUT_TAG_MONO: u32 = (100 << 16) | 1;
-const LAYOUT_TAG_STEREO: u32 = (101 << 16) | 2;
-const LAYOUT_TAG_STEREO_HEADPHONES: u32 = (102 << 16) | 2;
-const LAYOUT_TAG_MPEG_3_0_A: u32 = (113 << 16) | 3; // L R C
-const LAYOUT_TAG_MPEG_5_1_A: u32 = (121 << 16) | 6; // L R C LFE Ls Rs
-const LAYOUT_TAG_MPEG_7_1_A: u32 = (126 << 16) | 8; // L R C LFE Ls Rs Lc Rc
-const LAYOUT_TAG_DVD_10: u32 = (136 << 16) | 4; // L R C LFE
+
+// The channel bits that have an identical meaning in CAF and Symphonia.
+const CHANNEL_BITMAP_MASK: u32 = 0x3_ffff;
+
+// Channel labels from the CAF spec and CoreAudioBaseTypes.h.
+const LABEL_LEFT: u32 = 1;
+const LABEL_RIGHT: u32 = 2;
+const LABEL_CENTER: u32 = 3;
+const LABEL_LFE_SCREEN: u32 = 4;
+const LABEL_LEFT_SURROUND: u32 = 5;
+const LABEL_RIGHT_SURROUND: u32 = 6;
+const LABEL_LEFT_CENTER: u32 = 7;
+const LABEL_RIGHT_CENTER: u32 = 8;
+const LABEL_CENTER_SURROUND: u32 = 9;
+const LABEL_LEFT_SURROUND_DIRECT: u32 = 10;
+const LABEL_RIGHT_SURROUND_DIRECT: u32 = 11;
+const LABEL_TOP_CENTER_SURROUND: u32 = 12;
+const LABEL_VERTICAL_HEIGHT_LEFT: u32 = 13;
+const LABEL_VERTICAL_HEIGHT_CENTER: u32 = 14;
+const LABEL_VERTICAL_HEIGHT_RIGHT: u32 = 15;
+const LABEL_TOP_BACK_LEFT: u32 = 16;
+const LABEL_TOP_BACK_CENTER: u32 = 17;
+const LABEL_TOP_BACK_RIGHT: u32 = 18;
+const LABEL_REAR_SURROUND_LEFT: u32 = 33;
+const LABEL_REAR_SURROUND_RIGHT: u32 = 34;
+const LABEL_LEFT_WIDE: u32 = 35;
+const LABEL_RIGHT_WIDE: u32 = 36;
+const LABEL_LFE2: u32 = 37;
+const LABEL_HEADPHONES_LEFT: u32 = 301;
+const LABEL_HEADPHONES_RIGHT: u32 = 302;
+
+/// Translates a list of CAF channel labels into a Symphonia channels bitmask.
+///
+/// Returns `None` if a label is unsupported, or if two labels map to the same channel.
+fn channel_labels_to_channels(labels: &[u32]) -> Option<Channels> {
+    // When a layout has rear surround channels, the surround channels are at the sides.
+    let has_rear_surround = labels
+        .iter()
+        .any(|&label| label == LABEL_REAR_SURROUND_LEFT || label == LABEL_REAR_SURROUND_RIGHT);
+
+    let mut channels = Channels::empty();
+
+    for &label in labels {
+        let channel = match label {
+            LABEL_LEFT | LABEL_HEADPHONES_LEFT => Channels::FRONT_LEFT,
+            LABEL_RIGHT | LABEL_HEADPHONES_RIGHT => Channels::FRONT_RIGHT,
+            LABEL_CENTER => Channels::FRONT_CENTRE,
+            LABEL_LFE_SCREEN => Channels::LFE1,
+            LABEL_LEFT_SURROUND if has_rear_surround => Channels::SIDE_LEFT,
+            LABEL_RIGHT_SURROUND if has_rear_surround => Channels::SIDE_RIGHT,
+            LABEL_LEFT_SURROUND => Channels::REAR_LEFT,
+            LABEL_RIGHT_SURROUND => Channels::REAR_RIGHT,
+            LABEL_LEFT_CENTER => Channels::FRONT_LEFT_CENTRE,
+            LABEL_RIGHT_CENTER => Channels::FRONT_RIGHT_CENTRE,
+            LABEL_CENTER_SURROUND => Channels::REAR_CENTRE,
+            LABEL_LEFT_SURROUND_DIRECT => Channels::SIDE_LEFT,
+            LABEL_RIGHT_SURROUND_DIRECT => Channels::SIDE_RIGHT,
+            LABEL_TOP_CENTER_SURROUND => Channels::TOP_CENTRE,
+            LABEL_VERTICAL_HEIGHT_LEFT => Channels::TOP_FRONT_LEFT,
+            LABEL_VERTICAL_HEIGHT_CENTER => Channels::TOP_FRONT_CENTRE,
+            LABEL_VERTICAL_HEIGHT_RIGHT => Channels::TOP_FRONT_RIGHT,
+            LABEL_TOP_BACK_LEFT => Channels::TOP_REAR_LEFT,
+            LABEL_TOP_BACK_CENTER => Channels::TOP_REAR_CENTRE,
+            LABEL_TOP_BACK_RIGHT => Channels::TOP_REAR_RIGHT,
+            LABEL_REAR_SURROUND_LEFT => Channels::REAR_LEFT,
+            LABEL_REAR_SURROUND_RIGHT => Channels::REAR_RIGHT,
+            LABEL_LEFT_WIDE => Channels::FRONT_LEFT_WIDE,
+            LABEL_RIGHT_WIDE => Channels::FRONT_RIGHT_WIDE,
+            LABEL_LFE2 => Channels::LFE2,
+            unsupported => {
+                info!("unsupported channel label: {}", unsupported);
+                return None;
+            }
+        };
+
+        if channels.contains(channel) {
+            info!("duplicate channel label: {}", label);
+            return None;
+        }
+
+        channels |= channel;
+    }
+
+    if channels.is_empty() {
+        None
+    }
+    else {
+        Some(channels)
+    }
+}
+
+/// Gets the channel labels, in coded order, of a predefined CAF channel layout tag.
+///
+/// The ALAC decoder uses the same layout tags for the channel layout in its magic cookie.
+fn layout_tag_channel_labels(tag: u32) -> Option<&'static [u32]> {
+    const L: u32 = LABEL_LEFT;
+    const R: u32 = LABEL_RIGHT;
+    const C: u32 = LABEL_CENTER;
+    const LFE: u32 = LABEL_LFE_SCREEN;
+    const LS: u32 = LABEL_LEFT_SURROUND;
+    const RS: u32 = LABEL_RIGHT_SURROUND;
+    const LC: u32 = LABEL_LEFT_CENTER;
+    const RC: u32 = LABEL_RIGHT_CENTER;
+    const CS: u32 = LABEL_CENTER_SURROUND;
+    const LSD: u32 = LABEL_LEFT_SURROUND_DIRECT;
+    const RSD: u32 = LABEL_RIGHT_SURROUND_DIRECT;
+    const TS: u32 = LABEL_TOP_CENTER_SURROUND;
+    const VHL: u32 = LABEL_VERTICAL_HEIGHT_LEFT;
+    const VHC: u32 = LABEL_VERTICAL_HEIGHT_CENTER;
+    const VHR: u32 = LABEL_VERTICAL_HEIGHT_RIGHT;
+    const TBL: u32 = LABEL_TOP_BACK_LEFT;
+    const TBR: u32 = LABEL_TOP_BACK_RIGHT;
+    const RLS: u32 = LABEL_REAR_SURROUND_LEFT;
+    const RRS: u32 = LABEL_REAR_SURROUND_RIGHT;
+    const LW: u32 = LABEL_LEFT_WIDE;
+    const RW: u32 = LABEL_RIGHT_WIDE;
+
+    // The upper 16 bits of a layout tag identifies the layout.
+    let labels: &'static [u32] = match tag >> 16 {
+        // Mono. Symphonia represents a mono channel as front-left.
+        100 => &[L],
+        // Stereo, StereoHeadphones, MatrixStereo, and Binaural
+        101 | 102 | 103 | 106 => &[L, R],
+        // Quadraphonic
+        108 => &[L, R, LS, RS],
+        // Pentagonal
+        109 => &[L, R, LS, RS, C],
+        // Hexagonal
+        110 => &[L, R, LS, RS, C, CS],
+        // Octagonal
+        111 => &[L, R, LS, RS, C, CS, LW, RW],
+        // Cube
+        112 => &[L, R, LS, RS, VHL, VHR, TBL, TBR],
+        // MPEG_3_0_A and MPEG_3_0_B
+        113 => &[L, R, C],
+        114 => &[C, L, R],
+        // MPEG_4_0_A and MPEG_4_0_B
+        115 => &[L, R, C, CS],
+        116 => &[C, L, R, CS],
+        // MPEG_5_0_A to MPEG_5_0_D
+        117 => &[L, R, C, LS, RS],
+        118 => &[L, R, LS, RS, C],
+        119 => &[L, C, R, LS, RS],
+        120 => &[C, L, R, LS, RS],
+        // MPEG_5_1_A to MPEG_5_1_D
+        121 => &[L, R, C, LFE, LS, RS],
+        122 => &[L, R, LS, RS, C, LFE],
+        123 => &[L, C, R, LS, RS, LFE],
+        124 => &[C, L, R, LS, RS, LFE],
+        // MPEG_6_1_A
+        125 => &[L, R, C, LFE, LS, RS, CS],
+        // MPEG_7_1_A to MPEG_7_1_C
+        126 => &[L, R, C, LFE, LS, RS, LC, RC],
+        127 => &[C, LC, RC, L, R, LS, RS, LFE],
+        128 => &[L, R, C, LFE, LS, RS, RLS, RRS],
+        // Emagic_Default_7_1
+        129 => &[L, R, LS, RS, C, LFE, LC, RC],
+        // ITU_2_1 and ITU_2_2
+        131 => &[L, R, CS],
+        132 => &[L, R, LS, RS],
+        // DVD_4 to DVD_6
+        133 => &[L, R, LFE],
+        134 => &[L, R, LFE, CS],
+        135 => &[L, R, LFE, LS, RS],
+        // DVD_10, DVD_11, and DVD_18
+        136 => &[L, R, C, LFE],
+        137 => &[L, R, C, LFE, CS],
+        138 => &[L, R, LS, RS, LFE],
+        // AudioUnit_6_0 and AudioUnit_7_0
+        139 => &[L, R, LS, RS, C, CS],
+        140 => &[L, R, LS, RS, C, RLS, RRS],
+        // AAC_6_0, AAC_6_1, AAC_7_0, and AAC_Octagonal
+        141 => &[C, L, R, LS, RS, CS],
+        142 => &[C, L, R, LS, RS, CS, LFE],
+        143 => &[C, L, R, LS, RS, RLS, RRS],
+        144 => &[C, L, R, LS, RS, RLS, RRS, CS],
+        // AudioUnit_7_0_Front
+        148 => &[L, R, LS, RS, C, LC, RC],
+        // AC3_1_0_1 to AC3_3_1_1
+        149 => &[C, LFE],
+        150 => &[L, C, R],
+        151 => &[L, C, R, CS],
+        152 => &[L, C, R, LFE],
+        153 => &[L, R, CS, LFE],
+        154 => &[L, C, R, CS, LFE],
+        // EAC_6_0_A and EAC_7_0_A
+        155 => &[L, C, R, LS, RS, CS],
+        156 => &[L, C, R, LS, RS, RLS, RRS],
+        // EAC3_6_1_A to EAC3_6_1_C
+        157 => &[L, C, R, LS, RS, LFE, CS],
+        158 => &[L, C, R, LS, RS, LFE, TS],
+        159 => &[L, C, R, LS, RS, LFE, VHC],
+        // EAC3_7_1_A to EAC3_7_1_H
+        160 => &[L, C, R, LS, RS, LFE, RLS, RRS],
+        161 => &[L, C, R, LS, RS, LFE, LC, RC],
+        162 => &[L, C, R, LS, RS, LFE, LSD, RSD],
+        163 => &[L, C, R, LS, RS, LFE, LW, RW],
+        164 => &[L, C, R, LS, RS, LFE, VHL, VHR],
+        165 => &[L, C, R, LS, RS, LFE, CS, TS],
+        166 => &[L, C, R, LS, RS, LFE, CS, VHC],
+        167 => &[L, C, R, LS, RS, LFE, TS, VHC],
+        // DTS_3_1 and DTS_4_1
+        168 => &[C, L, R, LFE],
+        169 => &[C, L, R, CS, LFE],
+        // DTS_6_0_A to DTS_6_0_C
+        170 => &[LC, RC, L, R, LS, RS],
+        171 => &[C, L, R, RLS, RRS, TS],
+        172 => &[C, CS, L, R, RLS, RRS],
+        // DTS_6_1_A to DTS_6_1_C
+        173 => &[LC, RC, L, R, LS, RS, LFE],
+        174 => &[C, L, R, RLS, RRS, TS, LFE],
+        175 => &[C, CS, L, R, RLS, RRS, LFE],
+        // DTS_7_0 and DTS_7_1
+        176 => &[LC, C, RC, L, R, LS, RS],
+        177 => &[LC, C, RC, L, R, LS, RS, LFE],
+        // DTS_8_0_A, DTS_8_0_B, DTS_8_1_A, and DTS_8_1_B
+        178 => &[LC, RC, L, R, LS, RS, RLS, RRS],
+        179 => &[LC, C, RC, L, R, LS, CS, RS],
+        180 => &[LC, RC, L, R, LS, RS, RLS, RRS, LFE],
+        181 => &[LC, C, RC, L, R, LS, CS, RS, LFE],
+        // DTS_6_1_D
+        182 => &[C, L, R, LS, RS, LFE, CS],
+        // AAC_7_1_B and AAC_7_1_C
+        183 => &[C, L, R, LS, RS, RLS, RRS, LFE],
+        184 => &[C, L, R, LS, RS, LFE, VHL, VHR],
+        _ => return None,
+    };
+
+    Some(labels)
+}
 
 pub struct PacketTable {
     pub valid_frames: i64,
@@ -585,4 +782,82 @@ mod tests {
 
         assert!(read_variable_length_integer(&mut source).is_err());
     }
+
+    fn channel_layout(tag: u32, bitmap: u32, labels: &[u32]) -> ChannelLayout {
+        let channel_descriptions = labels
+            .iter()
+            .map(|&channel_label| ChannelDescription {
+                channel_label,
+                channel_flags: 0,
+                coordinates: [0.0; 3],
+            })
+            .collect();
+
+        ChannelLayout { channel_layout: tag, channel_bitmap: bitmap, channel_descriptions }
+    }
+
+    #[test]
+    fn channel_layout_tags() {
+        let five_point_one = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::FRONT_CENTRE
+            | Channels::LFE1
+            | Channels::REAR_LEFT
+            | Channels::REAR_RIGHT;
+
+        // MPEG_5_1_A to MPEG_5_1_D only differ in channel order.
+        for tag in 121..=124 {
+            let layout = channel_layout((tag << 16) | 6, 0, &[]);
+            assert_eq!(layout.channels(), Some(five_point_one));
+        }
+
+        // MPEG_7_1_C has rear surround channels, so the surround channels are at the sides.
+        let layout = channel_layout((128 << 16) | 8, 0, &[]);
+        assert_eq!(
+            layout.channels(),
+            Some(
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+                    | Channels::SIDE_LEFT
+                    | Channels::SIDE_RIGHT
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+            )
+        );
+
+        // The channel count of the tag must match the layout.
+        assert_eq!(channel_layout((124 << 16) | 5, 0, &[]).channels(), None);
+        // Unknown layouts are not supported.
+        assert_eq!(channel_layout((0xffff << 16) | 2, 0, &[]).channels(), None);
+    }
+
+    #[test]
+    fn channel_layout_bitmap() {
+        let layout = channel_layout(LAYOUT_TAG_USE_CHANNEL_BITMAP, 0x3f, &[]);
+        assert_eq!(layout.channels(), Some(Channels::from_bits(0x3f).unwrap()));
+
+        // Channel bits above the top-back-right channel have no Symphonia equivalent.
+        let layout = channel_layout(LAYOUT_TAG_USE_CHANNEL_BITMAP, 1 << 20, &[]);
+        assert_eq!(layout.channels(), None);
+    }
+
+    #[test]
+    fn channel_layout_descriptions() {
+        let layout = channel_layout(0, 0, &[LABEL_CENTER, LABEL_LEFT, LABEL_RIGHT, LABEL_LFE2]);
+        assert_eq!(
+            layout.channels(),
+            Some(
+                Channels::FRONT_CENTRE
+                    | Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::LFE2
+            )
+        );
+
+        // Duplicate and unknown channel labels are rejected.
+        assert_eq!(channel_layout(0, 0, &[LABEL_LEFT, LABEL_LEFT]).channels(), None);
+        assert_eq!(channel_layout(0, 0, &[LABEL_LEFT, 0xffff]).channels(), None);
+    }
 }
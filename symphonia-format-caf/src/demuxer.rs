@@ -27,6 +27,10 @@ const MAX_FRAMES_PER_PACKET: u64 = 1152;
 /// Core Audio Format (CAF) format reader.
 ///
 /// `CafReader` implements a demuxer for Core Audio Format containers.
+///
+/// The packets of compressed formats are located using the packet table. The priming and
+/// remainder frames recorded in the packet table are the encoder delay and padding, and are
+/// trimmed if gapless playback is enabled.
 pub struct CafReader {
     reader: MediaSourceStream,
     tracks: Vec<Track>,
@@ -35,6 +39,7 @@ pub struct CafReader {
     data_start_pos: u64,
     data_len: Option<u64>,
     packet_info: PacketInfo,
+    enable_gapless: bool,
 }
 
 enum PacketInfo {
@@ -54,7 +59,7 @@ impl QueryDescriptor for CafReader {
 }
 
 impl FormatReader for CafReader {
-    fn try_new(source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+    fn try_new(source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
         let mut reader = Self {
             reader: source,
             tracks: vec![],
@@ -63,6 +68,7 @@ impl FormatReader for CafReader {
             data_start_pos: 0,
             data_len: None,
             packet_info: PacketInfo::Unknown,
+            enable_gapless: options.enable_gapless,
         };
 
         reader.check_file_header()?;
@@ -102,8 +108,21 @@ impl FormatReader for CafReader {
             PacketInfo::Compressed { packets, ref mut current_packet_index } => {
                 if let Some(packet) = packets.get(*current_packet_index) {
                     *current_packet_index += 1;
-                    let buffer = self.reader.read_boxed_slice(packet.size as usize)?;
-                    Ok(Packet::new_from_boxed_slice(0, packet.start_frame, packet.frames, buffer))
+                    let buffer = self.reader.read_boxed_slice_exact(packet.size as usize)?;
+                    let mut packet =
+                        Packet::new_from_boxed_slice(0, packet.start_frame, packet.frames, buffer);
+
+                    if self.enable_gapless {
+                        let params = &self.tracks[0].codec_params;
+
+                        symphonia_core::formats::util::trim_packet(
+                            &mut packet,
+                            params.delay.unwrap_or(0),
+                            params.n_frames,
+                        );
+                    }
+
+                    Ok(packet)
                 }
                 else if *current_packet_index == packets.len() {
                     end_of_stream_error()
@@ -173,23 +192,18 @@ impl FormatReader for CafReader {
                 Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
             }
             PacketInfo::Compressed { packets, current_packet_index } => {
-                let current_ts = if let Some(packet) = packets.get(*current_packet_index) {
-                    TimeStamp::from(packet.start_frame)
-                }
-                else {
-                    error!("invalid packet index: {}", current_packet_index);
-                    return decode_error("caf: invalid packet index");
+                // If gapless playback is enabled, timestamps exclude the delay.
+                let delay = match self.tracks[0].codec_params.delay {
+                    Some(delay) if self.enable_gapless => u64::from(delay),
+                    _ => 0,
                 };
 
-                let search_range = if current_ts < required_ts {
-                    *current_packet_index..packets.len()
+                if packets.is_empty() {
+                    return seek_error(SeekErrorKind::Unseekable);
                 }
-                else {
-                    0..*current_packet_index
-                };
 
-                let packet_after_ts = packets[search_range]
-                    .partition_point(|packet| packet.start_frame < required_ts);
+                let packet_after_ts =
+                    packets.partition_point(|packet| packet.start_frame < required_ts + delay);
                 let seek_packet_index = packet_after_ts.saturating_sub(1);
                 let seek_packet = &packets[seek_packet_index];
 
@@ -209,7 +223,7 @@ impl FormatReader for CafReader {
                 }
 
                 *current_packet_index = seek_packet_index;
-                let actual_ts = TimeStamp::from(seek_packet.start_frame);
+                let actual_ts = TimeStamp::from(seek_packet.start_frame.saturating_sub(delay));
 
                 debug!(
                     "seek required_ts: {}, actual_ts: {}, (difference: {}, packet: {})",
@@ -279,9 +293,9 @@ impl CafReader {
                 codec_params.with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
             }
             n => {
-                // When the channel count is >2 then enable the first N channels.
-                // This can/should be overridden when parsing the channel layout chunk.
-                match Channels::from_bits(((1u64 << n as u64) - 1) as u32) {
+                // When the channel count is >2 then use discrete channels. This can/should be
+                // overridden when parsing the channel layout chunk.
+                match Channels::discrete(n as usize) {
                     Some(channels) => {
                         codec_params.with_channels(channels);
                     }
@@ -329,19 +343,51 @@ impl CafReader {
                     }
                 }
                 Some(ChannelLayout(layout)) => {
-                    if let Some(channels) = layout.channels() {
-                        codec_params.channels = Some(channels);
-                    }
-                    else {
-                        // Don't error if the layout doesn't correspond directly to a Symphonia
-                        // layout, the channels bitmap was set after the audio description was read
-                        // to match the number of channels, and that's probably OK.
-                        info!("couldn't convert the channel layout into a channel bitmap");
+                    let channels_per_frame =
+                        audio_description.as_ref().map(|desc| desc.channels_per_frame);
+
+                    match layout.channels() {
+                        Some(channels) if Some(channels.count() as u32) == channels_per_frame => {
+                            codec_params.channels = Some(channels);
+                        }
+                        Some(channels) => {
+                            info!(
+                                "channel layout has {} channels, expected {:?}",
+                                channels.count(),
+                                channels_per_frame
+                            );
+                        }
+                        None => {
+                            // Don't error if the layout doesn't correspond directly to a
+                            // Symphonia layout, the channels bitmap was set after the audio
+                            // description was read to match the number of channels, and that's
+                            // probably OK.
+                            info!("couldn't convert the channel layout into a channel bitmap");
+                        }
                     }
                 }
                 Some(PacketTable(table)) => {
                     if let PacketInfo::Compressed { ref mut packets, .. } = &mut self.packet_info {
-                        codec_params.with_n_frames(table.valid_frames as u64);
+                        // The priming and remainder frames are the encoder delay and padding.
+                        let delay = table.priming_frames.max(0) as u32;
+                        let padding = table.remainder_frames.max(0) as u32;
+
+                        codec_params.with_delay(delay).with_padding(padding);
+
+                        // Exclude the delay and padding from the number of frames only if they
+                        // will be trimmed.
+                        if self.enable_gapless {
+                            codec_params.with_n_frames(table.valid_frames as u64);
+                        }
+                        else {
+                            let n_frames = table.packets.iter().map(|packet| packet.frames).sum();
+                            codec_params.with_n_frames(n_frames);
+                        }
+
+                        if let Some(max_frames) = table.packets.iter().map(|p| p.frames).max() {
+                            codec_params.with_max_frames_per_packet(max_frames);
+                        }
+
                         *packets = table.packets;
                     }
                 }
@@ -371,3 +417,210 @@ impl CafReader {
         Ok(codec_params)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::formats::FormatReader;
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::*;
+
+    fn chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = chunk_type.to_vec();
+        chunk.extend_from_slice(&(data.len() as i64).to_be_bytes());
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    /// Build a 16-bit big-endian PCM CAF file with the given channel layout chunk payload.
+    fn build_caf(channels_per_frame: u32, chan: &[u8], n_frames: u32) -> Vec<u8> {
+        let mut desc = Vec::new();
+        desc.extend_from_slice(&48000f64.to_be_bytes());
+        desc.extend_from_slice(b"lpcm");
+        desc.extend_from_slice(&0u32.to_be_bytes());
+        desc.extend_from_slice(&(2 * channels_per_frame).to_be_bytes());
+        desc.extend_from_slice(&1u32.to_be_bytes());
+        desc.extend_from_slice(&channels_per_frame.to_be_bytes());
+        desc.extend_from_slice(&16u32.to_be_bytes());
+
+        // The edit count followed by the audio data.
+        let mut data = 0u32.to_be_bytes().to_vec();
+        data.resize(4 + (2 * channels_per_frame * n_frames) as usize, 0);
+
+        let mut caf = b"caff".to_vec();
+        caf.extend_from_slice(&1u16.to_be_bytes());
+        caf.extend_from_slice(&0u16.to_be_bytes());
+        caf.extend(chunk(b"desc", &desc));
+        caf.extend(chunk(b"chan", chan));
+        caf.extend(chunk(b"data", &data));
+        caf
+    }
+
+    /// Build a CAF file with the given compressed format, a constant number of frames per packet,
+    /// and a packet table. The bytes of each packet are its index plus 1.
+    fn build_compressed_caf(
+        format: &[u8; 4],
+        frames_per_packet: u32,
+        packet_sizes: &[u8],
+        priming: i32,
+        remainder: i32,
+    ) -> Vec<u8> {
+        let mut desc = Vec::new();
+        desc.extend_from_slice(&44100f64.to_be_bytes());
+        desc.extend_from_slice(format);
+        desc.extend_from_slice(&0u32.to_be_bytes());
+        desc.extend_from_slice(&0u32.to_be_bytes());
+        desc.extend_from_slice(&frames_per_packet.to_be_bytes());
+        desc.extend_from_slice(&2u32.to_be_bytes());
+        desc.extend_from_slice(&0u32.to_be_bytes());
+
+        let n_packets = packet_sizes.len() as i64;
+        let valid_frames =
+            n_packets * i64::from(frames_per_packet) - i64::from(priming + remainder);
+
+        // The sizes are variable length integers, and are all less than 128 bytes.
+        let mut pakt = n_packets.to_be_bytes().to_vec();
+        pakt.extend_from_slice(&valid_frames.to_be_bytes());
+        pakt.extend_from_slice(&priming.to_be_bytes());
+        pakt.extend_from_slice(&remainder.to_be_bytes());
+        pakt.extend_from_slice(packet_sizes);
+
+        let mut data = 0u32.to_be_bytes().to_vec();
+        for (i, &size) in packet_sizes.iter().enumerate() {
+            data.extend(std::iter::repeat(i as u8 + 1).take(usize::from(size)));
+        }
+
+        let mut caf = b"caff".to_vec();
+        caf.extend_from_slice(&1u16.to_be_bytes());
+        caf.extend_from_slice(&0u16.to_be_bytes());
+        caf.extend(chunk(b"desc", &desc));
+        caf.extend(chunk(b"kuki", &[0; 24]));
+        caf.extend(chunk(b"pakt", &pakt));
+        caf.extend(chunk(b"data", &data));
+        caf
+    }
+
+    fn open_caf(caf: Vec<u8>, enable_gapless: bool) -> CafReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(caf)), Default::default());
+        let mut options = FormatOptions::default();
+        options.with_gapless(enable_gapless);
+        CafReader::try_new(mss, &options).unwrap()
+    }
+
+    /// Reads all packets, and returns the timestamp, duration, trimming, and data of each.
+    fn read_packets(reader: &mut CafReader) -> Vec<(u64, u64, u32, u32, Vec<u8>)> {
+        let mut packets = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            let trim = (packet.trim_start, packet.trim_end);
+            packets.push((packet.ts(), packet.dur(), trim.0, trim.1, packet.buf().to_vec()));
+        }
+
+        packets
+    }
+
+    #[test]
+    fn verify_alac_packet_table() {
+        let caf = build_compressed_caf(b"alac", 4096, &[5, 9, 3], 0, 1000);
+
+        let mut reader = open_caf(caf.clone(), false);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_ALAC);
+        assert_eq!(params.n_frames, Some(3 * 4096));
+        assert_eq!(params.padding, Some(1000));
+        assert_eq!(params.max_frames_per_packet, Some(4096));
+        assert_eq!(params.extra_data.as_deref(), Some(&[0; 24][..]));
+
+        assert_eq!(
+            read_packets(&mut reader),
+            &[
+                (0, 4096, 0, 0, vec![1; 5]),
+                (4096, 4096, 0, 0, vec![2; 9]),
+                (8192, 4096, 0, 0, vec![3; 3]),
+            ]
+        );
+
+        // With gapless playback the remainder frames are trimmed from the last packet.
+        let mut reader = open_caf(caf, true);
+
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(3 * 4096 - 1000));
+
+        let packets = read_packets(&mut reader);
+        assert_eq!(packets[2], (8192, 3096, 0, 1000, vec![3; 3]));
+    }
+
+    #[test]
+    fn verify_aac_priming_frames_are_trimmed() {
+        let caf = build_compressed_caf(b"aac ", 1024, &[4, 4, 4, 4], 2112, 448);
+
+        let mut reader = open_caf(caf, true);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_AAC);
+        assert_eq!(params.delay, Some(2112));
+        assert_eq!(params.n_frames, Some(4 * 1024 - 2112 - 448));
+
+        let packets: Vec<_> = read_packets(&mut reader)
+            .into_iter()
+            .map(|(ts, dur, s, e, _)| (ts, dur, s, e))
+            .collect();
+
+        assert_eq!(
+            packets,
+            &[(0, 0, 1024, 0), (0, 0, 1024, 0), (0, 960, 64, 0), (960, 576, 0, 448)]
+        );
+
+        // Seeking accounts for the priming frames.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 960);
+        assert_eq!(reader.next_packet().unwrap().buf(), &[4; 4]);
+
+        // The first frame after the priming frames is in the third packet.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 0);
+        assert_eq!(reader.next_packet().unwrap().buf(), &[3; 4]);
+    }
+
+    fn read_channels(caf: Vec<u8>) -> Option<Channels> {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(caf)), Default::default());
+        let reader = CafReader::try_new(mss, &Default::default()).unwrap();
+        reader.tracks()[0].codec_params.channels
+    }
+
+    #[test]
+    fn verify_5_1_channel_layout() {
+        // MPEG_5_1_D: C L R Ls Rs LFE
+        let mut chan = ((124u32 << 16) | 6).to_be_bytes().to_vec();
+        chan.extend_from_slice(&0u32.to_be_bytes());
+        chan.extend_from_slice(&0u32.to_be_bytes());
+
+        let caf = build_caf(6, &chan, 16);
+
+        assert_eq!(
+            read_channels(caf),
+            Some(
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTRE
+                    | Channels::LFE1
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+            )
+        );
+    }
+
+    #[test]
+    fn verify_mismatched_channel_layout() {
+        // A stereo layout for a 6 channel stream is ignored.
+        let mut chan = ((101u32 << 16) | 2).to_be_bytes().to_vec();
+        chan.extend_from_slice(&0u32.to_be_bytes());
+        chan.extend_from_slice(&0u32.to_be_bytes());
+
+        let caf = build_caf(6, &chan, 16);
+
+        assert_eq!(read_channels(caf), Channels::from_bits(0x3f));
+    }
+}
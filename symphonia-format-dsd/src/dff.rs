@@ -0,0 +1,327 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::support_format;
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_DSD_MSBF};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+
+use log::debug;
+
+/// The maximum number of frames that will be in a packet.
+const DFF_MAX_FRAMES_PER_PACKET: u64 = 4096;
+
+/// Reads the identifier and the length of a chunk.
+fn read_chunk_header<B: ReadBytes>(reader: &mut B) -> Result<([u8; 4], u64)> {
+    let id = reader.read_quad_bytes()?;
+    let len = reader.read_be_u64()?;
+    Ok((id, len))
+}
+
+/// Ignores the remainder of a chunk, including the pad byte of odd length chunks.
+fn ignore_chunk<B: ReadBytes>(reader: &mut B, len: u64, read: u64) -> Result<()> {
+    if read > len {
+        return decode_error("dff: chunk is too short");
+    }
+
+    reader.ignore_bytes(len - read + (len & 1))?;
+    Ok(())
+}
+
+/// Gets the channels from a list of DSDIFF channel identifiers.
+fn channels(ids: &[[u8; 4]]) -> Option<Channels> {
+    let mut channels = Channels::empty();
+
+    for id in ids {
+        let channel = match id {
+            b"SLFT" | b"MLFT" => Channels::FRONT_LEFT,
+            b"SRGT" | b"MRGT" => Channels::FRONT_RIGHT,
+            b"C   " => Channels::FRONT_CENTRE,
+            b"LFE " => Channels::LFE1,
+            b"LS  " => Channels::REAR_LEFT,
+            b"RS  " => Channels::REAR_RIGHT,
+            _ => return None,
+        };
+
+        // The audio is interleaved in the order of the channel identifiers, but an audio buffer
+        // stores channels in the order of the channel bitmask. Therefore, the channels must be
+        // listed in the order of the bitmask, and without duplicates.
+        if channel.bits() <= channels.bits() {
+            return None;
+        }
+
+        channels |= channel;
+    }
+
+    Some(channels)
+}
+
+/// The properties of the sound stream.
+struct SoundProperties {
+    sample_rate: u32,
+    ids: Vec<[u8; 4]>,
+}
+
+impl SoundProperties {
+    fn read<B: ReadBytes>(reader: &mut B, len: u64) -> Result<SoundProperties> {
+        if len < 4 || reader.read_quad_bytes()? != *b"SND " {
+            return decode_error("dff: invalid property chunk");
+        }
+
+        let mut sample_rate = None;
+        let mut ids = None;
+
+        let mut read = 4;
+
+        while read < len {
+            let (id, chunk_len) = read_chunk_header(reader)?;
+
+            match &id {
+                b"FS  " => {
+                    sample_rate = Some(reader.read_be_u32()?);
+                    ignore_chunk(reader, chunk_len, 4)?;
+                }
+                b"CHNL" => {
+                    let n_channels = u64::from(reader.read_be_u16()?);
+
+                    let mut channel_ids = Vec::new();
+
+                    for _ in 0..n_channels.min(chunk_len.saturating_sub(2) / 4) {
+                        channel_ids.push(reader.read_quad_bytes()?);
+                    }
+
+                    ignore_chunk(reader, chunk_len, 2 + 4 * channel_ids.len() as u64)?;
+
+                    ids = Some(channel_ids);
+                }
+                b"CMPR" => {
+                    // Only uncompressed DSD is supported.
+                    if reader.read_quad_bytes()? != *b"DSD " {
+                        return unsupported_error("dff: compressed audio is not supported");
+                    }
+
+                    ignore_chunk(reader, chunk_len, 4)?;
+                }
+                _ => ignore_chunk(reader, chunk_len, 0)?,
+            }
+
+            read += 12 + chunk_len + (chunk_len & 1);
+        }
+
+        match (sample_rate, ids) {
+            (Some(sample_rate), Some(ids)) => Ok(SoundProperties { sample_rate, ids }),
+            (None, _) => decode_error("dff: missing sample rate chunk"),
+            (_, None) => decode_error("dff: missing channels chunk"),
+        }
+    }
+}
+
+/// DSD Interchange File Format (DSDIFF, or DFF) format reader.
+///
+/// `DffReader` implements a demuxer for the DSDIFF file format. DSDIFF stores the 1-bit audio of
+/// all channels interleaved byte-by-byte. Compressed (DST) audio is not supported.
+///
+/// Each frame of a DSD track is one byte, or 8 1-bit samples, per channel. Therefore, the sample
+/// rate of the track is 1/8th of the DSD sample rate.
+///
+/// Since every frame has the same length, seeking is supported by calculating the position of the
+/// desired timestamp.
+pub struct DffReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    n_channels: usize,
+    n_frames: u64,
+    data_start_pos: u64,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for DffReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "dff",
+            "DSD Interchange File Format",
+            &["dff"],
+            &["audio/dff", "audio/x-dff"],
+            &[b"FRM8"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for DffReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let (id, _) = read_chunk_header(&mut source)?;
+
+        if id != *b"FRM8" {
+            return unsupported_error("dff: missing frm8 chunk marker");
+        }
+
+        if source.read_quad_bytes()? != *b"DSD " {
+            return unsupported_error("dff: unsupported form type");
+        }
+
+        let mut props = None;
+
+        // Read chunks until the sound data chunk.
+        let data_len = loop {
+            let (id, len) = read_chunk_header(&mut source)?;
+
+            match &id {
+                b"FVER" => {
+                    // Only version 1.x is supported.
+                    if source.read_be_u32()? >> 24 != 1 {
+                        return unsupported_error("dff: unsupported format version");
+                    }
+
+                    ignore_chunk(&mut source, len, 4)?;
+                }
+                b"PROP" => props = Some(SoundProperties::read(&mut source, len)?),
+                b"DSD " => break len,
+                b"DST " => return unsupported_error("dff: compressed audio is not supported"),
+                _ => ignore_chunk(&mut source, len, 0)?,
+            }
+        };
+
+        let props = match props {
+            Some(props) => props,
+            _ => return decode_error("dff: missing property chunk"),
+        };
+
+        if props.ids.is_empty() {
+            return unsupported_error("dff: number of channels cannot be 0");
+        }
+
+        if props.sample_rate == 0 || props.sample_rate % 8 != 0 {
+            return unsupported_error("dff: unsupported sample rate");
+        }
+
+        let channels = match channels(&props.ids) {
+            Some(channels) => channels,
+            _ if props.ids.len() <= 32 => {
+                // The channels are unknown or out-of-order. Use the first channels of the bitmask
+                // as a substitute.
+                debug!("unknown channel layout, using the first {} channels", props.ids.len());
+
+                (0..props.ids.len()).fold(Channels::empty(), |channels, i| {
+                    channels | Channels::from_bits_truncate(1 << i)
+                })
+            }
+            _ => return unsupported_error("dff: too many channels"),
+        };
+
+        let n_channels = props.ids.len();
+
+        // The sound data chunk contains the frames of all channels, byte interleaved.
+        let n_frames = data_len / n_channels as u64;
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(CODEC_TYPE_DSD_MSBF)
+            .with_sample_rate(props.sample_rate / 8)
+            .with_time_base(TimeBase::new(1, props.sample_rate / 8))
+            .with_channels(channels)
+            .with_bits_per_coded_sample(1)
+            .with_max_frames_per_packet(DFF_MAX_FRAMES_PER_PACKET)
+            .with_n_frames(n_frames);
+
+        let data_start_pos = source.pos();
+
+        Ok(DffReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            n_channels,
+            n_frames,
+            data_start_pos,
+            next_packet_ts: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let ts = self.next_packet_ts;
+
+        if ts >= self.n_frames {
+            return end_of_stream_error();
+        }
+
+        let dur = (self.n_frames - ts).min(DFF_MAX_FRAMES_PER_PACKET);
+
+        let buf = self.reader.read_boxed_slice_exact(self.n_channels * dur as usize)?;
+
+        self.next_packet_ts += dur;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                self.tracks[0].codec_params.time_base.unwrap().calc_timestamp(time)
+            }
+        };
+
+        if ts > self.n_frames {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // The frames are byte interleaved, so a seek can be made directly to the desired frame.
+        let seek_pos = self.data_start_pos + ts * self.n_channels as u64;
+
+        debug!("seeking to frame_ts={}", ts);
+
+        // If the reader supports seeking we can seek directly to the frame's offset, otherwise,
+        // only forward seeks may be emulated by consuming bytes.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_packet_ts = ts;
+
+        Ok(SeekedTo { track_id: 0, actual_ts: ts, required_ts: ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
@@ -0,0 +1,295 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::support_format;
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::CodecParameters;
+use symphonia_core::codecs::{CODEC_TYPE_DSD_LSBF_PLANAR, CODEC_TYPE_DSD_MSBF_PLANAR};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+
+use log::debug;
+
+/// The length of the DSD chunk.
+const DSF_DSD_CHUNK_LEN: u64 = 28;
+
+/// The minimum length of the fmt chunk.
+const DSF_FMT_CHUNK_LEN: u64 = 52;
+
+/// The length of the data chunk header.
+const DSF_DATA_CHUNK_HEADER_LEN: u64 = 12;
+
+/// Gets the channels for a DSF channel type.
+fn channels(channel_type: u32) -> Option<Channels> {
+    let channels = match channel_type {
+        // Mono.
+        1 => Channels::FRONT_LEFT,
+        // Stereo.
+        2 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        // 3 channels.
+        3 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE,
+        // Quad.
+        4 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        // 4 channels.
+        5 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE | Channels::LFE1,
+        // 5 channels.
+        6 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        // 5.1 channels.
+        7 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::LFE1
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        _ => return None,
+    };
+
+    Some(channels)
+}
+
+/// DSD Stream File (DSF) format reader.
+///
+/// `DsfReader` implements a demuxer for the DSF file format. DSF stores the 1-bit audio of each
+/// channel in fixed length blocks, and each packet contains one block for every channel.
+///
+/// Each frame of a DSD track is one byte, or 8 1-bit samples, per channel. Therefore, the sample
+/// rate of the track is 1/8th of the DSD sample rate.
+///
+/// Since every block has the same length, seeking is supported by calculating the position of the
+/// blocks containing the desired timestamp.
+pub struct DsfReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    n_channels: usize,
+    block_len: u64,
+    n_frames: u64,
+    data_start_pos: u64,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for DsfReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!("dsf", "DSD Stream File", &["dsf"], &["audio/dsf"], &[b"DSD "])]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for DsfReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // The DSD chunk.
+        if source.read_quad_bytes()? != *b"DSD " {
+            return unsupported_error("dsf: missing dsd chunk marker");
+        }
+
+        if source.read_u64()? != DSF_DSD_CHUNK_LEN {
+            return decode_error("dsf: invalid dsd chunk length");
+        }
+
+        // The total file length, and the position of the ID3v2 metadata chunk.
+        source.ignore_bytes(16)?;
+
+        // The fmt chunk.
+        if source.read_quad_bytes()? != *b"fmt " {
+            return decode_error("dsf: missing fmt chunk");
+        }
+
+        let fmt_len = source.read_u64()?;
+
+        if fmt_len < DSF_FMT_CHUNK_LEN {
+            return decode_error("dsf: invalid fmt chunk length");
+        }
+
+        if source.read_u32()? != 1 {
+            return unsupported_error("dsf: unsupported format version");
+        }
+
+        // Only raw DSD is defined.
+        if source.read_u32()? != 0 {
+            return unsupported_error("dsf: unsupported format id");
+        }
+
+        let channel_type = source.read_u32()?;
+        let n_channels = source.read_u32()?;
+        let sample_rate = source.read_u32()?;
+        let bits_per_sample = source.read_u32()?;
+        let sample_count = source.read_u64()?;
+        let block_len = source.read_u32()?;
+
+        // The reserved field, and any unknown trailing fields.
+        source.ignore_bytes(fmt_len - DSF_FMT_CHUNK_LEN + 4)?;
+
+        let channels = match channels(channel_type) {
+            Some(channels) if channels.count() == n_channels as usize => channels,
+            _ => return unsupported_error("dsf: unsupported channel type"),
+        };
+
+        // A bits per sample of 1 indicates the least-significant bit of each byte is the earliest
+        // sample, while a bits per sample of 8 indicates the most-significant bit is.
+        let codec = match bits_per_sample {
+            1 => CODEC_TYPE_DSD_LSBF_PLANAR,
+            8 => CODEC_TYPE_DSD_MSBF_PLANAR,
+            _ => return unsupported_error("dsf: unsupported bits per sample"),
+        };
+
+        if sample_rate == 0 || sample_rate % 8 != 0 {
+            return unsupported_error("dsf: unsupported sample rate");
+        }
+
+        if block_len == 0 {
+            return decode_error("dsf: invalid block length");
+        }
+
+        // The data chunk.
+        if source.read_quad_bytes()? != *b"data" {
+            return decode_error("dsf: missing data chunk");
+        }
+
+        if source.read_u64()? < DSF_DATA_CHUNK_HEADER_LEN {
+            return decode_error("dsf: invalid data chunk length");
+        }
+
+        // Each frame is 8 1-bit samples. The last frame may be partially filled.
+        let n_frames = (sample_count + 7) / 8;
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(codec)
+            .with_sample_rate(sample_rate / 8)
+            .with_time_base(TimeBase::new(1, sample_rate / 8))
+            .with_channels(channels)
+            .with_bits_per_coded_sample(1)
+            .with_max_frames_per_packet(u64::from(block_len))
+            .with_n_frames(n_frames);
+
+        let data_start_pos = source.pos();
+
+        Ok(DsfReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            n_channels: n_channels as usize,
+            block_len: u64::from(block_len),
+            n_frames,
+            data_start_pos,
+            next_packet_ts: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let ts = self.next_packet_ts;
+
+        if ts >= self.n_frames {
+            return end_of_stream_error();
+        }
+
+        let block_len = self.block_len as usize;
+
+        let mut buf = vec![0; self.n_channels * block_len];
+        self.reader.read_buf_exact(&mut buf)?;
+
+        // The blocks of the last packet are padded. Only keep the audio frames of each block.
+        let dur = (self.n_frames - ts).min(self.block_len);
+
+        if dur < self.block_len {
+            let mut trimmed = Vec::with_capacity(self.n_channels * dur as usize);
+
+            for block in buf.chunks_exact(block_len) {
+                trimmed.extend_from_slice(&block[..dur as usize]);
+            }
+
+            buf = trimmed;
+        }
+
+        self.next_packet_ts += dur;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf.into_boxed_slice()))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                self.tracks[0].codec_params.time_base.unwrap().calc_timestamp(time)
+            }
+        };
+
+        if ts > self.n_frames {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // Seek to the start of the blocks containing the desired timestamp. The blocks of all
+        // channels are stored one after another.
+        let block_idx = ts / self.block_len;
+
+        let actual_ts = block_idx * self.block_len;
+        let seek_pos = self.data_start_pos + actual_ts * self.n_channels as u64;
+
+        debug!("seeking to frame_ts={}, block={}", actual_ts, block_idx);
+
+        // If the reader supports seeking we can seek directly to the blocks' offset, otherwise,
+        // only forward seeks may be emulated by consuming bytes.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_packet_ts = actual_ts;
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts: ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
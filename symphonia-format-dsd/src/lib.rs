@@ -0,0 +1,315 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![warn(rust_2018_idioms)]
+#![forbid(unsafe_code)]
+// The following lints are allowed in all Symphonia crates. Please see clippy.toml for their
+// justification.
+#![allow(clippy::comparison_chain)]
+#![allow(clippy::excessive_precision)]
+#![allow(clippy::identity_op)]
+#![allow(clippy::manual_range_contains)]
+
+mod dff;
+mod dsf;
+
+pub use dff::DffReader;
+pub use dsf::DsfReader;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_codec_dsd::{DsdDecoder, DsdToPcm};
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{Decoder, DecoderOptions};
+    use symphonia_core::errors::{Error, SeekErrorKind};
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::units::Time;
+
+    use super::{DffReader, DsfReader};
+
+    /// Generates pseudo-random DSD audio for each channel, most-significant bit first.
+    fn gen_audio(n_channels: usize, n_frames: usize) -> Vec<Vec<u8>> {
+        let mut state = 0x1234_5678u32;
+
+        (0..n_channels)
+            .map(|_| {
+                (0..n_frames)
+                    .map(|_| {
+                        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                        (state >> 24) as u8
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Builds a DSF file with 2.8224MHz audio. If `lsb_first` is set, the audio is stored with a
+    /// bits per sample of 1, otherwise 8.
+    fn build_dsf(
+        channel_type: u32,
+        lsb_first: bool,
+        block_len: usize,
+        audio: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let n_frames = audio[0].len();
+        let n_blocks = (n_frames + block_len - 1) / block_len;
+
+        let mut data = Vec::new();
+
+        for block in 0..n_blocks {
+            for channel in audio {
+                let start = block * block_len;
+                let end = (start + block_len).min(n_frames);
+
+                for &byte in &channel[start..end] {
+                    data.push(if lsb_first { byte.reverse_bits() } else { byte });
+                }
+
+                // The last block is padded with zeros.
+                data.resize(data.len() + block_len - (end - start), 0);
+            }
+        }
+
+        let mut buf = b"DSD ".to_vec();
+        buf.extend_from_slice(&28u64.to_le_bytes());
+        buf.extend_from_slice(&(28 + 52 + 12 + data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&0u64.to_le_bytes());
+
+        buf.extend_from_slice(b"fmt ");
+        buf.extend_from_slice(&52u64.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&channel_type.to_le_bytes());
+        buf.extend_from_slice(&(audio.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&2_822_400u32.to_le_bytes());
+        buf.extend_from_slice(&(if lsb_first { 1u32 } else { 8 }).to_le_bytes());
+        buf.extend_from_slice(&(8 * n_frames as u64).to_le_bytes());
+        buf.extend_from_slice(&(block_len as u32).to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+
+        buf.extend_from_slice(b"data");
+        buf.extend_from_slice(&(12 + data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&data);
+
+        buf
+    }
+
+    /// Builds a DSDIFF file with 2.8224MHz audio.
+    fn build_dff(ids: &[&[u8; 4]], audio: &[Vec<u8>]) -> Vec<u8> {
+        fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+            let mut buf = id.to_vec();
+            buf.extend_from_slice(&(body.len() as u64).to_be_bytes());
+            buf.extend_from_slice(body);
+
+            if body.len() % 2 != 0 {
+                buf.push(0);
+            }
+
+            buf
+        }
+
+        let mut chnl = (ids.len() as u16).to_be_bytes().to_vec();
+        ids.iter().for_each(|id| chnl.extend_from_slice(*id));
+
+        // A compression type, and a compression name as a pascal string.
+        let mut cmpr = b"DSD ".to_vec();
+        cmpr.push(14);
+        cmpr.extend_from_slice(b"not compressed");
+
+        let mut prop = b"SND ".to_vec();
+        prop.extend(chunk(b"FS  ", &2_822_400u32.to_be_bytes()));
+        prop.extend(chunk(b"CHNL", &chnl));
+        prop.extend(chunk(b"CMPR", &cmpr));
+
+        let data: Vec<u8> =
+            (0..audio[0].len()).flat_map(|i| audio.iter().map(move |channel| channel[i])).collect();
+
+        let mut form = b"DSD ".to_vec();
+        form.extend(chunk(b"FVER", &0x0105_0000u32.to_be_bytes()));
+        form.extend(chunk(b"PROP", &prop));
+        form.extend(chunk(b"COMT", &[0, 0, 1]));
+        form.extend(chunk(b"DSD ", &data));
+
+        chunk(b"FRM8", &form)
+    }
+
+    /// Decodes all packets, and returns the timestamp of each packet, the audio of each channel,
+    /// and the audio of each channel converted to PCM.
+    fn decode_all(reader: &mut dyn FormatReader) -> (Vec<u64>, Vec<Vec<u8>>, Vec<Vec<f32>>) {
+        let params = &reader.tracks()[0].codec_params;
+
+        let mut decoder = DsdDecoder::try_new(params, &DecoderOptions::default()).unwrap();
+
+        let rate = params.sample_rate.unwrap();
+        let channels = params.channels.unwrap();
+
+        let mut converter = DsdToPcm::new(rate, rate / 4, channels).unwrap();
+
+        let mut timestamps = Vec::new();
+        let mut audio = vec![Vec::new(); channels.count()];
+        let mut pcm = vec![Vec::new(); channels.count()];
+
+        while let Ok(packet) = reader.next_packet() {
+            timestamps.push(packet.ts());
+
+            let decoded = decoder.decode(&packet).unwrap();
+
+            match &decoded {
+                AudioBufferRef::U8(buf) => {
+                    assert_eq!(buf.frames() as u64, packet.dur());
+
+                    for (ch, audio) in audio.iter_mut().enumerate() {
+                        audio.extend_from_slice(buf.chan(ch));
+                    }
+                }
+                _ => unreachable!(),
+            }
+
+            match converter.process(&decoded).unwrap() {
+                AudioBufferRef::F32(buf) => {
+                    for (ch, pcm) in pcm.iter_mut().enumerate() {
+                        pcm.extend_from_slice(buf.chan(ch));
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        (timestamps, audio, pcm)
+    }
+
+    fn new_mss(buf: Vec<u8>) -> MediaSourceStream {
+        MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default())
+    }
+
+    #[test]
+    fn verify_dsf_and_dff_match() {
+        let audio = gen_audio(2, 10_000);
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+
+        let dsf_lsbf = build_dsf(2, true, 4096, &audio);
+        let dsf_msbf = build_dsf(2, false, 4096, &audio);
+        let dff = build_dff(&[b"SLFT", b"SRGT"], &audio);
+
+        let mut dsf_lsbf =
+            DsfReader::try_new(new_mss(dsf_lsbf), &FormatOptions::default()).unwrap();
+        let mut dsf_msbf =
+            DsfReader::try_new(new_mss(dsf_msbf), &FormatOptions::default()).unwrap();
+        let mut dff = DffReader::try_new(new_mss(dff), &FormatOptions::default()).unwrap();
+
+        for reader in [&dsf_lsbf as &dyn FormatReader, &dsf_msbf, &dff].iter() {
+            let params = &reader.tracks()[0].codec_params;
+            assert_eq!(params.sample_rate, Some(352_800));
+            assert_eq!(params.channels, Some(stereo));
+            assert_eq!(params.n_frames, Some(10_000));
+        }
+
+        // DSF packets contain one block per channel. The blocks of the last packet are padded.
+        let (dsf_lsbf_ts, dsf_lsbf_audio, dsf_lsbf_pcm) = decode_all(&mut dsf_lsbf);
+        let (dsf_msbf_ts, dsf_msbf_audio, dsf_msbf_pcm) = decode_all(&mut dsf_msbf);
+
+        assert_eq!(dsf_lsbf_ts, &[0, 4096, 8192]);
+        assert_eq!(dsf_msbf_ts, dsf_lsbf_ts);
+
+        let (_, dff_audio, dff_pcm) = decode_all(&mut dff);
+
+        // Regardless of the bit order and interleaving, the decoded audio is identical.
+        assert_eq!(dsf_lsbf_audio, audio);
+        assert_eq!(dsf_msbf_audio, audio);
+        assert_eq!(dff_audio, audio);
+
+        // Therefore, the decimated PCM audio is also identical.
+        assert_eq!(dsf_lsbf_pcm[0].len(), 2500);
+        assert_eq!(dsf_lsbf_pcm, dff_pcm);
+        assert_eq!(dsf_msbf_pcm, dff_pcm);
+    }
+
+    #[test]
+    fn verify_channel_layouts() {
+        let surround = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::FRONT_CENTRE
+            | Channels::LFE1
+            | Channels::REAR_LEFT
+            | Channels::REAR_RIGHT;
+
+        let audio = gen_audio(6, 100);
+
+        // A DSF 5.1 channel type.
+        let dsf = build_dsf(7, true, 4096, &audio);
+        let reader = DsfReader::try_new(new_mss(dsf), &FormatOptions::default()).unwrap();
+        assert_eq!(reader.tracks()[0].codec_params.channels, Some(surround));
+
+        // The number of channels must match the channel type.
+        let dsf = build_dsf(2, true, 4096, &audio);
+        assert!(DsfReader::try_new(new_mss(dsf), &FormatOptions::default()).is_err());
+
+        // A DSDIFF multi-channel speaker configuration.
+        let ids = [b"MLFT", b"MRGT", b"C   ", b"LFE ", b"LS  ", b"RS  "];
+        let dff = build_dff(&ids, &audio);
+        let mut reader = DffReader::try_new(new_mss(dff), &FormatOptions::default()).unwrap();
+        assert_eq!(reader.tracks()[0].codec_params.channels, Some(surround));
+
+        let (_, dff_audio, _) = decode_all(&mut reader);
+        assert_eq!(dff_audio, audio);
+
+        // Out-of-order channels substitute the first channels of the bitmask.
+        let dff = build_dff(&[b"SRGT", b"SLFT", b"C   "], &audio[..3]);
+        let reader = DffReader::try_new(new_mss(dff), &FormatOptions::default()).unwrap();
+        assert_eq!(
+            reader.tracks()[0].codec_params.channels,
+            Some(Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE)
+        );
+    }
+
+    #[test]
+    fn verify_seek() {
+        let audio = gen_audio(2, 10_000);
+
+        let dsf = build_dsf(2, true, 4096, &audio);
+        let dff = build_dff(&[b"SLFT", b"SRGT"], &audio);
+
+        let mut dsf = DsfReader::try_new(new_mss(dsf), &FormatOptions::default()).unwrap();
+        let mut dff = DffReader::try_new(new_mss(dff), &FormatOptions::default()).unwrap();
+
+        // DSF seeks land on the first frame of the block containing the timestamp.
+        let seeked = dsf.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 5000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 4096);
+
+        let (ts, dsf_audio, _) = decode_all(&mut dsf);
+        assert_eq!(ts, &[4096, 8192]);
+        assert_eq!(dsf_audio[0], &audio[0][4096..]);
+        assert_eq!(dsf_audio[1], &audio[1][4096..]);
+
+        // DSDIFF seeks land exactly on the timestamp. Seeking backwards is also possible.
+        let seeked = dff.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 9000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 9000);
+
+        let time = Time::new(0, 0.0125);
+        let seeked = dff.seek(SeekMode::Accurate, SeekTo::Time { time, track_id: None });
+        assert_eq!(seeked.unwrap().actual_ts, 4410);
+
+        let (ts, dff_audio, _) = decode_all(&mut dff);
+        assert_eq!(ts, &[4410, 8506]);
+        assert_eq!(dff_audio[0], &audio[0][4410..]);
+        assert_eq!(dff_audio[1], &audio[1][4410..]);
+
+        // Seeking past the end of the stream is an error.
+        for reader in [&mut dsf as &mut dyn FormatReader, &mut dff].iter_mut() {
+            let seeked =
+                reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 10_001, track_id: 0 });
+
+            match seeked {
+                Err(Error::SeekError(SeekErrorKind::OutOfRange)) => (),
+                _ => panic!("expected an out-of-range seek error"),
+            }
+        }
+    }
+}
@@ -5,18 +5,24 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::convert::TryFrom;
+
 use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 use symphonia_core::util::bits;
 
 use crate::atoms::{Atom, AtomHeader};
 
+use log::warn;
+
 /// Edit list entry.
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct ElstEntry {
-    segment_duration: u64,
-    media_time: i64,
+    /// The duration of the edit in movie timescale units.
+    pub segment_duration: u64,
+    /// The starting time of the edit in media timescale units, or -1 for an empty edit.
+    pub media_time: i64,
     media_rate_int: i16,
     media_rate_frac: i16,
 }
@@ -26,7 +32,75 @@ pub struct ElstEntry {
 #[allow(dead_code)]
 pub struct ElstAtom {
     header: AtomHeader,
-    entries: Vec<ElstEntry>,
+    pub entries: Vec<ElstEntry>,
+}
+
+impl ElstAtom {
+    /// Gets the offset of the start of the media relative to the start of the presentation in
+    /// media timescale units.
+    ///
+    /// Leading empty edits delay the presentation of the media, while the media time of the first
+    /// non-empty edit skips the leading part of the media.
+    pub fn offset(&self, movie_timescale: u32, media_timescale: u32) -> Option<i64> {
+        if self.entries.is_empty() || movie_timescale == 0 {
+            return None;
+        }
+
+        let mut offset = 0i64;
+
+        for entry in self.entries.iter() {
+            if entry.media_time == -1 {
+                // An empty edit. The segment duration is in movie timescale units.
+                let delay = i128::from(entry.segment_duration) * i128::from(media_timescale)
+                    / i128::from(movie_timescale);
+
+                offset = offset.saturating_add(i64::try_from(delay).unwrap_or(i64::MAX));
+            }
+            else {
+                offset = offset.saturating_sub(entry.media_time);
+                break;
+            }
+        }
+
+        Some(offset)
+    }
+
+    /// Gets the number of leading media frames to skip (the encoder delay), and the number of media
+    /// frames to present, in media timescale units.
+    ///
+    /// Only edit lists with zero or more leading empty edits followed by a single edit at the
+    /// normal rate are supported. Other edit lists cannot be represented by a delay and frame count
+    /// and are rejected. Leading empty edits only offset the track, see [`ElstAtom::offset`].
+    pub fn gapless(&self, movie_timescale: u32, media_timescale: u32) -> Option<(u64, u64)> {
+        if movie_timescale == 0 {
+            return None;
+        }
+
+        let mut edits = self.entries.iter().skip_while(|entry| entry.media_time == -1);
+
+        let edit = edits.next()?;
+
+        if edit.media_time < 0 {
+            return None;
+        }
+
+        if edits.next().is_some() {
+            warn!("edit list with multiple non-empty edits is unsupported for gapless playback");
+            return None;
+        }
+
+        if edit.media_rate_int != 1 || edit.media_rate_frac != 0 {
+            warn!("edit list with a non-normal media rate is unsupported for gapless playback");
+            return None;
+        }
+
+        // The segment duration is in movie timescale units. Round to the nearest media frame.
+        let num_frames = (u128::from(edit.segment_duration) * u128::from(media_timescale)
+            + u128::from(movie_timescale / 2))
+            / u128::from(movie_timescale);
+
+        Some((edit.media_time as u64, u64::try_from(num_frames).unwrap_or(u64::MAX)))
+    }
 }
 
 impl Atom for ElstAtom {
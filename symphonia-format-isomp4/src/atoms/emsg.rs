@@ -0,0 +1,118 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+
+use crate::atoms::{Atom, AtomHeader};
+
+/// The presentation time of an event message.
+#[derive(Copy, Clone, Debug)]
+pub enum EmsgTime {
+    /// The presentation time relative to the earliest presentation time of the segment following
+    /// the event message (version 0).
+    Delta(u32),
+    /// The absolute presentation time (version 1).
+    Absolute(u64),
+}
+
+/// Event message atom (DASH).
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct EmsgAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The URI identifying the scheme of the message.
+    pub scheme_id_uri: String,
+    /// The value of the event, the meaning of which is defined by the scheme.
+    pub value: String,
+    /// The timescale, in ticks per second, of the presentation time and duration.
+    pub timescale: u32,
+    /// The presentation time.
+    pub presentation_time: EmsgTime,
+    /// The duration of the event, or `0xffff_ffff` if unknown.
+    pub event_duration: u32,
+    /// The identifier of the event.
+    pub id: u32,
+    /// The message data.
+    pub message_data: Box<[u8]>,
+}
+
+impl Atom for EmsgAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let (version, _) = AtomHeader::read_extra(reader)?;
+
+        if header.data_len < AtomHeader::EXTRA_DATA_SIZE {
+            return decode_error("isomp4: invalid emsg atom length");
+        }
+
+        let len = (header.data_len - AtomHeader::EXTRA_DATA_SIZE) as usize;
+        let body = reader.read_boxed_slice_exact(len)?;
+
+        let mut reader = BufReader::new(&body);
+
+        // The order of the fields depends on the version.
+        let (scheme_id_uri, value, timescale, time, duration, id) = match version {
+            0 => {
+                let scheme_id_uri = read_null_terminated_string(&mut reader)?;
+                let value = read_null_terminated_string(&mut reader)?;
+                let timescale = reader.read_be_u32()?;
+                let delta = reader.read_be_u32()?;
+                let duration = reader.read_be_u32()?;
+                let id = reader.read_be_u32()?;
+
+                (scheme_id_uri, value, timescale, EmsgTime::Delta(delta), duration, id)
+            }
+            1 => {
+                let timescale = reader.read_be_u32()?;
+                let time = EmsgTime::Absolute(reader.read_be_u64()?);
+                let duration = reader.read_be_u32()?;
+                let id = reader.read_be_u32()?;
+                let scheme_id_uri = read_null_terminated_string(&mut reader)?;
+                let value = read_null_terminated_string(&mut reader)?;
+
+                (scheme_id_uri, value, timescale, time, duration, id)
+            }
+            _ => return decode_error("isomp4: invalid emsg version"),
+        };
+
+        if timescale == 0 {
+            return decode_error("isomp4: emsg timescale is 0");
+        }
+
+        let message_data = reader.read_buf_bytes_available_ref().into();
+
+        Ok(EmsgAtom {
+            header,
+            scheme_id_uri,
+            value,
+            timescale,
+            presentation_time: time,
+            event_duration: duration,
+            id,
+            message_data,
+        })
+    }
+}
+
+/// Read a null-terminated UTF-8 string.
+fn read_null_terminated_string(reader: &mut BufReader<'_>) -> Result<String> {
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_byte()? {
+            0 => break,
+            byte => buf.push(byte),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
@@ -13,6 +13,7 @@ pub(crate) mod co64;
 pub(crate) mod ctts;
 pub(crate) mod edts;
 pub(crate) mod elst;
+pub(crate) mod emsg;
 pub(crate) mod esds;
 pub(crate) mod flac;
 pub(crate) mod ftyp;
@@ -38,6 +39,7 @@ pub(crate) mod stsd;
 pub(crate) mod stss;
 pub(crate) mod stsz;
 pub(crate) mod stts;
+pub(crate) mod tfdt;
 pub(crate) mod tfhd;
 pub(crate) mod tkhd;
 pub(crate) mod traf;
@@ -54,6 +56,7 @@ pub use co64::Co64Atom;
 pub use ctts::CttsAtom;
 pub use edts::EdtsAtom;
 pub use elst::ElstAtom;
+pub use emsg::{EmsgAtom, EmsgTime};
 pub use esds::EsdsAtom;
 pub use flac::FlacAtom;
 pub use ftyp::FtypAtom;
@@ -79,6 +82,7 @@ pub use stsd::StsdAtom;
 pub use stss::StssAtom;
 pub use stsz::StszAtom;
 pub use stts::SttsAtom;
+pub use tfdt::TfdtAtom;
 pub use tfhd::TfhdAtom;
 pub use tkhd::TkhdAtom;
 pub use traf::TrafAtom;
@@ -117,6 +121,7 @@ pub enum AtomType {
     EncodedByTag,
     EncoderTag,
     Esds,
+    EventMessage,
     F32SampleEntry,
     F64SampleEntry,
     FileType,
@@ -182,6 +187,7 @@ pub enum AtomType {
     Track,
     TrackExtends,
     TrackFragment,
+    TrackFragmentDecodeTime,
     TrackFragmentHeader,
     TrackFragmentRun,
     TrackHeader,
@@ -212,6 +218,7 @@ impl From<[u8; 4]> for AtomType {
             b"dOps" => AtomType::OpusDsConfig,
             b"edts" => AtomType::Edit,
             b"elst" => AtomType::EditList,
+            b"emsg" => AtomType::EventMessage,
             b"esds" => AtomType::Esds,
             b"fl32" => AtomType::F32SampleEntry,
             b"fl64" => AtomType::F64SampleEntry,
@@ -250,6 +257,7 @@ impl From<[u8; 4]> for AtomType {
             b"stss" => AtomType::SyncSample,
             b"stsz" => AtomType::SampleSize,
             b"stts" => AtomType::TimeToSample,
+            b"tfdt" => AtomType::TrackFragmentDecodeTime,
             b"tfhd" => AtomType::TrackFragmentHeader,
             b"tkhd" => AtomType::TrackHeader,
             b"traf" => AtomType::TrackFragment,
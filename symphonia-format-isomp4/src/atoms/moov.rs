@@ -10,7 +10,7 @@ use symphonia_core::io::ReadBytes;
 use symphonia_core::meta::MetadataRevision;
 
 use crate::atoms::{
-    Atom, AtomHeader, AtomIterator, AtomType, MvexAtom, MvhdAtom, TrakAtom, UdtaAtom,
+    Atom, AtomHeader, AtomIterator, AtomType, MvexAtom, MvhdAtom, TrakAtom, TrexAtom, UdtaAtom,
 };
 
 use log::warn;
@@ -79,15 +79,24 @@ impl Atom for MoovAtom {
             return decode_error("isomp4: missing mvhd atom");
         }
 
-        // If fragmented, the mvex atom should contain a trex atom for each trak atom in moov.
-        if let Some(mvex) = mvex.as_ref() {
+        // If fragmented, the mvex atom should contain a trex atom for each trak atom in moov. Order
+        // the trex atoms such that they match the order of the trak atoms.
+        if let Some(mvex) = mvex.as_mut() {
+            let mut trexs = std::mem::take(&mut mvex.trexs);
+
             // For each trak, find a matching trex atom using the track id.
             for trak in traks.iter() {
-                let found = mvex.trexs.iter().any(|trex| trex.track_id == trak.tkhd.id);
+                let trex = match trexs.iter().position(|trex| trex.track_id == trak.tkhd.id) {
+                    Some(idx) => trexs.remove(idx),
+                    None => {
+                        // Some initialization segments have an empty mvex atom. The track fragments
+                        // must then provide all the sample defaults.
+                        warn!("missing trex atom for trak with id={}", trak.tkhd.id);
+                        TrexAtom::new_empty(trak.tkhd.id)
+                    }
+                };
 
-                if !found {
-                    warn!("missing trex atom for trak with id={}", trak.tkhd.id);
-                }
+                mvex.trexs.push(trex);
             }
         }
 
@@ -40,6 +40,13 @@ pub struct SidxAtom {
     pub references: Vec<SidxReference>,
 }
 
+impl SidxAtom {
+    /// Get the total duration of all referenced subsegments in timescale units.
+    pub fn total_duration(&self) -> u64 {
+        self.references.iter().map(|r| u64::from(r.subsegment_duration)).sum()
+    }
+}
+
 impl Atom for SidxAtom {
     fn header(&self) -> AtomHeader {
         self.header
@@ -313,11 +313,8 @@ fn lpcm_channels(num_channels: u32) -> Result<Channels> {
         return unsupported_error("isomp4: maximum 32 channels");
     }
 
-    // TODO: For LPCM, the channels are "auxilary". They do not have a speaker assignment. Symphonia
-    // does not have a way to represent this yet.
-    let channel_mask = !((!0 << 1) << (num_channels - 1));
-
-    match Channels::from_bits(channel_mask) {
+    // For LPCM, the channels are "auxilary". They do not have a speaker assignment.
+    match Channels::discrete(num_channels as usize) {
         Some(channels) => Ok(channels),
         _ => unsupported_error("isomp4: unsupported number of channels"),
     }
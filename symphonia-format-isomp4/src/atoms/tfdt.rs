@@ -0,0 +1,38 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::Result;
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader};
+
+/// Track fragment decode time atom.
+#[derive(Debug)]
+pub struct TfdtAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The absolute decode time, in media timescale units, of the first sample in the track
+    /// fragment.
+    pub base_media_decode_time: u64,
+}
+
+impl Atom for TfdtAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let (version, _) = AtomHeader::read_extra(reader)?;
+
+        let base_media_decode_time = match version {
+            0 => u64::from(reader.read_be_u32()?),
+            _ => reader.read_be_u64()?,
+        };
+
+        Ok(TfdtAtom { header, base_media_decode_time })
+    }
+}
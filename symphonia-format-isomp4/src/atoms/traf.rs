@@ -8,7 +8,7 @@
 use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 
-use crate::atoms::{Atom, AtomHeader, AtomIterator, AtomType, TfhdAtom, TrunAtom};
+use crate::atoms::{Atom, AtomHeader, AtomIterator, AtomType, TfdtAtom, TfhdAtom, TrunAtom};
 
 /// Track fragment atom.
 #[derive(Debug)]
@@ -17,6 +17,8 @@ pub struct TrafAtom {
     header: AtomHeader,
     /// Track fragment header.
     pub tfhd: TfhdAtom,
+    /// Optional, track fragment decode time.
+    pub tfdt: Option<TfdtAtom>,
     /// Track fragment sample runs.
     pub truns: Vec<TrunAtom>,
     /// The total number of samples in this track fragment.
@@ -30,6 +32,7 @@ impl Atom for TrafAtom {
 
     fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
         let mut tfhd = None;
+        let mut tfdt = None;
         let mut truns = Vec::new();
 
         let mut iter = AtomIterator::new(reader, header);
@@ -41,6 +44,9 @@ impl Atom for TrafAtom {
                 AtomType::TrackFragmentHeader => {
                     tfhd = Some(iter.read_atom::<TfhdAtom>()?);
                 }
+                AtomType::TrackFragmentDecodeTime => {
+                    tfdt = Some(iter.read_atom::<TfdtAtom>()?);
+                }
                 AtomType::TrackFragmentRun => {
                     let trun = iter.read_atom::<TrunAtom>()?;
 
@@ -58,6 +64,6 @@ impl Atom for TrafAtom {
             return decode_error("isomp4: missing tfhd atom");
         }
 
-        Ok(TrafAtom { header, tfhd: tfhd.unwrap(), truns, total_sample_count })
+        Ok(TrafAtom { header, tfhd: tfhd.unwrap(), tfdt, truns, total_sample_count })
     }
 }
@@ -8,7 +8,7 @@
 use symphonia_core::errors::Result;
 use symphonia_core::io::ReadBytes;
 
-use crate::atoms::{Atom, AtomHeader};
+use crate::atoms::{Atom, AtomHeader, AtomType};
 
 /// Track extends atom.
 #[allow(dead_code)]
@@ -28,6 +28,21 @@ pub struct TrexAtom {
     pub default_sample_flags: u32,
 }
 
+impl TrexAtom {
+    /// Instantiate a track extends atom for the track identified by `track_id` where all the
+    /// default values are 0. The track fragments must then provide all sample defaults.
+    pub fn new_empty(track_id: u32) -> Self {
+        TrexAtom {
+            header: AtomHeader { atype: AtomType::TrackExtends, atom_len: 0, data_len: 0 },
+            track_id,
+            default_sample_desc_idx: 1,
+            default_sample_duration: 0,
+            default_sample_size: 0,
+            default_sample_flags: 0,
+        }
+    }
+}
+
 impl Atom for TrexAtom {
     fn header(&self) -> AtomHeader {
         self.header
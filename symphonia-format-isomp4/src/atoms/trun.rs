@@ -23,7 +23,8 @@ pub struct TrunAtom {
     pub data_offset: Option<i32>,
     /// Number of samples in this run.
     pub sample_count: u32,
-    /// Sample flags for the first sample only.
+    /// Sample flags for the first sample only. These only override the default sample flags, the
+    /// duration and size of the first sample are unaffected.
     pub first_sample_flags: Option<u32>,
     /// Sample duration for each sample in this run.
     pub sample_duration: Vec<u32>,
@@ -51,27 +52,11 @@ impl TrunAtom {
         self.flags & TrunAtom::SAMPLE_DURATION_PRESENT != 0
     }
 
-    // Indicates if the duration of the first sample is provided.
-    pub fn is_first_sample_duration_present(&self) -> bool {
-        match self.first_sample_flags {
-            Some(flags) => flags & TrunAtom::FIRST_SAMPLE_FLAGS_PRESENT != 0,
-            None => false,
-        }
-    }
-
     /// Indicates if sample sizes are provided.
     pub fn is_sample_size_present(&self) -> bool {
         self.flags & TrunAtom::SAMPLE_SIZE_PRESENT != 0
     }
 
-    /// Indicates if the size for the first sample is provided.
-    pub fn is_first_sample_size_present(&self) -> bool {
-        match self.first_sample_flags {
-            Some(flags) => flags & TrunAtom::SAMPLE_SIZE_PRESENT != 0,
-            None => false,
-        }
-    }
-
     /// Indicates if sample flags are provided.
     #[allow(dead_code)]
     pub fn are_sample_flags_present(&self) -> bool {
@@ -90,16 +75,8 @@ impl TrunAtom {
             self.total_sample_duration
         }
         else {
-            // The duration of all samples in the track fragment are not explictly known.
-            if self.sample_count > 0 && self.is_first_sample_duration_present() {
-                // The first sample has an explictly recorded duration.
-                u64::from(self.sample_duration[0])
-                    + u64::from(self.sample_count - 1) * u64::from(default_dur)
-            }
-            else {
-                // All samples have the default duration.
-                u64::from(self.sample_count) * u64::from(default_dur)
-            }
+            // All samples have the default duration.
+            u64::from(self.sample_count) * u64::from(default_dur)
         }
     }
 
@@ -108,10 +85,6 @@ impl TrunAtom {
         if self.is_sample_size_present() {
             self.total_sample_size
         }
-        else if self.sample_count > 0 && self.is_first_sample_size_present() {
-            u64::from(self.sample_size[0])
-                + u64::from(self.sample_count - 1) * u64::from(default_size)
-        }
         else {
             u64::from(self.sample_count) * u64::from(default_size)
         }
@@ -139,18 +112,8 @@ impl TrunAtom {
             (ts, dur)
         }
         else {
-            // The duration of all samples in the track fragment are not unique.
-            let ts = if sample_num_rel > 0 && self.is_first_sample_duration_present() {
-                // The first sample has a unique duration.
-                u64::from(self.sample_duration[0])
-                    + u64::from(sample_num_rel - 1) * u64::from(default_dur)
-            }
-            else {
-                // Zero or more samples with identical durations.
-                u64::from(sample_num_rel) * u64::from(default_dur)
-            };
-
-            (ts, default_dur)
+            // All samples have the default duration.
+            (u64::from(sample_num_rel) * u64::from(default_dur), default_dur)
         }
     }
 
@@ -162,9 +125,6 @@ impl TrunAtom {
         if self.is_sample_size_present() {
             self.sample_size[sample_num_rel as usize]
         }
-        else if sample_num_rel == 0 && self.is_first_sample_size_present() {
-            self.sample_size[0]
-        }
         else {
             default_size
         }
@@ -190,18 +150,8 @@ impl TrunAtom {
             (offset, self.sample_size[sample_num_rel as usize])
         }
         else {
-            // The size of all samples in the track are not unique.
-            let offset = if sample_num_rel > 0 && self.is_first_sample_size_present() {
-                // The first sample has a unique size.
-                u64::from(self.sample_size[0])
-                    + u64::from(sample_num_rel - 1) * u64::from(default_size)
-            }
-            else {
-                // Zero or more identically sized samples.
-                u64::from(sample_num_rel) * u64::from(default_size)
-            };
-
-            (offset, default_size)
+            // All samples have the default size.
+            (u64::from(sample_num_rel) * u64::from(default_size), default_size)
         }
     }
 
@@ -223,19 +173,7 @@ impl TrunAtom {
             }
         }
         else {
-            if self.sample_count > 0 && self.is_first_sample_duration_present() {
-                // The first sample duration is unique.
-                let first_sample_dur = u64::from(self.sample_duration[0]);
-
-                if ts_delta >= first_sample_dur {
-                    ts_delta -= first_sample_dur;
-                    sample_num += 1;
-                }
-                else {
-                    ts_delta -= ts_delta;
-                }
-            }
-
+            // All samples have the default duration.
             sample_num += ts_delta.checked_div(u64::from(default_dur)).unwrap_or(0) as u32;
         }
 
@@ -11,14 +11,17 @@ use symphonia_core::codecs::CodecParameters;
 use symphonia_core::errors::{decode_error, seek_error, unsupported_error, Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered};
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataLog, MetadataRevision, Value};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
-use symphonia_core::units::Time;
+use symphonia_core::units::{Time, TimeBase};
+use symphonia_metadata::itunes::{self, GaplessInfo};
 
+use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::io::{Seek, SeekFrom};
 use std::sync::Arc;
 
-use crate::atoms::{AtomIterator, AtomType};
+use crate::atoms::{AtomIterator, AtomType, EmsgAtom, EmsgTime};
 use crate::atoms::{FtypAtom, MetaAtom, MoofAtom, MoovAtom, MvexAtom, SidxAtom, TrakAtom};
 use crate::stream::*;
 
@@ -34,11 +37,17 @@ pub struct TrackState {
     next_sample: u32,
     /// The current sample byte position relative to the start of the track.
     next_sample_pos: u64,
+    /// The offset of the track relative to the start of the presentation, if known.
+    offset: Option<i64>,
+    /// The stereo balance of the track, if known.
+    balance: Option<f32>,
+    /// Gapless playback information derived from the edit list, if present.
+    edit_gapless: Option<GaplessInfo>,
 }
 
 impl TrackState {
     #[allow(clippy::single_match)]
-    pub fn new(track_num: usize, trak: &TrakAtom) -> Self {
+    pub fn new(track_num: usize, trak: &TrakAtom, movie_timescale: u32) -> Self {
         let mut codec_params = CodecParameters::new();
 
         codec_params
@@ -48,12 +57,81 @@ impl TrackState {
         // Fill the codec parameters using the sample description atom.
         trak.mdia.minf.stbl.stsd.fill_codec_params(&mut codec_params);
 
-        Self { codec_params, track_num, cur_seg: 0, next_sample: 0, next_sample_pos: 0 }
+        // Get the track offset from the edit list, if present.
+        let offset = trak
+            .edts
+            .as_ref()
+            .and_then(|edts| edts.elst.as_ref())
+            .and_then(|elst| elst.offset(movie_timescale, trak.mdia.mdhd.timescale));
+
+        // Get the encoder delay and number of frames from the edit list, if present. The padding is
+        // the remainder of the media.
+        let edit_gapless = trak
+            .edts
+            .as_ref()
+            .and_then(|edts| edts.elst.as_ref())
+            .and_then(|elst| elst.gapless(movie_timescale, trak.mdia.mdhd.timescale))
+            .and_then(|(delay, num_frames)| {
+                let padding = trak.mdia.mdhd.duration.saturating_sub(delay + num_frames);
+
+                Some(GaplessInfo {
+                    delay: u32::try_from(delay).ok()?,
+                    padding: u32::try_from(padding).ok()?,
+                    num_frames,
+                })
+            });
+
+        // Get the stereo balance from the sound media header, if present.
+        let balance = trak.mdia.minf.smhd.as_ref().map(|smhd| f32::from(smhd.balance));
+
+        Self {
+            codec_params,
+            track_num,
+            cur_seg: 0,
+            next_sample: 0,
+            next_sample_pos: 0,
+            offset,
+            balance,
+            edit_gapless,
+        }
     }
 
     pub fn codec_params(&self) -> CodecParameters {
         self.codec_params.clone()
     }
+
+    /// Apply the gapless information to the codec parameters of an audio track. Returns `true` if
+    /// the gapless information was applied.
+    fn apply_gapless_info(&mut self, gapless: &GaplessInfo) -> bool {
+        // The gapless information is in units of audio frames. Therefore, it is only applicable to
+        // audio tracks where the timebase is the sample rate.
+        match (self.codec_params.sample_rate, self.codec_params.time_base) {
+            (Some(sample_rate), Some(tb)) if tb.numer == 1 && tb.denom == sample_rate => (),
+            _ => return false,
+        }
+
+        self.codec_params
+            .with_delay(gapless.delay)
+            .with_padding(gapless.padding)
+            .with_n_frames(gapless.num_frames);
+
+        true
+    }
+
+    /// Gets the gapless delay of the track.
+    fn delay(&self) -> u64 {
+        u64::from(self.codec_params.delay.unwrap_or(0))
+    }
+}
+
+/// Try to find and parse the iTunes gapless information tag in a metadata revision.
+fn find_gapless_info(rev: &MetadataRevision) -> Option<GaplessInfo> {
+    let tag = rev.tags().iter().find(|tag| tag.key == "com.apple.iTunes:iTunSMPB")?;
+
+    match &tag.value {
+        Value::String(value) => itunes::parse_itunsmpb(value),
+        _ => None,
+    }
 }
 
 /// Information regarding the next sample.
@@ -80,6 +158,92 @@ struct SampleDataInfo {
     len: u32,
 }
 
+/// The span of the presentation covered by one or more segment indexes of a track.
+#[derive(Debug)]
+struct IndexSpan {
+    /// The track ID of the referenced track.
+    reference_id: u32,
+    /// The timescale, in ticks per second, of the timestamps.
+    timescale: u32,
+    /// The earliest presentation timestamp.
+    start_ts: u64,
+    /// The timestamp after the end of the last subsegment.
+    end_ts: u64,
+}
+
+impl IndexSpan {
+    fn new(sidx: &SidxAtom) -> Self {
+        IndexSpan {
+            reference_id: sidx.reference_id,
+            timescale: sidx.timescale,
+            start_ts: sidx.earliest_pts,
+            end_ts: sidx.earliest_pts.saturating_add(sidx.total_duration()),
+        }
+    }
+
+    /// Extend the span to include the subsegments of another segment index. Segment indexes of
+    /// other tracks are ignored.
+    fn extend(&mut self, sidx: &SidxAtom) {
+        if sidx.reference_id == self.reference_id && sidx.timescale == self.timescale {
+            self.start_ts = self.start_ts.min(sidx.earliest_pts);
+            self.end_ts = self.end_ts.max(sidx.earliest_pts.saturating_add(sidx.total_duration()));
+        }
+    }
+
+    /// Get the duration of the span, if known.
+    fn time(&self) -> Option<Time> {
+        match (self.timescale, self.end_ts - self.start_ts) {
+            (0, _) | (_, 0) => None,
+            (timescale, dur) => Some(TimeBase::new(1, timescale).calc_time(dur)),
+        }
+    }
+}
+
+/// A DASH event message carried in-band by a fragmented MP4.
+///
+/// Event messages carry timed metadata such as now-playing information or advertisement markers.
+/// The interpretation of the value and message data is defined by the scheme.
+#[derive(Clone, Debug)]
+pub struct EventMessage {
+    /// The URI identifying the scheme of the message.
+    pub scheme_id_uri: String,
+    /// The value of the event, the meaning of which is defined by the scheme.
+    pub value: String,
+    /// The timescale, in ticks per second, of the presentation time and duration.
+    pub timescale: u32,
+    /// The presentation time of the event in timescale units, relative to the start of the
+    /// presentation.
+    pub presentation_time: u64,
+    /// The duration of the event in timescale units, if known.
+    pub duration: Option<u32>,
+    /// The identifier of the event.
+    pub id: u32,
+    /// The message data.
+    pub message_data: Box<[u8]>,
+}
+
+impl EventMessage {
+    fn new(emsg: EmsgAtom, presentation_time: u64) -> Self {
+        // A duration of 0xffff_ffff indicates an unknown duration.
+        let duration = Some(emsg.event_duration).filter(|&dur| dur != 0xffff_ffff);
+
+        EventMessage {
+            scheme_id_uri: emsg.scheme_id_uri,
+            value: emsg.value,
+            timescale: emsg.timescale,
+            presentation_time,
+            duration,
+            id: emsg.id,
+            message_data: emsg.message_data,
+        }
+    }
+
+    /// Get the presentation time of the event.
+    pub fn time(&self) -> Time {
+        TimeBase::new(1, self.timescale).calc_time(self.presentation_time)
+    }
+}
+
 /// ISO Base Media File Format (MP4, M4A, MOV, etc.) demultiplexer.
 ///
 /// `IsoMp4Reader` implements a demuxer for the ISO Base Media File Format.
@@ -94,9 +258,57 @@ pub struct IsoMp4Reader {
     track_states: Vec<TrackState>,
     /// Optional, movie extends atom used for fragmented streams.
     mvex: Option<Arc<MvexAtom>>,
+    /// The duration of the movie, if known.
+    duration: Option<Time>,
+    /// Format options.
+    options: FormatOptions,
+    /// Event messages with a presentation time relative to the next segment.
+    pending_event_messages: Vec<EmsgAtom>,
+    /// Event messages ready to be returned.
+    event_messages: VecDeque<EventMessage>,
 }
 
 impl IsoMp4Reader {
+    /// Get the next event message, if any.
+    ///
+    /// Event messages are returned in the order they were read. Since messages are read as they
+    /// are encountered in the stream, a message is usually returned before the packets it is
+    /// presented with. The application should hold a message until playback reaches its
+    /// presentation time.
+    pub fn next_event_message(&mut self) -> Option<EventMessage> {
+        self.event_messages.pop_front()
+    }
+
+    fn push_event_message(&mut self, emsg: EmsgAtom) {
+        match emsg.presentation_time {
+            EmsgTime::Absolute(time) => {
+                self.event_messages.push_back(EventMessage::new(emsg, time));
+            }
+            EmsgTime::Delta(_) => self.pending_event_messages.push(emsg),
+        }
+    }
+
+    /// Resolve the presentation time of pending event messages relative to a segment starting at
+    /// the given timestamp of the first track.
+    fn resolve_event_messages(&mut self, seg_start_ts: u64) {
+        let tb = self.tracks.first().and_then(|track| track.codec_params.time_base);
+
+        for emsg in self.pending_event_messages.drain(..) {
+            let delta = match emsg.presentation_time {
+                EmsgTime::Delta(delta) => u64::from(delta),
+                EmsgTime::Absolute(_) => unreachable!(),
+            };
+
+            // Convert the segment start timestamp into the timescale of the event message.
+            let start = tb.map_or(0, |tb| {
+                let ticks = u128::from(seg_start_ts) * u128::from(tb.numer);
+                (ticks * u128::from(emsg.timescale) / u128::from(tb.denom)) as u64
+            });
+
+            self.event_messages.push_back(EventMessage::new(emsg, start + delta));
+        }
+    }
+
     /// Idempotently gets information regarding the next sample of the media stream. This function
     /// selects the next sample with the lowest timestamp of all tracks.
     fn next_sample_info(&self) -> Result<Option<NextSampleInfo>> {
@@ -210,6 +422,12 @@ impl IsoMp4Reader {
                             warn!("moof fragment has a non-monotonic sequence number.");
                         }
 
+                        // Event messages preceding the segment are relative to its start.
+                        if !self.pending_event_messages.is_empty() {
+                            let start = seg.track_ts_range(0).start;
+                            self.resolve_event_messages(start);
+                        }
+
                         // Push the segment.
                         self.segs.push(Box::new(seg));
                     }
@@ -218,6 +436,10 @@ impl IsoMp4Reader {
                         return decode_error("isomp4: moof atom present without mvex atom");
                     }
                 }
+                AtomType::EventMessage => {
+                    let emsg = self.iter.read_atom::<EmsgAtom>()?;
+                    self.push_event_message(emsg);
+                }
                 _ => {
                     trace!("skipping atom: {:?}.", header.atype);
                     self.iter.consume_atom();
@@ -248,6 +470,12 @@ impl IsoMp4Reader {
             sample_num: u32,
         }
 
+        // If gapless playback is enabled, the timestamp is relative to the end of the delay.
+        let delay =
+            if self.options.enable_gapless { self.track_states[track_num].delay() } else { 0 };
+
+        let ts = ts + delay;
+
         let mut seek_loc = None;
         let mut seg_skip = 0;
 
@@ -296,7 +524,11 @@ impl IsoMp4Reader {
                 timing.ts as i64 - ts as i64
             );
 
-            Ok(SeekedTo { track_id: track_num as u32, required_ts: ts, actual_ts: timing.ts })
+            Ok(SeekedTo {
+                track_id: track_num as u32,
+                required_ts: ts - delay,
+                actual_ts: timing.ts.saturating_sub(delay),
+            })
         }
         else {
             // Timestamp was not found.
@@ -322,7 +554,7 @@ impl QueryDescriptor for IsoMp4Reader {
 }
 
 impl FormatReader for IsoMp4Reader {
-    fn try_new(mut mss: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+    fn try_new(mut mss: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
         // To get to beginning of the atom.
         mss.seek_buffered_rel(-4);
 
@@ -332,6 +564,9 @@ impl FormatReader for IsoMp4Reader {
         let mut moov = None;
         let mut sidx = None;
 
+        // The span of the presentation covered by the segment indexes, if any.
+        let mut index_span: Option<IndexSpan> = None;
+
         // Get the total length of the stream, if possible.
         let total_len = if is_seekable {
             let pos = mss.pos();
@@ -345,6 +580,12 @@ impl FormatReader for IsoMp4Reader {
         };
 
         let mut metadata = MetadataLog::default();
+        let mut gapless = None;
+
+        // Event messages preceding the first segment. Later event messages are read with their
+        // segment.
+        let mut event_messages = Vec::new();
+        let mut found_media = false;
 
         // Parse all atoms if the stream is seekable, otherwise parse all atoms up-to the mdat atom.
         let mut iter = AtomIterator::new_root(mss, total_len);
@@ -359,18 +600,23 @@ impl FormatReader for IsoMp4Reader {
                     moov = Some(iter.read_atom::<MoovAtom>()?);
                 }
                 AtomType::SegmentIndex => {
+                    let new_sidx = iter.read_atom::<SidxAtom>()?;
+
+                    match &mut index_span {
+                        Some(span) => span.extend(&new_sidx),
+                        None => index_span = Some(IndexSpan::new(&new_sidx)),
+                    }
+
                     // If the stream is not seekable, then it can only be assumed that the first
                     // segment index atom is indeed the first segment index because the format
                     // reader cannot practically skip past this point.
                     if !is_seekable {
-                        sidx = Some(iter.read_atom::<SidxAtom>()?);
+                        sidx = Some(new_sidx);
                         break;
                     }
                     else {
                         // If the stream is seekable, examine all segment indexes and select the
                         // index with the earliest presentation timestamp to be the first.
-                        let new_sidx = iter.read_atom::<SidxAtom>()?;
-
                         let is_earlier = match &sidx {
                             Some(sidx) => new_sidx.earliest_pts < sidx.earliest_pts,
                             _ => true,
@@ -382,6 +628,8 @@ impl FormatReader for IsoMp4Reader {
                     }
                 }
                 AtomType::MediaData | AtomType::MovieFragment => {
+                    found_media = true;
+
                     // The mdat atom contains the codec bitstream data. For segmented streams, a
                     // moof + mdat pair is required for playback. If the source is unseekable then
                     // the format reader cannot skip past these atoms without dropping samples.
@@ -401,9 +649,13 @@ impl FormatReader for IsoMp4Reader {
                     let mut meta = iter.read_atom::<MetaAtom>()?;
 
                     if let Some(rev) = meta.take_metadata() {
+                        gapless = gapless.or_else(|| find_gapless_info(&rev));
                         metadata.push(rev);
                     }
                 }
+                AtomType::EventMessage if !found_media => {
+                    event_messages.push(iter.read_atom::<EmsgAtom>()?);
+                }
                 AtomType::Free => (),
                 AtomType::Skip => (),
                 _ => {
@@ -451,37 +703,103 @@ impl FormatReader for IsoMp4Reader {
         }
 
         if let Some(rev) = moov.take_metadata() {
+            gapless = gapless.or_else(|| find_gapless_info(&rev));
             metadata.push(rev);
         }
 
+        // Get the duration of the movie. For fragmented streams, the movie header only states the
+        // duration of the samples in the moov atom, so prefer the movie extends header if present.
+        let movie_dur = match moov.mvex.as_ref().and_then(|mvex| mvex.mehd.as_ref()) {
+            Some(mehd) => mehd.fragment_duration,
+            None => moov.mvhd.duration,
+        };
+
+        // A duration of 0 or all 1s indicates the duration is unknown.
+        let duration = match (moov.mvhd.timescale, movie_dur) {
+            (0, _) | (_, 0) | (_, u64::MAX) => None,
+            (timescale, dur) => Some(TimeBase::new(1, timescale).calc_time(dur)),
+        };
+
+        // The initialization segment of a DASH or CMAF presentation usually does not state the
+        // duration of the presentation. If so, fallback to the span of the segment indexes.
+        let duration = duration.or_else(|| index_span.as_ref().and_then(|span| span.time()));
+
         // Instantiate a TrackState for each track in the stream.
-        let track_states = moov
+        let mut track_states = moov
             .traks
             .iter()
             .enumerate()
-            .map(|(t, trak)| TrackState::new(t, trak))
+            .map(|(t, trak)| TrackState::new(t, trak, moov.mvhd.timescale))
             .collect::<Vec<TrackState>>();
 
+        // Likewise, the media header of a fragmented track usually does not state the duration of
+        // the track. If so, and a segment index references the track, use the span of the index.
+        if let Some(span) = &index_span {
+            for (track, trak) in track_states.iter_mut().zip(moov.traks.iter()) {
+                let mdhd = &trak.mdia.mdhd;
+
+                if mdhd.duration == 0
+                    && trak.tkhd.id == span.reference_id
+                    && mdhd.timescale == span.timescale
+                {
+                    track.codec_params.with_n_frames(span.end_ts - span.start_ts);
+                }
+            }
+        }
+
+        // If gapless playback is enabled, apply the iTunes gapless information, if found.
+        // Otherwise, apply the gapless information from the edit list of each track, if found.
+        if options.enable_gapless {
+            if let Some(gapless) = &gapless {
+                debug!("applying gapless info: {:?}", gapless);
+
+                for state in track_states.iter_mut() {
+                    state.apply_gapless_info(gapless);
+                }
+            }
+            else {
+                for state in track_states.iter_mut() {
+                    if let Some(gapless) = state.edit_gapless {
+                        debug!("applying edit list gapless info: {:?}", gapless);
+
+                        if state.apply_gapless_info(&gapless) {
+                            // The encoder delay is now trimmed from the packets. Only the leading
+                            // empty edits remain as an offset.
+                            state.offset =
+                                state.offset.map(|offset| offset + i64::from(gapless.delay));
+                        }
+                    }
+                }
+            }
+        }
+
         // Instantiate a Tracks for all tracks above.
         let tracks = track_states
             .iter()
-            .map(|track| Track::new(track.track_num as u32, track.codec_params()))
+            .map(|track| {
+                let mut t = Track::new(track.track_num as u32, track.codec_params());
+
+                if let Some(offset) = track.offset {
+                    t.with_offset(offset);
+                }
+
+                if let Some(balance) = track.balance {
+                    t.with_balance(balance);
+                }
+
+                t
+            })
             .collect();
 
         // A Movie Extends (mvex) atom is required to support segmented streams. If the mvex atom is
         // present, wrap it in an Arc so it can be shared amongst all segments.
         let mvex = moov.mvex.take().map(Arc::new);
 
-        // The number of tracks specified in the moov atom must match the number in the mvex atom.
-        if let Some(mvex) = &mvex {
-            if mvex.trexs.len() != moov.traks.len() {
-                return decode_error("isomp4: mvex and moov track number mismatch");
-            }
-        }
+        let is_fragmented = mvex.is_some();
 
         let segs: Vec<Box<dyn StreamSegment>> = vec![Box::new(MoovSegment::new(moov))];
 
-        Ok(IsoMp4Reader {
+        let mut reader = IsoMp4Reader {
             iter,
             tracks,
             cues: Default::default(),
@@ -489,7 +807,23 @@ impl FormatReader for IsoMp4Reader {
             track_states,
             segs,
             mvex,
-        })
+            duration,
+            options: *options,
+            pending_event_messages: Default::default(),
+            event_messages: Default::default(),
+        };
+
+        for emsg in event_messages {
+            reader.push_event_message(emsg);
+        }
+
+        // If the stream is not fragmented there is no following segment. Event messages are then
+        // relative to the start of the presentation.
+        if !is_fragmented {
+            reader.resolve_event_messages(0);
+        }
+
+        Ok(reader)
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
@@ -531,12 +865,22 @@ impl FormatReader for IsoMp4Reader {
             }
         }
 
-        Ok(Packet::new_from_boxed_slice(
+        let mut packet = Packet::new_from_boxed_slice(
             next_sample_info.track_num as u32,
             next_sample_info.ts,
             u64::from(next_sample_info.dur),
             reader.read_boxed_slice_exact(sample_info.len as usize)?,
-        ))
+        );
+
+        if self.options.enable_gapless {
+            let params = &self.track_states[next_sample_info.track_num].codec_params;
+
+            if let Some(delay) = params.delay {
+                symphonia_core::formats::util::trim_packet(&mut packet, delay, params.n_frames);
+            }
+        }
+
+        Ok(packet)
     }
 
     fn metadata(&mut self) -> Metadata<'_> {
@@ -551,6 +895,10 @@ impl FormatReader for IsoMp4Reader {
         &self.tracks
     }
 
+    fn duration(&self) -> Option<Time> {
+        self.duration
+    }
+
     fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
         if self.tracks.is_empty() {
             return seek_error(SeekErrorKind::Unseekable);
@@ -602,3 +950,597 @@ impl FormatReader for IsoMp4Reader {
         self.iter.into_inner()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::codecs::CODEC_TYPE_AAC;
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSourceStream, ReadBytes};
+    use symphonia_core::meta::StandardTagKey;
+    use symphonia_core::units::TimeBase;
+    use symphonia_metadata::replaygain::ReplayGain;
+
+    use super::IsoMp4Reader;
+
+    fn atom(atype: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(8 + body.len() as u32).to_be_bytes());
+        buf.extend_from_slice(atype);
+        buf.extend_from_slice(body);
+        buf
+    }
+
+    fn full_atom(atype: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        atom(atype, &[&[0, 0, 0, 0], body].concat())
+    }
+
+    fn be_u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes().to_vec()).collect()
+    }
+
+    /// Builds a sample description atom with a single MP3 audio sample entry, version 0, 2
+    /// channels, and 16-bit.
+    fn mp3_stsd(sample_rate: u32) -> Vec<u8> {
+        let mut entry = vec![0; 6];
+        entry.extend_from_slice(&1u16.to_be_bytes());
+        entry.extend_from_slice(&[0; 8]);
+        entry.extend_from_slice(&2u16.to_be_bytes());
+        entry.extend_from_slice(&16u16.to_be_bytes());
+        entry.extend_from_slice(&[0; 4]);
+        entry.extend_from_slice(&(sample_rate << 16).to_be_bytes());
+
+        full_atom(b"stsd", &[&be_u32s(&[1])[..], &atom(b".mp3", &entry)].concat())
+    }
+
+    /// Builds a fragmented MP3-in-MP4 file with one 44.1kHz audio track. Each fragment contains
+    /// `samples_per_frag` samples of 1152 frames each, and a track fragment decode time atom with
+    /// the given base media decode time.
+    fn build_fragmented_mp4(samples_per_frag: u32, decode_times: &[u64]) -> Vec<u8> {
+        const SAMPLE_RATE: u32 = 44100;
+        const SAMPLE_DUR: u32 = 1152;
+        const SAMPLE_SIZE: u32 = 4;
+
+        let ftyp = atom(b"ftyp", b"iso6\0\0\0\0iso6mp41");
+
+        // The sample tables in the moov atom are empty, all samples are in the fragments.
+        let stbl = atom(
+            b"stbl",
+            &[
+                mp3_stsd(SAMPLE_RATE),
+                full_atom(b"stts", &be_u32s(&[0])),
+                full_atom(b"stsc", &be_u32s(&[0])),
+                full_atom(b"stsz", &be_u32s(&[0, 0])),
+                full_atom(b"stco", &be_u32s(&[0])),
+            ]
+            .concat(),
+        );
+
+        let mdhd_body = be_u32s(&[0, 0, SAMPLE_RATE, 0, 0]);
+        let hdlr_body = [&b"\0\0\0\0soun"[..], &[0; 12]].concat();
+        let tkhd_body = be_u32s(&[0, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        let mdia = atom(
+            b"mdia",
+            &[full_atom(b"mdhd", &mdhd_body), full_atom(b"hdlr", &hdlr_body), atom(b"minf", &stbl)]
+                .concat(),
+        );
+        let trak = atom(b"trak", &[full_atom(b"tkhd", &tkhd_body), mdia].concat());
+        let trex = full_atom(b"trex", &be_u32s(&[1, 1, SAMPLE_DUR, SAMPLE_SIZE, 0]));
+        let mvhd = full_atom(b"mvhd", &be_u32s(&[0, 0, 1000, 0, 0, 0]));
+
+        // The duration of all fragments in milliseconds.
+        let end_time =
+            decode_times.last().map_or(0, |&time| time) + u64::from(samples_per_frag * SAMPLE_DUR);
+        let mehd =
+            full_atom(b"mehd", &be_u32s(&[(end_time * 1000 / u64::from(SAMPLE_RATE)) as u32]));
+
+        let moov = atom(b"moov", &[mvhd, trak, atom(b"mvex", &[mehd, trex].concat())].concat());
+
+        let mut buf = [ftyp, moov].concat();
+
+        for (seq, &decode_time) in decode_times.iter().enumerate() {
+            let build_moof = |data_offset: u32| {
+                // Version 1 tfdt atom with a 64-bit base media decode time.
+                let tfdt_body = [&be_u32s(&[1 << 24])[..], &decode_time.to_be_bytes()].concat();
+                let tfdt = atom(b"tfdt", &tfdt_body);
+                // The trun atom has the data-offset-present flag set.
+                let trun = atom(b"trun", &be_u32s(&[0x1, samples_per_frag, data_offset]));
+                let tfhd = full_atom(b"tfhd", &be_u32s(&[1]));
+                let traf = atom(b"traf", &[tfhd, tfdt, trun].concat());
+                atom(b"moof", &[full_atom(b"mfhd", &be_u32s(&[seq as u32 + 1])), traf].concat())
+            };
+
+            // The data offset is relative to the start of the moof atom, and the sample data
+            // immediately follows the mdat atom header.
+            let moof = build_moof(build_moof(0).len() as u32 + 8);
+
+            let mdat_body: Vec<u8> =
+                (0..samples_per_frag).flat_map(|i| i.to_be_bytes().to_vec()).collect();
+
+            buf.extend(moof);
+            buf.extend(atom(b"mdat", &mdat_body));
+        }
+
+        buf
+    }
+
+    /// Builds a sample description atom with a single AAC-LC audio sample entry, 2 channels, and
+    /// 16-bit.
+    fn aac_stsd(sample_rate: u32) -> Vec<u8> {
+        let mut entry = vec![0; 6];
+        entry.extend_from_slice(&1u16.to_be_bytes());
+        entry.extend_from_slice(&[0; 8]);
+        entry.extend_from_slice(&2u16.to_be_bytes());
+        entry.extend_from_slice(&16u16.to_be_bytes());
+        entry.extend_from_slice(&[0; 4]);
+        entry.extend_from_slice(&(sample_rate << 16).to_be_bytes());
+
+        // An ES descriptor containing a decoder config descriptor with an AudioSpecificConfig for
+        // 44.1kHz stereo AAC-LC, and a SL config descriptor.
+        #[rustfmt::skip]
+        let es = [
+            0x03, 25, 0x00, 0x01, 0x00,
+            0x04, 17, 0x40, 0x15, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0x05, 2, 0x12, 0x10,
+            0x06, 1, 0x02,
+        ];
+        entry.extend(full_atom(b"esds", &es));
+
+        full_atom(b"stsd", &[&be_u32s(&[1])[..], &atom(b"mp4a", &entry)].concat())
+    }
+
+    /// Builds a CMAF-style AAC-in-MP4 file with one 44.1kHz audio track. The initialization
+    /// segment has an empty mvex atom, and is followed by one media segment per entry in
+    /// `frag_sizes`. Each media segment has a segment index, and one fragment with a sample of 1024
+    /// frames for each given sample size.
+    fn build_cmaf_mp4(frag_sizes: &[&[u32]]) -> Vec<u8> {
+        const SAMPLE_RATE: u32 = 44100;
+        const SAMPLE_DUR: u32 = 1024;
+
+        let ftyp = atom(b"ftyp", b"cmfc\0\0\0\0iso6cmfc");
+
+        let stbl = atom(
+            b"stbl",
+            &[
+                aac_stsd(SAMPLE_RATE),
+                full_atom(b"stts", &be_u32s(&[0])),
+                full_atom(b"stsc", &be_u32s(&[0])),
+                full_atom(b"stsz", &be_u32s(&[0, 0])),
+                full_atom(b"stco", &be_u32s(&[0])),
+            ]
+            .concat(),
+        );
+
+        let mdhd_body = be_u32s(&[0, 0, SAMPLE_RATE, 0, 0]);
+        let hdlr_body = [&b"\0\0\0\0soun"[..], &[0; 12]].concat();
+        let tkhd_body = be_u32s(&[0, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        let mdia = atom(
+            b"mdia",
+            &[full_atom(b"mdhd", &mdhd_body), full_atom(b"hdlr", &hdlr_body), atom(b"minf", &stbl)]
+                .concat(),
+        );
+        let trak = atom(b"trak", &[full_atom(b"tkhd", &tkhd_body), mdia].concat());
+        let mvhd = full_atom(b"mvhd", &be_u32s(&[0, 0, 1000, 0, 0, 0]));
+
+        let moov = atom(b"moov", &[mvhd, trak, atom(b"mvex", &[])].concat());
+
+        let mut buf = [ftyp, moov].concat();
+
+        let mut decode_time = 0;
+        let mut sample_value = 0u8;
+
+        for (seq, sizes) in frag_sizes.iter().enumerate() {
+            let build_moof = |data_offset: u32| {
+                let tfdt = full_atom(b"tfdt", &be_u32s(&[decode_time]));
+                // The trun atom has the data-offset, first-sample-flags, and sample-size present
+                // flags set. The first sample flags do not affect the duration or size of the first
+                // sample.
+                let mut trun_body = be_u32s(&[0x205, sizes.len() as u32, data_offset, 0x0200_0204]);
+                trun_body.extend(be_u32s(sizes));
+                let trun = atom(b"trun", &trun_body);
+                // The tfhd atom has the default-base-is-moof, and default-sample-duration present
+                // flags set.
+                let tfhd = atom(b"tfhd", &be_u32s(&[0x2_0008, 1, SAMPLE_DUR]));
+                let traf = atom(b"traf", &[tfhd, tfdt, trun].concat());
+                atom(b"moof", &[full_atom(b"mfhd", &be_u32s(&[seq as u32 + 1])), traf].concat())
+            };
+
+            let moof = build_moof(build_moof(0).len() as u32 + 8);
+
+            let mut mdat_body = Vec::new();
+
+            for &size in sizes.iter() {
+                mdat_body.extend(std::iter::repeat(sample_value).take(size as usize));
+                sample_value += 1;
+            }
+
+            let mdat = atom(b"mdat", &mdat_body);
+
+            // A version 0 segment index with a single reference to the fragment.
+            let frag_dur = SAMPLE_DUR * sizes.len() as u32;
+            let frag_size = (moof.len() + mdat.len()) as u32;
+            let sidx_body = be_u32s(&[1, SAMPLE_RATE, decode_time, 0, 1, frag_size, frag_dur, 0]);
+
+            buf.extend(atom(b"styp", b"msdh\0\0\0\0msdhmsix"));
+            buf.extend(full_atom(b"sidx", &sidx_body));
+            buf.extend(moof);
+            buf.extend(mdat);
+
+            decode_time += frag_dur;
+        }
+
+        buf
+    }
+
+    /// Builds a MP3-in-MP4 file with one 44.1kHz audio track containing `num_samples` samples of
+    /// 1152 frames each, an optional iTunes free-form tag, and an optional edit list with entries
+    /// of segment duration (in milliseconds) and media time.
+    fn build_mp4(
+        num_samples: u32,
+        freeform: Option<(&str, &str)>,
+        edits: &[(u32, i32)],
+    ) -> Vec<u8> {
+        const SAMPLE_RATE: u32 = 44100;
+        const SAMPLE_DUR: u32 = 1152;
+        const MOVIE_TIMESCALE: u32 = 1000;
+
+        let ftyp = atom(b"ftyp", b"M4A \0\0\0\0M4A mp42");
+
+        let stsd = mp3_stsd(SAMPLE_RATE);
+        let stts = full_atom(b"stts", &be_u32s(&[1, num_samples, SAMPLE_DUR]));
+        let stsc = full_atom(b"stsc", &be_u32s(&[1, 1, num_samples, 1]));
+        let stsz = full_atom(b"stsz", &be_u32s(&[4, num_samples]));
+
+        let mdhd_body = be_u32s(&[0, 0, SAMPLE_RATE, num_samples * SAMPLE_DUR, 0]);
+        let hdlr_body = [&b"\0\0\0\0soun"[..], &[0; 12]].concat();
+        let tkhd_body = be_u32s(&[0, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        // The movie duration is the total duration of the edits, or of the media if there are no
+        // edits.
+        let movie_dur = if edits.is_empty() {
+            num_samples * SAMPLE_DUR * MOVIE_TIMESCALE / SAMPLE_RATE
+        }
+        else {
+            edits.iter().map(|&(segment_duration, _)| segment_duration).sum()
+        };
+
+        let build_moov = |chunk_offset: u32| {
+            let stco = full_atom(b"stco", &be_u32s(&[1, chunk_offset]));
+            let stbl = atom(b"stbl", &[&stsd[..], &stts, &stsc, &stsz, &stco].concat());
+            let minf = atom(b"minf", &[full_atom(b"smhd", &[0; 4]), stbl].concat());
+            let mdia = atom(
+                b"mdia",
+                &[full_atom(b"mdhd", &mdhd_body), full_atom(b"hdlr", &hdlr_body), minf].concat(),
+            );
+            let edts = if edits.is_empty() {
+                Vec::new()
+            }
+            else {
+                let mut elst_body = be_u32s(&[edits.len() as u32]);
+                for &(segment_duration, media_time) in edits {
+                    elst_body.extend(be_u32s(&[segment_duration, media_time as u32, 1 << 16]));
+                }
+                atom(b"edts", &full_atom(b"elst", &elst_body))
+            };
+            let trak = atom(b"trak", &[full_atom(b"tkhd", &tkhd_body), edts, mdia].concat());
+            let mvhd = full_atom(b"mvhd", &be_u32s(&[0, 0, MOVIE_TIMESCALE, movie_dur, 0, 0]));
+
+            let udta = match freeform {
+                Some((name, value)) => {
+                    let tag = atom(
+                        b"----",
+                        &[
+                            full_atom(b"mean", b"com.apple.iTunes"),
+                            full_atom(b"name", name.as_bytes()),
+                            atom(b"data", &[&be_u32s(&[1, 0])[..], value.as_bytes()].concat()),
+                        ]
+                        .concat(),
+                    );
+                    atom(b"udta", &full_atom(b"meta", &atom(b"ilst", &tag)))
+                }
+                None => Vec::new(),
+            };
+
+            atom(b"moov", &[mvhd, trak, udta].concat())
+        };
+
+        // The chunk offset depends on the length of the moov atom, which is independent of the
+        // offset value itself.
+        let chunk_offset = (ftyp.len() + build_moov(0).len() + 8) as u32;
+
+        let mdat_body: Vec<u8> = (0..num_samples).flat_map(|i| i.to_be_bytes().to_vec()).collect();
+
+        [ftyp, build_moov(chunk_offset), atom(b"mdat", &mdat_body)].concat()
+    }
+
+    fn open_mp4(buf: Vec<u8>, enable_gapless: bool) -> IsoMp4Reader {
+        let mss = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        let mut options = FormatOptions::default();
+        options.with_gapless(enable_gapless);
+
+        // The format reader expects the probe to have read the first 4 bytes.
+        let mut mss = mss;
+        mss.read_quad_bytes().unwrap();
+
+        IsoMp4Reader::try_new(mss, &options).unwrap()
+    }
+
+    const ITUNSMPB: &str = " 00000000 00000840 000001C0 0000000000000800";
+
+    #[test]
+    fn verify_itunsmpb_gapless() {
+        let mut reader = open_mp4(build_mp4(4, Some(("iTunSMPB", ITUNSMPB)), &[]), true);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.delay, Some(2112));
+        assert_eq!(params.padding, Some(448));
+        assert_eq!(params.n_frames, Some(2048));
+
+        // The delay spans the first packet and part of the second, and the padding is at the end of
+        // the last packet.
+        let expected = [(0, 0, 1152, 0), (0, 192, 960, 0), (192, 1152, 0, 0), (1344, 704, 0, 448)];
+
+        for &(ts, dur, trim_start, trim_end) in expected.iter() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!((packet.ts, packet.dur), (ts, dur));
+            assert_eq!((packet.trim_start(), packet.trim_end()), (trim_start, trim_end));
+        }
+
+        assert!(reader.next_packet().is_err());
+
+        // Seeking is relative to the end of the delay.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1000, track_id: 0 });
+        let seeked = seeked.unwrap();
+        assert_eq!((seeked.required_ts, seeked.actual_ts), (1000, 192));
+
+        assert_eq!(reader.next_packet().unwrap().ts, 192);
+    }
+
+    #[test]
+    fn verify_itunsmpb_gapless_disabled() {
+        let mut reader = open_mp4(build_mp4(4, Some(("iTunSMPB", ITUNSMPB)), &[]), false);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.delay, None);
+        assert_eq!(params.n_frames, Some(4 * 1152));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.trim_start()), (0, 1152, 0));
+
+        // The raw free-form tag is always available as metadata.
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert!(tags.iter().any(|tag| tag.key == "com.apple.iTunes:iTunSMPB"));
+    }
+
+    #[test]
+    fn verify_itunnorm_replaygain() {
+        const ITUNNORM: &str = " 00000FA0 000003E8 00000000 00000000 00000000 00000000 \
+                                00004000 00002000 00000000 00000000";
+
+        let mut reader = open_mp4(build_mp4(4, Some(("iTunNORM", ITUNNORM)), &[]), false);
+
+        let metadata = reader.metadata();
+        let rg = ReplayGain::from_tags(metadata.current().unwrap().tags()).unwrap();
+
+        assert!((rg.track_gain.unwrap() - -6.0206).abs() < 1e-4);
+        assert_eq!(rg.track_peak, Some(0.5));
+        assert_eq!(rg.album_gain, None);
+    }
+
+    #[test]
+    fn verify_freeform_replaygain() {
+        let freeform = Some(("replaygain_track_gain", "-3.25 dB"));
+        let mut reader = open_mp4(build_mp4(4, freeform, &[]), false);
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::ReplayGainTrackGain));
+
+        let rg = ReplayGain::from_tags(tags).unwrap();
+        assert_eq!(rg.track_gain, Some(-3.25));
+        assert_eq!(rg.track_peak, None);
+    }
+
+    #[test]
+    fn verify_fragment_decode_time() {
+        // The first fragment starts part-way through the stream, and the second fragment
+        // continues where the first ended.
+        let mut reader = open_mp4(build_fragmented_mp4(2, &[11520, 13824]), false);
+
+        let mut timestamps = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            assert_eq!(packet.dur, 1152);
+            timestamps.push(packet.ts);
+        }
+
+        assert_eq!(timestamps, &[11520, 12672, 13824, 14976]);
+
+        // Seeking to a timestamp before the first fragment starts at the first sample.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 11520);
+
+        // Seeking to a timestamp in the second fragment.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 14000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 13824);
+        assert_eq!(reader.next_packet().unwrap().ts, 13824);
+    }
+
+    #[test]
+    fn verify_movie_duration() {
+        let movie_time = |ms: u64| Some(TimeBase::new(1, 1000).calc_time(ms));
+
+        // The duration of the movie is stated by the movie header.
+        let reader = open_mp4(build_mp4(4, None, &[]), false);
+        assert_eq!(reader.duration(), movie_time(104));
+
+        // The stated duration of the movie is that of the edits, not the media of the track.
+        let reader = open_mp4(build_mp4(4, None, &[(500, -1), (100, 1024)]), false);
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(4 * 1152));
+        assert_eq!(reader.duration(), movie_time(600));
+
+        // For a fragmented movie, the duration is stated by the movie extends header.
+        let reader = open_mp4(build_fragmented_mp4(2, &[0, 2304]), false);
+        assert_eq!(reader.duration(), movie_time(104));
+    }
+
+    #[test]
+    fn verify_edit_list_offset() {
+        // No edit list.
+        let reader = open_mp4(build_mp4(4, None, &[]), false);
+        assert_eq!(reader.tracks()[0].offset(), None);
+
+        // The first 1024 frames of the media are skipped.
+        let reader = open_mp4(build_mp4(4, None, &[(100, 1024)]), false);
+        assert_eq!(reader.tracks()[0].offset(), Some(-1024));
+
+        // The media is delayed by an empty edit of 500ms (22050 frames), and the first 1024 frames
+        // of the media are skipped.
+        let reader = open_mp4(build_mp4(4, None, &[(500, -1), (100, 1024)]), false);
+        assert_eq!(reader.tracks()[0].offset(), Some(22050 - 1024));
+
+        // The offset does not change the packet timestamps.
+        let mut reader = reader;
+        assert_eq!(reader.next_packet().unwrap().ts, 0);
+    }
+
+    #[test]
+    fn verify_edit_list_gapless() {
+        // The media is delayed by an empty edit of 500ms (22050 frames), the first 2112 frames of
+        // the media are the encoder delay, and 50ms (2205 frames) of the media are presented.
+        let edits = [(500, -1), (50, 2112)];
+
+        let mut reader = open_mp4(build_mp4(4, None, &edits), true);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.delay, Some(2112));
+        assert_eq!(params.padding, Some(4 * 1152 - 2112 - 2205));
+        assert_eq!(params.n_frames, Some(2205));
+
+        // The encoder delay is trimmed from the packets, so only the empty edit offsets the track.
+        assert_eq!(reader.tracks()[0].offset(), Some(22050));
+
+        let expected = [(0, 0, 1152, 0), (0, 192, 960, 0), (192, 1152, 0, 0), (1344, 861, 0, 291)];
+
+        for &(ts, dur, trim_start, trim_end) in expected.iter() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!((packet.ts, packet.dur), (ts, dur));
+            assert_eq!((packet.trim_start(), packet.trim_end()), (trim_start, trim_end));
+        }
+
+        // The iTunes gapless information takes precedence over the edit list.
+        let reader = open_mp4(build_mp4(4, Some(("iTunSMPB", ITUNSMPB)), &edits), true);
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(2048));
+        assert_eq!(reader.tracks()[0].offset(), Some(22050 - 2112));
+
+        // Edit lists with multiple non-empty edits are not used for gapless playback.
+        let reader = open_mp4(build_mp4(4, None, &[(20, 2112), (20, 0)]), true);
+        assert_eq!(reader.tracks()[0].codec_params.delay, None);
+        assert_eq!(reader.tracks()[0].offset(), Some(-2112));
+
+        // Edit lists are not used if gapless playback is disabled.
+        let reader = open_mp4(build_mp4(4, None, &edits), false);
+        assert_eq!(reader.tracks()[0].codec_params.delay, None);
+        assert_eq!(reader.tracks()[0].offset(), Some(22050 - 2112));
+    }
+
+    #[test]
+    fn verify_smhd_balance() {
+        let mut buf = build_mp4(4, None, &[]);
+
+        let reader = open_mp4(buf.clone(), false);
+        assert_eq!(reader.tracks()[0].balance(), Some(0.0));
+
+        // Set the balance, a signed 8.8 fixed point value following the version and flags, to
+        // -0.5.
+        let pos = buf.windows(4).position(|w| w == b"smhd").unwrap() + 8;
+        buf[pos..pos + 2].copy_from_slice(&(-128i16).to_be_bytes());
+
+        let reader = open_mp4(buf, false);
+        assert_eq!(reader.tracks()[0].balance(), Some(-0.5));
+    }
+
+    #[test]
+    fn verify_event_messages() {
+        let mut buf = build_fragmented_mp4(2, &[44100, 88200]);
+
+        let moofs: Vec<usize> =
+            buf.windows(4).enumerate().filter(|(_, w)| w == b"moof").map(|(i, _)| i - 4).collect();
+
+        // A version 1 emsg with an absolute presentation time of 2.5s before the second fragment.
+        let v1_body = [
+            &[1, 0, 0, 0][..],
+            &be_u32s(&[1000]),
+            &2500u64.to_be_bytes(),
+            &be_u32s(&[0xffff_ffff, 2]),
+            b"urn:scte:scte35:2013:bin\0\0",
+            &[0xfc, 0x30],
+        ]
+        .concat();
+        buf.splice(moofs[1]..moofs[1], atom(b"emsg", &v1_body));
+
+        // A version 0 emsg, 500ms into the first fragment which starts at 1s.
+        let v0_body =
+            [&b"https://aomedia.org/emsg/ID3\0np\0"[..], &be_u32s(&[1000, 500, 250, 1]), b"ID3"]
+                .concat();
+        buf.splice(moofs[0]..moofs[0], full_atom(b"emsg", &v0_body));
+
+        let mut reader = open_mp4(buf, false);
+
+        // The event message preceding the first fragment is read before any packets.
+        assert_eq!(reader.next_packet().unwrap().ts, 44100);
+
+        let msg = reader.next_event_message().unwrap();
+        assert_eq!(msg.scheme_id_uri, "https://aomedia.org/emsg/ID3");
+        assert_eq!(msg.value, "np");
+        assert_eq!((msg.timescale, msg.presentation_time), (1000, 1500));
+        assert_eq!((msg.duration, msg.id), (Some(250), 1));
+        assert_eq!(&msg.message_data[..], b"ID3");
+        assert_eq!(msg.time().seconds, 1);
+        assert!((msg.time().frac - 0.5).abs() < 1e-9);
+
+        assert!(reader.next_event_message().is_none());
+
+        while reader.next_packet().is_ok() {}
+
+        let msg = reader.next_event_message().unwrap();
+        assert_eq!(msg.scheme_id_uri, "urn:scte:scte35:2013:bin");
+        assert_eq!(msg.value, "");
+        assert_eq!((msg.timescale, msg.presentation_time), (1000, 2500));
+        assert_eq!((msg.duration, msg.id), (None, 2));
+        assert_eq!(&msg.message_data[..], &[0xfc, 0x30]);
+
+        assert!(reader.next_event_message().is_none());
+    }
+
+    #[test]
+    fn verify_cmaf_fragments() {
+        let mut reader = open_mp4(build_cmaf_mp4(&[&[3, 5, 4], &[6, 2]]), false);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_AAC);
+        assert_eq!(params.sample_rate, Some(44100));
+
+        // The initialization segment does not state a duration, so the segment indexes are used.
+        assert_eq!(params.n_frames, Some(5 * 1024));
+        assert_eq!(reader.duration(), Some(TimeBase::new(1, 44100).calc_time(5 * 1024)));
+
+        // The samples of both fragments have the default duration, and their own size.
+        let expected = [(0, 3), (1024, 5), (2048, 4), (3072, 6), (4096, 2)];
+
+        for (i, &(ts, size)) in expected.iter().enumerate() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!((packet.ts, packet.dur), (ts, 1024));
+            assert_eq!(packet.buf(), &vec![i as u8; size][..]);
+        }
+
+        assert!(reader.next_packet().is_err());
+
+        // Seeking into the second fragment.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 3500, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 3072);
+        assert_eq!(reader.next_packet().unwrap().buf(), &[3; 6]);
+    }
+}
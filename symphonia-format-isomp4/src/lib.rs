@@ -19,4 +19,4 @@ mod fourcc;
 mod fp;
 mod stream;
 
-pub use demuxer::IsoMp4Reader;
+pub use demuxer::{EventMessage, IsoMp4Reader};
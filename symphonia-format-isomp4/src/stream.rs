@@ -110,6 +110,13 @@ impl MoofSegment {
                     info.total_sample_duration += trun.total_duration(default_dur);
                 }
 
+                // If present, the decode time of the track fragment is the timestamp of the first
+                // sample. This keeps the timeline correct if fragments are missing, or if the
+                // stream was joined part-way through.
+                if let Some(tfdt) = &traf.tfdt {
+                    info.first_ts = tfdt.base_media_decode_time;
+                }
+
                 info.total_sample_count = traf.total_sample_count;
                 info.traf_idx = Some(traf_idx);
             }
@@ -174,6 +181,10 @@ impl StreamSegment for MoofSegment {
         let mut sample_num = self.seq[track_num].first_sample;
         let mut ts_accum = self.seq[track_num].first_ts;
 
+        // If there is a gap in the timeline before the track fragment, the timestamp may precede
+        // the first sample of the track fragment.
+        let ts = ts.max(ts_accum);
+
         let default_dur = traf
             .tfhd
             .default_sample_duration
@@ -10,6 +10,22 @@ use symphonia_core::codecs::CodecType;
 
 use crate::segment::TrackElement;
 
+/// Returns `true` if the codec type is one of the raw PCM codecs that may be stored in a track.
+pub(crate) fn is_pcm_codec(codec_type: CodecType) -> bool {
+    matches!(
+        codec_type,
+        codecs::CODEC_TYPE_PCM_U8
+            | codecs::CODEC_TYPE_PCM_S16BE
+            | codecs::CODEC_TYPE_PCM_S24BE
+            | codecs::CODEC_TYPE_PCM_S32BE
+            | codecs::CODEC_TYPE_PCM_S16LE
+            | codecs::CODEC_TYPE_PCM_S24LE
+            | codecs::CODEC_TYPE_PCM_S32LE
+            | codecs::CODEC_TYPE_PCM_F32LE
+            | codecs::CODEC_TYPE_PCM_F64LE
+    )
+}
+
 pub(crate) fn codec_id_to_type(track: &TrackElement) -> Option<CodecType> {
     let bit_depth = track.audio.as_ref().and_then(|a| a.bit_depth);
 
@@ -23,13 +39,16 @@ pub(crate) fn codec_id_to_type(track: &TrackElement) -> Option<CodecType> {
         "A_AAC/MPEG2/MAIN" | "A_AAC/MPEG2/LC" | "A_AAC/MPEG2/LC/SBR" | "A_AAC/MPEG2/SSR"
         | "A_AAC/MPEG4/MAIN" | "A_AAC/MPEG4/LC" | "A_AAC/MPEG4/LC/SBR" | "A_AAC/MPEG4/SSR"
         | "A_AAC/MPEG4/LTP" | "A_AAC" => Some(codecs::CODEC_TYPE_AAC),
+        // 8-bit PCM is unsigned, all other bit depths are signed.
         "A_PCM/INT/BIG" => match bit_depth? {
+            8 => Some(codecs::CODEC_TYPE_PCM_U8),
             16 => Some(codecs::CODEC_TYPE_PCM_S16BE),
             24 => Some(codecs::CODEC_TYPE_PCM_S24BE),
             32 => Some(codecs::CODEC_TYPE_PCM_S32BE),
             _ => None,
         },
         "A_PCM/INT/LIT" => match bit_depth? {
+            8 => Some(codecs::CODEC_TYPE_PCM_U8),
             16 => Some(codecs::CODEC_TYPE_PCM_S16LE),
             24 => Some(codecs::CODEC_TYPE_PCM_S24LE),
             32 => Some(codecs::CODEC_TYPE_PCM_S32LE),
@@ -40,6 +59,13 @@ pub(crate) fn codec_id_to_type(track: &TrackElement) -> Option<CodecType> {
             64 => Some(codecs::CODEC_TYPE_PCM_F64LE),
             _ => None,
         },
+        "S_TEXT/UTF8" => Some(codecs::CODEC_TYPE_TEXT_UTF8),
+        "S_TEXT/SSA" | "S_TEXT/ASS" | "S_SSA" | "S_ASS" => Some(codecs::CODEC_TYPE_SSA),
+        // WebM uses its own codec IDs for WebVTT, one per kind of text track.
+        "S_TEXT/WEBVTT" | "D_WEBVTT/SUBTITLES" | "D_WEBVTT/CAPTIONS" | "D_WEBVTT/DESCRIPTIONS" => {
+            Some(codecs::CODEC_TYPE_WEBVTT)
+        }
+        "S_HDMV/PGS" => Some(codecs::CODEC_TYPE_HDMV_PGS),
         _ => {
             log::info!("unknown codec: {}", &track.codec_id);
             None
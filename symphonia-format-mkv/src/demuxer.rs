@@ -11,6 +11,7 @@ use std::io::{Seek, SeekFrom};
 
 use symphonia_core::audio::Layout;
 use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_FLAC, CODEC_TYPE_VORBIS};
+use symphonia_core::codecs::{CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_U8};
 use symphonia_core::errors::{
     decode_error, end_of_stream_error, seek_error, unsupported_error, Error, Result, SeekErrorKind,
 };
@@ -23,10 +24,10 @@ use symphonia_core::probe::Instantiate;
 use symphonia_core::probe::{Descriptor, QueryDescriptor};
 use symphonia_core::sample::SampleFormat;
 use symphonia_core::support_format;
-use symphonia_core::units::TimeBase;
+use symphonia_core::units::{Time, TimeBase};
 use symphonia_utils_xiph::flac::metadata::{MetadataBlockHeader, MetadataBlockType};
 
-use crate::codecs::codec_id_to_type;
+use crate::codecs::{codec_id_to_type, is_pcm_codec};
 use crate::ebml::{EbmlElement, ElementHeader, ElementIterator};
 use crate::element_ids::{ElementType, ELEMENTS};
 use crate::lacing::{extract_frames, read_xiph_sizes, Frame};
@@ -43,11 +44,17 @@ pub struct TrackState {
     track_num: u32,
     /// Default frame duration in nanoseconds.
     pub(crate) default_frame_duration: Option<u64>,
+    /// Codec delay in nanoseconds.
+    codec_delay: Option<u64>,
 }
 
 /// Matroska (MKV) and WebM demultiplexer.
 ///
 /// `MkvReader` implements a demuxer for the Matroska and WebM formats.
+///
+/// If gapless playback is enabled, the codec delay of a track, and the discard padding of a block,
+/// are trimmed from the decoded audio. Packet timestamps are then relative to the end of the codec
+/// delay.
 pub struct MkvReader {
     /// Iterator over EBML element headers
     iter: ElementIterator<MediaSourceStream>,
@@ -59,6 +66,11 @@ pub struct MkvReader {
     frames: VecDeque<Frame>,
     timestamp_scale: u64,
     clusters: Vec<ClusterElement>,
+    /// The position of the first cluster, if known.
+    first_cluster_pos: Option<u64>,
+    /// The duration of the segment, if known.
+    duration: Option<Time>,
+    enable_gapless: bool,
 }
 
 #[derive(Debug)]
@@ -117,6 +129,60 @@ fn vorbis_extra_data_from_codec_private(extra: &[u8]) -> Result<Box<[u8]>> {
     .into_boxed_slice())
 }
 
+/// Converts a duration in nanoseconds to a number of audio frames at the given sample rate.
+fn nanos_to_frames(nanos: u64, sample_rate: u32) -> u32 {
+    let frames = (u128::from(nanos) * u128::from(sample_rate) + 500_000_000) / 1_000_000_000;
+    u32::try_from(frames).unwrap_or(u32::MAX)
+}
+
+/// Populate the trim information of a packet to remove the codec delay of the track, and the
+/// discard padding of the block the packet was read from. The timestamp and duration of the packet
+/// are adjusted to exclude the trimmed region.
+fn trim_packet(
+    packet: &mut Packet,
+    state: &TrackState,
+    timestamp_scale: u64,
+    discard_padding: Option<i64>,
+) {
+    let sample_rate = match state.codec_params.sample_rate {
+        Some(sample_rate) => sample_rate,
+        _ => return,
+    };
+
+    let timestamp_scale = timestamp_scale.max(1);
+
+    // The durations to trim from the start and end of the packet in nanoseconds.
+    let mut trim_start = 0;
+    let mut trim_end = 0;
+
+    if let Some(codec_delay) = state.codec_delay {
+        let ts = packet.ts.saturating_mul(timestamp_scale);
+
+        if ts < codec_delay {
+            trim_start = codec_delay - ts;
+
+            // If the duration of the packet is known, the trim can not exceed it.
+            if packet.dur > 0 {
+                trim_start = trim_start.min(packet.dur.saturating_mul(timestamp_scale));
+            }
+        }
+
+        packet.ts = packet.ts.saturating_sub(codec_delay / timestamp_scale);
+    }
+
+    // Positive discard padding is at the end of the block, and negative discard padding is at the
+    // start of the block.
+    match discard_padding {
+        Some(padding) if padding > 0 => trim_end = padding as u64,
+        Some(padding) if padding < 0 => trim_start += padding.unsigned_abs(),
+        _ => (),
+    }
+
+    packet.dur = packet.dur.saturating_sub((trim_start + trim_end) / timestamp_scale);
+    packet.trim_start = nanos_to_frames(trim_start, sample_rate);
+    packet.trim_end = nanos_to_frames(trim_end, sample_rate);
+}
+
 fn flac_extra_data_from_codec_private(codec_private: &[u8]) -> Result<Box<[u8]>> {
     let mut reader = BufReader::new(codec_private);
 
@@ -186,6 +252,9 @@ impl MkvReader {
             };
             self.iter.seek(pos)?;
 
+            // Discard all frames buffered from the previous position.
+            self.frames.clear();
+
             // Restore cluster's metadata
             self.current_cluster =
                 Some(ClusterState { timestamp: Some(cluster.timestamp), end: cluster.end });
@@ -270,6 +339,9 @@ impl MkvReader {
                 };
 
                 let group = self.iter.read_element_data::<BlockGroupElement>()?;
+
+                let first_frame = self.frames.len();
+
                 extract_frames(
                     &group.data,
                     group.duration,
@@ -278,6 +350,15 @@ impl MkvReader {
                     self.timestamp_scale,
                     &mut self.frames,
                 )?;
+
+                // The discard padding applies to the last frame of a laced block, or to the first
+                // frame if the padding is at the start of the block.
+                if let Some(padding) = group.discard_padding {
+                    if self.frames.len() > first_frame {
+                        let index = if padding < 0 { first_frame } else { self.frames.len() - 1 };
+                        self.frames[index].discard_padding = Some(padding);
+                    }
+                }
             }
             ElementType::Tags => {
                 let tags = self.iter.read_element_data::<TagsElement>()?;
@@ -298,7 +379,7 @@ impl MkvReader {
 }
 
 impl FormatReader for MkvReader {
-    fn try_new(mut reader: MediaSourceStream, _options: &FormatOptions) -> Result<Self>
+    fn try_new(mut reader: MediaSourceStream, options: &FormatOptions) -> Result<Self>
     where
         Self: Sized,
     {
@@ -333,6 +414,7 @@ impl FormatReader for MkvReader {
         let mut clusters = Vec::new();
         let mut metadata = MetadataLog::default();
         let mut current_cluster = None;
+        let mut first_cluster_pos = None;
 
         let mut seek_positions = Vec::new();
         while let Ok(Some(header)) = it.read_child_header() {
@@ -372,6 +454,7 @@ impl FormatReader for MkvReader {
                 ElementType::Cluster => {
                     // Set state for current cluster for the first call of `next_element`.
                     current_cluster = Some(ClusterState { timestamp: None, end: header.end() });
+                    first_cluster_pos = Some(header.pos);
 
                     // Don't look forward into the stream since
                     // we can't be sure that we'll find anything useful.
@@ -432,6 +515,12 @@ impl FormatReader for MkvReader {
 
         let info = info.ok_or(Error::DecodeError("mkv: missing Info element"))?;
 
+        // The segment duration is stated in timestamp scale units, where each unit is
+        // `timestamp_scale` nanoseconds.
+        let duration = info
+            .duration
+            .map(|dur| Time::from(dur * info.timestamp_scale as f64 / 1_000_000_000.0));
+
         // TODO: remove this unwrap?
         let time_base = TimeBase::new(u32::try_from(info.timestamp_scale).unwrap(), 1_000_000_000);
 
@@ -450,13 +539,18 @@ impl FormatReader for MkvReader {
             if let Some(audio) = track.audio {
                 codec_params.with_sample_rate(audio.sampling_frequency.round() as u32);
 
-                let format = audio.bit_depth.and_then(|bits| match bits {
-                    8 => Some(SampleFormat::S8),
-                    16 => Some(SampleFormat::S16),
-                    24 => Some(SampleFormat::S24),
-                    32 => Some(SampleFormat::S32),
-                    _ => None,
-                });
+                let format = match codec_type {
+                    Some(CODEC_TYPE_PCM_F32LE) => Some(SampleFormat::F32),
+                    Some(CODEC_TYPE_PCM_F64LE) => Some(SampleFormat::F64),
+                    Some(CODEC_TYPE_PCM_U8) => Some(SampleFormat::U8),
+                    _ => audio.bit_depth.and_then(|bits| match bits {
+                        8 => Some(SampleFormat::S8),
+                        16 => Some(SampleFormat::S16),
+                        24 => Some(SampleFormat::S24),
+                        32 => Some(SampleFormat::S32),
+                        _ => None,
+                    }),
+                };
 
                 if let Some(format) = format {
                     codec_params.with_sample_format(format);
@@ -485,27 +579,64 @@ impl FormatReader for MkvReader {
                     codec_params.with_channel_layout(layout);
                 }
 
-                if let Some(codec_type) = codec_type {
-                    codec_params.for_codec(codec_type);
-                    if let Some(codec_private) = track.codec_private {
-                        let extra_data = match codec_type {
-                            CODEC_TYPE_VORBIS => {
-                                vorbis_extra_data_from_codec_private(&codec_private)?
-                            }
-                            CODEC_TYPE_FLAC => flac_extra_data_from_codec_private(&codec_private)?,
-                            _ => codec_private,
-                        };
-                        codec_params.with_extra_data(extra_data);
-                    }
+                // Raw PCM is not packetized. If the default duration of a frame is known, use it
+                // to determine the maximum number of frames per packet. Otherwise, assume a packet
+                // contains no more than 1 second of audio.
+                if codec_type.map_or(false, is_pcm_codec) {
+                    let sample_rate = audio.sampling_frequency.round() as u64;
+
+                    let max_frames = match track.default_duration {
+                        Some(dur) => {
+                            (dur.saturating_mul(sample_rate) + 999_999_999) / 1_000_000_000
+                        }
+                        None => sample_rate,
+                    };
+
+                    codec_params.with_max_frames_per_packet(max_frames.max(1));
+                }
+            }
+
+            // For non-audio tracks, such as subtitle tracks, the codec type and private data are
+            // the only codec parameters. Subtitle packets are timed by the block timestamp and
+            // duration.
+            if let Some(codec_type) = codec_type {
+                codec_params.for_codec(codec_type);
+
+                if let Some(codec_private) = track.codec_private {
+                    let extra_data = match codec_type {
+                        CODEC_TYPE_VORBIS => vorbis_extra_data_from_codec_private(&codec_private)?,
+                        CODEC_TYPE_FLAC => flac_extra_data_from_codec_private(&codec_private)?,
+                        _ => codec_private,
+                    };
+                    codec_params.with_extra_data(extra_data);
                 }
             }
 
+            // The track offset and codec delay are both in nanoseconds. The codec delay is the
+            // duration of the leading part of the track that should not be presented. If gapless
+            // playback is enabled, the codec delay is trimmed instead.
+            let codec_delay = track.codec_delay.filter(|_| !options.enable_gapless);
+
+            let offset = match (track.track_offset, codec_delay) {
+                (None, None) => None,
+                (track_offset, codec_delay) => {
+                    let nanos = i128::from(track_offset.unwrap_or(0))
+                        - i128::from(codec_delay.unwrap_or(0));
+                    let units = nanos / i128::from(info.timestamp_scale.max(1));
+                    Some(i64::try_from(units).unwrap_or(0))
+                }
+            };
+
             let track_id = track.number as u32;
-            tracks.push(Track {
-                id: track_id,
-                codec_params: codec_params.clone(),
-                language: track.language,
-            });
+
+            let mut new_track = Track::new(track_id, codec_params.clone());
+            new_track.language = track.language;
+
+            if let Some(offset) = offset {
+                new_track.with_offset(offset);
+            }
+
+            tracks.push(new_track);
 
             states.insert(
                 track_id,
@@ -513,6 +644,7 @@ impl FormatReader for MkvReader {
                     codec_params,
                     track_num: track_id,
                     default_frame_duration: track.default_duration,
+                    codec_delay: track.codec_delay,
                 },
             );
         }
@@ -527,6 +659,9 @@ impl FormatReader for MkvReader {
             frames: VecDeque::new(),
             timestamp_scale: info.timestamp_scale,
             clusters,
+            first_cluster_pos,
+            duration,
+            enable_gapless: options.enable_gapless,
         })
     }
 
@@ -564,19 +699,54 @@ impl FormatReader for MkvReader {
         }
     }
 
+    fn rewind(&mut self) -> Result<()> {
+        // Rewind to the first cluster. This does not depend on the presence of cues.
+        let pos = match self.first_cluster_pos {
+            Some(pos) => pos,
+            None => return seek_error(SeekErrorKind::Unseekable),
+        };
+
+        match self.iter.seek(pos) {
+            Err(Error::SeekError(SeekErrorKind::ForwardOnly)) => {
+                return seek_error(SeekErrorKind::Unseekable)
+            }
+            result => result?,
+        }
+
+        // Discard all buffered frames, and the state of the current cluster. The cluster state will
+        // be restored when the first cluster is read again.
+        self.frames.clear();
+        self.current_cluster = None;
+
+        Ok(())
+    }
+
     fn tracks(&self) -> &[Track] {
         &self.tracks
     }
 
+    fn duration(&self) -> Option<Time> {
+        self.duration
+    }
+
     fn next_packet(&mut self) -> Result<Packet> {
         loop {
             if let Some(frame) = self.frames.pop_front() {
-                return Ok(Packet::new_from_boxed_slice(
+                let mut packet = Packet::new_from_boxed_slice(
                     frame.track,
                     frame.timestamp,
                     frame.duration,
                     frame.data,
-                ));
+                );
+
+                if self.enable_gapless {
+                    if let Some(state) = self.track_states.get(&frame.track) {
+                        let padding = frame.discard_padding;
+                        trim_packet(&mut packet, state, self.timestamp_scale, padding);
+                    }
+                }
+
+                return Ok(packet);
             }
             self.next_element()?;
         }
@@ -602,3 +772,312 @@ impl QueryDescriptor for MkvReader {
         255
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::{CodecType, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_S24LE};
+    use symphonia_core::codecs::{CODEC_TYPE_PCM_S16LE, CODEC_TYPE_TEXT_UTF8, CODEC_TYPE_WEBVTT};
+    use symphonia_core::formats::{FormatOptions, FormatReader};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::sample::SampleFormat;
+    use symphonia_core::units::Time;
+
+    use super::MkvReader;
+
+    /// Builds an element with an 8 byte data size.
+    fn element(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut buf = id.to_vec();
+        buf.push(0x01);
+        buf.extend_from_slice(&(data.len() as u64).to_be_bytes()[1..]);
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    fn uint_element(id: &[u8], value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    /// Builds a file with a single stereo 48kHz audio track, and a single block.
+    fn build_mkv(codec_id: &str, bit_depth: u64, block: &[u8]) -> Vec<u8> {
+        let ebml = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"matroska"));
+
+        let info =
+            element(&[0x15, 0x49, 0xa9, 0x66], &uint_element(&[0x2a, 0xd7, 0xb1], 1_000_000));
+
+        let audio = [
+            element(&[0xb5], &48000f64.to_be_bytes()),
+            uint_element(&[0x9f], 2),
+            uint_element(&[0x62, 0x64], bit_depth),
+        ]
+        .concat();
+
+        let track = [
+            uint_element(&[0xd7], 1),
+            uint_element(&[0x73, 0xc5], 1),
+            element(&[0x86], codec_id.as_bytes()),
+            element(&[0xe1], &audio),
+        ]
+        .concat();
+
+        let tracks = element(&[0x16, 0x54, 0xae, 0x6b], &element(&[0xae], &track));
+
+        // A simple block for track 1, with a relative timestamp of 0, and the keyframe flag set.
+        let simple_block = element(&[0xa3], &[&[0x81, 0x00, 0x00, 0x80][..], block].concat());
+
+        let cluster =
+            element(&[0x1f, 0x43, 0xb6, 0x75], &[uint_element(&[0xe7], 0), simple_block].concat());
+
+        [ebml, element(&[0x18, 0x53, 0x80, 0x67], &[info, tracks, cluster].concat())].concat()
+    }
+
+    fn verify_pcm_track(codec_id: &str, bit_depth: u64, codec: CodecType, format: SampleFormat) {
+        // Two audio frames of stereo audio.
+        let block: Vec<u8> = (0..4 * bit_depth as u8 / 8).collect();
+
+        let buf = build_mkv(codec_id, bit_depth, &block);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        let mut reader = MkvReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+
+        assert_eq!(params.codec, codec);
+        assert_eq!(params.sample_format, Some(format));
+        assert_eq!(params.bits_per_sample, Some(bit_depth as u32));
+        assert_eq!(params.sample_rate, Some(48000));
+
+        // Without a default duration, a packet is assumed to contain up-to 1 second of audio.
+        assert_eq!(params.max_frames_per_packet, Some(48000));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.buf(), &block[..]);
+    }
+
+    /// Builds a WebM file with a single stereo 48kHz Opus track with a codec delay of 312 frames
+    /// (6.5ms), and 5 blocks of 20ms each. The last block is in a block group with 10ms of discard
+    /// padding.
+    fn build_webm_opus() -> Vec<u8> {
+        let ebml = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"webm"));
+
+        // The segment has a duration of 100ms.
+        let info = element(
+            &[0x15, 0x49, 0xa9, 0x66],
+            &[
+                uint_element(&[0x2a, 0xd7, 0xb1], 1_000_000),
+                element(&[0x44, 0x89], &100f64.to_be_bytes()),
+            ]
+            .concat(),
+        );
+
+        let audio = [element(&[0xb5], &48000f64.to_be_bytes()), uint_element(&[0x9f], 2)].concat();
+
+        let track = [
+            uint_element(&[0xd7], 1),
+            uint_element(&[0x73, 0xc5], 1),
+            element(&[0x86], b"A_OPUS"),
+            uint_element(&[0x23, 0xe3, 0x83], 20_000_000),
+            uint_element(&[0x56, 0xaa], 6_500_000),
+            element(&[0xe1], &audio),
+        ]
+        .concat();
+
+        let tracks = element(&[0x16, 0x54, 0xae, 0x6b], &element(&[0xae], &track));
+
+        let mut cluster = uint_element(&[0xe7], 0);
+
+        // Blocks for track 1, with a relative timestamp in milliseconds, and the keyframe flag
+        // set.
+        for i in 0..4u8 {
+            cluster.extend(element(&[0xa3], &[0x81, 0x00, 20 * i, 0x80, i]));
+        }
+
+        let block = element(&[0xa1], &[0x81, 0x00, 80, 0x00, 4]);
+        let discard_padding = uint_element(&[0x75, 0xa2], 10_000_000);
+        cluster.extend(element(&[0xa0], &[block, discard_padding].concat()));
+
+        let cluster = element(&[0x1f, 0x43, 0xb6, 0x75], &cluster);
+
+        [ebml, element(&[0x18, 0x53, 0x80, 0x67], &[info, tracks, cluster].concat())].concat()
+    }
+
+    #[test]
+    fn verify_gapless_opus() {
+        let read_packets = |enable_gapless| {
+            let buf = build_webm_opus();
+            let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+            let mut options = FormatOptions::default();
+            options.with_gapless(enable_gapless);
+            let mut reader = MkvReader::try_new(mss, &options).unwrap();
+
+            let offset = reader.tracks()[0].offset();
+
+            let mut packets = Vec::new();
+
+            while let Ok(packet) = reader.next_packet() {
+                packets.push((packet.ts(), packet.dur(), packet.trim_start(), packet.trim_end()));
+            }
+
+            (offset, packets)
+        };
+
+        // Without gapless playback, the codec delay is presented as a negative track offset of
+        // 6ms.
+        let (offset, packets) = read_packets(false);
+
+        assert_eq!(offset, Some(-6));
+        assert!(packets.iter().all(|&(_, dur, start, end)| dur == 20 && start == 0 && end == 0));
+
+        // With gapless playback, the codec delay, and discard padding, are trimmed. Timestamps are
+        // in milliseconds, so the codec delay is truncated to 6ms.
+        let (offset, packets) = read_packets(true);
+
+        assert_eq!(offset, None);
+        assert_eq!(
+            packets,
+            &[(0, 14, 312, 0), (14, 20, 0, 0), (34, 20, 0, 0), (54, 20, 0, 0), (74, 10, 0, 480)]
+        );
+
+        // Each Opus packet decodes to 960 frames, and 20ms at 48kHz minus the codec delay and
+        // discard padding remain.
+        let frames: u32 = packets.iter().map(|&(_, _, start, end)| 960 - start - end).sum();
+        assert_eq!(frames, 5 * 960 - 312 - 480);
+    }
+
+    #[test]
+    fn verify_segment_duration() {
+        let open = |buf: Vec<u8>| {
+            let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+            MkvReader::try_new(mss, &FormatOptions::default()).unwrap()
+        };
+
+        // The duration is stated by the segment information in timestamp scale units.
+        let reader = open(build_webm_opus());
+        assert_eq!(reader.duration(), Some(Time::from(0.1)));
+
+        // The duration is optional.
+        let reader = open(build_mkv("A_PCM/INT/LIT", 16, &[0; 4]));
+        assert_eq!(reader.duration(), None);
+    }
+
+    #[test]
+    fn verify_pcm_int_lit_24_bit() {
+        verify_pcm_track("A_PCM/INT/LIT", 24, CODEC_TYPE_PCM_S24LE, SampleFormat::S24);
+    }
+
+    #[test]
+    fn verify_pcm_float_ieee_32_bit() {
+        verify_pcm_track("A_PCM/FLOAT/IEEE", 32, CODEC_TYPE_PCM_F32LE, SampleFormat::F32);
+    }
+
+    /// Builds a file with a stereo 48kHz audio track, a SRT subtitle track, and a WebVTT subtitle
+    /// track. The timestamp scale is 1ms.
+    fn build_mkv_subtitles() -> Vec<u8> {
+        let ebml = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"matroska"));
+
+        let info =
+            element(&[0x15, 0x49, 0xa9, 0x66], &uint_element(&[0x2a, 0xd7, 0xb1], 1_000_000));
+
+        let audio = [
+            element(&[0xb5], &48000f64.to_be_bytes()),
+            uint_element(&[0x9f], 2),
+            uint_element(&[0x62, 0x64], 16),
+        ]
+        .concat();
+
+        let audio_track = [
+            uint_element(&[0xd7], 1),
+            uint_element(&[0x73, 0xc5], 1),
+            uint_element(&[0x83], 2),
+            element(&[0x86], b"A_PCM/INT/LIT"),
+            element(&[0xe1], &audio),
+        ]
+        .concat();
+
+        let srt_track = [
+            uint_element(&[0xd7], 2),
+            uint_element(&[0x73, 0xc5], 2),
+            uint_element(&[0x83], 0x11),
+            element(&[0x86], b"S_TEXT/UTF8"),
+            element(&[0x22, 0xb5, 0x9c], b"eng"),
+        ]
+        .concat();
+
+        let vtt_track = [
+            uint_element(&[0xd7], 3),
+            uint_element(&[0x73, 0xc5], 3),
+            uint_element(&[0x83], 0x11),
+            element(&[0x86], b"S_TEXT/WEBVTT"),
+            element(&[0x63, 0xa2], b"WEBVTT"),
+        ]
+        .concat();
+
+        let tracks = element(
+            &[0x16, 0x54, 0xae, 0x6b],
+            &[
+                element(&[0xae], &audio_track),
+                element(&[0xae], &srt_track),
+                element(&[0xae], &vtt_track),
+            ]
+            .concat(),
+        );
+
+        // Builds a block group with a block for a track, a relative timestamp, and a duration.
+        let block_group = |track: u8, ts: u16, dur: u64, text: &[u8], extra: &[u8]| {
+            let header = [0x80 | track, (ts >> 8) as u8, ts as u8, 0x00];
+            let block = element(&[0xa1], &[&header[..], text].concat());
+            element(&[0xa0], &[block, uint_element(&[0x9b], dur), extra.to_vec()].concat())
+        };
+
+        // The WebVTT cue settings are stored in the block additions of the block group.
+        let block_more = [uint_element(&[0xee], 1), element(&[0xa5], b"\nline:0")].concat();
+        let additions = element(&[0x75, 0xa1], &element(&[0xa6], &block_more));
+
+        let cluster = [
+            uint_element(&[0xe7], 1000),
+            element(&[0xa3], &[0x81, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00]),
+            block_group(2, 500, 1500, b"Hello, world!", &[]),
+            block_group(3, 600, 900, b"A WebVTT cue", &additions),
+            block_group(2, 2500, 1000, b"Second cue\nover two lines", &[]),
+        ]
+        .concat();
+
+        let cluster = element(&[0x1f, 0x43, 0xb6, 0x75], &cluster);
+
+        [ebml, element(&[0x18, 0x53, 0x80, 0x67], &[info, tracks, cluster].concat())].concat()
+    }
+
+    #[test]
+    fn verify_subtitle_tracks() {
+        let buf = build_mkv_subtitles();
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        let mut reader = MkvReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let tracks = reader.tracks();
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[0].codec_params.codec, CODEC_TYPE_PCM_S16LE);
+        assert_eq!(tracks[1].codec_params.codec, CODEC_TYPE_TEXT_UTF8);
+        assert_eq!(tracks[1].language.as_deref(), Some("eng"));
+        assert_eq!(tracks[2].codec_params.codec, CODEC_TYPE_WEBVTT);
+        assert_eq!(tracks[2].codec_params.extra_data.as_deref(), Some(&b"WEBVTT"[..]));
+
+        // The audio packet.
+        assert_eq!(reader.next_packet().unwrap().track_id(), 1);
+
+        // The subtitle cues are timed by the block timestamps and durations in milliseconds.
+        let expected: [(u32, u64, u64, &[u8]); 3] = [
+            (2, 1500, 1500, b"Hello, world!"),
+            (3, 1600, 900, b"A WebVTT cue"),
+            (2, 3500, 1000, b"Second cue\nover two lines"),
+        ];
+
+        for &(track_id, ts, dur, text) in expected.iter() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!((packet.track_id(), packet.ts, packet.dur), (track_id, ts, dur));
+            assert_eq!(packet.buf(), text);
+        }
+
+        assert!(reader.next_packet().is_err());
+    }
+}
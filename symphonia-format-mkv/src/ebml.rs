@@ -29,12 +29,10 @@ pub(crate) fn read_tag<R: ReadBytes>(mut reader: R) -> Result<(u32, u32, bool)>
             break;
         }
 
-        // Read remaining octets
-        let mut vint = u32::from(byte);
-        for _ in 0..remaining_octets {
-            let byte = reader.read_byte()?;
-            vint = (vint << 8) | u32::from(byte);
-        }
+        // Read remaining octets. Unlike other variable size integers, the element ID includes the
+        // length marker bit.
+        let rest = reader.read_be_uint(remaining_octets as usize)? as u32;
+        let vint = (u32::from(byte) << (8 * remaining_octets)) | rest;
 
         // log::debug!("element with tag: {:X}", vint);
         return Ok((vint, remaining_octets + 1, false));
@@ -53,57 +51,32 @@ pub(crate) fn read_tag<R: ReadBytes>(mut reader: R) -> Result<(u32, u32, bool)>
     }
 }
 
-pub(crate) fn read_size<R: ReadBytes>(reader: R) -> Result<Option<u64>> {
-    let (size, len) = read_vint(reader)?;
-    if size == u64::MAX && len == 1 {
-        return Ok(None);
-    }
-    Ok(Some(size))
+/// Reads a single element data size (as in RFC8794) from the stream and returns it, `None` if the
+/// size is unknown, or an error.
+pub(crate) fn read_size<R: ReadBytes>(mut reader: R) -> Result<Option<u64>> {
+    Ok(reader.read_vint_value()?)
 }
 
 /// Reads a single unsigned variable size integer (as in RFC8794) from the stream
 /// and returns it or an error.
-pub(crate) fn read_unsigned_vint<R: ReadBytes>(reader: R) -> Result<u64> {
-    Ok(read_vint(reader)?.0)
+pub(crate) fn read_unsigned_vint<R: ReadBytes>(mut reader: R) -> Result<u64> {
+    Ok(reader.read_vint()?.0)
 }
 
 /// Reads a single signed variable size integer (as in RFC8794) from the stream
 /// and returns it or an error.
 pub(crate) fn read_signed_vint<R: ReadBytes>(mut reader: R) -> Result<i64> {
-    let (value, len) = read_vint(&mut reader)?;
+    let (value, len) = reader.read_vint()?;
     // Convert to a signed integer by range shifting.
     let half_range = i64::pow(2, (len * 7) - 1) - 1;
     Ok(value as i64 - half_range)
 }
 
-/// Reads a single unsigned variable size integer (as in RFC8794) from the stream
-/// and returns both its value and length in octects, or an error.
-fn read_vint<R: ReadBytes>(mut reader: R) -> Result<(u64, u32)> {
-    let byte = reader.read_byte()?;
-    if byte == 0xFF {
-        // Special case: unknown size elements.
-        return Ok((u64::MAX, 1));
-    }
-
-    let vint_width = byte.leading_zeros();
-    let mut vint = u64::from(byte);
-    // Clear VINT_MARKER bit
-    vint ^= 1 << (7 - vint_width);
-
-    // Read remaining octets
-    for _ in 0..vint_width {
-        let byte = reader.read_byte()?;
-        vint = (vint << 8) | u64::from(byte);
-    }
-
-    Ok((vint, vint_width + 1))
-}
-
 #[cfg(test)]
 mod tests {
     use symphonia_core::io::BufReader;
 
-    use super::{read_signed_vint, read_tag, read_unsigned_vint};
+    use super::{read_signed_vint, read_size, read_tag, read_unsigned_vint};
 
     #[test]
     fn element_tag_parsing() {
@@ -144,6 +117,20 @@ mod tests {
         assert_eq!(read_signed_vint(BufReader::new(&[0x80])).unwrap(), -63);
         assert_eq!(read_signed_vint(BufReader::new(&[0x40, 0x00])).unwrap(), -8191);
     }
+
+    #[test]
+    fn element_size_parsing() {
+        assert_eq!(read_size(BufReader::new(&[0x82])).unwrap(), Some(2));
+        assert_eq!(read_size(BufReader::new(&[0x41, 0x00])).unwrap(), Some(0x100));
+
+        // Unknown sizes, of any width.
+        assert_eq!(read_size(BufReader::new(&[0xff])).unwrap(), None);
+        assert_eq!(read_size(BufReader::new(&[0x7f, 0xff])).unwrap(), None);
+        assert_eq!(
+            read_size(BufReader::new(&[0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff])).unwrap(),
+            None
+        );
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -390,6 +377,14 @@ impl<R: ReadBytes> ElementIterator<R> {
         }
     }
 
+    /// Reads data of the current element as a signed integer.
+    pub(crate) fn read_i64(&mut self) -> Result<i64> {
+        match self.read_data()? {
+            ElementData::SignedInt(s) => Ok(s),
+            _ => Err(Error::DecodeError("mkv: expected a signed int")),
+        }
+    }
+
     /// Reads data of the current element as a floating-point number.
     pub(crate) fn read_f64(&mut self) -> Result<f64> {
         match self.read_data()? {
@@ -440,10 +435,7 @@ impl<R: ReadBytes> ElementIterator<R> {
                             return decode_error("mkv: invalid unsigned integer length");
                         }
 
-                        let mut buff = [0u8; 8];
-                        let offset = 8 - header.data_len as usize;
-                        self.reader.read_buf_exact(&mut buff[offset..])?;
-                        let value = u64::from_be_bytes(buff);
+                        let value = self.reader.read_be_uint(header.data_len as usize)?;
                         ElementData::UnsignedInt(value)
                     }
                     Type::Signed | Type::Date => {
@@ -452,11 +444,8 @@ impl<R: ReadBytes> ElementIterator<R> {
                             return decode_error("mkv: invalid signed integer length");
                         }
 
-                        let len = header.data_len as usize;
-                        let mut buff = [0u8; 8];
-                        self.reader.read_buf_exact(&mut buff[8 - len..])?;
-                        let value = u64::from_be_bytes(buff);
-                        let value = sign_extend_leq64_to_i64(value, (len as u32) * 8);
+                        let value = self.reader.read_be_uint(header.data_len as usize)?;
+                        let value = sign_extend_leq64_to_i64(value, (header.data_len as u32) * 8);
 
                         match ty {
                             Type::Signed => ElementData::SignedInt(value),
@@ -79,6 +79,7 @@ pub enum ElementType {
     CodecName,
     CodecDelay,
     SeekPreRoll,
+    TrackOffset,
     Video,
     FlagInterlaced,
     StereoMode,
@@ -244,6 +245,7 @@ lazy_static! {
         elems.insert(0x258688, (Type::String, ElementType::CodecName));
         elems.insert(0x56AA, (Type::Unsigned, ElementType::CodecDelay));
         elems.insert(0x56BB, (Type::Unsigned, ElementType::SeekPreRoll));
+        elems.insert(0x537F, (Type::Signed, ElementType::TrackOffset));
         elems.insert(0xE0, (Type::Master, ElementType::Video));
         elems.insert(0x9A, (Type::Unsigned, ElementType::FlagInterlaced));
         elems.insert(0x53B8, (Type::Unsigned, ElementType::StereoMode));
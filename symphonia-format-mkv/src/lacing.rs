@@ -69,6 +69,8 @@ pub(crate) struct Frame {
     /// Absolute frame timestamp.
     pub(crate) timestamp: u64,
     pub(crate) duration: u64,
+    /// The duration, in nanoseconds, of the padding to discard from the frame.
+    pub(crate) discard_padding: Option<i64>,
     pub(crate) data: Box<[u8]>,
 }
 
@@ -104,7 +106,7 @@ pub(crate) fn extract_frames(
         Lacing::None => {
             let data = reader.read_boxed_slice_exact(block.len() - reader.pos() as usize)?;
             let duration = block_duration.or(default_frame_duration).unwrap_or(0);
-            buffer.push_back(Frame { track, timestamp, data, duration });
+            buffer.push_back(Frame { track, timestamp, data, duration, discard_padding: None });
         }
         Lacing::Xiph | Lacing::Ebml => {
             // Read number of stored sizes which is actually `number of frames` - 1
@@ -123,14 +125,26 @@ pub(crate) fn extract_frames(
 
             for frame_size in sizes {
                 let data = reader.read_boxed_slice_exact(frame_size as usize)?;
-                buffer.push_back(Frame { track, timestamp, data, duration: frame_duration });
+                buffer.push_back(Frame {
+                    track,
+                    timestamp,
+                    data,
+                    duration: frame_duration,
+                    discard_padding: None,
+                });
                 timestamp += frame_duration;
             }
 
             // Size of last frame is not provided so we read to the end of the block.
             let size = block.len() - reader.pos() as usize;
             let data = reader.read_boxed_slice_exact(size)?;
-            buffer.push_back(Frame { track, timestamp, data, duration: frame_duration });
+            buffer.push_back(Frame {
+                track,
+                timestamp,
+                data,
+                duration: frame_duration,
+                discard_padding: None,
+            });
         }
         Lacing::FixedSize => {
             let frames = reader.read_byte()? as usize + 1;
@@ -145,7 +159,13 @@ pub(crate) fn extract_frames(
             let frame_size = total_size / frames;
             for _ in 0..frames {
                 let data = reader.read_boxed_slice_exact(frame_size)?;
-                buffer.push_back(Frame { track, timestamp, data, duration: frame_duration });
+                buffer.push_back(Frame {
+                    track,
+                    timestamp,
+                    data,
+                    duration: frame_duration,
+                    discard_padding: None,
+                });
                 timestamp += frame_duration;
             }
         }
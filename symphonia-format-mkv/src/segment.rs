@@ -23,6 +23,8 @@ pub(crate) struct TrackElement {
     pub(crate) codec_private: Option<Box<[u8]>>,
     pub(crate) audio: Option<AudioElement>,
     pub(crate) default_duration: Option<u64>,
+    pub(crate) codec_delay: Option<u64>,
+    pub(crate) track_offset: Option<i64>,
 }
 
 impl Element for TrackElement {
@@ -36,6 +38,8 @@ impl Element for TrackElement {
         let mut codec_private = None;
         let mut codec_id = None;
         let mut default_duration = None;
+        let mut codec_delay = None;
+        let mut track_offset = None;
 
         let mut it = header.children(reader);
         while let Some(header) = it.read_header()? {
@@ -61,6 +65,12 @@ impl Element for TrackElement {
                 ElementType::DefaultDuration => {
                     default_duration = Some(it.read_u64()?);
                 }
+                ElementType::CodecDelay => {
+                    codec_delay = Some(it.read_u64()?);
+                }
+                ElementType::TrackOffset => {
+                    track_offset = Some(it.read_i64()?);
+                }
                 other => {
                     log::debug!("ignored element {:?}", other);
                 }
@@ -75,6 +85,8 @@ impl Element for TrackElement {
             codec_private,
             audio,
             default_duration,
+            codec_delay,
+            track_offset,
         })
     }
 }
@@ -412,6 +424,9 @@ impl Element for CueTrackPositionsElement {
 pub(crate) struct BlockGroupElement {
     pub(crate) data: Box<[u8]>,
     pub(crate) duration: Option<u64>,
+    /// The duration, in nanoseconds, of the padding to discard from the end of the block, or from
+    /// the start of the block if negative.
+    pub(crate) discard_padding: Option<i64>,
 }
 
 impl Element for BlockGroupElement {
@@ -422,10 +437,11 @@ impl Element for BlockGroupElement {
 
         let mut data = None;
         let mut block_duration = None;
+        let mut discard_padding = None;
         while let Some(header) = it.read_header()? {
             match header.etype {
                 ElementType::DiscardPadding => {
-                    let _nanos = it.read_data()?;
+                    discard_padding = Some(it.read_i64()?);
                 }
                 ElementType::Block => {
                     data = Some(it.read_boxed_slice()?);
@@ -441,6 +457,7 @@ impl Element for BlockGroupElement {
         Ok(Self {
             data: data.ok_or(Error::DecodeError("mkv: missing block inside block group"))?,
             duration: block_duration,
+            discard_padding,
         })
     }
 }
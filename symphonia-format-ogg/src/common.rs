@@ -5,9 +5,36 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use symphonia_core::formats::Packet;
 use symphonia_core::meta::MetadataRevision;
 
 /// Side data variants.
 pub enum SideData {
     Metadata(MetadataRevision),
+    Fisbone(Fisbone),
+}
+
+/// Information about a logical stream from an OGG Skeleton fisbone packet.
+pub struct Fisbone {
+    /// The serial of the logical stream described by the fisbone.
+    pub serial: u32,
+    /// The granule rate of the logical stream as a numerator and denominator pair. The granule
+    /// rate is the number of granules per second.
+    pub granule_rate: (u64, u64),
+    /// The content type (MIME type) of the logical stream, if provided.
+    pub content_type: Option<String>,
+}
+
+/// A packet, and information about the OGG page it was read from.
+///
+/// This is lower-level information than is provided by `FormatReader::next_packet`, and is
+/// primarily useful for inspecting the packetization of a stream.
+pub struct OggPacket {
+    /// The packet.
+    pub packet: Packet,
+    /// The absolute granule position of the page the packet ended on. The interpretation of the
+    /// granule position is codec specific.
+    pub absgp: u64,
+    /// If `true`, the packet is the last packet that ended on the page.
+    pub is_last_in_page: bool,
 }
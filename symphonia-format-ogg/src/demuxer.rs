@@ -5,9 +5,12 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::io::{Seek, SeekFrom};
 
+use symphonia_core::diagnostics::{DiagnosticEvent, Diagnostics};
 use symphonia_core::errors::{reset_error, seek_error, unsupported_error};
 use symphonia_core::errors::{Error, Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
@@ -15,10 +18,11 @@ use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered
 use symphonia_core::meta::{Metadata, MetadataLog};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::support_format;
+use symphonia_core::units::Time;
 
 use log::{debug, info, warn};
 
-use super::common::SideData;
+use super::common::{Fisbone, OggPacket, SideData};
 use super::logical::LogicalStream;
 use super::mappings;
 use super::page::*;
@@ -27,12 +31,19 @@ use super::physical;
 /// OGG demultiplexer.
 ///
 /// `OggReader` implements a demuxer for Xiph's OGG container format.
+///
+/// If a new physical stream is chained to the current physical stream, as is common when streaming
+/// (e.g., Icecast), then `next_packet` will return the remaining packets of the current physical
+/// stream, read the headers of the new physical stream, and then return `ResetRequired`. The
+/// tracks are replaced with those of the new physical stream, and a new revision of metadata is
+/// pushed. Corrupt pages encountered while reading the headers are skipped.
 pub struct OggReader {
     reader: MediaSourceStream,
     tracks: Vec<Track>,
     cues: Vec<Cue>,
     metadata: MetadataLog,
     options: FormatOptions,
+    diagnostics: Diagnostics,
     /// The page reader.
     pages: PageReader,
     /// `LogicalStream` for each serial.
@@ -41,26 +52,37 @@ pub struct OggReader {
     phys_byte_range_start: u64,
     /// The position of the first byte of the next physical stream, if available.
     phys_byte_range_end: Option<u64>,
+    /// The duration of the current physical stream, if known.
+    duration: Option<Time>,
 }
 
 impl OggReader {
-    fn read_page(&mut self) -> Result<()> {
+    /// Read the next page, skipping any corrupt or invalid pages.
+    fn next_page(&mut self) -> Result<()> {
         // Try reading pages until a page is successfully read, or an IO error.
         loop {
             match self.pages.try_next_page(&mut self.reader) {
-                Ok(_) => break,
+                Ok(_) => return Ok(()),
                 Err(Error::IoError(e)) => return Err(Error::from(e)),
                 Err(e) => {
                     warn!("{}", e);
                 }
             }
         }
+    }
+
+    fn read_page(&mut self) -> Result<()> {
+        self.next_page()?;
 
         let page = self.pages.page();
 
         // If the page is marked as a first page, then try to start a new physical stream.
         if page.header.is_first_page {
             self.start_new_physical_stream()?;
+
+            let pos = self.reader.pos();
+            self.diagnostics.emit(DiagnosticEvent::ResetRequired { pos });
+
             return reset_error();
         }
 
@@ -96,18 +118,25 @@ impl OggReader {
         }
     }
 
-    fn next_logical_packet(&mut self) -> Result<Packet> {
+    /// Get the next packet from the container, and information about the page it was read from.
+    ///
+    /// Unlike `next_packet`, which only provides the timeline of the packet, this also provides the
+    /// granule position of the page the packet ended on, and if the packet was the last packet to
+    /// end on the page. This may be used by tooling to inspect the packetization of a stream.
+    pub fn next_ogg_packet(&mut self) -> Result<OggPacket> {
         loop {
-            let page = self.pages.page();
-
-            // Read the next packet. Packets are only ever buffered in the logical stream of the
-            // current page.
-            if let Some(stream) = self.streams.get_mut(&page.header.serial) {
-                if let Some(packet) = stream.next_packet() {
-                    return Ok(packet);
-                }
+            // Read the next packet. Packets are only ever buffered in one logical stream at a time.
+            // This is usually the logical stream of the current page. However, after starting a
+            // new physical stream, the current page may have been replaced by a page read while
+            // probing the physical stream. Therefore, find the logical stream with packets instead.
+            let packet = self.streams.values_mut().find_map(|stream| stream.next_packet());
+
+            if let Some(packet) = packet {
+                return Ok(packet);
             }
 
+            // All packets of the current physical stream must be consumed before a new physical
+            // stream is started when reading the next page.
             self.read_page()?;
         }
     }
@@ -197,20 +226,47 @@ impl OggReader {
             }
         }
 
+        // The last packet of the track consumed while searching for the desired timestamp.
+        let mut prev_packet = None;
+
         // Consume packets until reaching the desired timestamp.
         let actual_ts = loop {
             match self.peek_logical_packet() {
                 Some(packet) => {
-                    if packet.track_id() == serial && packet.ts + packet.dur >= required_ts {
-                        break packet.ts;
-                    }
+                    if packet.track_id() == serial {
+                        if packet.ts + packet.dur >= required_ts {
+                            break packet.ts;
+                        }
 
-                    self.discard_logical_packet();
+                        // The current page belongs to the track, so take the packet from its
+                        // logical stream instead of discarding it.
+                        prev_packet = self.streams.get_mut(&serial).unwrap().next_packet();
+                    }
+                    else {
+                        self.discard_logical_packet();
+                    }
                 }
                 _ => self.read_page()?,
             }
         };
 
+        // If the decoder only produces audio for a packet after decoding the packet before it,
+        // then return the previous packet first. It is given a duration of 0 since the decoder
+        // only uses it to prime itself.
+        let stream = self.streams.get_mut(&serial).unwrap();
+
+        if stream.needs_priming_packet() {
+            if let Some(mut prev) = prev_packet {
+                prev.packet.ts = actual_ts;
+                prev.packet.dur = 0;
+                prev.packet.trim_start = 0;
+                prev.packet.trim_end = 0;
+                prev.is_last_in_page = false;
+
+                stream.requeue_packet(prev);
+            }
+        }
+
         debug!(
             "seeked track={:#x} to packet_ts={} (delta={})",
             serial,
@@ -228,6 +284,9 @@ impl OggReader {
         // The start of page position.
         let mut byte_range_start = self.reader.pos();
 
+        // Fisbones, from an OGG Skeleton stream, describing the logical streams.
+        let mut fisbones = Vec::new();
+
         // Pre-condition: This function is only called when the current page is marked as a
         // first page.
         assert!(self.pages.header().is_first_page);
@@ -264,7 +323,7 @@ impl OggReader {
             }
 
             // Read the next page.
-            self.pages.try_next_page(&mut self.reader)?;
+            self.next_page()?;
         }
 
         // Each logical stream may contain additional header packets after the identification packet
@@ -282,6 +341,7 @@ impl OggReader {
                 for data in side_data {
                     match data {
                         SideData::Metadata(rev) => self.metadata.push(rev),
+                        SideData::Fisbone(fisbone) => fisbones.push(fisbone),
                     }
                 }
 
@@ -294,7 +354,12 @@ impl OggReader {
             // the end of the current page.
             byte_range_start = self.reader.pos();
 
-            self.pages.try_next_page(&mut self.reader)?;
+            self.next_page()?;
+        }
+
+        // Apply the information from the Skeleton stream, if present.
+        for fisbone in fisbones {
+            apply_fisbone(&mut streams, fisbone);
         }
 
         // Probe the logical streams for their start and end pages.
@@ -321,8 +386,8 @@ impl OggReader {
         // First, clear the existing track listing.
         self.tracks.clear();
 
-        // Second, add a track for all streams.
-        for (&serial, stream) in streams.iter() {
+        // Second, add a track for all streams that should be exposed as tracks.
+        for (&serial, stream) in streams.iter().filter(|(_, stream)| stream.is_track()) {
             // Warn if the track is not ready. This should not happen if the physical stream was
             // muxed properly.
             if !stream.is_ready() {
@@ -332,7 +397,15 @@ impl OggReader {
             self.tracks.push(Track::new(serial, stream.codec_params().clone()));
         }
 
-        // Third, replace all logical streams with the new set.
+        // Third, the duration of the physical stream is the duration of its longest track, as
+        // stated by the granule position of the last page of the track.
+        self.duration = streams
+            .values()
+            .filter(|stream| stream.is_track())
+            .filter_map(|stream| stream.duration())
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        // Fourth, replace all logical streams with the new set.
         self.streams = streams;
 
         // Last, store the lower and upper byte boundaries of the physical stream for seeking.
@@ -343,6 +416,38 @@ impl OggReader {
     }
 }
 
+/// Apply the information in a Skeleton fisbone to the logical stream it describes.
+fn apply_fisbone(streams: &mut BTreeMap<u32, LogicalStream>, fisbone: Fisbone) {
+    let stream = match streams.get_mut(&fisbone.serial) {
+        Some(stream) => stream,
+        _ => {
+            warn!("skeleton fisbone for unknown stream with serial={:#x}", fisbone.serial);
+            return;
+        }
+    };
+
+    info!(
+        "skeleton fisbone for stream with serial={:#x}, content_type={}",
+        fisbone.serial,
+        fisbone.content_type.as_deref().unwrap_or("unknown")
+    );
+
+    let params = stream.codec_params_mut();
+
+    // If the mapper could not determine the time base of the stream, derive it from the granule
+    // rate.
+    if params.time_base.is_none() {
+        let (numer, denom) = fisbone.granule_rate;
+
+        match (u32::try_from(denom), u32::try_from(numer)) {
+            (Ok(denom), Ok(numer)) if denom > 0 && numer > 0 => {
+                params.with_time_base(TimeBase::new(denom, numer));
+            }
+            _ => warn!("skeleton fisbone has an unsupported granule rate"),
+        }
+    }
+}
+
 impl QueryDescriptor for OggReader {
     fn query() -> &'static [Descriptor] {
         &[support_format!(
@@ -377,9 +482,11 @@ impl FormatReader for OggReader {
             metadata: Default::default(),
             streams: Default::default(),
             options: *options,
+            diagnostics: Default::default(),
             pages,
             phys_byte_range_start: 0,
             phys_byte_range_end: None,
+            duration: None,
         };
 
         ogg.start_new_physical_stream()?;
@@ -388,7 +495,7 @@ impl FormatReader for OggReader {
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
-        self.next_logical_packet()
+        Ok(self.next_ogg_packet()?.packet)
     }
 
     fn metadata(&mut self) -> Metadata<'_> {
@@ -403,6 +510,15 @@ impl FormatReader for OggReader {
         &self.tracks
     }
 
+    fn duration(&self) -> Option<Time> {
+        self.duration
+    }
+
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.pages.set_diagnostics(diagnostics.clone());
+        self.diagnostics = diagnostics;
+    }
+
     fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
         // Get the timestamp of the desired audio frame.
         let (required_ts, serial) = match to {
@@ -489,3 +605,599 @@ impl FormatReader for OggReader {
         self.reader
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use symphonia_core::checksum::Crc32;
+    use symphonia_core::diagnostics::{DiagnosticEvent, Diagnostics};
+    use symphonia_core::errors::Error;
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSource, MediaSourceStream, Monitor, ReadOnlySource};
+    use symphonia_core::meta::StandardVisualKey;
+    use symphonia_core::units::TimeBase;
+
+    use super::OggReader;
+
+    /// Builds an OGG page, for the logical stream with serial 1, containing complete packets only.
+    fn make_page(flags: u8, absgp: u64, sequence: u32, packets: &[&[u8]]) -> Vec<u8> {
+        make_serial_page(1, flags, absgp, sequence, packets)
+    }
+
+    /// Builds an OGG page, for the logical stream with the given serial, containing complete
+    /// packets only.
+    fn make_serial_page(
+        serial: u32,
+        flags: u8,
+        absgp: u64,
+        sequence: u32,
+        packets: &[&[u8]],
+    ) -> Vec<u8> {
+        let mut page = b"OggS\x00".to_vec();
+        page.push(flags);
+        page.extend_from_slice(&absgp.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(packets.len() as u8);
+
+        for packet in packets {
+            assert!(packet.len() < 255);
+            page.push(packet.len() as u8);
+        }
+
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+
+        let mut crc32 = Crc32::new(0);
+        crc32.process_buf_bytes(&page);
+        page[22..26].copy_from_slice(&crc32.crc().to_le_bytes());
+
+        page
+    }
+
+    #[test]
+    fn verify_page_sequence_gap_is_discontinuity() {
+        let ident = b"OpusHead\x01\x01\x00\x00\x80\xbb\x00\x00\x00\x00\x00";
+        let comment = b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00";
+
+        // Each Opus packet has a duration of 20ms, or 960 frames.
+        let audio = [0x08u8, 0x00];
+
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment]));
+        stream.extend(make_page(0x00, 1920, 2, &[&audio, &audio]));
+        // Page 3 is lost.
+        stream.extend(make_page(0x00, 5760, 4, &[&audio, &audio]));
+        stream.extend(make_page(0x04, 7680, 5, &[&audio]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let mut packets = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            packets.push((packet.ts(), packet.is_discontinuity()));
+        }
+
+        assert_eq!(
+            packets,
+            &[(0, false), (960, false), (3840, true), (4800, false), (6720, false)]
+        );
+    }
+
+    #[test]
+    fn verify_opus_metadata_block_picture() {
+        let ident = b"OpusHead\x01\x01\x00\x00\x80\xbb\x00\x00\x00\x00\x00";
+
+        // A base64 encoded FLAC picture block of a front cover with a PNG media type.
+        let picture = b"METADATA_BLOCK_PICTURE=\
+            AAAAAwAAAAlpbWFnZS9wbmcAAAAAAAAAAQAAAAEAAAAYAAAAAAAAAASJUE5H";
+
+        let mut comment = b"OpusTags\x00\x00\x00\x00\x01\x00\x00\x00".to_vec();
+        comment.extend_from_slice(&(picture.len() as u32).to_le_bytes());
+        comment.extend_from_slice(picture);
+
+        let audio = [0x08u8, 0x00];
+
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[&comment]));
+        stream.extend(make_page(0x04, 960, 2, &[&audio]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let metadata = reader.metadata();
+        let rev = metadata.current().unwrap();
+
+        assert!(rev.tags().is_empty());
+        assert_eq!(rev.visuals().len(), 1);
+
+        let visual = &rev.visuals()[0];
+
+        assert_eq!(visual.media_type, "image/png");
+        assert_eq!(visual.usage, Some(StandardVisualKey::FrontCover));
+        assert_eq!(&visual.data[..], b"\x89PNG");
+    }
+
+    #[test]
+    fn verify_rewind() {
+        let ident = b"OpusHead\x01\x01\x00\x00\x80\xbb\x00\x00\x00\x00\x00";
+        let comment = b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00";
+
+        let audio = [[0x08u8, 0x00], [0x08u8, 0x01], [0x08u8, 0x02]];
+
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment]));
+        stream.extend(make_page(0x00, 1920, 2, &[&audio[0], &audio[1]]));
+        stream.extend(make_page(0x04, 2880, 3, &[&audio[2]]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let mut read_all = || {
+            let mut packets = Vec::new();
+
+            while let Ok(packet) = reader.next_packet() {
+                packets.push((packet.ts(), packet.buf().to_vec()));
+            }
+
+            reader.rewind().unwrap();
+
+            packets
+        };
+
+        let first = read_all();
+        let second = read_all();
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn verify_opus_pre_skip() {
+        // An Opus stream with a pre-skip of 312 frames, and 2000 frames of audio.
+        let ident = b"OpusHead\x01\x01\x38\x01\x80\xbb\x00\x00\x00\x00\x00";
+        let comment = b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00";
+
+        let audio = [0x08u8, 0x00];
+
+        // The granule positions include the pre-skip.
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment]));
+        stream.extend(make_page(0x00, 1920, 2, &[&audio, &audio]));
+        stream.extend(make_page(0x04, 2312, 3, &[&audio]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut options = FormatOptions::default();
+        options.with_gapless(true);
+        let mut reader = OggReader::try_new(mss, &options).unwrap();
+
+        let params = &reader.default_track().unwrap().codec_params;
+
+        assert_eq!(params.delay, Some(312));
+        assert_eq!(params.padding, Some(568));
+        assert_eq!(params.n_frames, Some(2000));
+
+        let mut packets = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            packets.push((packet.ts(), packet.dur(), packet.trim_start(), packet.trim_end()));
+        }
+
+        // The pre-skip is trimmed from the first packet.
+        assert_eq!(packets, &[(0, 648, 312, 0), (648, 960, 0, 0), (1608, 392, 0, 568)]);
+
+        let n_frames: u64 = packets.iter().map(|&(_, dur, _, _)| dur).sum();
+        assert_eq!(n_frames, 2000);
+    }
+
+    #[test]
+    fn verify_duration() {
+        // An Opus stream with a pre-skip of 312 frames, and 2000 frames of audio.
+        let ident = b"OpusHead\x01\x01\x38\x01\x80\xbb\x00\x00\x00\x00\x00";
+        let comment = b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00";
+
+        let audio = [0x08u8, 0x00];
+
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment]));
+        stream.extend(make_page(0x00, 1920, 2, &[&audio, &audio]));
+        stream.extend(make_page(0x04, 2312, 3, &[&audio]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        // Without gapless playback, the track includes the pre-skip and padding frames, however
+        // the duration stated by the last granule position does not.
+        let params = &reader.default_track().unwrap().codec_params;
+        assert_eq!(params.n_frames, Some(2880));
+
+        assert_eq!(reader.duration(), Some(TimeBase::new(1, 48_000).calc_time(2000)));
+    }
+
+    #[test]
+    fn verify_ogg_packet_page_info() {
+        let ident = b"OpusHead\x01\x01\x00\x00\x80\xbb\x00\x00\x00\x00\x00";
+        let comment = b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00";
+
+        let audio = [0x08u8, 0x00];
+
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment]));
+        stream.extend(make_page(0x00, 1920, 2, &[&audio, &audio]));
+        stream.extend(make_page(0x00, 3840, 3, &[&audio, &audio]));
+        stream.extend(make_page(0x04, 4800, 4, &[&audio]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let mut packets = Vec::new();
+
+        while let Ok(ogg_packet) = reader.next_ogg_packet() {
+            packets.push((ogg_packet.packet.ts(), ogg_packet.absgp, ogg_packet.is_last_in_page));
+        }
+
+        assert_eq!(
+            packets,
+            &[
+                (0, 1920, false),
+                (960, 1920, true),
+                (1920, 3840, false),
+                (2880, 3840, true),
+                (3840, 4800, true),
+            ]
+        );
+
+        // The granule positions must be monotonically increasing.
+        assert!(packets.windows(2).all(|w| w[0].1 <= w[1].1));
+    }
+
+    #[test]
+    fn verify_skeleton() {
+        /// Builds a version 3 Skeleton fisbone packet.
+        fn make_fisbone(serial: u32, granule_rate: (u64, u64), content_type: &str) -> Vec<u8> {
+            let mut fisbone = b"fisbone\x00".to_vec();
+            fisbone.extend_from_slice(&44u32.to_le_bytes());
+            fisbone.extend_from_slice(&serial.to_le_bytes());
+            fisbone.extend_from_slice(&2u32.to_le_bytes());
+            fisbone.extend_from_slice(&granule_rate.0.to_le_bytes());
+            fisbone.extend_from_slice(&granule_rate.1.to_le_bytes());
+            fisbone.extend_from_slice(&[0; 8 + 4 + 1 + 3]);
+            fisbone.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+            fisbone
+        }
+
+        // A version 3.0 fishead packet with zeroed times.
+        let mut fishead = b"fishead\x00\x03\x00\x00\x00".to_vec();
+        fishead.extend_from_slice(&[0; 52]);
+
+        let ident = b"OpusHead\x01\x01\x00\x00\x80\xbb\x00\x00\x00\x00\x00";
+        let comment = b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00";
+        let unknown = b"\x80unknown";
+
+        let opus_bone = make_fisbone(1, (48000, 1), "audio/opus");
+        let unknown_bone = make_fisbone(3, (25, 1), "video/x-unknown");
+
+        let audio = [0x08u8, 0x00];
+
+        // The Skeleton stream has serial 2, the Opus stream has serial 1, and a stream for an
+        // unknown codec has serial 3.
+        let mut stream = Vec::new();
+        stream.extend(make_serial_page(2, 0x02, 0, 0, &[&fishead]));
+        stream.extend(make_serial_page(1, 0x02, 0, 0, &[ident]));
+        stream.extend(make_serial_page(3, 0x02, 0, 0, &[unknown]));
+        stream.extend(make_serial_page(2, 0x00, 0, 1, &[&opus_bone, &unknown_bone]));
+        stream.extend(make_serial_page(2, 0x04, 0, 2, &[&[]]));
+        stream.extend(make_serial_page(1, 0x00, 0, 1, &[comment]));
+        stream.extend(make_serial_page(1, 0x04, 1920, 2, &[&audio, &audio]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        // The Skeleton stream is not exposed as a track.
+        let tracks: Vec<_> =
+            reader.tracks().iter().map(|track| (track.id, track.codec_params.time_base)).collect();
+
+        // The time base of the unknown stream is derived from the granule rate of its fisbone.
+        assert_eq!(tracks, &[(1, Some(TimeBase::new(1, 48000))), (3, Some(TimeBase::new(1, 25)))]);
+
+        let mut packets = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            packets.push((packet.track_id(), packet.ts()));
+        }
+
+        assert_eq!(packets, &[(1, 0), (1, 960)]);
+    }
+
+    #[test]
+    fn verify_diagnostics() {
+        let ident = b"OpusHead\x01\x01\x00\x00\x80\xbb\x00\x00\x00\x00\x00";
+        let comment = b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00";
+
+        let audio = [0x08u8, 0x00];
+
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment]));
+        stream.extend(make_page(0x00, 960, 2, &[&audio]));
+
+        // Corrupt the packet of the fourth page.
+        let corrupt_pos = stream.len() as u64;
+        let mut corrupt = make_page(0x00, 1920, 3, &[&audio]);
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        stream.extend(corrupt);
+
+        stream.extend(make_page(0x04, 2880, 4, &[&audio]));
+
+        // A chained physical stream.
+        let reset_pos = stream.len() as u64;
+        stream.extend(make_serial_page(2, 0x02, 0, 0, &[ident]));
+        stream.extend(make_serial_page(2, 0x00, 0, 1, &[comment]));
+        stream.extend(make_serial_page(2, 0x04, 960, 2, &[&audio]));
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let handler = {
+            let events = Arc::clone(&events);
+            move |event: &DiagnosticEvent| events.lock().unwrap().push(event.clone())
+        };
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+        reader.set_diagnostics(Diagnostics::new(Arc::new(handler)));
+
+        let mut packets = Vec::new();
+
+        loop {
+            match reader.next_packet() {
+                Ok(packet) => packets.push(packet.ts()),
+                Err(Error::ResetRequired) => break,
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+
+        // The packet of the corrupt page is lost.
+        assert_eq!(packets, &[0, 1920]);
+
+        // The corrupt page is read once while scanning for the end of the physical stream when the
+        // reader was instantiated, but only reported when it is read again while reading packets.
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+
+        match events[0] {
+            DiagnosticEvent::CrcMismatch { pos, ts, expected, computed } => {
+                assert_eq!(pos, Some(corrupt_pos));
+                assert_eq!(ts, None);
+                assert_ne!(expected, computed);
+            }
+            _ => panic!("expected a crc mismatch"),
+        }
+
+        // The reset is reported after the headers of the new physical stream were read.
+        match events[1] {
+            DiagnosticEvent::ResetRequired { pos } => assert!(pos > reset_pos),
+            _ => panic!("expected a reset"),
+        }
+    }
+
+    #[test]
+    fn verify_chained_stream_reset() {
+        // A mono 8kHz, and a stereo 16kHz Vorbis identification header, both with block sizes of
+        // 256.
+        let ident_1 = b"\x01vorbis\x00\x00\x00\x00\x01\x40\x1f\x00\x00\
+            \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x88\x01";
+        let ident_2 = b"\x01vorbis\x00\x00\x00\x00\x02\x80\x3e\x00\x00\
+            \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x88\x01";
+        let comment = b"\x03vorbis\x00\x00\x00\x00\x00\x00\x00\x00\x01";
+        let setup = make_vorbis_setup();
+
+        let audio: Vec<[u8; 1]> = (0..4).map(|i| [i << 1]).collect();
+        let audio: Vec<&[u8]> = audio.iter().map(|packet| &packet[..]).collect();
+
+        let mut stream = Vec::new();
+        stream.extend(make_serial_page(1, 0x02, 0, 0, &[ident_1]));
+        stream.extend(make_serial_page(1, 0x00, 0, 1, &[comment, &setup]));
+        stream.extend(make_serial_page(1, 0x04, 384, 2, &audio));
+        // The second stream starts with a corrupt page following its first page, as may happen
+        // when streaming.
+        stream.extend(make_serial_page(2, 0x02, 0, 0, &[ident_2]));
+        let mut corrupt = make_serial_page(2, 0x00, 0, 1, &[comment, &setup]);
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        stream.extend(corrupt);
+        stream.extend(make_serial_page(2, 0x00, 0, 1, &[comment, &setup]));
+        stream.extend(make_serial_page(2, 0x04, 384, 2, &audio));
+
+        // Test with a seekable and an unseekable source.
+        let sources: Vec<Box<dyn MediaSource>> = vec![
+            Box::new(Cursor::new(stream.clone())),
+            Box::new(ReadOnlySource::new(Cursor::new(stream))),
+        ];
+
+        for source in sources {
+            let mss = MediaSourceStream::new(source, Default::default());
+
+            let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+            let mut events = Vec::new();
+
+            loop {
+                match reader.next_packet() {
+                    Ok(packet) => events.push(Some((packet.track_id(), packet.ts()))),
+                    Err(Error::ResetRequired) => {
+                        // The tracks are replaced before the reset is signalled.
+                        let tracks = reader.tracks();
+                        assert_eq!(tracks.len(), 1);
+                        assert_eq!(tracks[0].id, 2);
+                        assert_eq!(tracks[0].codec_params.sample_rate, Some(16000));
+                        assert_eq!(tracks[0].codec_params.channels.unwrap().count(), 2);
+                        events.push(None);
+                    }
+                    Err(Error::IoError(_)) => break,
+                    Err(err) => panic!("unexpected error: {}", err),
+                }
+            }
+
+            let stream: Vec<_> = (0..4).map(|i| Some((1, 128 * i.max(1) - 128))).collect();
+
+            let mut expected = stream.clone();
+            expected.push(None);
+            expected.extend(stream.iter().map(|event| event.map(|(_, ts)| (2, ts))));
+
+            assert_eq!(events, expected);
+        }
+    }
+
+    /// Builds a Vorbis setup header with a single codebook, floor, residue, mapping, and mode. The
+    /// setup is only complete enough to be parsed by the Vorbis mapper.
+    fn make_vorbis_setup() -> Vec<u8> {
+        let mut bits = Vec::new();
+
+        let mut put = |value: u32, len: u32| {
+            for i in 0..len {
+                bits.push(i < 32 && (value >> i) & 1 == 1);
+            }
+        };
+
+        // A codebook with 1 entry, 1 dimension, and no lookup table.
+        put(0, 8);
+        put(0x564342, 24);
+        put(1, 16);
+        put(1, 24);
+        put(0, 1 + 1 + 5 + 4);
+        // A placeholder time-domain transform.
+        put(0, 6 + 16);
+        // A type 1 floor without partitions.
+        put(0, 6);
+        put(1, 16);
+        put(0, 5 + 2 + 4);
+        // A type 0 residue with 1 classification.
+        put(0, 6 + 16 + 72 + 6 + 8 + 3 + 1);
+        // A type 0 mapping with 1 submap.
+        put(0, 6 + 16 + 1 + 1 + 2 + 24);
+        // A mode using the short block size.
+        put(0, 6 + 1 + 16 + 16 + 8);
+        // The framing flag.
+        put(1, 1);
+
+        let mut setup = b"\x05vorbis".to_vec();
+
+        for byte in bits.chunks(8) {
+            setup.push(byte.iter().rev().fold(0, |acc, &bit| (acc << 1) | bit as u8));
+        }
+
+        setup
+    }
+
+    #[test]
+    fn verify_vorbis_seek_returns_priming_packet() {
+        // A mono 8kHz Vorbis stream with block sizes of 256.
+        let ident = b"\x01vorbis\x00\x00\x00\x00\x01\x40\x1f\x00\x00\
+            \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x88\x01";
+        let comment = b"\x03vorbis\x00\x00\x00\x00\x00\x00\x00\x00\x01";
+        let setup = make_vorbis_setup();
+
+        // Each audio packet is identified by the packet number in the upper 7 bits. After the
+        // first packet, each packet has a duration of 128 frames.
+        let audio: Vec<[u8; 1]> = (0..12).map(|i| [i << 1]).collect();
+        let audio: Vec<&[u8]> = audio.iter().map(|packet| &packet[..]).collect();
+
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment, &setup]));
+        stream.extend(make_page(0x00, 384, 2, &audio[0..4]));
+        stream.extend(make_page(0x00, 896, 3, &audio[4..8]));
+        stream.extend(make_page(0x04, 1408, 4, &audio[8..12]));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(stream)), Default::default());
+
+        let mut reader = OggReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let mut seek = |ts| {
+            let seeked_to =
+                reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id: 1 }).unwrap();
+
+            let mut packets = Vec::new();
+
+            for _ in 0..2 {
+                let packet = reader.next_packet().unwrap();
+                packets.push((packet.buf()[0] >> 1, packet.ts(), packet.dur()));
+            }
+
+            (seeked_to.actual_ts, packets)
+        };
+
+        // The first packet produces no audio, so no priming packet is required.
+        assert_eq!(seek(0), (0, vec![(0, 0, 0), (1, 0, 128)]));
+        // The packet preceding the packet containing the timestamp is returned first.
+        assert_eq!(seek(100), (0, vec![(0, 0, 0), (1, 0, 128)]));
+        assert_eq!(seek(600), (512, vec![(4, 512, 0), (5, 512, 128)]));
+        // The priming packet may be on the previous page.
+        assert_eq!(seek(1000), (896, vec![(7, 896, 0), (8, 896, 128)]));
+        assert_eq!(seek(1407), (1280, vec![(10, 1280, 0), (11, 1280, 128)]));
+    }
+
+    #[test]
+    fn verify_vorbis_end_trim() {
+        // A mono 8kHz Vorbis stream with block sizes of 256.
+        let ident = b"\x01vorbis\x00\x00\x00\x00\x01\x40\x1f\x00\x00\
+            \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x88\x01";
+        let comment = b"\x03vorbis\x00\x00\x00\x00\x00\x00\x00\x00\x01";
+        let setup = make_vorbis_setup();
+
+        let audio: Vec<[u8; 1]> = (0..8).map(|i| [i << 1]).collect();
+        let audio: Vec<&[u8]> = audio.iter().map(|packet| &packet[..]).collect();
+
+        // The packets of the last page decode to 512 frames, but the granule position of the last
+        // page truncates the stream by 100 frames.
+        let mut stream = Vec::new();
+        stream.extend(make_page(0x02, 0, 0, &[ident]));
+        stream.extend(make_page(0x00, 0, 1, &[comment, &setup]));
+        stream.extend(make_page(0x00, 384, 2, &audio[0..4]));
+        stream.extend(make_page(0x04, 796, 3, &audio[4..8]));
+
+        // The end trim must be the same when the end of the stream can, and cannot, be inspected.
+        let sources: Vec<Box<dyn MediaSource>> = vec![
+            Box::new(Cursor::new(stream.clone())),
+            Box::new(ReadOnlySource::new(Cursor::new(stream))),
+        ];
+
+        for source in sources {
+            let mss = MediaSourceStream::new(source, Default::default());
+
+            let mut options = FormatOptions::default();
+            options.with_gapless(true);
+            let mut reader = OggReader::try_new(mss, &options).unwrap();
+
+            let mut packets = Vec::new();
+
+            while let Ok(packet) = reader.next_packet() {
+                packets.push((packet.ts(), packet.dur(), packet.trim_end()));
+            }
+
+            assert_eq!(packets.len(), 8);
+            assert_eq!(packets[7], (768, 28, 100));
+
+            let n_frames: u64 = packets.iter().map(|&(_, dur, _)| dur).sum();
+            assert_eq!(n_frames, 796);
+        }
+    }
+}
@@ -21,4 +21,5 @@ mod mappings;
 mod page;
 mod physical;
 
+pub use common::OggPacket;
 pub use demuxer::OggReader;
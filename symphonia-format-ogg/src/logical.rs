@@ -10,8 +10,9 @@ use std::collections::VecDeque;
 use symphonia_core::codecs::CodecParameters;
 use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::formats::Packet;
+use symphonia_core::units::Time;
 
-use super::common::SideData;
+use super::common::{OggPacket, SideData};
 use super::mappings::Mapper;
 use super::mappings::{MapResult, PacketParser};
 use super::page::Page;
@@ -41,13 +42,18 @@ pub struct InspectState {
 
 pub struct LogicalStream {
     mapper: Box<dyn Mapper>,
-    packets: VecDeque<Packet>,
+    packets: VecDeque<OggPacket>,
     part_buf: Vec<u8>,
     part_len: usize,
     prev_page_info: Option<PageInfo>,
+    /// The absolute granule position of the last page that completed a packet of stream data.
+    prev_data_absgp: Option<u64>,
     start_bound: Option<Bound>,
     end_bound: Option<Bound>,
     gapless: bool,
+    /// Set when packets were lost or dropped, and the next packet should be flagged as a
+    /// discontinuity.
+    discontinuity: bool,
 }
 
 impl LogicalStream {
@@ -60,9 +66,11 @@ impl LogicalStream {
             part_buf: Default::default(),
             part_len: 0,
             prev_page_info: None,
+            prev_data_absgp: None,
             start_bound: None,
             end_bound: None,
             gapless,
+            discontinuity: false,
         }
     }
 
@@ -70,6 +78,8 @@ impl LogicalStream {
     pub fn reset(&mut self) {
         self.part_len = 0;
         self.prev_page_info = None;
+        self.prev_data_absgp = None;
+        self.discontinuity = false;
         self.packets.clear();
         self.mapper.reset();
     }
@@ -79,29 +89,62 @@ impl LogicalStream {
         self.mapper.is_ready()
     }
 
+    /// Returns true if the stream should be exposed as a track.
+    pub fn is_track(&self) -> bool {
+        self.mapper.is_track()
+    }
+
+    /// Returns true if the packet preceding the first packet after a seek must also be decoded.
+    pub fn needs_priming_packet(&self) -> bool {
+        self.mapper.needs_priming_packet()
+    }
+
     /// Get the `CodecParameters` for the logical stream.
     pub fn codec_params(&self) -> &CodecParameters {
         self.mapper.codec_params()
     }
 
+    /// Get the duration of the logical stream as stated by the granule position of its last page.
+    /// Returns `None` if the last page has not been found, or the timebase is unknown.
+    pub fn duration(&self) -> Option<Time> {
+        let end_bound = self.end_bound.as_ref()?;
+        let time_base = self.codec_params().time_base?;
+
+        // The end bound includes the start delay if gapless playback is disabled.
+        let start_delay = match &self.start_bound {
+            Some(start_bound) if !self.gapless => start_bound.delay,
+            _ => 0,
+        };
+
+        Some(time_base.calc_time(end_bound.ts.saturating_sub(start_delay)))
+    }
+
+    /// Get a mutable reference to the `CodecParameters` for the logical stream.
+    pub fn codec_params_mut(&mut self) -> &mut CodecParameters {
+        self.mapper.codec_params_mut()
+    }
+
     /// Reads a page.
     pub fn read_page(&mut self, page: &Page<'_>) -> Result<Vec<SideData>> {
         // Side data vector. This will not allocate unless data is pushed to it (normal case).
         let mut side_data = Vec::new();
 
         // If the last sequence number is available, detect non-monotonicity and discontinuities
-        // in the stream. In these cases, clear any partial packet data.
+        // in the stream. In these cases, clear any partial packet data, and flag the next packet
+        // as a discontinuity.
         if let Some(last_ts) = &self.prev_page_info {
             if page.header.sequence < last_ts.seq {
                 warn!("detected stream page non-monotonicity");
                 self.part_len = 0;
+                self.discontinuity = true;
             }
             else if page.header.sequence - last_ts.seq > 1 {
                 warn!(
                     "detected stream discontinuity of {} page(s)",
-                    page.header.sequence - last_ts.seq
+                    page.header.sequence - last_ts.seq - 1
                 );
                 self.part_len = 0;
+                self.discontinuity = true;
             }
         }
 
@@ -116,6 +159,7 @@ impl LogicalStream {
 
             // Clear partial packet data.
             self.part_len = 0;
+            self.discontinuity = true;
         }
 
         // If there is no partial packet data buffered, a continuation page is not expected.
@@ -125,6 +169,7 @@ impl LogicalStream {
             if page.num_packets() > 0 {
                 warn!("unexpected continuation page, ignoring incomplete first packet");
                 iter.next();
+                self.discontinuity = true;
             }
             else {
                 warn!("unexpected continuation page, ignoring page");
@@ -144,12 +189,17 @@ impl LogicalStream {
             match self.mapper.map_packet(&data) {
                 Ok(MapResult::StreamData { dur }) => {
                     // Create a packet.
-                    self.packets.push_back(Packet::new_from_boxed_slice(
-                        page.header.serial,
-                        0,
-                        dur,
-                        data,
-                    ));
+                    let mut packet = Packet::new_from_boxed_slice(page.header.serial, 0, dur, data);
+
+                    // If packets were lost before this packet, flag it as a discontinuity.
+                    packet.set_discontinuity(self.discontinuity);
+                    self.discontinuity = false;
+
+                    self.packets.push_back(OggPacket {
+                        packet,
+                        absgp: page.header.absgp,
+                        is_last_in_page: false,
+                    });
                 }
                 Ok(MapResult::SideData { data }) => side_data.push(data),
                 Err(e) => {
@@ -169,44 +219,113 @@ impl LogicalStream {
         let num_new_packets = self.packets.len() - num_prev_packets;
 
         if num_new_packets > 0 {
-            // Get the start delay.
-            let start_delay = self.start_bound.as_ref().map_or(0, |b| b.delay);
-
-            // Assign timestamps by first calculating the timestamp of one past the last sample in
-            // in the last packet of this page, add the start delay.
-            let mut page_end_ts =
-                self.mapper.absgp_to_ts(page.header.absgp).saturating_add(start_delay);
-
-            // If this is the last page, then add the end delay to the timestamp.
-            if page.header.is_last_page {
-                let end_delay = self.end_bound.as_ref().map_or(0, |b| b.delay);
-                page_end_ts = page_end_ts.saturating_add(end_delay);
+            // The last packet queued from this page ended on this page, unless the page only
+            // contained the start of a packet.
+            if let Some(last) = self.packets.back_mut() {
+                last.is_last_in_page = true;
             }
 
-            // Then, iterate over the newly added packets in reverse order and subtract their
-            // cumulative duration at each iteration to get the timestamp of the first sample
-            // in each packet.
-            let mut page_dur = 0u64;
-
-            for packet in self.packets.iter_mut().rev().take(num_new_packets) {
-                page_dur = page_dur.saturating_add(packet.dur);
-                packet.ts = page_end_ts.saturating_sub(page_dur);
+            // If the end bound was not found by inspecting the end of the stream, such as when the
+            // stream is not seekable, derive it from the last page now.
+            if page.header.is_last_page && self.end_bound.is_none() {
+                self.end_bound = self.derive_end_bound(page, num_new_packets);
             }
 
-            if self.gapless {
-                for packet in self.packets.iter_mut().rev().take(num_new_packets) {
-                    symphonia_core::formats::util::trim_packet(
-                        packet,
-                        start_delay as u32,
-                        self.end_bound.as_ref().map(|b| b.ts),
-                    );
-                }
-            }
+            self.assign_timestamps(page, num_new_packets);
+
+            self.prev_data_absgp = Some(page.header.absgp);
         }
 
         Ok(side_data)
     }
 
+    /// Assign timestamps to, and trim, the last `num_packets` packets queued from the given page.
+    fn assign_timestamps(&mut self, page: &Page<'_>, num_packets: usize) {
+        // Get the start delay.
+        let start_delay = self.start_bound.as_ref().map_or(0, |b| b.delay);
+
+        // Assign timestamps by first calculating the timestamp of one past the last sample in
+        // in the last packet of this page, add the start delay.
+        let mut page_end_ts =
+            self.mapper.absgp_to_ts(page.header.absgp).saturating_add(start_delay);
+
+        // If this is the last page, then add the end delay to the timestamp.
+        if page.header.is_last_page {
+            let end_delay = self.end_bound.as_ref().map_or(0, |b| b.delay);
+            page_end_ts = page_end_ts.saturating_add(end_delay);
+        }
+
+        // Then, iterate over the newly added packets in reverse order and subtract their
+        // cumulative duration at each iteration to get the timestamp of the first sample
+        // in each packet.
+        let mut page_dur = 0u64;
+
+        for OggPacket { packet, .. } in self.packets.iter_mut().rev().take(num_packets) {
+            // Undo any previous trimming so that the packet may be re-timed.
+            packet.dur = packet.dur.saturating_add(u64::from(packet.trim_start + packet.trim_end));
+            packet.trim_start = 0;
+            packet.trim_end = 0;
+
+            page_dur = page_dur.saturating_add(packet.dur);
+            packet.ts = page_end_ts.saturating_sub(page_dur);
+        }
+
+        if self.gapless {
+            for ogg_packet in self.packets.iter_mut().rev().take(num_packets) {
+                symphonia_core::formats::util::trim_packet(
+                    &mut ogg_packet.packet,
+                    start_delay as u32,
+                    self.end_bound.as_ref().map(|b| b.ts),
+                );
+            }
+        }
+    }
+
+    /// Derive the end bound from the last page, and the last `num_packets` packets queued from it,
+    /// by comparing the granule position of the last page against the number of samples decoded
+    /// up-to the end of the page. Any excess samples are the end delay.
+    fn derive_end_bound(&self, page: &Page<'_>, num_packets: usize) -> Option<Bound> {
+        // The granule position of the previous page is required to count the decoded samples.
+        let prev_absgp = self.prev_data_absgp?;
+
+        let start_delay = self.start_bound.as_ref().map_or(0, |b| b.delay);
+
+        // The stated page end timestamp, calculated the same way as when inspecting the end page.
+        let page_end_ts = self
+            .mapper
+            .absgp_to_ts(page.header.absgp)
+            .saturating_add(if self.gapless { 0 } else { start_delay });
+
+        let page_dur = self
+            .packets
+            .iter()
+            .rev()
+            .take(num_packets)
+            .fold(0u64, |dur, ogg_packet| dur.saturating_add(ogg_packet.packet.dur));
+
+        // The actual page end timestamp is the timestamp of the previous page plus the decoded
+        // duration of this page.
+        let actual_page_end_ts = self
+            .mapper
+            .absgp_to_ts(prev_absgp)
+            .saturating_add(if self.gapless { 0 } else { start_delay })
+            .saturating_add(page_dur);
+
+        let delay = actual_page_end_ts.saturating_sub(page_end_ts);
+
+        Some(Bound { seq: page.header.sequence, ts: page_end_ts, delay })
+    }
+
+    /// Re-assign timestamps to the queued packets that were read from the given page.
+    fn retime_page_packets(&mut self, page: &Page<'_>) {
+        let num_packets =
+            self.packets.iter().rev().take_while(|p| p.absgp == page.header.absgp).count();
+
+        if num_packets > 0 {
+            self.assign_timestamps(page, num_packets);
+        }
+    }
+
     /// Returns true if the logical stream has packets buffered.
     pub fn has_packets(&self) -> bool {
         !self.packets.is_empty()
@@ -214,11 +333,11 @@ impl LogicalStream {
 
     /// Examine, but do not consume, the next packet.
     pub fn peek_packet(&self) -> Option<&Packet> {
-        self.packets.front()
+        self.packets.front().map(|ogg_packet| &ogg_packet.packet)
     }
 
     /// Consumes and returns the next packet.
-    pub fn next_packet(&mut self) -> Option<Packet> {
+    pub fn next_packet(&mut self) -> Option<OggPacket> {
         self.packets.pop_front()
     }
 
@@ -227,6 +346,11 @@ impl LogicalStream {
         self.packets.pop_front();
     }
 
+    /// Returns a packet to the front of the packet queue.
+    pub fn requeue_packet(&mut self, packet: OggPacket) {
+        self.packets.push_front(packet);
+    }
+
     /// Examine the first page of the non-setup codec bitstream to obtain the start time and start
     /// delay parameters.
     pub fn inspect_start_page(&mut self, page: &Page<'_>) {
@@ -273,6 +397,10 @@ impl LogicalStream {
 
         // Update start bound.
         self.start_bound = Some(bound);
+
+        // The packets of the start page are read before the start bound is known. Re-time them now
+        // that the start delay is known.
+        self.retime_page_packets(page);
     }
 
     /// Examines one or more of the last pages of the codec bitstream to obtain the end time and
@@ -362,7 +490,11 @@ impl LogicalStream {
                 codec_params.with_padding(bound.delay as u32);
             }
 
-            self.end_bound = Some(bound)
+            self.end_bound = Some(bound);
+
+            // If the stream consists of a single page, its packets were read before the end bound
+            // was known. Re-time them now that the end delay is known.
+            self.retime_page_packets(page);
         }
 
         // Update the state's bound.
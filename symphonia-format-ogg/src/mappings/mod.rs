@@ -12,6 +12,7 @@ use symphonia_core::errors::Result;
 
 mod flac;
 mod opus;
+mod skeleton;
 mod vorbis;
 
 /// Detect a `Mapper` for a logical stream given the identification packet of the stream.
@@ -19,6 +20,7 @@ pub fn detect(buf: &[u8]) -> Result<Option<Box<dyn Mapper>>> {
     let mapper = flac::detect(buf)?
         .or(vorbis::detect(buf)?)
         .or(opus::detect(buf)?)
+        .or(skeleton::detect(buf)?)
         .or_else(make_null_mapper);
 
     Ok(mapper)
@@ -74,6 +76,19 @@ pub trait Mapper: Send + Sync {
     fn is_ready(&self) -> bool {
         true
     }
+
+    /// Returns `true` if the stream should be exposed as a track. Streams that only carry
+    /// information about other streams should not be.
+    fn is_track(&self) -> bool {
+        true
+    }
+
+    /// Returns `true` if the decoder must decode the packet preceding a packet to produce the audio
+    /// of that packet. This is the case for codecs that overlap consecutive blocks, where the first
+    /// packet decoded after a reset only primes the decoder.
+    fn needs_priming_packet(&self) -> bool {
+        false
+    }
 }
 
 fn make_null_mapper() -> Option<Box<dyn Mapper>> {
@@ -142,7 +142,8 @@ pub fn detect(buf: &[u8]) -> Result<Option<Box<dyn Mapper>>> {
         .with_extra_data(Box::from(buf));
 
     // Instantiate the Opus mapper.
-    let mapper = Box::new(OpusMapper { codec_params, need_comment: true });
+    let mapper =
+        Box::new(OpusMapper { codec_params, need_comment: true, pre_skip: u64::from(pre_skip) });
 
     Ok(Some(mapper))
 }
@@ -193,7 +194,7 @@ impl PacketParser for OpusPacketParser {
             3 => match packet.get(1) {
                 Some(byte) => {
                     // TOC byte is followed by number of frames. See page 18 section 3.2.5 code 3
-                    let m = byte & 0b11111; // Note: it's actually called "M" in the rfc.
+                    let m = byte & 0b111111; // Note: it's actually called "M" in the rfc.
                     m as u64
                 }
                 None => {
@@ -213,6 +214,7 @@ impl PacketParser for OpusPacketParser {
 struct OpusMapper {
     codec_params: CodecParameters,
     need_comment: bool,
+    pre_skip: u64,
 }
 
 impl Mapper for OpusMapper {
@@ -232,6 +234,12 @@ impl Mapper for OpusMapper {
         &mut self.codec_params
     }
 
+    fn absgp_to_ts(&self, ts: u64) -> u64 {
+        // The granule position of an Opus stream includes the pre-skip samples, while the
+        // timestamp does not. The pre-skip then becomes the start delay of the stream.
+        ts.saturating_sub(self.pre_skip)
+    }
+
     fn make_parser(&self) -> Option<Box<dyn super::PacketParser>> {
         Some(Box::new(OpusPacketParser {}))
     }
@@ -264,3 +272,20 @@ impl Mapper for OpusMapper {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{OpusPacketParser, PacketParser};
+
+    #[test]
+    fn verify_packet_dur() {
+        let mut parser = OpusPacketParser {};
+
+        // SILK-only 20ms, one frame.
+        assert_eq!(parser.parse_next_packet_dur(&[0x08, 0x00]), 960);
+        // SILK-only 20ms, two frames.
+        assert_eq!(parser.parse_next_packet_dur(&[0x09, 0x00]), 1920);
+        // CELT-only 2.5ms, an arbitrary number of frames. Up-to 48 frames may be signalled.
+        assert_eq!(parser.parse_next_packet_dur(&[0x83, 0x28]), 40 * 120);
+    }
+}
@@ -0,0 +1,149 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common::{Fisbone, SideData};
+
+use super::{MapResult, Mapper, PacketParser};
+
+use symphonia_core::codecs::CodecParameters;
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+
+use log::warn;
+
+/// The minimum expected size of a Skeleton fishead packet (version 3.0).
+const OGG_SKELETON_MIN_FISHEAD_PACKET_SIZE: usize = 64;
+
+/// The minimum expected size of a Skeleton fisbone packet.
+const OGG_SKELETON_MIN_FISBONE_PACKET_SIZE: usize = 52;
+
+/// The signature for a Skeleton fishead packet.
+const OGG_SKELETON_FISHEAD_SIGNATURE: &[u8] = b"fishead\0";
+
+/// The signature for a Skeleton fisbone packet.
+const OGG_SKELETON_FISBONE_SIGNATURE: &[u8] = b"fisbone\0";
+
+/// The supported major version of the Skeleton bitstream.
+const OGG_SKELETON_VERSION_MAJOR_MIN: u16 = 3;
+const OGG_SKELETON_VERSION_MAJOR_MAX: u16 = 4;
+
+pub fn detect(buf: &[u8]) -> Result<Option<Box<dyn Mapper>>> {
+    // The fishead packet must be a minimum size.
+    if buf.len() < OGG_SKELETON_MIN_FISHEAD_PACKET_SIZE {
+        return Ok(None);
+    }
+
+    let mut reader = BufReader::new(buf);
+
+    // The first 8 bytes are the magic signature ASCII bytes.
+    let mut magic = [0; 8];
+    reader.read_buf_exact(&mut magic)?;
+
+    if magic != *OGG_SKELETON_FISHEAD_SIGNATURE {
+        return Ok(None);
+    }
+
+    // The next 16-bit integers are the major and minor version of the Skeleton bitstream.
+    let version_major = reader.read_u16()?;
+    let _version_minor = reader.read_u16()?;
+
+    if version_major < OGG_SKELETON_VERSION_MAJOR_MIN
+        || version_major > OGG_SKELETON_VERSION_MAJOR_MAX
+    {
+        return Ok(None);
+    }
+
+    // The presentation time, base time, and UTC time of the fishead packet are not used.
+
+    // Instantiate the Skeleton mapper. The Skeleton stream does not carry any codec bitstream.
+    let mapper = Box::new(SkeletonMapper { codec_params: CodecParameters::new() });
+
+    Ok(Some(mapper))
+}
+
+/// Read a fisbone packet. The signature must have already been read.
+fn read_fisbone(reader: &mut BufReader<'_>) -> Result<Fisbone> {
+    // The offset to the message header fields, relative to the offset field itself.
+    let msg_header_offset = reader.read_u32()?;
+
+    let serial = reader.read_u32()?;
+    let _n_header_packets = reader.read_u32()?;
+    let granule_rate_numer = reader.read_u64()?;
+    let granule_rate_denom = reader.read_u64()?;
+    let _base_granule = reader.read_u64()?;
+    let _preroll = reader.read_u32()?;
+    let _granule_shift = reader.read_u8()?;
+
+    // Skip any padding, and fields from later versions, to the message header fields.
+    if msg_header_offset < 41 {
+        return decode_error("ogg (skeleton): invalid fisbone message header offset");
+    }
+
+    reader.ignore_bytes(u64::from(msg_header_offset - 41))?;
+
+    // The message header fields are a sequence of "Name: value" lines terminated by CRLF.
+    let fields = String::from_utf8_lossy(reader.read_buf_bytes_available_ref());
+
+    let content_type = fields.split("\r\n").find_map(|field| {
+        let (name, value) = field.split_at(field.find(':')?);
+        if name.trim().eq_ignore_ascii_case("content-type") {
+            Some(value[1..].trim().to_string())
+        }
+        else {
+            None
+        }
+    });
+
+    Ok(Fisbone { serial, granule_rate: (granule_rate_numer, granule_rate_denom), content_type })
+}
+
+struct SkeletonMapper {
+    codec_params: CodecParameters,
+}
+
+impl Mapper for SkeletonMapper {
+    fn name(&self) -> &'static str {
+        "skeleton"
+    }
+
+    fn reset(&mut self) {
+        // Nothing to do.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.codec_params
+    }
+
+    fn codec_params_mut(&mut self) -> &mut CodecParameters {
+        &mut self.codec_params
+    }
+
+    fn make_parser(&self) -> Option<Box<dyn PacketParser>> {
+        None
+    }
+
+    fn map_packet(&mut self, packet: &[u8]) -> Result<MapResult> {
+        // Only fisbone packets are used. The end-of-stream packet is empty, and the keyframe index
+        // packets of version 4 are ignored.
+        if packet.len() >= OGG_SKELETON_MIN_FISBONE_PACKET_SIZE
+            && packet.starts_with(OGG_SKELETON_FISBONE_SIGNATURE)
+        {
+            let mut reader = BufReader::new(&packet[8..]);
+
+            match read_fisbone(&mut reader) {
+                Ok(fisbone) => return Ok(MapResult::SideData { data: SideData::Fisbone(fisbone) }),
+                Err(err) => warn!("ogg (skeleton): invalid fisbone packet ({})", err),
+            }
+        }
+
+        Ok(MapResult::Unknown)
+    }
+
+    fn is_track(&self) -> bool {
+        false
+    }
+}
@@ -256,6 +256,12 @@ impl Mapper for VorbisMapper {
     fn is_ready(&self) -> bool {
         self.has_setup_header
     }
+
+    fn needs_priming_packet(&self) -> bool {
+        // Vorbis blocks are lapped, so a packet only produces audio after the previous packet was
+        // decoded.
+        true
+    }
 }
 
 struct IdentHeader {
@@ -6,6 +6,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use symphonia_core::checksum::Crc32;
+use symphonia_core::diagnostics::{DiagnosticEvent, Diagnostics};
 use symphonia_core::errors::{decode_error, Error, Result};
 use symphonia_core::io::{BufReader, Monitor, MonitorStream, ReadBytes, SeekBuffered};
 
@@ -151,6 +152,7 @@ pub struct PageReader {
     packet_lens: Vec<u16>,
     page_buf: Vec<u8>,
     page_buf_len: usize,
+    diagnostics: Diagnostics,
 }
 
 impl PageReader {
@@ -163,6 +165,7 @@ impl PageReader {
             packet_lens: Vec::new(),
             page_buf: Vec::new(),
             page_buf_len: 0,
+            diagnostics: Default::default(),
         };
 
         page_reader.try_next_page(reader)?;
@@ -170,6 +173,11 @@ impl PageReader {
         Ok(page_reader)
     }
 
+    /// Sets the diagnostics hook that page CRC mismatches are emitted to.
+    pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
     /// Attempts to read the next page. If the page is corrupted or invalid, returns an error.
     pub fn try_next_page<B>(&mut self, reader: &mut B) -> Result<()>
     where
@@ -246,6 +254,13 @@ impl PageReader {
         if header.crc != calculated_crc {
             warn!("crc mismatch: expected {:#x}, got {:#x}", header.crc, calculated_crc);
 
+            self.diagnostics.emit(DiagnosticEvent::CrcMismatch {
+                pos: Some(sync_pos - 4),
+                ts: None,
+                expected: header.crc,
+                computed: calculated_crc,
+            });
+
             // Clear packet buffer.
             self.packet_lens.clear();
             self.page_buf_len = 0;
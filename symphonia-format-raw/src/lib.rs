@@ -0,0 +1,439 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![warn(rust_2018_idioms)]
+#![forbid(unsafe_code)]
+// The following lints are allowed in all Symphonia crates. Please see clippy.toml for their
+// justification.
+#![allow(clippy::comparison_chain)]
+#![allow(clippy::excessive_precision)]
+#![allow(clippy::identity_op)]
+#![allow(clippy::manual_range_contains)]
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::*;
+use symphonia_core::errors::{end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes};
+use symphonia_core::meta::{Metadata, MetadataLog};
+
+use log::debug;
+
+/// The maximum number of frames that will be in a packet.
+const MAX_FRAMES_PER_PACKET: u64 = 1152;
+
+/// Gets the number of bits used to store a single sample of a PCM codec.
+fn pcm_sample_width(codec_type: CodecType) -> Result<u32> {
+    let width = match codec_type {
+        CODEC_TYPE_PCM_S32LE | CODEC_TYPE_PCM_S32BE => 32,
+        CODEC_TYPE_PCM_S24LE | CODEC_TYPE_PCM_S24BE => 24,
+        CODEC_TYPE_PCM_S16LE | CODEC_TYPE_PCM_S16BE => 16,
+        CODEC_TYPE_PCM_S8 => 8,
+        CODEC_TYPE_PCM_U32LE | CODEC_TYPE_PCM_U32BE => 32,
+        CODEC_TYPE_PCM_U24LE | CODEC_TYPE_PCM_U24BE => 24,
+        CODEC_TYPE_PCM_U16LE | CODEC_TYPE_PCM_U16BE => 16,
+        CODEC_TYPE_PCM_U8 => 8,
+        CODEC_TYPE_PCM_F32LE | CODEC_TYPE_PCM_F32BE => 32,
+        CODEC_TYPE_PCM_F64LE | CODEC_TYPE_PCM_F64BE => 64,
+        CODEC_TYPE_PCM_ALAW | CODEC_TYPE_PCM_MULAW => 8,
+        _ => return unsupported_error("raw: codec is not a pcm codec"),
+    };
+
+    Ok(width)
+}
+
+/// Raw (headerless) PCM format reader.
+///
+/// `RawPcmReader` wraps a stream of interleaved PCM samples without a container. Since raw PCM has
+/// no header or marker, it cannot be probed, and the format of the audio must be provided by the
+/// application. Use `RawPcmReader::try_new_with_params` to instantiate the reader with codec
+/// parameters describing the audio.
+///
+/// If the reader is instantiated with `FormatReader::try_new`, the stream is assumed to contain
+/// CD audio: 44.1kHz stereo audio stored as signed 16-bit little-endian samples.
+pub struct RawPcmReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    frame_len: u64,
+    data_start_pos: u64,
+    data_end_pos: Option<u64>,
+}
+
+impl RawPcmReader {
+    /// Instantiate a `RawPcmReader` for the raw PCM stream described by the provided codec
+    /// parameters.
+    ///
+    /// The codec must be a PCM codec, and the sample rate, and the channels or channel layout,
+    /// are required. The codec type determines the sample format and byte order. Samples are
+    /// interleaved in the order of the channels in the channel bitmask.
+    pub fn try_new_with_params(
+        source: MediaSourceStream,
+        params: &CodecParameters,
+    ) -> Result<Self> {
+        let bits_per_sample = pcm_sample_width(params.codec)?;
+
+        let sample_rate = match params.sample_rate {
+            Some(rate) if rate > 0 => rate,
+            Some(_) => return unsupported_error("raw: sample rate cannot be 0"),
+            None => return unsupported_error("raw: sample rate is required"),
+        };
+
+        let channels = match (params.channels, params.channel_layout) {
+            (Some(channels), _) => channels,
+            (None, Some(layout)) => layout.into_channels(),
+            (None, None) => {
+                return unsupported_error("raw: channels or channel layout is required")
+            }
+        };
+
+        if channels.count() < 1 {
+            return unsupported_error("raw: number of channels cannot be 0");
+        }
+
+        // The length of a single interleaved audio frame in bytes.
+        let frame_len = u64::from(bits_per_sample / 8) * channels.count() as u64;
+
+        let data_start_pos = source.pos();
+
+        // If the length of the stream is known, then the total number of frames is known.
+        let data_end_pos = source.byte_len();
+
+        let mut codec_params = CodecParameters::new();
+
+        codec_params
+            .for_codec(params.codec)
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_channels(channels)
+            .with_bits_per_sample(bits_per_sample)
+            .with_bits_per_coded_sample(bits_per_sample)
+            .with_max_frames_per_packet(MAX_FRAMES_PER_PACKET);
+
+        if let Some(end) = data_end_pos {
+            codec_params.with_n_frames(end.saturating_sub(data_start_pos) / frame_len);
+        }
+
+        Ok(RawPcmReader {
+            reader: source,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            frame_len,
+            data_start_pos,
+            data_end_pos,
+        })
+    }
+}
+
+impl FormatReader for RawPcmReader {
+    fn try_new(source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // Raw PCM cannot be described by the format options, therefore assume CD audio.
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_PCM_S16LE)
+            .with_sample_rate(44_100)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        Self::try_new_with_params(source, &params)
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let pos = self.reader.pos();
+
+        // The packet timestamp is the position of the first byte of the first frame in the packet
+        // relative to the start of the stream divided by the length per frame.
+        let ts = (pos - self.data_start_pos) / self.frame_len;
+
+        let max_packet_len = MAX_FRAMES_PER_PACKET * self.frame_len;
+
+        // If the end of the stream is known, do not read past it. Otherwise, read as much as
+        // possible and discard any trailing partial frame.
+        let buf = match self.data_end_pos {
+            Some(end) => {
+                let n_frames_left = end.saturating_sub(pos) / self.frame_len;
+
+                if n_frames_left == 0 {
+                    return end_of_stream_error();
+                }
+
+                let packet_len = n_frames_left.min(MAX_FRAMES_PER_PACKET) * self.frame_len;
+                self.reader.read_boxed_slice_exact(packet_len as usize)?
+            }
+            None => {
+                let mut buf = vec![0; max_packet_len as usize];
+                let mut len = 0;
+
+                while len < buf.len() {
+                    match self.reader.read_buf(&mut buf[len..])? {
+                        0 => break,
+                        n => len += n,
+                    }
+                }
+
+                buf.truncate(len - (len % self.frame_len as usize));
+
+                if buf.is_empty() {
+                    return end_of_stream_error();
+                }
+
+                buf.into_boxed_slice()
+            }
+        };
+
+        let dur = buf.len() as u64 / self.frame_len;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        // If the total number of frames in the track is known, verify the desired frame timestamp
+        // does not exceed it.
+        if let Some(n_frames) = params.n_frames {
+            if ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to frame_ts={}", ts);
+
+        // Raw PCM has no packets, so a seek can be made directly to the requested frame.
+        let seek_pos = self.data_start_pos + ts * self.frame_len;
+
+        // If the reader supports seeking, seek directly to the frame's offset.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        // If the reader does not support seeking, only forward seeks can be emulated by consuming
+        // bytes. If the reader has to seek backwards, return an error.
+        else {
+            let current_pos = self.reader.pos();
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        Ok(SeekedTo { track_id: 0, actual_ts: ts, required_ts: ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_codec_pcm::PcmDecoder;
+    use symphonia_core::audio::{AudioBufferRef, Channels, Layout, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder};
+    use symphonia_core::codecs::{CODEC_TYPE_FLAC, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24BE};
+    use symphonia_core::errors::{Error, SeekErrorKind};
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSourceStream, ReadOnlySource};
+
+    use super::RawPcmReader;
+
+    fn make_params(channels: Channels) -> CodecParameters {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_PCM_S16LE).with_sample_rate(8_000).with_channels(channels);
+        params
+    }
+
+    fn make_reader(data: Vec<u8>, params: &CodecParameters) -> Result<RawPcmReader, Error> {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        RawPcmReader::try_new_with_params(mss, params)
+    }
+
+    #[test]
+    fn verify_raw_pcm_reader() {
+        // 2000 stereo S16LE frames where the left channel is the frame index and the right channel
+        // is the negated frame index.
+        let mut data = Vec::new();
+
+        for i in 0..2000i16 {
+            data.extend_from_slice(&i.to_le_bytes());
+            data.extend_from_slice(&(-i).to_le_bytes());
+        }
+
+        let params = make_params(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut reader = make_reader(data, &params).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.n_frames, Some(2000));
+
+        let mut decoder = PcmDecoder::try_new(params, &Default::default()).unwrap();
+
+        let mut next_frame = 0;
+
+        while let Ok(packet) = reader.next_packet() {
+            assert_eq!(packet.ts(), next_frame);
+
+            let decoded = decoder.decode(&packet).unwrap();
+
+            match decoded {
+                AudioBufferRef::S16(buf) => {
+                    for (i, (&l, &r)) in buf.chan(0).iter().zip(buf.chan(1)).enumerate() {
+                        assert_eq!(l, (next_frame as usize + i) as i16);
+                        assert_eq!(r, -l);
+                    }
+                }
+                _ => panic!("unexpected sample format"),
+            }
+
+            next_frame += packet.dur();
+        }
+
+        assert_eq!(next_frame, 2000);
+
+        // Seek back to an arbitrary frame.
+        let seeked =
+            reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1234, track_id: 0 }).unwrap();
+        assert_eq!(seeked.actual_ts, 1234);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts(), 1234);
+        assert_eq!(&packet.buf()[..2], &1234i16.to_le_bytes());
+    }
+
+    #[test]
+    fn verify_params() {
+        // The frame length is derived from the codec and the channel layout.
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_PCM_S24BE)
+            .with_sample_rate(48_000)
+            .with_channel_layout(Layout::FivePointOne);
+
+        let reader = make_reader(vec![0; 3 * 6 * 100 + 5], &params).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.n_frames, Some(100));
+        assert_eq!(params.bits_per_coded_sample, Some(24));
+        assert_eq!(params.channels.map(|channels| channels.count()), Some(6));
+
+        // Without explicit parameters, the stream is assumed to be CD audio.
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(vec![0; 400])), Default::default());
+        let reader = RawPcmReader::try_new(mss, &Default::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_PCM_S16LE);
+        assert_eq!(params.sample_rate, Some(44_100));
+        assert_eq!(params.n_frames, Some(100));
+
+        // Non-PCM codecs, and missing or invalid parameters, are unsupported.
+        let mut params = make_params(Channels::FRONT_LEFT);
+        params.for_codec(CODEC_TYPE_FLAC);
+        assert!(matches!(make_reader(vec![], &params), Err(Error::Unsupported(_))));
+
+        let mut params = make_params(Channels::FRONT_LEFT);
+        params.with_sample_rate(0);
+        assert!(matches!(make_reader(vec![], &params), Err(Error::Unsupported(_))));
+
+        let mut params = make_params(Channels::empty());
+        assert!(matches!(make_reader(vec![], &params), Err(Error::Unsupported(_))));
+
+        params.channels = None;
+        assert!(matches!(make_reader(vec![], &params), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn verify_packet_frames() {
+        // 2500 mono S16LE frames. All packets except the last contain 1152 frames.
+        let params = make_params(Channels::FRONT_LEFT);
+
+        let mut reader = make_reader(vec![0; 2 * 2500], &params).unwrap();
+
+        let mut decoder =
+            PcmDecoder::try_new(&reader.tracks()[0].codec_params, &Default::default()).unwrap();
+
+        let mut frames = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            assert_eq!(decoder.decode(&packet).unwrap().frames() as u64, packet.frames());
+            frames.push(packet.frames());
+        }
+
+        assert_eq!(frames, &[1152, 1152, 196]);
+    }
+
+    #[test]
+    fn verify_rewind() {
+        // 2000 mono S16LE frames where each sample is the frame index.
+        let data: Vec<u8> = (0..2000i16).flat_map(|i| i.to_le_bytes()).collect();
+
+        let params = make_params(Channels::FRONT_LEFT);
+
+        let decode_all = |reader: &mut RawPcmReader| {
+            let mut decoder =
+                PcmDecoder::try_new(&reader.tracks()[0].codec_params, &Default::default()).unwrap();
+
+            let mut samples = Vec::new();
+
+            while let Ok(packet) = reader.next_packet() {
+                match decoder.decode(&packet).unwrap() {
+                    AudioBufferRef::S16(buf) => samples.extend_from_slice(buf.chan(0)),
+                    _ => panic!("unexpected sample format"),
+                }
+            }
+
+            samples
+        };
+
+        let mut reader = make_reader(data.clone(), &params).unwrap();
+
+        let first = decode_all(&mut reader);
+        reader.rewind().unwrap();
+        let second = decode_all(&mut reader);
+
+        assert_eq!(first.len(), 2000);
+        assert_eq!(first, second);
+
+        // An unseekable source cannot be rewound.
+        let source = ReadOnlySource::new(Cursor::new(data));
+        let mss = MediaSourceStream::new(Box::new(source), Default::default());
+
+        let mut reader = RawPcmReader::try_new_with_params(mss, &params).unwrap();
+
+        decode_all(&mut reader);
+
+        assert!(matches!(reader.rewind(), Err(Error::SeekError(SeekErrorKind::Unseekable))));
+    }
+}
@@ -9,15 +9,15 @@ use std::fmt;
 
 use symphonia_core::codecs::{
     CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_F64BE, CODEC_TYPE_PCM_MULAW,
-    CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24BE, CODEC_TYPE_PCM_S32BE,
-    CODEC_TYPE_PCM_S8,
+    CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24BE, CODEC_TYPE_PCM_S24LE,
+    CODEC_TYPE_PCM_S32BE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_S8,
 };
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
-use symphonia_core::io::{MediaSourceStream, ReadBytes};
+use symphonia_core::io::{MediaSourceStream, ReadBytes, ScopedStream};
 
 use crate::common::{
     try_channel_count_to_mask, ChunkParser, FormatALaw, FormatData, FormatIeeeFloat, FormatMuLaw,
-    FormatPcm, PacketInfo, ParseChunk, ParseChunkTag,
+    FormatPcm, Id3Chunk, PacketInfo, ParseChunk, ParseChunkTag,
 };
 
 use extended::Extended;
@@ -73,7 +73,7 @@ impl CommonChunk {
 
     fn read_ieee_fmt(bits_per_sample: u16, n_channels: u16) -> Result<FormatData> {
         // Select the appropriate codec using bits per sample. Samples are always interleaved and
-        // little-endian encoded for the IEEE Float format.
+        // big-endian encoded for the IEEE Float format.
         let codec = match bits_per_sample {
             32 => CODEC_TYPE_PCM_F32BE,
             64 => CODEC_TYPE_PCM_F64BE,
@@ -85,9 +85,15 @@ impl CommonChunk {
     }
 
     fn read_sowt_fmt(bits_per_sample: u16, n_channels: u16) -> Result<FormatData> {
+        // The byte-swapped (little-endian) variant of PCM.
         let codec = match bits_per_sample {
+            8 => CODEC_TYPE_PCM_S8,
             16 => CODEC_TYPE_PCM_S16LE,
-            _ => return decode_error("aiff: bits per sample for sowt must be 16 bits"),
+            24 => CODEC_TYPE_PCM_S24LE,
+            32 => CODEC_TYPE_PCM_S32LE,
+            _ => {
+                return decode_error("aiff: bits per sample for sowt must be 8, 16, 24 or 32 bits")
+            }
         };
 
         let channels = try_channel_count_to_mask(n_channels)?;
@@ -95,13 +101,11 @@ impl CommonChunk {
     }
 
     fn read_twos_fmt(bits_per_sample: u16, n_channels: u16) -> Result<FormatData> {
-        let codec = match bits_per_sample {
-            16 => CODEC_TYPE_PCM_S16BE,
-            _ => return decode_error("aiff: bits per sample for twos must be 16 bits"),
-        };
-
-        let channels = try_channel_count_to_mask(n_channels)?;
-        Ok(FormatData::Pcm(FormatPcm { bits_per_sample, channels, codec }))
+        // Identical to uncompressed PCM.
+        match bits_per_sample {
+            8 | 16 | 24 | 32 => Self::read_pcm_fmt(bits_per_sample, n_channels),
+            _ => decode_error("aiff: bits per sample for twos must be 8, 16, 24 or 32 bits"),
+        }
     }
 
     pub fn packet_info(&self) -> Result<PacketInfo> {
@@ -130,6 +134,12 @@ impl CommonChunk {
             FormatData::Adpcm(_) => {
                 unsupported_error("aiff: packet info not implemented for format Adpcm")
             }
+            FormatData::Mpeg(_) => {
+                unsupported_error("aiff: packet info not implemented for format Mpeg")
+            }
+            FormatData::Ac3(_) => {
+                unsupported_error("aiff: packet info not implemented for format Ac3")
+            }
         }
     }
 }
@@ -192,6 +202,12 @@ impl fmt::Display for CommonChunk {
             FormatData::Adpcm(_) => {
                 writeln!(f, "\tformat_data: Adpcm DISPLAY UNSUPPORTED {{")?;
             }
+            FormatData::Mpeg(_) => {
+                writeln!(f, "\tformat_data: Mpeg DISPLAY UNSUPPORTED {{")?;
+            }
+            FormatData::Ac3(_) => {
+                writeln!(f, "\tformat_data: Ac3 DISPLAY UNSUPPORTED {{")?;
+            }
         };
 
         writeln!(f, "\t}}")?;
@@ -206,10 +222,19 @@ pub trait CommonChunkParser {
 
 impl CommonChunkParser for ChunkParser<CommonChunk> {
     fn parse_aiff(self, source: &mut MediaSourceStream) -> Result<CommonChunk> {
-        self.parse(source)
+        let mut scoped = ScopedStream::new(source, u64::from(self.len));
+
+        let common = self.parse(&mut scoped)?;
+
+        // Ignore any remaining data in the chunk.
+        scoped.ignore()?;
+
+        Ok(common)
     }
 
     fn parse_aifc(self, source: &mut MediaSourceStream) -> Result<CommonChunk> {
+        let mut source = ScopedStream::new(source, u64::from(self.len));
+
         let n_channels = source.read_be_i16()?;
         let n_sample_frames = source.read_be_u32()?;
         let sample_size = source.read_be_i16()?;
@@ -222,19 +247,25 @@ impl CommonChunkParser for ChunkParser<CommonChunk> {
 
         let compression_type = source.read_quad_bytes()?;
 
-        // Ignore pascal string containing compression_name
-        let str_len = source.read_byte()?;
-        source.ignore_bytes(str_len as u64)?;
-        // Total number of bytes in pascal string must be even, since len is excluded from our var, we add 1
-        if (str_len + 1) % 2 != 0 {
-            source.ignore_bytes(1)?;
-        }
+        // The pascal string containing the compression name, and any other data following it, is
+        // ignored.
+        source.ignore()?;
+
+        // The sample size of floating point samples is implied by the compression type, and some
+        // writers do not set it correctly.
+        let sample_size = match &compression_type {
+            b"fl32" | b"FL32" => 32,
+            b"fl64" | b"FL64" => 64,
+            _ => sample_size,
+        };
 
         let format_data = match &compression_type {
             b"none" | b"NONE" => CommonChunk::read_pcm_fmt(sample_size as u16, n_channels as u16),
             b"alaw" | b"ALAW" => CommonChunk::read_alaw_pcm_fmt(n_channels as u16),
             b"ulaw" | b"ULAW" => CommonChunk::read_mulaw_pcm_fmt(n_channels as u16),
-            b"fl32" | b"fl64" => CommonChunk::read_ieee_fmt(sample_size as u16, n_channels as u16),
+            b"fl32" | b"FL32" | b"fl64" | b"FL64" => {
+                CommonChunk::read_ieee_fmt(sample_size as u16, n_channels as u16)
+            }
             b"sowt" | b"SOWT" => CommonChunk::read_sowt_fmt(sample_size as u16, n_channels as u16),
             b"twos" | b"TWOS" => CommonChunk::read_twos_fmt(sample_size as u16, n_channels as u16),
             _ => return unsupported_error("aifc: Compression type not implemented"),
@@ -249,6 +280,33 @@ impl CommonChunkParser for ChunkParser<CommonChunk> {
     }
 }
 
+/// `FormatVersionChunk` is a required AIFF-C chunk, containing the version of the AIFF-C
+/// specification the file conforms to. Many writers omit it, therefore it is optional.
+pub struct FormatVersionChunk {
+    /// The timestamp of the version of the specification.
+    pub timestamp: u32,
+}
+
+impl FormatVersionChunk {
+    /// The timestamp of version 1 of the AIFF-C specification.
+    pub const AIFC_VERSION_1: u32 = 0xa280_5140;
+}
+
+impl ParseChunk for FormatVersionChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<FormatVersionChunk> {
+        if len < 4 {
+            return decode_error("aifc: malformed fver chunk");
+        }
+
+        let timestamp = reader.read_be_u32()?;
+
+        // Ignore any remaining data in the chunk.
+        reader.ignore_bytes(u64::from(len) - 4)?;
+
+        Ok(FormatVersionChunk { timestamp })
+    }
+}
+
 /// `SoundChunk` is a required AIFF chunk, containing the audio data.
 pub struct SoundChunk {
     pub len: u32,
@@ -260,6 +318,10 @@ pub struct SoundChunk {
 
 impl ParseChunk for SoundChunk {
     fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<SoundChunk> {
+        if len < 8 {
+            return decode_error("aiff: malformed sound chunk");
+        }
+
         let offset = reader.read_be_u32()?;
         let block_size = reader.read_be_u32()?;
 
@@ -273,7 +335,9 @@ impl ParseChunk for SoundChunk {
 
 pub enum RiffAiffChunks {
     Common(ChunkParser<CommonChunk>),
+    FormatVersion(ChunkParser<FormatVersionChunk>),
     Sound(ChunkParser<SoundChunk>),
+    Id3(ChunkParser<Id3Chunk>),
 }
 
 macro_rules! parser {
@@ -286,8 +350,14 @@ impl ParseChunkTag for RiffAiffChunks {
     fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
         match &tag {
             b"COMM" => parser!(RiffAiffChunks::Common, CommonChunk, tag, len),
+            b"FVER" => parser!(RiffAiffChunks::FormatVersion, FormatVersionChunk, tag, len),
             b"SSND" => parser!(RiffAiffChunks::Sound, SoundChunk, tag, len),
+            b"ID3 " | b"id3 " => parser!(RiffAiffChunks::Id3, Id3Chunk, tag, len),
             _ => None,
         }
     }
+
+    fn is_audio_data(tag: [u8; 4]) -> bool {
+        &tag == b"SSND"
+    }
 }
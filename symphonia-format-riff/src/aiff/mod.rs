@@ -16,10 +16,11 @@ use symphonia_core::meta::{Metadata, MetadataLog};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::support_format;
 
-use log::debug;
+use log::{debug, warn};
 
 use crate::common::{
-    append_data_params, append_format_params, next_packet, ByteOrder, ChunksReader, PacketInfo,
+    append_data_params, append_format_params, next_packet, read_trailing_chunks, ByteOrder,
+    ChunksReader, PacketInfo, TrailingChunks,
 };
 mod chunks;
 use chunks::*;
@@ -34,6 +35,13 @@ const AIFC_RIFF_FORM: [u8; 4] = *b"AIFC";
 /// Audio Interchange File Format (AIFF) format reader.
 ///
 /// `AiffReader` implements a demuxer for the AIFF container format.
+///
+/// AIFF-C files with uncompressed (`none`, `twos`), byte-swapped (`sowt`), floating point (`fl32`,
+/// `fl64`), A-law (`alaw`), and mu-law (`ulaw`) audio are supported.
+///
+/// If lenient parsing is enabled, a chunk with a length exceeding the length of the FORM chunk is
+/// tolerated. The length of the `SSND` chunk is trusted, but a chunk other than the `SSND` chunk is
+/// truncated to the remaining length of the FORM chunk.
 pub struct AiffReader {
     reader: MediaSourceStream,
     tracks: Vec<Track>,
@@ -64,7 +72,7 @@ impl QueryDescriptor for AiffReader {
 }
 
 impl FormatReader for AiffReader {
-    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
         // The FORM marker should be present.
         let marker = source.read_quad_bytes()?;
         if marker != AIFF_STREAM_MARKER {
@@ -75,13 +83,15 @@ impl FormatReader for AiffReader {
         // Therefore, the header was the chunk ID, and the next 4 bytes is the length of the RIFF
         // chunk.
         let riff_len = source.read_be_u32()?;
+        let riff_end_pos = source.pos() + u64::from(riff_len);
         let riff_form = source.read_quad_bytes()?;
 
         let mut riff_chunks = ChunksReader::<RiffAiffChunks>::new(riff_len, ByteOrder::BigEndian);
+        riff_chunks.set_lenient(options.enable_lenient);
 
         let mut codec_params = CodecParameters::new();
         //TODO: Chunks such as marker contain metadata, get it.
-        let metadata: MetadataLog = Default::default();
+        let mut metadata: MetadataLog = Default::default();
         let mut packet_info = PacketInfo::without_blocks(0);
 
         loop {
@@ -116,15 +126,40 @@ impl FormatReader for AiffReader {
                         common.sample_rate,
                     );
                 }
+                RiffAiffChunks::FormatVersion(fver) => {
+                    let fver = fver.parse(&mut source)?;
+
+                    // Later versions of the specification were never published, so warn but try
+                    // to continue.
+                    if fver.timestamp != FormatVersionChunk::AIFC_VERSION_1 {
+                        warn!("unknown aifc version: {:#x}", fver.timestamp);
+                    }
+                }
+                RiffAiffChunks::Id3(id3) => {
+                    if let Some(revision) = id3.parse(&mut source)?.metadata {
+                        metadata.push(revision);
+                    }
+                }
                 RiffAiffChunks::Sound(dat) => {
                     let data = dat.parse(&mut source)?;
 
-                    // Record the bounds of the data chunk.
+                    // Record the bounds of the data chunk. The sound chunk length includes the 8
+                    // byte offset and block size fields.
+                    let data_len = u64::from(data.len) - 8;
                     let data_start_pos = source.pos();
-                    let data_end_pos = data_start_pos + u64::from(data.len);
+                    let data_end_pos = data_start_pos + data_len;
 
                     // Append Sound chunk fields to codec parameters.
-                    append_data_params(&mut codec_params, data.len as u64, &packet_info);
+                    append_data_params(&mut codec_params, data_len, &packet_info);
+
+                    // Read the metadata chunks following the sound chunk.
+                    read_trailing_chunks(
+                        &mut source,
+                        data_end_pos + u64::from(data.len & 1),
+                        riff_end_pos,
+                        ByteOrder::BigEndian,
+                        |source, chunk: TrailingChunks| chunk.read(source, &mut metadata),
+                    )?;
 
                     // Add a new track using the collected codec parameters.
                     return Ok(AiffReader {
@@ -233,3 +268,89 @@ impl FormatReader for AiffReader {
         self.reader
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::Channels;
+    use symphonia_core::codecs::{CodecType, CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_S24LE};
+    use symphonia_core::formats::{FormatOptions, FormatReader};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::AiffReader;
+
+    /// Builds an AIFF-C file at 44.1 kHz with a format version chunk, and a common chunk with the
+    /// given compression type.
+    fn build_aifc(
+        compression: &[u8; 4],
+        n_channels: u16,
+        sample_size: u16,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let frame_len = usize::from(n_channels) * usize::from(sample_size) / 8;
+
+        let mut comm = n_channels.to_be_bytes().to_vec();
+        comm.extend_from_slice(&((data.len() / frame_len) as u32).to_be_bytes());
+        comm.extend_from_slice(&sample_size.to_be_bytes());
+        comm.extend_from_slice(&[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+        comm.extend_from_slice(compression);
+        // The compression name is a pascal string padded to an even length.
+        comm.extend_from_slice(b"\x0dlittle endian");
+
+        let form_len = 4 + 12 + 8 + comm.len() + 16 + data.len();
+
+        let mut buf = b"FORM".to_vec();
+        buf.extend_from_slice(&(form_len as u32).to_be_bytes());
+        buf.extend_from_slice(b"AIFC");
+        buf.extend_from_slice(b"FVER");
+        buf.extend_from_slice(&4u32.to_be_bytes());
+        buf.extend_from_slice(&0xa280_5140u32.to_be_bytes());
+        buf.extend_from_slice(b"COMM");
+        buf.extend_from_slice(&(comm.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&comm);
+        buf.extend_from_slice(b"SSND");
+        buf.extend_from_slice(&(8 + data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&[0; 8]);
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    /// Reads the file, verifies the codec, and returns the sample data of the first packet.
+    fn read_aifc(buf: Vec<u8>, codec: CodecType, channels: Channels) -> Box<[u8]> {
+        let mss = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        let mut reader = AiffReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, codec);
+        assert_eq!(params.channels, Some(channels));
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.n_frames, Some(2));
+
+        reader.next_packet().unwrap().data
+    }
+
+    #[test]
+    fn verify_aifc_sowt() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        let buf = build_aifc(b"sowt", 2, 24, &data);
+
+        let packet =
+            read_aifc(buf, CODEC_TYPE_PCM_S24LE, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        // The samples are little-endian.
+        assert_eq!(&packet[..], &data);
+        assert_eq!(i32::from_le_bytes([0, packet[0], packet[1], packet[2]]) >> 8, 0x030201);
+    }
+
+    #[test]
+    fn verify_aifc_fl32() {
+        let data: Vec<u8> = [0.5f32, -0.25].iter().flat_map(|s| s.to_be_bytes().to_vec()).collect();
+        let buf = build_aifc(b"fl32", 1, 32, &data);
+
+        let packet = read_aifc(buf, CODEC_TYPE_PCM_F32BE, Channels::FRONT_LEFT);
+
+        let samples: Vec<f32> =
+            packet.chunks_exact(4).map(|s| f32::from_be_bytes([s[0], s[1], s[2], s[3]])).collect();
+
+        assert_eq!(samples, &[0.5, -0.25]);
+    }
+}
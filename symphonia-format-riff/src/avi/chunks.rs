@@ -0,0 +1,321 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::ReadBytes;
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+
+use crate::common::{ChunkParser, ParseChunk, ParseChunkTag};
+use crate::wave::chunks::ListChunk;
+
+/// The stream header of a stream.
+pub struct StreamHeaderChunk {
+    /// The type of data contained in the stream (e.g., "auds" for audio, "vids" for video).
+    pub fcc_type: [u8; 4],
+    /// The preferred handler (codec) of the stream.
+    pub fcc_handler: [u8; 4],
+    /// Stream flags.
+    pub flags: u32,
+    /// The number of frames the audio stream is skewed ahead of the video stream.
+    pub initial_frames: u32,
+    /// Together with `rate`, the time scale of the stream. `rate / scale` is the number of units
+    /// per second.
+    pub scale: u32,
+    /// Together with `scale`, the time scale of the stream.
+    pub rate: u32,
+    /// The starting time of the stream in units.
+    pub start: u32,
+    /// The length of the stream in units.
+    pub length: u32,
+    /// The size of the largest chunk in the stream.
+    pub suggested_buffer_size: u32,
+    /// The size of a single unit. If 0, each chunk is a single unit.
+    pub sample_size: u32,
+}
+
+impl StreamHeaderChunk {
+    /// Returns true if the stream is an audio stream.
+    pub fn is_audio(&self) -> bool {
+        &self.fcc_type == b"auds"
+    }
+
+    /// Get the number of units contained in a chunk of the stream.
+    pub fn units(&self, len: u32) -> u64 {
+        match self.sample_size {
+            0 => 1,
+            sample_size => u64::from(len / sample_size),
+        }
+    }
+}
+
+impl ParseChunk for StreamHeaderChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<Self> {
+        // The stream header is 56 bytes long, however, some writers omit the frame rectangle
+        // at the end.
+        if len < 48 {
+            return decode_error("avi: malformed strh chunk");
+        }
+
+        let fcc_type = reader.read_quad_bytes()?;
+        let fcc_handler = reader.read_quad_bytes()?;
+        let flags = reader.read_u32()?;
+        let _priority = reader.read_u16()?;
+        let _language = reader.read_u16()?;
+        let initial_frames = reader.read_u32()?;
+        let scale = reader.read_u32()?;
+        let rate = reader.read_u32()?;
+        let start = reader.read_u32()?;
+        let length = reader.read_u32()?;
+        let suggested_buffer_size = reader.read_u32()?;
+        let _quality = reader.read_u32()?;
+        let sample_size = reader.read_u32()?;
+
+        // Ignore the frame rectangle, if present.
+        reader.ignore_bytes(u64::from(len - 48))?;
+
+        Ok(StreamHeaderChunk {
+            fcc_type,
+            fcc_handler,
+            flags,
+            initial_frames,
+            scale,
+            rate,
+            start,
+            length,
+            suggested_buffer_size,
+            sample_size,
+        })
+    }
+}
+
+impl fmt::Display for StreamHeaderChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "StreamHeaderChunk {{")?;
+        writeln!(f, "\tfcc_type: {},", String::from_utf8_lossy(&self.fcc_type))?;
+        writeln!(f, "\tfcc_handler: {},", String::from_utf8_lossy(&self.fcc_handler))?;
+        writeln!(f, "\tflags: {},", self.flags)?;
+        writeln!(f, "\tinitial_frames: {},", self.initial_frames)?;
+        writeln!(f, "\tscale: {},", self.scale)?;
+        writeln!(f, "\trate: {},", self.rate)?;
+        writeln!(f, "\tstart: {},", self.start)?;
+        writeln!(f, "\tlength: {},", self.length)?;
+        writeln!(f, "\tsuggested_buffer_size: {},", self.suggested_buffer_size)?;
+        writeln!(f, "\tsample_size: {},", self.sample_size)?;
+        writeln!(f, "}}")
+    }
+}
+
+/// The stream format of a stream. The contents of the chunk depend on the type of the stream,
+/// therefore, it is not read until the stream type is known.
+pub struct StreamFormatChunk {
+    pub len: u32,
+}
+
+impl ParseChunk for StreamFormatChunk {
+    fn parse<B: ReadBytes>(_: &mut B, _: [u8; 4], len: u32) -> Result<StreamFormatChunk> {
+        Ok(StreamFormatChunk { len })
+    }
+}
+
+/// A chunk of data belonging to a stream.
+pub struct StreamDataChunk {
+    /// The index of the stream.
+    pub stream: u32,
+    /// The two character code describing the type of data (e.g., "wb" for audio data).
+    pub kind: [u8; 2],
+    pub len: u32,
+}
+
+impl StreamDataChunk {
+    /// Returns true if the chunk contains audio data.
+    pub fn is_audio(&self) -> bool {
+        &self.kind == b"wb"
+    }
+}
+
+impl ParseChunk for StreamDataChunk {
+    fn parse<B: ReadBytes>(_: &mut B, tag: [u8; 4], len: u32) -> Result<StreamDataChunk> {
+        // The first two characters of the tag are the stream index in decimal.
+        let stream = u32::from(tag[0] - b'0') * 10 + u32::from(tag[1] - b'0');
+
+        Ok(StreamDataChunk { stream, kind: [tag[2], tag[3]], len })
+    }
+}
+
+/// The date the file was digitized (e.g., recorded by a camcorder). The format of the date is not
+/// standardized, but it is most often that of the C `ctime` function (e.g.,
+/// "THU OCT 16 12:34:56 2008").
+pub struct DigitizationDateChunk {
+    /// The date string, with any trailing null characters and whitespace removed.
+    pub date: String,
+}
+
+impl DigitizationDateChunk {
+    /// Get the date as a tag. If the date could be parsed, the tag is a standard date tag with the
+    /// date in ISO 8601 format. Otherwise, the tag contains the raw date string.
+    pub fn to_tag(&self) -> Tag {
+        match parse_date(&self.date) {
+            Some(date) => Tag::new(Some(StandardTagKey::Date), "IDIT", Value::from(date)),
+            None => Tag::new(None, "IDIT", Value::from(self.date.as_str())),
+        }
+    }
+}
+
+impl ParseChunk for DigitizationDateChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<DigitizationDateChunk> {
+        let buf = reader.read_boxed_slice_exact(len as usize)?;
+
+        let date = String::from_utf8_lossy(&buf);
+        let date = date.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+
+        Ok(DigitizationDateChunk { date })
+    }
+}
+
+/// Parse a month name, or its abbreviation, into the number of the month.
+fn parse_month_name(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] =
+        ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+    let name = name.to_ascii_lowercase();
+
+    MONTHS.iter().position(|month| name.starts_with(month)).map(|i| i as u32 + 1)
+}
+
+/// Attempt to parse a loosely formatted date into an ISO 8601 date and time.
+///
+/// The `ctime` format (e.g., "THU OCT 16 12:34:56 2008"), with or without the day of the week,
+/// and the numeric format (e.g., "2008:10:16 12:34:56", or "2008/10/16 12:34:56") are
+/// supported.
+fn parse_date(date: &str) -> Option<String> {
+    let mut fields: Vec<&str> = date
+        .split(|c: char| c.is_whitespace() || c == ':' || c == '/' || c == '-' || c == ',')
+        .filter(|field| !field.is_empty())
+        .collect();
+
+    // Skip the day of the week, if present.
+    if fields.len() == 7 && parse_month_name(fields[1]).is_some() {
+        fields.remove(0);
+    }
+
+    let (year, month, day, time) = match fields.as_slice() {
+        [month, day, hh, mm, ss, year] if parse_month_name(month).is_some() => {
+            (year, parse_month_name(month)?, day, [hh, mm, ss])
+        }
+        [year, month, day, hh, mm, ss] if year.len() == 4 => {
+            (year, month.parse().ok()?, day, [hh, mm, ss])
+        }
+        _ => return None,
+    };
+
+    let year: u32 = year.parse().ok()?;
+    let day: u32 = day.parse().ok()?;
+    let hh: u32 = time[0].parse().ok()?;
+    let mm: u32 = time[1].parse().ok()?;
+    let ss: u32 = time[2].parse().ok()?;
+
+    if year > 9999 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    if hh > 23 || mm > 59 || ss > 60 {
+        return None;
+    }
+
+    Some(format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, hh, mm, ss))
+}
+
+macro_rules! parser {
+    ($class:expr, $result:ty, $tag:expr, $len:expr) => {
+        Some($class(ChunkParser::<$result>::new($tag, $len)))
+    };
+}
+
+pub enum RiffAviChunks {
+    List(ChunkParser<ListChunk>),
+    DigitizationDate(ChunkParser<DigitizationDateChunk>),
+}
+
+impl ParseChunkTag for RiffAviChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        match &tag {
+            b"LIST" => parser!(RiffAviChunks::List, ListChunk, tag, len),
+            b"IDIT" => parser!(RiffAviChunks::DigitizationDate, DigitizationDateChunk, tag, len),
+            _ => None,
+        }
+    }
+}
+
+pub enum HeaderListChunks {
+    List(ChunkParser<ListChunk>),
+    DigitizationDate(ChunkParser<DigitizationDateChunk>),
+}
+
+impl ParseChunkTag for HeaderListChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        match &tag {
+            b"LIST" => parser!(HeaderListChunks::List, ListChunk, tag, len),
+            b"IDIT" => parser!(HeaderListChunks::DigitizationDate, DigitizationDateChunk, tag, len),
+            _ => None,
+        }
+    }
+}
+
+pub enum StreamListChunks {
+    Header(ChunkParser<StreamHeaderChunk>),
+    Format(ChunkParser<StreamFormatChunk>),
+}
+
+impl ParseChunkTag for StreamListChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        match &tag {
+            b"strh" => parser!(StreamListChunks::Header, StreamHeaderChunk, tag, len),
+            b"strf" => parser!(StreamListChunks::Format, StreamFormatChunk, tag, len),
+            _ => None,
+        }
+    }
+}
+
+pub enum MoviListChunks {
+    List(ChunkParser<ListChunk>),
+    Data(ChunkParser<StreamDataChunk>),
+}
+
+impl ParseChunkTag for MoviListChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        match &tag {
+            b"LIST" => parser!(MoviListChunks::List, ListChunk, tag, len),
+            [b'0'..=b'9', b'0'..=b'9', _, _] => {
+                parser!(MoviListChunks::Data, StreamDataChunk, tag, len)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_date;
+
+    #[test]
+    fn verify_parse_date() {
+        let date = Some("2008-10-16T12:34:56".to_string());
+
+        assert_eq!(parse_date("THU OCT 16 12:34:56 2008"), date);
+        assert_eq!(parse_date("Thu Oct 16 12:34:56 2008"), date);
+        assert_eq!(parse_date("October 16 12:34:56 2008"), date);
+        assert_eq!(parse_date("2008:10:16 12:34:56"), date);
+        assert_eq!(parse_date("2008/10/16 12:34:56"), date);
+
+        assert_eq!(parse_date("THU OCT 32 12:34:56 2008"), None);
+        assert_eq!(parse_date("2008:13:16 12:34:56"), None);
+        assert_eq!(parse_date("16.10.2008"), None);
+        assert_eq!(parse_date(""), None);
+    }
+}
@@ -0,0 +1,743 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::codecs::CodecParameters;
+use symphonia_core::errors::{end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, error, warn};
+
+use crate::common::{
+    append_format_params, ByteOrder, ChunksReader, FormatData, PacketInfo, ParseChunk,
+};
+use crate::wave::chunks::{read_info_chunk, WaveFormatChunk};
+
+mod chunks;
+use chunks::*;
+
+/// AVI is a RIFF stream, with a "RIFF" ASCII stream marker.
+const AVI_STREAM_MARKER: [u8; 4] = *b"RIFF";
+/// The RIFF form of AVI is "AVI ".
+const AVI_RIFF_FORM: [u8; 4] = *b"AVI ";
+
+/// The maximum length of an audio stream format chunk. The format chunk is a `WAVEFORMATEX`
+/// structure which may contain at most 65535 bytes of extension data.
+const MAX_AUDIO_FORMAT_LEN: u32 = 18 + u16::MAX as u32;
+
+/// The state of an audio stream.
+struct AviStream {
+    /// The track ID of the stream. This is the index of the stream in the stream header list.
+    track_id: u32,
+    /// The stream header.
+    header: StreamHeaderChunk,
+    /// The sample rate of the stream.
+    sample_rate: u32,
+    /// If the audio is stored as blocks, (e.g., PCM, ADPCM), packetization information used to
+    /// split chunks into packets. Otherwise, each chunk is a packet.
+    packet_info: Option<PacketInfo>,
+    /// The number of bytes, or units, read so far. Bytes are counted if the audio is stored as
+    /// blocks, otherwise units are counted.
+    consumed: u64,
+}
+
+impl AviStream {
+    /// Convert a number of stream units to a timestamp.
+    fn units_to_ts(&self, units: u64) -> u64 {
+        let scale = u128::from(self.header.scale) * u128::from(self.sample_rate);
+        (u128::from(units) * scale / u128::from(self.header.rate)) as u64
+    }
+
+    /// Get the timestamp of the next packet.
+    fn ts(&self) -> u64 {
+        match &self.packet_info {
+            Some(packet_info) => packet_info.get_frames(self.consumed),
+            None => self.units_to_ts(self.consumed),
+        }
+    }
+
+    /// Get the length and duration of the next packet of a chunk given the remaining length of
+    /// the chunk. If a packet cannot be formed from the remainder of the chunk, the length is 0.
+    fn next_packet_len(&self, chunk_len: u32) -> (u32, u64) {
+        match &self.packet_info {
+            Some(packet_info) => {
+                let n_blocks = (u64::from(chunk_len) / packet_info.block_size)
+                    .min(packet_info.max_blocks_per_packet);
+
+                let len = n_blocks * packet_info.block_size;
+
+                (len as u32, n_blocks * packet_info.frames_per_block)
+            }
+            None => {
+                let units = self.header.units(chunk_len);
+                let dur = self.units_to_ts(self.consumed + units) - self.ts();
+
+                (chunk_len, dur)
+            }
+        }
+    }
+
+    /// Advance the stream past a packet of the given length.
+    fn advance(&mut self, len: u32) {
+        match &self.packet_info {
+            Some(_) => self.consumed += u64::from(len),
+            None => self.consumed += self.header.units(len),
+        }
+    }
+}
+
+/// The unread remainder of an audio chunk.
+struct PendingChunk {
+    /// The index of the audio stream the chunk belongs to.
+    stream: usize,
+    /// The number of bytes remaining in the chunk.
+    len: u32,
+}
+
+/// Audio Video Interleave (AVI) format reader.
+///
+/// `AviReader` implements a demuxer for the audio streams of the AVI container format. Video and
+/// all other streams are skipped.
+///
+/// Audio stored as blocks (e.g., PCM, ADPCM) is split into packets of whole blocks. For all other
+/// audio, each chunk is returned as a packet, and is assumed to contain whole codec frames. The
+/// OpenDML (AVI 2.0) extensions for files larger than 1 GB are not supported.
+///
+/// Since the index is not read, seeking is performed by scanning the chunks of the stream.
+///
+/// Tags are read from the INFO list, and the digitization date (IDIT) chunk.
+///
+/// If lenient parsing is enabled, a chunk with a length exceeding the length of the RIFF chunk is
+/// tolerated.
+pub struct AviReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    streams: Vec<AviStream>,
+    lenient: bool,
+    movi_start_pos: u64,
+    movi_len: u32,
+    movi: ChunksReader<MoviListChunks>,
+    rec: Option<ChunksReader<MoviListChunks>>,
+    chunk: Option<PendingChunk>,
+}
+
+impl QueryDescriptor for AviReader {
+    fn query() -> &'static [Descriptor] {
+        &[
+            // AVI RIFF form
+            support_format!(
+                "avi",
+                "Audio Video Interleave",
+                &["avi"],
+                &["video/avi", "video/msvideo", "video/x-msvideo"],
+                &[b"RIFF"]
+            ),
+        ]
+    }
+
+    fn score(context: &[u8]) -> u8 {
+        // Other RIFF forms, such as WAVE, share the RIFF marker.
+        match context.get(8..12) {
+            Some(form) if form == AVI_RIFF_FORM => 255,
+            _ => 0,
+        }
+    }
+}
+
+/// Read a stream list. Returns the stream if it is a supported audio stream.
+fn read_stream_list(
+    source: &mut MediaSourceStream,
+    len: u32,
+    track_id: u32,
+) -> Result<Option<(AviStream, CodecParameters)>> {
+    let mut chunks = ChunksReader::<StreamListChunks>::new(len, ByteOrder::LittleEndian);
+
+    let mut header = None;
+    let mut format = None;
+
+    while let Some(chunk) = chunks.next(source)? {
+        match chunk {
+            StreamListChunks::Header(strh) => {
+                let strh = strh.parse(source)?;
+                debug!("{}", strh);
+                header = Some(strh);
+            }
+            StreamListChunks::Format(strf) => {
+                let strf = strf.parse(source)?;
+
+                // The stream format chunk must follow the stream header chunk. Only read the
+                // format of audio streams.
+                match &header {
+                    Some(header) if header.is_audio() && strf.len <= MAX_AUDIO_FORMAT_LEN => {
+                        format = Some(source.read_boxed_slice_exact(strf.len as usize)?);
+                    }
+                    _ => source.ignore_bytes(u64::from(strf.len))?,
+                }
+            }
+        }
+    }
+
+    chunks.finish(source)?;
+
+    let (header, format) = match (header, format) {
+        (Some(header), Some(format)) => (header, format),
+        _ => return Ok(None),
+    };
+
+    // The audio stream format is a WAVEFORMATEX structure, which is the same as the format chunk
+    // of a WAVE file.
+    let format =
+        match WaveFormatChunk::parse(&mut BufReader::new(&format), *b"strf", format.len() as u32) {
+            Ok(format) => format,
+            Err(err) => {
+                warn!("skipping audio stream {}: {}", track_id, err);
+                return Ok(None);
+            }
+        };
+
+    if format.sample_rate == 0 || header.scale == 0 || header.rate == 0 {
+        warn!("skipping audio stream {}: invalid time base", track_id);
+        return Ok(None);
+    }
+
+    let mut codec_params = CodecParameters::new();
+
+    append_format_params(&mut codec_params, &format.format_data, format.sample_rate);
+
+    // Compressed audio is packetized by the chunks of the stream.
+    let packet_info = match format.format_data {
+        FormatData::Mpeg(_) | FormatData::Ac3(_) => None,
+        _ => {
+            let packet_info = format.packet_info()?;
+
+            if packet_info.is_empty() {
+                warn!("skipping audio stream {}: block size is 0", track_id);
+                return Ok(None);
+            }
+
+            codec_params
+                .with_max_frames_per_packet(packet_info.get_max_frames_per_packet())
+                .with_frames_per_block(packet_info.frames_per_block);
+
+            Some(packet_info)
+        }
+    };
+
+    let stream =
+        AviStream { track_id, header, sample_rate: format.sample_rate, packet_info, consumed: 0 };
+
+    if stream.header.length > 0 {
+        codec_params.with_n_frames(stream.units_to_ts(u64::from(stream.header.length)));
+    }
+
+    Ok(Some((stream, codec_params)))
+}
+
+impl AviReader {
+    /// Reads chunks from the movi list until the next audio chunk of a supported audio stream is
+    /// found.
+    fn next_chunk(&mut self) -> Result<PendingChunk> {
+        loop {
+            // Read the next chunk from the current rec list, if any, otherwise, from the movi
+            // list.
+            let chunk = match self.rec.as_mut() {
+                Some(rec) => rec.next(&mut self.reader)?,
+                None => None,
+            };
+
+            let chunk = match chunk {
+                Some(chunk) => chunk,
+                None => {
+                    if let Some(mut rec) = self.rec.take() {
+                        rec.finish(&mut self.reader)?;
+                    }
+
+                    match self.movi.next(&mut self.reader)? {
+                        Some(chunk) => chunk,
+                        None => return end_of_stream_error(),
+                    }
+                }
+            };
+
+            match chunk {
+                MoviListChunks::List(lst) => {
+                    let list = lst.parse(&mut self.reader)?;
+
+                    // Chunks may be grouped into rec lists to be read together.
+                    match &list.form {
+                        b"rec " if self.rec.is_none() => {
+                            self.rec = Some(ChunksReader::new(list.len, ByteOrder::LittleEndian));
+                        }
+                        _ => list.skip(&mut self.reader)?,
+                    }
+                }
+                MoviListChunks::Data(dat) => {
+                    let data = dat.parse(&mut self.reader)?;
+
+                    let stream = self
+                        .streams
+                        .iter()
+                        .position(|stream| stream.track_id == data.stream && data.is_audio());
+
+                    match stream {
+                        Some(stream) if data.len > 0 => {
+                            return Ok(PendingChunk { stream, len: data.len });
+                        }
+                        _ => self.reader.ignore_bytes(u64::from(data.len))?,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Positions the reader at the start of the next packet. Returns the index of the stream
+    /// the packet belongs to, and the length and duration of the packet.
+    fn next_packet_bounds(&mut self) -> Result<(usize, u32, u64)> {
+        loop {
+            if let Some(chunk) = &self.chunk {
+                let (len, dur) = self.streams[chunk.stream].next_packet_len(chunk.len);
+
+                if len > 0 {
+                    return Ok((chunk.stream, len, dur));
+                }
+
+                // The remainder of the chunk is too short to form a packet.
+                let remaining = u64::from(chunk.len);
+
+                self.chunk = None;
+                self.reader.ignore_bytes(remaining)?;
+            }
+
+            self.chunk = Some(self.next_chunk()?);
+        }
+    }
+
+    /// Advances the state of the reader past a packet.
+    fn advance(&mut self, stream: usize, len: u32) {
+        self.streams[stream].advance(len);
+
+        if let Some(chunk) = self.chunk.as_mut() {
+            chunk.len -= len;
+
+            if chunk.len == 0 {
+                self.chunk = None;
+            }
+        }
+    }
+
+    /// Seek back to the start of the movi list, and reset all streams.
+    fn rewind_movi(&mut self) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(self.movi_start_pos))?;
+
+        self.movi = ChunksReader::new(self.movi_len, ByteOrder::LittleEndian);
+        self.movi.set_lenient(self.lenient);
+        self.rec = None;
+        self.chunk = None;
+
+        for stream in self.streams.iter_mut() {
+            stream.consumed = 0;
+        }
+
+        Ok(())
+    }
+}
+
+impl FormatReader for AviReader {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        // The RIFF marker should be present.
+        let marker = source.read_quad_bytes()?;
+
+        if marker != AVI_STREAM_MARKER {
+            return unsupported_error("avi: missing riff stream marker");
+        }
+
+        let riff_len = source.read_u32()?;
+        let riff_form = source.read_quad_bytes()?;
+
+        // The RIFF chunk contains AVI data.
+        if riff_form != AVI_RIFF_FORM {
+            error!("riff form is not avi ({})", String::from_utf8_lossy(&riff_form));
+
+            return unsupported_error("avi: riff form is not avi");
+        }
+
+        let mut riff_chunks = ChunksReader::<RiffAviChunks>::new(riff_len, ByteOrder::LittleEndian);
+        riff_chunks.set_lenient(options.enable_lenient);
+
+        let mut streams = Vec::new();
+        let mut tracks = Vec::new();
+
+        // The INFO list and the digitization date are combined into a single metadata revision.
+        let mut metadata = MetadataBuilder::new();
+
+        loop {
+            let chunk = riff_chunks.next(&mut source)?;
+
+            // The movi list, containing the stream data, should always be found.
+            let lst = match chunk {
+                Some(RiffAviChunks::List(lst)) => lst,
+                Some(RiffAviChunks::DigitizationDate(idit)) => {
+                    metadata.add_tag(idit.parse(&mut source)?.to_tag());
+                    continue;
+                }
+                None => return unsupported_error("avi: missing movi list"),
+            };
+
+            let list = lst.parse(&mut source)?;
+
+            match &list.form {
+                b"hdrl" => {
+                    // The header list contains the main header, and a stream list for each
+                    // stream.
+                    let mut hdrl =
+                        ChunksReader::<HeaderListChunks>::new(list.len, ByteOrder::LittleEndian);
+
+                    let mut n_streams = 0;
+
+                    while let Some(chunk) = hdrl.next(&mut source)? {
+                        let lst = match chunk {
+                            HeaderListChunks::List(lst) => lst,
+                            HeaderListChunks::DigitizationDate(idit) => {
+                                metadata.add_tag(idit.parse(&mut source)?.to_tag());
+                                continue;
+                            }
+                        };
+
+                        let list = lst.parse(&mut source)?;
+
+                        if &list.form != b"strl" {
+                            list.skip(&mut source)?;
+                            continue;
+                        }
+
+                        if let Some((stream, codec_params)) =
+                            read_stream_list(&mut source, list.len, n_streams)?
+                        {
+                            tracks.push(Track::new(stream.track_id, codec_params));
+                            streams.push(stream);
+                        }
+
+                        n_streams += 1;
+                    }
+
+                    hdrl.finish(&mut source)?;
+                }
+                b"movi" => {
+                    if streams.is_empty() {
+                        return unsupported_error("avi: no supported audio streams");
+                    }
+
+                    let movi_start_pos = source.pos();
+
+                    let mut movi = ChunksReader::new(list.len, ByteOrder::LittleEndian);
+                    movi.set_lenient(options.enable_lenient);
+
+                    let mut metadata_log = MetadataLog::default();
+
+                    let rev = metadata.metadata();
+
+                    if !rev.tags().is_empty() {
+                        metadata_log.push(rev);
+                    }
+
+                    return Ok(AviReader {
+                        reader: source,
+                        tracks,
+                        cues: Vec::new(),
+                        metadata: metadata_log,
+                        streams,
+                        lenient: options.enable_lenient,
+                        movi_start_pos,
+                        movi_len: list.len,
+                        movi,
+                        rec: None,
+                        chunk: None,
+                    });
+                }
+                b"INFO" => {
+                    for tag in read_info_chunk(&mut source, list.len)?.tags() {
+                        metadata.add_tag(tag.clone());
+                    }
+                }
+                _ => list.skip(&mut source)?,
+            }
+        }
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let (stream, len, dur) = self.next_packet_bounds()?;
+
+        let ts = self.streams[stream].ts();
+        let track_id = self.streams[stream].track_id;
+
+        let buf = self.reader.read_boxed_slice_exact(len as usize)?;
+
+        self.advance(stream, len);
+
+        Ok(Packet::new_from_boxed_slice(track_id, ts, dur, buf))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Get the index of the audio stream to seek, and the timestamp to seek to.
+        let (stream, ts) = match to {
+            SeekTo::TimeStamp { ts, track_id } => {
+                match self.streams.iter().position(|stream| stream.track_id == track_id) {
+                    Some(stream) => (stream, ts),
+                    None => return seek_error(SeekErrorKind::InvalidTrack),
+                }
+            }
+            SeekTo::Time { time, track_id } => {
+                let stream = match track_id {
+                    Some(track_id) => {
+                        self.streams.iter().position(|stream| stream.track_id == track_id)
+                    }
+                    None => Some(0),
+                };
+
+                match stream {
+                    Some(stream) => {
+                        let sample_rate = self.streams[stream].sample_rate;
+                        (stream, TimeBase::new(1, sample_rate).calc_timestamp(time))
+                    }
+                    None => return seek_error(SeekErrorKind::InvalidTrack),
+                }
+            }
+        };
+
+        debug!("seeking to frame_ts={}", ts);
+
+        // Chunks are scanned forward from the current position. If the desired timestamp is before
+        // the current position, start from the beginning of the movi list.
+        if ts < self.streams[stream].ts() {
+            if !self.reader.is_seekable() {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+
+            self.rewind_movi()?;
+        }
+
+        // Skip packets until the packet containing the desired timestamp is found.
+        loop {
+            let (packet_stream, len, dur) = match self.next_packet_bounds() {
+                Ok(bounds) => bounds,
+                Err(Error::IoError(ref err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return seek_error(SeekErrorKind::OutOfRange);
+                }
+                Err(err) => return Err(err),
+            };
+
+            let packet_ts = self.streams[packet_stream].ts();
+
+            if packet_stream == stream && packet_ts + dur > ts {
+                let delta = packet_ts as i64 - ts as i64;
+
+                debug!("seeked to packet_ts={} (delta={})", packet_ts, delta);
+
+                let track_id = self.streams[stream].track_id;
+
+                return Ok(SeekedTo { track_id, actual_ts: packet_ts, required_ts: ts });
+            }
+
+            self.reader.ignore_bytes(u64::from(len))?;
+            self.advance(packet_stream, len);
+        }
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::codecs::CODEC_TYPE_MP3;
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::{MetadataOptions, StandardTagKey};
+    use symphonia_core::probe::{Hint, Probe};
+
+    use super::AviReader;
+    use crate::test_util::{chunk, list, riff};
+    use crate::WavReader;
+
+    /// Builds a stream header.
+    fn strh(fcc_type: &[u8; 4], scale: u32, rate: u32, length: u32, sample_size: u32) -> Vec<u8> {
+        let mut data = fcc_type.to_vec();
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(&scale.to_le_bytes());
+        data.extend_from_slice(&rate.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&length.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&sample_size.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        chunk(b"strh", &data)
+    }
+
+    /// Builds an AVI file with a video stream, and a VBR MP3 audio stream of 3 frames. The frames
+    /// are not valid MP3 frames. The file is tagged with an INFO list and a digitization date.
+    fn build_avi() -> Vec<u8> {
+        // An MPEGLAYER3WAVEFORMAT for 44.1 kHz stereo MP3.
+        let mut mp3_fmt = Vec::new();
+        mp3_fmt.extend_from_slice(&0x55u16.to_le_bytes());
+        mp3_fmt.extend_from_slice(&2u16.to_le_bytes());
+        mp3_fmt.extend_from_slice(&44100u32.to_le_bytes());
+        mp3_fmt.extend_from_slice(&16000u32.to_le_bytes());
+        mp3_fmt.extend_from_slice(&1u16.to_le_bytes());
+        mp3_fmt.extend_from_slice(&0u16.to_le_bytes());
+        mp3_fmt.extend_from_slice(&12u16.to_le_bytes());
+        mp3_fmt.extend_from_slice(&[1, 0, 2, 0, 0, 0, 0x80, 0x01, 1, 0, 0x71, 0x05]);
+
+        let hdrl = list(
+            b"hdrl",
+            &[
+                chunk(b"avih", &[0; 56]),
+                list(b"strl", &[strh(b"vids", 1, 25, 3, 0), chunk(b"strf", &[0; 40])]),
+                list(b"strl", &[strh(b"auds", 1152, 44100, 3, 0), chunk(b"strf", &mp3_fmt)]),
+                chunk(b"IDIT", b"THU OCT 16 12:34:56 2008\n\0"),
+            ],
+        );
+
+        let movi = list(
+            b"movi",
+            &[
+                chunk(b"00dc", &[0xaa; 5]),
+                chunk(b"01wb", &[1, 1, 1, 1]),
+                list(b"rec ", &[chunk(b"00dc", &[0xbb; 2]), chunk(b"01wb", &[2, 2, 2])]),
+                chunk(b"JUNK", &[0; 4]),
+                chunk(b"01wb", &[3, 3, 3, 3, 3]),
+                chunk(b"00dc", &[0xcc; 3]),
+            ],
+        );
+
+        let info = list(b"INFO", &[chunk(b"INAM", b"Title"), chunk(b"IART", b"Artist")]);
+
+        riff(b"AVI ", &[hdrl, info, movi, chunk(b"idx1", &[0; 16])])
+    }
+
+    fn open_avi(buf: Vec<u8>) -> AviReader {
+        let mss = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        AviReader::try_new(mss, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_mp3_audio_stream() {
+        let mut reader = open_avi(build_avi());
+
+        // Only the audio stream is a track. The track ID is the stream index.
+        assert_eq!(reader.tracks().len(), 1);
+
+        let track = &reader.tracks()[0];
+
+        assert_eq!(track.id, 1);
+        assert_eq!(track.codec_params.codec, CODEC_TYPE_MP3);
+        assert_eq!(track.codec_params.sample_rate, Some(44100));
+        assert_eq!(track.codec_params.channels.map(|channels| channels.count()), Some(2));
+        assert_eq!(track.codec_params.n_frames, Some(3 * 1152));
+
+        // Each audio chunk is a packet, and video chunks are skipped.
+        let mut packets = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            packets.push((packet.track_id(), packet.ts(), packet.dur(), packet.buf().to_vec()));
+        }
+
+        assert_eq!(
+            packets,
+            vec![
+                (1, 0, 1152, vec![1; 4]),
+                (1, 1152, 1152, vec![2; 3]),
+                (1, 2304, 1152, vec![3; 5]),
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_seek() {
+        let mut reader = open_avi(build_avi());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1200, track_id: 1 });
+        let seeked = seeked.unwrap();
+
+        assert_eq!((seeked.required_ts, seeked.actual_ts), (1200, 1152));
+        assert_eq!(reader.next_packet().unwrap().buf(), &[2; 3]);
+
+        // Seek backwards to the first packet.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 10, track_id: 1 });
+        assert_eq!(seeked.unwrap().actual_ts, 0);
+        assert_eq!(reader.next_packet().unwrap().buf(), &[1; 4]);
+
+        // Seeking past the end of the stream is an error.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 4000, track_id: 1 });
+        assert!(seeked.is_err());
+
+        // Seeking a video stream is an error.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id: 0 });
+        assert!(seeked.is_err());
+    }
+
+    #[test]
+    fn verify_metadata() {
+        let mut reader = open_avi(build_avi());
+
+        let metadata = reader.metadata();
+        let rev = metadata.current().unwrap();
+
+        let tags: Vec<_> = rev
+            .tags()
+            .iter()
+            .map(|tag| (tag.std_key, tag.key.as_str(), tag.value.to_string()))
+            .collect();
+
+        // The digitization date is found in the header list, before the INFO list.
+        assert_eq!(
+            tags,
+            vec![
+                (Some(StandardTagKey::Date), "IDIT", "2008-10-16T12:34:56".to_string()),
+                (Some(StandardTagKey::TrackTitle), "INAM", "Title".to_string()),
+                (Some(StandardTagKey::Artist), "IART", "Artist".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_probe_riff_form() {
+        let mut probe = Probe::default();
+        probe.register_all::<WavReader>();
+        probe.register_all::<AviReader>();
+
+        // The WAVE reader is registered first, but the AVI reader should be selected.
+        let source = Box::new(std::io::Cursor::new(build_avi()));
+        let mss = MediaSourceStream::new(source, Default::default());
+
+        let probed = probe
+            .format(&Hint::new(), mss, &FormatOptions::default(), &MetadataOptions::default())
+            .unwrap();
+
+        assert_eq!(probed.format.tracks()[0].codec_params.codec, CODEC_TYPE_MP3);
+    }
+}
@@ -7,6 +7,7 @@
 
 /// `PacketInfo` helps to simulate packetization over a number of blocks of data.
 /// In case the codec is blockless the block size equals one full audio frame in bytes.
+use std::io::{Seek, SeekFrom};
 use std::marker::PhantomData;
 
 use symphonia_core::audio::Channels;
@@ -14,9 +15,11 @@ use symphonia_core::codecs::CodecParameters;
 use symphonia_core::codecs::CodecType;
 use symphonia_core::errors::{decode_error, end_of_stream_error, Error, Result};
 use symphonia_core::formats::prelude::*;
-use symphonia_core::io::{MediaSourceStream, ReadBytes};
+use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, ScopedStream};
+use symphonia_core::meta::{MetadataBuilder, MetadataLog, MetadataRevision};
+use symphonia_metadata::id3v2::read_id3v2;
 
-use log::{debug, info};
+use log::{debug, info, warn};
 
 pub enum ByteOrder {
     LittleEndian,
@@ -31,6 +34,12 @@ const MAX_FRAMES_PER_PACKET: u64 = 1152;
 /// enumeration
 pub trait ParseChunkTag: Sized {
     fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self>;
+
+    /// Returns `true` if the chunk with the given tag contains the audio data. When lenient, the
+    /// length of an audio data chunk is trusted over the length of its parent chunk.
+    fn is_audio_data(_tag: [u8; 4]) -> bool {
+        false
+    }
 }
 
 pub enum NullChunks {}
@@ -78,11 +87,7 @@ fn test_fix_channel_mask() {
 }
 
 pub fn try_channel_count_to_mask(count: u16) -> Result<Channels> {
-    (1..=32)
-        .contains(&count)
-        .then(|| Channels::from_bits(((1u64 << count) - 1) as u32))
-        .flatten()
-        .ok_or(Error::DecodeError("riff: invalid channel count"))
+    Channels::discrete(usize::from(count)).ok_or(Error::DecodeError("riff: invalid channel count"))
 }
 
 #[test]
@@ -107,12 +112,22 @@ pub struct ChunksReader<T: ParseChunkTag> {
     len: u32,
     byte_order: ByteOrder,
     consumed: u32,
+    lenient: bool,
     phantom: PhantomData<T>,
 }
 
 impl<T: ParseChunkTag> ChunksReader<T> {
     pub fn new(len: u32, byte_order: ByteOrder) -> Self {
-        ChunksReader { len, byte_order, consumed: 0, phantom: PhantomData }
+        ChunksReader { len, byte_order, consumed: 0, lenient: false, phantom: PhantomData }
+    }
+
+    /// If lenient, tolerate chunks with a length exceeding the length of the parent chunk.
+    ///
+    /// The length of an audio data chunk is trusted, while the length of any other chunk is
+    /// limited to the remaining length of the parent chunk.
+    pub fn set_lenient(&mut self, lenient: bool) -> &mut Self {
+        self.lenient = lenient;
+        self
     }
 
     pub fn next<B: ReadBytes>(&mut self, reader: &mut B) -> Result<Option<T>> {
@@ -132,7 +147,7 @@ impl<T: ParseChunkTag> ChunksReader<T> {
             // Read tag and len, the chunk header.
             let tag = reader.read_quad_bytes()?;
 
-            let len = match self.byte_order {
+            let mut len = match self.byte_order {
                 ByteOrder::LittleEndian => reader.read_u32()?,
                 ByteOrder::BigEndian => reader.read_be_u32()?,
             };
@@ -147,11 +162,29 @@ impl<T: ParseChunkTag> ChunksReader<T> {
                 // When ffmpeg encodes wave to stdout the riff (parent) and data chunk lengths are
                 // (2^32)-1 since the size can't be known ahead of time.
                 if !(self.len == len && len == u32::MAX) {
-                    debug!(
+                    // Some writers do not correctly update the parent chunk length. If lenient,
+                    // trust the chunk length instead. Any chunks following it will be ignored.
+                    if !self.lenient {
+                        debug!(
+                            "chunk length of {} exceeds parent (list) chunk length",
+                            String::from_utf8_lossy(&tag)
+                        );
+                        return decode_error(
+                            "riff: chunk length exceeds parent (list) chunk length",
+                        );
+                    }
+
+                    warn!(
                         "chunk length of {} exceeds parent (list) chunk length",
                         String::from_utf8_lossy(&tag)
                     );
-                    return decode_error("riff: chunk length exceeds parent (list) chunk length");
+
+                    // Only the length of the audio data chunk is trusted. Any other chunk may be
+                    // read into memory, so its untrusted length is limited to the remaining
+                    // length of the parent chunk.
+                    if !T::is_audio_data(tag) {
+                        len = self.len - self.consumed;
+                    }
                 }
             }
 
@@ -212,6 +245,119 @@ impl<P: ParseChunk> ChunkParser<P> {
     }
 }
 
+/// An ID3v2 tag chunk (`id3 ` or `ID3 `).
+pub struct Id3Chunk {
+    /// The metadata read from the ID3v2 tag, or `None` if the tag is malformed.
+    pub metadata: Option<MetadataRevision>,
+}
+
+impl ParseChunk for Id3Chunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _: [u8; 4], len: u32) -> Result<Id3Chunk> {
+        let mut scoped = ScopedStream::new(reader, u64::from(len));
+
+        let mut builder = MetadataBuilder::new();
+
+        // A malformed ID3v2 tag should not prevent the rest of the file from being read.
+        let metadata = match read_id3v2(&mut scoped, &mut builder) {
+            Ok(_) => Some(builder.metadata()),
+            Err(err) => {
+                warn!("ignoring malformed id3 chunk: {}", err);
+                None
+            }
+        };
+
+        // Ignore any remaining data in the chunk.
+        scoped.ignore()?;
+
+        Ok(Id3Chunk { metadata })
+    }
+}
+
+/// The metadata chunks that are read after the audio data chunk.
+pub enum TrailingChunks {
+    Id3(ChunkParser<Id3Chunk>),
+}
+
+impl ParseChunkTag for TrailingChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        match &tag {
+            b"id3 " | b"ID3 " => Some(TrailingChunks::Id3(ChunkParser::new(tag, len))),
+            _ => None,
+        }
+    }
+}
+
+impl TrailingChunks {
+    /// Reads the chunk, and appends any metadata it contains to the metadata log.
+    pub fn read(self, source: &mut MediaSourceStream, metadata: &mut MetadataLog) -> Result<()> {
+        match self {
+            TrailingChunks::Id3(id3) => {
+                if let Some(revision) = id3.parse(source)?.metadata {
+                    metadata.push(revision);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the chunks following the audio data chunk, starting at `start` and ending at `end`, and
+/// then seeks back to the current position. Each recognized chunk is passed to `read_chunk` to be
+/// read.
+///
+/// Taggers usually append metadata chunks, such as an ID3v2 tag, after the audio data. Since the
+/// audio data chunk is the last chunk read when opening a stream, the source must be seekable to
+/// read them. A malformed trailing chunk is ignored, along with any chunks following it.
+pub fn read_trailing_chunks<T, F>(
+    source: &mut MediaSourceStream,
+    start: u64,
+    end: u64,
+    byte_order: ByteOrder,
+    mut read_chunk: F,
+) -> Result<()>
+where
+    T: ParseChunkTag,
+    F: FnMut(&mut MediaSourceStream, T) -> Result<()>,
+{
+    // Chunk lengths may be larger than the stream if the stream was not finalized.
+    let end = match source.byte_len() {
+        Some(byte_len) => end.min(byte_len),
+        None => end,
+    };
+
+    if !source.is_seekable() || end < start.saturating_add(8) {
+        return Ok(());
+    }
+
+    let resume_pos = source.pos();
+
+    source.seek(SeekFrom::Start(start))?;
+
+    let len = (end - start).min(u64::from(u32::MAX)) as u32;
+    let mut chunks = ChunksReader::<T>::new(len, byte_order);
+
+    loop {
+        match chunks.next(source) {
+            Ok(Some(chunk)) => {
+                if let Err(err) = read_chunk(source, chunk) {
+                    warn!("ignoring malformed trailing chunk: {}", err);
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(err) => {
+                warn!("ignoring malformed trailing chunk: {}", err);
+                break;
+            }
+        }
+    }
+
+    source.seek(SeekFrom::Start(resume_pos))?;
+
+    Ok(())
+}
+
 pub enum FormatData {
     Pcm(FormatPcm),
     Adpcm(FormatAdpcm),
@@ -219,6 +365,8 @@ pub enum FormatData {
     Extensible(FormatExtensible),
     ALaw(FormatALaw),
     MuLaw(FormatMuLaw),
+    Mpeg(FormatMpeg),
+    Ac3(FormatAc3),
 }
 
 pub struct FormatPcm {
@@ -231,7 +379,8 @@ pub struct FormatPcm {
 }
 
 pub struct FormatAdpcm {
-    /// The number of bits per sample. At the moment only 4bit is supported.
+    /// The number of bits per sample. Microsoft ADPCM is always 4bit, while IMA ADPCM may be 3,
+    /// 4, or 5bit.
     pub bits_per_sample: u16,
     /// Channel bitmask.
     pub channels: Channels,
@@ -275,6 +424,20 @@ pub struct FormatMuLaw {
     pub codec: CodecType,
 }
 
+pub struct FormatMpeg {
+    /// Channel bitmask.
+    pub channels: Channels,
+    /// Codec type.
+    pub codec: CodecType,
+}
+
+pub struct FormatAc3 {
+    /// Channel bitmask.
+    pub channels: Channels,
+    /// Codec type.
+    pub codec: CodecType,
+}
+
 pub struct PacketInfo {
     pub block_size: u64,
     pub frames_per_block: u64,
@@ -374,7 +537,10 @@ pub fn append_format_params(
                 .with_channels(pcm.channels);
         }
         FormatData::Adpcm(adpcm) => {
-            codec_params.for_codec(adpcm.codec).with_channels(adpcm.channels);
+            codec_params
+                .for_codec(adpcm.codec)
+                .with_bits_per_coded_sample(u32::from(adpcm.bits_per_sample))
+                .with_channels(adpcm.channels);
         }
         FormatData::IeeeFloat(ieee) => {
             codec_params.for_codec(ieee.codec).with_channels(ieee.channels);
@@ -392,6 +558,12 @@ pub fn append_format_params(
         FormatData::MuLaw(mulaw) => {
             codec_params.for_codec(mulaw.codec).with_channels(mulaw.channels);
         }
+        FormatData::Mpeg(mpeg) => {
+            codec_params.for_codec(mpeg.codec).with_channels(mpeg.channels);
+        }
+        FormatData::Ac3(ac3) => {
+            codec_params.for_codec(ac3.codec).with_channels(ac3.channels);
+        }
     }
 }
 
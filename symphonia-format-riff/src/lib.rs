@@ -18,10 +18,19 @@ mod common;
 
 #[cfg(feature = "aiff")]
 mod aiff;
+#[cfg(feature = "avi")]
+mod avi;
 #[cfg(feature = "wav")]
 mod wave;
 
+#[cfg(all(test, feature = "wav"))]
+mod test_util;
+
 #[cfg(feature = "aiff")]
 pub use aiff::AiffReader;
+#[cfg(feature = "avi")]
+pub use avi::AviReader;
 #[cfg(feature = "wav")]
 pub use wave::WavReader;
+#[cfg(feature = "wav-write")]
+pub use wave::{WavSampleFormat, WavWriter};
@@ -0,0 +1,54 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Builders for the RIFF files used by the tests.
+
+/// Builds a chunk, padded to a 2-byte boundary.
+pub fn chunk(tag: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut buf = tag.to_vec();
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(data);
+    if data.len() & 1 == 1 {
+        buf.push(0);
+    }
+    buf
+}
+
+/// Builds the data of a list or RIFF chunk.
+fn form_data(form: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = form.to_vec();
+    data.extend(chunks.iter().flatten());
+    data
+}
+
+/// Builds a list chunk.
+pub fn list(form: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    chunk(b"LIST", &form_data(form, chunks))
+}
+
+/// Builds a RIFF file.
+pub fn riff(form: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    chunk(b"RIFF", &form_data(form, chunks))
+}
+
+/// Builds a WAVE file.
+pub fn wav(chunks: &[Vec<u8>]) -> Vec<u8> {
+    riff(b"WAVE", chunks)
+}
+
+/// Builds a format chunk for PCM audio.
+pub fn pcm_fmt(n_channels: u16, sample_rate: u32, bits_per_sample: u16) -> Vec<u8> {
+    let block_align = n_channels * (bits_per_sample / 8);
+
+    let mut data = 1u16.to_le_bytes().to_vec();
+    data.extend_from_slice(&n_channels.to_le_bytes());
+    data.extend_from_slice(&sample_rate.to_le_bytes());
+    data.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    data.extend_from_slice(&block_align.to_le_bytes());
+    data.extend_from_slice(&bits_per_sample.to_le_bytes());
+    chunk(b"fmt ", &data)
+}
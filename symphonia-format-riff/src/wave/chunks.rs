@@ -11,19 +11,22 @@ use symphonia_core::audio::Channels;
 use symphonia_core::codecs::CodecParameters;
 use symphonia_core::codecs::CodecType;
 use symphonia_core::codecs::{
-    CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS, CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32LE,
-    CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE,
-    CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
+    CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS, CODEC_TYPE_EAC3, CODEC_TYPE_MP1,
+    CODEC_TYPE_MP2, CODEC_TYPE_MP3, CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64LE,
+    CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE,
+    CODEC_TYPE_PCM_U8,
 };
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::io::{MediaSourceStream, ReadBytes};
-use symphonia_core::meta::{MetadataBuilder, MetadataRevision, Tag};
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision, StandardTagKey, Tag, Value};
 use symphonia_metadata::riff;
 
+use log::warn;
+
 use crate::common::{
     fix_channel_mask, try_channel_count_to_mask, ByteOrder, ChunkParser, ChunksReader, FormatALaw,
-    FormatAdpcm, FormatData, FormatExtensible, FormatIeeeFloat, FormatMuLaw, FormatPcm, NullChunks,
-    PacketInfo, ParseChunk, ParseChunkTag,
+    FormatAc3, FormatAdpcm, FormatData, FormatExtensible, FormatIeeeFloat, FormatMpeg, FormatMuLaw,
+    FormatPcm, Id3Chunk, NullChunks, PacketInfo, ParseChunk, ParseChunkTag,
 };
 
 pub struct WaveFormatChunk {
@@ -100,8 +103,13 @@ impl WaveFormatChunk {
         len: u32,
         codec: CodecType,
     ) -> Result<FormatData> {
-        if bits_per_sample != 4 {
-            return decode_error("wav: bits per sample for fmt_adpcm must be 4 bits");
+        // IMA ADPCM may also use 3 or 5-bit codes.
+        match (codec, bits_per_sample) {
+            (_, 4) | (CODEC_TYPE_ADPCM_IMA_WAV, 3 | 5) => (),
+            (CODEC_TYPE_ADPCM_IMA_WAV, _) => {
+                return decode_error("wav: bits per sample for fmt_adpcm must be 3, 4, or 5 bits");
+            }
+            _ => return decode_error("wav: bits per sample for fmt_adpcm must be 4 bits"),
         }
 
         // WaveFormatEx with extension data length field present and with atleast frames per block data.
@@ -201,7 +209,9 @@ impl WaveFormatChunk {
             );
         }
 
-        let channel_mask = fix_channel_mask(reader.read_u32()?, n_channels);
+        // Only the bits of the channel mask with a defined speaker position are used.
+        let positions = Channels::from_wave_channel_mask(reader.read_u32()?);
+        let channel_mask = fix_channel_mask(positions.bits(), n_channels);
 
         // Try to map channels.
         let channels = match Channels::from_bits(channel_mask) {
@@ -250,6 +260,27 @@ impl WaveFormatChunk {
             0x86, 0x44, 0xc8, 0xc1, 0xca, 0x00, 0x00, 0x00,
         ];
 
+        const KNOWN_SUB_FORMATS: [[u8; 16]; 6] = [
+            KSDATAFORMAT_SUBTYPE_PCM,
+            KSDATAFORMAT_SUBTYPE_IEEE_FLOAT,
+            KSDATAFORMAT_SUBTYPE_ALAW,
+            KSDATAFORMAT_SUBTYPE_MULAW,
+            KSDATAFORMAT_SUBTYPE_AMBISONIC_B_FORMAT_PCM,
+            KSDATAFORMAT_SUBTYPE_AMBISONIC_B_FORMAT_IEE_FLOAT,
+        ];
+
+        // The first three fields of a GUID are little-endian. Some writers incorrectly store them
+        // in big-endian byte order. If the GUID is unknown, but is known with the byte order of
+        // those fields swapped, then use the swapped GUID.
+        if !KNOWN_SUB_FORMATS.contains(&sub_format_guid) {
+            let swapped = swap_guid_byte_order(sub_format_guid);
+
+            if KNOWN_SUB_FORMATS.contains(&swapped) {
+                warn!("wav: fmt_ext sub-type guid has the wrong byte order");
+                sub_format_guid = swapped;
+            }
+        }
+
         // Verify support based on the format GUID.
         let codec = match sub_format_guid {
             KSDATAFORMAT_SUBTYPE_PCM | KSDATAFORMAT_SUBTYPE_AMBISONIC_B_FORMAT_PCM => {
@@ -267,7 +298,11 @@ impl WaveFormatChunk {
                     16 => CODEC_TYPE_PCM_S16LE,
                     24 => CODEC_TYPE_PCM_S24LE,
                     32 => CODEC_TYPE_PCM_S32LE,
-                    _ => unreachable!(),
+                    _ => {
+                        return decode_error(
+                            "wav: bits per coded sample for fmt_ext PCM sub-type must not be 0",
+                        )
+                    }
                 }
             }
             KSDATAFORMAT_SUBTYPE_IEEE_FLOAT | KSDATAFORMAT_SUBTYPE_AMBISONIC_B_FORMAT_IEE_FLOAT => {
@@ -341,6 +376,52 @@ impl WaveFormatChunk {
         Ok(FormatData::MuLaw(FormatMuLaw { codec: CODEC_TYPE_PCM_MULAW, channels }))
     }
 
+    fn read_mpeg_fmt<B: ReadBytes>(
+        reader: &mut B,
+        n_channels: u16,
+        len: u32,
+    ) -> Result<FormatData> {
+        // MPEG1WAVEFORMAT extends WaveFormatEx with 22 bytes of extension data. The first field,
+        // fwHeadLayer, is the MPEG audio layer. If the extension data is missing, assume layer 2.
+        let mut codec = CODEC_TYPE_MP2;
+        let mut extra_len = len - 16;
+
+        if extra_len >= 4 {
+            let _extra_size = reader.read_u16()?;
+
+            codec = match reader.read_u16()? {
+                0x1 => CODEC_TYPE_MP1,
+                0x2 => CODEC_TYPE_MP2,
+                0x4 => CODEC_TYPE_MP3,
+                _ => return decode_error("wav: invalid mpeg layer"),
+            };
+
+            extra_len -= 4;
+        }
+
+        reader.ignore_bytes(u64::from(extra_len))?;
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Mpeg(FormatMpeg { codec, channels }))
+    }
+
+    fn read_mp3_fmt<B: ReadBytes>(reader: &mut B, n_channels: u16, len: u32) -> Result<FormatData> {
+        // MPEGLAYER3WAVEFORMAT extends WaveFormatEx with information only required by the ACM
+        // codec. Skip it.
+        reader.ignore_bytes(u64::from(len - 16))?;
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Mpeg(FormatMpeg { codec: CODEC_TYPE_MP3, channels }))
+    }
+
+    fn read_ac3_fmt<B: ReadBytes>(reader: &mut B, n_channels: u16, len: u32) -> Result<FormatData> {
+        // Any extension data is not required to identify the stream. Skip it.
+        reader.ignore_bytes(u64::from(len - 16))?;
+
+        let channels = try_channel_count_to_mask(n_channels)?;
+        Ok(FormatData::Ac3(FormatAc3 { codec: CODEC_TYPE_EAC3, channels }))
+    }
+
     pub(crate) fn packet_info(&self) -> Result<PacketInfo> {
         match self.format_data {
             FormatData::Adpcm(FormatAdpcm { codec, bits_per_sample, .. })
@@ -355,9 +436,17 @@ impl WaveFormatChunk {
             FormatData::Adpcm(FormatAdpcm { codec, bits_per_sample, .. })
                 if codec == CODEC_TYPE_ADPCM_IMA_WAV =>
             {
-                let frames_per_block = (((self.block_align - (4 * self.n_channels)) * 8)
-                    / (bits_per_sample * self.n_channels)
-                    + 1) as u64;
+                let data_len = self.block_align.saturating_sub(4 * self.n_channels);
+
+                let frames_per_block = if bits_per_sample == 4 {
+                    ((data_len * 8) / (bits_per_sample * self.n_channels) + 1) as u64
+                }
+                else {
+                    // Codes that are not 4 bits wide are packed into groups of 32 codes per
+                    // channel, each stored as `bits_per_sample` 32-bit words.
+                    let group_len = 4 * u64::from(bits_per_sample) * u64::from(self.n_channels);
+                    32 * (u64::from(data_len) / group_len) + 1
+                };
                 PacketInfo::with_blocks(self.block_align, frames_per_block)
             }
             _ => Ok(PacketInfo::without_blocks(self.block_align)),
@@ -365,6 +454,15 @@ impl WaveFormatChunk {
     }
 }
 
+/// Swap the byte order of the first three fields (a 32-bit integer, followed by two 16-bit
+/// integers) of a GUID.
+fn swap_guid_byte_order(mut guid: [u8; 16]) -> [u8; 16] {
+    guid[0..4].reverse();
+    guid[4..6].reverse();
+    guid[6..8].reverse();
+    guid
+}
+
 impl ParseChunk for WaveFormatChunk {
     fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<WaveFormatChunk> {
         // WaveFormat has a minimal length of 16 bytes. This may be extended with format specific
@@ -388,6 +486,9 @@ impl ParseChunk for WaveFormatChunk {
         const WAVE_FORMAT_ALAW: u16 = 0x0006;
         const WAVE_FORMAT_MULAW: u16 = 0x0007;
         const WAVE_FORMAT_ADPCM_IMA: u16 = 0x0011;
+        const WAVE_FORMAT_MPEG: u16 = 0x0050;
+        const WAVE_FORMAT_MPEGLAYER3: u16 = 0x0055;
+        const WAVE_FORMAT_DVM: u16 = 0x2000;
         const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
 
         let format_data = match format {
@@ -413,6 +514,12 @@ impl ParseChunk for WaveFormatChunk {
                 len,
                 CODEC_TYPE_ADPCM_IMA_WAV,
             ),
+            // The MPEG Layer 1 or 2 Format.
+            WAVE_FORMAT_MPEG => Self::read_mpeg_fmt(reader, n_channels, len),
+            // The MPEG Layer 3 Format.
+            WAVE_FORMAT_MPEGLAYER3 => Self::read_mp3_fmt(reader, n_channels, len),
+            // The AC-3 Format.
+            WAVE_FORMAT_DVM => Self::read_ac3_fmt(reader, n_channels, len),
             // Unsupported format.
             _ => return unsupported_error("wav: unsupported wave format"),
         }?;
@@ -465,6 +572,16 @@ impl fmt::Display for WaveFormatChunk {
                 writeln!(f, "\t\tchannels: {},", mulaw.channels)?;
                 writeln!(f, "\t\tcodec: {},", mulaw.codec)?;
             }
+            FormatData::Mpeg(ref mpeg) => {
+                writeln!(f, "\tformat_data: Mpeg {{")?;
+                writeln!(f, "\t\tchannels: {},", mpeg.channels)?;
+                writeln!(f, "\t\tcodec: {},", mpeg.codec)?;
+            }
+            FormatData::Ac3(ref ac3) => {
+                writeln!(f, "\tformat_data: Ac3 {{")?;
+                writeln!(f, "\t\tchannels: {},", ac3.channels)?;
+                writeln!(f, "\t\tcodec: {},", ac3.codec)?;
+            }
         };
 
         writeln!(f, "\t}}")?;
@@ -542,6 +659,293 @@ impl ParseChunk for InfoChunk {
     }
 }
 
+/// The loudness measurements stored in a version 2 Broadcast Wave Format extension chunk. All
+/// values are in LUFS, LU, or dBTP, as appropriate.
+pub struct BextLoudness {
+    pub loudness_value: Option<f64>,
+    pub loudness_range: Option<f64>,
+    pub max_true_peak_level: Option<f64>,
+    pub max_momentary_loudness: Option<f64>,
+    pub max_short_term_loudness: Option<f64>,
+}
+
+/// The Broadcast Wave Format (BWF) extension chunk, as defined by EBU Tech 3285.
+pub struct BextChunk {
+    pub description: String,
+    pub originator: String,
+    pub originator_reference: String,
+    pub origination_date: String,
+    pub origination_time: String,
+    /// The timestamp, in samples since midnight, of the first sample.
+    pub time_reference: u64,
+    pub version: u16,
+    /// The SMPTE UMID. Only present in version 1 and later.
+    pub umid: Option<[u8; 64]>,
+    /// The loudness measurements. Only present in version 2 and later.
+    pub loudness: Option<BextLoudness>,
+    pub coding_history: String,
+}
+
+impl BextChunk {
+    /// The length of the fixed portion of the chunk preceeding the coding history.
+    const FIXED_LEN: u32 = 602;
+
+    /// The maximum length of the coding history that is read. Each line of the coding history
+    /// describes one step in the production of the audio, so the length of any real coding
+    /// history is well below this limit.
+    const MAX_CODING_HISTORY_LEN: u32 = 64 * 1024;
+
+    fn read_string<B: ReadBytes>(reader: &mut B, len: usize) -> Result<String> {
+        let mut buf = vec![0u8; len];
+        reader.read_buf_exact(&mut buf)?;
+
+        // Strings are null-padded, or may fill the entire field.
+        let end = buf.iter().position(|&b| b == 0).unwrap_or(len);
+
+        Ok(String::from_utf8_lossy(&buf[..end]).trim_end().to_string())
+    }
+
+    fn read_loudness<B: ReadBytes>(reader: &mut B) -> Result<Option<f64>> {
+        // Loudness values are stored as 100x the actual value. A value of 0x7fff indicates the
+        // value was not measured.
+        match reader.read_u16()? as i16 {
+            0x7fff => Ok(None),
+            value => Ok(Some(f64::from(value) / 100.0)),
+        }
+    }
+
+    /// Appends the fields of the chunk to the metadata as tags. Empty text fields are omitted.
+    pub fn append_tags(&self, builder: &mut MetadataBuilder) {
+        let fields = [
+            (Some(StandardTagKey::Description), "Description", &self.description),
+            (None, "Originator", &self.originator),
+            (None, "OriginatorReference", &self.originator_reference),
+            (None, "OriginationDate", &self.origination_date),
+            (None, "OriginationTime", &self.origination_time),
+        ];
+
+        for (std_key, key, value) in fields.iter() {
+            if !value.is_empty() {
+                builder.add_tag(Tag::new(*std_key, key, Value::from(value.as_str())));
+            }
+        }
+
+        builder.add_tag(Tag::new(None, "TimeReference", Value::from(self.time_reference)));
+
+        if let Some(loudness) = &self.loudness {
+            let values = [
+                ("LoudnessValue", loudness.loudness_value),
+                ("LoudnessRange", loudness.loudness_range),
+                ("MaxTruePeakLevel", loudness.max_true_peak_level),
+                ("MaxMomentaryLoudness", loudness.max_momentary_loudness),
+                ("MaxShortTermLoudness", loudness.max_short_term_loudness),
+            ];
+
+            for (key, value) in values.iter() {
+                if let Some(value) = value {
+                    builder.add_tag(Tag::new(None, key, Value::from(*value)));
+                }
+            }
+        }
+
+        if !self.coding_history.is_empty() {
+            builder.add_tag(Tag::new(
+                None,
+                "CodingHistory",
+                Value::from(self.coding_history.as_str()),
+            ));
+        }
+    }
+}
+
+impl ParseChunk for BextChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<BextChunk> {
+        if len < BextChunk::FIXED_LEN {
+            return decode_error("wav: malformed bext chunk");
+        }
+
+        let description = BextChunk::read_string(reader, 256)?;
+        let originator = BextChunk::read_string(reader, 32)?;
+        let originator_reference = BextChunk::read_string(reader, 32)?;
+        let origination_date = BextChunk::read_string(reader, 10)?;
+        let origination_time = BextChunk::read_string(reader, 8)?;
+
+        let time_reference_low = reader.read_u32()?;
+        let time_reference_high = reader.read_u32()?;
+        let time_reference = (u64::from(time_reference_high) << 32) | u64::from(time_reference_low);
+
+        let version = reader.read_u16()?;
+
+        // The UMID field was introduced in version 1, and is reserved in version 0.
+        let mut umid = [0u8; 64];
+        reader.read_buf_exact(&mut umid)?;
+
+        let umid = if version >= 1 { Some(umid) } else { None };
+
+        // The loudness fields were introduced in version 2, and are reserved in prior versions.
+        let loudness = if version >= 2 {
+            Some(BextLoudness {
+                loudness_value: BextChunk::read_loudness(reader)?,
+                loudness_range: BextChunk::read_loudness(reader)?,
+                max_true_peak_level: BextChunk::read_loudness(reader)?,
+                max_momentary_loudness: BextChunk::read_loudness(reader)?,
+                max_short_term_loudness: BextChunk::read_loudness(reader)?,
+            })
+        }
+        else {
+            reader.ignore_bytes(10)?;
+            None
+        };
+
+        // Reserved.
+        reader.ignore_bytes(180)?;
+
+        // The remainder of the chunk is the coding history. The length of the chunk is untrusted,
+        // therefore read at most the maximum length of the coding history, and ignore the
+        // remainder.
+        let coding_history_len = len - BextChunk::FIXED_LEN;
+        let read_len = coding_history_len.min(BextChunk::MAX_CODING_HISTORY_LEN);
+
+        let coding_history = BextChunk::read_string(reader, read_len as usize)?;
+        reader.ignore_bytes(u64::from(coding_history_len - read_len))?;
+
+        Ok(BextChunk {
+            description,
+            originator,
+            originator_reference,
+            origination_date,
+            origination_time,
+            time_reference,
+            version,
+            umid,
+            loudness,
+            coding_history,
+        })
+    }
+}
+
+impl fmt::Display for BextChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "BextChunk {{")?;
+        writeln!(f, "\tdescription: {},", self.description)?;
+        writeln!(f, "\toriginator: {},", self.originator)?;
+        writeln!(f, "\toriginator_reference: {},", self.originator_reference)?;
+        writeln!(f, "\torigination_date: {},", self.origination_date)?;
+        writeln!(f, "\torigination_time: {},", self.origination_time)?;
+        writeln!(f, "\ttime_reference: {},", self.time_reference)?;
+        writeln!(f, "\tversion: {},", self.version)?;
+
+        if let Some(umid) = &self.umid {
+            writeln!(f, "\tumid: {:x?},", &umid[..])?;
+        }
+
+        if let Some(loudness) = &self.loudness {
+            writeln!(f, "\tloudness_value: {:?},", loudness.loudness_value)?;
+            writeln!(f, "\tloudness_range: {:?},", loudness.loudness_range)?;
+            writeln!(f, "\tmax_true_peak_level: {:?},", loudness.max_true_peak_level)?;
+            writeln!(f, "\tmax_momentary_loudness: {:?},", loudness.max_momentary_loudness)?;
+            writeln!(f, "\tmax_short_term_loudness: {:?},", loudness.max_short_term_loudness)?;
+        }
+
+        writeln!(f, "\tcoding_history: {},", self.coding_history)?;
+        writeln!(f, "}}")
+    }
+}
+
+/// The ACID loop information chunk.
+pub struct AcidChunk {
+    pub flags: u32,
+    /// The MIDI note number of the root note. Only valid if the root note flag is set.
+    pub root_note: u16,
+    pub num_beats: u32,
+    pub meter_denominator: u16,
+    pub meter_numerator: u16,
+    /// The tempo in beats per minute.
+    pub tempo: f32,
+}
+
+impl AcidChunk {
+    /// The file is a one-shot, and not a loop.
+    const FLAG_ONE_SHOT: u32 = 0x1;
+    /// The root note is set.
+    const FLAG_ROOT_NOTE: u32 = 0x2;
+
+    /// Returns true if the file is a loop, and not a one-shot.
+    pub fn is_loop(&self) -> bool {
+        self.flags & AcidChunk::FLAG_ONE_SHOT == 0
+    }
+
+    /// Gets the name of the key (e.g., "C#") of the root note, if set.
+    pub fn key(&self) -> Option<&'static str> {
+        const NOTES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+        if self.flags & AcidChunk::FLAG_ROOT_NOTE != 0 {
+            Some(NOTES[usize::from(self.root_note) % 12])
+        }
+        else {
+            None
+        }
+    }
+
+    /// Appends the tempo, key, and loop information to the metadata as tags.
+    pub fn append_tags(&self, builder: &mut MetadataBuilder) {
+        if self.tempo.is_finite() && self.tempo > 0.0 {
+            builder.add_tag(Tag::new(Some(StandardTagKey::Bpm), "BPM", Value::from(self.tempo)));
+        }
+
+        if let Some(key) = self.key() {
+            builder.add_tag(Tag::new(Some(StandardTagKey::InitialKey), "InitialKey", key.into()));
+        }
+
+        builder.add_tag(Tag::new(None, "Loop", Value::from(self.is_loop())));
+
+        if self.num_beats > 0 {
+            builder.add_tag(Tag::new(None, "Beats", Value::from(self.num_beats)));
+        }
+
+        if self.meter_numerator > 0 && self.meter_denominator > 0 {
+            let meter = format!("{}/{}", self.meter_numerator, self.meter_denominator);
+            builder.add_tag(Tag::new(None, "TimeSignature", Value::from(meter)));
+        }
+    }
+}
+
+impl ParseChunk for AcidChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<AcidChunk> {
+        // The acid chunk has a fixed length of 24 bytes.
+        if len < 24 {
+            return decode_error("wav: malformed acid chunk");
+        }
+
+        let flags = reader.read_u32()?;
+        let root_note = reader.read_u16()?;
+        // Unknown.
+        reader.ignore_bytes(6)?;
+        let num_beats = reader.read_u32()?;
+        let meter_denominator = reader.read_u16()?;
+        let meter_numerator = reader.read_u16()?;
+        let tempo = reader.read_f32()?;
+
+        // Ignore any trailing bytes.
+        reader.ignore_bytes(u64::from(len - 24))?;
+
+        Ok(AcidChunk { flags, root_note, num_beats, meter_denominator, meter_numerator, tempo })
+    }
+}
+
+impl fmt::Display for AcidChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "AcidChunk {{")?;
+        writeln!(f, "\tflags: {:#x},", self.flags)?;
+        writeln!(f, "\troot_note: {},", self.root_note)?;
+        writeln!(f, "\tnum_beats: {},", self.num_beats)?;
+        writeln!(f, "\tmeter_denominator: {},", self.meter_denominator)?;
+        writeln!(f, "\tmeter_numerator: {},", self.meter_numerator)?;
+        writeln!(f, "\ttempo: {},", self.tempo)?;
+        writeln!(f, "}}")
+    }
+}
+
 pub struct DataChunk {
     pub len: u32,
 }
@@ -552,10 +956,207 @@ impl ParseChunk for DataChunk {
     }
 }
 
+/// A cue point in a cue chunk.
+pub struct WaveCuePoint {
+    /// The unique identifier of the cue point.
+    pub id: u32,
+    /// The position of the cue point, in samples, in the play order of a playlist.
+    pub position: u32,
+    /// The identifier of the chunk containing the cue point.
+    pub chunk_id: [u8; 4],
+    /// The position of the start of the chunk containing the cue point.
+    pub chunk_start: u32,
+    /// The position of the start of the block containing the cue point.
+    pub block_start: u32,
+    /// The offset, in samples, of the cue point from the start of the block.
+    pub sample_offset: u32,
+}
+
+/// The cue chunk (`cue `).
+pub struct CueChunk {
+    pub points: Vec<WaveCuePoint>,
+}
+
+impl ParseChunk for CueChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<CueChunk> {
+        if len < 4 {
+            return decode_error("wav: malformed cue chunk");
+        }
+
+        let n_points = reader.read_u32()?;
+
+        // Each cue point is 24 bytes long.
+        if n_points > (len - 4) / 24 {
+            return decode_error("wav: malformed cue chunk");
+        }
+
+        let mut points = Vec::with_capacity(n_points as usize);
+
+        for _ in 0..n_points {
+            points.push(WaveCuePoint {
+                id: reader.read_u32()?,
+                position: reader.read_u32()?,
+                chunk_id: reader.read_quad_bytes()?,
+                chunk_start: reader.read_u32()?,
+                block_start: reader.read_u32()?,
+                sample_offset: reader.read_u32()?,
+            });
+        }
+
+        // Ignore any trailing bytes.
+        reader.ignore_bytes(u64::from(len - 4 - 24 * n_points))?;
+
+        Ok(CueChunk { points })
+    }
+}
+
+impl fmt::Display for CueChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "CueChunk {{")?;
+        writeln!(f, "\tpoints: [")?;
+        for point in &self.points {
+            writeln!(f, "\t\t{{")?;
+            writeln!(f, "\t\t\tid: {},", point.id)?;
+            writeln!(f, "\t\t\tposition: {},", point.position)?;
+            writeln!(f, "\t\t\tchunk_id: {},", String::from_utf8_lossy(&point.chunk_id))?;
+            writeln!(f, "\t\t\tchunk_start: {},", point.chunk_start)?;
+            writeln!(f, "\t\t\tblock_start: {},", point.block_start)?;
+            writeln!(f, "\t\t\tsample_offset: {},", point.sample_offset)?;
+            writeln!(f, "\t\t}},")?;
+        }
+        writeln!(f, "\t],")?;
+        writeln!(f, "}}")
+    }
+}
+
+/// A segment of a playlist chunk.
+pub struct PlaylistSegment {
+    /// The identifier of the cue point the segment starts at.
+    pub cue_id: u32,
+    /// The length of the segment in samples.
+    pub len: u32,
+    /// The number of times the segment is played.
+    pub n_repeats: u32,
+}
+
+/// The playlist chunk (`plst`).
+pub struct PlstChunk {
+    pub segments: Vec<PlaylistSegment>,
+}
+
+impl ParseChunk for PlstChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<PlstChunk> {
+        if len < 4 {
+            return decode_error("wav: malformed plst chunk");
+        }
+
+        let n_segments = reader.read_u32()?;
+
+        // Each segment is 12 bytes long.
+        if n_segments > (len - 4) / 12 {
+            return decode_error("wav: malformed plst chunk");
+        }
+
+        let mut segments = Vec::with_capacity(n_segments as usize);
+
+        for _ in 0..n_segments {
+            segments.push(PlaylistSegment {
+                cue_id: reader.read_u32()?,
+                len: reader.read_u32()?,
+                n_repeats: reader.read_u32()?,
+            });
+        }
+
+        // Ignore any trailing bytes.
+        reader.ignore_bytes(u64::from(len - 4 - 12 * n_segments))?;
+
+        Ok(PlstChunk { segments })
+    }
+}
+
+impl fmt::Display for PlstChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "PlstChunk {{")?;
+        writeln!(f, "\tsegments: [")?;
+        for segment in &self.segments {
+            writeln!(f, "\t\t{{")?;
+            writeln!(f, "\t\t\tcue_id: {},", segment.cue_id)?;
+            writeln!(f, "\t\t\tlen: {},", segment.len)?;
+            writeln!(f, "\t\t\tn_repeats: {},", segment.n_repeats)?;
+            writeln!(f, "\t\t}},")?;
+        }
+        writeln!(f, "\t],")?;
+        writeln!(f, "}}")
+    }
+}
+
+/// A label (`labl`) or note (`note`) chunk of an associated data list. Both attach a text to a
+/// cue point.
+///
+/// Texts longer than `LabelChunk::MAX_TEXT_LEN` bytes are truncated.
+pub struct LabelChunk {
+    /// The identifier of the cue point the text is attached to.
+    pub cue_id: u32,
+    /// The text as a tag.
+    pub tag: Tag,
+}
+
+impl LabelChunk {
+    /// The maximum length of the text that is read. A label or note is a short description of a
+    /// cue point, so the length of any text is well below this limit.
+    const MAX_TEXT_LEN: u32 = 64 * 1024;
+}
+
+impl ParseChunk for LabelChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u32) -> Result<LabelChunk> {
+        if len < 4 {
+            return decode_error("wav: malformed label chunk");
+        }
+
+        let cue_id = reader.read_u32()?;
+
+        // The length of the chunk is untrusted, therefore read at most the maximum length of the
+        // text, and ignore the remainder.
+        let text_len = len - 4;
+        let read_len = text_len.min(LabelChunk::MAX_TEXT_LEN);
+
+        let mut text_buf = vec![0u8; read_len as usize];
+        reader.read_buf_exact(&mut text_buf)?;
+        reader.ignore_bytes(u64::from(text_len - read_len))?;
+
+        // The text is null-terminated.
+        let text_len = text_buf.iter().position(|&b| b == 0).unwrap_or(text_buf.len());
+        let text = String::from_utf8_lossy(&text_buf[..text_len]).into_owned();
+
+        let std_key = match &tag {
+            b"note" => Some(StandardTagKey::Comment),
+            _ => None,
+        };
+
+        let tag = Tag::new(std_key, &String::from_utf8_lossy(&tag), Value::from(text));
+
+        Ok(LabelChunk { cue_id, tag })
+    }
+}
+
+impl fmt::Display for LabelChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "LabelChunk {{")?;
+        writeln!(f, "\tcue_id: {},", self.cue_id)?;
+        writeln!(f, "\ttag: {},", self.tag)?;
+        writeln!(f, "}}")
+    }
+}
+
 pub enum RiffWaveChunks {
     Format(ChunkParser<WaveFormatChunk>),
     List(ChunkParser<ListChunk>),
     Fact(ChunkParser<FactChunk>),
+    Bext(ChunkParser<BextChunk>),
+    Acid(ChunkParser<AcidChunk>),
+    Id3(ChunkParser<Id3Chunk>),
+    Cue(ChunkParser<CueChunk>),
+    Plst(ChunkParser<PlstChunk>),
     Data(ChunkParser<DataChunk>),
 }
 
@@ -571,10 +1172,39 @@ impl ParseChunkTag for RiffWaveChunks {
             b"fmt " => parser!(RiffWaveChunks::Format, WaveFormatChunk, tag, len),
             b"LIST" => parser!(RiffWaveChunks::List, ListChunk, tag, len),
             b"fact" => parser!(RiffWaveChunks::Fact, FactChunk, tag, len),
+            b"bext" => parser!(RiffWaveChunks::Bext, BextChunk, tag, len),
+            b"acid" => parser!(RiffWaveChunks::Acid, AcidChunk, tag, len),
+            b"id3 " | b"ID3 " => parser!(RiffWaveChunks::Id3, Id3Chunk, tag, len),
+            b"cue " => parser!(RiffWaveChunks::Cue, CueChunk, tag, len),
+            b"plst" => parser!(RiffWaveChunks::Plst, PlstChunk, tag, len),
             b"data" => parser!(RiffWaveChunks::Data, DataChunk, tag, len),
             _ => None,
         }
     }
+
+    fn is_audio_data(tag: [u8; 4]) -> bool {
+        &tag == b"data"
+    }
+}
+
+/// The chunks that are read after the data chunk.
+pub enum RiffWaveTrailingChunks {
+    List(ChunkParser<ListChunk>),
+    Id3(ChunkParser<Id3Chunk>),
+    Cue(ChunkParser<CueChunk>),
+    Plst(ChunkParser<PlstChunk>),
+}
+
+impl ParseChunkTag for RiffWaveTrailingChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        match &tag {
+            b"LIST" => parser!(RiffWaveTrailingChunks::List, ListChunk, tag, len),
+            b"id3 " | b"ID3 " => parser!(RiffWaveTrailingChunks::Id3, Id3Chunk, tag, len),
+            b"cue " => parser!(RiffWaveTrailingChunks::Cue, CueChunk, tag, len),
+            b"plst" => parser!(RiffWaveTrailingChunks::Plst, PlstChunk, tag, len),
+            _ => None,
+        }
+    }
 }
 
 pub enum RiffInfoListChunks {
@@ -591,6 +1221,20 @@ impl ParseChunkTag for RiffInfoListChunks {
     }
 }
 
+pub enum RiffAdtlListChunks {
+    Label(ChunkParser<LabelChunk>),
+}
+
+impl ParseChunkTag for RiffAdtlListChunks {
+    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+        // Labelled text (`ltxt`) chunks are not supported.
+        match &tag {
+            b"labl" | b"note" => parser!(RiffAdtlListChunks::Label, LabelChunk, tag, len),
+            _ => None,
+        }
+    }
+}
+
 pub fn append_fact_params(codec_params: &mut CodecParameters, fact: &FactChunk) {
     codec_params.with_n_frames(u64::from(fact.n_frames));
 }
@@ -616,3 +1260,173 @@ pub fn read_info_chunk(source: &mut MediaSourceStream, len: u32) -> Result<Metad
 
     Ok(metadata_builder.metadata())
 }
+
+/// Reads the labels and notes of an associated data list (`LIST` of form `adtl`).
+pub fn read_adtl_chunk(source: &mut MediaSourceStream, len: u32) -> Result<Vec<LabelChunk>> {
+    let mut adtl_list = ChunksReader::<RiffAdtlListChunks>::new(len, ByteOrder::LittleEndian);
+
+    let mut labels = Vec::new();
+
+    while let Some(RiffAdtlListChunks::Label(label)) = adtl_list.next(source)? {
+        labels.push(label.parse(source)?);
+    }
+
+    adtl_list.finish(source)?;
+
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::{BufReader, ReadBytes};
+
+    use symphonia_core::meta::{MetadataBuilder, StandardTagKey, Value};
+
+    use super::{AcidChunk, BextChunk, LabelChunk, ParseChunk};
+
+    #[test]
+    fn verify_bext_v2_loudness() {
+        let coding_history = b"A=PCM,F=48000,W=24,M=stereo,T=original\r\n";
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&[0u8; 256]);
+        buf[..11].copy_from_slice(b"Description");
+        buf.extend_from_slice(&[0u8; 32 + 32]);
+        buf.extend_from_slice(b"2022-01-0112:34:56");
+        buf.extend_from_slice(&48_000u32.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&2u16.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 64]);
+        buf.extend_from_slice(&(-2300i16).to_le_bytes());
+        buf.extend_from_slice(&(750i16).to_le_bytes());
+        buf.extend_from_slice(&(-100i16).to_le_bytes());
+        buf.extend_from_slice(&(0x7fffi16).to_le_bytes());
+        buf.extend_from_slice(&(-1800i16).to_le_bytes());
+        buf.extend_from_slice(&[0u8; 180]);
+        buf.extend_from_slice(coding_history);
+
+        let len = buf.len() as u32;
+        let mut reader = BufReader::new(&buf);
+
+        let bext = BextChunk::parse(&mut reader, *b"bext", len).unwrap();
+
+        assert_eq!(bext.version, 2);
+        assert_eq!(bext.description, "Description");
+        assert_eq!(bext.origination_date, "2022-01-01");
+        assert_eq!(bext.origination_time, "12:34:56");
+        assert_eq!(bext.time_reference, (1 << 32) | 48_000);
+        assert_eq!(bext.coding_history, "A=PCM,F=48000,W=24,M=stereo,T=original");
+
+        let loudness = bext.loudness.as_ref().unwrap();
+        assert_eq!(loudness.loudness_value, Some(-23.0));
+        assert_eq!(loudness.loudness_range, Some(7.5));
+        assert_eq!(loudness.max_true_peak_level, Some(-1.0));
+        assert_eq!(loudness.max_momentary_loudness, None);
+        assert_eq!(loudness.max_short_term_loudness, Some(-18.0));
+
+        let mut builder = MetadataBuilder::new();
+        bext.append_tags(&mut builder);
+
+        let metadata = builder.metadata();
+
+        let tags: Vec<_> = metadata.tags().iter().map(|tag| tag.key.as_str()).collect();
+
+        // Empty text fields are not tags.
+        assert_eq!(
+            tags,
+            &[
+                "Description",
+                "OriginationDate",
+                "OriginationTime",
+                "TimeReference",
+                "LoudnessValue",
+                "LoudnessRange",
+                "MaxTruePeakLevel",
+                "MaxShortTermLoudness",
+                "CodingHistory",
+            ]
+        );
+
+        assert_eq!(metadata.tags()[0].std_key, Some(StandardTagKey::Description));
+        let time_reference = &metadata.tags()[3].value;
+        assert!(matches!(time_reference, Value::UnsignedInt(ts) if *ts == (1 << 32) | 48_000));
+    }
+
+    #[test]
+    fn verify_bext_coding_history_limit() {
+        // A version 0 bext chunk with a coding history exceeding the maximum length, followed by
+        // another byte.
+        let coding_history_len = BextChunk::MAX_CODING_HISTORY_LEN as usize + 16;
+
+        let mut buf = vec![0u8; BextChunk::FIXED_LEN as usize];
+        buf.extend(std::iter::repeat(b'A').take(coding_history_len));
+        buf.push(0xff);
+
+        let len = BextChunk::FIXED_LEN + coding_history_len as u32;
+        let mut reader = BufReader::new(&buf);
+
+        let bext = BextChunk::parse(&mut reader, *b"bext", len).unwrap();
+
+        // The coding history is truncated, but the whole chunk is consumed.
+        assert_eq!(bext.coding_history.len(), BextChunk::MAX_CODING_HISTORY_LEN as usize);
+        assert_eq!(reader.read_u8().unwrap(), 0xff);
+    }
+
+    #[test]
+    fn verify_acid_loop() {
+        let mut buf = Vec::new();
+        // Flags: root note set, stretch.
+        buf.extend_from_slice(&0x6u32.to_le_bytes());
+        // Root note: A4.
+        buf.extend_from_slice(&69u16.to_le_bytes());
+        buf.extend_from_slice(&0x8000u16.to_le_bytes());
+        buf.extend_from_slice(&0f32.to_le_bytes());
+        // 8 beats in 4/4 at 128 BPM.
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        buf.extend_from_slice(&4u16.to_le_bytes());
+        buf.extend_from_slice(&4u16.to_le_bytes());
+        buf.extend_from_slice(&128f32.to_le_bytes());
+
+        let len = buf.len() as u32;
+        let mut reader = BufReader::new(&buf);
+
+        let acid = AcidChunk::parse(&mut reader, *b"acid", len).unwrap();
+
+        assert!(acid.is_loop());
+        assert_eq!(acid.key(), Some("A"));
+        assert_eq!(acid.num_beats, 8);
+
+        let mut builder = MetadataBuilder::new();
+        acid.append_tags(&mut builder);
+        let rev = builder.metadata();
+
+        let find = |key| rev.tags().iter().find(|tag| tag.std_key == Some(key)).unwrap();
+
+        assert!(matches!(find(StandardTagKey::Bpm).value, Value::Float(bpm) if bpm == 128.0));
+        assert!(matches!(&find(StandardTagKey::InitialKey).value, Value::String(key) if key == "A"));
+    }
+
+    #[test]
+    fn verify_label_limit() {
+        // A malformed label chunk that is too short for the cue point identifier.
+        let mut reader = BufReader::new(&[0u8; 3]);
+        assert!(LabelChunk::parse(&mut reader, *b"labl", 3).is_err());
+
+        // A label chunk with a text exceeding the maximum text length, followed by another byte.
+        let text_len = LabelChunk::MAX_TEXT_LEN as usize + 16;
+
+        let mut buf = 7u32.to_le_bytes().to_vec();
+        buf.extend(std::iter::repeat(b'a').take(text_len));
+        buf.push(0xff);
+
+        let len = 4 + text_len as u32;
+        let mut reader = BufReader::new(&buf);
+
+        let label = LabelChunk::parse(&mut reader, *b"labl", len).unwrap();
+
+        // The text is truncated, but the whole chunk is consumed.
+        assert_eq!(label.cue_id, 7);
+        assert_eq!(label.tag.value.to_string().len(), LabelChunk::MAX_TEXT_LEN as usize);
+        assert_eq!(reader.read_u8().unwrap(), 0xff);
+    }
+}
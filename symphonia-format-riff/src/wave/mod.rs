@@ -12,18 +12,24 @@ use symphonia_core::errors::{seek_error, unsupported_error};
 use symphonia_core::errors::{Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::io::*;
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::support_format;
 
 use log::{debug, error};
 
 use crate::common::{
-    append_data_params, append_format_params, next_packet, ByteOrder, ChunksReader, PacketInfo,
+    append_data_params, append_format_params, next_packet, read_trailing_chunks, ByteOrder,
+    ChunksReader, FormatData, PacketInfo,
 };
-mod chunks;
+pub(crate) mod chunks;
 use chunks::*;
 
+#[cfg(feature = "wav-write")]
+mod writer;
+#[cfg(feature = "wav-write")]
+pub use writer::{WavSampleFormat, WavWriter};
+
 /// WAVE is actually a RIFF stream, with a "RIFF" ASCII stream marker.
 const WAVE_STREAM_MARKER: [u8; 4] = *b"RIFF";
 /// A possible RIFF form is "wave".
@@ -32,6 +38,16 @@ const WAVE_RIFF_FORM: [u8; 4] = *b"WAVE";
 /// Waveform Audio File Format (WAV) format reader.
 ///
 /// `WavReader` implements a demuxer for the WAVE container format.
+///
+/// If lenient parsing is enabled, a chunk with a length exceeding the length of the RIFF chunk is
+/// tolerated. The length of the `data` chunk is trusted, but a chunk other than the `data` chunk is
+/// truncated to the remaining length of the RIFF chunk.
+///
+/// The fields of a Broadcast Wave Format (BWF) `bext` chunk are exposed as tags, and the time
+/// reference is also available from [`WavReader::time_reference`].
+///
+/// The cue points of a `cue ` chunk are exposed as cues, tagged with the labels and notes of the
+/// associated data list that refer to them.
 pub struct WavReader {
     reader: MediaSourceStream,
     tracks: Vec<Track>,
@@ -40,6 +56,62 @@ pub struct WavReader {
     packet_info: PacketInfo,
     data_start_pos: u64,
     data_end_pos: u64,
+    time_reference: Option<u64>,
+}
+
+impl WavReader {
+    /// Gets the time reference of a Broadcast Wave Format file. This is the timestamp, in samples
+    /// since midnight, of the first sample of the track on the broadcast timeline.
+    pub fn time_reference(&self) -> Option<u64> {
+        self.time_reference
+    }
+}
+
+/// The cue points, and the labels and notes attached to them, collected while reading the chunks.
+#[derive(Default)]
+struct CueData {
+    points: Vec<WaveCuePoint>,
+    labels: Vec<LabelChunk>,
+    has_playlist: bool,
+}
+
+impl CueData {
+    /// Reads a list chunk. Riff lists can have many different forms, but only info and associated
+    /// data lists are supported.
+    fn read_list(
+        &mut self,
+        source: &mut MediaSourceStream,
+        list: ListChunk,
+        metadata: &mut MetadataLog,
+    ) -> Result<()> {
+        match &list.form {
+            b"INFO" => metadata.push(read_info_chunk(source, list.len)?),
+            b"adtl" => self.labels.extend(read_adtl_chunk(source, list.len)?),
+            _ => list.skip(source)?,
+        }
+        Ok(())
+    }
+
+    fn into_cues(self) -> Vec<Cue> {
+        let CueData { points, labels, has_playlist } = self;
+
+        points
+            .into_iter()
+            .map(|point| {
+                // The position is only meaningful if there is a playlist. Otherwise, the sample
+                // offset is used.
+                let start_ts = if has_playlist { point.position } else { point.sample_offset };
+
+                let tags = labels
+                    .iter()
+                    .filter(|label| label.cue_id == point.id)
+                    .map(|label| label.tag.clone())
+                    .collect();
+
+                Cue { index: point.id, start_ts: u64::from(start_ts), tags, points: Vec::new() }
+            })
+            .collect()
+    }
 }
 
 impl QueryDescriptor for WavReader {
@@ -56,13 +128,17 @@ impl QueryDescriptor for WavReader {
         ]
     }
 
-    fn score(_context: &[u8]) -> u8 {
-        255
+    fn score(context: &[u8]) -> u8 {
+        // Other RIFF forms, such as AVI, share the RIFF marker.
+        match context.get(8..12) {
+            Some(form) if form == WAVE_RIFF_FORM => 255,
+            _ => 0,
+        }
     }
 }
 
 impl FormatReader for WavReader {
-    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
         // The RIFF marker should be present.
         let marker = source.read_quad_bytes()?;
 
@@ -74,6 +150,7 @@ impl FormatReader for WavReader {
         // Therefore, the header was the chunk ID, and the next 4 bytes is the length of the RIFF
         // chunk.
         let riff_len = source.read_u32()?;
+        let riff_end_pos = source.pos() + u64::from(riff_len);
         let riff_form = source.read_quad_bytes()?;
 
         // The RIFF chunk contains WAVE data.
@@ -85,10 +162,13 @@ impl FormatReader for WavReader {
 
         let mut riff_chunks =
             ChunksReader::<RiffWaveChunks>::new(riff_len, ByteOrder::LittleEndian);
+        riff_chunks.set_lenient(options.enable_lenient);
 
         let mut codec_params = CodecParameters::new();
         let mut metadata: MetadataLog = Default::default();
         let mut packet_info = PacketInfo::without_blocks(0);
+        let mut time_reference = None;
+        let mut cue_data = CueData::default();
 
         loop {
             let chunk = riff_chunks.next(&mut source)?;
@@ -103,6 +183,12 @@ impl FormatReader for WavReader {
                 RiffWaveChunks::Format(fmt) => {
                     let format = fmt.parse(&mut source)?;
 
+                    // Compressed formats are not packetized by block, and therefore cannot be read
+                    // from a WAVE file.
+                    if let FormatData::Mpeg(_) | FormatData::Ac3(_) = format.format_data {
+                        return unsupported_error("wav: unsupported wave format");
+                    }
+
                     // The Format chunk contains the block_align field and possible additional information
                     // to handle packetization and seeking.
                     packet_info = format.packet_info()?;
@@ -125,14 +211,36 @@ impl FormatReader for WavReader {
                 }
                 RiffWaveChunks::List(lst) => {
                     let list = lst.parse(&mut source)?;
+                    cue_data.read_list(&mut source, list, &mut metadata)?;
+                }
+                RiffWaveChunks::Bext(bxt) => {
+                    let bext = bxt.parse(&mut source)?;
+
+                    time_reference = Some(bext.time_reference);
+
+                    let mut builder = MetadataBuilder::new();
+                    bext.append_tags(&mut builder);
+                    metadata.push(builder.metadata());
+                }
+                RiffWaveChunks::Acid(acd) => {
+                    let acid = acd.parse(&mut source)?;
 
-                    // Riff Lists can have many different forms, but WavReader only supports Info
-                    // lists.
-                    match &list.form {
-                        b"INFO" => metadata.push(read_info_chunk(&mut source, list.len)?),
-                        _ => list.skip(&mut source)?,
+                    let mut builder = MetadataBuilder::new();
+                    acid.append_tags(&mut builder);
+                    metadata.push(builder.metadata());
+                }
+                RiffWaveChunks::Id3(id3) => {
+                    if let Some(revision) = id3.parse(&mut source)?.metadata {
+                        metadata.push(revision);
                     }
                 }
+                RiffWaveChunks::Cue(cue) => {
+                    cue_data.points = cue.parse(&mut source)?.points;
+                }
+                RiffWaveChunks::Plst(plst) => {
+                    plst.parse(&mut source)?;
+                    cue_data.has_playlist = true;
+                }
                 RiffWaveChunks::Data(dat) => {
                     let data = dat.parse(&mut source)?;
 
@@ -143,15 +251,46 @@ impl FormatReader for WavReader {
                     // Append Data chunk fields to codec parameters.
                     append_data_params(&mut codec_params, data.len as u64, &packet_info);
 
+                    // Read the metadata and cue chunks following the data chunk, accounting for
+                    // padding.
+                    read_trailing_chunks(
+                        &mut source,
+                        data_end_pos + u64::from(data.len & 1),
+                        riff_end_pos,
+                        ByteOrder::LittleEndian,
+                        |source, chunk| {
+                            match chunk {
+                                RiffWaveTrailingChunks::List(lst) => {
+                                    let list = lst.parse(source)?;
+                                    cue_data.read_list(source, list, &mut metadata)?;
+                                }
+                                RiffWaveTrailingChunks::Id3(id3) => {
+                                    if let Some(revision) = id3.parse(source)?.metadata {
+                                        metadata.push(revision);
+                                    }
+                                }
+                                RiffWaveTrailingChunks::Cue(cue) => {
+                                    cue_data.points = cue.parse(source)?.points;
+                                }
+                                RiffWaveTrailingChunks::Plst(plst) => {
+                                    plst.parse(source)?;
+                                    cue_data.has_playlist = true;
+                                }
+                            }
+                            Ok(())
+                        },
+                    )?;
+
                     // Add a new track using the collected codec parameters.
                     return Ok(WavReader {
                         reader: source,
                         tracks: vec![Track::new(0, codec_params)],
-                        cues: Vec::new(),
+                        cues: cue_data.into_cues(),
                         metadata,
                         packet_info,
                         data_start_pos,
                         data_end_pos,
+                        time_reference,
                     });
                 }
             }
@@ -250,3 +389,421 @@ impl FormatReader for WavReader {
         self.reader
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::Channels;
+    use symphonia_core::codecs::{
+        CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_S16LE,
+    };
+    use symphonia_core::errors::Error;
+    use symphonia_core::formats::{FormatOptions, FormatReader, Packets};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::{MetadataOptions, StandardTagKey, StandardVisualKey};
+    use symphonia_core::probe::{Hint, Probe};
+
+    use super::WavReader;
+    use crate::test_util::{chunk, list, pcm_fmt, wav};
+    use crate::AviReader;
+
+    /// Builds a 16-bit mono PCM WAVE file with 4 frames, where the RIFF chunk length does not
+    /// account for the data chunk payload.
+    fn build_wav_with_short_riff_len() -> Vec<u8> {
+        let mut buf = wav(&[pcm_fmt(1, 8000, 16), chunk(b"data", &[1, 0, 2, 0, 3, 0, 4, 0])]);
+        buf[4..8].copy_from_slice(&36u32.to_le_bytes());
+        buf
+    }
+
+    fn open_wav(buf: Vec<u8>, enable_lenient: bool) -> symphonia_core::errors::Result<WavReader> {
+        let mss = MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+        WavReader::try_new(mss, FormatOptions::default().with_lenient(enable_lenient))
+    }
+
+    /// Builds an ID3v2.4 tag with a title and a front cover.
+    fn build_id3v2_tag(title: &str, cover: &[u8]) -> Vec<u8> {
+        fn frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+            // Frame sizes are synchsafe integers in ID3v2.4, all sizes used here are < 128.
+            assert!(body.len() < 128);
+            let mut frame = id.to_vec();
+            frame.extend_from_slice(&[0, 0, 0, body.len() as u8, 0, 0]);
+            frame.extend_from_slice(body);
+            frame
+        }
+
+        // UTF-8 encoded title.
+        let mut tit2 = vec![3];
+        tit2.extend_from_slice(title.as_bytes());
+
+        // Latin-1 encoding, media type, front cover picture type, empty description, and data.
+        let mut apic = vec![0];
+        apic.extend_from_slice(b"image/png\0");
+        apic.extend_from_slice(&[3, 0]);
+        apic.extend_from_slice(cover);
+
+        let mut frames = frame(b"TIT2", &tit2);
+        frames.extend(frame(b"APIC", &apic));
+
+        let mut tag = b"ID3".to_vec();
+        tag.extend_from_slice(&[4, 0, 0, 0, 0, 0, frames.len() as u8]);
+        tag.extend(frames);
+        tag
+    }
+
+    /// Builds a 16-bit mono PCM WAVE file with 4 frames and an ID3 chunk either before or after
+    /// the data chunk.
+    fn build_wav_with_id3_chunk(tag: &[u8], after_data: bool) -> Vec<u8> {
+        let data = chunk(b"data", &[1, 0, 2, 0, 3, 0, 4, 0]);
+        let id3 = chunk(b"id3 ", tag);
+
+        if after_data {
+            wav(&[pcm_fmt(1, 8000, 16), data, id3])
+        }
+        else {
+            wav(&[pcm_fmt(1, 8000, 16), id3, data])
+        }
+    }
+
+    /// Builds a 16-bit mono PCM WAVE file with 4 frames and a version 1 bext chunk.
+    fn build_wav_with_bext_chunk() -> Vec<u8> {
+        fn field(value: &str, len: usize) -> Vec<u8> {
+            let mut field = value.as_bytes().to_vec();
+            field.resize(len, 0);
+            field
+        }
+
+        let mut bext = field("Interview, take 2", 256);
+        bext.extend(field("Symphonia Recorder", 32));
+        bext.extend(field("", 32));
+        bext.extend_from_slice(b"2023-04-0509:30:00");
+        // 09:30:00 at 8kHz.
+        bext.extend_from_slice(&(8000u64 * 34200).to_le_bytes());
+        bext.extend_from_slice(&1u16.to_le_bytes());
+        bext.extend_from_slice(&[0; 64 + 10 + 180]);
+
+        let data = chunk(b"data", &[1, 0, 2, 0, 3, 0, 4, 0]);
+
+        wav(&[pcm_fmt(1, 8000, 16), chunk(b"bext", &bext), data])
+    }
+
+    #[test]
+    fn verify_bext_chunk() {
+        let mut reader = open_wav(build_wav_with_bext_chunk(), false).unwrap();
+
+        assert_eq!(reader.time_reference(), Some(8000 * 34200));
+
+        let metadata = reader.metadata();
+        let revision = metadata.current().unwrap();
+
+        let tags: Vec<_> = revision
+            .tags()
+            .iter()
+            .map(|tag| (tag.std_key, tag.key.as_str(), tag.value.to_string()))
+            .collect();
+
+        assert_eq!(
+            tags,
+            &[
+                (Some(StandardTagKey::Description), "Description", "Interview, take 2".into()),
+                (None, "Originator", "Symphonia Recorder".into()),
+                (None, "OriginationDate", "2023-04-05".into()),
+                (None, "OriginationTime", "09:30:00".into()),
+                (None, "TimeReference", "273600000".into()),
+            ]
+        );
+
+        // Without a bext chunk, there is no time reference.
+        let reader = open_wav(build_wav_with_short_riff_len(), true).unwrap();
+        assert_eq!(reader.time_reference(), None);
+    }
+
+    /// Builds a 16-bit mono PCM WAVE file with 4 frames, a cue chunk with two cue points before
+    /// the data chunk, and an associated data list after the data chunk. Optionally, a playlist
+    /// chunk is added.
+    fn build_wav_with_cue_chunk(with_playlist: bool) -> Vec<u8> {
+        // Cue points (id, position, sample offset) that are not in timestamp order.
+        let mut cue = 2u32.to_le_bytes().to_vec();
+        for &(id, position, sample_offset) in &[(2u32, 300u32, 3u32), (1, 100, 1)] {
+            cue.extend_from_slice(&id.to_le_bytes());
+            cue.extend_from_slice(&position.to_le_bytes());
+            cue.extend_from_slice(b"data");
+            cue.extend_from_slice(&[0; 8]);
+            cue.extend_from_slice(&sample_offset.to_le_bytes());
+        }
+
+        let mut plst = 1u32.to_le_bytes().to_vec();
+        plst.extend_from_slice(&1u32.to_le_bytes());
+        plst.extend_from_slice(&4u32.to_le_bytes());
+        plst.extend_from_slice(&1u32.to_le_bytes());
+
+        let adtl = list(
+            b"adtl",
+            &[
+                chunk(b"labl", b"\x01\0\0\0Verse\0"),
+                chunk(b"note", b"\x02\0\0\0Check levels\0"),
+                chunk(b"ltxt", &[2, 0, 0, 0, 1, 0, 0, 0]),
+                chunk(b"labl", b"\x02\0\0\0Chorus\0"),
+            ],
+        );
+
+        let mut chunks = vec![pcm_fmt(1, 8000, 16), chunk(b"cue ", &cue)];
+        if with_playlist {
+            chunks.push(chunk(b"plst", &plst));
+        }
+        chunks.push(chunk(b"data", &[1, 0, 2, 0, 3, 0, 4, 0]));
+        chunks.push(adtl);
+
+        wav(&chunks)
+    }
+
+    #[test]
+    fn verify_cue_chunk() {
+        for &with_playlist in &[false, true] {
+            let reader = open_wav(build_wav_with_cue_chunk(with_playlist), false).unwrap();
+
+            let cues: Vec<_> = reader
+                .cues()
+                .iter()
+                .map(|cue| {
+                    let tags: Vec<_> = cue
+                        .tags
+                        .iter()
+                        .map(|tag| (tag.std_key, tag.key.as_str(), tag.value.to_string()))
+                        .collect();
+                    (cue.index, cue.start_ts, tags)
+                })
+                .collect();
+
+            // Without a playlist, the start timestamp is the sample offset of the cue point.
+            let (start_ts_2, start_ts_1) = if with_playlist { (300, 100) } else { (3, 1) };
+
+            assert_eq!(
+                cues,
+                &[
+                    (
+                        2,
+                        start_ts_2,
+                        vec![
+                            (Some(StandardTagKey::Comment), "note", "Check levels".into()),
+                            (None, "labl", "Chorus".into()),
+                        ]
+                    ),
+                    (1, start_ts_1, vec![(None, "labl", "Verse".into())]),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn verify_id3_chunk() {
+        let cover = [0x89, b'P', b'N', b'G', 1, 2, 3];
+        let tag = build_id3v2_tag("Title", &cover);
+
+        for &after_data in &[false, true] {
+            let mut reader = open_wav(build_wav_with_id3_chunk(&tag, after_data), false).unwrap();
+
+            {
+                let metadata = reader.metadata();
+                let revision = metadata.current().unwrap();
+
+                let title = revision
+                    .tags()
+                    .iter()
+                    .find(|tag| tag.std_key == Some(StandardTagKey::TrackTitle))
+                    .unwrap();
+                assert_eq!(title.value.to_string(), "Title");
+
+                let visual = &revision.visuals()[0];
+                assert_eq!(visual.media_type, "image/png");
+                assert_eq!(visual.usage, Some(StandardVisualKey::FrontCover));
+                assert_eq!(&visual.data[..], &cover[..]);
+            }
+
+            // The audio data is still read from the start.
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.buf(), &[1, 0, 2, 0, 3, 0, 4, 0]);
+        }
+    }
+
+    #[test]
+    fn verify_lenient_chunk_exceeds_riff_len() {
+        // Strict mode rejects the data chunk exceeding the RIFF chunk.
+        assert!(open_wav(build_wav_with_short_riff_len(), false).is_err());
+
+        // Lenient mode trusts the data chunk length.
+        let mut reader = open_wav(build_wav_with_short_riff_len(), true).unwrap();
+
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(4));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.buf(), &[1, 0, 2, 0, 3, 0, 4, 0]);
+    }
+
+    #[test]
+    fn verify_lenient_chunk_len_is_limited() {
+        // A bext chunk claiming to be almost 4 GiB long instead of a data chunk.
+        let mut buf = wav(&[pcm_fmt(1, 8000, 16), chunk(b"bext", &[0; 8])]);
+        let len_pos = buf.len() - 12;
+        buf[len_pos..len_pos + 4].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+
+        // In lenient mode, only the length of the data chunk is trusted. The length of the bext
+        // chunk is limited to the remaining length of the RIFF chunk, which is too short for a
+        // bext chunk.
+        assert!(open_wav(buf, true).is_err());
+    }
+
+    /// The KSDATAFORMAT_SUBTYPE_IEEE_FLOAT sub-format GUID.
+    const IEEE_FLOAT_GUID: [u8; 16] =
+        [3, 0, 0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xaa, 0, 0x38, 0x9b, 0x71];
+
+    /// Builds a stereo WAVE_FORMAT_EXTENSIBLE file with the given bits per coded sample, bits per
+    /// sample, and sub-format GUID, and 2 frames of audio.
+    fn build_ext_wav(bits_per_coded_sample: u16, bits_per_sample: u16, guid: [u8; 16]) -> Vec<u8> {
+        let block_align = 2 * bits_per_coded_sample / 8;
+        let data = vec![0; 2 * usize::from(block_align)];
+
+        let mut fmt = 0xfffeu16.to_le_bytes().to_vec();
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&48000u32.to_le_bytes());
+        fmt.extend_from_slice(&(48000 * u32::from(block_align)).to_le_bytes());
+        fmt.extend_from_slice(&block_align.to_le_bytes());
+        fmt.extend_from_slice(&bits_per_coded_sample.to_le_bytes());
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&bits_per_sample.to_le_bytes());
+        fmt.extend_from_slice(&3u32.to_le_bytes());
+        fmt.extend_from_slice(&guid);
+
+        wav(&[chunk(b"fmt ", &fmt), chunk(b"data", &data)])
+    }
+
+    #[test]
+    fn verify_ext_ieee_float() {
+        for &(bits, codec) in &[(32, CODEC_TYPE_PCM_F32LE), (64, CODEC_TYPE_PCM_F64LE)] {
+            let reader = open_wav(build_ext_wav(bits, bits, IEEE_FLOAT_GUID), false).unwrap();
+
+            let params = &reader.tracks()[0].codec_params;
+            assert_eq!(params.codec, codec);
+            assert_eq!(params.n_frames, Some(2));
+        }
+
+        // Truncated sample widths are not supported for floating point samples.
+        assert!(open_wav(build_ext_wav(32, 24, IEEE_FLOAT_GUID), false).is_err());
+        assert!(open_wav(build_ext_wav(16, 16, IEEE_FLOAT_GUID), false).is_err());
+    }
+
+    #[test]
+    fn verify_ext_sub_format_guid() {
+        // An unknown sub-format is rejected as unsupported.
+        let mut guid = IEEE_FLOAT_GUID;
+        guid[0] = 0xff;
+
+        let result = open_wav(build_ext_wav(32, 32, guid), false);
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+
+        // A sub-format GUID with the first three fields in big-endian byte order is accepted.
+        let mut guid = IEEE_FLOAT_GUID;
+        guid[0..4].reverse();
+        guid[6..8].reverse();
+
+        let reader = open_wav(build_ext_wav(32, 32, guid), false).unwrap();
+        assert_eq!(reader.tracks()[0].codec_params.codec, CODEC_TYPE_PCM_F32LE);
+
+        // A PCM sub-format with 0 bits per coded sample is rejected.
+        let mut guid = IEEE_FLOAT_GUID;
+        guid[0] = 1;
+
+        assert!(matches!(open_wav(build_ext_wav(0, 0, guid), false), Err(Error::DecodeError(_))));
+    }
+
+    #[test]
+    fn verify_ext_channel_mask() {
+        let open_with_mask = |mask: u32| {
+            let mut buf = build_ext_wav(32, 32, IEEE_FLOAT_GUID);
+            buf[40..44].copy_from_slice(&mask.to_le_bytes());
+            open_wav(buf, false).unwrap().tracks()[0].codec_params.channels
+        };
+
+        let stereo = Some(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        assert_eq!(open_with_mask(0x0000_0003), stereo);
+        assert_eq!(open_with_mask(0x0000_0600), Some(Channels::SIDE_LEFT | Channels::SIDE_RIGHT));
+
+        // Bits without a speaker position are ignored, and channels without a position are
+        // assigned the next positions.
+        assert_eq!(open_with_mask(0x8000_0003), stereo);
+        assert_eq!(open_with_mask(0x0004_0001), stereo);
+        assert_eq!(open_with_mask(0), stereo);
+    }
+
+    #[test]
+    fn verify_discrete_channels() {
+        const N_CHANNELS: u16 = 10;
+
+        // A 16-bit PCM WAVE file with 10 channels and no channel mask.
+        let data: Vec<u8> = (0..N_CHANNELS).flat_map(|ch| ch.to_le_bytes()).collect();
+        let buf = wav(&[pcm_fmt(N_CHANNELS, 48000, 16), chunk(b"data", &data)]);
+
+        let mut reader = open_wav(buf, false).unwrap();
+
+        let channels = reader.tracks()[0].codec_params.channels;
+        assert_eq!(channels, Channels::discrete(usize::from(N_CHANNELS)));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.dur, 1);
+        assert_eq!(packet.buf().len(), 2 * usize::from(N_CHANNELS));
+    }
+
+    #[test]
+    fn verify_probe_mime_hint() {
+        let mut probe = Probe::default();
+        probe.register_all::<AviReader>();
+        probe.register_all::<WavReader>();
+
+        let probe_wav = |hint: &Hint| {
+            let buf = build_wav_with_id3_chunk(&[], true);
+            let mss =
+                MediaSourceStream::new(Box::new(std::io::Cursor::new(buf)), Default::default());
+
+            probe.format(hint, mss, &FormatOptions::default(), &MetadataOptions::default()).unwrap()
+        };
+
+        // The stream is identified only by its MIME type, there is no file extension.
+        let mut hint = Hint::new();
+        hint.with_mime("audio/wav");
+
+        let probed = probe_wav(&hint);
+        assert_eq!(probed.format.tracks()[0].codec_params.codec, CODEC_TYPE_PCM_S16LE);
+
+        // A wrong MIME type does not select a format that does not support the stream.
+        let mut hint = Hint::new();
+        hint.with_mime("video/avi");
+
+        let probed = probe_wav(&hint);
+        assert_eq!(probed.format.tracks()[0].codec_params.codec, CODEC_TYPE_PCM_S16LE);
+    }
+
+    #[test]
+    fn verify_packets_iterator() {
+        const N_FRAMES: u32 = 3000;
+
+        // A 16-bit mono PCM WAVE file.
+        let data = vec![0; 2 * N_FRAMES as usize];
+        let buf = wav(&[pcm_fmt(1, 8000, 16), chunk(b"data", &data)]);
+
+        // Packets contain at most 1152 frames.
+        let mut reader = open_wav(buf.clone(), false).unwrap();
+        let track_id = reader.tracks()[0].id;
+
+        let durations: Vec<u64> = Packets::new(&mut reader)
+            .filter(|packet| matches!(packet, Ok(packet) if packet.track_id() == track_id))
+            .map(|packet| packet.unwrap().dur())
+            .collect();
+
+        assert_eq!(durations, &[1152, 1152, 696]);
+
+        // The iterator ends at the end of the stream, and the packets of a boxed reader can also
+        // be iterated.
+        let mut reader: Box<dyn FormatReader> = Box::new(open_wav(buf, false).unwrap());
+        assert_eq!(reader.packets().count(), 3);
+        assert!(reader.packets().next().is_none());
+    }
+}
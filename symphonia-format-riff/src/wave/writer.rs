@@ -0,0 +1,379 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom, Write};
+
+use symphonia_core::audio::{AudioBufferRef, Channels, SampleBuffer, SignalSpec};
+use symphonia_core::conv::ConvertibleSample;
+use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::sample::i24;
+
+/// The format tag of integer PCM audio.
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+/// The format tag of IEEE floating point audio.
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+/// The format tag of audio described by the extensible format chunk.
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// The sub-format GUID suffix shared by all sub-formats of the extensible format chunk. The
+/// sub-format GUID is the format tag, as a little-endian `u16`, followed by this suffix.
+#[rustfmt::skip]
+const KSDATAFORMAT_SUBTYPE_SUFFIX: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+/// The sample format of the audio written by a [`WavWriter`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WavSampleFormat {
+    /// Signed 16-bit integer PCM.
+    S16,
+    /// Signed 24-bit integer PCM.
+    S24,
+    /// Signed 32-bit integer PCM.
+    S32,
+    /// 32-bit IEEE floating point.
+    F32,
+}
+
+impl WavSampleFormat {
+    /// Gets the number of bits per sample.
+    fn bits_per_sample(self) -> u16 {
+        match self {
+            WavSampleFormat::S16 => 16,
+            WavSampleFormat::S24 => 24,
+            WavSampleFormat::S32 | WavSampleFormat::F32 => 32,
+        }
+    }
+
+    /// Gets the format tag of the sample format.
+    fn format_tag(self) -> u16 {
+        match self {
+            WavSampleFormat::F32 => WAVE_FORMAT_IEEE_FLOAT,
+            _ => WAVE_FORMAT_PCM,
+        }
+    }
+}
+
+/// Waveform Audio File Format (WAV) writer.
+///
+/// `WavWriter` writes interleaved PCM audio into a WAVE file. The header is written when the writer
+/// is instantiated, and the chunk lengths are patched when the writer is finalized. Therefore, a
+/// WAVE file is only valid after [`WavWriter::finalize`] is called.
+///
+/// The extensible format chunk is used if the stream has more than two channels, more than 16 bits
+/// per sample, or a channel layout that is not the default for its number of channels.
+/// Channels without an equivalent speaker position in a WAVEFORMATEXTENSIBLE channel mask are
+/// written without speaker positions.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    spec: SignalSpec,
+    format: WavSampleFormat,
+    riff_len_pos: u64,
+    data_start_pos: u64,
+    data_len: u32,
+    buf: Vec<u8>,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Instantiate a new `WavWriter` that writes audio with the given signal specification and
+    /// sample format, and write the WAVE header.
+    pub fn new(mut writer: W, spec: SignalSpec, format: WavSampleFormat) -> Result<Self> {
+        let n_channels = spec.channels.count() as u16;
+        let bits_per_sample = format.bits_per_sample();
+        let block_align = n_channels * (bits_per_sample / 8);
+
+        let byte_rate = match spec.rate.checked_mul(u32::from(block_align)) {
+            Some(byte_rate) => byte_rate,
+            None => return unsupported_error("wav: sample rate too large"),
+        };
+
+        // Channels without an equivalent speaker position are written without positions.
+        let channel_mask = spec.channels.to_wave_channel_mask().unwrap_or(0);
+
+        let is_extensible = n_channels > 2
+            || bits_per_sample > 16
+            || Channels::discrete(usize::from(n_channels)) != Some(spec.channels);
+
+        let riff_len_pos = writer.stream_position()? + 4;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&(if is_extensible { 40u32 } else { 16u32 }).to_le_bytes())?;
+
+        let format_tag = if is_extensible { WAVE_FORMAT_EXTENSIBLE } else { format.format_tag() };
+
+        writer.write_all(&format_tag.to_le_bytes())?;
+        writer.write_all(&n_channels.to_le_bytes())?;
+        writer.write_all(&spec.rate.to_le_bytes())?;
+        writer.write_all(&byte_rate.to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&bits_per_sample.to_le_bytes())?;
+
+        if is_extensible {
+            // The size of the extension, the number of valid bits per sample, the channel mask,
+            // and the sub-format GUID.
+            writer.write_all(&22u16.to_le_bytes())?;
+            writer.write_all(&bits_per_sample.to_le_bytes())?;
+            writer.write_all(&channel_mask.to_le_bytes())?;
+            writer.write_all(&format.format_tag().to_le_bytes())?;
+            writer.write_all(&KSDATAFORMAT_SUBTYPE_SUFFIX)?;
+        }
+
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        let data_start_pos = writer.stream_position()?;
+
+        Ok(WavWriter {
+            writer,
+            spec,
+            format,
+            riff_len_pos,
+            data_start_pos,
+            data_len: 0,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Gets the signal specification of the audio being written.
+    pub fn spec(&self) -> &SignalSpec {
+        &self.spec
+    }
+
+    /// Gets the sample format of the audio being written.
+    pub fn format(&self) -> WavSampleFormat {
+        self.format
+    }
+
+    /// Converts the audio buffer to the sample format of the writer, and writes it.
+    ///
+    /// The signal specification of the audio buffer must match the signal specification of the
+    /// writer.
+    pub fn write(&mut self, buf: AudioBufferRef<'_>) -> Result<()> {
+        if *buf.spec() != self.spec {
+            return unsupported_error("wav: audio buffer signal specification does not match");
+        }
+
+        self.buf.clear();
+
+        match self.format {
+            WavSampleFormat::S16 => {
+                encode(buf, &mut self.buf, |s: i16, out| out.extend_from_slice(&s.to_le_bytes()))
+            }
+            WavSampleFormat::S24 => encode(buf, &mut self.buf, |s: i24, out| {
+                out.extend_from_slice(&s.inner().to_le_bytes()[..3])
+            }),
+            WavSampleFormat::S32 => {
+                encode(buf, &mut self.buf, |s: i32, out| out.extend_from_slice(&s.to_le_bytes()))
+            }
+            WavSampleFormat::F32 => {
+                encode(buf, &mut self.buf, |s: f32, out| out.extend_from_slice(&s.to_le_bytes()))
+            }
+        }
+
+        // The length of the RIFF chunk, including the padding byte of the data chunk, must fit in
+        // a u32.
+        let riff_len = (self.data_start_pos - self.riff_len_pos - 4)
+            + u64::from(self.data_len)
+            + self.buf.len() as u64
+            + 1;
+
+        if riff_len > u64::from(u32::MAX) {
+            return unsupported_error("wav: data chunk too large");
+        }
+
+        self.writer.write_all(&self.buf)?;
+        self.data_len += self.buf.len() as u32;
+
+        Ok(())
+    }
+
+    /// Writes the padding byte of the data chunk, if required, patches the chunk lengths, and
+    /// returns the underlying writer.
+    pub fn finalize(mut self) -> Result<W> {
+        let pad_len = self.data_len & 1;
+
+        if pad_len != 0 {
+            self.writer.write_all(&[0])?;
+        }
+
+        let end_pos = self.writer.stream_position()?;
+
+        // The RIFF chunk spans from after its length to the end of the padded data chunk.
+        let riff_len = (end_pos - self.riff_len_pos - 4) as u32;
+
+        self.writer.seek(SeekFrom::Start(self.riff_len_pos))?;
+        self.writer.write_all(&riff_len.to_le_bytes())?;
+
+        self.writer.seek(SeekFrom::Start(self.data_start_pos - 4))?;
+        self.writer.write_all(&self.data_len.to_le_bytes())?;
+
+        self.writer.seek(SeekFrom::Start(end_pos))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Converts the audio buffer into interleaved samples of type `S`, and encodes each sample into
+/// `out` using the provided function.
+fn encode<S, F>(buf: AudioBufferRef<'_>, out: &mut Vec<u8>, encode_sample: F)
+where
+    S: ConvertibleSample,
+    F: Fn(S, &mut Vec<u8>),
+{
+    let mut samples = SampleBuffer::<S>::new(buf.frames() as u64, *buf.spec());
+    samples.copy_interleaved_ref(buf);
+
+    out.reserve(samples.len() * std::mem::size_of::<S>());
+
+    for &sample in samples.samples() {
+        encode_sample(sample, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, Channels, Signal, SignalSpec};
+    use symphonia_core::codecs::{
+        CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE,
+    };
+    use symphonia_core::formats::{FormatOptions, FormatReader};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::{WavSampleFormat, WavWriter};
+    use crate::WavReader;
+
+    fn new_writer(spec: SignalSpec, format: WavSampleFormat) -> WavWriter<Cursor<Vec<u8>>> {
+        WavWriter::new(Cursor::new(Vec::new()), spec, format).unwrap()
+    }
+
+    #[test]
+    fn verify_header() {
+        let spec = SignalSpec::new(8000, Channels::FRONT_LEFT);
+
+        let mut buf = AudioBuffer::<i16>::new(3, spec);
+        buf.render_reserved(Some(3));
+        buf.chan_mut(0).copy_from_slice(&[1, -1, 0x1234]);
+
+        let mut writer = new_writer(spec, WavSampleFormat::S16);
+        writer.write(buf.as_audio_buffer_ref()).unwrap();
+
+        let wav = writer.finalize().unwrap().into_inner();
+
+        let mut expected = b"RIFF".to_vec();
+        expected.extend_from_slice(&(4 + 24 + 8 + 6u32).to_le_bytes());
+        expected.extend_from_slice(b"WAVEfmt ");
+        expected.extend_from_slice(&16u32.to_le_bytes());
+        expected.extend_from_slice(&[1, 0, 1, 0]);
+        expected.extend_from_slice(&8000u32.to_le_bytes());
+        expected.extend_from_slice(&16000u32.to_le_bytes());
+        expected.extend_from_slice(&[2, 0, 16, 0]);
+        expected.extend_from_slice(b"data");
+        expected.extend_from_slice(&6u32.to_le_bytes());
+        expected.extend_from_slice(&[0x01, 0x00, 0xff, 0xff, 0x34, 0x12]);
+
+        assert_eq!(wav, expected);
+    }
+
+    #[test]
+    fn verify_extensible_header() {
+        let channels = Channels::FRONT_CENTRE;
+        let spec = SignalSpec::new(44100, channels);
+
+        let mut buf = AudioBuffer::<i32>::new(1, spec);
+        buf.render_reserved(Some(1));
+
+        let mut writer = new_writer(spec, WavSampleFormat::S24);
+        writer.write(buf.as_audio_buffer_ref()).unwrap();
+
+        // A buffer with a different signal specification is rejected.
+        let other = AudioBuffer::<i32>::new(1, SignalSpec::new(48000, channels));
+        assert!(writer.write(other.as_audio_buffer_ref()).is_err());
+
+        let wav = writer.finalize().unwrap().into_inner();
+
+        // The extensible format chunk is used since the channel layout is not the default, and the
+        // 3 byte data chunk is padded.
+        assert_eq!(wav.len(), 12 + 48 + 8 + 3 + 1);
+        assert_eq!(&wav[4..8], &(wav.len() as u32 - 8).to_le_bytes());
+        assert_eq!(&wav[20..22], &[0xfe, 0xff]);
+        assert_eq!(&wav[40..44], &0x0004u32.to_le_bytes());
+        assert_eq!(&wav[64..68], &3u32.to_le_bytes());
+    }
+
+    #[test]
+    fn verify_round_trip() {
+        let spec = SignalSpec::new(8000, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        // A stereo sawtooth of 3000 frames, with the right channel inverted.
+        let mut buf = AudioBuffer::<i16>::new(3000, spec);
+        buf.render_reserved(Some(3000));
+
+        let (left, right) = buf.chan_pair_mut(0, 1);
+
+        for (i, (l, r)) in left.iter_mut().zip(right).enumerate() {
+            *l = (i as i16).wrapping_mul(97);
+            *r = !*l;
+        }
+
+        let formats = [
+            (WavSampleFormat::S16, CODEC_TYPE_PCM_S16LE),
+            (WavSampleFormat::S24, CODEC_TYPE_PCM_S24LE),
+            (WavSampleFormat::S32, CODEC_TYPE_PCM_S32LE),
+            (WavSampleFormat::F32, CODEC_TYPE_PCM_F32LE),
+        ];
+
+        for &(format, codec) in &formats {
+            let mut writer = new_writer(spec, format);
+            writer.write(buf.as_audio_buffer_ref()).unwrap();
+
+            let wav = writer.finalize().unwrap().into_inner();
+
+            let mss = MediaSourceStream::new(Box::new(Cursor::new(wav)), Default::default());
+            let mut reader = WavReader::try_new(mss, &FormatOptions::default()).unwrap();
+
+            let params = &reader.tracks()[0].codec_params;
+            assert_eq!(params.codec, codec, "{:?}", format);
+            assert_eq!(params.sample_rate, Some(8000));
+            assert_eq!(params.channels, Some(spec.channels));
+            assert_eq!(params.n_frames, Some(3000));
+
+            let mut data = Vec::new();
+
+            while let Ok(packet) = reader.next_packet() {
+                data.extend_from_slice(packet.buf());
+            }
+
+            // Every sample format converts losslessly from 16-bit, so converting the samples read
+            // back to 16-bit must yield the samples that were written.
+            let width = usize::from(format.bits_per_sample() / 8);
+
+            let samples: Vec<i16> = data
+                .chunks_exact(width)
+                .map(|s| match format {
+                    WavSampleFormat::S16 => i16::from_le_bytes([s[0], s[1]]),
+                    WavSampleFormat::S24 => i16::from_le_bytes([s[1], s[2]]),
+                    WavSampleFormat::S32 => i16::from_le_bytes([s[2], s[3]]),
+                    WavSampleFormat::F32 => {
+                        (f32::from_le_bytes([s[0], s[1], s[2], s[3]]) * 32768.0) as i16
+                    }
+                })
+                .collect();
+
+            let expected: Vec<i16> =
+                buf.chan(0).iter().zip(buf.chan(1)).flat_map(|(&l, &r)| vec![l, r]).collect();
+
+            assert_eq!(samples, expected, "{:?}", format);
+        }
+    }
+}
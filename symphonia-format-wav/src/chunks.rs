@@ -75,11 +75,7 @@ fn test_fix_channel_mask() {
 }
 
 fn try_channel_count_to_mask(count: u16) -> Result<Channels> {
-    (1..=32)
-        .contains(&count)
-        .then(|| Channels::from_bits(((1u64 << count) - 1) as u32))
-        .flatten()
-        .ok_or(Error::DecodeError("wav: invalid channel count"))
+    Channels::discrete(usize::from(count)).ok_or(Error::DecodeError("wav: invalid channel count"))
 }
 
 #[test]
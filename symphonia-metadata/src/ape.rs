@@ -0,0 +1,430 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An APEv1 and APEv2 tag reader.
+//!
+//! APE tags are usually appended to the end of a file, and may be followed by an ID3v1 tag. An APE
+//! tag always ends with a footer that gives the length of the tag, and therefore the tag must be
+//! found by reading the footer first.
+
+use std::collections::HashMap;
+use std::io::{Seek, SeekFrom};
+
+use lazy_static::lazy_static;
+use log::warn;
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{FiniteStream, MediaSource, MediaSourceStream, ReadBytes, ScopedStream};
+use symphonia_core::meta::{MetadataBuilder, StandardTagKey, Tag, Value};
+
+lazy_static! {
+    static ref APE_ITEM_MAP: HashMap<&'static str, StandardTagKey> = {
+        let mut m = HashMap::new();
+        m.insert("album"                    , StandardTagKey::Album);
+        m.insert("album artist"             , StandardTagKey::AlbumArtist);
+        m.insert("albumartist"              , StandardTagKey::AlbumArtist);
+        m.insert("arranger"                 , StandardTagKey::Arranger);
+        m.insert("artist"                   , StandardTagKey::Artist);
+        m.insert("bpm"                      , StandardTagKey::Bpm);
+        m.insert("catalog"                  , StandardTagKey::IdentCatalogNumber);
+        m.insert("comment"                  , StandardTagKey::Comment);
+        m.insert("composer"                 , StandardTagKey::Composer);
+        m.insert("conductor"                , StandardTagKey::Conductor);
+        m.insert("copyright"                , StandardTagKey::Copyright);
+        m.insert("disc"                     , StandardTagKey::DiscNumber);
+        m.insert("ean/upc"                  , StandardTagKey::IdentEanUpn);
+        m.insert("encodedby"                , StandardTagKey::EncodedBy);
+        m.insert("encoder"                  , StandardTagKey::Encoder);
+        m.insert("genre"                    , StandardTagKey::Genre);
+        m.insert("isrc"                     , StandardTagKey::IdentIsrc);
+        m.insert("label"                    , StandardTagKey::Label);
+        m.insert("language"                 , StandardTagKey::Language);
+        m.insert("lyricist"                 , StandardTagKey::Lyricist);
+        m.insert("lyrics"                   , StandardTagKey::Lyrics);
+        m.insert("media"                    , StandardTagKey::MediaFormat);
+        m.insert("mood"                     , StandardTagKey::Mood);
+        m.insert("musicbrainz_albumartistid", StandardTagKey::MusicBrainzAlbumArtistId);
+        m.insert("musicbrainz_albumid"      , StandardTagKey::MusicBrainzAlbumId);
+        m.insert("musicbrainz_artistid"     , StandardTagKey::MusicBrainzArtistId);
+        m.insert("musicbrainz_trackid"      , StandardTagKey::MusicBrainzRecordingId);
+        m.insert("performer"                , StandardTagKey::Performer);
+        m.insert("publisher"                , StandardTagKey::Label);
+        m.insert("record date"              , StandardTagKey::Date);
+        m.insert("replaygain_album_gain"    , StandardTagKey::ReplayGainAlbumGain);
+        m.insert("replaygain_album_peak"    , StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("replaygain_track_gain"    , StandardTagKey::ReplayGainTrackGain);
+        m.insert("replaygain_track_peak"    , StandardTagKey::ReplayGainTrackPeak);
+        m.insert("subtitle"                 , StandardTagKey::TrackSubtitle);
+        m.insert("title"                    , StandardTagKey::TrackTitle);
+        m.insert("track"                    , StandardTagKey::TrackNumber);
+        m.insert("year"                     , StandardTagKey::Date);
+        m
+    };
+}
+
+/// The preamble of an APE tag header or footer.
+const APE_PREAMBLE: [u8; 8] = *b"APETAGEX";
+
+/// The length of an APE tag header or footer.
+const APE_HEADER_LEN: u32 = 32;
+
+/// The length of an ID3v1 tag.
+const ID3V1_TAG_LEN: u64 = 128;
+
+/// The maximum length of an APE tag.
+const MAX_APE_TAG_LEN: u32 = 16 * 1024 * 1024;
+
+/// APE tag version 1.0 (APEv1).
+const APE_VERSION_1: u32 = 1000;
+
+/// APE tag version 2.0 (APEv2).
+const APE_VERSION_2: u32 = 2000;
+
+/// The type of the value of an APEv2 tag item.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ItemType {
+    /// UTF-8 text, with multiple values separated by a null character.
+    Text,
+    /// Binary data.
+    Binary,
+    /// A UTF-8 link to an external resource.
+    Locator,
+}
+
+/// The header or footer of an APE tag.
+#[derive(Debug)]
+pub struct ApeHeader {
+    /// The version of the tag. Either 1000 (APEv1), or 2000 (APEv2).
+    pub version: u32,
+    /// The length of the tag items and footer in bytes. This excludes the header, if present.
+    pub tag_len: u32,
+    /// The number of tag items.
+    pub n_items: u32,
+    /// The global flags of the tag.
+    pub flags: u32,
+}
+
+impl ApeHeader {
+    /// Read the header or footer of an APE tag, starting at the preamble.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<ApeHeader> {
+        let mut preamble = [0; 8];
+        reader.read_buf_exact(&mut preamble)?;
+
+        if preamble != APE_PREAMBLE {
+            return unsupported_error("ape: not an ape tag");
+        }
+
+        Self::read_after_preamble(reader)
+    }
+
+    fn read_after_preamble<B: ReadBytes>(reader: &mut B) -> Result<ApeHeader> {
+        let version = reader.read_u32()?;
+        let tag_len = reader.read_u32()?;
+        let n_items = reader.read_u32()?;
+        let flags = reader.read_u32()?;
+
+        // The remaining 8 bytes are reserved.
+        reader.ignore_bytes(8)?;
+
+        if version != APE_VERSION_1 && version != APE_VERSION_2 {
+            return unsupported_error("ape: unsupported tag version");
+        }
+
+        if tag_len < APE_HEADER_LEN || tag_len > MAX_APE_TAG_LEN {
+            return decode_error("ape: invalid tag length");
+        }
+
+        Ok(ApeHeader { version, tag_len, n_items, flags })
+    }
+
+    /// Returns `true` if the tag has a header in addition to the footer. APEv1 tags never have a
+    /// header.
+    pub fn has_header(&self) -> bool {
+        self.version == APE_VERSION_2 && self.flags & 0x8000_0000 != 0
+    }
+
+    /// Returns `true` if this is the header of the tag, or `false` if it is the footer.
+    pub fn is_header(&self) -> bool {
+        self.version == APE_VERSION_2 && self.flags & 0x2000_0000 != 0
+    }
+
+    /// Gets the total length of the tag in bytes, including the header, if present.
+    pub fn total_len(&self) -> u64 {
+        u64::from(self.tag_len) + if self.has_header() { u64::from(APE_HEADER_LEN) } else { 0 }
+    }
+}
+
+/// Read the items of an APE tag into tags. The reader must be positioned at the first item.
+///
+/// Text items with multiple values yield one tag per value. Binary items yield a tag with a binary
+/// value. For APEv1 tags, all items are text.
+pub fn read_ape_items<B: ReadBytes>(
+    reader: &mut B,
+    header: &ApeHeader,
+    metadata: &mut MetadataBuilder,
+) -> Result<()> {
+    // The items are followed by the footer.
+    let mut items = ScopedStream::new(reader, u64::from(header.tag_len - APE_HEADER_LEN));
+
+    for _ in 0..header.n_items {
+        let value_len = items.read_u32()?;
+        let flags = items.read_u32()?;
+
+        // The key is 2 to 255 printable ASCII characters, terminated by a null character.
+        let mut key = String::new();
+
+        loop {
+            match items.read_byte()? {
+                0 => break,
+                c @ 0x20..=0x7e if key.len() < 255 => key.push(char::from(c)),
+                _ => return decode_error("ape: invalid item key"),
+            }
+        }
+
+        if key.len() < 2 {
+            return decode_error("ape: item key is too short");
+        }
+
+        if u64::from(value_len) > items.bytes_available() {
+            return decode_error("ape: item value exceeds the tag length");
+        }
+
+        let value = items.read_boxed_slice_exact(value_len as usize)?;
+
+        // APEv1 only supports text items.
+        let item_type = match header.version {
+            APE_VERSION_2 => match (flags >> 1) & 0x3 {
+                0 => ItemType::Text,
+                1 => ItemType::Binary,
+                2 => ItemType::Locator,
+                _ => {
+                    warn!("ape: ignoring item '{}' with a reserved type", key);
+                    continue;
+                }
+            },
+            _ => ItemType::Text,
+        };
+
+        let std_key = APE_ITEM_MAP.get(key.to_ascii_lowercase().as_str()).copied();
+
+        match item_type {
+            ItemType::Text | ItemType::Locator => {
+                // Each value of a text item is separated by a null character.
+                for value in value.split(|&b| b == 0) {
+                    let value = String::from_utf8_lossy(value);
+                    metadata.add_tag(Tag::new(std_key, &key, Value::from(value)));
+                }
+            }
+            ItemType::Binary => {
+                metadata.add_tag(Tag::new(std_key, &key, Value::from(&value[..])));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Find and read an APE tag at the end of the stream. The APE tag may be followed by an ID3v1 tag.
+///
+/// Returns `true` if an APE tag was read. The position of the stream is restored afterwards. If
+/// the stream is not seekable, or the length of the stream is unknown, then no tag is read.
+pub fn read_ape_tag_at_end(
+    reader: &mut MediaSourceStream,
+    metadata: &mut MetadataBuilder,
+) -> Result<bool> {
+    if !reader.is_seekable() {
+        return Ok(false);
+    }
+
+    let pos = reader.pos();
+
+    let result = find_ape_tag_at_end(reader, metadata);
+
+    reader.seek(SeekFrom::Start(pos))?;
+
+    result
+}
+
+fn find_ape_tag_at_end(
+    reader: &mut MediaSourceStream,
+    metadata: &mut MetadataBuilder,
+) -> Result<bool> {
+    let mut end = match reader.byte_len() {
+        Some(len) => len,
+        None => return Ok(false),
+    };
+
+    // If there is an ID3v1 tag at the end of the stream, then the APE tag precedes it.
+    if end >= ID3V1_TAG_LEN {
+        reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+        if reader.read_triple_bytes()? == *b"TAG" {
+            end -= ID3V1_TAG_LEN;
+        }
+    }
+
+    if end < u64::from(APE_HEADER_LEN) {
+        return Ok(false);
+    }
+
+    reader.seek(SeekFrom::Start(end - u64::from(APE_HEADER_LEN)))?;
+
+    let mut preamble = [0; 8];
+    reader.read_buf_exact(&mut preamble)?;
+
+    if preamble != APE_PREAMBLE {
+        return Ok(false);
+    }
+
+    let footer = ApeHeader::read_after_preamble(reader)?;
+
+    if footer.is_header() {
+        return decode_error("ape: expected a tag footer");
+    }
+
+    if u64::from(footer.tag_len) > end {
+        return decode_error("ape: tag length exceeds the stream length");
+    }
+
+    reader.seek(SeekFrom::Start(end - u64::from(footer.tag_len)))?;
+
+    read_ape_items(reader, &footer, metadata)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::{MetadataBuilder, StandardTagKey, Value};
+
+    use super::*;
+
+    /// Builds an APE tag item.
+    fn item(key: &str, flags: u32, value: &[u8]) -> Vec<u8> {
+        let mut buf = (value.len() as u32).to_le_bytes().to_vec();
+        buf.extend_from_slice(&flags.to_le_bytes());
+        buf.extend_from_slice(key.as_bytes());
+        buf.push(0);
+        buf.extend_from_slice(value);
+        buf
+    }
+
+    /// Builds an APE tag header or footer.
+    fn header(version: u32, tag_len: usize, n_items: usize, flags: u32) -> Vec<u8> {
+        let mut buf = APE_PREAMBLE.to_vec();
+        buf.extend_from_slice(&version.to_le_bytes());
+        buf.extend_from_slice(&(tag_len as u32).to_le_bytes());
+        buf.extend_from_slice(&(n_items as u32).to_le_bytes());
+        buf.extend_from_slice(&flags.to_le_bytes());
+        buf.extend_from_slice(&[0; 8]);
+        buf
+    }
+
+    /// Builds an APEv2 tag with a header and footer.
+    fn build_apev2(items: &[Vec<u8>]) -> Vec<u8> {
+        let n_items = items.len();
+        let items = items.concat();
+        let tag_len = items.len() + 32;
+
+        let mut buf = header(2000, tag_len, n_items, 0xa000_0000);
+        buf.extend_from_slice(&items);
+        buf.extend_from_slice(&header(2000, tag_len, n_items, 0x8000_0000));
+        buf
+    }
+
+    /// The standard key, key, and value of a tag.
+    type TagFields = (Option<StandardTagKey>, String, Value);
+
+    fn read_tags(buf: Vec<u8>) -> (bool, Vec<TagFields>, u64) {
+        let mut mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        mss.ignore_bytes(4).unwrap();
+
+        let mut builder = MetadataBuilder::new();
+        let found = read_ape_tag_at_end(&mut mss, &mut builder).unwrap();
+
+        let tags = builder
+            .metadata()
+            .tags()
+            .iter()
+            .map(|tag| (tag.std_key, tag.key.clone(), tag.value.clone()))
+            .collect();
+
+        (found, tags, mss.pos())
+    }
+
+    fn texts(tags: &[TagFields]) -> Vec<(&str, String)> {
+        tags.iter().map(|(_, key, value)| (key.as_str(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn verify_apev2_tag_at_end() {
+        let tag = build_apev2(&[
+            item("Title", 0, b"Song"),
+            item("Artist", 0, b"First\0Second"),
+            item("Cover Art (Front)", 0x2, b"cover.jpg\0\xff\xd8"),
+        ]);
+
+        // The APE tag is followed by an ID3v1 tag.
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+
+        for trailer in [Vec::new(), id3v1].iter() {
+            let buf = [&[0xaa; 300][..], &tag, trailer].concat();
+
+            let (found, tags, pos) = read_tags(buf);
+
+            assert!(found);
+            assert_eq!(pos, 4);
+
+            assert_eq!(
+                texts(&tags[..3]),
+                &[
+                    ("Title", "Song".into()),
+                    ("Artist", "First".into()),
+                    ("Artist", "Second".into())
+                ]
+            );
+
+            assert_eq!(tags[0].0, Some(StandardTagKey::TrackTitle));
+            assert_eq!(tags[1].0, Some(StandardTagKey::Artist));
+
+            // Binary items are not split into multiple values.
+            match &tags[3].2 {
+                Value::Binary(data) => assert_eq!(&data[..], b"cover.jpg\0\xff\xd8"),
+                _ => panic!("expected a binary value"),
+            }
+        }
+    }
+
+    #[test]
+    fn verify_apev1_tag_at_end() {
+        // APEv1 tags have no header, and all items are text regardless of the item flags.
+        let items = [item("ALBUM", 0, b"Record"), item("Year", 0x2, b"1999")].concat();
+
+        let mut buf = vec![0xaa; 64];
+        buf.extend_from_slice(&items);
+        buf.extend_from_slice(&header(1000, items.len() + 32, 2, 0));
+
+        let (found, tags, _) = read_tags(buf);
+
+        assert!(found);
+        assert_eq!(texts(&tags), &[("ALBUM", "Record".into()), ("Year", "1999".into())]);
+        assert_eq!(tags[1].0, Some(StandardTagKey::Date));
+    }
+
+    #[test]
+    fn verify_no_tag_at_end() {
+        let (found, tags, pos) = read_tags(vec![0xaa; 300]);
+
+        assert!(!found);
+        assert!(tags.is_empty());
+        assert_eq!(pos, 4);
+    }
+}
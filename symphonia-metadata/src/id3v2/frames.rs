@@ -80,7 +80,7 @@ use super::util;
 //   x   TT1    TIT1             ContentGroup       Content group description
 //   x   TT2    TIT2             TrackTitle         Title/songname/content description
 //   x   TT3    TIT3             TrackSubtitle      Subtitle/Description refinement
-//   x   TKE    TKEY                                Initial key
+//   x   TKE    TKEY             InitialKey         Initial key
 //   x   TLA    TLAN             Language           Language(s)
 //   x   TLE    TLEN                                Length
 //   x                  TMCL                        Musician credits list
@@ -138,12 +138,15 @@ use super::util;
 //   x   TSA                     SortAlbum          (Apple iTunes) Album sort order
 //   x   TS2    TSO2             SortAlbumArtist    (Apple iTunes) Album artist sort order
 //   x   TSC    TSOC             SortComposer       (Apple iTunes) Composer sort order
+//   x          CHAP                                (Chapter addendum) Chapter
+//   x          CTOC                                (Chapter addendum) Table of contents
 //
 // Information on these frames can be found at:
 //
 //     ID3v2.2: http://id3.org/id3v2-00
 //     ID3v2.3: http://id3.org/d3v2.3.0
 //     ID3v2.4: http://id3.org/id3v2.4.0-frames
+//     Chapter addendum: http://id3.org/id3v2-chapters-1.0
 
 /// The result of parsing a frame.
 pub enum FrameResult {
@@ -159,6 +162,34 @@ pub enum FrameResult {
     Visual(Visual),
     /// A frame was parsed and yielded many `Tag`s.
     MultipleTags(Vec<Tag>),
+    /// A `CHAP` frame was parsed.
+    Chapter(ChapterFrame),
+    /// A `CTOC` frame was parsed.
+    TableOfContents(TocFrame),
+}
+
+/// A `CHAP` (chapter) frame.
+pub struct ChapterFrame {
+    /// The element ID of the chapter.
+    pub element_id: String,
+    /// The start time of the chapter in milliseconds.
+    pub start_ms: u32,
+    /// The end time of the chapter in milliseconds.
+    pub end_ms: u32,
+    /// The embedded sub-frames describing the chapter.
+    pub sub_frames: Box<[u8]>,
+}
+
+/// A `CTOC` (table of contents) frame.
+pub struct TocFrame {
+    /// The element ID of the table of contents.
+    pub element_id: String,
+    /// If `true`, this is the root of the table of contents tree.
+    pub is_top_level: bool,
+    /// The element IDs of the child chapters and tables of contents.
+    pub children: Vec<String>,
+    /// The embedded sub-frames describing the table of contents.
+    pub sub_frames: Box<[u8]>,
 }
 
 /// Makes a frame result for a frame containing invalid data.
@@ -255,8 +286,10 @@ lazy_static! {
             // m.insert(b"AENC", read_null_frame);
             m.insert(b"APIC", (read_apic_frame as FrameParser, None));
             // m.insert(b"ASPI", read_null_frame);
+            m.insert(b"CHAP", (read_chap_frame, None));
             m.insert(b"COMM", (read_comm_uslt_frame, Some(StandardTagKey::Comment)));
             // m.insert(b"COMR", read_null_frame);
+            m.insert(b"CTOC", (read_ctoc_frame, None));
             // m.insert(b"ENCR", read_null_frame);
             // m.insert(b"EQU2", read_null_frame);
             // m.insert(b"EQUA", read_null_frame);
@@ -301,7 +334,7 @@ lazy_static! {
             m.insert(b"TIT1", (read_text_frame, Some(StandardTagKey::ContentGroup)));
             m.insert(b"TIT2", (read_text_frame, Some(StandardTagKey::TrackTitle)));
             m.insert(b"TIT3", (read_text_frame, Some(StandardTagKey::TrackSubtitle)));
-            m.insert(b"TKEY", (read_text_frame, None));
+            m.insert(b"TKEY", (read_text_frame, Some(StandardTagKey::InitialKey)));
             m.insert(b"TLAN", (read_text_frame, Some(StandardTagKey::Language)));
             m.insert(b"TLEN", (read_text_frame, None));
             m.insert(b"TMCL", (read_text_frame, None));
@@ -911,6 +944,60 @@ fn read_apic_frame(
     Ok(FrameResult::Visual(visual))
 }
 
+/// Reads a `CHAP` (chapter) frame. The embedded sub-frames are returned unparsed since they must be
+/// read with the frame reader for the version of the tag.
+fn read_chap_frame(
+    reader: &mut BufReader<'_>,
+    _: Option<StandardTagKey>,
+    _: &str,
+) -> Result<FrameResult> {
+    let element_id =
+        scan_text(reader, Encoding::Iso8859_1, reader.bytes_available() as usize)?.into_owned();
+
+    let start_ms = reader.read_be_u32()?;
+    let end_ms = reader.read_be_u32()?;
+
+    // The start and end byte offsets are ignored since the times are always present, and byte
+    // offsets cannot be mapped to a sample accurate position.
+    reader.ignore_bytes(8)?;
+
+    let sub_frames = Box::from(reader.read_buf_bytes_available_ref());
+
+    Ok(FrameResult::Chapter(ChapterFrame { element_id, start_ms, end_ms, sub_frames }))
+}
+
+/// Reads a `CTOC` (table of contents) frame. The embedded sub-frames are returned unparsed since
+/// they must be read with the frame reader for the version of the tag.
+fn read_ctoc_frame(
+    reader: &mut BufReader<'_>,
+    _: Option<StandardTagKey>,
+    _: &str,
+) -> Result<FrameResult> {
+    let element_id =
+        scan_text(reader, Encoding::Iso8859_1, reader.bytes_available() as usize)?.into_owned();
+
+    // The ordered flag is ignored since the child elements are always kept in the order they are
+    // listed.
+    let flags = reader.read_u8()?;
+    let n_entries = reader.read_u8()?;
+
+    let mut children = Vec::with_capacity(usize::from(n_entries));
+
+    for _ in 0..n_entries {
+        let child = scan_text(reader, Encoding::Iso8859_1, reader.bytes_available() as usize)?;
+        children.push(child.into_owned());
+    }
+
+    let sub_frames = Box::from(reader.read_buf_bytes_available_ref());
+
+    Ok(FrameResult::TableOfContents(TocFrame {
+        element_id,
+        is_top_level: flags & 0x2 != 0,
+        children,
+        sub_frames,
+    }))
+}
+
 /// Enumeration of valid encodings for text fields in ID3v2 tags
 #[derive(Copy, Clone, Debug)]
 enum Encoding {
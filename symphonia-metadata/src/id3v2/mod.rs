@@ -7,11 +7,15 @@
 
 //! An ID3v2 metadata reader.
 
+use std::collections::HashSet;
+
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::io::*;
+use symphonia_core::meta::{Chapter, ChapterGroup, ChapterGroupItem, Tag, Visual};
 use symphonia_core::meta::{MetadataBuilder, MetadataOptions, MetadataReader, MetadataRevision};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::support_metadata;
+use symphonia_core::units::TimeBase;
 
 use log::{info, trace, warn};
 
@@ -293,6 +297,9 @@ fn read_id3v2_body<B: ReadBytes + FiniteStream>(
         _ => unreachable!(),
     };
 
+    let mut chapters = Vec::new();
+    let mut tocs = Vec::new();
+
     loop {
         // Read frames based on the major version of the tag.
         let frame = match header.major_version {
@@ -319,6 +326,14 @@ fn read_id3v2_body<B: ReadBytes + FiniteStream>(
             FrameResult::Visual(visual) => {
                 metadata.add_visual(visual);
             }
+            // Chapter and table of contents frames are collected, and assembled into chapters
+            // after all frames are read since they may reference each other in any order.
+            FrameResult::Chapter(chapter) => {
+                chapters.push(chapter);
+            }
+            FrameResult::TableOfContents(toc) => {
+                tocs.push(toc);
+            }
             // An unknown frame was encountered.
             FrameResult::UnsupportedFrame(ref id) => {
                 info!("unsupported frame {}", id);
@@ -335,9 +350,132 @@ fn read_id3v2_body<B: ReadBytes + FiniteStream>(
         }
     }
 
+    if !chapters.is_empty() {
+        metadata.set_chapters(build_chapter_group(&chapters, &tocs, header)?);
+    }
+
     Ok(())
 }
 
+/// Reads the sub-frames embedded in a `CHAP` or `CTOC` frame, returning the tags and visuals they
+/// contain.
+fn read_sub_frames(data: &[u8], header: &Header) -> Result<(Vec<Tag>, Vec<Visual>)> {
+    let mut reader = BufReader::new(data);
+
+    let mut tags = Vec::new();
+    let mut visuals = Vec::new();
+
+    while reader.bytes_available() >= 10 {
+        let frame = match header.major_version {
+            3 => read_id3v2p3_frame(&mut reader),
+            4 => read_id3v2p4_frame(&mut reader),
+            _ => break,
+        }?;
+
+        match frame {
+            FrameResult::Padding => break,
+            FrameResult::Tag(tag) => tags.push(tag),
+            FrameResult::MultipleTags(multi_tags) => tags.extend(multi_tags),
+            FrameResult::Visual(visual) => visuals.push(visual),
+            FrameResult::UnsupportedFrame(ref id) => info!("unsupported sub-frame {}", id),
+            FrameResult::InvalidData(ref id) => warn!("invalid data for {} sub-frame", id),
+            // Chapters may not be nested within chapters.
+            FrameResult::Chapter(_) | FrameResult::TableOfContents(_) => {
+                warn!("ignoring nested chapter frame");
+            }
+        }
+    }
+
+    Ok((tags, visuals))
+}
+
+/// Builds the top-level chapter group from the chapter and table of contents frames of a tag.
+///
+/// If there is a top-level table of contents, the chapter group follows the table of contents
+/// tree. Otherwise, the chapter group contains all chapters ordered by their start time.
+fn build_chapter_group(
+    chapters: &[ChapterFrame],
+    tocs: &[TocFrame],
+    header: &Header,
+) -> Result<ChapterGroup> {
+    if let Some(toc) = tocs.iter().find(|toc| toc.is_top_level) {
+        let mut visited = HashSet::new();
+        visited.insert(toc.element_id.as_str());
+
+        return build_toc_group(toc, chapters, tocs, header, &mut visited);
+    }
+
+    let mut chapters: Vec<&ChapterFrame> = chapters.iter().collect();
+    chapters.sort_by_key(|chapter| chapter.start_ms);
+
+    let mut group = ChapterGroup::default();
+
+    for chapter in chapters {
+        group.items.push(ChapterGroupItem::Chapter(build_chapter(chapter, header)?));
+    }
+
+    Ok(group)
+}
+
+/// Builds a chapter group from a table of contents frame, recursively building nested tables of
+/// contents.
+///
+/// Each chapter or table of contents is added to the chapter tree at most once. `visited` contains
+/// the element IDs of the chapters and tables of contents that were already added. This breaks
+/// cycles, and prevents a tag from expanding into an exponentially large tree by referencing the
+/// same elements repeatedly.
+fn build_toc_group<'a>(
+    toc: &TocFrame,
+    chapters: &'a [ChapterFrame],
+    tocs: &'a [TocFrame],
+    header: &Header,
+    visited: &mut HashSet<&'a str>,
+) -> Result<ChapterGroup> {
+    let (tags, visuals) = read_sub_frames(&toc.sub_frames, header)?;
+
+    let mut group = ChapterGroup { items: Vec::new(), tags, visuals };
+
+    for child in toc.children.iter() {
+        if let Some(chapter) = chapters.iter().find(|chapter| &chapter.element_id == child) {
+            if !visited.insert(chapter.element_id.as_str()) {
+                warn!("ignoring repeated chapter {}", child);
+                continue;
+            }
+
+            group.items.push(ChapterGroupItem::Chapter(build_chapter(chapter, header)?));
+        }
+        else if let Some(nested) = tocs.iter().find(|toc| &toc.element_id == child) {
+            if !visited.insert(nested.element_id.as_str()) {
+                warn!("ignoring repeated or cyclic table of contents {}", child);
+                continue;
+            }
+
+            let nested = build_toc_group(nested, chapters, tocs, header, visited)?;
+            group.items.push(ChapterGroupItem::Group(nested));
+        }
+        else {
+            warn!("table of contents references missing element {}", child);
+        }
+    }
+
+    Ok(group)
+}
+
+/// Builds a chapter from a chapter frame.
+fn build_chapter(chapter: &ChapterFrame, header: &Header) -> Result<Chapter> {
+    let (tags, visuals) = read_sub_frames(&chapter.sub_frames, header)?;
+
+    // Chapter times are in milliseconds.
+    let tb = TimeBase::new(1, 1000);
+
+    Ok(Chapter {
+        start_time: tb.calc_time(u64::from(chapter.start_ms)),
+        end_time: Some(tb.calc_time(u64::from(chapter.end_ms))),
+        tags,
+        visuals,
+    })
+}
+
 pub fn read_id3v2<B: ReadBytes>(reader: &mut B, metadata: &mut MetadataBuilder) -> Result<()> {
     // Read the (sorta) version agnostic tag header.
     let header = read_id3v2_header(reader)?;
@@ -420,3 +558,180 @@ impl MetadataReader for Id3v2Reader {
         Ok(builder.metadata())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BufReader;
+    use symphonia_core::meta::{ChapterGroup, ChapterGroupItem, MetadataBuilder, StandardTagKey};
+    use symphonia_core::units::Time;
+
+    use super::read_id3v2;
+
+    /// Encodes a 28-bit integer as a syncsafe integer.
+    fn syncsafe(value: u32) -> [u8; 4] {
+        [
+            (value >> 21) as u8 & 0x7f,
+            (value >> 14) as u8 & 0x7f,
+            (value >> 7) as u8 & 0x7f,
+            value as u8 & 0x7f,
+        ]
+    }
+
+    /// Builds a frame for the given major version.
+    fn frame(version: u8, id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut buf = id.to_vec();
+        match version {
+            3 => buf.extend_from_slice(&(body.len() as u32).to_be_bytes()),
+            _ => buf.extend_from_slice(&syncsafe(body.len() as u32)),
+        }
+        buf.extend_from_slice(&[0, 0]);
+        buf.extend_from_slice(body);
+        buf
+    }
+
+    /// Builds a UTF-8 `TIT2` (title) frame.
+    fn title(version: u8, title: &str) -> Vec<u8> {
+        frame(version, b"TIT2", &[&[3], title.as_bytes()].concat())
+    }
+
+    /// Builds a `CHAP` frame with the given sub-frames.
+    fn chap(version: u8, id: &str, start_ms: u32, end_ms: u32, sub_frames: &[u8]) -> Vec<u8> {
+        let mut body = [id.as_bytes(), &[0]].concat();
+        body.extend_from_slice(&start_ms.to_be_bytes());
+        body.extend_from_slice(&end_ms.to_be_bytes());
+        body.extend_from_slice(&[0xff; 8]);
+        body.extend_from_slice(sub_frames);
+        frame(version, b"CHAP", &body)
+    }
+
+    /// Builds a `CTOC` frame with the given flags, children, and sub-frames.
+    fn ctoc(version: u8, id: &str, flags: u8, children: &[&str], sub_frames: &[u8]) -> Vec<u8> {
+        let mut body = [id.as_bytes(), &[0, flags, children.len() as u8]].concat();
+        for child in children {
+            body.extend_from_slice(child.as_bytes());
+            body.push(0);
+        }
+        body.extend_from_slice(sub_frames);
+        frame(version, b"CTOC", &body)
+    }
+
+    /// Builds a tag with the given major version containing the given frames.
+    fn tag(version: u8, frames: &[Vec<u8>]) -> Vec<u8> {
+        let body = frames.concat();
+        let mut buf = vec![b'I', b'D', b'3', version, 0, 0];
+        buf.extend_from_slice(&syncsafe(body.len() as u32));
+        buf.extend_from_slice(&body);
+        buf
+    }
+
+    fn ms(time: Time) -> u64 {
+        time.seconds * 1000 + (time.frac * 1000.0).round() as u64
+    }
+
+    /// Flattens a chapter group into a list of the depth, title, and start and end time in
+    /// milliseconds of each chapter or group.
+    fn flatten(group: &ChapterGroup, depth: usize, out: &mut Vec<(usize, String, u64, u64)>) {
+        let title = |tags: &[symphonia_core::meta::Tag]| {
+            tags.iter()
+                .find(|tag| tag.std_key == Some(StandardTagKey::TrackTitle))
+                .map(|tag| tag.value.to_string())
+                .unwrap_or_default()
+        };
+
+        for item in group.items.iter() {
+            match item {
+                ChapterGroupItem::Chapter(chapter) => {
+                    let end = chapter.end_time.map(ms).unwrap();
+                    out.push((depth, title(&chapter.tags), ms(chapter.start_time), end));
+                }
+                ChapterGroupItem::Group(group) => {
+                    out.push((depth, title(&group.tags), 0, 0));
+                    flatten(group, depth + 1, out);
+                }
+            }
+        }
+    }
+
+    fn read_chapters(buf: &[u8]) -> (Vec<(usize, String, u64, u64)>, usize) {
+        let mut builder = MetadataBuilder::new();
+        read_id3v2(&mut BufReader::new(buf), &mut builder).unwrap();
+
+        let rev = builder.metadata();
+        let mut chapters = Vec::new();
+        flatten(rev.chapters().unwrap(), 0, &mut chapters);
+
+        (chapters, rev.tags().len())
+    }
+
+    #[test]
+    fn verify_nested_chapters() {
+        let apic = frame(4, b"APIC", b"\0image/png\0\x03\0\x89PNG");
+
+        let buf = tag(
+            4,
+            &[
+                title(4, "Episode"),
+                chap(4, "chp0", 0, 5000, &title(4, "Intro")),
+                chap(4, "chp1", 5000, 65_500, &[title(4, "Interview"), apic].concat()),
+                chap(4, "chp2", 65_500, 90_000, &title(4, "Outro")),
+                ctoc(4, "toc1", 0x01, &["chp1", "chp2"], &title(4, "Main")),
+                ctoc(4, "toc0", 0x03, &["chp0", "toc1"], &title(4, "Contents")),
+            ],
+        );
+
+        let (chapters, n_tags) = read_chapters(&buf);
+
+        assert_eq!(
+            chapters,
+            &[
+                (0, "Intro".to_string(), 0, 5000),
+                (0, "Main".to_string(), 0, 0),
+                (1, "Interview".to_string(), 5000, 65_500),
+                (1, "Outro".to_string(), 65_500, 90_000),
+            ]
+        );
+
+        // The sub-frames of the chapters are not tags of the tag.
+        assert_eq!(n_tags, 1);
+    }
+
+    #[test]
+    fn verify_cyclic_chapters() {
+        // Every table of contents references itself, the other table of contents, and the chapter
+        // many times. Each element is only added to the chapter tree once.
+        let children = ["toc0", "toc1", "chp0"].repeat(64);
+
+        let buf = tag(
+            4,
+            &[
+                chap(4, "chp0", 0, 5000, &title(4, "Intro")),
+                ctoc(4, "toc1", 0x01, &children, &title(4, "Main")),
+                ctoc(4, "toc0", 0x03, &children, &title(4, "Contents")),
+            ],
+        );
+
+        let (chapters, _) = read_chapters(&buf);
+
+        assert_eq!(chapters, &[(0, "Main".to_string(), 0, 0), (1, "Intro".to_string(), 0, 5000)]);
+    }
+
+    #[test]
+    fn verify_chapters_without_toc() {
+        // Without a top-level table of contents, the chapters are ordered by start time.
+        let buf = tag(
+            3,
+            &[
+                chap(3, "b", 1000, 2000, &title(3, "Second")),
+                chap(3, "a", 0, 1000, &title(3, "First")),
+                ctoc(3, "toc", 0x00, &["b"], &[]),
+            ],
+        );
+
+        let (chapters, _) = read_chapters(&buf);
+
+        assert_eq!(
+            chapters,
+            &[(0, "First".to_string(), 0, 1000), (0, "Second".to_string(), 1000, 2000)]
+        );
+    }
+}
@@ -60,6 +60,15 @@ lazy_static! {
         m.insert("com.apple.iTunes:originaldate", StandardTagKey::OriginalDate);
         m.insert("com.apple.iTunes:PRODUCER", StandardTagKey::Producer);
         m.insert("com.apple.iTunes:REMIXER", StandardTagKey::Remixer);
+        // ReplayGain tags are written in both lower and upper case.
+        m.insert("com.apple.iTunes:replaygain_album_gain", StandardTagKey::ReplayGainAlbumGain);
+        m.insert("com.apple.iTunes:replaygain_album_peak", StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("com.apple.iTunes:replaygain_track_gain", StandardTagKey::ReplayGainTrackGain);
+        m.insert("com.apple.iTunes:replaygain_track_peak", StandardTagKey::ReplayGainTrackPeak);
+        m.insert("com.apple.iTunes:REPLAYGAIN_ALBUM_GAIN", StandardTagKey::ReplayGainAlbumGain);
+        m.insert("com.apple.iTunes:REPLAYGAIN_ALBUM_PEAK", StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("com.apple.iTunes:REPLAYGAIN_TRACK_GAIN", StandardTagKey::ReplayGainTrackGain);
+        m.insert("com.apple.iTunes:REPLAYGAIN_TRACK_PEAK", StandardTagKey::ReplayGainTrackPeak);
         m.insert("com.apple.iTunes:SCRIPT", StandardTagKey::Script);
         m.insert("com.apple.iTunes:SUBTITLE", StandardTagKey::TrackSubtitle);
         m
@@ -70,3 +79,108 @@ lazy_static! {
 pub fn std_key_from_tag(key: &str) -> Option<StandardTagKey> {
     ITUNES_TAG_MAP.get(key).copied()
 }
+
+/// Gapless playback information stored in the iTunes `iTunSMPB` free-form tag.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GaplessInfo {
+    /// The number of priming (delay) frames added by the encoder.
+    pub delay: u32,
+    /// The number of padding frames added by the encoder.
+    pub padding: u32,
+    /// The number of frames in the original audio, excluding the delay and padding.
+    pub num_frames: u64,
+}
+
+/// Try to parse the value of an `iTunSMPB` free-form tag.
+///
+/// The value is a string of space-separated hexadecimal fields. The first field is reserved, the
+/// second is the encoder delay, the third is the padding, and the fourth is the number of frames
+/// in the original audio. Any further fields are ignored.
+pub fn parse_itunsmpb(value: &str) -> Option<GaplessInfo> {
+    let mut fields = value.split_whitespace().skip(1);
+
+    let delay = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let padding = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let num_frames = u64::from_str_radix(fields.next()?, 16).ok()?;
+
+    Some(GaplessInfo { delay, padding, num_frames })
+}
+
+/// Sound Check normalization information stored in the iTunes `iTunNORM` free-form tag.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SoundCheck {
+    /// The gain, in decibels (dB), to apply to normalize the loudness of the track.
+    pub gain: f32,
+    /// The peak sample amplitude of the track, where 1.0 is full scale.
+    pub peak: f32,
+}
+
+/// Try to parse the value of an `iTunNORM` free-form tag.
+///
+/// The value is a string of 10 space-separated hexadecimal fields. The first two fields are the
+/// loudness of the left and right channels as a ratio to the Sound Check reference level,
+/// multiplied by 1000. The seventh and eighth fields are the peak sample amplitudes of the left
+/// and right channels, where 32768 is full scale. The remaining fields are not needed for
+/// normalization.
+///
+/// Unlike ReplayGain, which stores a gain in decibels, Sound Check stores a linear loudness
+/// ratio. The gain is therefore `-10 * log10(ratio / 1000)` dB for the louder channel.
+pub fn parse_itunnorm(value: &str) -> Option<SoundCheck> {
+    let fields = value
+        .split_whitespace()
+        .map(|field| u32::from_str_radix(field, 16).ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    if fields.len() < 10 {
+        return None;
+    }
+
+    let loudness = fields[0].max(fields[1]);
+
+    if loudness == 0 {
+        return None;
+    }
+
+    let gain = -10.0 * (f64::from(loudness) / 1000.0).log10();
+    let peak = f64::from(fields[6].max(fields[7])) / 32768.0;
+
+    Some(SoundCheck { gain: gain as f32, peak: peak as f32 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_itunnorm, parse_itunsmpb, GaplessInfo};
+
+    #[test]
+    fn verify_parse_itunsmpb() {
+        let value = " 00000000 00000840 000001C0 0000000000A6BE40 00000000 00000000 00000000 \
+                     00000000 00000000 00000000 00000000 00000000";
+
+        assert_eq!(
+            parse_itunsmpb(value),
+            Some(GaplessInfo { delay: 2112, padding: 448, num_frames: 10_927_680 })
+        );
+
+        assert_eq!(parse_itunsmpb(" 00000000 00000840"), None);
+        assert_eq!(parse_itunsmpb(" 00000000 0000084G 000001C0 0000000000A6BE40"), None);
+    }
+
+    #[test]
+    fn verify_parse_itunnorm() {
+        // The loudest channel is 4 times the reference level, and the peak is at half scale.
+        let value = " 000003E8 00000FA0 00000000 00000000 00000000 00000000 00004000 00002000 \
+                     00000000 00000000";
+
+        let sound_check = parse_itunnorm(value).unwrap();
+        assert!((sound_check.gain - -6.0206).abs() < 1e-4);
+        assert_eq!(sound_check.peak, 0.5);
+
+        // A loudness equal to the reference level needs no gain.
+        let value = " 000003E8 000003E8 00000000 00000000 00000000 00000000 00007FFF 00007FFF \
+                     00000000 00000000";
+        assert_eq!(parse_itunnorm(value).unwrap().gain, 0.0);
+
+        assert_eq!(parse_itunnorm(" 000003E8 000003E8"), None);
+        assert_eq!(parse_itunnorm(" 00000000 00000000 0 0 0 0 0 0 0 0"), None);
+    }
+}
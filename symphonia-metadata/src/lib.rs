@@ -14,9 +14,11 @@
 #![allow(clippy::identity_op)]
 #![allow(clippy::manual_range_contains)]
 
+pub mod ape;
 pub mod flac;
 pub mod id3v1;
 pub mod id3v2;
 pub mod itunes;
+pub mod replaygain;
 pub mod riff;
 pub mod vorbis;
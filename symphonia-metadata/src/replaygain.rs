@@ -0,0 +1,162 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A container-independent model of ReplayGain loudness normalization information.
+//!
+//! ReplayGain information is stored differently by each container and tagging format. Vorbis
+//! comments, APE tags, ID3v2 `TXXX` frames, and MP4 free-form atoms store the gain in decibels
+//! and the peak as a linear amplitude. Each of these is mapped to the `ReplayGain*` standard tag
+//! keys by the relevant reader. iTunes instead stores Sound Check information in the `iTunNORM`
+//! free-form atom, which is converted into an equivalent track gain and peak.
+//!
+//! # Reference levels
+//!
+//! A ReplayGain gain is relative to a reference loudness. The original ReplayGain specification
+//! uses a reference of 89 dB SPL, while ReplayGain 2.0 uses -18 LUFS. The two are approximately
+//! equivalent, so no adjustment is made between them.
+//!
+//! The reference loudness of Sound Check is not documented by Apple. The gain derived from
+//! `iTunNORM` is used as-is without any offset, and may therefore differ slightly from a
+//! ReplayGain gain calculated for the same track.
+
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+
+use crate::itunes::parse_itunnorm;
+
+/// ReplayGain loudness normalization information.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ReplayGain {
+    /// The track gain in decibels (dB).
+    pub track_gain: Option<f32>,
+    /// The track peak sample amplitude, where 1.0 is full scale.
+    pub track_peak: Option<f32>,
+    /// The album gain in decibels (dB).
+    pub album_gain: Option<f32>,
+    /// The album peak sample amplitude, where 1.0 is full scale.
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Get the ReplayGain information from a list of tags.
+    ///
+    /// Tags with a ReplayGain standard tag key take precedence. If there is no track gain, the
+    /// track gain and peak are derived from an iTunes `iTunNORM` tag, if present. Returns `None`
+    /// if no ReplayGain information was found.
+    pub fn from_tags(tags: &[Tag]) -> Option<ReplayGain> {
+        let mut rg = ReplayGain::default();
+
+        for tag in tags {
+            match tag.std_key {
+                Some(StandardTagKey::ReplayGainTrackGain) => rg.track_gain = parse_gain(&tag.value),
+                Some(StandardTagKey::ReplayGainTrackPeak) => rg.track_peak = parse_peak(&tag.value),
+                Some(StandardTagKey::ReplayGainAlbumGain) => rg.album_gain = parse_gain(&tag.value),
+                Some(StandardTagKey::ReplayGainAlbumPeak) => rg.album_peak = parse_peak(&tag.value),
+                _ => (),
+            }
+        }
+
+        if rg.track_gain.is_none() {
+            let mut itunnorm = tags.iter().filter(|tag| tag.key.ends_with("iTunNORM"));
+
+            if let Some(sound_check) = itunnorm.find_map(|tag| match &tag.value {
+                Value::String(value) => parse_itunnorm(value),
+                _ => None,
+            }) {
+                rg.track_gain = Some(sound_check.gain);
+                rg.track_peak = rg.track_peak.or(Some(sound_check.peak));
+            }
+        }
+
+        if rg == ReplayGain::default() {
+            None
+        }
+        else {
+            Some(rg)
+        }
+    }
+
+    /// Get the linear scale factor to apply to samples for the track gain, if present.
+    pub fn track_scale(&self) -> Option<f32> {
+        self.track_gain.map(gain_to_scale)
+    }
+
+    /// Get the linear scale factor to apply to samples for the album gain, if present.
+    pub fn album_scale(&self) -> Option<f32> {
+        self.album_gain.map(gain_to_scale)
+    }
+}
+
+/// Convert a gain in decibels (dB) to a linear scale factor.
+pub fn gain_to_scale(gain: f32) -> f32 {
+    10f32.powf(gain / 20.0)
+}
+
+/// Parse a gain value such as "-6.50 dB".
+fn parse_gain(value: &Value) -> Option<f32> {
+    match value {
+        Value::Float(gain) => Some(*gain as f32),
+        Value::String(value) => {
+            let value = value.trim();
+            let value = value
+                .strip_suffix("dB")
+                .or_else(|| value.strip_suffix("db"))
+                .or_else(|| value.strip_suffix("DB"))
+                .unwrap_or(value);
+
+            value.trim().parse::<f32>().ok().filter(|gain| gain.is_finite())
+        }
+        _ => None,
+    }
+}
+
+/// Parse a linear peak amplitude value such as "0.988312".
+fn parse_peak(value: &Value) -> Option<f32> {
+    match value {
+        Value::Float(peak) => Some(*peak as f32),
+        Value::String(value) => {
+            value.trim().parse::<f32>().ok().filter(|peak| peak.is_finite() && *peak >= 0.0)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::meta::{StandardTagKey, Tag, Value};
+
+    use super::{gain_to_scale, ReplayGain};
+
+    fn tag(key: &str, std_key: Option<StandardTagKey>, value: &str) -> Tag {
+        Tag::new(std_key, key, Value::from(value))
+    }
+
+    #[test]
+    fn verify_replaygain_from_tags() {
+        let tags = vec![
+            tag("REPLAYGAIN_TRACK_GAIN", Some(StandardTagKey::ReplayGainTrackGain), "-6.50 dB"),
+            tag("REPLAYGAIN_TRACK_PEAK", Some(StandardTagKey::ReplayGainTrackPeak), "0.988312"),
+            tag("REPLAYGAIN_ALBUM_GAIN", Some(StandardTagKey::ReplayGainAlbumGain), "+1.2dB"),
+            tag("com.apple.iTunes:iTunNORM", None, " 000003E8 000003E8 0 0 0 0 0 0 0 0"),
+        ];
+
+        let rg = ReplayGain::from_tags(&tags).unwrap();
+        assert_eq!(rg.track_gain, Some(-6.5));
+        assert_eq!(rg.track_peak, Some(0.988312));
+        assert_eq!(rg.album_gain, Some(1.2));
+        assert_eq!(rg.album_peak, None);
+
+        assert_eq!(ReplayGain::from_tags(&tags[3..]).unwrap().track_gain, Some(0.0));
+        assert_eq!(ReplayGain::from_tags(&[]), None);
+    }
+
+    #[test]
+    fn verify_gain_to_scale() {
+        assert_eq!(gain_to_scale(0.0), 1.0);
+        assert!((gain_to_scale(-6.0206) - 0.5).abs() < 1e-4);
+        assert!((gain_to_scale(20.0) - 10.0).abs() < 1e-4);
+    }
+}
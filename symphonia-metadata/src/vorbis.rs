@@ -14,7 +14,7 @@ use log::warn;
 
 use symphonia_core::errors::Result;
 use symphonia_core::io::{BufReader, ReadBytes};
-use symphonia_core::meta::{MetadataBuilder, StandardTagKey, Tag, Value};
+use symphonia_core::meta::{MetadataBuilder, StandardTagKey, StandardVisualKey, Tag, Value, Visual};
 
 use crate::flac;
 
@@ -61,6 +61,7 @@ lazy_static! {
         m.insert("engineer"                    , StandardTagKey::Engineer);
         m.insert("ensemble"                    , StandardTagKey::Ensemble);
         m.insert("genre"                       , StandardTagKey::Genre);
+        m.insert("initialkey"                  , StandardTagKey::InitialKey);
         m.insert("isrc"                        , StandardTagKey::IdentIsrc);
         m.insert("language"                    , StandardTagKey::Language);
         m.insert("label"                       , StandardTagKey::Label);
@@ -115,20 +116,75 @@ lazy_static! {
     };
 }
 
-/// Parse a string containing a base64 encoded FLAC picture block into a visual.
-fn parse_base64_picture_block(encoded: &str, metadata: &mut MetadataBuilder) {
-    if let Some(data) = base64_decode(encoded) {
-        if flac::read_picture_block(&mut BufReader::new(&data), metadata).is_err() {
-            warn!("invalid picture block data");
+/// The maximum length of a base64 encoded picture. Pictures exceeding this length are ignored.
+const MAX_ENCODED_PICTURE_LEN: usize = 32 * 1024 * 1024;
+
+/// The state of the comments read so far that are relevant to pictures.
+#[derive(Default)]
+struct PictureState {
+    /// True if a picture block was read.
+    has_picture_block: bool,
+    /// The base64 encoded images from the non-standard `COVERART` comments.
+    cover_art: Vec<String>,
+    /// The media types from the non-standard `COVERARTMIME` comments.
+    cover_art_media_types: Vec<String>,
+}
+
+/// Decode a base64 encoded picture if it does not exceed the maximum length.
+fn decode_base64_picture(encoded: &str) -> Option<Box<[u8]>> {
+    if encoded.len() > MAX_ENCODED_PICTURE_LEN {
+        warn!("ignoring picture exceeding {} bytes", MAX_ENCODED_PICTURE_LEN);
+        return None;
+    }
+
+    let decoded = base64_decode(encoded);
+
+    if decoded.is_none() {
+        warn!("the base64 encoding of a picture is invalid");
+    }
+
+    decoded
+}
+
+/// Parse a string containing a base64 encoded FLAC picture block into a visual. Returns true if
+/// a visual was added.
+fn parse_base64_picture_block(encoded: &str, metadata: &mut MetadataBuilder) -> bool {
+    if let Some(data) = decode_base64_picture(encoded) {
+        if flac::read_picture_block(&mut BufReader::new(&data), metadata).is_ok() {
+            return true;
         }
+
+        warn!("invalid picture block data");
     }
-    else {
-        warn!("the base64 encoding of a picture block is invalid");
+
+    false
+}
+
+/// Add the pictures from the non-standard `COVERART` and `COVERARTMIME` comments as visuals. The
+/// n-th media type belongs to the n-th picture.
+fn add_cover_art(state: PictureState, metadata: &mut MetadataBuilder) {
+    let mut media_types = state.cover_art_media_types.into_iter();
+
+    for encoded in state.cover_art.iter() {
+        // If a media type was not provided, then it is unknown.
+        let media_type = media_types.next().unwrap_or_default();
+
+        if let Some(data) = decode_base64_picture(encoded) {
+            metadata.add_visual(Visual {
+                media_type,
+                dimensions: None,
+                bits_per_pixel: None,
+                color_mode: None,
+                usage: Some(StandardVisualKey::FrontCover),
+                tags: Default::default(),
+                data,
+            });
+        }
     }
 }
 
 /// Parse the given Vorbis Comment string into a `Tag`.
-fn parse_comment(tag: &str, metadata: &mut MetadataBuilder) {
+fn parse_comment(tag: &str, metadata: &mut MetadataBuilder, pictures: &mut PictureState) {
     // Vorbis Comments (aka tags) are stored as <key>=<value> where <key> is
     // a reduced ASCII-only identifier and <value> is a UTF8 value.
     //
@@ -143,7 +199,18 @@ fn parse_comment(tag: &str, metadata: &mut MetadataBuilder) {
         // Attempt to decode it as such. If this fails in any way, treat the comment as a regular
         // tag.
         if key_lower == "metadata_block_picture" {
-            parse_base64_picture_block(value, metadata);
+            if parse_base64_picture_block(value, metadata) {
+                pictures.has_picture_block = true;
+            }
+        }
+        // The non-standard "COVERART" and "COVERARTMIME" comments are the base64 encoded image
+        // data and media type of a picture. These are only used if there are no picture blocks,
+        // and are therefore decoded after all comments are read.
+        else if key_lower == "coverart" {
+            pictures.cover_art.push(value.to_string());
+        }
+        else if key_lower == "coverartmime" {
+            pictures.cover_art_media_types.push(value.to_string());
         }
         else {
             // Attempt to assign a standardized tag key.
@@ -167,6 +234,8 @@ pub fn read_comment_no_framing<B: ReadBytes>(
     // Read the number of comments.
     let n_comments = reader.read_u32()? as usize;
 
+    let mut pictures = PictureState::default();
+
     for _ in 0..n_comments {
         // Read the comment string length in bytes.
         let comment_length = reader.read_u32()?;
@@ -176,7 +245,12 @@ pub fn read_comment_no_framing<B: ReadBytes>(
         reader.read_buf_exact(&mut comment_bytes)?;
 
         // Parse the comment string into a Tag and insert it into the parsed tag list.
-        parse_comment(&String::from_utf8_lossy(&comment_bytes), metadata);
+        parse_comment(&String::from_utf8_lossy(&comment_bytes), metadata, &mut pictures);
+    }
+
+    // The legacy cover art comments are a fallback for when there are no picture blocks.
+    if !pictures.has_picture_block {
+        add_cover_art(pictures, metadata);
     }
 
     Ok(())
@@ -273,7 +347,58 @@ fn base64_decode(encoded: &str) -> Option<Box<[u8]>> {
 
 #[cfg(test)]
 mod tests {
-    use super::base64_decode;
+    use symphonia_core::io::BufReader;
+    use symphonia_core::meta::{MetadataBuilder, StandardVisualKey};
+
+    use super::{base64_decode, read_comment_no_framing};
+
+    /// Builds a Vorbis Comment, without framing, from the given comments.
+    fn build_comment(comments: &[&str]) -> Vec<u8> {
+        let mut buf = vec![0; 4];
+        buf.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+
+        for comment in comments {
+            buf.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            buf.extend_from_slice(comment.as_bytes());
+        }
+
+        buf
+    }
+
+    /// A base64 encoded FLAC picture block of a front cover with a PNG media type.
+    const PICTURE_BLOCK: &str = "AAAAAwAAAAlpbWFnZS9wbmcAAAAAAAAAAQAAAAEAAAAYAAAAAAAAAASJUE5H";
+
+    #[test]
+    fn verify_cover_art_comments() {
+        let read = |comments: &[&str]| {
+            let mut builder = MetadataBuilder::new();
+            let buf = build_comment(comments);
+            read_comment_no_framing(&mut BufReader::new(&buf), &mut builder).unwrap();
+            builder.metadata()
+        };
+
+        // The legacy cover art comments are read if there is no picture block.
+        let rev = read(&["TITLE=a", "COVERARTMIME=image/jpeg", "COVERART=/9j/4A=="]);
+
+        assert_eq!(rev.tags().len(), 1);
+        assert_eq!(rev.visuals().len(), 1);
+        assert_eq!(rev.visuals()[0].media_type, "image/jpeg");
+        assert_eq!(rev.visuals()[0].usage, Some(StandardVisualKey::FrontCover));
+        assert_eq!(&rev.visuals()[0].data[..], &[0xff, 0xd8, 0xff, 0xe0]);
+
+        // The picture block takes precedence over the legacy cover art comments.
+        let block = format!("METADATA_BLOCK_PICTURE={}", PICTURE_BLOCK);
+        let rev = read(&["COVERART=/9j/4A==", &block]);
+
+        assert_eq!(rev.visuals().len(), 1);
+        assert_eq!(rev.visuals()[0].media_type, "image/png");
+        assert_eq!(&rev.visuals()[0].data[..], b"\x89PNG");
+
+        // Invalid pictures are ignored.
+        let rev = read(&["COVERART=!!!!", "METADATA_BLOCK_PICTURE=AAAA"]);
+
+        assert!(rev.visuals().is_empty());
+    }
 
     #[test]
     fn verify_base64_decode() {
@@ -18,7 +18,7 @@ use std::io::Write;
 use std::path::Path;
 
 use lazy_static::lazy_static;
-use symphonia::core::codecs::{DecoderOptions, FinalizeResult, CODEC_TYPE_NULL};
+use symphonia::core::codecs::{DecoderOptions, FinalizeResult, SampleSkipper, CODEC_TYPE_NULL};
 use symphonia::core::errors::{Error, Result};
 use symphonia::core::formats::{Cue, FormatOptions, FormatReader, SeekMode, SeekTo, Track};
 use symphonia::core::io::{MediaSource, MediaSourceStream, ReadOnlySource};
@@ -157,8 +157,8 @@ fn run(args: &ArgMatches) -> Result<i32> {
     let mss = MediaSourceStream::new(source, Default::default());
 
     // Use the default options for format readers other than for gapless playback.
-    let format_opts =
-        FormatOptions { enable_gapless: !args.is_present("no-gapless"), ..Default::default() };
+    let mut format_opts = FormatOptions::default();
+    format_opts.with_gapless(!args.is_present("no-gapless"));
 
     // Use the default options for metadata readers.
     let metadata_opts: MetadataOptions = Default::default();
@@ -187,11 +187,11 @@ fn run(args: &ArgMatches) -> Result<i32> {
             // Select the operating mode.
             if args.is_present("verify-only") {
                 // Verify-only mode decodes and verifies the audio, but does not play it.
-                decode_only(probed.format, &DecoderOptions { verify: true, ..Default::default() })
+                decode_only(probed.format, DecoderOptions::default().with_verify(true))
             }
             else if args.is_present("decode-only") {
                 // Decode-only mode decodes the audio, but does not play or verify it.
-                decode_only(probed.format, &DecoderOptions { verify: false, ..Default::default() })
+                decode_only(probed.format, DecoderOptions::default().with_verify(false))
             }
             else if args.is_present("probe-only") {
                 // Probe-only mode only prints information about the format, tracks, metadata, etc.
@@ -212,8 +212,8 @@ fn run(args: &ArgMatches) -> Result<i32> {
                 };
 
                 // Set the decoder options.
-                let decode_opts =
-                    DecoderOptions { verify: args.is_present("verify"), ..Default::default() };
+                let mut decode_opts = DecoderOptions::default();
+                decode_opts.with_verify(args.is_present("verify"));
 
                 // Play it!
                 play(probed.format, track, seek, &decode_opts, no_progress)
@@ -266,7 +266,7 @@ fn decode_only(mut reader: Box<dyn FormatReader>, decode_opts: &DecoderOptions)
 #[derive(Copy, Clone)]
 struct PlayTrackOptions {
     track_id: u32,
-    seek_ts: u64,
+    skipper: SampleSkipper,
 }
 
 fn play(
@@ -287,43 +287,39 @@ fn play(
         _ => return Ok(0),
     };
 
-    // If seeking, seek the reader to the time or timestamp specified and get the timestamp of the
-    // seeked position. All packets with a timestamp < the seeked position will not be played.
-    //
-    // Note: This is a half-baked approach to seeking! After seeking the reader, packets should be
-    // decoded and *samples* discarded up-to the exact *sample* indicated by required_ts. The
-    // current approach will discard excess samples if seeking to a sample within a packet.
-    let seek_ts = if let Some(seek) = seek {
+    // If seeking, seek the reader to the time or timestamp specified. The reader may seek to a
+    // position before the requested position, so the decoded samples preceding the requested
+    // position are skipped.
+    let skipper = if let Some(seek) = seek {
         let seek_to = match seek {
             SeekPosition::Time(t) => SeekTo::Time { time: Time::from(t), track_id: Some(track_id) },
             SeekPosition::Timetamp(ts) => SeekTo::TimeStamp { ts, track_id },
         };
 
-        // Attempt the seek. If the seek fails, ignore the error and return a seek timestamp of 0 so
-        // that no samples are trimmed.
+        // Attempt the seek. If the seek fails, ignore the error so that no samples are skipped.
         match reader.seek(SeekMode::Accurate, seek_to) {
-            Ok(seeked_to) => seeked_to.required_ts,
+            Ok(seeked_to) => SampleSkipper::from_seeked_to(&seeked_to),
             Err(Error::ResetRequired) => {
                 print_tracks(reader.tracks());
                 track_id = first_supported_track(reader.tracks()).unwrap().id;
-                0
+                SampleSkipper::default()
             }
             Err(err) => {
                 // Don't give-up on a seek error.
                 warn!("seek error: {}", err);
-                0
+                SampleSkipper::default()
             }
         }
     }
     else {
-        // If not seeking, the seek timestamp is 0.
-        0
+        // If not seeking, no samples are skipped.
+        SampleSkipper::default()
     };
 
     // The audio output device.
     let mut audio_output = None;
 
-    let mut track_info = PlayTrackOptions { track_id, seek_ts };
+    let mut track_info = PlayTrackOptions { track_id, skipper };
 
     let result = loop {
         match play_track(&mut reader, &mut audio_output, track_info, decode_opts, no_progress) {
@@ -337,7 +333,7 @@ fn play(
                 // Select the first supported track since the user's selected track number might no
                 // longer be valid or make sense.
                 let track_id = first_supported_track(reader.tracks()).unwrap().id;
-                track_info = PlayTrackOptions { track_id, seek_ts: 0 };
+                track_info = PlayTrackOptions { track_id, skipper: SampleSkipper::default() };
             }
             res => break res,
         }
@@ -354,7 +350,7 @@ fn play(
 fn play_track(
     reader: &mut Box<dyn FormatReader>,
     audio_output: &mut Option<Box<dyn output::AudioOutput>>,
-    play_opts: PlayTrackOptions,
+    mut play_opts: PlayTrackOptions,
     decode_opts: &DecoderOptions,
     no_progress: bool,
 ) -> Result<i32> {
@@ -367,6 +363,16 @@ fn play_track(
     // Create a decoder for the track.
     let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, decode_opts)?;
 
+    // If the decoder knows the signal specification and capacity of the decoded audio before
+    // decoding, open the audio output now to reduce start-up latency.
+    if audio_output.is_none() {
+        if let (Some(spec), Some(duration)) =
+            (decoder.output_spec(), decoder.max_frames_per_packet())
+        {
+            audio_output.replace(output::try_open(spec, duration).unwrap());
+        }
+    }
+
     // Get the selected track's timebase and duration.
     let tb = track.codec_params.time_base;
     let dur = track.codec_params.n_frames.map(|frames| track.codec_params.start_ts + frames);
@@ -414,9 +420,9 @@ fn play_track(
                     // TODO: Check the audio spec. and duration hasn't changed.
                 }
 
-                // Write the decoded audio samples to the audio output if the presentation timestamp
-                // for the packet is >= the seeked position (0 if not seeking).
-                if packet.ts() >= play_opts.seek_ts {
+                // Write the decoded audio samples to the audio output, skipping the samples before
+                // the seeked position (none if not seeking).
+                if let Some(decoded) = play_opts.skipper.apply(decoded) {
                     if !no_progress {
                         print_progress(packet.ts(), dur, tb);
                     }
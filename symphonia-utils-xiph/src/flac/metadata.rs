@@ -11,7 +11,8 @@ use symphonia_core::audio::Channels;
 use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::formats::{util::SeekIndex, Cue, CuePoint};
 use symphonia_core::io::*;
-use symphonia_core::meta::{StandardTagKey, Tag, Value, VendorData};
+use symphonia_core::meta::{MetadataBuilder, StandardTagKey, Tag, Value, VendorData};
+use symphonia_metadata::riff;
 
 #[derive(PartialEq, Eq)]
 pub enum MetadataBlockType {
@@ -25,7 +26,8 @@ pub enum MetadataBlockType {
     Unknown(u8),
 }
 
-fn flac_channels_to_channels(channels: u32) -> Channels {
+/// Get the default channel layout of a FLAC stream with the given number of channels (1 to 8).
+pub fn flac_channels_to_channels(channels: u32) -> Channels {
     debug_assert!(channels > 0 && channels < 9);
 
     match channels {
@@ -146,10 +148,11 @@ impl StreamInfo {
 
         let mut br = BitStreamLtr::new(reader);
 
-        // Read sample rate, valid rates are [1, 655350] Hz.
+        // Read sample rate, valid rates are [1, 1048575] Hz. Older revisions of the specification
+        // limited the sample rate to 655350 Hz, but the full range of the 20-bit field is valid.
         info.sample_rate = br.read_bits_leq32(20)?;
 
-        if info.sample_rate < 1 || info.sample_rate > 655_350 {
+        if info.sample_rate < 1 {
             return decode_error("flac: stream sample rate out of bounds");
         }
 
@@ -238,7 +241,7 @@ fn printable_ascii_to_string(bytes: &[u8]) -> Option<String> {
     Some(result)
 }
 
-/// Read a cuesheet block.
+/// Read a cuesheet block. Each track, except the lead-out track, is added as a cue.
 pub fn read_cuesheet_block<B: ReadBytes>(reader: &mut B, cues: &mut Vec<Cue>) -> Result<()> {
     // Read cuesheet catalog number. The catalog number only allows printable ASCII characters.
     let mut catalog_number_buf = vec![0u8; 128];
@@ -279,18 +282,21 @@ pub fn read_cuesheet_block<B: ReadBytes>(reader: &mut B, cues: &mut Vec<Cue>) ->
         return decode_error("flac: cuesheets for CD-DA must not have more than 100 tracks");
     }
 
+    // The lead-out track marks the end of the audio, and is not a cue.
+    let lead_out = if is_cdda { 170 } else { 255 };
+
     for _ in 0..n_tracks {
-        read_cuesheet_track(reader, is_cdda, cues)?;
+        let cue = read_cuesheet_track(reader, is_cdda)?;
+
+        if cue.index != lead_out {
+            cues.push(cue);
+        }
     }
 
     Ok(())
 }
 
-fn read_cuesheet_track<B: ReadBytes>(
-    reader: &mut B,
-    is_cdda: bool,
-    cues: &mut Vec<Cue>,
-) -> Result<()> {
+fn read_cuesheet_track<B: ReadBytes>(reader: &mut B, is_cdda: bool) -> Result<Cue> {
     let n_offset_samples = reader.read_be_u64()?;
 
     // For a CD-DA cuesheet, the track sample offset is the same as the first index (INDEX 00 or
@@ -362,9 +368,7 @@ fn read_cuesheet_track<B: ReadBytes>(
         cue.points.push(read_cuesheet_track_index(reader, is_cdda)?);
     }
 
-    cues.push(cue);
-
-    Ok(())
+    Ok(cue)
 }
 
 fn read_cuesheet_track_index<B: ReadBytes>(reader: &mut B, is_cdda: bool) -> Result<CuePoint> {
@@ -394,6 +398,10 @@ pub fn read_application_block<B: ReadBytes>(
     reader: &mut B,
     block_length: u32,
 ) -> Result<VendorData> {
+    if block_length < 4 {
+        return decode_error("flac: application block is too short");
+    }
+
     // Read the application identifier. Usually this is just 4 ASCII characters, but it is not
     // limited to that. Non-printable ASCII characters must be escaped to create a valid UTF8
     // string.
@@ -407,6 +415,97 @@ pub fn read_application_block<B: ReadBytes>(
     Ok(VendorData { ident, data })
 }
 
+/// Read the foreign RIFF (WAVE) or AIFF chunks preserved in an application block by the reference
+/// encoder's `--keep-foreign-metadata` option, and add any tags they contain to the metadata.
+///
+/// Tags are read from RIFF INFO list chunks, and AIFF text chunks. Application blocks that do not
+/// contain foreign metadata are ignored.
+pub fn read_foreign_metadata(vendor_data: &VendorData, metadata: &mut MetadataBuilder) {
+    let is_aiff = match vendor_data.ident.as_str() {
+        "riff" => false,
+        "aiff" => true,
+        _ => return,
+    };
+
+    let mut buf = &vendor_data.data[..];
+
+    // An application block contains one or more complete chunks. The first block also contains
+    // the header of the RIFF or FORM chunk, and the last block may contain the header of the
+    // audio data chunk without its body.
+    while let Some((id, len)) = read_chunk_header(buf, is_aiff) {
+        // The header of the RIFF or FORM chunk is followed by the form type instead of a body.
+        if &id == b"RIFF" || &id == b"FORM" {
+            buf = &buf[12.min(buf.len())..];
+            continue;
+        }
+
+        // Only the header of the audio data chunk is preserved.
+        let body = match buf[8..].get(..len) {
+            Some(body) => body,
+            _ => break,
+        };
+
+        if is_aiff {
+            read_aiff_text_chunk(id, body, metadata);
+        }
+        else if &id == b"LIST" && body.starts_with(b"INFO") {
+            read_riff_info_list(&body[4..], metadata);
+        }
+
+        // Skip the chunk, and the padding byte if present.
+        buf = &buf[8 + len..];
+        buf = &buf[(len & 1).min(buf.len())..];
+    }
+}
+
+/// Read the identifier and length of the chunk at the start of the buffer. Chunks are padded to an
+/// even length.
+fn read_chunk_header(buf: &[u8], is_big_endian: bool) -> Option<([u8; 4], usize)> {
+    if buf.len() < 8 {
+        return None;
+    }
+
+    let len = [buf[4], buf[5], buf[6], buf[7]];
+
+    let len = if is_big_endian { u32::from_be_bytes(len) } else { u32::from_le_bytes(len) };
+
+    Some(([buf[0], buf[1], buf[2], buf[3]], len as usize))
+}
+
+/// Read the sub-chunks of a RIFF INFO list chunk as tags.
+fn read_riff_info_list(mut buf: &[u8], metadata: &mut MetadataBuilder) {
+    while let Some((id, len)) = read_chunk_header(buf, false) {
+        let value = match buf[8..].get(..len) {
+            Some(value) => value,
+            _ => break,
+        };
+
+        // Strings in INFO chunks are null-terminated.
+        let end = value.iter().position(|&b| b == 0).unwrap_or(value.len());
+
+        metadata.add_tag(riff::parse(id, &value[..end]));
+
+        // Skip the chunk, and the padding byte if present.
+        buf = &buf[8 + len..];
+        buf = &buf[(len & 1).min(buf.len())..];
+    }
+}
+
+/// Read an AIFF text chunk as a tag. Other chunks are ignored.
+fn read_aiff_text_chunk(id: [u8; 4], buf: &[u8], metadata: &mut MetadataBuilder) {
+    let std_key = match &id {
+        b"NAME" => StandardTagKey::TrackTitle,
+        b"AUTH" => StandardTagKey::Artist,
+        b"(c) " => StandardTagKey::Copyright,
+        b"ANNO" => StandardTagKey::Comment,
+        _ => return,
+    };
+
+    let value = String::from_utf8_lossy(buf);
+
+    metadata.add_tag(Tag::new(Some(std_key), &String::from_utf8_lossy(&id), Value::from(value)));
+}
+
 pub use symphonia_metadata::flac::read_comment_block;
 pub use symphonia_metadata::flac::read_picture_block;
 
@@ -7,7 +7,8 @@
 
 use symphonia_core::audio::Channels;
 
-/// Get the mapping 0 channel listing for the given number of channels.
+/// Get the mapping 0 channel listing for the given number of channels. Streams with more than 8
+/// channels use discrete channels.
 pub fn vorbis_channels_to_channels(num_channels: u8) -> Option<Channels> {
     let channels = match num_channels {
         1 => Channels::FRONT_LEFT,
@@ -53,7 +54,8 @@ pub fn vorbis_channels_to_channels(num_channels: u8) -> Option<Channels> {
                 | Channels::REAR_RIGHT
                 | Channels::LFE1
         }
-        _ => return None,
+        // The channel mapping of more than 8 channels is application defined.
+        _ => return Channels::discrete(usize::from(num_channels)),
     };
 
     Some(channels)
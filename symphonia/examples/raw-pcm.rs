@@ -0,0 +1,73 @@
+use std::env;
+use std::fs::File;
+use std::path::Path;
+
+use symphonia::core::audio::{Channels, SampleBuffer};
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_PCM_S16LE};
+use symphonia::core::errors::Error;
+use symphonia::core::formats::FormatReader;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::default::formats::RawPcmReader;
+
+fn main() {
+    // Get command line arguments.
+    let args: Vec<String> = env::args().collect();
+
+    // Create a media source for the raw PCM file.
+    let file = Box::new(File::open(Path::new(&args[1])).unwrap());
+
+    // Create the media source stream using the boxed media source from above.
+    let mss = MediaSourceStream::new(file, Default::default());
+
+    // Raw PCM has no header, therefore the format of the audio must be provided. In this example,
+    // the file is assumed to contain 44.1kHz stereo audio stored as signed 16-bit little-endian
+    // interleaved samples.
+    let mut params = CodecParameters::new();
+
+    params
+        .for_codec(CODEC_TYPE_PCM_S16LE)
+        .with_sample_rate(44_100)
+        .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+    // Instantiate the raw PCM reader directly since raw PCM cannot be probed.
+    let mut format = RawPcmReader::try_new_with_params(mss, &params).unwrap();
+
+    // Get the one and only track.
+    let track = format.default_track().unwrap();
+
+    // Create a decoder for the track.
+    let decoder_opts: DecoderOptions = Default::default();
+
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts).unwrap();
+
+    let mut sample_count = 0;
+    let mut sample_buf = None;
+
+    // Get the next packet from the format reader until the end of the stream.
+    while let Ok(packet) = format.next_packet() {
+        // Decode the packet into audio samples, ignoring any decode errors.
+        match decoder.decode(&packet) {
+            Ok(audio_buf) => {
+                // If this is the *first* decoded packet, create a sample buffer matching the
+                // decoded audio buffer format.
+                if sample_buf.is_none() {
+                    let spec = *audio_buf.spec();
+                    let duration = audio_buf.capacity() as u64;
+
+                    sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
+                }
+
+                // Copy the decoded audio buffer into the sample buffer in an interleaved format.
+                if let Some(buf) = &mut sample_buf {
+                    buf.copy_interleaved_ref(audio_buf);
+
+                    sample_count += buf.samples().len();
+                    print!("\rDecoded {} samples", sample_count);
+                }
+            }
+            Err(Error::DecodeError(_)) => (),
+            Err(_) => break,
+        }
+    }
+}
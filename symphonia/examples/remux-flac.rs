@@ -0,0 +1,243 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use symphonia::core::codecs::CODEC_TYPE_FLAC;
+use symphonia::core::errors::{Error, Result};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Copy the packets of the first FLAC track of the input file, in any container, into a native FLAC
+/// file without re-encoding them. Returns the number of packets copied.
+fn remux(input: &Path, output: &Path) -> Result<u64> {
+    let file = Box::new(File::open(input)?);
+    let mss = MediaSourceStream::new(file, Default::default());
+
+    // Use the file extension to help the probe guess the format.
+    let mut hint = Hint::new();
+
+    if let Some(extension) = input.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    let mut format = probed.format;
+
+    // The codec type and extra data of a track are all that is required to write the header of
+    // the new container. For FLAC, the extra data is the stream information block.
+    let track = format.tracks().iter().find(|track| track.codec_params.codec == CODEC_TYPE_FLAC);
+
+    let track = match track {
+        Some(track) => track,
+        None => return Err(Error::Unsupported("no flac track")),
+    };
+
+    let stream_info = match &track.codec_params.extra_data {
+        Some(extra_data) => extra_data.clone(),
+        None => return Err(Error::Unsupported("flac track has no stream information")),
+    };
+
+    let track_id = track.id;
+
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    // The stream marker, followed by the stream information block as the last metadata block.
+    writer.write_all(b"fLaC")?;
+    writer.write_all(&[0x80])?;
+    writer.write_all(&(stream_info.len() as u32).to_be_bytes()[1..])?;
+    writer.write_all(&stream_info)?;
+
+    let mut n_packets = 0;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        // Packets of all tracks are interleaved. Only copy the packets of the FLAC track.
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        // A FLAC packet is exactly one FLAC frame, and the frames of a native FLAC stream are not
+        // framed any further. Therefore, the packet data is written as-is.
+        writer.write_all(packet.buf())?;
+        n_packets += 1;
+    }
+
+    writer.flush()?;
+    Ok(n_packets)
+}
+
+fn main() {
+    // Get command line arguments.
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() != 3 {
+        eprintln!("Usage: remux-flac INPUT OUTPUT");
+        std::process::exit(2);
+    }
+
+    match remux(Path::new(&args[1]), Path::new(&args[2])) {
+        Ok(n_packets) => println!("Wrote {} packets to {}", n_packets, args[2]),
+        Err(err) => {
+            eprintln!("remux failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use symphonia::core::audio::{AudioBufferRef, Signal};
+    use symphonia::core::checksum::{Crc16Ansi, Crc32, Crc8Ccitt};
+    use symphonia::core::codecs::{Decoder, CODEC_TYPE_FLAC};
+    use symphonia::core::formats::{FormatOptions, FormatReader, Packet};
+    use symphonia::core::io::{MediaSourceStream, Monitor};
+    use symphonia::default::codecs::FlacDecoder;
+    use symphonia::default::formats::{FlacReader, OggReader};
+
+    use super::remux;
+
+    const N_FRAMES: u8 = 4;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("symphonia-remux-{}-{}", std::process::id(), name))
+    }
+
+    /// Builds a mono 16-bit FLAC frame of 192 samples with a constant subframe.
+    fn build_frame(frame_num: u8, value: u16) -> Vec<u8> {
+        let mut frame = vec![0xff, 0xf8, 0x19, 0x08, frame_num];
+
+        let mut crc8 = Crc8Ccitt::new(0);
+        crc8.process_buf_bytes(&frame);
+        frame.push(crc8.crc());
+
+        frame.push(0x00);
+        frame.extend_from_slice(&value.to_be_bytes());
+
+        let mut crc16 = Crc16Ansi::new(0);
+        crc16.process_buf_bytes(&frame);
+        frame.extend_from_slice(&crc16.crc().to_be_bytes());
+
+        frame
+    }
+
+    /// Builds an OGG page containing complete packets only.
+    fn build_page(flags: u8, absgp: u64, sequence: u32, packets: &[&[u8]]) -> Vec<u8> {
+        let mut page = b"OggS\x00".to_vec();
+        page.push(flags);
+        page.extend_from_slice(&absgp.to_le_bytes());
+        page.extend_from_slice(&1u32.to_le_bytes());
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(packets.len() as u8);
+        page.extend(packets.iter().map(|packet| packet.len() as u8));
+
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+
+        let mut crc32 = Crc32::new(0);
+        crc32.process_buf_bytes(&page);
+        page[22..26].copy_from_slice(&crc32.crc().to_le_bytes());
+
+        page
+    }
+
+    /// Builds an OGG FLAC stream of `N_FRAMES` frames sampled at 44.1 kHz.
+    fn build_ogg_flac() -> Vec<u8> {
+        // Block length 192, unknown frame sizes, 44.1 kHz, 1 channel, 16-bit, and the total number
+        // of samples.
+        let mut stream_info =
+            vec![0x00, 0xc0, 0x00, 0xc0, 0, 0, 0, 0, 0, 0, 0x0a, 0xc4, 0x40, 0xf0];
+        stream_info.extend_from_slice(&(192 * u32::from(N_FRAMES)).to_be_bytes());
+        stream_info.extend_from_slice(&[0; 16]);
+
+        let mut ident = vec![0x7f];
+        ident.extend_from_slice(b"FLAC\x01\x00\x00\x01fLaC\x00\x00\x00\x22");
+        ident.extend_from_slice(&stream_info);
+
+        // An empty Vorbis comment block.
+        let comment = [0x84, 0x00, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        let frames: Vec<Vec<u8>> =
+            (0..N_FRAMES).map(|i| build_frame(i, 100 * u16::from(i))).collect();
+        let frames: Vec<&[u8]> = frames.iter().map(|frame| frame.as_slice()).collect();
+
+        [
+            build_page(0x02, 0, 0, &[&ident]),
+            build_page(0x00, 0, 1, &[&comment]),
+            build_page(0x04, 192 * u64::from(N_FRAMES), 2, &frames),
+        ]
+        .concat()
+    }
+
+    fn read_packets(reader: &mut dyn FormatReader) -> Vec<Packet> {
+        let mut packets = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            packets.push(packet);
+        }
+
+        packets
+    }
+
+    fn open(path: &Path) -> MediaSourceStream {
+        let file = Box::new(std::fs::File::open(path).unwrap());
+        MediaSourceStream::new(file, Default::default())
+    }
+
+    #[test]
+    fn verify_remux_ogg_flac() {
+        let input = temp_path("input.ogg");
+        let output = temp_path("output.flac");
+
+        std::fs::write(&input, build_ogg_flac()).unwrap();
+
+        assert_eq!(remux(&input, &output).unwrap(), u64::from(N_FRAMES));
+
+        let mut ogg = OggReader::try_new(open(&input), &FormatOptions::default()).unwrap();
+        let mut flac = FlacReader::try_new(open(&output), &FormatOptions::default()).unwrap();
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+
+        // The remuxed stream has the same codec parameters.
+        let params = &flac.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_FLAC);
+        assert_eq!(params.extra_data, ogg.tracks()[0].codec_params.extra_data);
+        assert_eq!(params.n_frames, Some(192 * u64::from(N_FRAMES)));
+
+        let mut decoder = FlacDecoder::try_new(params, &Default::default()).unwrap();
+
+        // The remuxed stream has the same packets.
+        let expected = read_packets(&mut ogg);
+        let actual = read_packets(&mut flac);
+
+        assert_eq!(actual.len(), expected.len());
+
+        for (i, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+            assert_eq!((actual.ts(), actual.dur()), (expected.ts(), expected.dur()));
+            assert_eq!(actual.buf(), expected.buf());
+
+            match decoder.decode(actual).unwrap() {
+                AudioBufferRef::S32(buf) => assert_eq!(buf.chan(0)[0], (100 * i as i32) << 16),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
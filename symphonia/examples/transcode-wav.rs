@@ -0,0 +1,425 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, Write};
+use std::path::Path;
+
+use symphonia::core::audio::{
+    AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, DownmixOptions, Signal,
+};
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::conv::dither::{Dither, DitherType, Identity, Rectangular, Triangular};
+use symphonia::core::dsp::resample::Resampler;
+use symphonia::core::errors::{Error, Result};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::sample::{i24, Sample};
+use symphonia::default::formats::{WavSampleFormat, WavWriter};
+
+/// The transcoding options.
+struct Options {
+    format: WavSampleFormat,
+    dither: DitherType,
+    downmix: bool,
+    rate: Option<u32>,
+}
+
+/// A function that dithers a 32-bit sample before it is converted to a lower bit depth.
+type DitherFn = Box<dyn FnMut(i32) -> i32>;
+
+fn new_dither_fn<T: Sample + 'static>(dither: &DitherType) -> DitherFn {
+    let mut dither: Box<dyn Dither<i32, T>> = match dither {
+        DitherType::Identity => Box::new(Identity::new()),
+        DitherType::Rectangular => Box::new(Rectangular::new()),
+        DitherType::Triangular => Box::new(Triangular::new()),
+    };
+
+    Box::new(move |sample| dither.dither(sample))
+}
+
+/// A WAV writer that dithers the audio if the output sample format has a lower bit depth than 32
+/// bits.
+struct DitheredWriter<W: Write + Seek> {
+    writer: WavWriter<W>,
+    dither: Option<DitherFn>,
+    n_frames: u64,
+}
+
+impl<W: Write + Seek> DitheredWriter<W> {
+    fn new(writer: WavWriter<W>, dither: &DitherType) -> Self {
+        let dither = match writer.format() {
+            WavSampleFormat::S16 => Some(new_dither_fn::<i16>(dither)),
+            WavSampleFormat::S24 => Some(new_dither_fn::<i24>(dither)),
+            _ => None,
+        };
+
+        DitheredWriter { writer, dither, n_frames: 0 }
+    }
+
+    fn write(&mut self, buf: AudioBufferRef<'_>) -> Result<()> {
+        self.n_frames += buf.frames() as u64;
+
+        match self.dither.as_mut() {
+            Some(dither) => {
+                // Dither the audio as 32-bit samples. The writer then truncates the samples to the
+                // output sample format.
+                let mut dithered = AudioBuffer::<i32>::new(buf.capacity() as u64, *buf.spec());
+                buf.convert(&mut dithered);
+
+                for c in 0..dithered.spec().channels.count() {
+                    for sample in dithered.chan_mut(c) {
+                        *sample = dither(*sample);
+                    }
+                }
+
+                self.writer.write(dithered.as_audio_buffer_ref())
+            }
+            None => self.writer.write(buf),
+        }
+    }
+}
+
+/// Decode the default track of the input file, and write it to a WAV file. Returns the number of
+/// frames written.
+fn transcode(input: &Path, output: &Path, options: &Options) -> Result<u64> {
+    let file = Box::new(File::open(input)?);
+    let mss = MediaSourceStream::new(file, Default::default());
+
+    // Use the file extension to help the probe guess the format.
+    let mut hint = Hint::new();
+
+    if let Some(extension) = input.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    let mut format = probed.format;
+
+    let track = match format.default_track() {
+        Some(track) => track,
+        None => return Err(Error::Unsupported("no default track")),
+    };
+
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let track_id = track.id;
+
+    // The writer and resampler are created once the first packet is decoded and the signal
+    // specification is known.
+    let mut writer = None;
+    let mut resampler = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let audio_buf = match decoder.decode(&packet) {
+            Ok(audio_buf) => audio_buf,
+            Err(Error::DecodeError(err)) => {
+                // Decode errors are not fatal. Skip the packet.
+                eprintln!("decode error: {}", err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Downmix to mono using the ITU-R BS.775 coefficients.
+        let audio_buf = if options.downmix && audio_buf.spec().channels.count() > 1 {
+            audio_buf.downmix(Channels::FRONT_CENTRE, &DownmixOptions::default())?
+        }
+        else {
+            audio_buf
+        };
+
+        let mut spec = *audio_buf.spec();
+
+        if writer.is_none() {
+            if let Some(rate) = options.rate.filter(|&rate| rate != spec.rate) {
+                resampler = Some(Resampler::new(spec.rate, rate, spec.channels));
+                spec.rate = rate;
+            }
+
+            let file = BufWriter::new(File::create(output)?);
+
+            writer = Some(DitheredWriter::new(
+                WavWriter::new(file, spec, options.format)?,
+                &options.dither,
+            ));
+        }
+
+        let writer = writer.as_mut().unwrap();
+
+        match resampler.as_mut() {
+            Some(resampler) => writer.write(resampler.process(&audio_buf).as_audio_buffer_ref())?,
+            None => writer.write(audio_buf)?,
+        }
+    }
+
+    let mut writer = match writer {
+        Some(writer) => writer,
+        None => return Err(Error::DecodeError("no audio was decoded")),
+    };
+
+    // Write the audio retained by the resampler.
+    if let Some(resampler) = resampler.as_mut() {
+        writer.write(resampler.flush().as_audio_buffer_ref())?;
+    }
+
+    writer.writer.finalize()?;
+
+    Ok(writer.n_frames)
+}
+
+fn main() {
+    // Get command line arguments.
+    let args: Vec<String> = env::args().collect();
+
+    let mut paths = Vec::new();
+
+    let mut options = Options {
+        format: WavSampleFormat::S16,
+        dither: DitherType::Triangular,
+        downmix: false,
+        rate: None,
+    };
+
+    let mut iter = args.iter().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--bits" => {
+                options.format = match iter.next().map(|value| value.as_str()) {
+                    Some("16") => WavSampleFormat::S16,
+                    Some("24") => WavSampleFormat::S24,
+                    Some("32") => WavSampleFormat::S32,
+                    Some("f32") => WavSampleFormat::F32,
+                    _ => return usage(),
+                }
+            }
+            "--dither" => {
+                options.dither = match iter.next().map(|value| value.as_str()) {
+                    Some("none") => DitherType::Identity,
+                    Some("rect") => DitherType::Rectangular,
+                    Some("tri") => DitherType::Triangular,
+                    _ => return usage(),
+                }
+            }
+            "--rate" => {
+                options.rate = match iter.next().and_then(|value| value.parse().ok()) {
+                    Some(rate) if rate > 0 => Some(rate),
+                    _ => return usage(),
+                }
+            }
+            "--mono" => options.downmix = true,
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.len() != 2 {
+        return usage();
+    }
+
+    match transcode(Path::new(paths[0]), Path::new(paths[1]), &options) {
+        Ok(n_frames) => println!("Wrote {} frames to {}", n_frames, paths[1]),
+        Err(err) => {
+            eprintln!("transcode failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn usage() {
+    eprintln!(
+        "Usage: transcode-wav [--bits 16|24|32|f32] [--dither none|rect|tri] [--rate RATE] \
+         [--mono] INPUT OUTPUT"
+    );
+    std::process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, Channels, SampleBuffer};
+    use symphonia::core::audio::{Signal, SignalSpec};
+    use symphonia::core::conv::dither::DitherType;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::default::formats::{WavSampleFormat, WavWriter};
+
+    use super::{transcode, Options};
+
+    const N_FRAMES: usize = 2000;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("symphonia-transcode-{}-{}", std::process::id(), name))
+    }
+
+    /// Write a 32-bit stereo 44.1 kHz WAV file, with each sample of each channel generated by the
+    /// function provided.
+    fn write_source<F: Fn(usize, usize) -> i32>(path: &Path, f: F) {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(N_FRAMES as u64, spec);
+        buf.render_reserved(Some(N_FRAMES));
+
+        for c in 0..2 {
+            for (i, sample) in buf.chan_mut(c).iter_mut().enumerate() {
+                *sample = f(c, i);
+            }
+        }
+
+        let file = std::fs::File::create(path).unwrap();
+
+        let mut writer = WavWriter::new(file, spec, WavSampleFormat::S32).unwrap();
+        writer.write(buf.as_audio_buffer_ref()).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    /// A pseudo-random test signal.
+    fn noise(c: usize, i: usize) -> i32 {
+        ((2 * i + c) as u32).wrapping_mul(0x9e37_79b9) as i32 >> 1
+    }
+
+    /// Decode a file into interleaved f64 samples, returning the signal specification.
+    fn decode(path: &Path) -> (SignalSpec, Vec<f64>) {
+        let file = Box::new(std::fs::File::open(path).unwrap());
+        let mss = MediaSourceStream::new(file, Default::default());
+
+        let probed = symphonia::default::get_probe()
+            .format(&Default::default(), mss, &FormatOptions::default(), &Default::default())
+            .unwrap();
+
+        let mut format = probed.format;
+        let params = format.default_track().unwrap().codec_params.clone();
+        let mut decoder =
+            symphonia::default::get_codecs().make(&params, &Default::default()).unwrap();
+
+        let mut samples = Vec::new();
+
+        while let Ok(packet) = format.next_packet() {
+            let audio_buf = decoder.decode(&packet).unwrap();
+
+            let mut buf = SampleBuffer::<f32>::new(audio_buf.capacity() as u64, *audio_buf.spec());
+            buf.copy_interleaved_ref(audio_buf);
+
+            samples.extend(buf.samples().iter().map(|&sample| f64::from(sample)));
+        }
+
+        let spec = SignalSpec::new(params.sample_rate.unwrap(), params.channels.unwrap());
+
+        (spec, samples)
+    }
+
+    fn transcode_source<F>(name: &str, f: F, options: Options) -> (SignalSpec, Vec<f64>, Vec<f64>)
+    where
+        F: Fn(usize, usize) -> i32,
+    {
+        let source = temp_path(&format!("{}-source.wav", name));
+        let output = temp_path(&format!("{}-output.wav", name));
+
+        write_source(&source, f);
+
+        let n_frames = transcode(&source, &output, &options).unwrap();
+
+        let (_, expected) = decode(&source);
+        let (spec, actual) = decode(&output);
+
+        std::fs::remove_file(source).unwrap();
+        std::fs::remove_file(output).unwrap();
+
+        assert_eq!(n_frames as usize * spec.channels.count(), actual.len());
+
+        (spec, expected, actual)
+    }
+
+    fn max_delta(expected: &[f64], actual: &[f64]) -> f64 {
+        assert_eq!(expected.len(), actual.len());
+        expected.iter().zip(actual).map(|(e, a)| (e - a).abs()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn verify_round_trip() {
+        let cases = [
+            ("s16", WavSampleFormat::S16, DitherType::Identity, 1.0 / 32768.0),
+            ("s16-tri", WavSampleFormat::S16, DitherType::Triangular, 2.0 / 32768.0),
+            ("s24-rect", WavSampleFormat::S24, DitherType::Rectangular, 2.0 / 8388608.0),
+            ("s32", WavSampleFormat::S32, DitherType::Identity, 1e-7),
+            ("f32", WavSampleFormat::F32, DitherType::Identity, 1e-7),
+        ];
+
+        for (name, format, dither, tolerance) in cases {
+            let options = Options { format, dither, downmix: false, rate: None };
+
+            let (spec, expected, actual) = transcode_source(name, noise, options);
+
+            assert_eq!(spec.channels.count(), 2);
+            assert!(max_delta(&expected, &actual) <= tolerance, "{}", name);
+        }
+    }
+
+    #[test]
+    fn verify_round_trip_downmix() {
+        let options = Options {
+            format: WavSampleFormat::S32,
+            dither: DitherType::Identity,
+            downmix: true,
+            rate: None,
+        };
+
+        let (spec, expected, actual) = transcode_source("mono", noise, options);
+
+        // Both front channels are mixed into the centre channel at -3 dB.
+        let expected: Vec<f64> = expected
+            .chunks_exact(2)
+            .map(|frame| (frame[0] + frame[1]) * std::f64::consts::FRAC_1_SQRT_2)
+            .collect();
+
+        assert_eq!(spec.channels, Channels::FRONT_CENTRE);
+        assert!(max_delta(&expected, &actual) <= 1e-7);
+    }
+
+    #[test]
+    fn verify_resample() {
+        let options = Options {
+            format: WavSampleFormat::F32,
+            dither: DitherType::Identity,
+            downmix: false,
+            rate: Some(22050),
+        };
+
+        // A 1 kHz sine, well below the Nyquist frequency of the output.
+        let sine =
+            |rate: f64, i: usize| (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / rate).sin();
+
+        let source = |_, i| (sine(44100.0, i) * 0.5 * f64::from(i32::MAX)) as i32;
+
+        let (spec, _, actual) = transcode_source("resample", source, options);
+
+        assert_eq!(spec.rate, 22050);
+        assert_eq!(actual.len(), N_FRAMES);
+
+        // Ignore the start and end of the output, where the filter window extends beyond the
+        // audio.
+        for (i, frame) in actual.chunks_exact(2).enumerate().skip(100).take(N_FRAMES / 2 - 200) {
+            let expected = 0.5 * sine(22050.0, i);
+            assert!((frame[0] - expected).abs() < 1e-3, "{}", i);
+            assert_eq!(frame[0], frame[1]);
+        }
+    }
+}
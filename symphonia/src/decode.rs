@@ -0,0 +1,369 @@
+// Symphonia
+// Copyright (c) 2019-2024 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `decode` module provides an iterator over the decoded audio of a track that handles the
+//! demux and decode loop, and recovers from decode errors.
+
+use std::borrow::Cow;
+use std::io;
+
+use symphonia_core::audio::{AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::codecs::{Decoder, DecoderOptions};
+use symphonia_core::errors::{unsupported_error, Error, Result};
+use symphonia_core::formats::FormatReader;
+use symphonia_core::sample::Sample;
+
+use crate::default::get_codecs;
+
+/// Instantiate an iterator over the decoded audio of the track with the given track ID, using a
+/// decoder from the default `CodecRegistry`.
+///
+/// See [`DecodeFrames`] for how errors are handled.
+pub fn decode_frames(reader: Box<dyn FormatReader>, track_id: u32) -> Result<DecodeFrames> {
+    let track = match reader.track(track_id) {
+        Some(track) => track,
+        _ => return unsupported_error("symphonia: track not found"),
+    };
+
+    let decoder = get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    Ok(DecodeFrames::new(reader, decoder, track_id))
+}
+
+/// `DecodeFrames` is an iterator over the decoded audio of a single track.
+///
+/// Packets are read from the `FormatReader`, packets belonging to other tracks are discarded, and
+/// the remaining packets are decoded. Each decoded audio buffer is returned as an owned
+/// `AudioBufferRef` so that it may be retained after the next packet is decoded.
+///
+/// A `DecodeError` is not fatal. The error is returned, and the next call to `next` continues with
+/// the next packet. Optionally, the undecodeable packet can be replaced with silence of the same
+/// duration, which is returned after the error. All other errors are fatal and end the iterator
+/// after being returned. The end of the stream ends the iterator without returning an error.
+pub struct DecodeFrames {
+    reader: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    insert_silence: bool,
+    silence: Option<AudioBufferRef<'static>>,
+    is_done: bool,
+}
+
+impl DecodeFrames {
+    /// Instantiate an iterator over the decoded audio of the track with the given track ID using
+    /// the provided decoder.
+    pub fn new(reader: Box<dyn FormatReader>, decoder: Box<dyn Decoder>, track_id: u32) -> Self {
+        DecodeFrames {
+            reader,
+            decoder,
+            track_id,
+            insert_silence: false,
+            silence: None,
+            is_done: false,
+        }
+    }
+
+    /// If enabled, return silence, with the duration of the packet, after a decode error. The
+    /// duration is limited to the maximum number of frames per packet of the decoder. Default:
+    /// `false`.
+    pub fn with_silence(mut self, enable: bool) -> Self {
+        self.insert_silence = enable;
+        self
+    }
+
+    /// Gets a mutable reference to the `FormatReader`.
+    pub fn reader(&mut self) -> &mut dyn FormatReader {
+        self.reader.as_mut()
+    }
+
+    /// Gets a mutable reference to the `Decoder`.
+    pub fn decoder(&mut self) -> &mut dyn Decoder {
+        self.decoder.as_mut()
+    }
+
+    /// Returns the `FormatReader` and `Decoder`, consuming the iterator.
+    pub fn into_inner(self) -> (Box<dyn FormatReader>, Box<dyn Decoder>) {
+        (self.reader, self.decoder)
+    }
+}
+
+impl Iterator for DecodeFrames {
+    type Item = Result<AudioBufferRef<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(silence) = self.silence.take() {
+            return Some(Ok(silence));
+        }
+
+        if self.is_done {
+            return None;
+        }
+
+        loop {
+            let packet = match self.reader.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    // The end of the stream was reached.
+                    self.is_done = true;
+                    return None;
+                }
+                Err(err) => {
+                    self.is_done = true;
+                    return Some(Err(err));
+                }
+            };
+
+            // Discard packets of other tracks.
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => return Some(Ok(decoded.into_owned())),
+                Err(Error::DecodeError(err)) => {
+                    if self.insert_silence && packet.dur() > 0 {
+                        self.silence = make_silence(self.decoder.as_ref(), packet.dur());
+                    }
+
+                    return Some(Err(Error::DecodeError(err)));
+                }
+                Err(err) => {
+                    self.is_done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Make an audio buffer with the given number of frames of silence, and the same sample format
+/// and signal specification as the audio decoded by the decoder.
+///
+/// The number of frames is taken from an untrusted packet, and is therefore limited to the maximum
+/// number of frames the decoder may decode a packet into. If the signal specification, or the
+/// maximum number of frames, is not known, then no silence is made.
+fn make_silence(decoder: &dyn Decoder, n_frames: u64) -> Option<AudioBufferRef<'static>> {
+    fn silence<S: Sample>(n_frames: u64, spec: SignalSpec) -> Cow<'static, AudioBuffer<S>> {
+        let mut silence = AudioBuffer::new(n_frames, spec);
+        silence.render_silence(None);
+        Cow::Owned(silence)
+    }
+
+    let params = decoder.codec_params();
+    let last_decoded = decoder.last_decoded();
+
+    // Prefer the signal specification reported by the decoder. The last decoded audio buffer is
+    // not used since it may be an unused buffer without any channels if nothing was decoded yet.
+    let spec = match decoder.output_spec() {
+        Some(spec) => spec,
+        None => SignalSpec::new(params.sample_rate?, params.channels?),
+    };
+
+    if spec.channels.count() == 0 {
+        return None;
+    }
+
+    let max_frames = decoder
+        .max_frames_per_packet()
+        .or(params.max_frames_per_packet)
+        .or_else(|| Some(last_decoded.capacity() as u64).filter(|&capacity| capacity > 0))?;
+
+    let n_frames = n_frames.min(max_frames);
+
+    let silence = match last_decoded {
+        AudioBufferRef::U8(_) => AudioBufferRef::U8(silence(n_frames, spec)),
+        AudioBufferRef::U16(_) => AudioBufferRef::U16(silence(n_frames, spec)),
+        AudioBufferRef::U24(_) => AudioBufferRef::U24(silence(n_frames, spec)),
+        AudioBufferRef::U32(_) => AudioBufferRef::U32(silence(n_frames, spec)),
+        AudioBufferRef::S8(_) => AudioBufferRef::S8(silence(n_frames, spec)),
+        AudioBufferRef::S16(_) => AudioBufferRef::S16(silence(n_frames, spec)),
+        AudioBufferRef::S24(_) => AudioBufferRef::S24(silence(n_frames, spec)),
+        AudioBufferRef::S32(_) => AudioBufferRef::S32(silence(n_frames, spec)),
+        AudioBufferRef::F32(_) => AudioBufferRef::F32(silence(n_frames, spec)),
+        AudioBufferRef::F64(_) => AudioBufferRef::F64(silence(n_frames, spec)),
+    };
+
+    Some(silence)
+}
+
+#[cfg(all(test, feature = "pcm", feature = "wav"))]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::{
+        AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec,
+    };
+    use symphonia_core::codecs::FinalizeResult;
+    use symphonia_core::codecs::{CodecDescriptor, CodecParameters, Decoder, DecoderOptions};
+    use symphonia_core::errors::{decode_error, Error, Result};
+    use symphonia_core::formats::{FormatOptions, FormatReader, Packet};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_format_riff::{WavSampleFormat, WavWriter};
+
+    use super::{decode_frames, DecodeFrames};
+    use crate::default::codecs::PcmDecoder;
+    use crate::default::formats::WavReader;
+
+    /// A decoder that fails to decode the packet with the given timestamp.
+    struct FailingDecoder {
+        inner: PcmDecoder,
+        fail_ts: u64,
+    }
+
+    impl Decoder for FailingDecoder {
+        fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+            // Fails to decode the first packet by default.
+            Ok(FailingDecoder { inner: PcmDecoder::try_new(params, options)?, fail_ts: 0 })
+        }
+
+        fn supported_codecs() -> &'static [CodecDescriptor] {
+            &[]
+        }
+
+        fn reset(&mut self) {
+            self.inner.reset()
+        }
+
+        fn codec_params(&self) -> &CodecParameters {
+            self.inner.codec_params()
+        }
+
+        fn output_spec(&self) -> Option<SignalSpec> {
+            self.inner.output_spec()
+        }
+
+        fn max_frames_per_packet(&self) -> Option<u64> {
+            self.inner.max_frames_per_packet()
+        }
+
+        fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+            if packet.ts() == self.fail_ts {
+                return decode_error("test: corrupt packet");
+            }
+            self.inner.decode(packet)
+        }
+
+        fn finalize(&mut self) -> FinalizeResult {
+            self.inner.finalize()
+        }
+
+        fn last_decoded(&self) -> AudioBufferRef<'_> {
+            self.inner.last_decoded()
+        }
+    }
+
+    /// Builds a mono 16-bit PCM WAVE file with 3000 frames. The value of each sample is its index
+    /// divided by 1000.
+    fn build_wav() -> Vec<u8> {
+        let spec = SignalSpec::new(8000, Channels::FRONT_LEFT);
+
+        let mut buf = AudioBuffer::<i16>::new(3000, spec);
+        buf.render_reserved(Some(3000));
+
+        for (i, sample) in buf.chan_mut(0).iter_mut().enumerate() {
+            *sample = (i / 1000) as i16;
+        }
+
+        let mut writer =
+            WavWriter::new(Cursor::new(Vec::new()), spec, WavSampleFormat::S16).unwrap();
+        writer.write(buf.as_audio_buffer_ref()).unwrap();
+        writer.finalize().unwrap().into_inner()
+    }
+
+    fn open_wav() -> Box<dyn FormatReader> {
+        let source = Box::new(Cursor::new(build_wav()));
+        let mss = MediaSourceStream::new(source, Default::default());
+        Box::new(WavReader::try_new(mss, &FormatOptions::default()).unwrap())
+    }
+
+    /// Collects the number of frames and the first sample of each decoded buffer, or `None` for
+    /// each error.
+    fn collect(frames: DecodeFrames) -> Vec<Option<(usize, i16)>> {
+        frames
+            .map(|result| match result {
+                Ok(AudioBufferRef::S16(buf)) => Some((buf.frames(), buf.chan(0)[0])),
+                Ok(_) => panic!("unexpected sample format"),
+                Err(Error::DecodeError(_)) => None,
+                Err(err) => panic!("unexpected error: {}", err),
+            })
+            .collect()
+    }
+
+    fn failing_decoder(reader: &dyn FormatReader, fail_ts: u64) -> Box<dyn Decoder> {
+        let params = &reader.tracks()[0].codec_params;
+        let mut decoder = FailingDecoder::try_new(params, &DecoderOptions::default()).unwrap();
+        decoder.fail_ts = fail_ts;
+        Box::new(decoder)
+    }
+
+    #[test]
+    fn verify_decode_frames() {
+        let mut frames = decode_frames(open_wav(), 0).unwrap();
+
+        let mut samples = Vec::new();
+
+        for buf in &mut frames {
+            match buf.unwrap() {
+                AudioBufferRef::S16(buf) => samples.extend_from_slice(buf.chan(0)),
+                _ => panic!("unexpected sample format"),
+            }
+        }
+
+        let expected: Vec<i16> = (0..3000).map(|i| i / 1000).collect();
+        assert_eq!(samples, expected);
+
+        // The iterator ends at the end of the stream, and stays ended.
+        assert!(frames.next().is_none());
+
+        // An unknown track is an error.
+        assert!(decode_frames(open_wav(), 1).is_err());
+    }
+
+    #[test]
+    fn verify_decode_error_is_recoverable() {
+        let reader = open_wav();
+        let decoder = failing_decoder(reader.as_ref(), 1152);
+
+        // The second packet is an error, decoding continues with the third packet, and the
+        // iterator ends at the end of the stream.
+        let results = collect(DecodeFrames::new(reader, decoder, 0));
+
+        assert_eq!(results, &[Some((1152, 0)), None, Some((696, 2))]);
+    }
+
+    #[test]
+    fn verify_decode_error_with_silence() {
+        let reader = open_wav();
+        let decoder = failing_decoder(reader.as_ref(), 1152);
+
+        // The error is followed by silence with the duration of the packet.
+        let results = collect(DecodeFrames::new(reader, decoder, 0).with_silence(true));
+
+        assert_eq!(results, &[Some((1152, 0)), None, Some((1152, 0)), Some((696, 2))]);
+    }
+
+    #[test]
+    fn verify_decode_error_with_silence_before_first_decode() {
+        let reader = open_wav();
+        let decoder = failing_decoder(reader.as_ref(), 0);
+
+        // Nothing was decoded before the error, so the silence takes its signal specification
+        // from the decoder.
+        let mut frames = DecodeFrames::new(reader, decoder, 0).with_silence(true);
+
+        assert!(frames.next().unwrap().is_err());
+
+        match frames.next().unwrap().unwrap() {
+            AudioBufferRef::S16(buf) => {
+                assert_eq!(*buf.spec(), SignalSpec::new(8000, Channels::FRONT_LEFT));
+                assert_eq!(buf.frames(), 1152);
+                assert!(buf.chan(0).iter().all(|&s| s == 0));
+            }
+            _ => panic!("unexpected sample format"),
+        }
+    }
+}
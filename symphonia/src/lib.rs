@@ -27,10 +27,13 @@
 //! | Format   | Feature Flag | Gapless* | Default |
 //! |----------|--------------|----------|---------|
 //! | AIFF     | `aiff`       | Yes      | No      |
+//! | AVI      | `avi`        | No       | No      |
 //! | CAF      | `caf`        | No       | No      |
+//! | DSF/DFF  | `dsd`        | No       | No      |
 //! | ISO/MP4  | `isomp4`     | No       | No      |
 //! | MKV/WebM | `mkv`        | No       | Yes     |
 //! | OGG      | `ogg`        | Yes      | Yes     |
+//! | Raw PCM  | `raw`        | No       | No      |
 //! | Wave     | `wav`        | Yes      | Yes     |
 //!
 //! \* Gapless playback requires support from both the demuxer and decoder.
@@ -46,11 +49,15 @@
 //! | AAC-LC   | `aac`        | No      | No      |
 //! | ADPCM    | `adpcm`      | Yes     | Yes     |
 //! | ALAC     | `alac`       | Yes     | No      |
+//! | DSD      | `dsd`        | No      | No      |
 //! | FLAC     | `flac`       | Yes     | Yes     |
 //! | MP1      | `mp1`, `mpa` | No      | No      |
 //! | MP2      | `mp2`, `mpa` | No      | No      |
 //! | MP3      | `mp3`, `mpa` | Yes     | No      |
 //! | PCM      | `pcm`        | Yes     | Yes     |
+//! | PS-ADPCM | `psx-adpcm`  | No      | No      |
+//! | QOA      | `qoa`        | No      | No      |
+//! | Shorten  | `shorten`    | No      | No      |
 //! | Vorbis   | `vorbis`     | Yes     | Yes     |
 //!
 //! **Tip:** All codecs can be enabled with the `all-codecs` feature flag. Similarly, all MPEG
@@ -66,6 +73,12 @@
 //! * RIFF
 //! * Vorbis Comment (in OGG & FLAC)
 //!
+//! ## Writing
+//!
+//! Symphonia is primarily a decoding library. However, decoded audio may be written to a Wave file
+//! using [`default::formats::WavWriter`] if the `wav-write` feature flag is enabled. It is not
+//! enabled by default.
+//!
 //! ## Optimizations
 //!
 //! SIMD optimizations are **not** enabled by default. They may be enabled on a per-instruction
@@ -80,6 +93,10 @@
 //!
 //! **Tip:** All SIMD optimizations can be enabled with the `opt-simd` feature flag.
 //!
+//! FLAC streams may be decoded in parallel using [`default::codecs::ParallelFlacDecoder`] if the
+//! `flac-parallel` feature flag is enabled. Enabling this feature flag will pull in the `rayon`
+//! dependency. It is not enabled by default.
+//!
 //! # Usage
 //!
 //! The following steps describe a basic usage of Symphonia:
@@ -120,6 +137,9 @@
 //!     [`RawSampleBuffer`][core::audio::RawSampleBuffer] to export the audio out of Symphonia.
 //! 11. Repeat step 9 and 10 until the end-of-stream error is returned.
 //!
+//! Alternatively, steps 8 through 11 can be performed by the iterator returned by
+//! [`decode_frames`][decode::decode_frames], which also recovers from decode errors.
+//!
 //! An example implementation of a simple audio player (symphonia-play) can be found in the
 //! Project Symphonia git repository.
 //!
@@ -134,6 +154,8 @@
 //! [`FormatReader`][core::formats::FormatReader] trait for a demuxer trait and register with
 //! the appropriate registry or probe!
 
+pub mod decode;
+
 pub mod default {
     //! The `default` module provides convenience functions and registries to get an implementer
     //! up-and-running as quickly as possible, and to reduce boiler-plate. Using the `default`
@@ -144,6 +166,8 @@ pub mod default {
 
         #[cfg(feature = "flac")]
         pub use symphonia_bundle_flac::FlacDecoder;
+        #[cfg(feature = "flac-parallel")]
+        pub use symphonia_bundle_flac::ParallelFlacDecoder;
         #[cfg(any(feature = "mp1", feature = "mp2", feature = "mp3"))]
         pub use symphonia_bundle_mp3::MpaDecoder;
         #[cfg(feature = "aac")]
@@ -152,8 +176,14 @@ pub mod default {
         pub use symphonia_codec_adpcm::AdpcmDecoder;
         #[cfg(feature = "alac")]
         pub use symphonia_codec_alac::AlacDecoder;
+        #[cfg(feature = "dsd")]
+        pub use symphonia_codec_dsd::{DsdDecoder, DsdToPcm};
         #[cfg(feature = "pcm")]
         pub use symphonia_codec_pcm::PcmDecoder;
+        #[cfg(feature = "qoa")]
+        pub use symphonia_codec_qoa::QoaDecoder;
+        #[cfg(feature = "shorten")]
+        pub use symphonia_codec_shorten::ShortenDecoder;
         #[cfg(feature = "vorbis")]
         pub use symphonia_codec_vorbis::VorbisDecoder;
 
@@ -171,18 +201,32 @@ pub mod default {
         pub use symphonia_bundle_mp3::MpaReader;
         #[cfg(feature = "aac")]
         pub use symphonia_codec_aac::AdtsReader;
+        #[cfg(feature = "aac")]
+        pub use symphonia_codec_aac::LoasReader;
+        #[cfg(feature = "qoa")]
+        pub use symphonia_codec_qoa::QoaReader;
+        #[cfg(feature = "shorten")]
+        pub use symphonia_codec_shorten::ShortenReader;
         #[cfg(feature = "caf")]
         pub use symphonia_format_caf::CafReader;
+        #[cfg(feature = "dsd")]
+        pub use symphonia_format_dsd::{DffReader, DsfReader};
         #[cfg(feature = "isomp4")]
         pub use symphonia_format_isomp4::IsoMp4Reader;
         #[cfg(feature = "mkv")]
         pub use symphonia_format_mkv::MkvReader;
         #[cfg(feature = "ogg")]
         pub use symphonia_format_ogg::OggReader;
+        #[cfg(feature = "raw")]
+        pub use symphonia_format_raw::RawPcmReader;
         #[cfg(feature = "aiff")]
         pub use symphonia_format_riff::AiffReader;
+        #[cfg(feature = "avi")]
+        pub use symphonia_format_riff::AviReader;
         #[cfg(feature = "wav")]
         pub use symphonia_format_riff::WavReader;
+        #[cfg(feature = "wav-write")]
+        pub use symphonia_format_riff::{WavSampleFormat, WavWriter};
 
         #[deprecated = "use `default::formats::MpaReader` instead"]
         #[cfg(any(feature = "mp1", feature = "mp2", feature = "mp3"))]
@@ -245,6 +289,9 @@ pub mod default {
         #[cfg(feature = "alac")]
         registry.register_all::<codecs::AlacDecoder>();
 
+        #[cfg(feature = "dsd")]
+        registry.register_all::<codecs::DsdDecoder>();
+
         #[cfg(feature = "flac")]
         registry.register_all::<codecs::FlacDecoder>();
 
@@ -254,6 +301,12 @@ pub mod default {
         #[cfg(feature = "pcm")]
         registry.register_all::<codecs::PcmDecoder>();
 
+        #[cfg(feature = "qoa")]
+        registry.register_all::<codecs::QoaDecoder>();
+
+        #[cfg(feature = "shorten")]
+        registry.register_all::<codecs::ShortenDecoder>();
+
         #[cfg(feature = "vorbis")]
         registry.register_all::<codecs::VorbisDecoder>();
     }
@@ -270,9 +323,18 @@ pub mod default {
         #[cfg(feature = "aac")]
         probe.register_all::<formats::AdtsReader>();
 
+        #[cfg(feature = "aac")]
+        probe.register_all::<formats::LoasReader>();
+
         #[cfg(feature = "caf")]
         probe.register_all::<formats::CafReader>();
 
+        #[cfg(feature = "dsd")]
+        probe.register_all::<formats::DffReader>();
+
+        #[cfg(feature = "dsd")]
+        probe.register_all::<formats::DsfReader>();
+
         #[cfg(feature = "flac")]
         probe.register_all::<formats::FlacReader>();
 
@@ -285,6 +347,9 @@ pub mod default {
         #[cfg(feature = "aiff")]
         probe.register_all::<formats::AiffReader>();
 
+        #[cfg(feature = "avi")]
+        probe.register_all::<formats::AviReader>();
+
         #[cfg(feature = "wav")]
         probe.register_all::<formats::WavReader>();
 
@@ -294,9 +359,92 @@ pub mod default {
         #[cfg(feature = "mkv")]
         probe.register_all::<formats::MkvReader>();
 
+        #[cfg(feature = "qoa")]
+        probe.register_all::<formats::QoaReader>();
+
+        #[cfg(feature = "shorten")]
+        probe.register_all::<formats::ShortenReader>();
+
         // Metadata
         probe.register_all::<Id3v2Reader>();
     }
 }
 
 pub use symphonia_core as core;
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(feature = "flac", feature = "pcm"))]
+    #[test]
+    fn verify_codec_registry_lookup() {
+        use symphonia_core::codecs::{CodecRegistry, CODEC_TYPE_FLAC, CODEC_TYPE_PCM_S16LE};
+
+        use crate::default::codecs::{FlacDecoder, PcmDecoder};
+
+        let mut registry = CodecRegistry::new();
+        registry.register_all::<PcmDecoder>();
+        registry.register_all::<FlacDecoder>();
+
+        let flac = registry.get_codec_by_name("flac").unwrap();
+        assert_eq!(flac.codec, CODEC_TYPE_FLAC);
+
+        // Short names are matched case-insensitively.
+        let pcm = registry.get_codec_by_name("PCM_S16LE").unwrap();
+        assert_eq!(pcm.codec, CODEC_TYPE_PCM_S16LE);
+
+        assert!(registry.get_codec_by_name("vorbis").is_none());
+
+        // All registered codecs are listed exactly once.
+        let mut names: Vec<&str> =
+            registry.codecs().map(|descriptor| descriptor.short_name).collect();
+        names.sort_unstable();
+        names.dedup();
+
+        assert_eq!(names.len(), registry.codecs().count());
+        assert!(names.contains(&"flac"));
+        assert!(names.contains(&"pcm_s16le"));
+    }
+
+    #[cfg(feature = "wav")]
+    #[test]
+    fn verify_probe_search_limit() {
+        use std::io::Cursor;
+
+        use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, Channels, Signal, SignalSpec};
+        use symphonia_core::errors::Error;
+        use symphonia_core::formats::FormatOptions;
+        use symphonia_core::io::MediaSourceStream;
+        use symphonia_core::probe::Hint;
+        use symphonia_format_riff::{WavSampleFormat, WavWriter};
+
+        // A mono 16-bit PCM WAVE file with 4 frames, preceded by 64 KiB of junk.
+        let spec = SignalSpec::new(8000, Channels::FRONT_LEFT);
+
+        let mut audio = AudioBuffer::<i16>::new(4, spec);
+        audio.render_reserved(Some(4));
+
+        let mut junk = Cursor::new(vec![0; 64 * 1024]);
+        junk.set_position(64 * 1024);
+
+        let mut writer = WavWriter::new(junk, spec, WavSampleFormat::S16).unwrap();
+        writer.write(audio.as_audio_buffer_ref()).unwrap();
+
+        let buf = writer.finalize().unwrap().into_inner();
+
+        let probe = |probe_search_limit| {
+            let mss =
+                MediaSourceStream::new(Box::new(Cursor::new(buf.clone())), Default::default());
+            let mut format_opts = FormatOptions::default();
+            format_opts.with_probe_search_limit(probe_search_limit);
+
+            crate::default::get_probe().format(&Hint::new(), mss, &format_opts, &Default::default())
+        };
+
+        // The WAVE file is found within the default limit.
+        let probed = probe(FormatOptions::default().probe_search_limit).unwrap();
+        assert_eq!(probed.format.tracks()[0].codec_params.n_frames, Some(4));
+
+        // The WAVE file is not found if the limit is within the junk.
+        assert!(matches!(probe(32 * 1024), Err(Error::Unsupported(_))));
+    }
+}